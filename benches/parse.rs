@@ -0,0 +1,379 @@
+//! Parser benchmarks over the test corpus
+//!
+//! Measures doctora's parse throughput on every document in `tests/corpus/`:
+//!
+//! ```text
+//! cargo bench --bench parse
+//! ```
+//!
+//! The `tokens` bench splits the same work into lexing and parsing. The
+//! `pathological` group times worst-case inline input, which must parse in
+//! linear time:
+//!
+//! ```text
+//! cargo bench --bench parse -- pathological
+//! ```
+//!
+//! # Comparing Against Asciidoctor
+//!
+//! Set `DOCTORA_BENCH_EXTERNAL=1` to also convert the same corpus with Ruby
+//! Asciidoctor and asciidoctor.js and print a comparison table before the
+//! Criterion run:
+//!
+//! ```text
+//! DOCTORA_BENCH_EXTERNAL=1 cargo bench --bench parse
+//! ```
+//!
+//! The commands default to `asciidoctor` (Ruby gem) and `asciidoctorjs` (the
+//! `asciidoctor` npm package) and can be overridden with
+//! `DOCTORA_BENCH_ASCIIDOCTOR` and `DOCTORA_BENCH_ASCIIDOCTOR_JS`. Tools that
+//! are not installed are reported as skipped.
+//!
+//! External tools run once per document and iteration, reading the document
+//! from stdin, so their times include process startup. Treat the ratios as an
+//! upper bound on doctora's advantage for single-document conversions. The
+//! ratios are relative to doctora's own conversion to a standalone HTML page;
+//! its parse time alone is listed too.
+//!
+//! Each tool's HTML, doctora's included, is written to
+//! `target/bench-compare/<tool>/<document>.html`, and the report lists, for
+//! each pair of tools, the documents whose HTML differs once blank lines and
+//! indentation are ignored.
+
+use criterion::{Criterion, Throughput};
+use doctora::options::ParseOptions;
+use doctora::parse_document;
+use doctora::render::html::{HtmlOptions, render_html};
+use std::fs;
+use std::hint::black_box;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Iterations per document when timing external tools
+const EXTERNAL_ITERATIONS: u32 = 5;
+
+/// Corpus documents as `(name, source)`, sorted by name
+fn corpus() -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("tests/corpus should exist")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "adoc"))
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            (name, fs::read_to_string(&path).unwrap())
+        })
+        .collect()
+}
+
+fn bench_parse(c: &mut Criterion, documents: &[(String, String)]) {
+    let mut group = c.benchmark_group("parse");
+    for (name, source) in documents {
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_function(name.as_str(), |b| {
+            b.iter(|| parse_document(black_box(source)))
+        });
+    }
+    group.finish();
+}
+
+/// A data-heavy table, as found in reference manuals: `rows` rows of six
+/// short cells, with a header row
+fn generated_table(rows: usize) -> String {
+    let mut source = String::from(
+        "[cols=\"1,2,1,1,3,1\"]\n|===\n|Id |Name |Type |Default |Description |Since\n\n",
+    );
+    for row in 0..rows {
+        source.push_str(&format!(
+            "|{row} |option_{row} |int |{} |Sets the **{row}th** value |1.{}\n",
+            row % 7,
+            row % 10
+        ));
+    }
+    source.push_str("|===\n");
+    source
+}
+
+/// Parse throughput on large generated tables
+///
+/// Table cells dominate memory in data-heavy manuals, so this tracks the
+/// per-cell cost separately from the corpus documents.
+fn bench_tables(c: &mut Criterion) {
+    let mut group = c.benchmark_group("table");
+    for rows in [100, 5_000] {
+        let source = generated_table(rows);
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_function(format!("{rows}_rows"), |b| {
+            b.iter(|| parse_document(black_box(&source)))
+        });
+    }
+    group.finish();
+}
+
+/// Worst-case inline inputs: one long line of `count` repetitions of each
+/// pattern
+///
+/// Each pattern leaves thousands of marks or brackets open, which a pairing
+/// or bracket search that rescans the line would make quadratic. Throughput
+/// should stay flat as `count` grows.
+const PATHOLOGICAL: [(&str, &str); 7] = [
+    ("bold_italic", "**a _"),
+    ("open_italics", "_x a* "),
+    ("xref_openers", "<<"),
+    ("anchor_openers", "[[a "),
+    ("footnotes", "footnote:["),
+    ("images", "image:a[ "),
+    ("links_between_pairs", "*a* http://x.y "),
+];
+
+/// Parse throughput on delimiter- and bracket-heavy lines
+fn bench_pathological(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pathological");
+    for (name, pattern) in PATHOLOGICAL {
+        for count in [1_000, 10_000] {
+            let source = pattern.repeat(count);
+            group.throughput(Throughput::Bytes(source.len() as u64));
+            group.bench_function(format!("{name}/{count}"), |b| {
+                b.iter(|| parse_document(black_box(&source)))
+            });
+        }
+    }
+    group.finish();
+}
+
+/// An external converter invoked as `<command...> -o - -`
+struct ExternalTool {
+    label: &'static str,
+    dir_name: &'static str,
+    command: Vec<String>,
+}
+
+impl ExternalTool {
+    fn from_env(label: &'static str, dir_name: &'static str, var: &str, default: &str) -> Self {
+        let command = std::env::var(var).unwrap_or_else(|_| default.to_string());
+        Self {
+            label,
+            dir_name,
+            command: command.split_whitespace().map(str::to_string).collect(),
+        }
+    }
+
+    /// Convert `source` to HTML, returning the output and elapsed time
+    fn convert(&self, source: &str) -> Option<(String, Duration)> {
+        let (program, args) = self.command.split_first()?;
+        let start = Instant::now();
+        let mut child = Command::new(program)
+            .args(args)
+            .args(["-o", "-", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(source.as_bytes())
+            .ok()?;
+        let output = child.wait_with_output().ok()?;
+        let elapsed = start.elapsed();
+        output.status.success().then(|| {
+            (
+                String::from_utf8_lossy(&output.stdout).into_owned(),
+                elapsed,
+            )
+        })
+    }
+}
+
+/// Timing and output of one tool over the whole corpus
+struct ToolRun {
+    label: &'static str,
+    total: Duration,
+    outputs: Option<Vec<String>>,
+}
+
+/// Time doctora and the external tools on `documents` and print a report
+fn compare_external(documents: &[(String, String)]) {
+    let bytes: usize = documents.iter().map(|(_, s)| s.len()).sum();
+    let out_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/bench-compare");
+
+    let start = Instant::now();
+    for _ in 0..EXTERNAL_ITERATIONS {
+        for (_, source) in documents {
+            let _ = black_box(parse_document(black_box(source)));
+        }
+    }
+    let parse_only = ToolRun {
+        label: "doctora (parse only)",
+        total: start.elapsed(),
+        outputs: None,
+    };
+
+    let tools = [
+        ExternalTool::from_env(
+            "Asciidoctor (Ruby)",
+            "asciidoctor",
+            "DOCTORA_BENCH_ASCIIDOCTOR",
+            "asciidoctor",
+        ),
+        ExternalTool::from_env(
+            "asciidoctor.js",
+            "asciidoctor-js",
+            "DOCTORA_BENCH_ASCIIDOCTOR_JS",
+            "asciidoctorjs",
+        ),
+    ];
+
+    let mut runs = vec![run_doctora(documents, &out_dir), parse_only];
+    for tool in &tools {
+        match run_external(tool, documents, &out_dir) {
+            Some(run) => runs.push(run),
+            None => println!(
+                "{}: skipped (`{}` not available)",
+                tool.label,
+                tool.command.join(" ")
+            ),
+        }
+    }
+
+    let baseline = runs[0].total;
+    println!();
+    println!(
+        "Corpus: {} documents, {} bytes, {} iterations",
+        documents.len(),
+        bytes,
+        EXTERNAL_ITERATIONS
+    );
+    println!(
+        "{:<24} {:>12} {:>12} {:>10}",
+        "Tool", "Total", "MiB/s", "Relative"
+    );
+    for run in &runs {
+        let mib = (bytes as f64 * f64::from(EXTERNAL_ITERATIONS)) / (1024.0 * 1024.0);
+        println!(
+            "{:<24} {:>12.2?} {:>12.2} {:>9.1}x",
+            run.label,
+            run.total,
+            mib / run.total.as_secs_f64(),
+            run.total.as_secs_f64() / baseline.as_secs_f64(),
+        );
+    }
+
+    let outputs: Vec<(&str, &[String])> = runs
+        .iter()
+        .filter_map(|run| Some((run.label, run.outputs.as_deref()?)))
+        .collect();
+    println!();
+    for (i, (a, a_html)) in outputs.iter().enumerate() {
+        for (b, b_html) in &outputs[i + 1..] {
+            let differing: Vec<&str> = documents
+                .iter()
+                .zip(a_html.iter().zip(b_html.iter()))
+                .filter(|(_, (x, y))| !same_html(x, y))
+                .map(|((name, _), _)| name.as_str())
+                .collect();
+            println!(
+                "Output differences between {} and {}: {} of {} documents {:?}",
+                a,
+                b,
+                differing.len(),
+                documents.len(),
+                differing
+            );
+        }
+    }
+    println!("HTML output written to {}\n", out_dir.display());
+}
+
+/// True if `a` and `b` are the same HTML apart from blank lines and
+/// indentation
+fn same_html(a: &str, b: &str) -> bool {
+    fn lines(html: &str) -> impl Iterator<Item = &str> {
+        html.lines().map(str::trim).filter(|line| !line.is_empty())
+    }
+    lines(a).eq(lines(b))
+}
+
+/// Convert every document to a standalone HTML page `EXTERNAL_ITERATIONS`
+/// times, writing the pages under `out_dir`
+fn run_doctora(documents: &[(String, String)], out_dir: &Path) -> ToolRun {
+    let parse_options = ParseOptions::new().substitute_attributes(true);
+    let html_options = HtmlOptions::new().standalone(true);
+    let tool_dir = out_dir.join("doctora");
+    fs::create_dir_all(&tool_dir).expect("target/bench-compare should be writable");
+
+    let mut total = Duration::ZERO;
+    let mut outputs = Vec::with_capacity(documents.len());
+    for (name, source) in documents {
+        let mut html = String::new();
+        for _ in 0..EXTERNAL_ITERATIONS {
+            let start = Instant::now();
+            // A document that fails to parse converts to an empty page, as
+            // the external tools' failures are skipped
+            html = doctora::parse_document_with_options(black_box(source), &parse_options)
+                .map(|doc| render_html(&doc, &html_options))
+                .unwrap_or_default();
+            total += start.elapsed();
+        }
+        fs::write(tool_dir.join(format!("{}.html", name)), &html)
+            .expect("target/bench-compare should be writable");
+        outputs.push(html);
+    }
+
+    ToolRun {
+        label: "doctora (HTML)",
+        total,
+        outputs: Some(outputs),
+    }
+}
+
+/// Convert every document `EXTERNAL_ITERATIONS` times; `None` if the tool fails
+fn run_external(
+    tool: &ExternalTool,
+    documents: &[(String, String)],
+    out_dir: &Path,
+) -> Option<ToolRun> {
+    let tool_dir = out_dir.join(tool.dir_name);
+    fs::create_dir_all(&tool_dir).ok()?;
+
+    let mut total = Duration::ZERO;
+    let mut outputs = Vec::with_capacity(documents.len());
+    for (name, source) in documents {
+        let mut html = String::new();
+        for _ in 0..EXTERNAL_ITERATIONS {
+            let (output, elapsed) = tool.convert(source)?;
+            total += elapsed;
+            html = output;
+        }
+        fs::write(tool_dir.join(format!("{}.html", name)), &html).ok()?;
+        outputs.push(html);
+    }
+
+    Some(ToolRun {
+        label: tool.label,
+        total,
+        outputs: Some(outputs),
+    })
+}
+
+fn main() {
+    let documents = corpus();
+    if std::env::var_os("DOCTORA_BENCH_EXTERNAL").is_some() {
+        compare_external(&documents);
+    }
+
+    let mut criterion = Criterion::default().configure_from_args();
+    bench_parse(&mut criterion, &documents);
+    bench_tables(&mut criterion);
+    bench_pathological(&mut criterion);
+    criterion.final_summary();
+}
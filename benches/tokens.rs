@@ -0,0 +1,117 @@
+//! Lexer and parser benchmarks, measured separately
+//!
+//! The parse benchmark times `parse_document` end to end, which hides how the
+//! cost splits between the Logos lexer and the Winnow parser. This one times
+//! each stage on its own, and the two together, over generated documents of
+//! increasing size:
+//!
+//! ```text
+//! cargo bench --bench tokens
+//! cargo bench --bench tokens -- lex/
+//! ```
+//!
+//! | Group           | Measures                                             |
+//! |-----------------|------------------------------------------------------|
+//! | `lex`           | `lex_with_spans`, including its line-syntax passes   |
+//! | `parse_tokens`  | `parse_document_winnow` on tokens lexed beforehand   |
+//! | `lex_and_parse` | Both, as `parse_document` runs them                  |
+//!
+//! Each group reports throughput in bytes for every input size, so a change
+//! to one stage shows up in its own group and the combined one, and the
+//! other stage's numbers stay put. Sizes are approximate: the generator
+//! repeats whole sections until the target is reached.
+
+use criterion::{BenchmarkId, Criterion, Throughput};
+use doctora::parser_winnow::parse_document_winnow;
+use doctora::token::lex_with_spans;
+use std::hint::black_box;
+
+/// Approximate document sizes, in bytes
+const SIZES: [usize; 4] = [1 << 10, 1 << 14, 1 << 18, 1 << 20];
+
+/// A document of about `size` bytes mixing the common block and inline
+/// syntax: sections, formatted paragraphs, lists, listings, and tables
+fn generated_document(size: usize) -> String {
+    let mut source = String::from("= Generated Manual\n:version: 1.0\n\n");
+    let mut section = 0;
+    while source.len() < size {
+        section += 1;
+        source.push_str(&format!(
+            "== Section {section}\n\n\
+             This is *bold*, _italic_, and `code` text with a https://example.com[link] \
+             and a <<section-{section},cross reference>>.\n\n\
+             * First item with **strong** text\n\
+             * Second item\n\
+             ** Nested item\n\n\
+             . Step one\n\
+             . Step two\n\n\
+             Term {section}:: Definition of the term.\n\n\
+             [source,rust]\n\
+             ----\n\
+             fn section_{section}() -> usize {{\n    {section}\n}}\n\
+             ----\n\n\
+             |===\n\
+             |Name |Value\n\n\
+             |a{section} |{section}\n\
+             |b{section} |{}\n\
+             |===\n\n",
+            section * 2
+        ));
+    }
+    source
+}
+
+fn bench_lex(c: &mut Criterion, documents: &[(usize, String)]) {
+    let mut group = c.benchmark_group("lex");
+    for (size, source) in documents {
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), source, |b, source| {
+            b.iter(|| lex_with_spans(black_box(source)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_parse_tokens(c: &mut Criterion, documents: &[(usize, String)]) {
+    let mut group = c.benchmark_group("parse_tokens");
+    for (size, source) in documents {
+        let tokens = lex_with_spans(source);
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), source, |b, source| {
+            b.iter(|| parse_document_winnow(black_box(source), black_box(&tokens)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_lex_and_parse(c: &mut Criterion, documents: &[(usize, String)]) {
+    let mut group = c.benchmark_group("lex_and_parse");
+    for (size, source) in documents {
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), source, |b, source| {
+            b.iter(|| {
+                let tokens = lex_with_spans(black_box(source));
+                parse_document_winnow(source, &tokens)
+            })
+        });
+    }
+    group.finish();
+}
+
+fn main() {
+    let documents: Vec<(usize, String)> = SIZES
+        .iter()
+        .map(|&size| (size, generated_document(size)))
+        .collect();
+    for (size, source) in &documents {
+        if let Err(err) = parse_document_winnow(source, &lex_with_spans(source)) {
+            panic!("generated document of {size} bytes does not parse: {err}");
+        }
+    }
+
+    let mut criterion = Criterion::default().configure_from_args();
+    bench_lex(&mut criterion, &documents);
+    bench_parse_tokens(&mut criterion, &documents);
+    bench_lex_and_parse(&mut criterion, &documents);
+    criterion.final_summary();
+}
@@ -0,0 +1,1350 @@
+//! Antora-style multi-component sites
+//!
+//! Antora organizes documentation into *component versions*: a directory
+//! (usually a branch of a repository) with an `antora.yml` descriptor and
+//! its content sorted into modules and families:
+//!
+//! ```text
+//! antora.yml
+//! modules/
+//!   ROOT/
+//!     nav.adoc
+//!     pages/index.adoc
+//!     partials/intro.adoc
+//!     examples/config.yml
+//!     images/diagram.png
+//!     attachments/sample.zip
+//!   admin/
+//!     pages/install.adoc
+//! ```
+//!
+//! [`ComponentVersion::load`] reads such a directory, and a
+//! [`ContentCatalog`] holds the component versions of a site. Every file
+//! has a *resource ID*, `version@component:module:family$relative`
+//! ([`ResourceId`]), and references name files by the parts that differ
+//! from the referencing page's:
+//!
+//! | Reference                             | Resolves to                     |
+//! |---------------------------------------|---------------------------------|
+//! | `xref:install.adoc[]`                 | Page in the same module         |
+//! | `xref:admin:install.adoc#setup[]`     | Page in module `admin`          |
+//! | `xref:docs:admin:install.adoc[]`      | Latest version of `docs`        |
+//! | `xref:2.0@docs:admin:install.adoc[]`  | Version `2.0` of `docs`         |
+//! | `xref:docs::index.adoc[]`             | Module `ROOT` of `docs`         |
+//! | `include::partial$intro.adoc[]`       | Partial in the same module      |
+//! | `include::admin:example$config.yml[]` | Example in module `admin`       |
+//! | `image::diagram.png[]`                | Image in the same module        |
+//!
+//! A component named without a version means its latest version: the
+//! highest, comparing versions segment by segment (numerically where both
+//! segments are numbers). An unversioned component version (`version: ~`)
+//! counts as the highest.
+//!
+//! # Building
+//!
+//! [`ContentCatalog::parse_pages`] parses every page into a [`BatchResult`],
+//! so the site pipeline ([`crate::site`], [`crate::theme`]) builds it like
+//! any batch. Each page:
+//!
+//! 1. gets the descriptor's `asciidoc.attributes`, substituted in its text,
+//!    and generated section IDs;
+//! 2. has its includes expanded, with resource IDs resolved relative to the
+//!    included file (plain relative paths still work within a family);
+//! 3. has its `xref:` macros and `<<page.adoc#id>>` references to other
+//!    pages turned into links to the target page's URL, showing the
+//!    target's title when the reference has no text, and its image targets
+//!    pointed at the published image.
+//!
+//! Pages are named by their output path, `component/version/module/page.adoc`
+//! with the version left out for unversioned components and the module for
+//! `ROOT`, so [`SiteOptions::page_url`] gives Antora's URLs. Images and
+//! attachments are published under `_images` and `_attachments` of their
+//! module's directory ([`AntoraBuild::assets`]).
+//!
+//! Pages come in reading order: for each component version, the pages its
+//! navigation files (`nav` in the descriptor) reference, in order, then the
+//! others sorted by module and path. References that resolve to nothing are
+//! left as they are and reported in [`AntoraBuild::unresolved`].
+//!
+//! # Descriptors
+//!
+//! `antora.yml` is read with a small YAML reader that supports what
+//! descriptors use: nested block mappings, block and flow lists of scalars,
+//! quoted scalars, and comments. The keys used are `name` (required),
+//! `version` (required; `~` for unversioned), `title`, `nav`, and
+//! `asciidoc.attributes`. An attribute set to `false` or `~` is left out;
+//! `true` sets it to the empty string.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::antora::{ComponentVersion, ContentCatalog};
+//! use doctora::batch::FailurePolicy;
+//! use doctora::site::SiteOptions;
+//!
+//! let mut docs = ComponentVersion::from_descriptor("name: docs\nversion: '2.0'\n").unwrap();
+//! docs.add_file("modules/ROOT/pages/index.adoc", "= Home\n\nSee xref:admin:install.adoc[].\n");
+//! docs.add_file("modules/admin/pages/install.adoc", "= Installing\n\nSteps.\n");
+//!
+//! let mut catalog = ContentCatalog::new();
+//! catalog.add(docs);
+//! let site = SiteOptions::new("https://docs.example.com");
+//! let build = catalog.parse_pages(&site, FailurePolicy::Abort).unwrap();
+//!
+//! let names: Vec<&str> = build.result.documents.iter().map(|d| d.name.as_str()).collect();
+//! assert_eq!(names, ["docs/2.0/index.adoc", "docs/2.0/admin/install.adoc"]);
+//!
+//! let html = doctora::render::html::render_html(
+//!     &build.result.documents[0].document,
+//!     &Default::default(),
+//! );
+//! assert!(html.contains(
+//!     "<a href=\"https://docs.example.com/docs/2.0/admin/install.html\">Installing</a>"
+//! ));
+//! ```
+
+use crate::ast::visit::{VisitorMut, walk_block_mut, walk_inline_mut};
+use crate::ast::{Block, Document, Inline};
+use crate::attributes::Attributes;
+use crate::batch::{BatchError, BatchResult, FailurePolicy, guarded};
+use crate::include::{
+    IncludeOptions, IncludeResolver, directive, expand_includes, is_verbatim_delimiter,
+};
+use crate::options::ParseOptions;
+use crate::parse_document_with_options;
+use crate::site::SiteOptions;
+use crate::theme::{SiteFile, list_files};
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+use thiserror::Error;
+
+/// Module whose pages sit directly under the component version's URL
+pub const ROOT_MODULE: &str = "ROOT";
+
+/// `xref:target[text]` macros
+static XREF_MACRO: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"xref:([^\s\[\]]+)\[([^\]]*)\]").unwrap());
+
+/// Error produced when a component version cannot be loaded
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AntoraError {
+    /// A file could not be read
+    #[error("cannot read {}: {message}", path.display())]
+    Io { path: PathBuf, message: String },
+    /// `antora.yml` is not valid
+    #[error("invalid component descriptor: {message}")]
+    Descriptor { message: String },
+}
+
+/// Kind of file in a module
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Family {
+    /// A page of the site (`pages/`)
+    Page,
+    /// A fragment for including into pages (`partials/`)
+    Partial,
+    /// An example file for including into pages (`examples/`)
+    Example,
+    /// An image (`images/`)
+    Image,
+    /// A downloadable file (`attachments/`)
+    Attachment,
+}
+
+impl Family {
+    const ALL: [Family; 5] = [
+        Family::Page,
+        Family::Partial,
+        Family::Example,
+        Family::Image,
+        Family::Attachment,
+    ];
+
+    /// Name used in resource IDs (`partial`)
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Family::Page => "page",
+            Family::Partial => "partial",
+            Family::Example => "example",
+            Family::Image => "image",
+            Family::Attachment => "attachment",
+        }
+    }
+
+    /// Directory of the family in a module (`partials`)
+    pub fn dir(self) -> &'static str {
+        match self {
+            Family::Page => "pages",
+            Family::Partial => "partials",
+            Family::Example => "examples",
+            Family::Image => "images",
+            Family::Attachment => "attachments",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|family| family.as_str() == name)
+    }
+
+    fn from_dir(dir: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|family| family.dir() == dir)
+    }
+}
+
+impl fmt::Display for Family {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The full ID of a file in a content catalog
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ResourceId {
+    /// Component name
+    pub component: String,
+    /// Component version; empty for an unversioned component
+    pub version: String,
+    /// Module name
+    pub module: String,
+    /// Family of the file
+    pub family: Family,
+    /// Path within the family's directory, with `/` separators
+    pub relative: String,
+}
+
+impl fmt::Display for ResourceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.version.is_empty() {
+            write!(f, "{}@", self.version)?;
+        }
+        write!(
+            f,
+            "{}:{}:{}${}",
+            self.component, self.module, self.family, self.relative
+        )
+    }
+}
+
+impl ResourceId {
+    /// Output path of the component version and module, without a trailing
+    /// `/` (`docs/2.0/admin`)
+    fn module_path(&self) -> String {
+        let mut path = self.component.clone();
+        if !self.version.is_empty() {
+            path.push('/');
+            path.push_str(&self.version);
+        }
+        if self.module != ROOT_MODULE {
+            path.push('/');
+            path.push_str(&self.module);
+        }
+        path
+    }
+
+    /// Name of the batch document for a page (see "Building" above)
+    pub fn page_name(&self) -> String {
+        format!("{}/{}", self.module_path(), self.relative)
+    }
+
+    /// Output path of a published image or attachment, relative to the
+    /// site root
+    pub fn asset_path(&self) -> String {
+        format!(
+            "{}/_{}/{}",
+            self.module_path(),
+            self.family.dir(),
+            self.relative
+        )
+    }
+}
+
+/// A file of a component version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resource {
+    /// The file's ID
+    pub id: ResourceId,
+    /// File contents
+    pub contents: Vec<u8>,
+}
+
+impl Resource {
+    /// The contents as text, with invalid UTF-8 replaced
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.contents).into_owned()
+    }
+}
+
+/// One version of a component (see the module documentation)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ComponentVersion {
+    /// Component name
+    pub name: String,
+    /// Version; empty if unversioned
+    pub version: String,
+    /// Display title, if the descriptor sets one
+    pub title: Option<String>,
+    /// Navigation files, relative to the descriptor's directory
+    pub nav: Vec<String>,
+    /// AsciiDoc attributes for every page, in descriptor order
+    pub attributes: Vec<(String, String)>,
+    /// Files of the modules, in the order they were added
+    pub resources: Vec<Resource>,
+    /// Contents of the navigation files by path
+    pub nav_sources: HashMap<String, String>,
+}
+
+impl ComponentVersion {
+    /// Loads the component version in `dir`: its `antora.yml` and the files
+    /// under `modules/`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AntoraError::Io`] if a file cannot be read, and
+    /// [`AntoraError::Descriptor`] if `antora.yml` is invalid.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, AntoraError> {
+        let dir = dir.as_ref();
+        let descriptor = dir.join("antora.yml");
+        let text = fs::read_to_string(&descriptor).map_err(|err| io_error(&descriptor, err))?;
+        let mut component = Self::from_descriptor(&text)?;
+        let modules = dir.join("modules");
+        if modules.is_dir() {
+            let files = list_files(&modules).map_err(|(path, err)| io_error(&path, err))?;
+            for path in files {
+                let contents = fs::read(&path).map_err(|err| io_error(&path, err))?;
+                let relative = path.strip_prefix(dir).unwrap_or(&path);
+                component.add_file(&relative.to_string_lossy().replace('\\', "/"), contents);
+            }
+        }
+        Ok(component)
+    }
+
+    /// Creates a component version from the text of its `antora.yml`,
+    /// without files
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AntoraError::Descriptor`] if `yaml` is not a mapping with a
+    /// `name` and a `version`, or uses YAML this reader does not support.
+    pub fn from_descriptor(yaml: &str) -> Result<Self, AntoraError> {
+        let descriptor =
+            yaml::parse(yaml).map_err(|message| AntoraError::Descriptor { message })?;
+        let invalid = |message: &str| AntoraError::Descriptor {
+            message: message.to_string(),
+        };
+        let name = match descriptor.get("name") {
+            Some(yaml::Value::Scalar(name)) if !name.is_empty() => name.clone(),
+            _ => return Err(invalid("missing `name`")),
+        };
+        let version = match descriptor.get("version") {
+            Some(yaml::Value::Scalar(version)) => version.clone(),
+            Some(yaml::Value::Null) => String::new(),
+            _ => return Err(invalid("missing `version`")),
+        };
+        let title = match descriptor.get("title") {
+            Some(yaml::Value::Scalar(title)) => Some(title.clone()),
+            _ => None,
+        };
+        let nav = match descriptor.get("nav") {
+            Some(yaml::Value::List(items)) => items
+                .iter()
+                .map(|item| match item {
+                    yaml::Value::Scalar(path) => Ok(path.clone()),
+                    _ => Err(invalid("`nav` must list file paths")),
+                })
+                .collect::<Result<_, _>>()?,
+            None | Some(yaml::Value::Null) => Vec::new(),
+            Some(_) => return Err(invalid("`nav` must list file paths")),
+        };
+        let mut attributes = Vec::new();
+        if let Some(yaml::Value::Map(entries)) = descriptor
+            .get("asciidoc")
+            .and_then(|asciidoc| asciidoc.get("attributes"))
+        {
+            for (name, value) in entries {
+                let value = match value {
+                    yaml::Value::Scalar(value) if value == "false" => continue,
+                    yaml::Value::Scalar(value) if value == "true" => String::new(),
+                    yaml::Value::Scalar(value) => value.clone(),
+                    yaml::Value::Null => continue,
+                    _ => return Err(invalid("attribute values must be scalars")),
+                };
+                // A trailing `@` lets pages override the value, which they
+                // always can here
+                let value = value.strip_suffix('@').unwrap_or(&value).to_string();
+                attributes.push((name.clone(), value));
+            }
+        }
+        Ok(Self {
+            name,
+            version,
+            title,
+            nav,
+            attributes,
+            ..Self::default()
+        })
+    }
+
+    /// Adds the file at `path`, relative to the descriptor's directory
+    /// (`modules/ROOT/pages/index.adoc`)
+    ///
+    /// Navigation files listed in the descriptor are kept for the reading
+    /// order; other files outside a family directory are ignored. Returns
+    /// whether the file was kept.
+    pub fn add_file(&mut self, path: &str, contents: impl Into<Vec<u8>>) -> bool {
+        let path = path.trim_start_matches("./");
+        let contents = contents.into();
+        if self.nav.iter().any(|nav| nav == path) {
+            let text = String::from_utf8_lossy(&contents).into_owned();
+            self.nav_sources.insert(path.to_string(), text);
+            return true;
+        }
+        let mut parts = path.splitn(4, '/');
+        let (Some("modules"), Some(module), Some(dir), Some(relative)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return false;
+        };
+        let Some(family) = Family::from_dir(dir) else {
+            return false;
+        };
+        let id = ResourceId {
+            component: self.name.clone(),
+            version: self.version.clone(),
+            module: module.to_string(),
+            family,
+            relative: relative.to_string(),
+        };
+        self.resources.retain(|resource| resource.id != id);
+        self.resources.push(Resource { id, contents });
+        true
+    }
+
+    /// Pages in reading order (see "Building" above)
+    fn reading_order<'a>(&'a self, catalog: &'a ContentCatalog) -> Vec<&'a Resource> {
+        let mut order: Vec<&Resource> = Vec::new();
+        for nav in &self.nav {
+            let Some(source) = self.nav_sources.get(nav) else {
+                continue;
+            };
+            // modules/<module>/nav.adoc
+            let module = nav.split('/').nth(1).unwrap_or(ROOT_MODULE);
+            let context = ResourceId {
+                component: self.name.clone(),
+                version: self.version.clone(),
+                module: module.to_string(),
+                family: Family::Page,
+                relative: String::new(),
+            };
+            for captures in XREF_MACRO.captures_iter(source) {
+                let target = captures[1].split('#').next().unwrap_or_default();
+                if let Some(page) = catalog.resolve(target, &context, Family::Page)
+                    && page.id.component == self.name
+                    && page.id.version == self.version
+                    && !order.iter().any(|seen| seen.id == page.id)
+                {
+                    order.push(page);
+                }
+            }
+        }
+        let mut rest: Vec<&Resource> = self
+            .resources
+            .iter()
+            .filter(|resource| resource.id.family == Family::Page)
+            .filter(|resource| !order.iter().any(|seen| seen.id == resource.id))
+            .collect();
+        rest.sort_by(|a, b| {
+            (a.id.module != ROOT_MODULE, &a.id.module, &a.id.relative).cmp(&(
+                b.id.module != ROOT_MODULE,
+                &b.id.module,
+                &b.id.relative,
+            ))
+        });
+        order.extend(rest);
+        order
+    }
+}
+
+/// A reference that resolves to no file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedReference {
+    /// Batch name of the page with the reference
+    pub page: String,
+    /// The reference as written
+    pub target: String,
+}
+
+/// Outcome of [`ContentCatalog::parse_pages`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AntoraBuild {
+    /// The parsed pages, named by output path
+    pub result: BatchResult,
+    /// Images and attachments to publish
+    pub assets: Vec<SiteFile>,
+    /// References that resolve to no file, in reading order
+    pub unresolved: Vec<UnresolvedReference>,
+}
+
+/// The component versions of a site
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentCatalog {
+    /// Component versions in the order they were added
+    pub components: Vec<ComponentVersion>,
+}
+
+impl ContentCatalog {
+    /// Creates an empty catalog
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `component`, replacing a component version with the same name
+    /// and version
+    pub fn add(&mut self, component: ComponentVersion) {
+        self.components
+            .retain(|c| c.name != component.name || c.version != component.version);
+        self.components.push(component);
+    }
+
+    /// The file `id`, if the catalog has it
+    pub fn get(&self, id: &ResourceId) -> Option<&Resource> {
+        self.component(&id.component, &id.version)?
+            .resources
+            .iter()
+            .find(|resource| resource.id == *id)
+    }
+
+    /// The file a reference names, relative to the file `context`
+    ///
+    /// `family` applies when the reference names none (`page` for `xref:`,
+    /// `image` for images).
+    pub fn resolve(&self, target: &str, context: &ResourceId, family: Family) -> Option<&Resource> {
+        let (version, rest) = match target.split_once('@') {
+            Some((version, rest)) => (Some(version), rest),
+            None => (None, target),
+        };
+        let (coordinates, family, relative) = match rest.split_once('$') {
+            Some((before, relative)) => match before.rsplit_once(':') {
+                Some((coordinates, family)) => (Some(coordinates), family, relative),
+                None => (None, before, relative),
+            },
+            None => match rest.rsplit_once(':') {
+                Some((coordinates, relative)) => (Some(coordinates), family.as_str(), relative),
+                None => (None, family.as_str(), rest),
+            },
+        };
+        let family = Family::from_name(family)?;
+        let (component, module) = match coordinates.map(|c| c.split_once(':')) {
+            None => (None, context.module.as_str()),
+            Some(None) => (None, coordinates?),
+            // `component::page.adoc` names the ROOT module
+            Some(Some((component, ""))) => (Some(component), ROOT_MODULE),
+            Some(Some((component, module))) => (Some(component), module),
+        };
+        let version = match (version, component) {
+            (Some(version), _) => version.to_string(),
+            (None, None) => context.version.clone(),
+            (None, Some(component)) => self.latest_version(component)?.to_string(),
+        };
+        let id = ResourceId {
+            component: component.unwrap_or(&context.component).to_string(),
+            version: if version == "~" {
+                String::new()
+            } else {
+                version
+            },
+            module: module.to_string(),
+            family,
+            relative: relative.to_string(),
+        };
+        self.get(&id)
+    }
+
+    /// Parse every page of the catalog (see "Building" above), handling
+    /// failures according to `policy`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BatchError::Aborted`] for the first failing page when
+    /// `policy` is [`FailurePolicy::Abort`].
+    pub fn parse_pages(
+        &self,
+        site: &SiteOptions,
+        policy: FailurePolicy,
+    ) -> Result<AntoraBuild, BatchError> {
+        let shared = Arc::new(self.clone());
+        let mut parsed = Vec::new();
+        for component in &self.components {
+            for page in component.reading_order(self) {
+                let document = guarded(|| parse_page(page, component, &shared));
+                parsed.push((page, document));
+            }
+        }
+        let titles: HashMap<&ResourceId, String> = parsed
+            .iter()
+            .filter_map(|(page, document)| {
+                let title = document.as_ref().ok()?.header.as_ref()?.title.clone()?;
+                Some((&page.id, title))
+            })
+            .collect();
+
+        let mut build = AntoraBuild::default();
+        for (page, document) in parsed {
+            let mut links = Links {
+                catalog: self,
+                site,
+                context: &page.id,
+                titles: &titles,
+                unresolved: Vec::new(),
+            };
+            let document = document.map(|mut document| {
+                links.visit_document_mut(&mut document);
+                document
+            });
+            let name = page.id.page_name();
+            build
+                .unresolved
+                .extend(
+                    links
+                        .unresolved
+                        .into_iter()
+                        .map(|target| UnresolvedReference {
+                            page: name.clone(),
+                            target,
+                        }),
+                );
+            build.result.record(name, document, policy)?;
+        }
+        for component in &self.components {
+            for resource in &component.resources {
+                if matches!(resource.id.family, Family::Image | Family::Attachment) {
+                    build.assets.push(SiteFile {
+                        path: resource.id.asset_path(),
+                        contents: resource.contents.clone(),
+                    });
+                }
+            }
+        }
+        Ok(build)
+    }
+
+    fn component(&self, name: &str, version: &str) -> Option<&ComponentVersion> {
+        self.components
+            .iter()
+            .find(|c| c.name == name && c.version == version)
+    }
+
+    /// The latest version of the component `name` (see the module
+    /// documentation)
+    fn latest_version(&self, name: &str) -> Option<&str> {
+        self.components
+            .iter()
+            .filter(|c| c.name == name)
+            .map(|c| c.version.as_str())
+            .max_by(|a, b| compare_versions(a, b))
+    }
+}
+
+/// Order versions segment by segment, numerically where both segments are
+/// numbers; the empty (unversioned) version is the highest
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Greater,
+        (false, true) => return Ordering::Less,
+        (false, false) => {}
+    }
+    let mut a_parts = a.trim_start_matches('v').split(['.', '-']);
+    let mut b_parts = b.trim_start_matches('v').split(['.', '-']);
+    loop {
+        let ordering = match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                _ => a.cmp(b),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Parse `page` of `component`: attributes, includes, then `xref:` macros
+/// (see "Building" above)
+fn parse_page(
+    page: &Resource,
+    component: &ComponentVersion,
+    catalog: &Arc<ContentCatalog>,
+) -> Result<Document, String> {
+    let mut options = ParseOptions::new()
+        .substitute_attributes(true)
+        .generate_section_ids(true);
+    for (name, value) in &component.attributes {
+        options = options.attribute(name, value);
+    }
+    let attributes = options
+        .initial_attributes()
+        .map_err(|err| err.to_string())?;
+    let path = virtual_path(&page.id);
+    let includes = IncludeOptions::new(path.parent().unwrap_or(Path::new("/")))
+        .resolver(CatalogResolver(Arc::clone(catalog)));
+    let expanded =
+        expand_includes(&page.text(), &attributes, &includes).map_err(|err| err.to_string())?;
+    parse_document_with_options(&xref_macros(&expanded), &options).map_err(|err| err.to_string())
+}
+
+/// Rewrite `xref:target[text]` macros outside verbatim blocks as
+/// `<<target,text>>` references
+fn xref_macros(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut fence: Option<&str> = None;
+    for line in source.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        if fence == Some(text) {
+            fence = None;
+        } else if fence.is_none() && is_verbatim_delimiter(text) {
+            fence = Some(text);
+        }
+        if fence.is_some() {
+            out.push_str(line);
+            continue;
+        }
+        let rewritten =
+            XREF_MACRO.replace_all(line, |captures: &regex::Captures<'_>| match &captures[2] {
+                "" => format!("<<{}>>", &captures[1]),
+                text => format!("<<{},{text}>>", &captures[1]),
+            });
+        out.push_str(&rewritten);
+    }
+    out
+}
+
+/// Path standing for the file `id` while its includes are expanded
+///
+/// The include expander joins targets to the including file's directory,
+/// so `include::partial$a.adoc[]` in a page reaches [`CatalogResolver`] as
+/// `/@version@component/module/page/partial$a.adoc`.
+fn virtual_path(id: &ResourceId) -> PathBuf {
+    PathBuf::from(format!(
+        "/@{}@{}/{}/{}/{}",
+        id.version, id.component, id.module, id.family, id.relative
+    ))
+}
+
+/// Reads included files from a content catalog
+#[derive(Debug, Clone)]
+struct CatalogResolver(Arc<ContentCatalog>);
+
+impl IncludeResolver for CatalogResolver {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        let not_found = || io::Error::from(io::ErrorKind::NotFound);
+        let path = path.to_string_lossy().replace('\\', "/");
+        let mut parts = path.trim_start_matches('/').splitn(4, '/');
+        let (Some(coordinates), Some(module), Some(family), Some(rest)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(not_found());
+        };
+        let (version, component) = coordinates
+            .strip_prefix('@')
+            .and_then(|c| c.split_once('@'))
+            .ok_or_else(not_found)?;
+        let family = Family::from_name(family).ok_or_else(not_found)?;
+        // The first segment holding a resource ID starts the target; the
+        // segments before it are the including file's directory
+        let segments: Vec<&str> = rest.split('/').collect();
+        let start = segments
+            .iter()
+            .position(|segment| segment.contains(['$', ':', '@']));
+        let context = ResourceId {
+            component: component.to_string(),
+            version: version.to_string(),
+            module: module.to_string(),
+            family,
+            relative: match start {
+                Some(start) => segments[..start].join("/"),
+                None => rest.to_string(),
+            },
+        };
+        let resource = match start {
+            Some(start) => {
+                let target = segments[start..].join("/");
+                self.0.resolve(&target, &context, family)
+            }
+            None => self.0.get(&context),
+        };
+        let resource = resource.ok_or_else(not_found)?;
+        Ok(anchor_includes(&resource.text(), &resource.id))
+    }
+}
+
+/// Point the relative include targets of the file `id` at its own
+/// directory
+///
+/// The expander resolves them against the directory of the path it asked
+/// for, which for `include::partial$a.adoc[]` is the including file's.
+fn anchor_includes(text: &str, id: &ResourceId) -> String {
+    let path = virtual_path(id);
+    let dir = path.parent().unwrap_or(Path::new("/"));
+    let mut out = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let target = line
+            .strip_prefix("include::")
+            .and_then(|rest| directive(rest.trim_end_matches(['\n', '\r'])))
+            .map(|(target, _)| target)
+            .filter(|target| {
+                !target.starts_with('/')
+                    && !target.contains("://")
+                    && !target.contains(['$', ':', '@'])
+            });
+        match target {
+            Some(target) => {
+                let absolute = dir.join(target).to_string_lossy().replace('\\', "/");
+                out.push_str(&format!("include::{absolute}{}", &line[9 + target.len()..]));
+            }
+            None => out.push_str(line),
+        }
+    }
+    out
+}
+
+/// Points references to other pages and images at their URLs
+struct Links<'a> {
+    catalog: &'a ContentCatalog,
+    site: &'a SiteOptions,
+    /// The page being rewritten
+    context: &'a ResourceId,
+    /// Titles of the parsed pages
+    titles: &'a HashMap<&'a ResourceId, String>,
+    /// References that resolved to nothing
+    unresolved: Vec<String>,
+}
+
+impl Links<'_> {
+    /// The URL of the image `target`, unless it is a URL or absolute path
+    fn image(&mut self, target: &mut String) {
+        if target.contains("://") || target.starts_with('/') || target.starts_with("data:") {
+            return;
+        }
+        match self.catalog.resolve(target, self.context, Family::Image) {
+            Some(image) => {
+                *target = format!("{}/{}", self.site.base_url(), image.id.asset_path());
+            }
+            None => self.unresolved.push(target.clone()),
+        }
+    }
+}
+
+impl VisitorMut for Links<'_> {
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        if let Block::Image { target, .. } = block {
+            self.image(target);
+        }
+        walk_block_mut(self, block);
+    }
+
+    fn visit_inline_mut(&mut self, inline: &mut Inline) {
+        match inline {
+            Inline::Image { target, .. } => self.image(target),
+            Inline::XRef { id, text } => {
+                let (page, fragment) = match id.split_once('#') {
+                    Some((page, fragment)) => (page, Some(fragment)),
+                    None => (id.as_str(), None),
+                };
+                if page.is_empty() || !page.ends_with(".adoc") {
+                    return walk_inline_mut(self, inline);
+                }
+                let Some(resource) = self.catalog.resolve(page, self.context, Family::Page) else {
+                    self.unresolved.push(id.clone());
+                    return walk_inline_mut(self, inline);
+                };
+                let mut url = self.site.page_url(&resource.id.page_name());
+                if let Some(fragment) = fragment.filter(|fragment| !fragment.is_empty()) {
+                    url.push('#');
+                    url.push_str(fragment);
+                }
+                let text = if text.is_empty() {
+                    let title = self
+                        .titles
+                        .get(&resource.id)
+                        .cloned()
+                        .unwrap_or_else(|| resource.id.relative.clone());
+                    vec![Inline::Text(title)]
+                } else {
+                    std::mem::take(text)
+                };
+                *inline = Inline::Link {
+                    target: url,
+                    text,
+                    attributes: Attributes::new(),
+                };
+            }
+            _ => {}
+        }
+        walk_inline_mut(self, inline);
+    }
+}
+
+fn io_error(path: &Path, err: io::Error) -> AntoraError {
+    AntoraError::Io {
+        path: path.to_path_buf(),
+        message: err.to_string(),
+    }
+}
+
+/// The subset of YAML that component descriptors use
+mod yaml {
+    /// A YAML node
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Value {
+        Null,
+        Scalar(String),
+        List(Vec<Value>),
+        Map(Vec<(String, Value)>),
+    }
+
+    impl Value {
+        /// The value of `key` in a mapping
+        pub fn get(&self, key: &str) -> Option<&Value> {
+            match self {
+                Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+                _ => None,
+            }
+        }
+    }
+
+    /// A significant line: its indentation and content
+    struct Line<'a> {
+        indent: usize,
+        text: &'a str,
+        number: usize,
+    }
+
+    /// Parse a document whose root is a block mapping
+    pub fn parse(text: &str) -> Result<Value, String> {
+        let lines: Vec<Line<'_>> = text
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                let content = strip_comment(line).trim_end();
+                let trimmed = content.trim_start();
+                (!trimmed.is_empty() && trimmed != "---").then(|| Line {
+                    indent: content.len() - trimmed.len(),
+                    text: trimmed,
+                    number: index + 1,
+                })
+            })
+            .collect();
+        if lines.is_empty() {
+            return Ok(Value::Map(Vec::new()));
+        }
+        let mut position = 0;
+        let value = node(&lines, &mut position, lines[0].indent)?;
+        match lines.get(position) {
+            Some(line) => Err(format!("line {}: unexpected indentation", line.number)),
+            None => Ok(value),
+        }
+    }
+
+    /// Parse the block node starting at `lines[*position]`, indented by
+    /// `indent`
+    fn node(lines: &[Line<'_>], position: &mut usize, indent: usize) -> Result<Value, String> {
+        if is_item(lines[*position].text) {
+            let mut items = Vec::new();
+            while let Some(line) = lines.get(*position)
+                && line.indent == indent
+                && is_item(line.text)
+            {
+                let item = line.text[1..].trim_start();
+                *position += 1;
+                items.push(if item.is_empty() {
+                    nested(lines, position, indent, false)?
+                } else {
+                    scalar(item)
+                });
+            }
+            return Ok(Value::List(items));
+        }
+        let mut entries = Vec::new();
+        while let Some(line) = lines.get(*position)
+            && line.indent == indent
+        {
+            if is_item(line.text) {
+                return Err(format!("line {}: list item in a mapping", line.number));
+            }
+            let Some((key, value)) = split_entry(line.text) else {
+                return Err(format!("line {}: expected `key: value`", line.number));
+            };
+            *position += 1;
+            let value = if value.is_empty() {
+                nested(lines, position, indent, true)?
+            } else {
+                scalar(value)
+            };
+            entries.push((unquote(key).to_string(), value));
+        }
+        Ok(Value::Map(entries))
+    }
+
+    /// Parse the node nested under a key or list item at `indent`; a list
+    /// under a key may sit at the key's own indentation
+    fn nested(
+        lines: &[Line<'_>],
+        position: &mut usize,
+        indent: usize,
+        under_key: bool,
+    ) -> Result<Value, String> {
+        match lines.get(*position) {
+            Some(next) if next.indent > indent => node(lines, position, next.indent),
+            Some(next) if under_key && next.indent == indent && is_item(next.text) => {
+                node(lines, position, indent)
+            }
+            _ => Ok(Value::Null),
+        }
+    }
+
+    fn is_item(text: &str) -> bool {
+        text == "-" || text.starts_with("- ")
+    }
+
+    /// Split `key: value` at the first colon outside quotes that ends the
+    /// line or is followed by a space
+    fn split_entry(text: &str) -> Option<(&str, &str)> {
+        let mut quote = None;
+        for (index, c) in text.char_indices() {
+            match (quote, c) {
+                (None, '\'' | '"') if index == 0 => quote = Some(c),
+                (Some(q), c) if c == q => quote = None,
+                (None, ':') => {
+                    let rest = &text[index + 1..];
+                    if rest.is_empty() || rest.starts_with(' ') {
+                        return Some((text[..index].trim(), rest.trim()));
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// A scalar, or a flow list of scalars (`[a, b]`)
+    fn scalar(text: &str) -> Value {
+        if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            return Value::List(
+                inner
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(scalar)
+                    .collect(),
+            );
+        }
+        match text {
+            "~" | "null" => Value::Null,
+            _ => Value::Scalar(unquote(text).to_string()),
+        }
+    }
+
+    fn unquote(text: &str) -> &str {
+        for quote in ['\'', '"'] {
+            if let Some(inner) = text.strip_prefix(quote).and_then(|t| t.strip_suffix(quote)) {
+                return inner;
+            }
+        }
+        text
+    }
+
+    /// `line` without a trailing `# comment` outside quotes
+    fn strip_comment(line: &str) -> &str {
+        let mut quote = None;
+        let mut previous = ' ';
+        for (index, c) in line.char_indices() {
+            match (quote, c) {
+                (None, '\'' | '"') => quote = Some(c),
+                (Some(q), c) if c == q => quote = None,
+                (None, '#') if previous.is_whitespace() => return &line[..index],
+                _ => {}
+            }
+            previous = c;
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::html::{HtmlOptions, render_html};
+    use std::collections::BTreeSet;
+
+    /// Every resource ID of `catalog`
+    fn ids(catalog: &ContentCatalog) -> BTreeSet<String> {
+        catalog
+            .components
+            .iter()
+            .flat_map(|c| &c.resources)
+            .map(|resource| resource.id.to_string())
+            .collect()
+    }
+
+    fn component(descriptor: &str, files: &[(&str, &str)]) -> ComponentVersion {
+        let mut component = ComponentVersion::from_descriptor(descriptor).unwrap();
+        for (path, contents) in files {
+            component.add_file(path, *contents);
+        }
+        component
+    }
+
+    fn html(build: &AntoraBuild, name: &str) -> String {
+        let document = build
+            .result
+            .documents
+            .iter()
+            .find(|document| document.name == name)
+            .unwrap();
+        render_html(&document.document, &HtmlOptions::new())
+    }
+
+    #[test]
+    fn test_descriptor() {
+        let descriptor = "# Component\nname: docs\nversion: '2.0' # quoted\ntitle: \"Docs: Guide\"\n\
+                          nav:\n- modules/ROOT/nav.adoc\n- modules/admin/nav.adoc\n\
+                          asciidoc:\n  attributes:\n    product: Acme\n    hidden: false\n    \
+                          experimental: true\n    soft: value@\n";
+        let component = ComponentVersion::from_descriptor(descriptor).unwrap();
+        assert_eq!(component.name, "docs");
+        assert_eq!(component.version, "2.0");
+        assert_eq!(component.title.as_deref(), Some("Docs: Guide"));
+        assert_eq!(
+            component.nav,
+            ["modules/ROOT/nav.adoc", "modules/admin/nav.adoc"]
+        );
+        assert_eq!(
+            component.attributes,
+            [
+                ("product".to_string(), "Acme".to_string()),
+                ("experimental".to_string(), String::new()),
+                ("soft".to_string(), "value".to_string()),
+            ]
+        );
+
+        let unversioned =
+            ComponentVersion::from_descriptor("name: tools\nversion: ~\nnav: [a.adoc, b.adoc]\n")
+                .unwrap();
+        assert_eq!(unversioned.version, "");
+        assert_eq!(unversioned.nav, ["a.adoc", "b.adoc"]);
+
+        for invalid in [
+            "version: 1.0\n",
+            "name: docs\n",
+            "name: docs\n  version: 1\n",
+            "- a\n",
+        ] {
+            assert!(
+                matches!(
+                    ComponentVersion::from_descriptor(invalid),
+                    Err(AntoraError::Descriptor { .. })
+                ),
+                "{invalid:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_resource_ids() {
+        let mut catalog = ContentCatalog::new();
+        for version in ["1.0", "2.0", "10.0"] {
+            catalog.add(component(
+                &format!("name: docs\nversion: '{version}'\n"),
+                &[
+                    ("modules/ROOT/pages/index.adoc", ""),
+                    ("modules/admin/pages/install.adoc", ""),
+                    ("modules/admin/partials/steps.adoc", ""),
+                    ("README.md", ""),
+                ],
+            ));
+        }
+        catalog.add(component(
+            "name: tools\nversion: ~\n",
+            &[("modules/ROOT/images/logo.png", "")],
+        ));
+        assert_eq!(ids(&catalog).len(), 10);
+
+        let context = catalog.components[1].resources[0].id.clone();
+        assert_eq!(context.to_string(), "2.0@docs:ROOT:page$index.adoc");
+        let resolve = |target: &str, family: Family| {
+            catalog
+                .resolve(target, &context, family)
+                .map(|resource| resource.id.to_string())
+        };
+        assert_eq!(
+            resolve("admin:install.adoc", Family::Page).as_deref(),
+            Some("2.0@docs:admin:page$install.adoc")
+        );
+        assert_eq!(
+            resolve("1.0@admin:partial$steps.adoc", Family::Page).as_deref(),
+            Some("1.0@docs:admin:partial$steps.adoc")
+        );
+        assert_eq!(
+            resolve("docs:ROOT:index.adoc", Family::Page).as_deref(),
+            Some("10.0@docs:ROOT:page$index.adoc")
+        );
+        assert_eq!(
+            resolve("tools:ROOT:logo.png", Family::Image).as_deref(),
+            Some("tools:ROOT:image$logo.png")
+        );
+        assert_eq!(resolve("install.adoc", Family::Page), None);
+        assert_eq!(resolve("admin:install.adoc", Family::Partial), None);
+        assert_eq!(resolve("admin:bogus$install.adoc", Family::Page), None);
+
+        let id = &catalog.components[3].resources[0].id;
+        assert_eq!(id.asset_path(), "tools/_images/logo.png");
+        assert_eq!(
+            catalog.components[0].resources[1].id.page_name(),
+            "docs/1.0/admin/install.adoc"
+        );
+    }
+
+    #[test]
+    fn test_parse_pages() {
+        let mut catalog = ContentCatalog::new();
+        catalog.add(component(
+            "name: docs\nversion: '2.0'\nnav: [modules/ROOT/nav.adoc]\n\
+             asciidoc:\n  attributes:\n    product: Acme\n",
+            &[
+                (
+                    "modules/ROOT/nav.adoc",
+                    "* xref:admin:install.adoc[]\n* xref:index.adoc[]\n",
+                ),
+                (
+                    "modules/ROOT/pages/index.adoc",
+                    "= Docs\n\n{product} docs. See xref:admin:install.adoc#_setup[setup], \
+                     <<admin:install.adoc#>>, xref:tools::index.adoc[] and xref:gone.adoc[].\n\n\
+                     image::diagram.png[]\n\n----\nxref:kept.adoc[]\n----\n",
+                ),
+                (
+                    "modules/admin/pages/install.adoc",
+                    "= Installing\n\ninclude::partial$steps.adoc[]\n",
+                ),
+                (
+                    "modules/admin/partials/steps.adoc",
+                    "== Setup\n\ninclude::more/detail.adoc[]\n",
+                ),
+                ("modules/admin/partials/more/detail.adoc", "Run it.\n"),
+                ("modules/ROOT/images/diagram.png", "PNG"),
+                ("modules/ROOT/attachments/sample.zip", "ZIP"),
+            ],
+        ));
+        catalog.add(component(
+            "name: tools\nversion: ~\n",
+            &[("modules/ROOT/pages/index.adoc", "= Tools\n")],
+        ));
+        let site = SiteOptions::new("/site");
+        let build = catalog.parse_pages(&site, FailurePolicy::Abort).unwrap();
+
+        let names: Vec<&str> = build
+            .result
+            .documents
+            .iter()
+            .map(|document| document.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "docs/2.0/admin/install.adoc",
+                "docs/2.0/index.adoc",
+                "tools/index.adoc"
+            ]
+        );
+
+        let index = html(&build, "docs/2.0/index.adoc");
+        assert!(index.contains(
+            "<p>Acme docs. See <a href=\"/site/docs/2.0/admin/install.html#_setup\">setup</a>, \
+             <a href=\"/site/docs/2.0/admin/install.html\">Installing</a>, \
+             <a href=\"/site/tools/index.html\">Tools</a> and <a href=\"gone.html\">"
+        ));
+        assert!(index.contains("<img src=\"/site/docs/2.0/_images/diagram.png\""));
+        assert!(index.contains("xref:kept.adoc[]"));
+
+        let install = html(&build, "docs/2.0/admin/install.adoc");
+        assert!(
+            install.contains("<h2 id=\"_setup\">Setup</h2>"),
+            "{install}"
+        );
+        assert!(install.contains("<p>Run it.</p>"));
+
+        assert_eq!(
+            build.unresolved,
+            [UnresolvedReference {
+                page: "docs/2.0/index.adoc".to_string(),
+                target: "gone.adoc".to_string(),
+            }]
+        );
+        let assets: Vec<&str> = build
+            .assets
+            .iter()
+            .map(|asset| asset.path.as_str())
+            .collect();
+        assert_eq!(
+            assets,
+            [
+                "docs/2.0/_images/diagram.png",
+                "docs/2.0/_attachments/sample.zip"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failures() {
+        let mut catalog = ContentCatalog::new();
+        catalog.add(component(
+            "name: docs\nversion: '1'\n",
+            &[
+                (
+                    "modules/ROOT/pages/a.adoc",
+                    "include::partial$missing.adoc[]\n",
+                ),
+                ("modules/ROOT/pages/b.adoc", "Fine.\n"),
+            ],
+        ));
+        let site = SiteOptions::new("");
+        let err = catalog
+            .parse_pages(&site, FailurePolicy::Abort)
+            .unwrap_err();
+        let BatchError::Aborted { document, .. } = err;
+        assert_eq!(document, "docs/1/a.adoc");
+
+        let build = catalog.parse_pages(&site, FailurePolicy::Skip).unwrap();
+        assert_eq!(build.result.documents.len(), 1);
+        assert_eq!(build.result.diagnostics[0].document, "docs/1/a.adoc");
+    }
+
+    #[test]
+    fn test_load() {
+        let root = std::env::temp_dir().join(format!("doctora-antora-{}", std::process::id()));
+        fs::create_dir_all(root.join("modules/ROOT/pages")).unwrap();
+        fs::write(root.join("antora.yml"), "name: docs\nversion: '1.0'\n").unwrap();
+        fs::write(root.join("modules/ROOT/pages/index.adoc"), "= Home\n").unwrap();
+        fs::write(root.join("modules/ROOT/notes.txt"), "ignored").unwrap();
+
+        let component = ComponentVersion::load(&root);
+        let missing = ComponentVersion::load(root.join("missing"));
+        fs::remove_dir_all(&root).unwrap();
+
+        let component = component.unwrap();
+        assert_eq!(component.resources.len(), 1);
+        assert_eq!(
+            component.resources[0].id.to_string(),
+            "1.0@docs:ROOT:page$index.adoc"
+        );
+        assert!(matches!(missing, Err(AntoraError::Io { .. })));
+    }
+}
@@ -6,10 +6,47 @@
 //! # Structure
 //!
 //! The AST is hierarchical:
-//! - `Document` contains a vector of `Block` nodes
-//! - `Block` can be a `Section` (heading with nested blocks) or `Paragraph`
+//! - `Document` contains an optional `DocumentHeader` (title, authors,
+//!   revision, attribute entries), a vector of `Block` nodes, and the
+//!   document attributes
+//! - `Block` can be a `Section` (heading with nested blocks), `Paragraph`,
+//!   `UnorderedList`, `OrderedList`, `DescriptionList`, `Listing`, `Table`,
+//!   `Quote`, `Example`, `Sidebar`, `Open` (containers of nested blocks),
+//!   `Verse`, `AttributeEntry` (`:name: value` after the header), or
+//!   `Unknown` (unsupported syntax, kept as written)
+//! - Every `Block` carries `BlockMetadata`: the title and attribute list lines
+//!   written before it, if any (title, ID, roles, options, positional and
+//!   named attributes)
 //! - `Paragraph` contains a vector of `Inline` nodes
+//! - Lists contain `ListItem`s, which hold inline text and attached blocks
+//!   (continuation paragraphs and nested lists); description lists contain
+//!   `DescriptionItem` term/description pairs
+//! - Tables contain `TableRow`s of `TableCell`s, which hold inline content
 //! - `Inline` can be plain `Text`, `Bold`, or `Italic` formatting
+//!
+//! [`visit`] walks the tree, so passes over the AST only handle the nodes
+//! they care about ([`Visitor`], [`VisitorMut`]).
+//!
+//! # Serde
+//!
+//! With the `serde` feature, the AST types implement `Serialize` and
+//! `Deserialize` with serde's default representation: structs are objects,
+//! and enum variants are objects keyed by the variant name
+//! (`{"Paragraph": {...}}`, `{"Text": "..."}`). Attributes are an object of
+//! names to values. The representation follows the Rust types, so it
+//! changes when they do; JSON for other tools comes from
+//! [`crate::schema::to_json`], the versioned format with a published schema
+//! and migrations.
+
+pub mod visit;
+
+pub use visit::{Visitor, VisitorMut};
+
+use crate::attributes::{AttributeError, Attributes};
+use crate::limits::Truncation;
+use crate::subs::Substitutions;
+use crate::token::Span;
+use std::collections::BTreeMap;
 
 /// Root document node
 ///
@@ -18,33 +55,45 @@
 /// # Examples
 ///
 /// ```
-/// use doctora::ast::{Document, Block, Inline};
-///
-/// let doc = Document {
-///     blocks: vec![
-///         Block::Section {
-///             level: 1,
-///             title: "Document Title".to_string(),
-///             content: vec![],
-///         },
-///     ],
-/// };
+/// use doctora::ast::{Block, BlockMetadata, Document, Inline};
+///
+/// let doc = Document::with_blocks(vec![
+///     Block::Section {
+///         level: 1,
+///         title: vec![Inline::Text("Document Title".to_string())],
+///         content: vec![],
+///         anchor: None,
+///         metadata: BlockMetadata::default(),
+///     },
+/// ]);
 /// ```
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Document {
+    /// Document header, if the document starts with one
+    pub header: Option<DocumentHeader>,
     /// Top-level blocks in the document
     pub blocks: Vec<Block>,
+    /// Document attributes
+    pub attributes: Attributes,
+    /// Structures cut down by [`Limits`](crate::limits::Limits) while parsing
+    pub truncations: Vec<Truncation>,
 }
 
 impl Document {
     /// Creates a new empty document
     pub fn new() -> Self {
-        Self { blocks: Vec::new() }
+        Self::with_blocks(Vec::new())
     }
 
     /// Creates a document with the given blocks
     pub fn with_blocks(blocks: Vec<Block>) -> Self {
-        Self { blocks }
+        Self {
+            header: None,
+            blocks,
+            attributes: Attributes::new(),
+            truncations: Vec::new(),
+        }
     }
 }
 
@@ -54,6 +103,137 @@ impl Default for Document {
     }
 }
 
+/// Document header
+///
+/// The header is the run of lines at the top of the document up to the first
+/// blank line: the `= Title` line, then an author line and a revision line
+/// (only after a title), then attribute entries:
+///
+/// ```asciidoc
+/// = Document Title
+/// Ada Lovelace <ada@example.com>; Charles Babbage
+/// v1.2, 2024-05-01: Draft
+/// :toc:
+/// :version: 1.2
+/// ```
+///
+/// The resolved attribute values, including those derived from the title,
+/// author, and revision lines (`doctitle`, `author`, `email`, `revnumber`,
+/// ...), are in [`Document::attributes`].
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DocumentHeader {
+    /// Document title, without the `=` marker
+    pub title: Option<String>,
+    /// Authors from the author line, in order
+    pub authors: Vec<Author>,
+    /// Revision line
+    pub revision: Option<Revision>,
+    /// Attribute entries, in source order
+    pub attributes: Vec<AttributeEntry>,
+}
+
+/// An author from the header's author line (`Name <email>`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Author {
+    /// Full name
+    pub name: String,
+    /// Email address, without the angle brackets
+    pub email: Option<String>,
+}
+
+/// The header's revision line (`v1.2, 2024-05-01: Remark`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Revision {
+    /// Revision number, without a leading `v`
+    pub number: Option<String>,
+    /// Revision date, as written
+    pub date: Option<String>,
+    /// Revision remark (the text after `:`)
+    pub remark: Option<String>,
+}
+
+/// An attribute entry line (`:name: value`, or `:name!:` to unset)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttributeEntry {
+    /// Attribute name
+    pub name: String,
+    /// Raw value, before interpolation; `None` unsets the attribute
+    pub value: Option<String>,
+}
+
+impl DocumentHeader {
+    /// Define the header's attributes in `attributes`
+    ///
+    /// Derived attributes (`doctitle`, `author`, `firstname`, `revnumber`,
+    /// ...) come first, so attribute entries can reference or override them.
+    /// Attributes for which `locked` returns true are left unchanged, and
+    /// values are limited to `max` bytes.
+    pub(crate) fn define_attributes(
+        &self,
+        attributes: &mut Attributes,
+        locked: impl Fn(&str) -> bool,
+        max: Option<usize>,
+    ) -> Result<(), AttributeError> {
+        let mut derived: Vec<(&str, String)> = Vec::new();
+        if let Some(title) = &self.title {
+            derived.push(("doctitle", title.clone()));
+        }
+        if let Some(author) = self.authors.first() {
+            let names: Vec<&str> = author.name.split_whitespace().collect();
+            derived.push(("author", author.name.clone()));
+            if let Some(email) = &author.email {
+                derived.push(("email", email.clone()));
+            }
+            if let Some(first) = names.first() {
+                derived.push(("firstname", first.to_string()));
+            }
+            if let [_, middle, _] = names[..] {
+                derived.push(("middlename", middle.to_string()));
+            }
+            if let [_, .., last] = names[..] {
+                derived.push(("lastname", last.to_string()));
+            }
+            let initials = names.iter().filter_map(|n| n.chars().next()).collect();
+            derived.push(("authorinitials", initials));
+            let all: Vec<&str> = self.authors.iter().map(|a| a.name.as_str()).collect();
+            derived.push(("authors", all.join(", ")));
+        }
+        if let Some(revision) = &self.revision {
+            for (name, value) in [
+                ("revnumber", &revision.number),
+                ("revdate", &revision.date),
+                ("revremark", &revision.remark),
+            ] {
+                if let Some(value) = value {
+                    derived.push((name, value.clone()));
+                }
+            }
+        }
+
+        // Derived values are taken literally, not interpolated
+        for (name, value) in derived {
+            if !locked(name) {
+                attributes.set(name, value);
+            }
+        }
+        for entry in self.attributes.iter().filter(|entry| !locked(&entry.name)) {
+            match &entry.value {
+                Some(value) => {
+                    attributes.define_within(entry.name.clone(), value, max)?;
+                }
+                None => {
+                    attributes.unset(&entry.name);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Block-level AST nodes
 ///
 /// Blocks represent structural elements like sections and paragraphs.
@@ -62,29 +242,37 @@ impl Default for Document {
 /// # Examples
 ///
 /// ```
-/// use doctora::ast::{Block, Inline};
+/// use doctora::ast::{Block, BlockMetadata, Inline};
 ///
 /// // Section with nested content
 /// let section = Block::Section {
 ///     level: 2,
-///     title: "Section Title".to_string(),
+///     title: vec![Inline::Text("Section Title".to_string())],
 ///     content: vec![
 ///         Block::Paragraph {
 ///             content: vec![Inline::Text("Paragraph text".to_string())],
+///             metadata: BlockMetadata::default(),
 ///         },
 ///     ],
+///     anchor: None,
+///     metadata: BlockMetadata::default(),
 /// };
 ///
-/// // Simple paragraph
+/// // Simple paragraph with a role (`[.lead]`)
 /// let para = Block::Paragraph {
 ///     content: vec![
 ///         Inline::Text("Some ".to_string()),
 ///         Inline::Bold(vec![Inline::Text("bold".to_string())]),
 ///         Inline::Text(" text".to_string()),
 ///     ],
+///     metadata: BlockMetadata {
+///         roles: vec!["lead".to_string()],
+///         ..BlockMetadata::default()
+///     },
 /// };
 /// ```
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Block {
     /// Section (heading with nested content)
     ///
@@ -93,10 +281,14 @@ pub enum Block {
     Section {
         /// Heading level (1-6, where 1 is the top level)
         level: u8,
-        /// Section title (plain text for POC)
-        title: String,
+        /// Section title, with inline formatting (see [`Block::title_text`])
+        title: Vec<Inline>,
         /// Nested blocks (paragraphs, subsections, etc.)
         content: Vec<Block>,
+        /// Anchor from an `[[id]]` or `[#id]` line before the heading
+        anchor: Option<Anchor>,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
     },
 
     /// Paragraph (text with inline formatting)
@@ -106,9 +298,628 @@ pub enum Block {
     Paragraph {
         /// Inline content (text and formatting)
         content: Vec<Inline>,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Unordered list (`*` or `-` markers)
+    ///
+    /// Items whose marker differs from the list's own marker (e.g. `**`
+    /// under `*`) form a nested list inside the preceding item.
+    UnorderedList {
+        /// List items, in source order
+        items: Vec<ListItem>,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Ordered list (`.` markers)
+    OrderedList {
+        /// List items, in source order
+        items: Vec<ListItem>,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Description list (`term:: description` entries)
+    DescriptionList {
+        /// Term/description pairs, in source order
+        items: Vec<DescriptionItem>,
+        /// The separator between each term and its description, as written
+        /// (`::`, `:::`, `::::` or `;;`)
+        separator: String,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Listing (`----`) or literal (`....`) block
+    ///
+    /// The content is kept verbatim: no inline formatting is parsed in it.
+    Listing {
+        /// Source language (`[source,rust]`), if any
+        language: Option<String>,
+        /// Lines between the delimiters, without the final line feed
+        content: String,
+        /// Block attributes from the preceding attribute list line
+        ///
+        /// `style` is always set: the first positional attribute, or
+        /// `listing` / `literal` depending on the delimiter.
+        attributes: Attributes,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Quote block (`____` delimited, or a paragraph styled `[quote]`)
+    Quote {
+        /// Who is quoted (`[quote, attribution, citation]`)
+        attribution: Option<String>,
+        /// Title of the quoted work
+        citation: Option<String>,
+        /// Quoted blocks
+        content: Vec<Block>,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Verse block (a quote block or paragraph styled `[verse]`)
+    ///
+    /// Unlike a quote, the content is a single run of text whose line breaks
+    /// are kept.
+    Verse {
+        /// Who is quoted (`[verse, attribution, citation]`)
+        attribution: Option<String>,
+        /// Title of the quoted work
+        citation: Option<String>,
+        /// Verse text, with line feeds between lines
+        content: Vec<Inline>,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Admonition (`NOTE: text`, or a paragraph or example block styled
+    /// `[NOTE]`, `[TIP]`, `[IMPORTANT]`, `[CAUTION]` or `[WARNING]`)
+    Admonition {
+        /// Which admonition it is
+        kind: AdmonitionKind,
+        /// The paragraph of `NOTE: text` and styled paragraphs, or the
+        /// blocks of an example block
+        content: Vec<Block>,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Example block (`====` delimited)
+    Example {
+        /// Nested blocks
+        content: Vec<Block>,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Sidebar block (`****` delimited)
+    Sidebar {
+        /// Nested blocks
+        content: Vec<Block>,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Open block (`--` delimited)
+    ///
+    /// A generic container, mostly used to attach a block attribute list to
+    /// content that has no delimiters of its own, such as several paragraphs
+    /// or a list. The style (first positional attribute), if any, says what
+    /// the block stands for (`[abstract]`, `[partintro]`).
+    Open {
+        /// Nested blocks
+        content: Vec<Block>,
+        /// Block attributes from the preceding attribute list line
+        attributes: Attributes,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Table (`|===` delimited)
+    Table {
+        /// Column specifications, from the `cols` attribute or one per cell
+        /// of the first line
+        columns: Vec<ColumnSpec>,
+        /// Header row (`options="header"`, or a first line followed by a
+        /// blank line)
+        header: Option<TableRow>,
+        /// Body rows
+        rows: Vec<TableRow>,
+        /// Block attributes from the preceding attribute list line
+        attributes: Attributes,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Block image (`image::target[alt,width,height]` on its own line)
+    Image {
+        /// Image path or URL, as written
+        target: String,
+        /// Macro attributes, with the positional ones named `alt`, `width`
+        /// and `height`, plus the preceding attribute list line's attributes
+        attributes: Attributes,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Passthrough block (`++++` delimited)
+    ///
+    /// The content is raw output for one backend, written without any
+    /// substitutions. The `backend` attribute says which one
+    /// (`[backend=latex]`), `html` by default; every other backend skips the
+    /// block (see [`Block::passthrough_for`]).
+    Passthrough {
+        /// The lines between the delimiters (without the final line feed)
+        content: String,
+        /// The backend the content is written for, in lower case
+        backend: String,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Line (`// text`) or block (`////` delimited) comment
+    ///
+    /// Comments produce no output. The parser only keeps them when asked to
+    /// (see [`ParseOptions::keep_comments`](crate::options::ParseOptions::keep_comments)),
+    /// for tools that write the source back.
+    Comment {
+        /// Text after the `//` of a line comment, or the lines between the
+        /// delimiters of a block comment (without the final line feed)
+        content: String,
+        /// True for a `////` block comment
+        delimited: bool,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Attribute entry after the header (`:name: value`, `:name!:`)
+    ///
+    /// Entries produce no output. They define or unset the attribute from
+    /// that point on, so only later references see the new value (see
+    /// [`crate::resolve`]). Entries in the header are kept in
+    /// [`DocumentHeader::attributes`] instead.
+    AttributeEntry {
+        /// The entry as written
+        entry: AttributeEntry,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
+    },
+
+    /// Syntax the parser recognizes as a block but does not model
+    ///
+    /// Block macros other than `image::` (`video::file.mp4[]`, `toc::[]`)
+    /// and delimited blocks without a kind of their own (`` ``` `` fences,
+    /// `,===` and `:===` data tables) are kept as written, so tools that
+    /// write the source back reproduce them unchanged. Renderers skip them,
+    /// apart from the lists `toc::[figures]` and its siblings ask for (see
+    /// [`crate::outline`]).
+    Unknown {
+        /// The block's source lines, from its first to its last line
+        /// (without the final line feed)
+        raw: String,
+        /// Byte range of `raw` in the parsed source
+        span: Span,
+        /// Attribute list line before the block
+        metadata: BlockMetadata,
     },
 }
 
+/// A single list item
+///
+/// # Examples
+///
+/// ```
+/// use doctora::ast::{Block, BlockMetadata, Inline, ListItem};
+///
+/// // * Parent
+/// // ** Child
+/// let list = Block::UnorderedList {
+///     items: vec![ListItem {
+///         content: vec![Inline::Text("Parent".to_string())],
+///         blocks: vec![Block::UnorderedList {
+///             items: vec![ListItem::new(vec![Inline::Text("Child".to_string())])],
+///             metadata: BlockMetadata::default(),
+///         }],
+///     }],
+///     metadata: BlockMetadata::default(),
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ListItem {
+    /// Item text (may span several source lines)
+    pub content: Vec<Inline>,
+    /// Blocks attached with a list continuation (`+`) and nested lists
+    pub blocks: Vec<Block>,
+}
+
+impl ListItem {
+    /// Creates an item with text and no attached blocks
+    pub fn new(content: Vec<Inline>) -> Self {
+        Self {
+            content,
+            blocks: Vec::new(),
+        }
+    }
+}
+
+/// A term and its description in a description list
+///
+/// # Examples
+///
+/// ```
+/// use doctora::ast::{DescriptionItem, Inline};
+///
+/// // CPU:: The brain of the computer
+/// let item = DescriptionItem {
+///     term: vec![Inline::Text("CPU".to_string())],
+///     description: vec![Inline::Text("The brain of the computer".to_string())],
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DescriptionItem {
+    /// The term being described
+    pub term: Vec<Inline>,
+    /// The description (may span several source lines; empty if the term
+    /// has no description)
+    pub description: Vec<Inline>,
+}
+
+/// A table column specification
+///
+/// Widths are relative: `cols="1,2,1"` gives the middle column half of the
+/// table width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColumnSpec {
+    /// Relative width (1 if unspecified)
+    pub width: u32,
+}
+
+/// A row of table cells
+///
+/// A row has fewer cells than the table has columns when cells span
+/// several columns or when cells from earlier rows span into it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TableRow {
+    /// Cells, in source order
+    pub cells: Vec<TableCell>,
+}
+
+/// A table cell
+///
+/// # Examples
+///
+/// ```
+/// use doctora::ast::{Inline, TableCell};
+///
+/// // 2+| Wide cell
+/// let cell = TableCell {
+///     colspan: 2,
+///     ..TableCell::new(vec![Inline::Text("Wide cell".to_string())])
+/// };
+/// assert_eq!(cell.rowspan, 1);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TableCell {
+    /// Cell text (may span several source lines)
+    pub content: Vec<Inline>,
+    /// Number of columns the cell spans (`2+|`)
+    pub colspan: usize,
+    /// Number of rows the cell spans (`.2+|`)
+    pub rowspan: usize,
+}
+
+impl TableCell {
+    /// Creates a cell spanning one column and one row
+    pub fn new(content: Vec<Inline>) -> Self {
+        Self {
+            content,
+            colspan: 1,
+            rowspan: 1,
+        }
+    }
+}
+
+/// A cross-reference target: `[[id]]`, `[[id,reftext]]`, or `[#id]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Anchor {
+    /// The ID that cross references (`<<id>>`) point to
+    pub id: String,
+    /// Text for cross references to this anchor that have none of their own
+    pub reftext: Option<String>,
+}
+
+impl Anchor {
+    /// Creates an anchor without reference text
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            reftext: None,
+        }
+    }
+}
+
+/// The title line (`.Title`) and attribute list line (`[source,ruby]`,
+/// `[.role#id%option]`) written before a block, split into their parts
+///
+/// Every block carries one; it is empty if the block has neither line.
+/// The shorthands in the first positional attribute are taken apart: in
+/// `[source#main.wide%linenums,ruby]` the style is `source`, the ID `main`,
+/// the role `wide` and the option `linenums`.
+///
+/// # Examples
+///
+/// ```
+/// use doctora::ast::BlockMetadata;
+/// use doctora::attributes::parse_attribute_list;
+///
+/// let metadata = BlockMetadata::from(&parse_attribute_list("source.wide%linenums,ruby,role=dark"));
+/// assert_eq!(metadata.style(), Some("source"));
+/// assert_eq!(metadata.positional, vec!["source", "ruby"]);
+/// assert_eq!(metadata.roles, vec!["wide", "dark"]);
+/// assert!(metadata.has_option("linenums"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockMetadata {
+    /// Block title, from a `.Title` line: the caption of a figure, table or
+    /// listing
+    pub title: Option<Vec<Inline>>,
+    /// ID from the `#id` shorthand or the `id` attribute
+    pub id: Option<String>,
+    /// Roles from `.role` shorthands and the `role` attribute
+    pub roles: Vec<String>,
+    /// Options from `%option` shorthands and the `options` attribute
+    pub options: Vec<String>,
+    /// Positional attributes in order, the first without its shorthands
+    ///
+    /// Skipped positions (`[,ruby]`) are empty strings; trailing empty
+    /// positions are dropped, so `[.role]` has none.
+    pub positional: Vec<String>,
+    /// Named attributes (`name=value`), including `id`, `role` and
+    /// `options` as written
+    pub named: BTreeMap<String, String>,
+}
+
+impl BlockMetadata {
+    /// Returns true if the block had no title and no attribute line (or an
+    /// empty one)
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The block style: the first positional attribute, if it is not empty
+    pub fn style(&self) -> Option<&str> {
+        self.positional
+            .first()
+            .map(String::as_str)
+            .filter(|style| !style.is_empty())
+    }
+
+    /// Returns true if the block has option `name`
+    pub fn has_option(&self, name: &str) -> bool {
+        self.options.iter().any(|option| option == name)
+    }
+
+    /// Returns true if a source block numbers its lines: the `linenums`
+    /// option (`[source%linenums,ruby]`) or third positional attribute
+    /// (`[source,ruby,linenums]`)
+    pub fn line_numbers(&self) -> bool {
+        self.has_option("linenums") || self.positional.get(2).is_some_and(|p| p == "linenums")
+    }
+
+    /// The 1-based line numbers listed in the `highlight` attribute, sorted
+    ///
+    /// Entries are separated by commas or semicolons and are a line number or
+    /// an inclusive range (`2..4` or `2-4`): `highlight="1,3..5"` is lines 1,
+    /// 3, 4 and 5. Entries that are not numbers are ignored.
+    pub fn highlight_lines(&self) -> Vec<usize> {
+        let Some(spec) = self.named.get("highlight") else {
+            return Vec::new();
+        };
+        let mut lines: Vec<usize> = spec
+            .split([',', ';'])
+            .filter_map(|entry| {
+                let entry = entry.trim();
+                let (start, end) = entry
+                    .split_once("..")
+                    .or_else(|| entry.split_once('-'))
+                    .unwrap_or((entry, entry));
+                Some(start.trim().parse::<usize>().ok()?..=end.trim().parse().ok()?)
+            })
+            .flatten()
+            .filter(|&line| line > 0)
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+        lines
+    }
+
+    /// Text alignment from a `text-left`, `text-center`, `text-right` or
+    /// `text-justify` role; the last one wins
+    pub fn text_align(&self) -> Option<TextAlign> {
+        self.roles
+            .iter()
+            .rev()
+            .find_map(|role| match role.as_str() {
+                "text-left" => Some(TextAlign::Left),
+                "text-center" => Some(TextAlign::Center),
+                "text-right" => Some(TextAlign::Right),
+                "text-justify" => Some(TextAlign::Justify),
+                _ => None,
+            })
+    }
+
+    /// Indentation level from an `indent-N` role (`[.indent-2]`), 0 without
+    /// one; the deepest wins
+    pub fn indent(&self) -> u8 {
+        self.roles
+            .iter()
+            .filter_map(|role| role.strip_prefix("indent-")?.parse().ok())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Horizontal alignment of a block's text, set with a role (see
+/// [`BlockMetadata::text_align`])
+///
+/// HTML output keeps the role as a class; print backends position lines with
+/// [`align_offset`](crate::render::measure::align_offset).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextAlign {
+    /// Flush left (`text-left`)
+    Left,
+    /// Centered (`text-center`)
+    Center,
+    /// Flush right (`text-right`)
+    Right,
+    /// Flush on both sides (`text-justify`); the last line is flush left
+    Justify,
+}
+
+impl From<&Attributes> for BlockMetadata {
+    /// Split a parsed attribute list (see
+    /// [`parse_attribute_list`](crate::attributes::parse_attribute_list))
+    fn from(attributes: &Attributes) -> Self {
+        let mut metadata = BlockMetadata {
+            id: attributes.id().map(str::to_string),
+            roles: attributes.roles().into_iter().map(str::to_string).collect(),
+            options: attributes
+                .options()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            ..Self::default()
+        };
+        for (name, value) in attributes.iter() {
+            match name.parse::<usize>() {
+                Ok(position) if position > 0 => {
+                    if metadata.positional.len() < position {
+                        metadata.positional.resize(position, String::new());
+                    }
+                    metadata.positional[position - 1] = value.to_string();
+                }
+                _ => {
+                    metadata.named.insert(name.to_string(), value.to_string());
+                }
+            }
+        }
+        if let Some(first) = metadata.positional.first_mut() {
+            *first = attributes.style().unwrap_or_default().to_string();
+        }
+        while metadata.positional.last().is_some_and(String::is_empty) {
+            metadata.positional.pop();
+        }
+        metadata
+    }
+}
+
+/// The kind of an [`Block::Admonition`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AdmonitionKind {
+    /// `NOTE`
+    Note,
+    /// `TIP`
+    Tip,
+    /// `IMPORTANT`
+    Important,
+    /// `CAUTION`
+    Caution,
+    /// `WARNING`
+    Warning,
+}
+
+impl AdmonitionKind {
+    /// Every kind, in the order Asciidoctor lists them
+    pub const ALL: [AdmonitionKind; 5] = [
+        AdmonitionKind::Note,
+        AdmonitionKind::Tip,
+        AdmonitionKind::Important,
+        AdmonitionKind::Caution,
+        AdmonitionKind::Warning,
+    ];
+
+    /// The label the kind is written with (`NOTE`)
+    pub fn label(self) -> &'static str {
+        match self {
+            AdmonitionKind::Note => "NOTE",
+            AdmonitionKind::Tip => "TIP",
+            AdmonitionKind::Important => "IMPORTANT",
+            AdmonitionKind::Caution => "CAUTION",
+            AdmonitionKind::Warning => "WARNING",
+        }
+    }
+
+    /// The lowercase name (`note`), used for class names and icon files
+    pub fn name(self) -> &'static str {
+        match self {
+            AdmonitionKind::Note => "note",
+            AdmonitionKind::Tip => "tip",
+            AdmonitionKind::Important => "important",
+            AdmonitionKind::Caution => "caution",
+            AdmonitionKind::Warning => "warning",
+        }
+    }
+
+    /// The caption shown for the kind (`Note`), unless the document sets
+    /// its `*-caption` attribute (`note-caption`)
+    pub fn caption(self) -> &'static str {
+        match self {
+            AdmonitionKind::Note => "Note",
+            AdmonitionKind::Tip => "Tip",
+            AdmonitionKind::Important => "Important",
+            AdmonitionKind::Caution => "Caution",
+            AdmonitionKind::Warning => "Warning",
+        }
+    }
+
+    /// The kind written as `label`, if it is one
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.label() == label)
+    }
+}
+
+/// A line-breaking hint kept as its own inline node (see [`crate::hints`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BreakHint {
+    /// A soft hyphen (`&shy;`): the word may break here, with a hyphen
+    SoftHyphen,
+    /// A non-breaking space (`&nbsp;`, `{nbsp}`): a space the line must not
+    /// break at
+    NoBreakSpace,
+}
+
+impl BreakHint {
+    /// The Unicode character for this hint
+    pub fn as_char(self) -> char {
+        match self {
+            BreakHint::SoftHyphen => '\u{00AD}',
+            BreakHint::NoBreakSpace => '\u{00A0}',
+        }
+    }
+
+    /// The hint for `c`, if it is one
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            '\u{00AD}' => Some(BreakHint::SoftHyphen),
+            '\u{00A0}' => Some(BreakHint::NoBreakSpace),
+            _ => None,
+        }
+    }
+}
+
 /// Inline-level AST nodes
 ///
 /// Inline nodes represent text content and formatting within paragraphs.
@@ -133,6 +944,7 @@ pub enum Block {
 /// ]);
 /// ```
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Inline {
     /// Plain text content
     Text(String),
@@ -148,6 +960,268 @@ pub enum Inline {
     /// Contains the formatted inline content.
     /// Can be nested with other formatting.
     Italic(Vec<Inline>),
+
+    /// Monospace formatting (`` ` ``)
+    ///
+    /// Contains the formatted inline content: unlike a listing block, inline
+    /// code keeps bold and italic marks active.
+    Monospace(Vec<Inline>),
+
+    /// Highlighted text (`#text#`), rendered as marked text
+    Highlight(Vec<Inline>),
+
+    /// Text with roles (`[line-through]#text#` or `[.small]#text#`)
+    ///
+    /// A `#` pair preceded by an attribute list carries roles instead of
+    /// highlighting. Renderers give the well-known decoration roles
+    /// `line-through`, `underline` and `small` their own markup.
+    Span {
+        /// Roles from the attribute list, in order
+        roles: Vec<String>,
+        /// The formatted inline content
+        content: Vec<Inline>,
+    },
+
+    /// Superscript (`^text^`)
+    Superscript(Vec<Inline>),
+
+    /// Subscript (`~text~`)
+    Subscript(Vec<Inline>),
+
+    /// A link (`https://example.com[text]`) or URL autolink
+    Link {
+        /// The URL
+        target: String,
+        /// Link text; empty for autolinks and `[]`, which show the URL
+        text: Vec<Inline>,
+        /// Attributes after the text, such as `window` and `role`
+        attributes: Attributes,
+    },
+
+    /// A cross reference (`<<id>>` or `<<id,text>>`)
+    XRef {
+        /// ID of the referenced anchor
+        id: String,
+        /// Link text; empty if the reference has none, in which case
+        /// renderers use the target's reference text or title
+        text: Vec<Inline>,
+    },
+
+    /// An inline anchor (`[[id]]` or `[[id,reftext]]`)
+    Anchor(Anchor),
+
+    /// An inline image (`image:target[alt,width,height]`)
+    Image {
+        /// Image path or URL, as written
+        target: String,
+        /// Macro attributes, with the positional ones named `alt`, `width`
+        /// and `height`
+        attributes: Attributes,
+    },
+
+    /// An inline icon (`icon:name[size]`)
+    ///
+    /// Renderers show the icon from `iconsdir` (see
+    /// [`crate::targets::icon_target`]) when the document sets `icons`.
+    Icon {
+        /// Icon name, such as `heart`
+        name: String,
+        /// Macro attributes, with the first positional one named `size`
+        attributes: Attributes,
+    },
+
+    /// A footnote (`footnote:[text]`, `footnote:id[text]`)
+    ///
+    /// A named footnote with empty text (`footnote:id[]`) refers back to the
+    /// earlier footnote with that ID. See [`crate::footnotes`] for numbering.
+    Footnote {
+        /// ID for later references, if named
+        id: Option<String>,
+        /// The footnote text, empty for a reference
+        text: Vec<Inline>,
+    },
+
+    /// A soft hyphen or non-breaking space, produced by
+    /// [`crate::hints::mark_break_hints`]
+    BreakHint(BreakHint),
+}
+
+impl Block {
+    /// Returns the block's anchor, if it has one
+    ///
+    /// Sections keep their anchor in [`Block::Section::anchor`]; blocks with
+    /// an attribute list keep it in the `id` and `reftext` attributes (see
+    /// [`Attributes::id`]). Other blocks take it from their
+    /// [`BlockMetadata`].
+    pub fn anchor(&self) -> Option<Anchor> {
+        match self {
+            Block::Section { anchor, .. } => anchor.clone(),
+            _ => match self.attributes() {
+                Some(attributes) => Some(Anchor {
+                    id: attributes.id()?.to_string(),
+                    reftext: attributes.get("reftext").map(str::to_string),
+                }),
+                None => {
+                    let metadata = self.metadata();
+                    Some(Anchor {
+                        id: metadata.id.clone()?,
+                        reftext: metadata.named.get("reftext").cloned(),
+                    })
+                }
+            },
+        }
+    }
+
+    /// Returns the block's roles
+    ///
+    /// Like [`Block::anchor`], they come from the block attributes if the
+    /// block has them (see [`Attributes::roles`]), and from its
+    /// [`BlockMetadata`] otherwise.
+    pub fn roles(&self) -> Vec<&str> {
+        match self.attributes() {
+            Some(attributes) => attributes.roles(),
+            None => self.metadata().roles.iter().map(String::as_str).collect(),
+        }
+    }
+
+    /// Returns the plain text of a section title, without its formatting
+    ///
+    /// For `== Using *doctora*` this is `Using doctora`. Other blocks have no
+    /// section title and return `None`; their `.Title` line is in
+    /// [`BlockMetadata::title`].
+    pub fn title_text(&self) -> Option<String> {
+        match self {
+            Block::Section { title, .. } => Some(plain_text(title)),
+            _ => None,
+        }
+    }
+
+    /// Returns the block's attribute list line, split up
+    pub fn metadata(&self) -> &BlockMetadata {
+        match self {
+            Block::Section { metadata, .. }
+            | Block::Paragraph { metadata, .. }
+            | Block::UnorderedList { metadata, .. }
+            | Block::OrderedList { metadata, .. }
+            | Block::DescriptionList { metadata, .. }
+            | Block::Listing { metadata, .. }
+            | Block::Quote { metadata, .. }
+            | Block::Verse { metadata, .. }
+            | Block::Admonition { metadata, .. }
+            | Block::Example { metadata, .. }
+            | Block::Sidebar { metadata, .. }
+            | Block::Open { metadata, .. }
+            | Block::Table { metadata, .. }
+            | Block::Image { metadata, .. }
+            | Block::Passthrough { metadata, .. }
+            | Block::Comment { metadata, .. }
+            | Block::AttributeEntry { metadata, .. }
+            | Block::Unknown { metadata, .. } => metadata,
+        }
+    }
+
+    /// Returns the block's attribute list line for modification
+    pub fn metadata_mut(&mut self) -> &mut BlockMetadata {
+        match self {
+            Block::Section { metadata, .. }
+            | Block::Paragraph { metadata, .. }
+            | Block::UnorderedList { metadata, .. }
+            | Block::OrderedList { metadata, .. }
+            | Block::DescriptionList { metadata, .. }
+            | Block::Listing { metadata, .. }
+            | Block::Quote { metadata, .. }
+            | Block::Verse { metadata, .. }
+            | Block::Admonition { metadata, .. }
+            | Block::Example { metadata, .. }
+            | Block::Sidebar { metadata, .. }
+            | Block::Open { metadata, .. }
+            | Block::Table { metadata, .. }
+            | Block::Image { metadata, .. }
+            | Block::Passthrough { metadata, .. }
+            | Block::Comment { metadata, .. }
+            | Block::AttributeEntry { metadata, .. }
+            | Block::Unknown { metadata, .. } => metadata,
+        }
+    }
+
+    /// Returns the block attributes, for blocks that carry an attribute list
+    pub fn attributes(&self) -> Option<&Attributes> {
+        match self {
+            Block::Listing { attributes, .. }
+            | Block::Table { attributes, .. }
+            | Block::Open { attributes, .. }
+            | Block::Image { attributes, .. } => Some(attributes),
+            _ => None,
+        }
+    }
+
+    /// Returns the block's type name, as in the JSON schema (`paragraph`,
+    /// `unordered_list`, `listing`, ...)
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Block::Section { .. } => "section",
+            Block::Paragraph { .. } => "paragraph",
+            Block::UnorderedList { .. } => "unordered_list",
+            Block::OrderedList { .. } => "ordered_list",
+            Block::DescriptionList { .. } => "description_list",
+            Block::Listing { .. } => "listing",
+            Block::Quote { .. } => "quote",
+            Block::Verse { .. } => "verse",
+            Block::Admonition { .. } => "admonition",
+            Block::Example { .. } => "example",
+            Block::Sidebar { .. } => "sidebar",
+            Block::Open { .. } => "open",
+            Block::Table { .. } => "table",
+            Block::Image { .. } => "image",
+            Block::Passthrough { .. } => "passthrough",
+            Block::Comment { .. } => "comment",
+            Block::AttributeEntry { .. } => "attribute_entry",
+            Block::Unknown { .. } => "unknown",
+        }
+    }
+
+    /// Returns the substitutions applied to this block's content by default
+    ///
+    /// A block's `subs` attribute is parsed relative to these defaults (see
+    /// [`Substitutions::parse`]).
+    pub fn default_substitutions(&self) -> Substitutions {
+        match self {
+            Block::Section { .. }
+            | Block::Paragraph { .. }
+            | Block::UnorderedList { .. }
+            | Block::OrderedList { .. }
+            | Block::DescriptionList { .. }
+            | Block::Quote { .. }
+            | Block::Verse { .. }
+            | Block::Admonition { .. }
+            | Block::Example { .. }
+            | Block::Sidebar { .. }
+            | Block::Open { .. }
+            | Block::Table { .. }
+            | Block::Image { .. } => Substitutions::NORMAL,
+            Block::Listing { .. } => Substitutions::VERBATIM,
+            Block::Passthrough { .. }
+            | Block::Comment { .. }
+            | Block::AttributeEntry { .. }
+            | Block::Unknown { .. } => Substitutions::empty(),
+        }
+    }
+
+    /// Returns the raw content of a passthrough block written for `backend`
+    ///
+    /// Backends call this to decide whether to emit a passthrough block:
+    /// `None` for passthroughs meant for another backend, and for any other
+    /// kind of block.
+    pub fn passthrough_for(&self, backend: &str) -> Option<&str> {
+        match self {
+            Block::Passthrough {
+                content,
+                backend: target,
+                ..
+            } if target.eq_ignore_ascii_case(backend) => Some(content),
+            _ => None,
+        }
+    }
 }
 
 impl Inline {
@@ -166,6 +1240,81 @@ impl Inline {
         matches!(self, Inline::Italic(_))
     }
 
+    /// Checks if this inline node is monospace
+    pub fn is_monospace(&self) -> bool {
+        matches!(self, Inline::Monospace(_))
+    }
+
+    /// Checks if this inline node is highlighted
+    pub fn is_highlight(&self) -> bool {
+        matches!(self, Inline::Highlight(_))
+    }
+
+    /// Checks if this inline node is a span with roles
+    pub fn is_span(&self) -> bool {
+        matches!(self, Inline::Span { .. })
+    }
+
+    /// Checks if this inline node is superscript
+    pub fn is_superscript(&self) -> bool {
+        matches!(self, Inline::Superscript(_))
+    }
+
+    /// Checks if this inline node is subscript
+    pub fn is_subscript(&self) -> bool {
+        matches!(self, Inline::Subscript(_))
+    }
+
+    /// Checks if this inline node is a link
+    pub fn is_link(&self) -> bool {
+        matches!(self, Inline::Link { .. })
+    }
+
+    /// Checks if this inline node is a cross reference
+    pub fn is_xref(&self) -> bool {
+        matches!(self, Inline::XRef { .. })
+    }
+
+    /// Checks if this inline node is an image
+    pub fn is_image(&self) -> bool {
+        matches!(self, Inline::Image { .. })
+    }
+
+    /// Checks if this inline node is a footnote
+    pub fn is_footnote(&self) -> bool {
+        matches!(self, Inline::Footnote { .. })
+    }
+
+    /// Checks if this inline node is a line-breaking hint
+    pub fn is_break_hint(&self) -> bool {
+        matches!(self, Inline::BreakHint(_))
+    }
+
+    /// Number of characters in the plain text of this node
+    ///
+    /// See [`text_len`].
+    pub fn text_len(&self) -> usize {
+        match self {
+            Inline::Text(text) => text.chars().count(),
+            Inline::Bold(nested)
+            | Inline::Italic(nested)
+            | Inline::Monospace(nested)
+            | Inline::Highlight(nested)
+            | Inline::Superscript(nested)
+            | Inline::Subscript(nested)
+            | Inline::Span {
+                content: nested, ..
+            } => text_len(nested),
+            Inline::Link { target, text, .. } if text.is_empty() => target.chars().count(),
+            Inline::Link { text, .. } | Inline::XRef { text, .. } => text_len(text),
+            Inline::Anchor(_)
+            | Inline::Image { .. }
+            | Inline::Icon { .. }
+            | Inline::Footnote { .. } => 0,
+            Inline::BreakHint(_) => 1,
+        }
+    }
+
     /// Extracts text content if this is a Text node
     pub fn as_text(&self) -> Option<&str> {
         if let Inline::Text(s) = self {
@@ -176,6 +1325,76 @@ impl Inline {
     }
 }
 
+/// Number of characters in the plain text of inline content
+///
+/// This is the length of [`plain_text`] without building the string, so
+/// formatting marks are not counted.
+///
+/// # Examples
+///
+/// ```
+/// use doctora::ast::{Inline, text_len};
+///
+/// let content = vec![
+///     Inline::Text("Café ".to_string()),
+///     Inline::Italic(vec![Inline::Text("au lait".to_string())]),
+/// ];
+/// assert_eq!(text_len(&content), 12);
+/// ```
+pub fn text_len(content: &[Inline]) -> usize {
+    content.iter().map(Inline::text_len).sum()
+}
+
+/// Concatenates the plain text of inline content, dropping all formatting
+///
+/// Links contribute their text, or their URL if they have no text. Cross
+/// references contribute their own text only, since resolving their target
+/// needs the whole document. Break hints contribute their character.
+/// Anchors, images and footnotes contribute nothing.
+///
+/// # Examples
+///
+/// ```
+/// use doctora::ast::{Inline, plain_text};
+///
+/// let content = vec![
+///     Inline::Text("Some ".to_string()),
+///     Inline::Bold(vec![Inline::Text("bold".to_string())]),
+///     Inline::Text(" text".to_string()),
+/// ];
+/// assert_eq!(plain_text(&content), "Some bold text");
+/// ```
+pub fn plain_text(content: &[Inline]) -> String {
+    let mut out = String::new();
+    push_plain_text(content, &mut out);
+    out
+}
+
+/// Appends the plain text of `content` to `out`
+fn push_plain_text(content: &[Inline], out: &mut String) {
+    for inline in content {
+        match inline {
+            Inline::Text(text) => out.push_str(text),
+            Inline::Bold(nested)
+            | Inline::Italic(nested)
+            | Inline::Monospace(nested)
+            | Inline::Highlight(nested)
+            | Inline::Superscript(nested)
+            | Inline::Subscript(nested)
+            | Inline::Span {
+                content: nested, ..
+            } => push_plain_text(nested, out),
+            Inline::Link { target, text, .. } if text.is_empty() => out.push_str(target),
+            Inline::Link { text, .. } | Inline::XRef { text, .. } => push_plain_text(text, out),
+            Inline::Anchor(_)
+            | Inline::Image { .. }
+            | Inline::Icon { .. }
+            | Inline::Footnote { .. } => {}
+            Inline::BreakHint(hint) => out.push(hint.as_char()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -187,10 +1406,29 @@ mod tests {
 
         let doc = Document::with_blocks(vec![Block::Paragraph {
             content: vec![Inline::Text("Test".to_string())],
+            metadata: BlockMetadata::default(),
         }]);
         assert_eq!(doc.blocks.len(), 1);
     }
 
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_round_trip() {
+        let doc = crate::parse_document(
+            "= Guide\n:version: 2\n\n[#intro.lead]\n== Intro\n\n\
+             * _one_ <<intro>>\n\n|===\n|a |b\n|===\n\nSee https://example.com[docs].\n",
+        )
+        .unwrap();
+        let json = serde_json::to_string(&doc).unwrap();
+        assert_eq!(serde_json::from_str::<Document>(&json).unwrap(), doc);
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["attributes"]["version"], "2");
+        assert_eq!(value["header"]["title"], "Guide");
+        assert_eq!(value["blocks"][0]["Section"]["title"][0]["Text"], "Intro");
+        assert!(serde_json::from_str::<Document>("{}").is_err());
+    }
+
     #[test]
     fn test_document_default() {
         let doc = Document::default();
@@ -201,9 +1439,10 @@ mod tests {
     fn test_block_paragraph() {
         let para = Block::Paragraph {
             content: vec![Inline::Text("Test".to_string())],
+            metadata: BlockMetadata::default(),
         };
 
-        if let Block::Paragraph { content } = para {
+        if let Block::Paragraph { content, .. } = para {
             assert_eq!(content.len(), 1);
         } else {
             panic!("Expected Paragraph");
@@ -214,13 +1453,22 @@ mod tests {
     fn test_block_section() {
         let section = Block::Section {
             level: 1,
-            title: "Title".to_string(),
+            title: vec![Inline::Text("Title".to_string())],
             content: vec![],
+            anchor: None,
+            metadata: BlockMetadata::default(),
         };
+        assert_eq!(section.title_text().as_deref(), Some("Title"));
 
-        if let Block::Section { level, title, content } = section {
+        if let Block::Section {
+            level,
+            title,
+            content,
+            ..
+        } = section
+        {
             assert_eq!(level, 1);
-            assert_eq!(title, "Title");
+            assert_eq!(title, vec![Inline::Text("Title".to_string())]);
             assert_eq!(content.len(), 0);
         } else {
             panic!("Expected Section");
@@ -231,17 +1479,22 @@ mod tests {
     fn test_nested_section() {
         let section = Block::Section {
             level: 1,
-            title: "Main".to_string(),
+            title: vec![Inline::Text("Main".to_string())],
             content: vec![
                 Block::Paragraph {
                     content: vec![Inline::Text("Para".to_string())],
+                    metadata: BlockMetadata::default(),
                 },
                 Block::Section {
                     level: 2,
-                    title: "Sub".to_string(),
+                    title: vec![Inline::Text("Sub".to_string())],
                     content: vec![],
+                    anchor: None,
+                    metadata: BlockMetadata::default(),
                 },
             ],
+            anchor: None,
+            metadata: BlockMetadata::default(),
         };
 
         if let Block::Section { level, content, .. } = section {
@@ -252,6 +1505,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_block_default_substitutions() {
+        let para = Block::Paragraph {
+            content: vec![],
+            metadata: BlockMetadata::default(),
+        };
+        assert_eq!(para.default_substitutions(), Substitutions::NORMAL);
+
+        let listing = Block::Listing {
+            language: None,
+            content: String::new(),
+            attributes: Attributes::new(),
+            metadata: BlockMetadata::default(),
+        };
+        assert_eq!(listing.default_substitutions(), Substitutions::VERBATIM);
+    }
+
     #[test]
     fn test_inline_text() {
         let text = Inline::Text("Hello".to_string());
@@ -294,6 +1564,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_plain_text() {
+        let content = vec![
+            Inline::Text("a ".to_string()),
+            Inline::Bold(vec![
+                Inline::Text("b ".to_string()),
+                Inline::Italic(vec![Inline::Text("c".to_string())]),
+            ]),
+        ];
+        assert_eq!(plain_text(&content), "a b c");
+        assert_eq!(plain_text(&[]), "");
+        assert_eq!(text_len(&content), 5);
+        assert_eq!(content[1].text_len(), 3);
+    }
+
     #[test]
     fn test_complex_paragraph() {
         let para = Block::Paragraph {
@@ -304,12 +1589,51 @@ mod tests {
                 Inline::Italic(vec![Inline::Text("italic".to_string())]),
                 Inline::Text(".".to_string()),
             ],
+            metadata: BlockMetadata::default(),
         };
 
-        if let Block::Paragraph { content } = para {
+        if let Block::Paragraph { content, .. } = para {
             assert_eq!(content.len(), 5);
         } else {
             panic!("Expected Paragraph");
         }
     }
+
+    #[test]
+    fn test_alignment_and_indent_roles() {
+        let metadata = |roles: &[&str]| BlockMetadata {
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+            ..BlockMetadata::default()
+        };
+        assert_eq!(metadata(&[]).text_align(), None);
+        assert_eq!(metadata(&[]).indent(), 0);
+        assert_eq!(
+            metadata(&["lead", "text-center"]).text_align(),
+            Some(TextAlign::Center)
+        );
+        assert_eq!(
+            metadata(&["text-left", "text-right"]).text_align(),
+            Some(TextAlign::Right)
+        );
+        assert_eq!(metadata(&["indent-1", "indent-3", "indent-x"]).indent(), 3);
+    }
+
+    #[test]
+    fn test_line_numbers_and_highlight() {
+        let metadata =
+            |line: &str| BlockMetadata::from(&crate::attributes::parse_attribute_list(line));
+        assert!(metadata("source%linenums,ruby").line_numbers());
+        assert!(metadata("source,ruby,linenums").line_numbers());
+        assert!(!metadata("source,ruby").line_numbers());
+
+        assert_eq!(
+            metadata("source,highlight=\"7;1,3..5,2-3\"").highlight_lines(),
+            vec![1, 2, 3, 4, 5, 7]
+        );
+        assert_eq!(
+            metadata("source,highlight=x,0").highlight_lines(),
+            Vec::<usize>::new()
+        );
+        assert!(metadata("source").highlight_lines().is_empty());
+    }
 }
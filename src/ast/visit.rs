@@ -0,0 +1,382 @@
+//! Recursive traversal of the AST
+//!
+//! [`Visitor`] walks a document by shared reference, [`VisitorMut`] by
+//! mutable reference. Each has a hook per node kind whose default
+//! implementation calls the matching `walk_*` function, which visits the
+//! node's children. An implementation overrides the hooks it cares about;
+//! to keep descending, an override calls the `walk_*` function itself, and
+//! to skip a subtree it does not.
+//!
+//! Children are visited in source order: a block's title (from its
+//! attribute list) comes before its content, a section's title before its
+//! blocks, a list item's text before its attached blocks, and a
+//! description list term before its description.
+//!
+//! # Examples
+//!
+//! Counting the bold runs of a document, including those in titles and
+//! nested blocks:
+//!
+//! ```
+//! use doctora::ast::visit::{Visitor, walk_inline};
+//! use doctora::ast::Inline;
+//!
+//! #[derive(Default)]
+//! struct BoldCount(usize);
+//!
+//! impl Visitor for BoldCount {
+//!     fn visit_inline(&mut self, inline: &Inline) {
+//!         if inline.is_bold() {
+//!             self.0 += 1;
+//!         }
+//!         walk_inline(self, inline);
+//!     }
+//! }
+//!
+//! let doc = doctora::parse_document("== *A* title\n\n* item with *bold*\n").unwrap();
+//! let mut count = BoldCount::default();
+//! count.visit_document(&doc);
+//! assert_eq!(count.0, 2);
+//! ```
+//!
+//! Upper-casing all text in place:
+//!
+//! ```
+//! use doctora::ast::visit::{VisitorMut, walk_inline_mut};
+//! use doctora::ast::{Inline, plain_text};
+//! use doctora::ast::Block;
+//!
+//! struct Shout;
+//!
+//! impl VisitorMut for Shout {
+//!     fn visit_inline_mut(&mut self, inline: &mut Inline) {
+//!         if let Inline::Text(text) = inline {
+//!             *text = text.to_uppercase();
+//!         }
+//!         walk_inline_mut(self, inline);
+//!     }
+//! }
+//!
+//! let mut doc = doctora::parse_document("Say _hello_.").unwrap();
+//! Shout.visit_document_mut(&mut doc);
+//! let Block::Paragraph { content, .. } = &doc.blocks[0] else { unreachable!() };
+//! assert_eq!(plain_text(content), "SAY HELLO.");
+//! ```
+
+use super::{Block, Document, Inline};
+
+/// A read-only walk over a document
+///
+/// See the [module documentation](self).
+pub trait Visitor {
+    /// Visit a document; by default, its blocks
+    fn visit_document(&mut self, doc: &Document) {
+        walk_document(self, doc);
+    }
+
+    /// Visit a block; by default, its title and children
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block);
+    }
+
+    /// Visit an inline node; by default, its nested content
+    fn visit_inline(&mut self, inline: &Inline) {
+        walk_inline(self, inline);
+    }
+}
+
+/// A walk over a document that can change it in place
+///
+/// See the [module documentation](self).
+pub trait VisitorMut {
+    /// Visit a document; by default, its blocks
+    fn visit_document_mut(&mut self, doc: &mut Document) {
+        walk_document_mut(self, doc);
+    }
+
+    /// Visit a block; by default, its title and children
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        walk_block_mut(self, block);
+    }
+
+    /// Visit an inline node; by default, its nested content
+    fn visit_inline_mut(&mut self, inline: &mut Inline) {
+        walk_inline_mut(self, inline);
+    }
+}
+
+/// Visit the blocks of `doc`
+pub fn walk_document<V: Visitor + ?Sized>(visitor: &mut V, doc: &Document) {
+    for block in &doc.blocks {
+        visitor.visit_block(block);
+    }
+}
+
+/// Visit the title and the child blocks and inline nodes of `block`
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &Block) {
+    let inlines = |visitor: &mut V, content: &[Inline]| {
+        for inline in content {
+            visitor.visit_inline(inline);
+        }
+    };
+    let blocks = |visitor: &mut V, content: &[Block]| {
+        for block in content {
+            visitor.visit_block(block);
+        }
+    };
+    if let Some(title) = &block.metadata().title {
+        inlines(visitor, title);
+    }
+    match block {
+        Block::Section { title, content, .. } => {
+            inlines(visitor, title);
+            blocks(visitor, content);
+        }
+        Block::Paragraph { content, .. } | Block::Verse { content, .. } => {
+            inlines(visitor, content)
+        }
+        Block::UnorderedList { items, .. } | Block::OrderedList { items, .. } => {
+            for item in items {
+                inlines(visitor, &item.content);
+                blocks(visitor, &item.blocks);
+            }
+        }
+        Block::DescriptionList { items, .. } => {
+            for item in items {
+                inlines(visitor, &item.term);
+                inlines(visitor, &item.description);
+            }
+        }
+        Block::Table { header, rows, .. } => {
+            for row in header.iter().chain(rows) {
+                for cell in &row.cells {
+                    inlines(visitor, &cell.content);
+                }
+            }
+        }
+        Block::Quote { content, .. }
+        | Block::Admonition { content, .. }
+        | Block::Example { content, .. }
+        | Block::Sidebar { content, .. }
+        | Block::Open { content, .. } => blocks(visitor, content),
+        Block::Listing { .. }
+        | Block::Image { .. }
+        | Block::Passthrough { .. }
+        | Block::Comment { .. }
+        | Block::AttributeEntry { .. }
+        | Block::Unknown { .. } => {}
+    }
+}
+
+/// Visit the nested content of `inline`: the text of formatting, links,
+/// cross references, and footnotes
+pub fn walk_inline<V: Visitor + ?Sized>(visitor: &mut V, inline: &Inline) {
+    match inline {
+        Inline::Bold(nested)
+        | Inline::Italic(nested)
+        | Inline::Monospace(nested)
+        | Inline::Highlight(nested)
+        | Inline::Span {
+            content: nested, ..
+        }
+        | Inline::Superscript(nested)
+        | Inline::Subscript(nested)
+        | Inline::Link { text: nested, .. }
+        | Inline::XRef { text: nested, .. }
+        | Inline::Footnote { text: nested, .. } => {
+            for inline in nested {
+                visitor.visit_inline(inline);
+            }
+        }
+        Inline::Text(_)
+        | Inline::Anchor(_)
+        | Inline::Image { .. }
+        | Inline::Icon { .. }
+        | Inline::BreakHint(_) => {}
+    }
+}
+
+/// Visit the blocks of `doc`, mutably
+pub fn walk_document_mut<V: VisitorMut + ?Sized>(visitor: &mut V, doc: &mut Document) {
+    for block in &mut doc.blocks {
+        visitor.visit_block_mut(block);
+    }
+}
+
+/// Visit the title and the child blocks and inline nodes of `block`,
+/// mutably
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(visitor: &mut V, block: &mut Block) {
+    let inlines = |visitor: &mut V, content: &mut [Inline]| {
+        for inline in content {
+            visitor.visit_inline_mut(inline);
+        }
+    };
+    let blocks = |visitor: &mut V, content: &mut [Block]| {
+        for block in content {
+            visitor.visit_block_mut(block);
+        }
+    };
+    if let Some(title) = &mut block.metadata_mut().title {
+        inlines(visitor, title);
+    }
+    match block {
+        Block::Section { title, content, .. } => {
+            inlines(visitor, title);
+            blocks(visitor, content);
+        }
+        Block::Paragraph { content, .. } | Block::Verse { content, .. } => {
+            inlines(visitor, content)
+        }
+        Block::UnorderedList { items, .. } | Block::OrderedList { items, .. } => {
+            for item in items {
+                inlines(visitor, &mut item.content);
+                blocks(visitor, &mut item.blocks);
+            }
+        }
+        Block::DescriptionList { items, .. } => {
+            for item in items {
+                inlines(visitor, &mut item.term);
+                inlines(visitor, &mut item.description);
+            }
+        }
+        Block::Table { header, rows, .. } => {
+            for row in header.iter_mut().chain(rows) {
+                for cell in &mut row.cells {
+                    inlines(visitor, &mut cell.content);
+                }
+            }
+        }
+        Block::Quote { content, .. }
+        | Block::Admonition { content, .. }
+        | Block::Example { content, .. }
+        | Block::Sidebar { content, .. }
+        | Block::Open { content, .. } => blocks(visitor, content),
+        Block::Listing { .. }
+        | Block::Image { .. }
+        | Block::Passthrough { .. }
+        | Block::Comment { .. }
+        | Block::AttributeEntry { .. }
+        | Block::Unknown { .. } => {}
+    }
+}
+
+/// Visit the nested content of `inline`, mutably
+pub fn walk_inline_mut<V: VisitorMut + ?Sized>(visitor: &mut V, inline: &mut Inline) {
+    match inline {
+        Inline::Bold(nested)
+        | Inline::Italic(nested)
+        | Inline::Monospace(nested)
+        | Inline::Highlight(nested)
+        | Inline::Span {
+            content: nested, ..
+        }
+        | Inline::Superscript(nested)
+        | Inline::Subscript(nested)
+        | Inline::Link { text: nested, .. }
+        | Inline::XRef { text: nested, .. }
+        | Inline::Footnote { text: nested, .. } => {
+            for inline in nested {
+                visitor.visit_inline_mut(inline);
+            }
+        }
+        Inline::Text(_)
+        | Inline::Anchor(_)
+        | Inline::Image { .. }
+        | Inline::Icon { .. }
+        | Inline::BreakHint(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    /// Records the order in which nodes are visited
+    #[derive(Default)]
+    struct Trace(Vec<String>);
+
+    impl Visitor for Trace {
+        fn visit_block(&mut self, block: &Block) {
+            let name = format!("{block:?}");
+            self.0
+                .push(name[..name.find(' ').unwrap_or(name.len())].to_string());
+            walk_block(self, block);
+        }
+
+        fn visit_inline(&mut self, inline: &Inline) {
+            if let Some(text) = inline.as_text() {
+                self.0.push(text.to_string());
+            }
+            walk_inline(self, inline);
+        }
+    }
+
+    #[test]
+    fn test_visit_order() {
+        let doc = parse_document(
+            "== Setup\n\n* one *two*\n+\nmore\n\n----\nx\n----\n\nterm:: desc\n\n\
+             |===\n|cell\n|===\n\n.Steps\n====\ninside footnote:[note]\n====\n",
+        )
+        .unwrap();
+        let mut trace = Trace::default();
+        trace.visit_document(&doc);
+        assert_eq!(
+            trace.0,
+            [
+                "Section",
+                "Setup",
+                "UnorderedList",
+                "one ",
+                "two",
+                "Paragraph",
+                "more",
+                "Listing",
+                "DescriptionList",
+                "term",
+                "desc",
+                "Table",
+                "cell",
+                "Example",
+                "Steps",
+                "Paragraph",
+                "inside ",
+                "note",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_visit_mut_skips_subtrees() {
+        /// Drops the text of footnotes, leaving the rest alone
+        struct DropFootnotes;
+
+        impl VisitorMut for DropFootnotes {
+            fn visit_inline_mut(&mut self, inline: &mut Inline) {
+                match inline {
+                    Inline::Footnote { text, .. } => text.clear(),
+                    Inline::Text(text) => text.push('!'),
+                    _ => walk_inline_mut(self, inline),
+                }
+            }
+        }
+
+        let mut doc = parse_document("A footnote:[b] _c_").unwrap();
+        DropFootnotes.visit_document_mut(&mut doc);
+        let Block::Paragraph { content, .. } = &doc.blocks[0] else {
+            panic!("expected a paragraph");
+        };
+        assert_eq!(
+            content,
+            &[
+                Inline::Text("A !".to_string()),
+                Inline::Footnote {
+                    id: None,
+                    text: vec![]
+                },
+                Inline::Text(" !".to_string()),
+                Inline::Italic(vec![Inline::Text("c!".to_string())]),
+            ]
+        );
+    }
+}
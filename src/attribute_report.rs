@@ -0,0 +1,516 @@
+//! Report of attribute definitions and references across a document set
+//!
+//! Large documentation projects accumulate attributes: versions, product
+//! names, URLs, feature flags. Over time some are defined in several places
+//! with different values, some are never referenced, and some are referenced
+//! but defined nowhere. [`attribute_report`] scans a set of documents and
+//! lists, for every attribute name that appears in them:
+//!
+//! - each attribute entry (`:name: value`, `:name!:`), with its location and
+//!   whether it defines, overrides, unsets, or is ignored ([`EntryEffect`]);
+//! - each reference (`{name}`, and the names in `ifdef::` and `ifndef::`
+//!   directives), with its location;
+//! - the value it resolves to at the end of each document that mentions it.
+//!
+//! Each document is read on its own, the way
+//! [`parse_batch`](crate::batch::parse_batch) parses them, starting from the
+//! attributes passed in. Like attributes set through
+//! [`ParseOptions::attribute`](crate::options::ParseOptions::attribute),
+//! those take precedence over the documents' entries, so their entries are
+//! reported as ignored.
+//!
+//! The scan works on the source text. Entries and references inside
+//! verbatim blocks (listings, literals, passthroughs, comments) and comment
+//! lines are not counted, and includes are not followed. Built-in attributes
+//! (the character replacements of [`intrinsic`], and the attributes derived
+//! from the header or the clock) are never reported as undefined.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::attribute_report::{EntryEffect, attribute_report};
+//! use doctora::attributes::Attributes;
+//!
+//! let files = [
+//!     ("index.adoc", ":product: Doctor A\n:version: 1.0\n\nWelcome to {product}.\n"),
+//!     ("install.adoc", ":product: Doctor A\n:product: Doctor B\n\nInstall {product} {release}.\n"),
+//! ];
+//! let report = attribute_report(files, &Attributes::new());
+//!
+//! let product = report.get("product").unwrap();
+//! assert_eq!(product.entries[2].effect, EntryEffect::Override);
+//! assert_eq!(product.references.len(), 2);
+//! assert!(product.is_inconsistent());
+//!
+//! assert!(report.get("version").unwrap().is_unused());
+//! assert!(report.get("release").unwrap().is_undefined());
+//! println!("{}", report);
+//! ```
+
+use crate::attributes::{Attributes, interpolate_with, is_name_char};
+use crate::include::is_verbatim_delimiter;
+use crate::parser_winnow::attribute_entry;
+use crate::resolve::intrinsic;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Attributes defined from the document header or the clock rather than by
+/// entries
+const DERIVED: &[&str] = &[
+    "doctitle",
+    "author",
+    "authors",
+    "email",
+    "firstname",
+    "middlename",
+    "lastname",
+    "authorinitials",
+    "revnumber",
+    "revdate",
+    "revremark",
+    "localdate",
+    "localtime",
+    "localdatetime",
+    "localyear",
+    "docdate",
+    "doctime",
+    "docdatetime",
+    "docyear",
+];
+
+/// What an attribute entry does to its attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EntryEffect {
+    /// Sets an attribute that had no value in the document
+    Define,
+    /// Replaces a value set by an earlier entry in the document
+    Override,
+    /// Unsets the attribute (`:name!:`)
+    Unset,
+    /// Has no effect, since the attribute was passed in
+    Ignore,
+}
+
+impl EntryEffect {
+    /// Short identifier used in reports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EntryEffect::Define => "define",
+            EntryEffect::Override => "override",
+            EntryEffect::Unset => "unset",
+            EntryEffect::Ignore => "ignore",
+        }
+    }
+}
+
+/// A position in a document of the set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// Name of the document, as passed to [`attribute_report`]
+    pub file: String,
+    /// 1-based line
+    pub line: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// An attribute entry for one attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryUse {
+    /// Where the entry is
+    pub location: Location,
+    /// Raw value, before interpolation; `None` for an unset entry
+    pub value: Option<String>,
+    /// What the entry does
+    pub effect: EntryEffect,
+}
+
+/// The value of an attribute at the end of one document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinalValue {
+    /// Name of the document
+    pub file: String,
+    /// Resolved value, `None` if the attribute is unset or never defined
+    ///
+    /// Attributes derived from the header or the clock are `None` unless an
+    /// entry or the caller sets them.
+    pub value: Option<String>,
+}
+
+/// Everything the document set does with one attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeUsage {
+    /// Attribute name
+    pub name: String,
+    /// Value passed in to [`attribute_report`], if any
+    pub passed: Option<String>,
+    /// True for an attribute the processor defines itself
+    pub built_in: bool,
+    /// Attribute entries, in input order
+    pub entries: Vec<EntryUse>,
+    /// References, in input order
+    pub references: Vec<Location>,
+    /// Final values, one per document with an entry or reference, in input
+    /// order
+    pub values: Vec<FinalValue>,
+}
+
+impl AttributeUsage {
+    /// Returns true if the attribute is set by an entry but never referenced
+    ///
+    /// Attributes that configure the processor (`toc`, `idprefix`, ...) are
+    /// used without references, so check before pruning them.
+    pub fn is_unused(&self) -> bool {
+        self.references.is_empty()
+            && self
+                .entries
+                .iter()
+                .any(|entry| matches!(entry.effect, EntryEffect::Define | EntryEffect::Override))
+    }
+
+    /// Returns true if the attribute is referenced but never defined, passed
+    /// in, or built in
+    pub fn is_undefined(&self) -> bool {
+        !self.references.is_empty()
+            && !self.built_in
+            && self.passed.is_none()
+            && self.entries.iter().all(|entry| entry.value.is_none())
+    }
+
+    /// Returns true if documents end with different values for the
+    /// attribute
+    pub fn is_inconsistent(&self) -> bool {
+        self.values
+            .windows(2)
+            .any(|pair| pair[0].value != pair[1].value)
+    }
+}
+
+/// Attribute usage across a document set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeReport {
+    /// Number of documents scanned
+    pub documents: usize,
+    /// Attributes in name order
+    pub attributes: Vec<AttributeUsage>,
+}
+
+impl AttributeReport {
+    /// The usage of the attribute `name`, if the documents mention it
+    pub fn get(&self, name: &str) -> Option<&AttributeUsage> {
+        self.attributes
+            .binary_search_by(|usage| usage.name.as_str().cmp(name))
+            .ok()
+            .map(|index| &self.attributes[index])
+    }
+}
+
+impl fmt::Display for AttributeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Attribute report: {} documents ({} attributes)",
+            self.documents,
+            self.attributes.len()
+        )?;
+        for usage in &self.attributes {
+            let mut flags = Vec::new();
+            if usage.is_unused() {
+                flags.push("unused");
+            }
+            if usage.is_undefined() {
+                flags.push("undefined");
+            }
+            if usage.is_inconsistent() {
+                flags.push("inconsistent");
+            }
+            if usage.built_in {
+                flags.push("built-in");
+            }
+            match &usage.passed {
+                Some(value) => write!(f, "  {} (passed {value:?})", usage.name)?,
+                None => write!(f, "  {}", usage.name)?,
+            }
+            if flags.is_empty() {
+                writeln!(f)?;
+            } else {
+                writeln!(f, " [{}]", flags.join(", "))?;
+            }
+            for entry in &usage.entries {
+                match &entry.value {
+                    Some(value) => writeln!(
+                        f,
+                        "    {} {:<9} {value:?}",
+                        entry.location,
+                        entry.effect.as_str()
+                    )?,
+                    None => writeln!(f, "    {} {}", entry.location, entry.effect.as_str())?,
+                }
+            }
+            for location in &usage.references {
+                writeln!(f, "    {location} reference")?;
+            }
+            for value in &usage.values {
+                match &value.value {
+                    Some(resolved) => writeln!(f, "    {} = {resolved:?}", value.file)?,
+                    None => writeln!(f, "    {} unset", value.file)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Report the attribute usage of `files`, `(name, source)` pairs, with
+/// `attributes` passed in to every document (see the module documentation)
+pub fn attribute_report<I, N, S>(files: I, attributes: &Attributes) -> AttributeReport
+where
+    I: IntoIterator<Item = (N, S)>,
+    N: Into<String>,
+    S: AsRef<str>,
+{
+    let mut usages: BTreeMap<String, AttributeUsage> = BTreeMap::new();
+    let mut documents = 0;
+    for (name, source) in files {
+        let name = name.into();
+        documents += 1;
+        let mut mentioned = Vec::new();
+        let mut values = attributes.clone();
+        let mut set_here = BTreeSet::new();
+
+        let mut fence: Option<&str> = None;
+        for (index, line) in source.as_ref().lines().enumerate() {
+            let text = line.trim_end_matches('\r');
+            let location = || Location {
+                file: name.clone(),
+                line: index + 1,
+            };
+            if let Some(delimiter) = fence {
+                if text.trim_end() == delimiter {
+                    fence = None;
+                }
+                continue;
+            }
+            if is_verbatim_delimiter(text.trim_end()) {
+                fence = Some(text.trim_end());
+                continue;
+            }
+            if text.starts_with("//") {
+                continue;
+            }
+            for reference in references(text) {
+                usage(&mut usages, &mut mentioned, attributes, &reference)
+                    .references
+                    .push(location());
+            }
+            let Some(entry) = attribute_entry(text) else {
+                continue;
+            };
+            let effect = if attributes.contains(&entry.name) {
+                EntryEffect::Ignore
+            } else if entry.value.is_none() {
+                EntryEffect::Unset
+            } else if set_here.contains(&entry.name) {
+                EntryEffect::Override
+            } else {
+                EntryEffect::Define
+            };
+            match (&entry.value, effect) {
+                (_, EntryEffect::Ignore) => {}
+                (Some(value), _) => {
+                    // A value over the length limit is reported as undefined
+                    if values.define(entry.name.clone(), value).is_err() {
+                        values.unset(&entry.name);
+                    }
+                    set_here.insert(entry.name.clone());
+                }
+                (None, _) => {
+                    values.unset(&entry.name);
+                    set_here.remove(&entry.name);
+                }
+            }
+            usage(&mut usages, &mut mentioned, attributes, &entry.name)
+                .entries
+                .push(EntryUse {
+                    location: location(),
+                    value: entry.value,
+                    effect,
+                });
+        }
+
+        for attribute in mentioned {
+            let usage = usages
+                .get_mut(&attribute)
+                .expect("mentioned attributes have a usage");
+            usage.values.push(FinalValue {
+                file: name.clone(),
+                value: values
+                    .get(&attribute)
+                    .or_else(|| intrinsic(&attribute))
+                    .map(str::to_string),
+            });
+        }
+    }
+    AttributeReport {
+        documents,
+        attributes: usages.into_values().collect(),
+    }
+}
+
+/// The usage of `name` in `usages`, added if it is new, noting that the
+/// current document mentions it
+fn usage<'a>(
+    usages: &'a mut BTreeMap<String, AttributeUsage>,
+    mentioned: &mut Vec<String>,
+    attributes: &Attributes,
+    name: &str,
+) -> &'a mut AttributeUsage {
+    if !mentioned.iter().any(|seen| seen == name) {
+        mentioned.push(name.to_string());
+    }
+    usages
+        .entry(name.to_string())
+        .or_insert_with(|| AttributeUsage {
+            name: name.to_string(),
+            passed: attributes.get(name).map(str::to_string),
+            built_in: intrinsic(name).is_some() || DERIVED.contains(&name),
+            entries: Vec::new(),
+            references: Vec::new(),
+            values: Vec::new(),
+        })
+}
+
+/// Names of the attributes that `line` references, in order
+///
+/// Besides `{name}` references, these are the names an `ifdef::` or
+/// `ifndef::` directive tests (`ifdef::a,b[]`, `ifdef::a+b[]`).
+fn references(line: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    if let Some(rest) = line
+        .strip_prefix("ifdef::")
+        .or_else(|| line.strip_prefix("ifndef::"))
+        && let Some((condition, _)) = rest.split_once('[')
+    {
+        names.extend(
+            condition
+                .split([',', '+'])
+                .filter(|name| !name.is_empty() && name.chars().all(is_name_char))
+                .map(str::to_string),
+        );
+    }
+    interpolate_with(line, |name| {
+        names.push(name.to_string());
+        None
+    });
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(files: &[(&str, &str)]) -> AttributeReport {
+        attribute_report(files.iter().copied(), &Attributes::new())
+    }
+
+    #[test]
+    fn test_entries_and_values() {
+        let report = report(&[
+            (
+                "a.adoc",
+                "= A\n:first: Ada\n:full: {first} Lovelace\n\n:first: Grace\n\nBy {full}.\n",
+            ),
+            ("b.adoc", ":first!:\n\nNothing.\n"),
+        ]);
+        let first = report.get("first").unwrap();
+        let effects: Vec<_> = first
+            .entries
+            .iter()
+            .map(|entry| (entry.location.to_string(), entry.effect))
+            .collect();
+        assert_eq!(
+            effects,
+            [
+                ("a.adoc:2".to_string(), EntryEffect::Define),
+                ("a.adoc:5".to_string(), EntryEffect::Override),
+                ("b.adoc:1".to_string(), EntryEffect::Unset),
+            ]
+        );
+        assert_eq!(
+            first.values,
+            [
+                FinalValue {
+                    file: "a.adoc".to_string(),
+                    value: Some("Grace".to_string()),
+                },
+                FinalValue {
+                    file: "b.adoc".to_string(),
+                    value: None,
+                },
+            ]
+        );
+        // The reference in the entry's value counts
+        assert_eq!(first.references[0].line, 3);
+
+        let full = report.get("full").unwrap();
+        assert_eq!(full.values[0].value.as_deref(), Some("Ada Lovelace"));
+        assert!(!full.is_unused());
+        assert_eq!(report.documents, 2);
+    }
+
+    #[test]
+    fn test_passed_attributes_take_precedence() {
+        let mut passed = Attributes::new();
+        passed.set("version", "2.0");
+        let report = attribute_report(
+            [("a.adoc", ":version: 1.0\n\nVersion {version}.\n")],
+            &passed,
+        );
+        let version = report.get("version").unwrap();
+        assert_eq!(version.passed.as_deref(), Some("2.0"));
+        assert_eq!(version.entries[0].effect, EntryEffect::Ignore);
+        assert_eq!(version.values[0].value.as_deref(), Some("2.0"));
+        assert!(!version.is_undefined());
+    }
+
+    #[test]
+    fn test_references_and_exclusions() {
+        let report = report(&[(
+            "a.adoc",
+            ":used:\n:unused: x\n\n\
+             ifdef::used,env-github[]\n\\{escaped} {missing} {plus} {doctitle}\n\n\
+             ----\n:listing: x\n{listing}\n----\n\n// {comment}\n",
+        )]);
+        let names: Vec<_> = report
+            .attributes
+            .iter()
+            .map(|usage| usage.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "doctitle",
+                "env-github",
+                "missing",
+                "plus",
+                "unused",
+                "used"
+            ]
+        );
+        assert!(report.get("unused").unwrap().is_unused());
+        assert!(!report.get("used").unwrap().is_unused());
+        assert!(report.get("missing").unwrap().is_undefined());
+        assert!(report.get("env-github").unwrap().is_undefined());
+        assert!(!report.get("plus").unwrap().is_undefined());
+        assert!(!report.get("doctitle").unwrap().is_undefined());
+
+        let text = report.to_string();
+        assert!(text.starts_with("Attribute report: 1 documents (6 attributes)\n"));
+        assert!(text.contains("  unused [unused]\n    a.adoc:2 define    \"x\"\n"));
+        assert!(text.contains("  plus [built-in]\n    a.adoc:5 reference\n    a.adoc = \"+\"\n"));
+    }
+}
@@ -0,0 +1,940 @@
+//! Document attribute storage and value interpolation
+//!
+//! AsciiDoc attributes are named string values (`:version: 1.2`) that can be
+//! referenced elsewhere as `{version}`. Attribute values may themselves
+//! reference earlier attributes, which is how version strings and URLs are
+//! usually composed:
+//!
+//! ```asciidoc
+//! :first: Ada
+//! :last: Lovelace
+//! :full-name: {first} {last}
+//! ```
+//!
+//! # Resolution Rules
+//!
+//! - [`Attributes::define`] resolves references **at definition time**, using the
+//!   values defined so far. Later redefinitions of `first` do not change
+//!   `full-name`. This matches how attribute entries in a document body behave.
+//! - References to undefined attributes are left in place verbatim (`{missing}`),
+//!   matching the `attribute-missing: skip` default.
+//! - A backslash escapes a reference: `\{first}` produces the literal `{first}`.
+//! - [`Attributes::define_all`] resolves a batch of definitions that may reference
+//!   each other in any order (e.g. attributes passed on the command line). It
+//!   detects reference cycles and reports them as [`AttributeError::Cycle`].
+//! - Resolved values are capped in length, since each definition can double
+//!   the one before (`:b: {a}{a}`, `:c: {b}{b}`, ...). A value longer than
+//!   [`DEFAULT_MAX_ATTRIBUTE_LENGTH`] bytes, or the limit given to
+//!   [`Attributes::define_within`], is [`AttributeError::TooLong`].
+//!
+//! # Conditional Expressions
+//!
+//! [`evaluate`] computes the condition of an `ifeval::[...]` directive
+//! (`{version} >= 2`) the way Asciidoctor does, so build tools can decide
+//! conditional content ahead of time. Operands are coerced to strings,
+//! numbers, booleans, or nil before they are compared; see its
+//! documentation for the rules.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::attributes::Attributes;
+//!
+//! let mut attrs = Attributes::new();
+//! attrs.define("first", "Ada").unwrap();
+//! attrs.define("last", "Lovelace").unwrap();
+//! attrs.define("full-name", "{first} {last}").unwrap();
+//!
+//! assert_eq!(attrs.get("full-name"), Some("Ada Lovelace"));
+//! ```
+
+pub use crate::limits::DEFAULT_MAX_ATTRIBUTE_LENGTH;
+use std::collections::{BTreeMap, HashMap};
+use thiserror::Error;
+
+/// Error produced while resolving attribute definitions
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AttributeError {
+    /// Definitions reference each other in a cycle
+    ///
+    /// `chain` lists the attribute names along the cycle, starting and ending
+    /// with the same name (e.g. `["a", "b", "a"]`).
+    #[error("attribute reference cycle: {}", chain.join(" -> "))]
+    Cycle { chain: Vec<String> },
+
+    /// A resolved value is longer than the limit (see
+    /// [`Limits::max_attribute_length`](crate::limits::Limits::max_attribute_length))
+    #[error("attribute {name} resolves to more than {max} bytes")]
+    TooLong { name: String, max: usize },
+
+    /// An `ifeval` expression is not two operands around a comparison
+    /// operator (see [`evaluate`])
+    #[error("invalid ifeval expression: {expr:?}")]
+    Expression { expr: String },
+
+    /// [`ParseOptions::env_attributes`](crate::options::ParseOptions::env_attributes)
+    /// was given an empty prefix, which would map every environment variable
+    #[error(
+        "empty environment attribute prefix: every environment variable would become an attribute"
+    )]
+    EmptyEnvPrefix,
+}
+
+/// A set of document attributes
+///
+/// Values are stored fully resolved. Iteration is in name order, so reports
+/// built from an `Attributes` value are deterministic.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Attributes {
+    values: BTreeMap<String, String>,
+}
+
+impl Attributes {
+    /// Creates an empty attribute set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value of `name`, if defined
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// Returns true if `name` is defined
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    /// Sets `name` to `value` verbatim, without resolving references
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.values.insert(name.into(), value.into());
+    }
+
+    /// Defines `name`, resolving references in `raw` against current values
+    ///
+    /// Returns the resolved value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AttributeError::TooLong`] if the value would be longer than
+    /// [`DEFAULT_MAX_ATTRIBUTE_LENGTH`]. In that case `name` is unchanged.
+    pub fn define(&mut self, name: impl Into<String>, raw: &str) -> Result<&str, AttributeError> {
+        self.define_within(name, raw, Some(DEFAULT_MAX_ATTRIBUTE_LENGTH))
+    }
+
+    /// [`define`](Self::define) with the value limited to `max` bytes
+    /// (`None` = unlimited)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use doctora::attributes::{AttributeError, Attributes};
+    ///
+    /// let mut attrs = Attributes::new();
+    /// attrs.define_within("a", "xxxx", Some(8)).unwrap();
+    /// attrs.define_within("b", "{a}{a}", Some(8)).unwrap();
+    /// assert_eq!(
+    ///     attrs.define_within("c", "{b}{b}", Some(8)),
+    ///     Err(AttributeError::TooLong { name: "c".to_string(), max: 8 })
+    /// );
+    /// assert!(!attrs.contains("c"));
+    /// ```
+    pub fn define_within(
+        &mut self,
+        name: impl Into<String>,
+        raw: &str,
+        max: Option<usize>,
+    ) -> Result<&str, AttributeError> {
+        let name = name.into();
+        let Some(value) = interpolate_limited(raw, max, |name| self.get(name).map(str::to_string))
+        else {
+            return Err(AttributeError::TooLong {
+                name,
+                max: max.unwrap_or_default(),
+            });
+        };
+        let slot = self.values.entry(name).or_default();
+        *slot = value;
+        Ok(slot)
+    }
+
+    /// Defines a batch of attributes whose values may reference each other
+    ///
+    /// Each value may reference any attribute in the batch (regardless of
+    /// order) or any attribute already defined. Batch entries take precedence
+    /// over existing values when resolving references.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AttributeError::Cycle`] if the batch contains a reference
+    /// cycle, or [`AttributeError::TooLong`] if a value would be longer than
+    /// [`DEFAULT_MAX_ATTRIBUTE_LENGTH`]. In that case no attributes are
+    /// defined.
+    pub fn define_all<'a, I>(&mut self, entries: I) -> Result<(), AttributeError>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        self.define_all_within(entries, Some(DEFAULT_MAX_ATTRIBUTE_LENGTH))
+    }
+
+    /// [`define_all`](Self::define_all) with each value limited to `max`
+    /// bytes (`None` = unlimited)
+    pub fn define_all_within<'a, I>(
+        &mut self,
+        entries: I,
+        max: Option<usize>,
+    ) -> Result<(), AttributeError>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let pending: HashMap<&str, &str> = entries.into_iter().collect();
+        let mut resolved: HashMap<&str, String> = HashMap::new();
+
+        let mut names: Vec<&str> = pending.keys().copied().collect();
+        names.sort_unstable();
+        for name in names {
+            let mut stack = Vec::new();
+            self.resolve_pending(name, &pending, max, &mut resolved, &mut stack)?;
+        }
+
+        for (name, value) in resolved {
+            self.values.insert(name.to_string(), value);
+        }
+        Ok(())
+    }
+
+    /// Removes `name`, returning its previous value
+    pub fn unset(&mut self, name: &str) -> Option<String> {
+        self.values.remove(name)
+    }
+
+    /// Iterates over `(name, value)` pairs in name order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Number of defined attributes
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns true if no attributes are defined
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Style of a block, from its attribute list
+    ///
+    /// The style is the first positional attribute without its `.role`,
+    /// `#id`, and `%option` shorthands, so `[source.wide%linenums]` has the
+    /// style `source` and `[.wide]` has none.
+    pub fn style(&self) -> Option<&str> {
+        self.get("1")
+            .and_then(|style| style.split(['.', '#', '%']).next())
+            .filter(|style| !style.is_empty())
+    }
+
+    /// ID of a block, from its attribute list
+    ///
+    /// The `#id` shorthand in the first positional attribute (`[#intro]`,
+    /// `[source#main,rust]`) takes precedence over the `id` attribute.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use doctora::attributes::parse_attribute_list;
+    ///
+    /// assert_eq!(parse_attribute_list("source#main.wide,rust").id(), Some("main"));
+    /// assert_eq!(parse_attribute_list("id=intro").id(), Some("intro"));
+    /// assert_eq!(parse_attribute_list("source").id(), None);
+    /// ```
+    pub fn id(&self) -> Option<&str> {
+        let first = self.get("1").unwrap_or_default();
+        let shorthand = first.find('#').map(|start| {
+            let id = &first[start + 1..];
+            &id[..id.find(['.', '#', '%']).unwrap_or(id.len())]
+        });
+        shorthand
+            .filter(|id| !id.is_empty())
+            .or_else(|| self.get("id"))
+    }
+
+    /// Roles of a block, from its attribute list
+    ///
+    /// Roles come from `.role` shorthands in the first positional attribute
+    /// (`[quote.fancy]`, `[.api-endpoint]`) followed by the space-separated
+    /// `role` attribute.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use doctora::attributes::parse_attribute_list;
+    ///
+    /// let attrs = parse_attribute_list("source.wide#main.dark,rust,role=\"a b\"");
+    /// assert_eq!(attrs.roles(), vec!["wide", "dark", "a", "b"]);
+    /// ```
+    pub fn roles(&self) -> Vec<&str> {
+        let mut roles = Vec::new();
+        let mut rest = self.get("1").unwrap_or_default();
+        while let Some(start) = rest.find(['.', '#', '%']) {
+            let shorthand = &rest[start + 1..];
+            let end = shorthand.find(['.', '#', '%']).unwrap_or(shorthand.len());
+            if rest[start..].starts_with('.') && end > 0 {
+                roles.push(&shorthand[..end]);
+            }
+            rest = &shorthand[end..];
+        }
+        roles.extend(self.get("role").into_iter().flat_map(str::split_whitespace));
+        roles
+    }
+
+    /// Options of a block, from its attribute list
+    ///
+    /// Options come from `%option` shorthands in the first positional
+    /// attribute (`[%header]`, `[source%linenums]`) followed by the
+    /// comma-separated `options` and `opts` attributes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use doctora::attributes::parse_attribute_list;
+    ///
+    /// let attrs = parse_attribute_list("%header%footer,options=\"autowidth, nowrap\"");
+    /// assert_eq!(attrs.options(), vec!["header", "footer", "autowidth", "nowrap"]);
+    /// ```
+    pub fn options(&self) -> Vec<&str> {
+        let mut options = Vec::new();
+        let mut rest = self.get("1").unwrap_or_default();
+        while let Some(start) = rest.find(['.', '#', '%']) {
+            let shorthand = &rest[start + 1..];
+            let end = shorthand.find(['.', '#', '%']).unwrap_or(shorthand.len());
+            if rest[start..].starts_with('%') && end > 0 {
+                options.push(&shorthand[..end]);
+            }
+            rest = &shorthand[end..];
+        }
+        options.extend(
+            ["options", "opts"]
+                .into_iter()
+                .filter_map(|name| self.get(name))
+                .flat_map(|list| list.split(','))
+                .map(str::trim)
+                .filter(|option| !option.is_empty()),
+        );
+        options
+    }
+
+    /// Replaces `{name}` references in `text` with current attribute values
+    ///
+    /// Undefined references are kept verbatim and `\{name}` yields `{name}`.
+    pub fn interpolate(&self, text: &str) -> String {
+        interpolate_with(text, |name| self.get(name).map(str::to_string))
+    }
+
+    /// Resolve one batch entry depth-first, tracking the reference chain
+    fn resolve_pending<'a>(
+        &self,
+        name: &'a str,
+        pending: &HashMap<&'a str, &'a str>,
+        max: Option<usize>,
+        resolved: &mut HashMap<&'a str, String>,
+        stack: &mut Vec<&'a str>,
+    ) -> Result<String, AttributeError> {
+        if let Some(value) = resolved.get(name) {
+            return Ok(value.clone());
+        }
+        if let Some(start) = stack.iter().position(|entry| *entry == name) {
+            let mut chain: Vec<String> = stack[start..].iter().map(|s| s.to_string()).collect();
+            chain.push(name.to_string());
+            return Err(AttributeError::Cycle { chain });
+        }
+
+        let raw = pending[name];
+        stack.push(name);
+
+        let mut error = None;
+        let value = interpolate_limited(raw, max, |reference| {
+            if error.is_some() {
+                return None;
+            }
+            match pending.get_key_value(reference) {
+                Some((&key, _)) => match self.resolve_pending(key, pending, max, resolved, stack) {
+                    Ok(value) => Some(value),
+                    Err(err) => {
+                        error = Some(err);
+                        None
+                    }
+                },
+                None => self.get(reference).map(str::to_string),
+            }
+        });
+
+        stack.pop();
+        if let Some(err) = error {
+            return Err(err);
+        }
+        let value = value.ok_or_else(|| AttributeError::TooLong {
+            name: name.to_string(),
+            max: max.unwrap_or_default(),
+        })?;
+        resolved.insert(name, value.clone());
+        Ok(value)
+    }
+}
+
+/// Returns true if `c` may appear in an attribute name
+pub(crate) fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Replace `{name}` references in `text` using `lookup`
+///
+/// References for which `lookup` returns `None` are kept verbatim.
+pub(crate) fn interpolate_with<F>(text: &str, lookup: F) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    interpolate_limited(text, None, lookup).unwrap_or_default()
+}
+
+/// [`interpolate_with`], giving up with `None` as soon as the result is
+/// longer than `max` bytes
+fn interpolate_limited<F>(text: &str, max: Option<usize>, mut lookup: F) -> Option<String>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(open) = rest.find('{') {
+        let after = &rest[open + 1..];
+        let name_len = after
+            .find(|c: char| !is_name_char(c))
+            .unwrap_or(after.len());
+        let closed = name_len > 0 && after[name_len..].starts_with('}');
+
+        if !closed {
+            out.push_str(&rest[..=open]);
+            rest = after;
+            continue;
+        }
+
+        let name = &after[..name_len];
+        let reference_end = open + 1 + name_len + 1;
+
+        if rest[..open].ends_with('\\') {
+            out.push_str(&rest[..open - 1]);
+            out.push_str(&rest[open..reference_end]);
+        } else {
+            out.push_str(&rest[..open]);
+            match lookup(name) {
+                Some(value) => out.push_str(&value),
+                None => out.push_str(&rest[open..reference_end]),
+            }
+        }
+        if max.is_some_and(|max| out.len() > max) {
+            return None;
+        }
+        rest = &rest[reference_end..];
+    }
+
+    out.push_str(rest);
+    max.is_none_or(|max| out.len() <= max).then_some(out)
+}
+
+/// Evaluate an `ifeval` condition against `attributes`
+///
+/// The expression is two operands compared with `==`, `!=`, `<`, `<=`, `>`,
+/// or `>=`. Attribute references in each operand are replaced first, with
+/// undefined ones dropped. The operand is then coerced as Asciidoctor does:
+///
+/// - Quoted (`'...'` or `"..."`): the string between the quotes
+/// - Empty: nil
+/// - `true` or `false`: a boolean
+/// - Containing `.`: a float, from its leading number (`1.5`, `2.x` is 2.0)
+/// - Otherwise: an integer, from its leading digits (`10`, `abc` is 0)
+///
+/// Numbers compare by value, whether integer or float, and strings
+/// byte-wise. Nil equals nil and booleans equal themselves, but neither is
+/// ordered; operands of different types are neither equal nor ordered.
+///
+/// # Examples
+///
+/// ```
+/// use doctora::attributes::{Attributes, evaluate};
+///
+/// let mut attrs = Attributes::new();
+/// attrs.set("version", "2.1");
+/// attrs.set("backend", "html5");
+///
+/// assert_eq!(evaluate("{version} >= 2", &attrs), Ok(true));
+/// assert_eq!(evaluate(r#""{backend}" == "html5""#, &attrs), Ok(true));
+/// assert_eq!(evaluate("{backend} == html5", &attrs), Ok(true)); // both 0
+/// assert!(evaluate("{version}", &attrs).is_err());
+/// ```
+///
+/// # Errors
+///
+/// Fails with [`AttributeError::Expression`] if `expr` has no comparison
+/// operator with an operand on each side.
+pub fn evaluate(expr: &str, attributes: &Attributes) -> Result<bool, AttributeError> {
+    const OPERATORS: [&str; 6] = ["==", "!=", "<=", ">=", "<", ">"];
+    let invalid = || AttributeError::Expression {
+        expr: expr.to_string(),
+    };
+    let trimmed = expr.trim();
+    // The first operator after the first character, as in Asciidoctor
+    let (at, operator) = trimmed
+        .char_indices()
+        .skip(1)
+        .find_map(|(at, _)| {
+            OPERATORS
+                .iter()
+                .find(|operator| trimmed[at..].starts_with(*operator))
+                .map(|operator| (at, *operator))
+        })
+        .ok_or_else(invalid)?;
+    let lhs = trimmed[..at].trim_end_matches([' ', '\t']);
+    let rhs = trimmed[at + operator.len()..].trim_start_matches([' ', '\t']);
+    if lhs.is_empty() || rhs.is_empty() {
+        return Err(invalid());
+    }
+    let lhs = Operand::resolve(lhs, attributes);
+    let rhs = Operand::resolve(rhs, attributes);
+    Ok(match operator {
+        "==" => lhs.equals(&rhs),
+        "!=" => !lhs.equals(&rhs),
+        _ => lhs.ordering(&rhs).is_some_and(|ordering| match operator {
+            "<" => ordering.is_lt(),
+            "<=" => ordering.is_le(),
+            ">" => ordering.is_gt(),
+            _ => ordering.is_ge(),
+        }),
+    })
+}
+
+/// A coerced `ifeval` operand
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl Operand {
+    /// Substitute attributes in `raw` and coerce it (see [`evaluate`])
+    fn resolve(raw: &str, attributes: &Attributes) -> Self {
+        let quoted = raw.len() >= 2
+            && (raw.starts_with('"') && raw.ends_with('"')
+                || raw.starts_with('\'') && raw.ends_with('\''));
+        let raw = if quoted { &raw[1..raw.len() - 1] } else { raw };
+        let value = interpolate_with(raw, |name| {
+            Some(attributes.get(name).unwrap_or_default().to_string())
+        });
+        if quoted {
+            return Self::Str(value);
+        }
+        match value.as_str() {
+            "" => Self::Nil,
+            "true" => Self::Bool(true),
+            "false" => Self::Bool(false),
+            // Blank, but not empty
+            blank if blank.trim_end().is_empty() => Self::Str(" ".to_string()),
+            number if number.contains('.') => Self::Float(leading_float(number)),
+            number => leading_integer(number),
+        }
+    }
+
+    fn equals(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Nil, Self::Nil) => true,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            _ => self
+                .ordering(other)
+                .is_some_and(|ordering| ordering.is_eq()),
+        }
+    }
+
+    fn ordering(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => Some(a.cmp(b)),
+            (Self::Str(a), Self::Str(b)) => Some(a.cmp(b)),
+            (Self::Int(_) | Self::Float(_), Self::Int(_) | Self::Float(_)) => {
+                self.as_f64().partial_cmp(&other.as_f64())
+            }
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> f64 {
+        match self {
+            Self::Int(n) => *n as f64,
+            Self::Float(n) => *n,
+            _ => f64::NAN,
+        }
+    }
+}
+
+/// Length of the optionally signed run of digits at the start of `text`
+fn digits_len(text: &str) -> usize {
+    let sign = usize::from(text.starts_with(['-', '+']));
+    sign + text[sign..].bytes().take_while(u8::is_ascii_digit).count()
+}
+
+/// The integer `text` starts with, after leading whitespace (0 if none),
+/// as Ruby's `to_i` reads it
+fn leading_integer(text: &str) -> Operand {
+    let text = text.trim_start();
+    let digits = &text[..digits_len(text)];
+    match digits.parse() {
+        Ok(n) => Operand::Int(n),
+        // Too long for an integer
+        Err(_) if digits.len() > 1 => Operand::Float(digits.parse().unwrap_or_default()),
+        Err(_) => Operand::Int(0),
+    }
+}
+
+/// The decimal number `text` starts with, after leading whitespace (0.0 if
+/// none), as Ruby's `to_f` reads it
+fn leading_float(text: &str) -> f64 {
+    let text = text.trim_start();
+    let mut end = digits_len(text);
+    if let Some(fraction) = text[end..].strip_prefix('.') {
+        let fraction_len = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if fraction_len > 0 {
+            end += 1 + fraction_len;
+        }
+    }
+    text[..end].parse().unwrap_or_default()
+}
+
+/// Parse a block attribute list (the text between `[` and `]`)
+///
+/// Positional values are stored under their 1-based position (`"1"`, `"2"`,
+/// ...) and `name=value` entries under their name. Values may be quoted to
+/// include commas. Empty positional values (`[,rust]`) keep their position
+/// but are not stored.
+///
+/// # Examples
+///
+/// ```
+/// use doctora::attributes::parse_attribute_list;
+///
+/// let attrs = parse_attribute_list(r#"source,rust,subs="attributes,quotes""#);
+/// assert_eq!(attrs.get("1"), Some("source"));
+/// assert_eq!(attrs.get("2"), Some("rust"));
+/// assert_eq!(attrs.get("subs"), Some("attributes,quotes"));
+/// ```
+pub fn parse_attribute_list(list: &str) -> Attributes {
+    let mut attrs = Attributes::new();
+    let mut position = 0;
+    for entry in split_attribute_list(list) {
+        let named = entry.split_once('=').filter(|(name, _)| {
+            let name = name.trim();
+            !name.is_empty() && name.chars().all(is_name_char)
+        });
+        match named {
+            Some((name, value)) => attrs.set(name.trim(), unquote(value.trim())),
+            None => {
+                position += 1;
+                let value = unquote(entry.trim());
+                if !value.is_empty() {
+                    attrs.set(position.to_string(), value);
+                }
+            }
+        }
+    }
+    attrs
+}
+
+/// Split an attribute list at commas outside double quotes
+fn split_attribute_list(list: &str) -> Vec<&str> {
+    let mut entries = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                entries.push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !list.trim().is_empty() {
+        entries.push(&list[start..]);
+    }
+    entries
+}
+
+/// Strip surrounding double quotes from `value`
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_define_and_get() {
+        let mut attrs = Attributes::new();
+        assert!(attrs.is_empty());
+        assert_eq!(attrs.define("version", "1.2"), Ok("1.2"));
+        assert_eq!(attrs.get("version"), Some("1.2"));
+        assert!(attrs.contains("version"));
+        assert_eq!(attrs.len(), 1);
+    }
+
+    #[test]
+    fn test_define_resolves_at_definition_time() {
+        let mut attrs = Attributes::new();
+        attrs.define("version", "1.2").unwrap();
+        attrs
+            .define("url", "https://example.com/v{version}/")
+            .unwrap();
+        attrs.define("version", "2.0").unwrap();
+
+        assert_eq!(attrs.get("url"), Some("https://example.com/v1.2/"));
+    }
+
+    #[test]
+    fn test_self_reference_uses_previous_value() {
+        let mut attrs = Attributes::new();
+        attrs.define("path", "docs").unwrap();
+        attrs.define("path", "{path}/api").unwrap();
+        assert_eq!(attrs.get("path"), Some("docs/api"));
+    }
+
+    #[test]
+    fn test_doubling_chain_is_too_long() {
+        let mut attrs = Attributes::new();
+        attrs.define("a0", "x").unwrap();
+        let err = (1..64)
+            .find_map(|level| {
+                let raw = format!("{{a{0}}}{{a{0}}}", level - 1);
+                attrs.define(format!("a{level}"), &raw).err()
+            })
+            .unwrap();
+
+        // 2^20 bytes is the longest value allowed
+        assert_eq!(
+            err,
+            AttributeError::TooLong {
+                name: "a21".to_string(),
+                max: DEFAULT_MAX_ATTRIBUTE_LENGTH,
+            }
+        );
+        assert_eq!(attrs.get("a20").map(str::len), Some(1 << 20));
+        assert!(!attrs.contains("a21"));
+    }
+
+    #[test]
+    fn test_missing_reference_kept() {
+        let mut attrs = Attributes::new();
+        attrs.define("greeting", "Hello {name}!").unwrap();
+        assert_eq!(attrs.get("greeting"), Some("Hello {name}!"));
+    }
+
+    #[test]
+    fn test_escaped_reference() {
+        let mut attrs = Attributes::new();
+        attrs.define("name", "Ada").unwrap();
+        assert_eq!(attrs.interpolate(r"\{name} is {name}"), "{name} is Ada");
+    }
+
+    #[test]
+    fn test_non_references_untouched() {
+        let attrs = Attributes::new();
+        assert_eq!(
+            attrs.interpolate("{ not a ref } {} {a b}"),
+            "{ not a ref } {} {a b}"
+        );
+        assert_eq!(attrs.interpolate("trailing {"), "trailing {");
+    }
+
+    #[test]
+    fn test_set_is_verbatim() {
+        let mut attrs = Attributes::new();
+        attrs.define("a", "x").unwrap();
+        attrs.set("b", "{a}");
+        assert_eq!(attrs.get("b"), Some("{a}"));
+    }
+
+    #[test]
+    fn test_unset() {
+        let mut attrs = Attributes::new();
+        attrs.define("a", "x").unwrap();
+        assert_eq!(attrs.unset("a"), Some("x".to_string()));
+        assert_eq!(attrs.get("a"), None);
+    }
+
+    #[test]
+    fn test_define_all_any_order() {
+        let mut attrs = Attributes::new();
+        attrs.define("host", "example.com").unwrap();
+        attrs
+            .define_all([
+                ("url", "https://{host}/{path}"),
+                ("path", "v{version}"),
+                ("version", "3"),
+            ])
+            .unwrap();
+
+        assert_eq!(attrs.get("url"), Some("https://example.com/v3"));
+        assert_eq!(attrs.get("path"), Some("v3"));
+    }
+
+    #[test]
+    fn test_define_all_too_long() {
+        let mut attrs = Attributes::new();
+        let err = attrs
+            .define_all_within([("c", "{b}{b}"), ("b", "{a}{a}"), ("a", "xyz")], Some(8))
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            AttributeError::TooLong {
+                name: "c".to_string(),
+                max: 8,
+            }
+        );
+        assert!(attrs.is_empty());
+        attrs
+            .define_all_within([("c", "{b}{b}"), ("b", "{a}{a}"), ("a", "xyz")], None)
+            .unwrap();
+        assert_eq!(attrs.get("c"), Some("xyzxyzxyzxyz"));
+    }
+
+    #[test]
+    fn test_define_all_detects_cycle() {
+        let mut attrs = Attributes::new();
+        let err = attrs
+            .define_all([("a", "{b}"), ("b", "{c}"), ("c", "{a}")])
+            .unwrap_err();
+
+        let AttributeError::Cycle { chain } = &err else {
+            panic!("expected a cycle, got {err:?}");
+        };
+        assert_eq!(chain.first(), chain.last());
+        assert_eq!(chain.len(), 4);
+        assert!(err.to_string().contains("->"));
+        assert!(attrs.is_empty());
+    }
+
+    #[test]
+    fn test_define_all_self_cycle() {
+        let mut attrs = Attributes::new();
+        let err = attrs.define_all([("a", "{a}")]).unwrap_err();
+        assert_eq!(
+            err,
+            AttributeError::Cycle {
+                chain: vec!["a".to_string(), "a".to_string()]
+            }
+        );
+    }
+
+    #[test]
+    fn test_iter_in_name_order() {
+        let mut attrs = Attributes::new();
+        attrs.set("b", "2");
+        attrs.set("a", "1");
+        let names: Vec<&str> = attrs.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut attrs = Attributes::new();
+        attrs.set("version", "2.1");
+        attrs.set("count", "10");
+        attrs.set("name", "doctora");
+        attrs.set("flag", "true");
+        let cases = [
+            // Numbers compare by value, integer or float
+            ("{count} == 10", true),
+            ("{count} > 9", true),
+            ("{count} <= 9", false),
+            ("{version} >= 2", true),
+            ("{version} < 2.10", false), // floats, not version numbers
+            ("1 == 1.0", true),
+            ("-3 < 2", true),
+            // Quoted operands are strings; unquoted text reads as 0
+            ("\"{name}\" == 'doctora'", true),
+            ("'{name}' != \"other\"", true),
+            ("'abc' < 'abd'", true),
+            ("{name} == other", true),
+            ("'10' == 10", false),
+            // Booleans and nil equal themselves but are not ordered
+            ("{flag} == true", true),
+            ("{flag} != false", true),
+            ("true > false", false),
+            ("{missing} == {undefined}", true),
+            ("{missing} <= {undefined}", false),
+            // No spaces needed around the operator, and the first one wins
+            ("{count}>=10", true),
+            ("1==2==2", false),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(evaluate(expr, &attrs), Ok(expected), "{expr}");
+        }
+        for expr in ["{count}", "== 10", "10 <", "", "=10"] {
+            assert_eq!(
+                evaluate(expr, &attrs),
+                Err(AttributeError::Expression {
+                    expr: expr.to_string()
+                }),
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_attribute_list() {
+        let attrs = parse_attribute_list(" source , rust,linenums, title=\"A, B\" ");
+        assert_eq!(attrs.get("1"), Some("source"));
+        assert_eq!(attrs.get("2"), Some("rust"));
+        assert_eq!(attrs.get("3"), Some("linenums"));
+        assert_eq!(attrs.get("title"), Some("A, B"));
+
+        let attrs = parse_attribute_list(",rust");
+        assert_eq!(attrs.get("1"), None);
+        assert_eq!(attrs.get("2"), Some("rust"));
+
+        assert!(parse_attribute_list("").is_empty());
+    }
+
+    #[test]
+    fn test_roles() {
+        assert_eq!(
+            parse_attribute_list(".api-endpoint").roles(),
+            vec!["api-endpoint"]
+        );
+        assert_eq!(
+            parse_attribute_list("abstract%header.card.").roles(),
+            vec!["card"]
+        );
+        assert!(parse_attribute_list("source,rust").roles().is_empty());
+    }
+
+    #[test]
+    fn test_options() {
+        assert_eq!(
+            parse_attribute_list("source%linenums.wide").options(),
+            vec!["linenums"]
+        );
+        assert_eq!(
+            parse_attribute_list("opts=header").options(),
+            vec!["header"]
+        );
+        assert!(parse_attribute_list("%,role=x").options().is_empty());
+    }
+}
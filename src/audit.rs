@@ -0,0 +1,401 @@
+//! Security audit of AsciiDoc sources
+//!
+//! Documentation pipelines that accept third-party contributions need to know
+//! where a document can affect output beyond plain text. [`audit_source`]
+//! scans a document and reports every:
+//!
+//! | Kind                            | What is reported                                        |
+//! |---------------------------------|---------------------------------------------------------|
+//! | [`FindingKind::Passthrough`]    | `++++` blocks, `[pass]` blocks, `pass:[]`, `+++...+++`  |
+//! | [`FindingKind::Substitutions`]  | `subs=` overrides, with the resulting active groups     |
+//! | [`FindingKind::Include`]        | `include::` targets outside the project root, or remote |
+//! | [`FindingKind::ExternalUrl`]    | `http://`, `https://`, and `ftp://` URLs                |
+//!
+//! The scan works on the source text, not the AST, so it also covers syntax the
+//! parser does not support yet. It is deliberately conservative: text inside
+//! verbatim blocks is scanned too, and include targets that use attribute
+//! references are reported because they cannot be resolved statically.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::audit::{AuditOptions, FindingKind, audit_source};
+//!
+//! let source = "See https://example.com.\n\n++++\n<script>alert(1)</script>\n++++\n";
+//! let report = audit_source("guide.adoc", source, &AuditOptions::new("/site", "/site/docs"));
+//!
+//! let kinds: Vec<FindingKind> = report.findings.iter().map(|f| f.kind).collect();
+//! assert_eq!(kinds, vec![FindingKind::ExternalUrl, FindingKind::Passthrough]);
+//! println!("{}", report);
+//! ```
+
+use crate::subs::Substitutions;
+use crate::token::LineIndex;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+/// Category of an audit finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FindingKind {
+    /// Content that reaches the output without escaping
+    Passthrough,
+    /// A `subs` override changing which substitutions apply
+    Substitutions,
+    /// An include that leaves the project root or is fetched remotely
+    Include,
+    /// A link or reference to an external URL
+    ExternalUrl,
+}
+
+impl FindingKind {
+    /// Short identifier used in reports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FindingKind::Passthrough => "passthrough",
+            FindingKind::Substitutions => "substitutions",
+            FindingKind::Include => "include",
+            FindingKind::ExternalUrl => "external-url",
+        }
+    }
+}
+
+/// A single audit finding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditFinding {
+    /// Category of the finding
+    pub kind: FindingKind,
+    /// 1-based line of the finding
+    pub line: usize,
+    /// 1-based column (in characters) of the finding
+    pub column: usize,
+    /// Explanation for the reviewer
+    pub detail: String,
+}
+
+/// All findings for one document, in source order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditReport {
+    /// Name of the audited document
+    pub document: String,
+    /// Findings in source order
+    pub findings: Vec<AuditFinding>,
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Security audit: {} ({} findings)",
+            self.document,
+            self.findings.len()
+        )?;
+        for finding in &self.findings {
+            writeln!(
+                f,
+                "  {:>4}:{:<3} {:<14} {}",
+                finding.line,
+                finding.column,
+                finding.kind.as_str(),
+                finding.detail
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the audited document lives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditOptions {
+    /// Includes must resolve inside this directory
+    pub project_root: PathBuf,
+    /// Directory of the audited document; relative includes resolve from here
+    pub document_dir: PathBuf,
+}
+
+impl AuditOptions {
+    /// Creates options for a document in `document_dir` within `project_root`
+    pub fn new(project_root: impl Into<PathBuf>, document_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            project_root: project_root.into(),
+            document_dir: document_dir.into(),
+        }
+    }
+}
+
+/// URL schemes reported as external
+const URL_SCHEMES: [&str; 3] = ["https://", "http://", "ftp://"];
+
+/// Audit `source` and collect findings in source order
+pub fn audit_source(document: &str, source: &str, options: &AuditOptions) -> AuditReport {
+    let index = LineIndex::new(source);
+    let mut findings: Vec<(usize, FindingKind, String)> = Vec::new();
+
+    let mut offset = 0;
+    let mut open_passthrough: Option<&str> = None;
+    for line in source.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        let delimiter = text.trim_end();
+        if delimiter.len() >= 4 && delimiter.bytes().all(|b| b == b'+') {
+            // Report the opening delimiter only; the block ends at the matching line
+            match open_passthrough {
+                Some(open) if open == delimiter => open_passthrough = None,
+                Some(_) => {}
+                None => {
+                    open_passthrough = Some(delimiter);
+                    findings.push((
+                        offset,
+                        FindingKind::Passthrough,
+                        "passthrough block; block content is output unescaped".to_string(),
+                    ));
+                }
+            }
+        } else {
+            audit_line(text, offset, options, &mut findings);
+        }
+        offset += line.len();
+    }
+
+    findings.sort_by_key(|(offset, ..)| *offset);
+    AuditReport {
+        document: document.to_string(),
+        findings: findings
+            .into_iter()
+            .map(|(offset, kind, detail)| {
+                let (line, column) = index.line_col(offset);
+                AuditFinding {
+                    kind,
+                    line,
+                    column,
+                    detail,
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Collect findings for one line starting at byte `offset`
+fn audit_line(
+    line: &str,
+    offset: usize,
+    options: &AuditOptions,
+    findings: &mut Vec<(usize, FindingKind, String)>,
+) {
+    let trimmed = line.trim_end();
+
+    if let Some(target) = line.strip_prefix("include::") {
+        let target = target.split('[').next().unwrap_or_default();
+        if let Some(detail) = include_finding(target, options) {
+            findings.push((offset, FindingKind::Include, detail));
+        }
+        return;
+    }
+
+    if let Some(attrlist) = trimmed.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+        audit_attribute_list(attrlist, offset, findings);
+    }
+
+    for (pos, _) in line.match_indices("pass:") {
+        if line[pos + 5..].contains('[') {
+            findings.push((
+                offset + pos,
+                FindingKind::Passthrough,
+                "inline pass macro; content is output without the normal substitutions".to_string(),
+            ));
+        }
+    }
+    let mut rest = 0;
+    while let Some(start) = line[rest..].find("+++") {
+        let start = rest + start;
+        let Some(len) = line[start + 3..].find("+++") else {
+            break;
+        };
+        findings.push((
+            offset + start,
+            FindingKind::Passthrough,
+            "inline triple-plus passthrough; content is output unescaped".to_string(),
+        ));
+        rest = start + 3 + len + 3;
+    }
+
+    for scheme in URL_SCHEMES {
+        for (pos, _) in line.match_indices(scheme) {
+            let url: String = line[pos..]
+                .chars()
+                .take_while(|c| !c.is_whitespace() && !matches!(c, '[' | ']' | '<' | '>' | '"'))
+                .collect();
+            let url = url.trim_end_matches(['.', ',', ';', ':', ')']);
+            findings.push((offset + pos, FindingKind::ExternalUrl, url.to_string()));
+        }
+    }
+}
+
+/// Report `[pass]` blocks and `subs=` overrides in a block attribute list
+fn audit_attribute_list(
+    attrlist: &str,
+    offset: usize,
+    findings: &mut Vec<(usize, FindingKind, String)>,
+) {
+    let style = attrlist.split(',').next().unwrap_or_default().trim();
+    if style == "pass" {
+        findings.push((
+            offset,
+            FindingKind::Passthrough,
+            "[pass] block style; block content is output unescaped".to_string(),
+        ));
+    }
+
+    let Some(pos) = attrlist.find("subs=") else {
+        return;
+    };
+    let value = &attrlist[pos + 5..];
+    let spec = match value.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+        None => value.split(',').next().unwrap_or_default(),
+    };
+    let defaults = match style {
+        "source" | "listing" | "literal" => Substitutions::VERBATIM,
+        _ => Substitutions::NORMAL,
+    };
+
+    let detail = match Substitutions::parse(spec, defaults) {
+        Ok(subs) => {
+            let warning = if subs.contains(Substitutions::SPECIAL_CHARACTERS) {
+                ""
+            } else {
+                "; special characters are NOT escaped"
+            };
+            format!("subs=\"{}\" -> active: {}{}", spec, subs, warning)
+        }
+        Err(err) => format!("subs=\"{}\" is invalid: {}", spec, err),
+    };
+    findings.push((offset + 1 + pos, FindingKind::Substitutions, detail));
+}
+
+/// Describe why an include target needs review, if it does
+fn include_finding(target: &str, options: &AuditOptions) -> Option<String> {
+    if URL_SCHEMES.iter().any(|scheme| target.starts_with(scheme)) {
+        return Some(format!("remote include of {}", target));
+    }
+    if target.contains('{') {
+        return Some(format!(
+            "include target `{}` uses attribute references and cannot be checked statically",
+            target
+        ));
+    }
+
+    let resolved = normalize(&options.document_dir.join(target));
+    let root = normalize(&options.project_root);
+    (!resolved.starts_with(&root)).then(|| {
+        format!(
+            "include `{}` resolves to {} outside the project root {}",
+            target,
+            resolved.display(),
+            root.display()
+        )
+    })
+}
+
+/// Lexically resolve `.` and `..` components without touching the filesystem
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audit(source: &str) -> Vec<(FindingKind, usize, String)> {
+        audit_source(
+            "doc.adoc",
+            source,
+            &AuditOptions::new("/site", "/site/docs"),
+        )
+        .findings
+        .into_iter()
+        .map(|f| (f.kind, f.line, f.detail))
+        .collect()
+    }
+
+    #[test]
+    fn test_clean_document() {
+        assert!(audit("= Title\n\nJust **text**.\n").is_empty());
+    }
+
+    #[test]
+    fn test_passthroughs() {
+        let findings = audit("++++\n<b>raw</b>\n++++\n\n[pass]\nx\n\nA pass:[<u>] and +++<i>+++.");
+        let lines: Vec<usize> = findings.iter().map(|(_, line, _)| *line).collect();
+        assert_eq!(lines, vec![1, 5, 8, 8]);
+        assert!(
+            findings
+                .iter()
+                .all(|(k, ..)| *k == FindingKind::Passthrough)
+        );
+    }
+
+    #[test]
+    fn test_external_urls() {
+        let findings = audit("Go to https://example.com/a, or http://x.org[X].");
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    FindingKind::ExternalUrl,
+                    1,
+                    "https://example.com/a".to_string()
+                ),
+                (FindingKind::ExternalUrl, 1, "http://x.org".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_includes() {
+        let findings = audit(
+            "include::part.adoc[]\ninclude::../shared/a.adoc[]\ninclude::../../etc/passwd[]\ninclude::{dir}/x.adoc[]\ninclude::https://evil.example/x.adoc[]\n",
+        );
+        let lines: Vec<usize> = findings.iter().map(|(_, line, _)| *line).collect();
+        assert_eq!(lines, vec![3, 4, 5]);
+        assert!(findings[0].2.contains("outside the project root"));
+        assert!(findings[2].2.starts_with("remote include"));
+    }
+
+    #[test]
+    fn test_subs_overrides() {
+        let findings =
+            audit("[source,rust,subs=\"attributes+\"]\n----\nx\n----\n\n[subs=quotes]\nx");
+        assert_eq!(findings.len(), 2);
+        assert_eq!(
+            findings[0].2,
+            "subs=\"attributes+\" -> active: specialcharacters,attributes"
+        );
+        assert!(
+            findings[1]
+                .2
+                .ends_with("special characters are NOT escaped")
+        );
+    }
+
+    #[test]
+    fn test_report_format() {
+        let report = audit_source(
+            "doc.adoc",
+            "\n  see https://a.example",
+            &AuditOptions::new("/", "/"),
+        );
+        assert_eq!(
+            report.to_string(),
+            "Security audit: doc.adoc (1 findings)\n     2:7   external-url   https://a.example\n"
+        );
+    }
+}
@@ -0,0 +1,325 @@
+//! Batch processing of many documents with a configurable failure policy
+//!
+//! Site builds convert hundreds of documents in one run. By default a single
+//! malformed document stops the batch ([`FailurePolicy::Abort`]). The fail-soft
+//! policies keep going instead and report the failure as a
+//! [`BatchDiagnostic`]:
+//!
+//! | Policy                   | On failure                                |
+//! |--------------------------|-------------------------------------------|
+//! | [`FailurePolicy::Abort`] | Stop and return [`BatchError::Aborted`]   |
+//! | [`FailurePolicy::Skip`]  | Leave the document out of the results     |
+//! | [`FailurePolicy::Stub`]  | Emit a stub document describing the error |
+//!
+//! Panics inside the parser count as failures too, so with `Skip` or `Stub` a
+//! batch always runs to completion.
+//!
+//! [`parse_batch_observed`] additionally reports progress and diagnostics to an
+//! [`events::Subscriber`](crate::events::Subscriber). Both parse one document
+//! at a time; [`crate::scheduler`] parses batches in parallel within a memory
+//! budget.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::batch::{FailurePolicy, parse_batch};
+//!
+//! let inputs = [("good.adoc", "Hello."), ("bad.adoc", "= Unterminated")];
+//! let result = parse_batch(inputs, FailurePolicy::Stub).unwrap();
+//!
+//! assert_eq!(result.documents.len(), 2);
+//! assert!(result.documents[1].stub);
+//! assert_eq!(result.diagnostics[0].document, "bad.adoc");
+//! ```
+
+use crate::ast::{Block, BlockMetadata, Document, Inline};
+use crate::events::{Event, NoopSubscriber, Subscriber};
+use crate::parse_document;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Instant;
+use thiserror::Error;
+
+/// What to do when a document in a batch fails
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// Stop the batch at the first failure
+    #[default]
+    Abort,
+    /// Drop failed documents and continue
+    Skip,
+    /// Replace failed documents with a stub describing the error and continue
+    Stub,
+}
+
+/// Error returned when a batch is aborted
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BatchError {
+    /// A document failed under [`FailurePolicy::Abort`]
+    #[error("batch aborted at {document}: {message}")]
+    Aborted { document: String, message: String },
+}
+
+/// A failure recorded while processing a batch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchDiagnostic {
+    /// Name of the document that failed
+    pub document: String,
+    /// Description of the failure
+    pub message: String,
+}
+
+/// A document produced by a batch
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchDocument {
+    /// Name the document was submitted under
+    pub name: String,
+    /// The parsed document, or the stub for a failed one
+    pub document: Document,
+    /// True if `document` is a stub generated under [`FailurePolicy::Stub`]
+    pub stub: bool,
+}
+
+/// Outcome of a completed batch
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchResult {
+    /// Documents in input order (failed documents are absent under `Skip`)
+    pub documents: Vec<BatchDocument>,
+    /// One entry per failed document, in input order
+    pub diagnostics: Vec<BatchDiagnostic>,
+}
+
+impl BatchResult {
+    /// Returns true if every document succeeded
+    pub fn is_clean(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+/// Parse `(name, source)` pairs, handling failures according to `policy`
+///
+/// # Errors
+///
+/// Returns [`BatchError::Aborted`] for the first failing document when `policy`
+/// is [`FailurePolicy::Abort`]. The other policies never fail.
+pub fn parse_batch<I, N, S>(inputs: I, policy: FailurePolicy) -> Result<BatchResult, BatchError>
+where
+    I: IntoIterator<Item = (N, S)>,
+    N: Into<String>,
+    S: AsRef<str>,
+{
+    parse_batch_observed(inputs, policy, &NoopSubscriber)
+}
+
+/// Like [`parse_batch`], reporting each document's progress to `subscriber`
+///
+/// Every document produces [`Event::DocumentStarted`], then
+/// [`Event::DiagnosticEmitted`] if it failed, then
+/// [`Event::ConversionFinished`]. Under [`FailurePolicy::Abort`] the batch
+/// stops after the failing document's events.
+///
+/// # Errors
+///
+/// Same as [`parse_batch`].
+pub fn parse_batch_observed<I, N, S>(
+    inputs: I,
+    policy: FailurePolicy,
+    subscriber: &dyn Subscriber,
+) -> Result<BatchResult, BatchError>
+where
+    I: IntoIterator<Item = (N, S)>,
+    N: Into<String>,
+    S: AsRef<str>,
+{
+    let mut result = BatchResult::default();
+    for (name, source) in inputs {
+        let name = name.into();
+        let parsed = parse_observed(&name, source.as_ref(), subscriber);
+        result.record(name, parsed, policy)?;
+    }
+    Ok(result)
+}
+
+/// Parse one batch document, reporting its events to `subscriber`
+pub(crate) fn parse_observed(
+    name: &str,
+    source: &str,
+    subscriber: &dyn Subscriber,
+) -> Result<Document, String> {
+    subscriber.on_event(&Event::DocumentStarted { document: name });
+    let started = Instant::now();
+    let parsed = parse_guarded(source);
+
+    if let Err(message) = &parsed {
+        subscriber.on_event(&Event::DiagnosticEmitted {
+            document: name,
+            message,
+        });
+    }
+    subscriber.on_event(&Event::ConversionFinished {
+        document: name,
+        elapsed: started.elapsed(),
+        succeeded: parsed.is_ok(),
+    });
+    parsed
+}
+
+impl BatchResult {
+    /// Add the outcome of parsing the document `name`, applying `policy` to
+    /// a failure
+    pub(crate) fn record(
+        &mut self,
+        name: String,
+        parsed: Result<Document, String>,
+        policy: FailurePolicy,
+    ) -> Result<(), BatchError> {
+        let message = match parsed {
+            Ok(document) => {
+                self.documents.push(BatchDocument {
+                    name,
+                    document,
+                    stub: false,
+                });
+                return Ok(());
+            }
+            Err(message) => message,
+        };
+
+        match policy {
+            FailurePolicy::Abort => {
+                return Err(BatchError::Aborted {
+                    document: name,
+                    message,
+                });
+            }
+            FailurePolicy::Skip => {}
+            FailurePolicy::Stub => self.documents.push(BatchDocument {
+                name: name.clone(),
+                document: stub_document(&name, &message),
+                stub: true,
+            }),
+        }
+        self.diagnostics.push(BatchDiagnostic {
+            document: name,
+            message,
+        });
+        Ok(())
+    }
+}
+
+/// Parse `source`, turning parser panics into errors
+fn parse_guarded(source: &str) -> Result<Document, String> {
+    guarded(|| parse_document(source).map_err(|err| err.to_string()))
+}
+
+/// Run `parse`, turning parser panics into errors
+pub(crate) fn guarded(
+    parse: impl FnOnce() -> Result<Document, String>,
+) -> Result<Document, String> {
+    panic::catch_unwind(AssertUnwindSafe(parse)).unwrap_or_else(|payload| {
+        let reason = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Err(format!("parser panicked: {}", reason))
+    })
+}
+
+/// Build the placeholder document emitted for a failed document
+fn stub_document(name: &str, message: &str) -> Document {
+    Document::with_blocks(vec![Block::Section {
+        level: 1,
+        title: vec![Inline::Text(name.to_string())],
+        content: vec![Block::Paragraph {
+            content: vec![Inline::Text(format!(
+                "This document could not be converted: {}",
+                message
+            ))],
+            metadata: BlockMetadata::default(),
+        }],
+        anchor: None,
+        metadata: BlockMetadata::default(),
+    }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUTS: [(&str, &str); 3] = [
+        ("a.adoc", "First."),
+        ("bad.adoc", "= No newline"),
+        ("c.adoc", "Third."),
+    ];
+
+    #[test]
+    fn test_abort_stops_at_first_failure() {
+        let err = parse_batch(INPUTS, FailurePolicy::Abort).unwrap_err();
+        let BatchError::Aborted { document, .. } = &err;
+        assert_eq!(document, "bad.adoc");
+        assert!(err.to_string().starts_with("batch aborted at bad.adoc"));
+    }
+
+    #[test]
+    fn test_skip_drops_failed_documents() {
+        let result = parse_batch(INPUTS, FailurePolicy::Skip).unwrap();
+        let names: Vec<&str> = result.documents.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["a.adoc", "c.adoc"]);
+        assert_eq!(result.diagnostics.len(), 1);
+        assert!(!result.is_clean());
+    }
+
+    #[test]
+    fn test_stub_replaces_failed_documents() {
+        let result = parse_batch(INPUTS, FailurePolicy::Stub).unwrap();
+        assert_eq!(result.documents.len(), 3);
+
+        let stub = &result.documents[1];
+        assert!(stub.stub);
+        let Block::Section { title, content, .. } = &stub.document.blocks[0] else {
+            panic!("Expected Section");
+        };
+        assert_eq!(title, &vec![Inline::Text("bad.adoc".to_string())]);
+        assert_eq!(content.len(), 1);
+        assert_eq!(result.diagnostics[0].document, "bad.adoc");
+    }
+
+    #[test]
+    fn test_observed_events() {
+        use std::sync::Mutex;
+
+        let log = Mutex::new(Vec::new());
+        let subscriber = |event: &Event<'_>| {
+            let entry = match event {
+                Event::DocumentStarted { document } => format!("start {}", document),
+                Event::DiagnosticEmitted { document, .. } => format!("diagnostic {}", document),
+                Event::ConversionFinished {
+                    document,
+                    succeeded,
+                    ..
+                } => format!("finish {} {}", document, succeeded),
+                _ => "other".to_string(),
+            };
+            log.lock().unwrap().push(entry);
+        };
+
+        let _ = parse_batch_observed(INPUTS, FailurePolicy::Abort, &subscriber);
+        assert_eq!(
+            log.into_inner().unwrap(),
+            vec![
+                "start a.adoc",
+                "finish a.adoc true",
+                "start bad.adoc",
+                "diagnostic bad.adoc",
+                "finish bad.adoc false",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clean_batch() {
+        let result = parse_batch(vec![("x", "Fine.".to_string())], FailurePolicy::Abort).unwrap();
+        assert!(result.is_clean());
+        assert!(!result.documents[0].stub);
+    }
+}
@@ -0,0 +1,298 @@
+//! Clock abstraction for date and time attributes
+//!
+//! The built-in attributes `localdate`, `localtime`, `docdate`, and friends are
+//! derived from the current time. To keep tests and reproducible builds stable,
+//! the time source is a [`Clock`] injected through
+//! [`ParseOptions::clock`](crate::options::ParseOptions::clock).
+//!
+//! - [`SystemClock`] (the default) reads the system time in UTC, or the
+//!   `SOURCE_DATE_EPOCH` environment variable when it is set, following the
+//!   reproducible-builds convention.
+//! - [`FixedClock`] always returns the same [`Timestamp`].
+//!
+//! # Formatting
+//!
+//! [`Timestamp::format`] supports a small `strftime` subset:
+//!
+//! | Directive | Meaning                               |
+//! |-----------|---------------------------------------|
+//! | `%Y`      | Year (`2025`)                         |
+//! | `%m`      | Month, zero padded (`01`-`12`)        |
+//! | `%d`      | Day of month, zero padded (`01`-`31`) |
+//! | `%H`      | Hour, zero padded (`00`-`23`)         |
+//! | `%M`      | Minute, zero padded                   |
+//! | `%S`      | Second, zero padded                   |
+//! | `%z`      | UTC offset (`+0000`, `-0530`)         |
+//! | `%Z`      | `UTC` for offset zero, else as `%z`   |
+//! | `%%`      | A literal `%`                         |
+//!
+//! Unknown directives are copied to the output unchanged. The patterns used for
+//! the built-in attributes come from the `date-format` (default `%Y-%m-%d`) and
+//! `time-format` (default `%H:%M:%S %Z`) attributes, whether passed as options
+//! or set in the document header.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::clock::Timestamp;
+//!
+//! // 2024-02-29 12:30:05 UTC
+//! let ts = Timestamp::from_unix(1_709_209_805);
+//! assert_eq!(ts.format("%Y-%m-%d %H:%M:%S %Z"), "2024-02-29 12:30:05 UTC");
+//! ```
+
+use crate::attributes::Attributes;
+use std::fmt::Debug;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A point in time with the UTC offset it should be displayed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timestamp {
+    /// Seconds since the Unix epoch (UTC)
+    pub unix_seconds: i64,
+    /// Offset from UTC in minutes, applied when formatting
+    pub utc_offset_minutes: i32,
+}
+
+impl Timestamp {
+    /// Creates a UTC timestamp from seconds since the Unix epoch
+    pub fn from_unix(unix_seconds: i64) -> Self {
+        Self {
+            unix_seconds,
+            utc_offset_minutes: 0,
+        }
+    }
+
+    /// Returns the same instant, displayed with the given UTC offset
+    pub fn with_offset(self, utc_offset_minutes: i32) -> Self {
+        Self {
+            utc_offset_minutes,
+            ..self
+        }
+    }
+
+    /// Formats the timestamp using the `strftime` subset described in the
+    /// [module documentation](self)
+    pub fn format(&self, pattern: &str) -> String {
+        let local = self.unix_seconds + i64::from(self.utc_offset_minutes) * 60;
+        let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+        let seconds_of_day = local.rem_euclid(86_400);
+        let (hour, minute, second) = (
+            seconds_of_day / 3600,
+            seconds_of_day % 3600 / 60,
+            seconds_of_day % 60,
+        );
+
+        let mut out = String::with_capacity(pattern.len() + 16);
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('Y') => out.push_str(&year.to_string()),
+                Some('m') => out.push_str(&format!("{:02}", month)),
+                Some('d') => out.push_str(&format!("{:02}", day)),
+                Some('H') => out.push_str(&format!("{:02}", hour)),
+                Some('M') => out.push_str(&format!("{:02}", minute)),
+                Some('S') => out.push_str(&format!("{:02}", second)),
+                Some('z') => out.push_str(&self.offset_string()),
+                Some('Z') if self.utc_offset_minutes == 0 => out.push_str("UTC"),
+                Some('Z') => out.push_str(&self.offset_string()),
+                Some('%') => out.push('%'),
+                Some(other) => {
+                    out.push('%');
+                    out.push(other);
+                }
+                None => out.push('%'),
+            }
+        }
+        out
+    }
+
+    /// UTC offset as `+HHMM` / `-HHMM`
+    fn offset_string(&self) -> String {
+        let sign = if self.utc_offset_minutes < 0 {
+            '-'
+        } else {
+            '+'
+        };
+        let minutes = self.utc_offset_minutes.unsigned_abs();
+        format!("{}{:02}{:02}", sign, minutes / 60, minutes % 60)
+    }
+}
+
+/// Source of the current time
+pub trait Clock: Debug + Send + Sync {
+    /// Returns the current time
+    fn now(&self) -> Timestamp;
+}
+
+/// The system clock, in UTC
+///
+/// If `SOURCE_DATE_EPOCH` is set to an integer, that value is used instead so
+/// builds are reproducible without code changes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        if let Some(epoch) = std::env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|value| value.trim().parse::<i64>().ok())
+        {
+            return Timestamp::from_unix(epoch);
+        }
+
+        let seconds = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_secs() as i64,
+            Err(before) => -(before.duration().as_secs() as i64),
+        };
+        Timestamp::from_unix(seconds)
+    }
+}
+
+/// A clock that always returns the same timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub Timestamp);
+
+impl Clock for FixedClock {
+    fn now(&self) -> Timestamp {
+        self.0
+    }
+}
+
+/// Default `strftime` pattern for the date attributes
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Default `strftime` pattern for the time attributes
+pub const DEFAULT_TIME_FORMAT: &str = "%H:%M:%S %Z";
+
+/// Define the built-in date and time attributes from `now`
+///
+/// Sets `localdate`, `localtime`, `localdatetime`, and `localyear`, plus the
+/// `doc*` equivalents. Without a source file, the document date is the parse
+/// time. The patterns come from the `date-format` and `time-format` attributes
+/// in `formats` when defined there. Attributes that are already defined are
+/// left untouched, so callers can pin any of them directly.
+pub(crate) fn insert_date_attributes(
+    attributes: &mut Attributes,
+    now: Timestamp,
+    formats: &Attributes,
+) {
+    let date_format = formats.get("date-format").unwrap_or(DEFAULT_DATE_FORMAT);
+    let time_format = formats.get("time-format").unwrap_or(DEFAULT_TIME_FORMAT);
+
+    let date = now.format(date_format);
+    let time = now.format(time_format);
+    let datetime = format!("{} {}", date, time);
+    let year = now.format("%Y");
+
+    for prefix in ["local", "doc"] {
+        for (suffix, value) in [
+            ("date", &date),
+            ("time", &time),
+            ("datetime", &datetime),
+            ("year", &year),
+        ] {
+            let name = format!("{}{}", prefix, suffix);
+            if !attributes.contains(&name) {
+                attributes.set(name, value.as_str());
+            }
+        }
+    }
+}
+
+/// Convert days since 1970-01-01 to a proleptic Gregorian (year, month, day)
+///
+/// Based on Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_epoch() {
+        let ts = Timestamp::from_unix(0);
+        assert_eq!(
+            ts.format("%Y-%m-%d %H:%M:%S %z"),
+            "1970-01-01 00:00:00 +0000"
+        );
+    }
+
+    #[test]
+    fn test_leap_day_and_century() {
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(
+            Timestamp::from_unix(951_782_400).format("%Y-%m-%d"),
+            "2000-02-29"
+        );
+    }
+
+    #[test]
+    fn test_offset_applied() {
+        // 2025-01-01 00:30 UTC is still 2024-12-31 in UTC-5
+        let ts = Timestamp::from_unix(1_735_691_400).with_offset(-300);
+        assert_eq!(ts.format("%Y-%m-%d %H:%M %z"), "2024-12-31 19:30 -0500");
+        assert_eq!(ts.format("%Z"), "-0500");
+
+        let ts = Timestamp::from_unix(0).with_offset(330);
+        assert_eq!(ts.format("%H:%M %z"), "05:30 +0530");
+    }
+
+    #[test]
+    fn test_literal_and_unknown_directives() {
+        let ts = Timestamp::from_unix(0);
+        assert_eq!(ts.format("100%% at %Q%"), "100% at %Q%");
+    }
+
+    #[test]
+    fn test_date_attributes() {
+        let mut attrs = Attributes::new();
+        insert_date_attributes(
+            &mut attrs,
+            Timestamp::from_unix(1_709_209_805),
+            &Attributes::new(),
+        );
+
+        assert_eq!(attrs.get("localdate"), Some("2024-02-29"));
+        assert_eq!(attrs.get("localtime"), Some("12:30:05 UTC"));
+        assert_eq!(attrs.get("docdatetime"), Some("2024-02-29 12:30:05 UTC"));
+        assert_eq!(attrs.get("docyear"), Some("2024"));
+    }
+
+    #[test]
+    fn test_date_attributes_respect_formats_and_overrides() {
+        let mut attrs = Attributes::new();
+        attrs.set("date-format", "%d.%m.%Y");
+        attrs.set("time-format", "%H:%M");
+        attrs.set("docdate", "pinned");
+        let formats = attrs.clone();
+        insert_date_attributes(&mut attrs, Timestamp::from_unix(1_709_209_805), &formats);
+
+        assert_eq!(attrs.get("localdate"), Some("29.02.2024"));
+        assert_eq!(attrs.get("localtime"), Some("12:30"));
+        assert_eq!(attrs.get("docdate"), Some("pinned"));
+    }
+
+    #[test]
+    fn test_fixed_clock() {
+        let ts = Timestamp::from_unix(42);
+        assert_eq!(FixedClock(ts).now(), ts);
+    }
+}
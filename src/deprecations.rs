@@ -0,0 +1,383 @@
+//! Deprecated syntax diagnostics and migration to modern syntax
+//!
+//! AsciiDoc has accumulated syntax that still works but has a modern
+//! replacement. [`find_deprecations`] reports each use with the text that
+//! replaces it, [`report_deprecations`] sends them to a
+//! [`Subscriber`] as [`Event::DeprecatedSyntax`] so build systems can route
+//! them into their own logging, and [`fix_deprecations`] applies every
+//! replacement, migrating a document in one step.
+//!
+//! | Kind                                       | Legacy syntax                        | Replacement                  |
+//! |--------------------------------------------|--------------------------------------|------------------------------|
+//! | [`DeprecationKind::RenamedAttribute`]      | `:numbered:`, `:toc2:`               | `:sectnums:`, `:toc: left`   |
+//! | [`DeprecationKind::ObsoleteAttribute`]     | `:linkattrs:` (always on now)        | the entry is removed         |
+//! | [`DeprecationKind::ExtensionlessXref`]     | `<<other#id>>`, `xref:other#id[]`    | `<<other.adoc#id>>`          |
+//! | [`DeprecationKind::LegacyQuotes`]          | ` ``curved'' ` quotes (compat mode)  | `` "`curved`" ``             |
+//! | [`DeprecationKind::LegacyPassthrough`]     | `$$text$$`                           | `+++text+++`                 |
+//!
+//! Like [`crate::audit`], the scan works on the source text, so it also
+//! covers syntax the parser does not model. Verbatim blocks and line
+//! comments are skipped.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::deprecations::{DeprecationKind, find_deprecations, fix_deprecations};
+//!
+//! let source = "= Guide\n:numbered:\n\nSee <<setup#install>>.\n";
+//! let found = find_deprecations(source);
+//! assert_eq!(found[0].kind, DeprecationKind::RenamedAttribute);
+//! assert_eq!(found[1].replacement, "setup.adoc");
+//!
+//! assert_eq!(
+//!     fix_deprecations(source),
+//!     "= Guide\n:sectnums:\n\nSee <<setup.adoc#install>>.\n"
+//! );
+//! ```
+
+use crate::events::{Event, Subscriber};
+use crate::include::is_verbatim_delimiter;
+use crate::parser_winnow::attribute_entry;
+use crate::token::{LineIndex, Span};
+use std::fmt;
+
+/// Category of a deprecated construct
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeprecationKind {
+    /// An attribute entry for an attribute that was renamed
+    RenamedAttribute,
+    /// An attribute entry for an attribute that no longer has any effect
+    ObsoleteAttribute,
+    /// A cross reference to another document without its `.adoc` extension
+    ExtensionlessXref,
+    /// Compat-mode curved quotes (` ``text'' `)
+    LegacyQuotes,
+    /// A `$$` inline passthrough
+    LegacyPassthrough,
+}
+
+impl DeprecationKind {
+    /// Short identifier used in reports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeprecationKind::RenamedAttribute => "renamed-attribute",
+            DeprecationKind::ObsoleteAttribute => "obsolete-attribute",
+            DeprecationKind::ExtensionlessXref => "extensionless-xref",
+            DeprecationKind::LegacyQuotes => "legacy-quotes",
+            DeprecationKind::LegacyPassthrough => "legacy-passthrough",
+        }
+    }
+}
+
+/// A use of deprecated syntax, with its modern replacement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deprecation {
+    /// Category of the construct
+    pub kind: DeprecationKind,
+    /// 1-based line of the construct
+    pub line: usize,
+    /// 1-based column (in characters) of the construct
+    pub column: usize,
+    /// Byte range of the text to replace
+    pub span: Span,
+    /// Text that replaces `span`; empty to remove it
+    pub replacement: String,
+    /// Explanation for the author
+    pub message: String,
+}
+
+impl fmt::Display for Deprecation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line,
+            self.column,
+            self.kind.as_str(),
+            self.message
+        )
+    }
+}
+
+/// Renamed attributes: old name, new name, and the value that keeps the old
+/// meaning when the entry has none
+const RENAMED: [(&str, &str, &str); 2] = [("numbered", "sectnums", ""), ("toc2", "toc", "left")];
+
+/// Attributes whose behavior became the default
+const OBSOLETE: [&str; 1] = ["linkattrs"];
+
+/// Find deprecated syntax in `source`, in source order
+pub fn find_deprecations(source: &str) -> Vec<Deprecation> {
+    let index = LineIndex::new(source);
+    let mut found: Vec<(Span, DeprecationKind, String, String)> = Vec::new();
+
+    let mut offset = 0;
+    let mut fence: Option<&str> = None;
+    for line in source.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        let start = offset;
+        offset += line.len();
+        if let Some(delimiter) = fence {
+            if text.trim_end() == delimiter {
+                fence = None;
+            }
+            continue;
+        }
+        if is_verbatim_delimiter(text.trim_end()) {
+            fence = Some(text.trim_end());
+            continue;
+        }
+        if text.starts_with("//") {
+            continue;
+        }
+        if let Some(entry) = attribute_entry(text) {
+            if let Some(&(_, new, default)) = RENAMED.iter().find(|(old, ..)| *old == entry.name) {
+                let replacement = match entry.value.as_deref() {
+                    None => format!(":{new}!:"),
+                    Some("") if default.is_empty() => format!(":{new}:"),
+                    Some("") => format!(":{new}: {default}"),
+                    Some(value) => format!(":{new}: {value}"),
+                };
+                let message = format!("`{}` is deprecated; use `{new}`", entry.name);
+                found.push((
+                    start..start + text.len(),
+                    DeprecationKind::RenamedAttribute,
+                    replacement,
+                    message,
+                ));
+            } else if OBSOLETE.contains(&entry.name.as_str()) {
+                let message = format!("`{}` no longer has any effect", entry.name);
+                found.push((
+                    start..offset,
+                    DeprecationKind::ObsoleteAttribute,
+                    String::new(),
+                    message,
+                ));
+            }
+            continue;
+        }
+        scan_line(text, start, &mut found);
+    }
+
+    found.sort_by_key(|(span, ..)| span.start);
+    found
+        .into_iter()
+        .map(|(span, kind, replacement, message)| {
+            let (line, column) = index.line_col(span.start);
+            Deprecation {
+                kind,
+                line,
+                column,
+                span,
+                replacement,
+                message,
+            }
+        })
+        .collect()
+}
+
+/// Find deprecated syntax in `source` and report each use to `subscriber`
+///
+/// `document` names the source in the events. Returns the deprecations, as
+/// [`find_deprecations`] does.
+pub fn report_deprecations(
+    document: &str,
+    source: &str,
+    subscriber: &dyn Subscriber,
+) -> Vec<Deprecation> {
+    let found = find_deprecations(source);
+    for deprecation in &found {
+        subscriber.on_event(&Event::DeprecatedSyntax {
+            document,
+            line: deprecation.line,
+            column: deprecation.column,
+            kind: deprecation.kind.as_str(),
+            message: &deprecation.message,
+            replacement: &deprecation.replacement,
+        });
+    }
+    found
+}
+
+/// Rewrite `source` to modern syntax by applying every replacement
+/// [`find_deprecations`] suggests
+///
+/// Text other than the deprecated constructs is kept byte for byte.
+pub fn fix_deprecations(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut end = 0;
+    for deprecation in find_deprecations(source) {
+        if deprecation.span.start < end {
+            continue;
+        }
+        output.push_str(&source[end..deprecation.span.start]);
+        output.push_str(&deprecation.replacement);
+        end = deprecation.span.end;
+    }
+    output.push_str(&source[end..]);
+    output
+}
+
+/// Collect inline deprecations in one line starting at byte `offset`
+fn scan_line(line: &str, offset: usize, found: &mut Vec<(Span, DeprecationKind, String, String)>) {
+    for (pos, _) in line.match_indices("<<") {
+        let target = line[pos + 2..].split([',', '>']).next().unwrap_or_default();
+        if let Some(document) = extensionless(target) {
+            let start = offset + pos + 2;
+            found.push(xref_fix(start, document));
+        }
+    }
+    for (pos, _) in line.match_indices("xref:") {
+        let target = line[pos + 5..].split('[').next().unwrap_or_default();
+        if let Some(document) = extensionless(target) {
+            let start = offset + pos + 5;
+            found.push(xref_fix(start, document));
+        }
+    }
+
+    let mut from = 0;
+    while let Some(open) = line[from..].find("``").map(|i| from + i) {
+        let rest = &line[open + 2..];
+        let close = rest.find("''");
+        let text = close.map(|close| &rest[..close]);
+        match text {
+            Some(text)
+                if !text.is_empty()
+                    && !text.starts_with(char::is_whitespace)
+                    && !text.contains('`') =>
+            {
+                let end = open + 2 + text.len() + 2;
+                found.push((
+                    offset + open..offset + end,
+                    DeprecationKind::LegacyQuotes,
+                    format!("\"`{text}`\""),
+                    "``text'' quotes need compat mode; use \"`text`\"".to_string(),
+                ));
+                from = end;
+            }
+            _ => from = open + 2,
+        }
+    }
+
+    let mut from = 0;
+    while let Some(open) = line[from..].find("$$").map(|i| from + i) {
+        let Some(close) = line[open + 2..].find("$$") else {
+            break;
+        };
+        let text = &line[open + 2..open + 2 + close];
+        let end = open + 2 + close + 2;
+        if !text.is_empty() && !text.contains("+++") {
+            found.push((
+                offset + open..offset + end,
+                DeprecationKind::LegacyPassthrough,
+                format!("+++{text}+++"),
+                "$$ passthroughs are deprecated; use +++".to_string(),
+            ));
+        }
+        from = end;
+    }
+}
+
+/// The document part of an xref `target` (`other#id`), if it names another
+/// document without an extension
+fn extensionless(target: &str) -> Option<&str> {
+    let (document, _) = target.split_once('#')?;
+    let file = document.rsplit('/').next().unwrap_or(document);
+    let plain = !file.is_empty()
+        && !file.contains('.')
+        && !document.contains(['{', ' ', '<', '>'])
+        && !document.contains("://");
+    plain.then_some(document)
+}
+
+/// The deprecation for an extensionless xref document starting at `start`
+fn xref_fix(start: usize, document: &str) -> (Span, DeprecationKind, String, String) {
+    (
+        start..start + document.len(),
+        DeprecationKind::ExtensionlessXref,
+        format!("{document}.adoc"),
+        format!("reference `{document}` with its extension: `{document}.adoc`"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn kinds(source: &str) -> Vec<(usize, &'static str, String)> {
+        find_deprecations(source)
+            .into_iter()
+            .map(|d| (d.line, d.kind.as_str(), d.replacement))
+            .collect()
+    }
+
+    #[test]
+    fn test_attribute_entries() {
+        let source = "= T\n:numbered:\n:toc2:\n:linkattrs:\n:numbered!:\n:toc2: right\n\nText\n";
+        assert_eq!(
+            kinds(source),
+            vec![
+                (2, "renamed-attribute", ":sectnums:".to_string()),
+                (3, "renamed-attribute", ":toc: left".to_string()),
+                (4, "obsolete-attribute", String::new()),
+                (5, "renamed-attribute", ":sectnums!:".to_string()),
+                (6, "renamed-attribute", ":toc: right".to_string()),
+            ]
+        );
+        assert_eq!(
+            fix_deprecations(source),
+            "= T\n:sectnums:\n:toc: left\n:sectnums!:\n:toc: right\n\nText\n"
+        );
+    }
+
+    #[test]
+    fn test_inline_constructs() {
+        let source = "See <<a/b#c,B>>, xref:other#x[], <<local>>, <<c.adoc#d>>.\n\
+                      A ``quoted'' word, ``mono`` and $$<b>$$.\n\
+                      ----\n<<raw#x>> $$kept$$\n----\n\
+                      // <<comment#x>>\n";
+        assert_eq!(
+            kinds(source),
+            vec![
+                (1, "extensionless-xref", "a/b.adoc".to_string()),
+                (1, "extensionless-xref", "other.adoc".to_string()),
+                (2, "legacy-quotes", "\"`quoted`\"".to_string()),
+                (2, "legacy-passthrough", "+++<b>+++".to_string()),
+            ]
+        );
+        assert_eq!(
+            fix_deprecations(source),
+            "See <<a/b.adoc#c,B>>, xref:other.adoc#x[], <<local>>, <<c.adoc#d>>.\n\
+             A \"`quoted`\" word, ``mono`` and +++<b>+++.\n\
+             ----\n<<raw#x>> $$kept$$\n----\n\
+             // <<comment#x>>\n"
+        );
+        assert!(find_deprecations(&fix_deprecations(source)).is_empty());
+    }
+
+    #[test]
+    fn test_report_to_subscriber() {
+        let log = Mutex::new(Vec::new());
+        let logger = |event: &Event<'_>| {
+            if let Event::DeprecatedSyntax {
+                document,
+                line,
+                kind,
+                ..
+            } = event
+            {
+                log.lock()
+                    .unwrap()
+                    .push(format!("{document}:{line} {kind}"));
+            }
+        };
+        let found = report_deprecations("a.adoc", "Text\n\n:numbered:\n", &logger);
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].to_string(),
+            "3:1: renamed-attribute: `numbered` is deprecated; use `sectnums`"
+        );
+        assert_eq!(*log.lock().unwrap(), ["a.adoc:3 renamed-attribute"]);
+    }
+}
@@ -0,0 +1,421 @@
+//! Documentation coverage of code APIs
+//!
+//! Teams that require "every public API has a doc section" need to know which
+//! symbols the documentation set leaves out. [`check_coverage`] takes a list
+//! of symbol paths (`ast::Block`, `parse_document`), usually exported from
+//! rustdoc JSON with [`symbols_from_rustdoc`], and looks for each one in the
+//! documents of a batch:
+//!
+//! | Coverage                 | The symbol ...                                             |
+//! |--------------------------|------------------------------------------------------------|
+//! | [`Coverage::Anchored`]   | has an anchor with its path as the ID (`[[ast::Block]]`)   |
+//! | [`Coverage::Mentioned`]  | appears as monospace text (`` `ast::Block` ``, `` `Block` ``) |
+//! | [`Coverage::Missing`]    | does neither                                               |
+//!
+//! Anchors are the strong form: they give the symbol a section (or block) of
+//! its own that other pages can link to. A monospace span mentions a symbol
+//! if it is the symbol's path or a `::` suffix of it, optionally followed by
+//! `()`, so `` `Block` `` mentions `ast::Block`. Monospace text in section
+//! titles counts too. Stub documents of failed batch entries are skipped.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::batch::{FailurePolicy, parse_batch};
+//! use doctora::doc_coverage::{Coverage, check_coverage};
+//!
+//! let result = parse_batch(
+//!     vec![(
+//!         "api.adoc".to_string(),
+//!         "[[parse_document]]\n== Parsing\n\nReturns an `ast::Document`.\n".to_string(),
+//!     )],
+//!     FailurePolicy::Abort,
+//! )
+//! .unwrap();
+//!
+//! let report = check_coverage(&["parse_document", "ast::Document", "ast::Block"], &result);
+//! let coverage: Vec<Coverage> = report.symbols.iter().map(|s| s.coverage).collect();
+//! assert_eq!(coverage, [Coverage::Anchored, Coverage::Mentioned, Coverage::Missing]);
+//! assert_eq!(report.gaps().count(), 1);
+//! println!("{}", report);
+//! ```
+
+use crate::ast::{Block, Document, Inline, plain_text};
+use crate::batch::BatchResult;
+use crate::xref::anchors;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+/// How well the documentation covers a symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Coverage {
+    /// Neither anchored nor mentioned anywhere
+    Missing,
+    /// Mentioned as monospace text, but without an anchor of its own
+    Mentioned,
+    /// An anchor's ID is the symbol's path
+    Anchored,
+}
+
+impl Coverage {
+    /// Short identifier used in reports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Coverage::Missing => "missing",
+            Coverage::Mentioned => "mentioned",
+            Coverage::Anchored => "anchored",
+        }
+    }
+}
+
+/// Coverage of one symbol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolCoverage {
+    /// The symbol's path, as given
+    pub symbol: String,
+    /// Strongest coverage found in any document
+    pub coverage: Coverage,
+    /// Documents with that coverage, in batch order
+    pub documents: Vec<String>,
+}
+
+/// Coverage of every symbol checked, in the order given
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverageReport {
+    /// One entry per symbol
+    pub symbols: Vec<SymbolCoverage>,
+}
+
+impl CoverageReport {
+    /// Symbols the documentation does not cover
+    pub fn gaps(&self) -> impl Iterator<Item = &SymbolCoverage> {
+        self.symbols
+            .iter()
+            .filter(|symbol| symbol.coverage == Coverage::Missing)
+    }
+
+    /// Number of symbols with `coverage`
+    pub fn count(&self, coverage: Coverage) -> usize {
+        self.symbols
+            .iter()
+            .filter(|symbol| symbol.coverage == coverage)
+            .count()
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Documentation coverage: {} of {} symbols ({} anchored, {} mentioned)",
+            self.symbols.len() - self.count(Coverage::Missing),
+            self.symbols.len(),
+            self.count(Coverage::Anchored),
+            self.count(Coverage::Mentioned),
+        )?;
+        for symbol in &self.symbols {
+            write!(f, "  {:<10} {}", symbol.coverage.as_str(), symbol.symbol)?;
+            if !symbol.documents.is_empty() {
+                write!(f, " ({})", symbol.documents.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check which of `symbols` the documents of `result` anchor or mention
+pub fn check_coverage<S: AsRef<str>>(symbols: &[S], result: &BatchResult) -> CoverageReport {
+    // Per document: anchor IDs and monospace spans, each with `()` removed
+    let documents: Vec<(&str, BTreeSet<String>, BTreeSet<String>)> = result
+        .documents
+        .iter()
+        .filter(|document| !document.stub)
+        .map(|document| {
+            let ids = anchors(&document.document)
+                .into_iter()
+                .map(|anchor| anchor.id)
+                .collect();
+            (document.name.as_str(), ids, code_spans(&document.document))
+        })
+        .collect();
+
+    let mut cache: HashMap<&str, SymbolCoverage> = HashMap::new();
+    let symbols = symbols
+        .iter()
+        .map(|symbol| {
+            let symbol = symbol.as_ref();
+            cache
+                .entry(symbol)
+                .or_insert_with(|| {
+                    let mut coverage = Coverage::Missing;
+                    let mut found = Vec::new();
+                    for (name, ids, spans) in &documents {
+                        let here = if ids.contains(symbol) {
+                            Coverage::Anchored
+                        } else if suffixes(symbol).any(|suffix| spans.contains(suffix)) {
+                            Coverage::Mentioned
+                        } else {
+                            continue;
+                        };
+                        if here > coverage {
+                            coverage = here;
+                            found.clear();
+                        }
+                        if here == coverage {
+                            found.push(name.to_string());
+                        }
+                    }
+                    SymbolCoverage {
+                        symbol: symbol.to_string(),
+                        coverage,
+                        documents: found,
+                    }
+                })
+                .clone()
+        })
+        .collect();
+    CoverageReport { symbols }
+}
+
+/// Public item paths of the documented crate in rustdoc JSON output
+/// (`cargo rustdoc -- -Z unstable-options --output-format json`)
+///
+/// Paths are relative to the crate (`ast::Block`), sorted, and cover the
+/// items listed under `paths`: modules, types, traits, functions, constants
+/// and macros, but not methods or fields. Items of other crates and the
+/// crate root itself are left out.
+pub fn symbols_from_rustdoc(json: &Value) -> Vec<String> {
+    let Some(paths) = json["paths"].as_object() else {
+        return Vec::new();
+    };
+    let symbols: BTreeSet<String> = paths
+        .iter()
+        .filter(|(id, summary)| {
+            summary["crate_id"] == 0
+                && json["index"]
+                    .get(id.as_str())
+                    .is_none_or(|item| item["visibility"] == "public")
+        })
+        .filter_map(|(_, summary)| {
+            let segments: Vec<&str> = summary["path"]
+                .as_array()?
+                .iter()
+                .map(Value::as_str)
+                .collect::<Option<_>>()?;
+            (segments.len() > 1).then(|| segments[1..].join("::"))
+        })
+        .collect();
+    symbols.into_iter().collect()
+}
+
+/// The symbol itself and its shorter `::` suffixes (`a::b::C`, `b::C`, `C`)
+fn suffixes(symbol: &str) -> impl Iterator<Item = &str> {
+    std::iter::once(symbol).chain(
+        symbol
+            .match_indices("::")
+            .map(move |(i, _)| &symbol[i + 2..]),
+    )
+}
+
+/// Monospace text throughout `doc`, trimmed and without a trailing `()`
+fn code_spans(doc: &Document) -> BTreeSet<String> {
+    let mut spans = BTreeSet::new();
+    blocks(&doc.blocks, &mut spans);
+    spans
+}
+
+fn insert_span(text: &str, spans: &mut BTreeSet<String>) {
+    let text = text.trim();
+    let text = text.strip_suffix("()").unwrap_or(text);
+    if !text.is_empty() {
+        spans.insert(text.to_string());
+    }
+}
+
+fn blocks(content: &[Block], spans: &mut BTreeSet<String>) {
+    for block in content {
+        match block {
+            Block::Section { title, content, .. } => {
+                inlines(title, spans);
+                blocks(content, spans);
+            }
+            Block::Paragraph { content, .. } | Block::Verse { content, .. } => {
+                inlines(content, spans)
+            }
+            Block::UnorderedList { items, .. } | Block::OrderedList { items, .. } => {
+                for item in items {
+                    inlines(&item.content, spans);
+                    blocks(&item.blocks, spans);
+                }
+            }
+            Block::DescriptionList { items, .. } => {
+                for item in items {
+                    inlines(&item.term, spans);
+                    inlines(&item.description, spans);
+                }
+            }
+            Block::Table { header, rows, .. } => {
+                for row in header.iter().chain(rows) {
+                    for cell in &row.cells {
+                        inlines(&cell.content, spans);
+                    }
+                }
+            }
+            Block::Quote { content, .. }
+            | Block::Admonition { content, .. }
+            | Block::Example { content, .. }
+            | Block::Sidebar { content, .. }
+            | Block::Open { content, .. } => blocks(content, spans),
+            Block::Listing { .. }
+            | Block::Image { .. }
+            | Block::Passthrough { .. }
+            | Block::Comment { .. }
+            | Block::AttributeEntry { .. }
+            | Block::Unknown { .. } => {}
+        }
+    }
+}
+
+fn inlines(content: &[Inline], spans: &mut BTreeSet<String>) {
+    for inline in content {
+        match inline {
+            Inline::Monospace(nested) => insert_span(&plain_text(nested), spans),
+            Inline::Bold(nested)
+            | Inline::Italic(nested)
+            | Inline::Highlight(nested)
+            | Inline::Span {
+                content: nested, ..
+            }
+            | Inline::Superscript(nested)
+            | Inline::Subscript(nested)
+            | Inline::Link { text: nested, .. }
+            | Inline::XRef { text: nested, .. }
+            | Inline::Footnote { text: nested, .. } => inlines(nested, spans),
+            Inline::Text(_)
+            | Inline::Anchor(_)
+            | Inline::Image { .. }
+            | Inline::Icon { .. }
+            | Inline::BreakHint(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::{FailurePolicy, parse_batch};
+    use serde_json::json;
+
+    fn batch(documents: &[(&str, &str)]) -> BatchResult {
+        let documents: Vec<(String, String)> = documents
+            .iter()
+            .map(|(name, source)| (name.to_string(), source.to_string()))
+            .collect();
+        parse_batch(documents, FailurePolicy::Abort).unwrap()
+    }
+
+    #[test]
+    fn test_coverage_levels() {
+        let result = batch(&[
+            (
+                "guide.adoc",
+                "== Using `Engine`\n\n* Call `parse_document()` first.\n\n[[render::html]]\nSee <<render::html>>.\n",
+            ),
+            (
+                "api.adoc",
+                "[#engine::Engine]\n== Engine\n\n|===\n|`Limits`\n|===\n",
+            ),
+        ]);
+        let report = check_coverage(
+            &[
+                "engine::Engine",
+                "parse_document",
+                "limits::Limits",
+                "render::html",
+                "token::Token",
+                "parse_document",
+            ],
+            &result,
+        );
+        let summary: Vec<(&str, Coverage, Vec<String>)> = report
+            .symbols
+            .iter()
+            .map(|s| (s.symbol.as_str(), s.coverage, s.documents.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "engine::Engine",
+                    Coverage::Anchored,
+                    vec!["api.adoc".to_string()]
+                ),
+                (
+                    "parse_document",
+                    Coverage::Mentioned,
+                    vec!["guide.adoc".to_string()]
+                ),
+                (
+                    "limits::Limits",
+                    Coverage::Mentioned,
+                    vec!["api.adoc".to_string()]
+                ),
+                (
+                    "render::html",
+                    Coverage::Anchored,
+                    vec!["guide.adoc".to_string()]
+                ),
+                ("token::Token", Coverage::Missing, vec![]),
+                (
+                    "parse_document",
+                    Coverage::Mentioned,
+                    vec!["guide.adoc".to_string()]
+                ),
+            ]
+        );
+        assert_eq!(
+            report.gaps().map(|s| s.symbol.as_str()).collect::<Vec<_>>(),
+            ["token::Token"]
+        );
+        assert!(
+            report
+                .to_string()
+                .starts_with("Documentation coverage: 5 of 6 symbols (2 anchored, 3 mentioned)\n")
+        );
+    }
+
+    #[test]
+    fn test_mentions_need_a_path_suffix() {
+        let result = batch(&[("a.adoc", "The `Block` and `st::Token` types.\n")]);
+        let report = check_coverage(&["ast::Block", "token::Token", "ast::Blocks"], &result);
+        let coverage: Vec<Coverage> = report.symbols.iter().map(|s| s.coverage).collect();
+        assert_eq!(
+            coverage,
+            [Coverage::Mentioned, Coverage::Missing, Coverage::Missing]
+        );
+    }
+
+    #[test]
+    fn test_symbols_from_rustdoc() {
+        let json = json!({
+            "index": {
+                "1": { "visibility": "public" },
+                "3": { "visibility": "crate" },
+            },
+            "paths": {
+                "0": { "crate_id": 0, "path": ["doctora"], "kind": "module" },
+                "1": { "crate_id": 0, "path": ["doctora", "ast", "Block"], "kind": "enum" },
+                "2": { "crate_id": 0, "path": ["doctora", "parse_document"], "kind": "function" },
+                "3": { "crate_id": 0, "path": ["doctora", "private"], "kind": "module" },
+                "4": { "crate_id": 1, "path": ["std", "string", "String"], "kind": "struct" },
+            },
+        });
+        assert_eq!(
+            symbols_from_rustdoc(&json),
+            ["ast::Block", "parse_document"]
+        );
+        assert!(symbols_from_rustdoc(&json!({})).is_empty());
+    }
+}
@@ -0,0 +1,375 @@
+//! Shared engine for long-running services
+//!
+//! A web service converting documents per request should not rebuild the
+//! [`ProcessorRegistry`] (and its role templates and asset hooks) for every
+//! request, and one request must never see state left behind by another.
+//! [`DoctoraEngine`] owns the registry, the default options, and an optional
+//! parse cache. It is `Send + Sync`: build it once, share it (e.g. in an
+//! `Arc`), and call [`DoctoraEngine::parse`] or [`DoctoraEngine::convert`]
+//! from any thread.
+//!
+//! Configuration is fixed once the engine is built. Per-request settings go in
+//! a [`Request`], which overrides the engine defaults for that call only:
+//!
+//! ```
+//! use doctora::engine::{DoctoraEngine, Request};
+//! use doctora::options::ParseOptions;
+//! use doctora::processor::Output;
+//!
+//! let engine = DoctoraEngine::new()
+//!     .parse_options(ParseOptions::new().attribute("product", "Doctor A"));
+//!
+//! let html = engine.convert("Hello.", "html", &Request::new()).unwrap();
+//! assert_eq!(html, Output::Text("<p>Hello.</p>\n".to_string()));
+//!
+//! let request = Request::new().parse_options(ParseOptions::new().attribute("product", "Other"));
+//! let doc = engine.parse("Hello.", &request).unwrap();
+//! assert_eq!(doc.attributes.get("product"), Some("Other"));
+//! assert_eq!(engine.parse("Hello.", &Request::new()).unwrap().attributes.get("product"), Some("Doctor A"));
+//! ```
+//!
+//! # Parse Cache
+//!
+//! With [`DoctoraEngine::cache_capacity`], documents parsed with the engine's
+//! default parse options are cached by source text, evicting the oldest entry
+//! when full. Requests with their own parse options bypass the cache. Cached
+//! documents keep the date attributes (`localdate`, ...) of their first parse,
+//! so inject a fixed [`Clock`](crate::clock::Clock) when that matters.
+
+use crate::ast::Document;
+use crate::error_recovery::ParseError;
+use crate::options::ParseOptions;
+use crate::parse_document_with_options;
+use crate::processor::{Output, Processor, ProcessorError, ProcessorRegistry};
+use crate::render::html::{HtmlOptions, HtmlProcessor, render_html};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use thiserror::Error;
+
+/// Error returned by [`DoctoraEngine::convert`]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EngineError {
+    /// The source could not be parsed
+    #[error("parse failed: {0}")]
+    Parse(ParseError),
+    /// The document could not be converted
+    #[error(transparent)]
+    Processor(#[from] ProcessorError),
+}
+
+/// Per-request settings overriding the engine defaults
+#[derive(Debug, Clone, Default)]
+pub struct Request {
+    /// Parse options replacing the engine's, if set
+    parse: Option<ParseOptions>,
+    /// HTML options replacing the engine's, if set
+    html: Option<HtmlOptions>,
+}
+
+impl Request {
+    /// Creates a request that uses the engine defaults
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses this request's source with `options`
+    pub fn parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse = Some(options);
+        self
+    }
+
+    /// Renders this request's HTML with `options`
+    ///
+    /// Only applies while the built-in `html` processor handles the format.
+    pub fn html_options(mut self, options: HtmlOptions) -> Self {
+        self.html = Some(options);
+        self
+    }
+}
+
+/// Parser and processors shared across requests
+#[derive(Debug, Default)]
+pub struct DoctoraEngine {
+    /// Processors selected by format
+    registry: ProcessorRegistry,
+    /// Parse options used when a request has none
+    parse_options: ParseOptions,
+    /// HTML options used when a request has none
+    html_options: HtmlOptions,
+    /// Documents parsed with `parse_options`, if caching is enabled
+    cache: Option<Mutex<ParseCache>>,
+}
+
+impl DoctoraEngine {
+    /// Creates an engine with the built-in processors and default options
+    pub fn new() -> Self {
+        Self {
+            registry: ProcessorRegistry::with_builtins(),
+            ..Self::default()
+        }
+    }
+
+    /// Adds `processor` to the engine's registry (see
+    /// [`ProcessorRegistry::register`])
+    pub fn processor(mut self, processor: Box<dyn Processor>) -> Self {
+        self.registry.register(processor);
+        self
+    }
+
+    /// Sets the parse options used by requests without their own
+    pub fn parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse_options = options;
+        self.clear_cache();
+        self
+    }
+
+    /// Sets the HTML options used by requests without their own
+    pub fn html_options(mut self, options: HtmlOptions) -> Self {
+        self.registry.register(Box::new(HtmlProcessor {
+            options: options.clone(),
+        }));
+        self.html_options = options;
+        self
+    }
+
+    /// Caches up to `capacity` parsed documents (0 disables the cache)
+    pub fn cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache = (capacity > 0).then(|| Mutex::new(ParseCache::new(capacity)));
+        self
+    }
+
+    /// The engine's processor registry
+    pub fn registry(&self) -> &ProcessorRegistry {
+        &self.registry
+    }
+
+    /// Parse `source` with the request's parse options, or the engine's
+    ///
+    /// # Errors
+    ///
+    /// Same as [`parse_document_with_options`].
+    pub fn parse(&self, source: &str, request: &Request) -> Result<Document, ParseError> {
+        if let Some(options) = &request.parse {
+            return parse_document_with_options(source, options);
+        }
+        let Some(cache) = &self.cache else {
+            return parse_document_with_options(source, &self.parse_options);
+        };
+        if let Some(doc) = lock(cache).get(source) {
+            return Ok(doc);
+        }
+        // Parse outside the lock so one slow document doesn't block others
+        let doc = parse_document_with_options(source, &self.parse_options)?;
+        lock(cache).insert(source, doc.clone());
+        Ok(doc)
+    }
+
+    /// Parse `source` and convert it to `format`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EngineError::Parse`] if `source` fails to parse, or the
+    /// processor's error.
+    pub fn convert(
+        &self,
+        source: &str,
+        format: &str,
+        request: &Request,
+    ) -> Result<Output, EngineError> {
+        let doc = self.parse(source, request).map_err(EngineError::Parse)?;
+        Ok(self.convert_document(&doc, format, request)?)
+    }
+
+    /// Convert an already parsed `doc` to `format`
+    ///
+    /// # Errors
+    ///
+    /// Same as [`ProcessorRegistry::convert`].
+    pub fn convert_document(
+        &self,
+        doc: &Document,
+        format: &str,
+        request: &Request,
+    ) -> Result<Output, ProcessorError> {
+        if let Some(options) = &request.html
+            && self
+                .registry
+                .get(format)
+                .is_some_and(|p| p.name() == "html")
+        {
+            return Ok(Output::Text(render_html(doc, options)));
+        }
+        self.registry.convert(doc, format)
+    }
+
+    /// The HTML options used by requests without their own
+    pub fn default_html_options(&self) -> &HtmlOptions {
+        &self.html_options
+    }
+
+    /// Drops every cached document
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            lock(cache).clear();
+        }
+    }
+}
+
+/// Lock `cache`, recovering from a panic in another request
+fn lock(cache: &Mutex<ParseCache>) -> std::sync::MutexGuard<'_, ParseCache> {
+    cache
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Parsed documents by source text, evicting the oldest first
+#[derive(Debug, Default)]
+struct ParseCache {
+    capacity: usize,
+    documents: HashMap<String, Document>,
+    /// Sources in insertion order
+    order: VecDeque<String>,
+}
+
+impl ParseCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ..Self::default()
+        }
+    }
+
+    fn get(&self, source: &str) -> Option<Document> {
+        self.documents.get(source).cloned()
+    }
+
+    fn insert(&mut self, source: &str, doc: Document) {
+        if self.documents.insert(source.to_string(), doc).is_some() {
+            return;
+        }
+        self.order.push_back(source.to_string());
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.documents.remove(&oldest);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.documents.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::ProcessorContext;
+    use std::sync::Arc;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_engine_is_send_sync() {
+        assert_send_sync::<DoctoraEngine>();
+        assert_send_sync::<Request>();
+    }
+
+    #[test]
+    fn test_request_options_do_not_leak() {
+        let engine = DoctoraEngine::new().html_options(HtmlOptions::new().node_ids(true));
+
+        let request = Request::new().html_options(HtmlOptions::new());
+        let plain = engine.convert("Hi.", "html", &request).unwrap();
+        assert_eq!(plain.as_text(), Some("<p>Hi.</p>\n"));
+
+        let default = engine.convert("Hi.", "html5", &Request::new()).unwrap();
+        assert!(default.as_text().unwrap().contains("data-node="));
+        assert!(engine.default_html_options().node_ids);
+    }
+
+    #[test]
+    fn test_custom_processor_and_errors() {
+        struct Count;
+
+        impl Processor for Count {
+            fn name(&self) -> &str {
+                "count"
+            }
+
+            fn can_handle(&self, format: &str) -> bool {
+                format == "count"
+            }
+
+            fn process(
+                &self,
+                doc: &Document,
+                _: &ProcessorContext<'_>,
+            ) -> Result<Output, ProcessorError> {
+                Ok(Output::Text(doc.blocks.len().to_string()))
+            }
+        }
+
+        let engine = DoctoraEngine::new().processor(Box::new(Count));
+        let request = Request::new().html_options(HtmlOptions::new());
+        assert_eq!(
+            engine.convert("A.\n\nB.", "count", &request).unwrap(),
+            Output::Text("2".to_string())
+        );
+        assert_eq!(
+            engine.convert("A.", "docx", &request),
+            Err(EngineError::Processor(ProcessorError::UnknownFormat(
+                "docx".to_string()
+            )))
+        );
+        assert!(matches!(
+            engine.convert("= No newline", "html", &request),
+            Err(EngineError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_cache() {
+        let engine = DoctoraEngine::new()
+            .parse_options(ParseOptions::new().attribute("v", "1"))
+            .cache_capacity(1);
+
+        let first = engine.parse("One.", &Request::new()).unwrap();
+        assert_eq!(engine.parse("One.", &Request::new()).unwrap(), first);
+        assert_eq!(lock(engine.cache.as_ref().unwrap()).documents.len(), 1);
+
+        engine.parse("Two.", &Request::new()).unwrap();
+        let cache = lock(engine.cache.as_ref().unwrap());
+        assert!(cache.documents.contains_key("Two."));
+        assert!(!cache.documents.contains_key("One."));
+        drop(cache);
+
+        // Per-request options bypass the cache
+        let request = Request::new().parse_options(ParseOptions::new().attribute("v", "2"));
+        let doc = engine.parse("Two.", &request).unwrap();
+        assert_eq!(doc.attributes.get("v"), Some("2"));
+        engine.clear_cache();
+        assert!(lock(engine.cache.as_ref().unwrap()).documents.is_empty());
+    }
+
+    #[test]
+    fn test_shared_across_threads() {
+        let engine = Arc::new(DoctoraEngine::new().cache_capacity(8));
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let engine = Arc::clone(&engine);
+                std::thread::spawn(move || {
+                    let source = format!("Request {}.", i % 2);
+                    engine.convert(&source, "html", &Request::new()).unwrap()
+                })
+            })
+            .collect();
+        for (i, handle) in handles.into_iter().enumerate() {
+            let html = handle.join().unwrap();
+            assert_eq!(
+                html.as_text(),
+                Some(format!("<p>Request {}.</p>\n", i % 2).as_str())
+            );
+        }
+    }
+}
@@ -72,16 +72,17 @@
 //! # Implementation Plan
 //!
 //! ## Phase 1: Basic Error Types (Current - v0.1.0)
-//! - [ ] Define `ParseError` struct with location info
-//! - [ ] Define error categories (syntax, structure, semantic)
-//! - [ ] Convert Winnow errors to structured errors
-//! - [ ] Single error reporting (fail-fast)
+//! - [x] Define `ParseError` struct with location info
+//! - [x] Define error categories (syntax, structure, semantic)
+//! - [x] Convert Winnow errors to structured errors
+//! - [x] Single error reporting (fail-fast)
 //!
 //! ## Phase 2: Multiple Error Collection (v0.2.0)
-//! - [ ] Add error collector to parser state
-//! - [ ] Implement synchronization points
-//! - [ ] Return `(Option<AST>, Vec<Error>)`
-//! - [ ] Test with intentionally broken documents
+//! - [x] Add error collector to parser state
+//! - [x] Implement synchronization points
+//! - [x] Return `(Document, Vec<ParseError>)` (see
+//!   [`parse_document_recovering`](crate::parse_document_recovering))
+//! - [x] Test with intentionally broken documents
 //!
 //! ## Phase 3: Advanced Recovery (v0.3.0)
 //! - [ ] Implement panic mode recovery
@@ -108,47 +109,121 @@
 //!    = Note: Bold delimiters must be balanced within a paragraph
 //! ```
 
+use crate::attributes::AttributeError;
+use crate::include::IncludeError;
+use crate::token::Span;
 use thiserror::Error;
 
-/// Parser error with location information
+/// How serious a [`ParseError`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The document parsed, but something in it is probably not what the
+    /// author meant
+    Warning,
+    /// The document could not be parsed
+    Error,
+}
+
+/// Error returned by [`parse_document`](crate::parse_document) and
+/// [`parse_document_with_options`](crate::parse_document_with_options)
+///
+/// Syntax errors carry the byte span of the offending source, which
+/// [`LineIndex`](crate::token::LineIndex) turns into line and column numbers.
+///
+/// # Examples
 ///
-/// This will be expanded in future versions to include:
-/// - Span information (start/end byte positions)
-/// - Line/column numbers
-/// - Context snippets
-/// - Suggested fixes
-#[derive(Error, Debug, Clone, PartialEq)]
+/// ```
+/// use doctora::options::ParseOptions;
+/// use doctora::{ParseError, Severity, parse_document_with_options};
+///
+/// let options = ParseOptions::new().attribute("a", "{b}").attribute("b", "{a}");
+/// let err = parse_document_with_options("Text.", &options).unwrap_err();
+/// assert!(matches!(err, ParseError::Attributes(_)));
+/// assert_eq!(err.severity(), Severity::Error);
+/// assert_eq!(err.span(), None);
+/// ```
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum ParseError {
     /// Unexpected token encountered
-    #[error("Unexpected token at position {position}: expected {expected}, got {got}")]
+    #[error("unexpected {got} at {}..{}: expected {expected}", span.start, span.end)]
     UnexpectedToken {
-        position: usize,
+        /// Source span of the token
+        span: Span,
+        /// What the parser was looking for
         expected: String,
+        /// Description of the token found (see
+        /// [`Token::description`](crate::token::Token::description))
         got: String,
     },
 
     /// Unclosed delimiter (bold, italic, etc.)
-    #[error("Unclosed {delimiter} delimiter starting at position {start}")]
-    UnclosedDelimiter { delimiter: String, start: usize },
+    #[error("unclosed {delimiter} delimiter at {}..{}", span.start, span.end)]
+    UnclosedDelimiter {
+        /// The opening delimiter as written
+        delimiter: String,
+        /// Source span of the opening delimiter
+        span: Span,
+    },
+
+    /// Blocks nested deeper than
+    /// [`Limits::max_nesting_depth`](crate::limits::Limits::max_nesting_depth)
+    #[error("blocks nested more than {max} deep at {}..{}", span.start, span.end)]
+    NestingTooDeep {
+        /// Source span of the block that goes past the limit
+        span: Span,
+        /// The nesting limit
+        max: usize,
+    },
 
     /// Invalid document structure
-    #[error("Invalid structure: {message}")]
+    #[error("invalid structure: {message}")]
     InvalidStructure { message: String },
 
     /// End of input reached unexpectedly
-    #[error("Unexpected end of input: {context}")]
-    UnexpectedEOF { context: String },
+    #[error("unexpected end of input: expected {expected}")]
+    UnexpectedEof {
+        /// What the parser was looking for
+        expected: String,
+    },
+
+    /// Supplied attributes reference each other in a cycle
+    #[error(transparent)]
+    Attributes(#[from] AttributeError),
+
+    /// An include directive could not be expanded
+    #[error(transparent)]
+    Include(#[from] IncludeError),
+}
+
+impl ParseError {
+    /// Source span the error points at, if it has one
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::UnclosedDelimiter { span, .. }
+            | ParseError::NestingTooDeep { span, .. } => Some(span.clone()),
+            ParseError::InvalidStructure { .. }
+            | ParseError::UnexpectedEof { .. }
+            | ParseError::Attributes(_)
+            | ParseError::Include(_) => None,
+        }
+    }
+
+    /// How serious the error is
+    ///
+    /// All of these errors lose content, so they are [`Severity::Error`].
+    pub fn severity(&self) -> Severity {
+        Severity::Error
+    }
 }
 
 /// Error recovery context
 ///
-/// Maintains state during error recovery:
-/// - Collected errors
-/// - Current recovery strategy
-/// - Synchronization points
-///
-/// **Status**: Design only - not yet implemented
-#[allow(dead_code)]
+/// Collects the errors of one parse. In fail-fast mode ([`ErrorRecovery::new`])
+/// the parser stops at the first error; in collecting mode
+/// ([`ErrorRecovery::collecting`]) it records the error, skips to the next
+/// synchronization point (a blank line or a heading) and carries on.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ErrorRecovery {
     /// Accumulated errors during parsing
     errors: Vec<ParseError>,
@@ -157,16 +232,28 @@ pub struct ErrorRecovery {
     fail_fast: bool,
 }
 
-#[allow(dead_code)]
 impl ErrorRecovery {
-    /// Create new error recovery context
+    /// Create a fail-fast error recovery context
     pub fn new() -> Self {
         Self {
             errors: Vec::new(),
-            fail_fast: true, // Default to fail-fast for now
+            fail_fast: true,
+        }
+    }
+
+    /// Create a context that keeps parsing after errors
+    pub fn collecting() -> Self {
+        Self {
+            errors: Vec::new(),
+            fail_fast: false,
         }
     }
 
+    /// Returns true if parsing stops at the first error
+    pub fn is_fail_fast(&self) -> bool {
+        self.fail_fast
+    }
+
     /// Record an error during parsing
     pub fn record_error(&mut self, error: ParseError) {
         self.errors.push(error);
@@ -181,6 +268,11 @@ impl ErrorRecovery {
     pub fn has_errors(&self) -> bool {
         !self.errors.is_empty()
     }
+
+    /// The collected errors, in source order
+    pub fn into_errors(self) -> Vec<ParseError> {
+        self.errors
+    }
 }
 
 impl Default for ErrorRecovery {
@@ -203,22 +295,33 @@ mod tests {
     #[test]
     fn test_record_error() {
         let mut recovery = ErrorRecovery::new();
-        recovery.record_error(ParseError::UnexpectedEOF {
-            context: "test".to_string(),
+        recovery.record_error(ParseError::UnexpectedEof {
+            expected: "test".to_string(),
         });
         assert!(recovery.has_errors());
         assert_eq!(recovery.errors().len(), 1);
     }
 
+    #[test]
+    fn test_modes() {
+        assert!(ErrorRecovery::new().is_fail_fast());
+        let mut recovery = ErrorRecovery::collecting();
+        assert!(!recovery.is_fail_fast());
+        let error = ParseError::InvalidStructure {
+            message: "test".to_string(),
+        };
+        recovery.record_error(error.clone());
+        assert_eq!(recovery.into_errors(), vec![error]);
+    }
+
     #[test]
     fn test_parse_error_display() {
         let error = ParseError::UnclosedDelimiter {
             delimiter: "**".to_string(),
-            start: 42,
+            span: 42..44,
         };
-        let message = error.to_string();
-        assert!(message.contains("Unclosed"));
-        assert!(message.contains("**"));
-        assert!(message.contains("42"));
+        assert_eq!(error.to_string(), "unclosed ** delimiter at 42..44");
+        assert_eq!(error.span(), Some(42..44));
+        assert_eq!(error.severity(), Severity::Error);
     }
 }
@@ -0,0 +1,110 @@
+//! Pipeline events for build observability
+//!
+//! Build systems want to show progress, collect metrics, and route diagnostics
+//! into their own logging. Instead of printing, the processing pipeline reports
+//! what it is doing as [`Event`]s to a [`Subscriber`].
+//!
+//! Any `Fn(&Event)` closure is a subscriber, so simple integrations need no
+//! extra types:
+//!
+//! ```
+//! use doctora::batch::{FailurePolicy, parse_batch_observed};
+//! use doctora::events::Event;
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! let finished = AtomicUsize::new(0);
+//! let progress = |event: &Event<'_>| {
+//!     if let Event::ConversionFinished { .. } = event {
+//!         finished.fetch_add(1, Ordering::Relaxed);
+//!     }
+//! };
+//!
+//! parse_batch_observed([("a.adoc", "A."), ("b.adoc", "B.")], FailurePolicy::Skip, &progress)
+//!     .unwrap();
+//! assert_eq!(finished.load(Ordering::Relaxed), 2);
+//! ```
+
+use std::path::Path;
+use std::time::Duration;
+
+/// Something that happened while processing documents
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event<'a> {
+    /// Processing of a document began
+    DocumentStarted {
+        /// Name the document was submitted under
+        document: &'a str,
+    },
+    /// An include directive was resolved to a file or URL and read (see
+    /// [`expand_includes_observed`](crate::include::expand_includes_observed))
+    IncludeResolved {
+        /// Name the document was submitted under
+        document: &'a str,
+        /// Target as written in the directive, with attribute references
+        /// replaced
+        target: &'a str,
+        /// Resolved path, or the URL as written
+        path: &'a Path,
+        /// 1 for an include in the document itself, 2 for one in a file it
+        /// includes, and so on
+        depth: usize,
+    },
+    /// A diagnostic (error or warning) was reported for a document
+    DiagnosticEmitted {
+        /// Document the diagnostic belongs to
+        document: &'a str,
+        /// Human-readable description
+        message: &'a str,
+    },
+    /// A document uses deprecated syntax (see [`crate::deprecations`])
+    DeprecatedSyntax {
+        /// Document containing the construct
+        document: &'a str,
+        /// 1-based line of the construct
+        line: usize,
+        /// 1-based column (in characters) of the construct
+        column: usize,
+        /// Kind of construct, as [`DeprecationKind::as_str`](crate::deprecations::DeprecationKind::as_str)
+        kind: &'a str,
+        /// Human-readable description
+        message: &'a str,
+        /// Modern syntax that replaces the construct; empty to remove it
+        replacement: &'a str,
+    },
+    /// Processing of a document ended
+    ConversionFinished {
+        /// Name the document was submitted under
+        document: &'a str,
+        /// Wall-clock time spent on the document
+        elapsed: Duration,
+        /// False if the document failed (see the preceding diagnostic)
+        succeeded: bool,
+    },
+}
+
+/// Receives pipeline [`Event`]s
+///
+/// Subscribers must be thread-safe so the same subscriber can observe
+/// documents processed in parallel.
+pub trait Subscriber: Send + Sync {
+    /// Called for every event, in the order the events occur per document
+    fn on_event(&self, event: &Event<'_>);
+}
+
+impl<F> Subscriber for F
+where
+    F: Fn(&Event<'_>) + Send + Sync,
+{
+    fn on_event(&self, event: &Event<'_>) {
+        self(event)
+    }
+}
+
+/// A subscriber that ignores all events
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopSubscriber;
+
+impl Subscriber for NoopSubscriber {
+    fn on_event(&self, _event: &Event<'_>) {}
+}
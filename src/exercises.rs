@@ -0,0 +1,216 @@
+//! Exercise and solution numbering for training material
+//!
+//! Example blocks styled `exercise` or `solution` are exercises and their
+//! solutions:
+//!
+//! ```asciidoc
+//! .Reverse a list
+//! [exercise#reverse]
+//! ====
+//! Write a function that reverses a list.
+//! ====
+//!
+//! [solution]
+//! ====
+//! Use `iter().rev()`.
+//! ====
+//! ```
+//!
+//! Exercises are numbered from 1 in document order. A solution shares the
+//! number of its exercise: the one whose ID its `exercise` attribute names
+//! (`[solution,exercise=reverse]`, for solutions collected at the end of a
+//! chapter), otherwise the nearest exercise before it. A solution naming an
+//! unknown exercise, or written before any exercise, is unnumbered.
+//!
+//! Like [`Footnotes`](crate::footnotes::Footnotes), an [`Exercises`]
+//! registry numbers blocks as a renderer emits them with
+//! [`Exercises::register`], which returns the block's caption label
+//! (`Exercise 1`, `Solution 1`).
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::exercises::Exercises;
+//! use doctora::parse_document;
+//!
+//! let doc = parse_document(
+//!     "[exercise#sum]\n====\nAdd.\n====\n\n[exercise]\n====\nSort.\n====\n\n\
+//!      [solution,exercise=sum]\n====\nUse `+`.\n====\n",
+//! )
+//! .unwrap();
+//! let mut exercises = Exercises::default();
+//! let labels: Vec<String> = doc
+//!     .blocks
+//!     .iter()
+//!     .filter_map(|block| exercises.register(block))
+//!     .map(|label| label.caption())
+//!     .collect();
+//!
+//! assert_eq!(labels, ["Exercise 1", "Exercise 2", "Solution 1"]);
+//! ```
+
+use crate::ast::Block;
+use std::collections::HashMap;
+
+/// Whether a block is an exercise or a solution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExerciseKind {
+    /// An example block styled `exercise`
+    Exercise,
+    /// An example block styled `solution`
+    Solution,
+}
+
+impl ExerciseKind {
+    /// The kind of `block`, if it is an exercise or solution
+    pub fn of(block: &Block) -> Option<Self> {
+        let Block::Example { metadata, .. } = block else {
+            return None;
+        };
+        [Self::Exercise, Self::Solution]
+            .into_iter()
+            .find(|kind| metadata.style() == Some(kind.style()))
+    }
+
+    /// The block style: `exercise` or `solution`
+    pub fn style(self) -> &'static str {
+        match self {
+            Self::Exercise => "exercise",
+            Self::Solution => "solution",
+        }
+    }
+
+    /// The caption word: `Exercise` or `Solution`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Exercise => "Exercise",
+            Self::Solution => "Solution",
+        }
+    }
+}
+
+/// The label of an exercise or solution, as returned by
+/// [`Exercises::register`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExerciseLabel {
+    /// Exercise or solution
+    pub kind: ExerciseKind,
+    /// Number of the exercise, or `None` for an unnumbered solution
+    pub number: Option<usize>,
+}
+
+impl ExerciseLabel {
+    /// The caption: `Exercise 2`, `Solution 2`, or `Solution` if unnumbered
+    pub fn caption(&self) -> String {
+        match self.number {
+            Some(number) => format!("{} {number}", self.kind.name()),
+            None => self.kind.name().to_string(),
+        }
+    }
+}
+
+/// Exercise numbers assigned so far
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Exercises {
+    /// Number of exercises registered
+    count: usize,
+    /// Numbers of exercises by ID
+    by_id: HashMap<String, usize>,
+}
+
+impl Exercises {
+    /// Label `block` if it is an exercise or solution, numbering it
+    ///
+    /// An exercise gets the next number; a solution gets the number of the
+    /// exercise it belongs to (see the module documentation).
+    pub fn register(&mut self, block: &Block) -> Option<ExerciseLabel> {
+        let kind = ExerciseKind::of(block)?;
+        let number = match kind {
+            ExerciseKind::Exercise => {
+                self.count += 1;
+                if let Some(anchor) = block.anchor() {
+                    self.by_id.entry(anchor.id).or_insert(self.count);
+                }
+                Some(self.count)
+            }
+            ExerciseKind::Solution => match block.metadata().named.get("exercise") {
+                Some(id) => self.by_id.get(id).copied(),
+                None => (self.count > 0).then_some(self.count),
+            },
+        };
+        Some(ExerciseLabel { kind, number })
+    }
+
+    /// Number of the exercise with ID `id`, if registered
+    pub fn number(&self, id: &str) -> Option<usize> {
+        self.by_id.get(id).copied()
+    }
+
+    /// Number of exercises registered
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns true if no exercise has been registered
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    fn labels(source: &str) -> Vec<Option<ExerciseLabel>> {
+        let doc = parse_document(source).unwrap();
+        let mut exercises = Exercises::default();
+        doc.blocks
+            .iter()
+            .map(|block| exercises.register(block))
+            .collect()
+    }
+
+    #[test]
+    fn test_solutions_follow_their_exercise() {
+        let labels = labels(
+            "[solution]\n====\nEarly.\n====\n\n[exercise]\n====\nA.\n====\n\n\
+             [solution]\n====\nB.\n====\n\n[example]\n====\nC.\n====\n\nText.\n",
+        );
+        let solution = |number| {
+            Some(ExerciseLabel {
+                kind: ExerciseKind::Solution,
+                number,
+            })
+        };
+        assert_eq!(
+            labels,
+            [
+                solution(None),
+                Some(ExerciseLabel {
+                    kind: ExerciseKind::Exercise,
+                    number: Some(1),
+                }),
+                solution(Some(1)),
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_solutions_by_id() {
+        let captions: Vec<String> = labels(
+            "[exercise#one]\n====\nA.\n====\n\n[exercise#two]\n====\nB.\n====\n\n\
+             [solution,exercise=one]\n====\nA.\n====\n\n[solution,exercise=three]\n====\nC.\n====\n",
+        )
+        .into_iter()
+        .flatten()
+        .map(|label| label.caption())
+        .collect();
+        assert_eq!(
+            captions,
+            ["Exercise 1", "Exercise 2", "Solution 1", "Solution"]
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Footnote numbering across a parsed document
+//!
+//! The parser leaves each [`Inline::Footnote`] where it is written. Footnotes
+//! are numbered from 1 in document order; a named footnote's references
+//! (`footnote:id[]`) share its number instead of getting their own. A
+//! [`Footnotes`] registry assigns the numbers and keeps the text of each
+//! numbered footnote, so renderers can emit a marker where the footnote is
+//! written and the list of footnotes at the end, each linking back.
+//!
+//! Renderers that may skip content (role templates, for instance) register
+//! footnotes as they emit them with [`Footnotes::register`], so numbering
+//! follows the output. [`Footnotes::new`] registers every footnote of a
+//! document up front.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::footnotes::Footnotes;
+//! use doctora::parse_document;
+//!
+//! let doc = parse_document(
+//!     "A.footnote:[First.]\n\nB.footnote:warn[Careful.]\n\nC.footnote:warn[]\n",
+//! )
+//! .unwrap();
+//! let footnotes = Footnotes::new(&doc);
+//!
+//! assert_eq!(footnotes.len(), 2);
+//! assert_eq!(footnotes.entries()[1].id.as_deref(), Some("warn"));
+//! assert_eq!(footnotes.number("warn"), Some(2));
+//! ```
+
+use crate::ast::visit::{Visitor, walk_inline};
+use crate::ast::{Document, Inline};
+use std::collections::HashMap;
+
+/// A numbered footnote
+#[derive(Debug, Clone, PartialEq)]
+pub struct FootnoteEntry {
+    /// Number, from 1 in document order
+    pub number: usize,
+    /// ID of a named footnote
+    pub id: Option<String>,
+    /// The footnote text
+    pub text: Vec<Inline>,
+}
+
+/// Where a footnote in the text points, as returned by
+/// [`Footnotes::register`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FootnoteRef {
+    /// Number of the footnote
+    pub number: usize,
+    /// True where the footnote is defined, false for a reference to an
+    /// earlier named footnote
+    pub first: bool,
+}
+
+/// Footnotes by number
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Footnotes {
+    entries: Vec<FootnoteEntry>,
+    /// Numbers of named footnotes by ID
+    by_id: HashMap<String, usize>,
+}
+
+impl Footnotes {
+    /// Number every footnote in `doc`
+    pub fn new(doc: &Document) -> Self {
+        let mut footnotes = Self::default();
+        Numbering(&mut footnotes).visit_document(doc);
+        footnotes
+    }
+
+    /// Number the footnote `id`/`text` found next in the output
+    ///
+    /// A footnote with text gets the next number, unless it is named with
+    /// an ID already registered, which keeps its first text. A reference
+    /// (empty text) gets the number of the named footnote it refers to, or
+    /// `None` if there is no such footnote yet.
+    pub fn register(&mut self, id: Option<&str>, text: &[Inline]) -> Option<FootnoteRef> {
+        if let Some(&number) = id.and_then(|id| self.by_id.get(id)) {
+            return Some(FootnoteRef {
+                number,
+                first: false,
+            });
+        }
+        if text.is_empty() {
+            return None;
+        }
+        let number = self.entries.len() + 1;
+        if let Some(id) = id {
+            self.by_id.insert(id.to_string(), number);
+        }
+        self.entries.push(FootnoteEntry {
+            number,
+            id: id.map(str::to_string),
+            text: text.to_vec(),
+        });
+        Some(FootnoteRef {
+            number,
+            first: true,
+        })
+    }
+
+    /// The numbered footnotes, in order
+    pub fn entries(&self) -> &[FootnoteEntry] {
+        &self.entries
+    }
+
+    /// Number of the footnote named `id`, if registered
+    pub fn number(&self, id: &str) -> Option<usize> {
+        self.by_id.get(id).copied()
+    }
+
+    /// Number of footnotes
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// True if there are no footnotes
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Registers footnotes in document order, without descending into their
+/// text
+struct Numbering<'a>(&'a mut Footnotes);
+
+impl Visitor for Numbering<'_> {
+    fn visit_inline(&mut self, inline: &Inline) {
+        match inline {
+            Inline::Footnote { id, text } => {
+                self.0.register(id.as_deref(), text);
+            }
+            _ => walk_inline(self, inline),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    #[test]
+    fn test_numbering_and_references() {
+        let doc = parse_document(
+            "* a footnote:x[X.]\n\n|===\n|footnote:[In a cell.]\n|===\n\n\
+             Again footnote:x[] and footnote:x[Ignored.], footnote:y[].\n",
+        )
+        .unwrap();
+        let footnotes = Footnotes::new(&doc);
+        let numbers: Vec<(usize, Option<&str>)> = footnotes
+            .entries()
+            .iter()
+            .map(|entry| (entry.number, entry.id.as_deref()))
+            .collect();
+        assert_eq!(numbers, vec![(1, Some("x")), (2, None)]);
+        assert_eq!(
+            footnotes.entries()[0].text,
+            vec![Inline::Text("X.".to_string())]
+        );
+        assert_eq!(footnotes.number("y"), None);
+    }
+
+    #[test]
+    fn test_register() {
+        let text = vec![Inline::Text("T".to_string())];
+        let mut footnotes = Footnotes::default();
+        assert_eq!(footnotes.register(Some("a"), &[]), None);
+        assert_eq!(
+            footnotes.register(Some("a"), &text),
+            Some(FootnoteRef {
+                number: 1,
+                first: true
+            })
+        );
+        assert_eq!(
+            footnotes.register(Some("a"), &[]),
+            Some(FootnoteRef {
+                number: 1,
+                first: false
+            })
+        );
+        assert_eq!(footnotes.register(None, &text).unwrap().number, 2);
+        assert!(!footnotes.is_empty());
+    }
+}
@@ -0,0 +1,1304 @@
+//! Inline formatting pairs (bold, italic, monospace, highlight, superscript
+//! and subscript)
+//!
+//! AsciiDoc has two forms of most formatting marks:
+//!
+//! | Mark      | Constrained  | Unconstrained    |
+//! |-----------|--------------|------------------|
+//! | Bold      | `*text*`     | `**text**`       |
+//! | Italic    | `_text_`     | `__text__`       |
+//! | Monospace | `` `text` `` | ``` ``text`` ``` |
+//! | Highlight | `#text#`     | `##text##`       |
+//!
+//! Constrained marks only apply around whole words: the opening mark must not
+//! follow a word character and must precede a non-space, and the closing mark
+//! must follow a non-space and must not precede a word character. So
+//! `snake_case_name` and `2 * 3 * 4` stay literal. Unconstrained marks apply
+//! anywhere, including inside words (`**B**old`).
+//!
+//! Superscript (`^text^`) and subscript (`~text~`) have a single form that
+//! applies anywhere (`E=mc^2^`, `H~2~O`), but the text between the marks
+//! must not be empty or contain whitespace, so `~/a b~/c` stays literal.
+//!
+//! Marks are paired with a stack, scanning left to right. A closing mark
+//! pairs with the nearest open mark of the same form; open marks above it on
+//! the stack overlap the pair and are dropped. Marks that never pair are kept
+//! as literal text, so overlapping markup degrades the same way Asciidoctor
+//! does. Pairing, like the search for the brackets that close links,
+//! macros, and cross references, takes linear time however many marks a
+//! line leaves open:
+//!
+//! ```
+//! use doctora::ast::Inline;
+//! use doctora::formatting::format_inlines;
+//! use doctora::token::lex_with_spans;
+//!
+//! let source = "**a _b** c_";
+//! let content = format_inlines(source, &lex_with_spans(source));
+//! assert_eq!(
+//!     content,
+//!     vec![
+//!         Inline::Bold(vec![Inline::Text("a _b".to_string())]),
+//!         Inline::Text(" c_".to_string()),
+//!     ]
+//! );
+//! ```
+//!
+//! Text between marks is sliced from the source, so spacing and unpaired
+//! marks are kept exactly as written.
+//!
+//! # Escaping
+//!
+//! A backslash before an opening mark escapes the pair: the backslash is
+//! dropped and both marks stay literal (`\*not bold*` reads `*not bold*`).
+//! Formatting inside an escaped pair still applies. A backslash before a mark
+//! that would not pair anyway is kept, so `C:\_data` stays as written.
+//!
+//! ```
+//! use doctora::ast::Inline;
+//! use doctora::formatting::format_inlines;
+//! use doctora::token::lex_with_spans;
+//!
+//! let source = r"\**kwargs** and \*args* are _literal_";
+//! let content = format_inlines(source, &lex_with_spans(source));
+//! assert_eq!(content[0], Inline::Text("**kwargs** and *args* are ".to_string()));
+//! assert!(content[1].is_italic());
+//! ```
+//!
+//! # Roles
+//!
+//! An attribute list directly before a `#` pair gives it roles instead of
+//! highlighting: `[line-through]#gone#` and `[.small]#fine print#` become an
+//! [`Inline::Span`]. The roles are the first positional attribute (without
+//! shorthands), the `.role` shorthands, and the `role` attribute. A list
+//! with none of them is kept as text. A backslash before the list keeps it
+//! as text and the pair highlighted (`\[x]#text#`).
+//!
+//! ```
+//! use doctora::ast::Inline;
+//! use doctora::formatting::format_inlines;
+//! use doctora::token::lex_with_spans;
+//!
+//! let source = "[.underline]#Read# this";
+//! let content = format_inlines(source, &lex_with_spans(source));
+//! assert_eq!(
+//!     content[0],
+//!     Inline::Span {
+//!         roles: vec!["underline".to_string()],
+//!         content: vec![Inline::Text("Read".to_string())],
+//!     }
+//! );
+//! ```
+//!
+//! # Links
+//!
+//! A [`Token::Url`] becomes an [`Inline::Link`]: an autolink on its own, or a
+//! link macro when `[text]` follows directly. Links are atomic, so marks
+//! around a link can pair (`*see https://example.com[docs]*`) but marks
+//! inside its brackets never pair with marks outside. The bracket text is
+//! formatted on its own. If it contains `=`, it is an attribute list whose
+//! first positional attribute is the text (`[docs,role=external]`), and a
+//! trailing `^` on the text is short for `window=_blank`.
+//!
+//! ```
+//! use doctora::ast::Inline;
+//! use doctora::attributes::Attributes;
+//! use doctora::formatting::format_inlines;
+//! use doctora::token::lex_with_spans;
+//!
+//! let source = "See https://example.com[*the* docs].";
+//! let content = format_inlines(source, &lex_with_spans(source));
+//! assert_eq!(
+//!     content[1],
+//!     Inline::Link {
+//!         target: "https://example.com".to_string(),
+//!         text: vec![
+//!             Inline::Bold(vec![Inline::Text("the".to_string())]),
+//!             Inline::Text(" docs".to_string()),
+//!         ],
+//!         attributes: Attributes::new(),
+//!     }
+//! );
+//! ```
+//!
+//! # Cross References and Anchors
+//!
+//! `<<id>>` and `<<id,text>>` become an [`Inline::XRef`], and `[[id]]` or
+//! `[[id,reftext]]` an [`Inline::Anchor`]. Like links they are atomic, and
+//! the cross reference text is formatted on its own. An id must not contain
+//! whitespace; an anchor id must also start with a letter, `_` or `:` and
+//! continue with letters, digits, `_`, `-`, `.` or `:`. Anything else is
+//! kept as literal text.
+//!
+//! ```
+//! use doctora::ast::{Anchor, Inline};
+//! use doctora::formatting::format_inlines;
+//! use doctora::token::lex_with_spans;
+//!
+//! let source = "[[setup]]See <<install,the install steps>>.";
+//! let content = format_inlines(source, &lex_with_spans(source));
+//! assert_eq!(content[0], Inline::Anchor(Anchor::new("setup")));
+//! assert_eq!(
+//!     content[2],
+//!     Inline::XRef {
+//!         id: "install".to_string(),
+//!         text: vec![Inline::Text("the install steps".to_string())],
+//!     }
+//! );
+//! ```
+//!
+//! # Images
+//!
+//! `image:target[alt,width,height]` becomes an [`Inline::Image`]. The target
+//! runs from the colon to the `[` and must not contain whitespace; the first
+//! three positional attributes are named `alt`, `width` and `height` (see
+//! [`Inline::Image`]). Images are atomic too, so `_` in a file name never
+//! starts italics. `image::` on its own line is a block image. An icon,
+//! `icon:name[size]`, becomes an [`Inline::Icon`] the same way.
+//!
+//! ```
+//! use doctora::ast::Inline;
+//! use doctora::formatting::format_inlines;
+//! use doctora::token::lex_with_spans;
+//!
+//! let source = "Click image:save_icon.png[Save,16] to save.";
+//! let content = format_inlines(source, &lex_with_spans(source));
+//! let Inline::Image { target, attributes } = &content[1] else {
+//!     panic!("Expected Image");
+//! };
+//! assert_eq!(target, "save_icon.png");
+//! assert_eq!(attributes.get("alt"), Some("Save"));
+//! assert_eq!(attributes.get("width"), Some("16"));
+//! ```
+//!
+//! # Footnotes
+//!
+//! `footnote:[text]` becomes an [`Inline::Footnote`], and so does
+//! `footnote:id[text]`, which names the footnote so that `footnote:id[]` can
+//! refer back to it. The text runs to the matching `]` on the line (nested
+//! brackets, such as a link's, are kept) and is formatted on its own.
+//!
+//! ```
+//! use doctora::ast::Inline;
+//! use doctora::formatting::format_inlines;
+//! use doctora::token::lex_with_spans;
+//!
+//! let source = "Done.footnote:disclaimer[Mostly *done*.]";
+//! let content = format_inlines(source, &lex_with_spans(source));
+//! let Inline::Footnote { id, text } = &content[1] else {
+//!     panic!("Expected Footnote");
+//! };
+//! assert_eq!(id.as_deref(), Some("disclaimer"));
+//! assert!(text[1].is_bold());
+//! ```
+//!
+//! # Substitutions
+//!
+//! Formatting marks are the `quotes` substitution group, and links, cross
+//! references, anchors, images and footnotes the `macros` group.
+//! [`format_inlines_with`] leaves the syntax of a group that is not in its
+//! [`Substitutions`] as text, as a block's `subs` attribute asks.
+//!
+//! ```
+//! use doctora::ast::Inline;
+//! use doctora::formatting::format_inlines_with;
+//! use doctora::subs::Substitutions;
+//! use doctora::token::lex_with_spans;
+//!
+//! let source = "*Bold* https://example.com";
+//! let subs = Substitutions::NORMAL - Substitutions::QUOTES;
+//! let content = format_inlines_with(source, &lex_with_spans(source), subs);
+//! assert_eq!(content[0], Inline::Text("*Bold* ".to_string()));
+//! assert!(matches!(content[1], Inline::Link { .. }));
+//! ```
+
+use crate::ast::{Anchor, Inline};
+use crate::attributes::{Attributes, parse_attribute_list};
+use crate::subs::Substitutions;
+use crate::token::{Span, Token, lex_with_spans};
+use std::collections::HashSet;
+
+/// A formatting mark
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+    /// Bold (`*` / `**`)
+    Bold,
+    /// Italic (`_` / `__`)
+    Italic,
+    /// Monospace (`` ` `` / ``` `` ```)
+    Monospace,
+    /// Highlight (`#` / `##`)
+    Highlight,
+    /// Superscript (`^`)
+    Superscript,
+    /// Subscript (`~`)
+    Subscript,
+}
+
+/// A formatting mark found in the source, with the roles it may play
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delimiter {
+    /// The mark
+    pub mark: Mark,
+    /// True for the doubled form (`**`, `__`)
+    pub unconstrained: bool,
+    /// Byte range of the mark in the source
+    pub span: Span,
+    /// Whether the mark may open a formatted range
+    pub can_open: bool,
+    /// Whether the mark may close a formatted range
+    pub can_close: bool,
+    /// True if a backslash directly precedes the mark (see "Escaping")
+    pub escaped: bool,
+}
+
+/// Parse one line of inline content into formatted inline nodes
+///
+/// `tokens` are the line's tokens (from [`lex_with_spans`]
+/// on `source`); the content covers the source from the first token to the
+/// last.
+pub fn format_inlines(source: &str, tokens: &[(Token, Span)]) -> Vec<Inline> {
+    format_inlines_with(source, tokens, Substitutions::NORMAL)
+}
+
+/// [`format_inlines`] with only the formatting of the `quotes` and `macros`
+/// groups in `subs` (see "Substitutions" above)
+pub fn format_inlines_with(
+    source: &str,
+    tokens: &[(Token, Span)],
+    subs: Substitutions,
+) -> Vec<Inline> {
+    let (Some((_, first)), Some((_, last))) = (tokens.first(), tokens.last()) else {
+        return Vec::new();
+    };
+    let delimiters = if subs.contains(Substitutions::QUOTES) {
+        scan_delimiters(source, tokens)
+    } else {
+        Vec::new()
+    };
+    let atoms = if subs.contains(Substitutions::MACROS) {
+        scan_atoms(source, tokens)
+    } else {
+        Vec::new()
+    };
+    if delimiters.is_empty() && atoms.is_empty() {
+        // Plain text, the common case for table cells and list items
+        return vec![Inline::Text(source[first.start..last.end].to_string())];
+    }
+    let pairs = match_delimiters(&delimiters);
+    build(source, first.start..last.end, &delimiters, &pairs, &atoms)
+}
+
+/// Find the atomic nodes among `tokens` (links, cross references, inline
+/// anchors and images), with their source spans
+pub(crate) fn scan_atoms(source: &str, tokens: &[(Token, Span)]) -> Vec<(Span, Inline)> {
+    let lookahead = Lookahead::new(source, tokens);
+    let mut atoms = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let Some(end) = atom_end(source, tokens, &lookahead, i) else {
+            i += 1;
+            continue;
+        };
+        let span = tokens[i].1.start..tokens[end - 1].1.end;
+        let node = if let Some(open) = macro_bracket(source, tokens, i, "image") {
+            Inline::Image {
+                target: source[tokens[i + 2].1.start..tokens[open].1.start].to_string(),
+                attributes: image_attributes(&source[tokens[open].1.end..tokens[end - 1].1.start]),
+            }
+        } else if let Some(open) = macro_bracket(source, tokens, i, "icon") {
+            Inline::Icon {
+                name: source[tokens[i + 2].1.start..tokens[open].1.start].to_string(),
+                attributes: icon_attributes(&source[tokens[open].1.end..tokens[end - 1].1.start]),
+            }
+        } else if let Some(open) = footnote_bracket(source, tokens, i) {
+            let id = &source[tokens[i + 1].1.end..tokens[open].1.start];
+            let text = source[tokens[open].1.end..tokens[end - 1].1.start].trim();
+            Inline::Footnote {
+                id: (!id.is_empty()).then(|| id.to_string()),
+                text: format_inlines(text, &lex_with_spans(text)),
+            }
+        } else if tokens[i].0 == Token::Url {
+            let target = &source[tokens[i].1.clone()];
+            let brackets =
+                (end > i + 1).then(|| &source[tokens[i + 1].1.end..tokens[end - 1].1.start]);
+            link(target, brackets)
+        } else {
+            // Between the doubled brackets, which atom_end has validated
+            let inner = &source[span.start + 2..span.end - 2];
+            match &source[tokens[i].1.clone()] {
+                "<" => xref(inner),
+                _ => Inline::Anchor(parse_anchor(inner).expect("validated by atom_end")),
+            }
+        };
+        atoms.push((span, node));
+        i = end;
+    }
+    atoms
+}
+
+/// Index just past the atom starting at `tokens[start]`, if one does
+fn atom_end(
+    source: &str,
+    tokens: &[(Token, Span)],
+    lookahead: &Lookahead,
+    start: usize,
+) -> Option<usize> {
+    match &tokens[start].0 {
+        Token::Url => Some(link_end(source, tokens, lookahead, start)),
+        Token::Word => {
+            if let Some(open) = footnote_bracket(source, tokens, start) {
+                return lookahead.matching[open].map(|close| close + 1);
+            }
+            let open = macro_bracket(source, tokens, start, "image")
+                .or_else(|| macro_bracket(source, tokens, start, "icon"))?;
+            lookahead.bracket(open + 1).map(|close| close + 1)
+        }
+        Token::Punctuation => {
+            let open = &source[tokens[start].1.clone()];
+            let closes = match open {
+                "<" => &lookahead.doubled_angle,
+                "[" => &lookahead.doubled_square,
+                _ => return None,
+            };
+            if !doubled(source, tokens, start, open) {
+                return None;
+            }
+            let end = closes.get(start + 2).copied().flatten()? + 2;
+            let inner = &source[tokens[start].1.start + 2..tokens[end - 1].1.end - 2];
+            let valid = match open {
+                "<" => xref_id(inner).is_some(),
+                _ => parse_anchor(inner).is_some(),
+            };
+            valid.then_some(end)
+        }
+        _ => None,
+    }
+}
+
+/// True if `tokens[index]` and the token directly after it are both the
+/// punctuation `text`
+fn doubled(source: &str, tokens: &[(Token, Span)], index: usize, text: &str) -> bool {
+    matches!(
+        (tokens.get(index), tokens.get(index + 1)),
+        (Some((Token::Punctuation, first)), Some((Token::Punctuation, second)))
+            if &source[first.clone()] == text
+                && &source[second.clone()] == text
+                && first.end == second.start
+    )
+}
+
+/// Where the brackets among a line's tokens close
+///
+/// Atoms end at a closing bracket further along the line. Searching for it
+/// from every opening bracket would take quadratic time on lines full of
+/// unclosed brackets (`<<<<...`), so the closers are found in two passes over
+/// the tokens up front. Each table is indexed by token.
+struct Lookahead {
+    /// The first `]` at or after each token, within its line
+    bracket: Vec<Option<usize>>,
+    /// The first `]` at or after each token, before the next blank line
+    link_bracket: Vec<Option<usize>>,
+    /// For each `[`, the `]` closing it within its line, counting nested
+    /// brackets
+    matching: Vec<Option<usize>>,
+    /// The first `>>` at or after each token, within its line
+    doubled_angle: Vec<Option<usize>>,
+    /// The first `]]` at or after each token, within its line
+    doubled_square: Vec<Option<usize>>,
+}
+
+impl Lookahead {
+    fn new(source: &str, tokens: &[(Token, Span)]) -> Self {
+        let len = tokens.len();
+        let mut lookahead = Self {
+            bracket: vec![None; len],
+            link_bracket: vec![None; len],
+            matching: vec![None; len],
+            doubled_angle: vec![None; len],
+            doubled_square: vec![None; len],
+        };
+        let (mut bracket, mut link_bracket, mut angle, mut square) = (None, None, None, None);
+        for index in (0..len).rev() {
+            match tokens[index].0 {
+                Token::BlankLine => {
+                    (bracket, link_bracket, angle, square) = (None, None, None, None)
+                }
+                Token::Newline => (bracket, angle, square) = (None, None, None),
+                _ => {
+                    if is_punctuation(source, tokens, index, "]") {
+                        bracket = Some(index);
+                        link_bracket = Some(index);
+                    }
+                    if doubled(source, tokens, index, ">") {
+                        angle = Some(index);
+                    }
+                    if doubled(source, tokens, index, "]") {
+                        square = Some(index);
+                    }
+                }
+            }
+            lookahead.bracket[index] = bracket;
+            lookahead.link_bracket[index] = link_bracket;
+            lookahead.doubled_angle[index] = angle;
+            lookahead.doubled_square[index] = square;
+        }
+        let mut open = Vec::new();
+        for index in 0..len {
+            if matches!(tokens[index].0, Token::Newline | Token::BlankLine) {
+                open.clear();
+            } else if is_punctuation(source, tokens, index, "[") {
+                open.push(index);
+            } else if is_punctuation(source, tokens, index, "]")
+                && let Some(bracket) = open.pop()
+            {
+                lookahead.matching[bracket] = Some(index);
+            }
+        }
+        lookahead
+    }
+
+    /// The first `]` at or after `tokens[index]`, within its line
+    fn bracket(&self, index: usize) -> Option<usize> {
+        self.bracket.get(index).copied().flatten()
+    }
+}
+
+/// Index of the `[` after the target, if the inline macro `macro_name`
+/// (`image:` or `icon:` directly followed by a target) starts at
+/// `tokens[start]`
+///
+/// The target runs up to the first `[` without whitespace; `image::` is a
+/// block macro, not an inline one.
+fn macro_bracket(
+    source: &str,
+    tokens: &[(Token, Span)],
+    start: usize,
+    macro_name: &str,
+) -> Option<usize> {
+    let (Token::Word, name) = &tokens[start] else {
+        return None;
+    };
+    let continues_word = source[..name.start]
+        .chars()
+        .next_back()
+        .is_some_and(char::is_alphanumeric);
+    if continues_word
+        || &source[name.clone()] != macro_name
+        || tokens
+            .get(start + 1)
+            .is_none_or(|(_, colon)| colon.start != name.end)
+        || !is_punctuation(source, tokens, start + 1, ":")
+        || is_punctuation(source, tokens, start + 2, ":")
+    {
+        return None;
+    }
+    let mut index = start + 1;
+    loop {
+        let (token, span) = tokens.get(index + 1)?;
+        if span.start != tokens[index].1.end || matches!(token, Token::Newline | Token::BlankLine) {
+            return None;
+        }
+        index += 1;
+        if is_punctuation(source, tokens, index, "[") {
+            return (index > start + 2).then_some(index);
+        }
+    }
+}
+
+/// Index of the `[` after the ID, if a footnote macro (`footnote:` and an
+/// optional ID) starts at `tokens[start]`
+///
+/// An ID is letters, digits, `_` and `-`, directly between the colon and
+/// the `[`.
+fn footnote_bracket(source: &str, tokens: &[(Token, Span)], start: usize) -> Option<usize> {
+    let (Token::Word, name) = &tokens[start] else {
+        return None;
+    };
+    let continues_word = source[..name.start]
+        .chars()
+        .next_back()
+        .is_some_and(char::is_alphanumeric);
+    if continues_word
+        || &source[name.clone()] != "footnote"
+        || tokens
+            .get(start + 1)
+            .is_none_or(|(_, colon)| colon.start != name.end)
+        || !is_punctuation(source, tokens, start + 1, ":")
+    {
+        return None;
+    }
+    let mut index = start + 1;
+    loop {
+        let (token, span) = tokens.get(index + 1)?;
+        if span.start != tokens[index].1.end {
+            return None;
+        }
+        index += 1;
+        if is_punctuation(source, tokens, index, "[") {
+            return Some(index);
+        }
+        let id_part = matches!(token, Token::Word | Token::ItalicDelimiter)
+            || is_punctuation(source, tokens, index, "-");
+        if !id_part {
+            return None;
+        }
+    }
+}
+
+/// True if `tokens[index]` is the punctuation `text`
+fn is_punctuation(source: &str, tokens: &[(Token, Span)], index: usize, text: &str) -> bool {
+    matches!(tokens.get(index), Some((Token::Punctuation, span)) if &source[span.clone()] == text)
+}
+
+/// Parse the attribute list of an image macro (the text between `[` and `]`)
+///
+/// The first three positional attributes are named `alt`, `width` and
+/// `height`; named attributes of the same name take precedence.
+pub(crate) fn image_attributes(list: &str) -> Attributes {
+    let mut attributes = parse_attribute_list(list);
+    for (position, name) in ["alt", "width", "height"].into_iter().enumerate() {
+        let position = (position + 1).to_string();
+        if let Some(value) = attributes.unset(&position)
+            && !attributes.contains(name)
+        {
+            attributes.set(name, value);
+        }
+    }
+    attributes
+}
+
+/// Parse the attribute list of an icon macro, naming the first positional
+/// attribute `size`
+fn icon_attributes(list: &str) -> Attributes {
+    let mut attributes = parse_attribute_list(list);
+    if let Some(size) = attributes.unset("1")
+        && !attributes.contains("size")
+    {
+        attributes.set("size", size);
+    }
+    attributes
+}
+
+/// Index just past the link starting at the [`Token::Url`] `tokens[url]`,
+/// including `[...]` when it directly follows the URL
+fn link_end(source: &str, tokens: &[(Token, Span)], lookahead: &Lookahead, url: usize) -> usize {
+    if !is_punctuation(source, tokens, url + 1, "[") || tokens[url + 1].1.start != tokens[url].1.end
+    {
+        return url + 1;
+    }
+    lookahead
+        .link_bracket
+        .get(url + 2)
+        .copied()
+        .flatten()
+        .map_or(url + 1, |close| close + 1)
+}
+
+/// Build the link node for `target` and the text between its brackets
+fn link(target: &str, brackets: Option<&str>) -> Inline {
+    let brackets = brackets.unwrap_or_default();
+    let (mut attributes, text) = if brackets.contains('=') {
+        let attributes = parse_attribute_list(brackets);
+        let text = attributes.get("1").unwrap_or_default().to_string();
+        (attributes, text)
+    } else {
+        (Attributes::new(), brackets.to_string())
+    };
+    let text = match text.strip_suffix('^') {
+        Some(text) => {
+            attributes.set("window", "_blank");
+            text
+        }
+        None => &text,
+    };
+    let text = text.trim();
+    Inline::Link {
+        target: target.to_string(),
+        text: format_inlines(text, &lex_with_spans(text)),
+        attributes,
+    }
+}
+
+/// The target of a cross reference, if `inner` (between `<<` and `>>`)
+/// starts with a valid one
+fn xref_id(inner: &str) -> Option<&str> {
+    let id = inner.split(',').next().unwrap_or_default();
+    (!id.is_empty() && !id.contains(char::is_whitespace)).then_some(id)
+}
+
+/// Build the cross reference node for the text between `<<` and `>>`
+fn xref(inner: &str) -> Inline {
+    let text = inner.split_once(',').map_or("", |(_, text)| text.trim());
+    Inline::XRef {
+        id: xref_id(inner).unwrap_or_default().to_string(),
+        text: format_inlines(text, &lex_with_spans(text)),
+    }
+}
+
+/// Parse the content of an `[[id]]` or `[[id,reftext]]` anchor
+pub(crate) fn parse_anchor(inner: &str) -> Option<Anchor> {
+    let (id, reftext) = match inner.split_once(',') {
+        Some((id, reftext)) => (id, Some(reftext.trim())),
+        None => (inner, None),
+    };
+    let mut chars = id.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || matches!(c, '_' | ':'))
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'));
+    valid.then(|| Anchor {
+        id: id.to_string(),
+        reftext: reftext
+            .filter(|reftext| !reftext.is_empty())
+            .map(str::to_string),
+    })
+}
+
+/// Find the formatting marks among `tokens`
+pub fn scan_delimiters(source: &str, tokens: &[(Token, Span)]) -> Vec<Delimiter> {
+    let lookahead = Lookahead::new(source, tokens);
+    let mut delimiters = Vec::new();
+    // Token indices of superscript and subscript marks that close a pair
+    let mut closers = HashSet::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let (token, span) = &tokens[i];
+        let (mark, unconstrained, span) = match token {
+            // Marks inside atoms pair among themselves (see "Links")
+            Token::Url | Token::Punctuation | Token::Word
+                if let Some(end) = atom_end(source, tokens, &lookahead, i) =>
+            {
+                i = end;
+                continue;
+            }
+            Token::BoldDelimiter => (Mark::Bold, true, span.clone()),
+            Token::Punctuation if &source[span.clone()] == "*" => (Mark::Bold, false, span.clone()),
+            Token::ItalicDelimiter => match tokens.get(i + 1) {
+                Some((Token::ItalicDelimiter, next)) if next.start == span.end => {
+                    i += 1;
+                    (Mark::Italic, true, span.start..next.end)
+                }
+                _ => (Mark::Italic, false, span.clone()),
+            },
+            Token::MonospaceDelimiter => match tokens.get(i + 1) {
+                Some((Token::MonospaceDelimiter, next)) if next.start == span.end => {
+                    i += 1;
+                    (Mark::Monospace, true, span.start..next.end)
+                }
+                _ => (Mark::Monospace, false, span.clone()),
+            },
+            Token::HighlightDelimiter => match tokens.get(i + 1) {
+                Some((Token::HighlightDelimiter, next)) if next.start == span.end => {
+                    i += 1;
+                    (Mark::Highlight, true, span.start..next.end)
+                }
+                _ => (Mark::Highlight, false, span.clone()),
+            },
+            Token::SuperscriptDelimiter | Token::SubscriptDelimiter => {
+                let mark = if *token == Token::SuperscriptDelimiter {
+                    Mark::Superscript
+                } else {
+                    Mark::Subscript
+                };
+                let closes = closers.remove(&i);
+                if !closes {
+                    match script_close(source, tokens, i) {
+                        Some(close) => {
+                            closers.insert(close);
+                        }
+                        None => {
+                            i += 1;
+                            continue;
+                        }
+                    }
+                }
+                delimiters.push(Delimiter {
+                    mark,
+                    unconstrained: true,
+                    span: span.clone(),
+                    can_open: !closes,
+                    can_close: closes,
+                    escaped: source[..span.start].ends_with('\\'),
+                });
+                i += 1;
+                continue;
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let before = source[..span.start].chars().next_back();
+        let after = source[span.end..].chars().next();
+        let (can_open, can_close) = if unconstrained {
+            (true, true)
+        } else {
+            (
+                before.is_none_or(|c| !is_word(c) && !matches!(c, ';' | ':' | '}'))
+                    && after.is_some_and(|c| !c.is_whitespace()),
+                before.is_some_and(|c| !c.is_whitespace()) && after.is_none_or(|c| !is_word(c)),
+            )
+        };
+        delimiters.push(Delimiter {
+            mark,
+            unconstrained,
+            escaped: before == Some('\\'),
+            span,
+            can_open,
+            can_close,
+        });
+        i += 1;
+    }
+    delimiters
+}
+
+/// Index of the mark closing the superscript or subscript opened at
+/// `tokens[open]`: the next mark of the same kind, if the text between is
+/// neither empty nor contains whitespace
+fn script_close(source: &str, tokens: &[(Token, Span)], open: usize) -> Option<usize> {
+    let kind = &tokens[open].0;
+    let close = (open + 1..tokens.len()).find(|&index| tokens[index].0 == *kind)?;
+    let between = &source[tokens[open].1.end..tokens[close].1.start];
+    (!between.is_empty() && !between.contains(char::is_whitespace)).then_some(close)
+}
+
+/// Pair opening and closing delimiters, returning `(open, close)` indices
+/// into `delimiters` in closing order
+///
+/// See the module documentation for the pairing rules. Pairs never overlap
+/// and never enclose an empty range.
+pub fn match_delimiters(delimiters: &[Delimiter]) -> Vec<(usize, usize)> {
+    let mut stack: Vec<usize> = Vec::new();
+    // The open marks of each kind as `(position in stack, delimiter)`, so a
+    // closing mark finds its opener without walking past marks of other
+    // kinds. Entries for marks since dropped from the stack are stale and
+    // skipped.
+    let mut open_by_kind: [Vec<(usize, usize)>; 12] = Default::default();
+    let mut pairs = Vec::new();
+
+    for (index, delimiter) in delimiters.iter().enumerate() {
+        let kind = delimiter.mark as usize * 2 + usize::from(delimiter.unconstrained);
+        let openers = &mut open_by_kind[kind];
+        let is_open = |&(position, open): &(usize, usize)| stack.get(position) == Some(&open);
+        while openers.last().is_some_and(|entry| !is_open(entry)) {
+            openers.pop();
+        }
+        let opener = delimiter
+            .can_close
+            .then(|| {
+                openers.iter().rev().find(|entry| {
+                    is_open(entry) && delimiters[entry.1].span.end < delimiter.span.start
+                })
+            })
+            .flatten()
+            .map(|&(position, _)| position);
+
+        if let Some(position) = opener {
+            pairs.push((stack[position], index));
+            stack.truncate(position);
+        } else if delimiter.can_open {
+            openers.push((stack.len(), index));
+            stack.push(index);
+        }
+    }
+    pairs
+}
+
+/// Build the inline tree for `range` from paired delimiters
+fn build(
+    source: &str,
+    range: Span,
+    delimiters: &[Delimiter],
+    pairs: &[(usize, usize)],
+    atoms: &[(Span, Inline)],
+) -> Vec<Inline> {
+    // Paired delimiters sorted by position; each pair's closer follows its
+    // contents because pairs never overlap
+    let mut events: Vec<(usize, bool, bool)> = Vec::with_capacity(pairs.len() * 2);
+    for &(open, close) in pairs {
+        let escaped = delimiters[open].escaped;
+        events.push((open, true, escaped));
+        events.push((close, false, escaped));
+    }
+    events.sort_by_key(|&(index, _, _)| delimiters[index].span.start);
+
+    // Each open pair with the roles of its attribute list, if it has one
+    let mut stack: Vec<(Mark, Option<Vec<String>>, Vec<Inline>)> =
+        vec![(Mark::Bold, None, Vec::new())];
+    let mut cursor = range.start;
+    for (index, opens, escaped) in events {
+        let span = &delimiters[index].span;
+        if escaped {
+            // Drop the backslash and keep both marks as text
+            if opens {
+                push_source(
+                    &mut stack.last_mut().unwrap().2,
+                    source,
+                    cursor..span.start - 1,
+                    atoms,
+                );
+                cursor = span.start;
+            }
+            continue;
+        }
+        let mut roles = None;
+        if opens && delimiters[index].mark == Mark::Highlight {
+            let constrained = !delimiters[index].unconstrained;
+            if let Some((start, list)) = role_list(source, cursor..span.start, constrained, atoms) {
+                let content = &mut stack.last_mut().unwrap().2;
+                if source[..start].ends_with('\\') && start > cursor {
+                    // Drop the backslash and keep the list as text
+                    push_source(content, source, cursor..start - 1, atoms);
+                    cursor = start;
+                } else if !list.is_empty() {
+                    push_source(content, source, cursor..start, atoms);
+                    cursor = span.start;
+                    roles = Some(list);
+                }
+            }
+        }
+        push_source(
+            &mut stack.last_mut().unwrap().2,
+            source,
+            cursor..span.start,
+            atoms,
+        );
+        cursor = span.end;
+        if opens {
+            stack.push((delimiters[index].mark, roles, Vec::new()));
+        } else {
+            let (mark, roles, content) = stack.pop().unwrap();
+            let node = match mark {
+                Mark::Bold => Inline::Bold(content),
+                Mark::Italic => Inline::Italic(content),
+                Mark::Monospace => Inline::Monospace(content),
+                Mark::Highlight => match roles {
+                    Some(roles) => Inline::Span { roles, content },
+                    None => Inline::Highlight(content),
+                },
+                Mark::Superscript => Inline::Superscript(content),
+                Mark::Subscript => Inline::Subscript(content),
+            };
+            stack.last_mut().unwrap().2.push(node);
+        }
+    }
+    let (_, _, mut content) = stack.pop().unwrap();
+    push_source(&mut content, source, cursor..range.end, atoms);
+    content
+}
+
+/// Offset of the `[` of the attribute list `text` ends with: `[` and `]`
+/// around text without brackets or line breaks
+pub(crate) fn attribute_list_start(text: &str) -> Option<usize> {
+    let open = text.strip_suffix(']')?.rfind('[')?;
+    let list = &text[open + 1..text.len() - 1];
+    (!list.is_empty() && !list.contains('\n')).then_some(open)
+}
+
+/// The attribute list at the end of source `range`, before a `#` mark, as
+/// its start and its roles
+///
+/// The list must not be part of an atom, and before a constrained mark its
+/// `[` must not follow a word character, like the mark itself.
+fn role_list(
+    source: &str,
+    range: Span,
+    constrained: bool,
+    atoms: &[(Span, Inline)],
+) -> Option<(usize, Vec<String>)> {
+    let start = range.start + attribute_list_start(&source[range.clone()])?;
+    // Atoms are sorted and never overlap, so only the first one ending
+    // after the `[` can overlap the list
+    let first = atoms.partition_point(|(span, _)| span.end <= start);
+    if atoms
+        .get(first)
+        .is_some_and(|(span, _)| span.start < range.end)
+    {
+        return None;
+    }
+    let before = source[..start].chars().next_back();
+    if constrained
+        && before.is_some_and(|c| c != '\\' && (is_word(c) || matches!(c, ';' | ':' | '}')))
+    {
+        return None;
+    }
+    let attributes = parse_attribute_list(&source[start + 1..range.end - 1]);
+    let roles = attributes
+        .style()
+        .into_iter()
+        .flat_map(str::split_whitespace)
+        .chain(attributes.roles())
+        .map(str::to_string)
+        .collect();
+    Some((start, roles))
+}
+
+/// Append the text and atoms of source `range` to `content`
+fn push_source(content: &mut Vec<Inline>, source: &str, range: Span, atoms: &[(Span, Inline)]) {
+    // Atoms are sorted and never overlap, so those in `range` are adjacent
+    let first = atoms.partition_point(|(span, _)| span.start < range.start);
+    let mut cursor = range.start;
+    for (span, atom) in atoms[first..]
+        .iter()
+        .take_while(|(span, _)| span.end <= range.end)
+    {
+        push_text(content, &source[cursor..span.start]);
+        content.push(atom.clone());
+        cursor = span.end;
+    }
+    push_text(content, &source[cursor..range.end]);
+}
+
+/// Append non-empty `text` to `content`, extending a trailing text node
+fn push_text(content: &mut Vec<Inline>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match content.last_mut() {
+        Some(Inline::Text(last)) => last.push_str(text),
+        _ => content.push(Inline::Text(text.to_string())),
+    }
+}
+
+/// Word characters, as in the `\p{Word}` class of Asciidoctor's patterns
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::plain_text;
+    use crate::token::lex_with_spans;
+
+    /// Compact rendering: `<b>..</b>` for bold, `<i>..</i>` for italic
+    fn show(content: &[Inline]) -> String {
+        content
+            .iter()
+            .map(|inline| match inline {
+                Inline::Text(text) => text.clone(),
+                Inline::Bold(nested) => format!("<b>{}</b>", show(nested)),
+                Inline::Italic(nested) => format!("<i>{}</i>", show(nested)),
+                Inline::Monospace(nested) => format!("<c>{}</c>", show(nested)),
+                Inline::Highlight(nested) => format!("<m>{}</m>", show(nested)),
+                Inline::Span { roles, content } => {
+                    format!("<span {}>{}</span>", roles.join(" "), show(content))
+                }
+                Inline::Superscript(nested) => format!("<sup>{}</sup>", show(nested)),
+                Inline::Subscript(nested) => format!("<sub>{}</sub>", show(nested)),
+                Inline::Link { target, text, .. } => format!("<a {target}>{}</a>", show(text)),
+                Inline::XRef { id, text } => format!("<x {id}>{}</x>", show(text)),
+                Inline::Anchor(anchor) => format!("<#{}>", anchor.id),
+                Inline::Image { target, .. } => format!("<img {target}>"),
+                Inline::Icon { name, .. } => format!("<icon {name}>"),
+                Inline::Footnote { id, text } => {
+                    format!(
+                        "<fn {}>{}</fn>",
+                        id.as_deref().unwrap_or_default(),
+                        show(text)
+                    )
+                }
+                Inline::BreakHint(hint) => format!("<{hint:?}>"),
+            })
+            .collect()
+    }
+
+    fn format(source: &str) -> String {
+        show(&format_inlines(source, &lex_with_spans(source)))
+    }
+
+    #[test]
+    fn test_formatting_table() {
+        let cases = [
+            // Basic forms
+            ("**bold**", "<b>bold</b>"),
+            ("*bold*", "<b>bold</b>"),
+            ("_italic_", "<i>italic</i>"),
+            ("__italic__", "<i>italic</i>"),
+            ("a **b** c", "a <b>b</b> c"),
+            ("a *b* c", "a <b>b</b> c"),
+            ("a _b_ c", "a <i>b</i> c"),
+            ("**a** _b_", "<b>a</b> <i>b</i>"),
+            ("`code`", "<c>code</c>"),
+            ("``code``", "<c>code</c>"),
+            ("a `b` c", "a <c>b</c> c"),
+            ("#key# and ##in##side", "<m>key</m> and <m>in</m>side"),
+            ("E=mc^2^", "E=mc<sup>2</sup>"),
+            ("H~2~O", "H<sub>2</sub>O"),
+            ("^*a*^ *~b~*", "<sup><b>a</b></sup> <b><sub>b</sub></b>"),
+            ("#a ^b^ c#", "<m>a <sup>b</sup> c</m>"),
+            // Nesting
+            ("**a _b_ c**", "<b>a <i>b</i> c</b>"),
+            ("_a **b** c_", "<i>a <b>b</b> c</i>"),
+            ("*a _b_*", "<b>a <i>b</i></b>"),
+            ("__a **b**__", "<i>a <b>b</b></i>"),
+            ("`*a* snake_case`", "<c><b>a</b> snake_case</c>"),
+            ("*`a`*", "<b><c>a</c></b>"),
+            // `_` is a word character, so `*` before `__` cannot close
+            ("__a *b*__", "<i>a *b*</i>"),
+            // Unconstrained marks work inside words
+            ("**B**old", "<b>B</b>old"),
+            ("un__frigging__believable", "un<i>frigging</i>believable"),
+            ("Vec``<T>``s", "Vec<c><T></c>s"),
+            // Constrained marks need word boundaries
+            ("snake_case_name", "snake_case_name"),
+            ("a*b*c", "a*b*c"),
+            ("2 * 3 * 4", "2 * 3 * 4"),
+            ("a`b`c", "a`b`c"),
+            ("C# and F#", "C# and F#"),
+            // Superscript and subscript text has no whitespace
+            ("^a b^", "^a b^"),
+            ("~/a b~/c", "~/a b~/c"),
+            ("^^", "^^"),
+            ("2^3^4^", "2<sup>3</sup>4^"),
+            // Escaped pairs are literal, without the backslash
+            ("\\*a*", "*a*"),
+            ("x \\**a _b_** y", "x **a <i>b</i>** y"),
+            ("*a \\_b_*", "<b>a _b_</b>"),
+            ("\\^2^ \\#a#", "^2^ #a#"),
+            // Attribute lists give `#` pairs roles
+            ("[line-through]#a#", "<span line-through>a</span>"),
+            (
+                "[.small]#a# [.x.y]##b##c",
+                "<span small>a</span> <span x y>b</span>c",
+            ),
+            ("[big, role=\"a b\"]#c#", "<span big a b>c</span>"),
+            ("a[.x]##b## a[.x]#b#", "a<span x>b</span> a[.x]<m>b</m>"),
+            ("[id=x]#a# \\[.x]#b#", "[id=x]<m>a</m> [.x]<m>b</m>"),
+            ("[[a]]#b# [x\n]#c#", "<#a><m>b</m> [x\n]<m>c</m>"),
+            ("[.u]*a*", "[.u]<b>a</b>"),
+            ("C:\\_data \\*", "C:\\_data \\*"),
+            ("*a\\*", "<b>a\\</b>"),
+            ("_ spaced _", "_ spaced _"),
+            ("_italic_.", "<i>italic</i>."),
+            ("(*bold*)", "(<b>bold</b>)"),
+            // Unpaired marks stay literal
+            ("**open", "**open"),
+            ("close**", "close**"),
+            ("_open", "_open"),
+            ("a ** b", "a ** b"),
+            ("****", "****"),
+            // Overlapping marks: the first completed pair wins
+            ("**a _b** c_", "<b>a _b</b> c_"),
+            ("_a **b_ c**", "<i>a **b</i> c**"),
+            ("*a _b* c_", "<b>a _b</b> c_"),
+            // Constrained and unconstrained forms do not pair with each other
+            ("**a*", "**a*"),
+            ("*a**", "*a**"),
+            ("__a_", "__a_"),
+            ("``a`", "``a`"),
+        ];
+
+        let failures: Vec<String> = cases
+            .iter()
+            .filter_map(|&(source, expected)| {
+                let actual = format(source);
+                (actual != expected)
+                    .then(|| format!("{source:?}: expected {expected:?}, got {actual:?}"))
+            })
+            .collect();
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_links() {
+        let cases = [
+            ("https://a.io", "<a https://a.io></a>"),
+            ("see https://a.io.", "see <a https://a.io></a>."),
+            ("https://a.io[]", "<a https://a.io></a>"),
+            ("https://a.io[A *b*]", "<a https://a.io>A <b>b</b></a>"),
+            // Links are atomic: marks pair around them, not into them
+            ("*https://a.io/x_y_z*", "<b><a https://a.io/x_y_z></a></b>"),
+            ("_a https://a.io[b_ c]", "_a <a https://a.io>b_ c</a>"),
+            ("**https://a.io[x**]**", "<b><a https://a.io>x**</a></b>"),
+            // Brackets must follow directly and close before a blank line
+            ("https://a.io [x]", "<a https://a.io></a> [x]"),
+            ("https://a.io[x", "<a https://a.io></a>[x"),
+        ];
+        let failures: Vec<String> = cases
+            .iter()
+            .filter_map(|&(source, expected)| {
+                let actual = format(source);
+                (actual != expected)
+                    .then(|| format!("{source:?}: expected {expected:?}, got {actual:?}"))
+            })
+            .collect();
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_link_attributes() {
+        let link = |source: &str| {
+            let content = format_inlines(source, &lex_with_spans(source));
+            let Some(Inline::Link {
+                text, attributes, ..
+            }) = content.into_iter().next()
+            else {
+                panic!("expected a link in {source:?}");
+            };
+            (plain_text(&text), attributes)
+        };
+        let (text, attributes) = link("https://a.io[\"Hi, there\",role=ext,window=main]");
+        assert_eq!(text, "Hi, there");
+        assert_eq!(attributes.roles(), ["ext"]);
+        assert_eq!(attributes.get("window"), Some("main"));
+
+        let (text, attributes) = link("https://a.io[Docs^]");
+        assert_eq!(text, "Docs");
+        assert_eq!(attributes.get("window"), Some("_blank"));
+
+        // Without `=` the whole text is kept, commas included
+        let (text, attributes) = link("https://a.io[a, b]");
+        assert_eq!(text, "a, b");
+        assert!(attributes.is_empty());
+    }
+
+    #[test]
+    fn test_xrefs_and_anchors() {
+        let cases = [
+            ("<<intro>>", "<x intro></x>"),
+            (
+                "see <<intro,the *intro*>>.",
+                "see <x intro>the <b>intro</b></x>.",
+            ),
+            ("[[a1]]Text", "<#a1>Text"),
+            ("[[a1,First]] *b*", "<#a1> <b>b</b>"),
+            // Atomic, like links
+            ("*<<a_b,c*>>*", "<b><x a_b>c*</x></b>"),
+            // Invalid ids and unclosed runs stay literal
+            ("<<has space>>", "<<has space>>"),
+            ("a << b", "a << b"),
+            ("<<a> >", "<<a> >"),
+            ("[[1a]]", "[[1a]]"),
+            ("[[a b]]", "[[a b]]"),
+        ];
+        let failures: Vec<String> = cases
+            .iter()
+            .filter_map(|&(source, expected)| {
+                let actual = format(source);
+                (actual != expected)
+                    .then(|| format!("{source:?}: expected {expected:?}, got {actual:?}"))
+            })
+            .collect();
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+
+        let content = format_inlines("[[a1, First ]]", &lex_with_spans("[[a1, First ]]"));
+        assert_eq!(
+            content,
+            vec![Inline::Anchor(Anchor {
+                id: "a1".to_string(),
+                reftext: Some("First".to_string()),
+            })]
+        );
+    }
+
+    #[test]
+    fn test_footnotes() {
+        for (source, expected) in [
+            ("A.footnote:[Note.]", "A.<fn >Note.</fn>"),
+            ("A footnote:n-1[*B*] c", "A <fn n-1><b>B</b></fn> c"),
+            ("Again.footnote:n-1[]", "Again.<fn n-1></fn>"),
+            (
+                "footnote:[See https://a.io[docs] and [x].] *y*",
+                "<fn >See <a https://a.io>docs</a> and [x].</fn> <b>y</b>",
+            ),
+            // Not footnotes
+            ("footnote:a b[c]", "footnote:a b[c]"),
+            ("footnote:[open", "footnote:[open"),
+            ("myfootnote:[x]", "myfootnote:[x]"),
+        ] {
+            assert_eq!(format(source), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_images() {
+        for (source, expected) in [
+            ("image:a.png[]", "<img a.png>"),
+            (
+                "see image:my_pic.png[A _b_] _c_",
+                "see <img my_pic.png> <i>c</i>",
+            ),
+            ("*image:a.png[x]*", "<b><img a.png></b>"),
+            (
+                "image:https://example.com/a.png[]",
+                "<img https://example.com/a.png>",
+            ),
+            // Not inline images
+            ("image::a.png[]", "image::a.png[]"),
+            ("image: a.png[]", "image: a.png[]"),
+            ("myimage:a.png[]", "myimage:a.png[]"),
+            ("image:a.png", "image:a.png"),
+            ("image:[]", "image:[]"),
+            ("icon:heart[2x]", "<icon heart>"),
+            ("a icon:heart[] b", "a <icon heart> b"),
+            ("icon:heart", "icon:heart"),
+        ] {
+            assert_eq!(format(source), expected, "{source}");
+        }
+
+        let source = r#"image:a.png["Alt, text",40,width=50,role=icon]"#;
+        let Some(Inline::Image { attributes, .. }) =
+            format_inlines(source, &lex_with_spans(source)).pop()
+        else {
+            panic!("Expected Image");
+        };
+        assert_eq!(attributes.get("alt"), Some("Alt, text"));
+        assert_eq!(attributes.get("width"), Some("50"));
+        assert_eq!(attributes.get("height"), None);
+        assert_eq!(attributes.get("role"), Some("icon"));
+        assert!(!attributes.contains("1"));
+    }
+
+    #[test]
+    fn test_delimiter_roles() {
+        let source = "a _b_ c";
+        let delimiters = scan_delimiters(source, &lex_with_spans(source));
+        assert_eq!(delimiters.len(), 2);
+        assert!(delimiters[0].can_open && !delimiters[0].can_close);
+        assert!(!delimiters[1].can_open && delimiters[1].can_close);
+    }
+
+    /// Every combination of up to six pieces keeps its text: removing the
+    /// marks from the input and from the output gives the same string
+    #[test]
+    fn test_all_short_combinations_keep_text() {
+        const PIECES: [&str; 8] = ["**", "*", "_", "`", "#", "^", "a", " "];
+        let strip = |s: &str| s.replace(['*', '_', '`', '#', '^'], "");
+
+        let mut sources = vec![String::new()];
+        for _ in 0..6 {
+            sources = sources
+                .iter()
+                .flat_map(|source| PIECES.iter().map(move |piece| format!("{source}{piece}")))
+                .collect();
+            for source in &sources {
+                let tokens = lex_with_spans(source);
+                let content = format_inlines(source, &tokens);
+                assert_eq!(
+                    strip(&plain_text(&content)),
+                    strip(source.trim()),
+                    "{source:?}"
+                );
+            }
+        }
+    }
+
+    /// Lines that leave thousands of marks or brackets open format in linear
+    /// time; a quadratic scan would take minutes here
+    #[test]
+    fn test_unclosed_runs_stay_literal() {
+        for pattern in ["<<", "[[a ", "footnote:[", "image:a[ ", "_x a* "] {
+            let source = pattern.repeat(20_000);
+            let content = format_inlines(&source, &lex_with_spans(&source));
+            assert_eq!(content, [Inline::Text(source.trim().to_string())]);
+        }
+        // Bold, space, link, and space, except after the last link
+        let source = "*a* http://x.y ".repeat(20_000);
+        assert_eq!(
+            format_inlines(&source, &lex_with_spans(&source)).len(),
+            4 * 20_000 - 1
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! Soft hyphens and non-breaking spaces as break hint nodes
+//!
+//! Print backends (PDF, LaTeX) break lines themselves, so they need to know
+//! where a word may be hyphenated and which spaces must not be broken at.
+//! Authors mark those with a soft hyphen or a non-breaking space: the
+//! character itself, its HTML character reference (`&shy;`, `&#173;`,
+//! `&#xAD;`, `&nbsp;`, `&#160;`, `&#xA0;`), or the `{nbsp}` attribute.
+//! [`mark_break_hints`] splits them out of the text into
+//! [`Inline::BreakHint`] nodes, so backends can honor them without scanning
+//! text for invisible characters, and the HTML renderer writes them back as
+//! `&shy;` and `&nbsp;`.
+//!
+//! Like Asciidoctor's character reference handling, this belongs to the
+//! `replacements` substitution group: blocks whose substitutions leave it out
+//! (see [`crate::subs`]) keep their text as written. Run it after
+//! [`substitute_attributes`](crate::resolve::substitute_attributes) so
+//! `{nbsp}` is already a character; [`ParseOptions::break_hints`] does both in
+//! that order.
+//!
+//! [`ParseOptions::break_hints`]: crate::options::ParseOptions::break_hints
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::ast::{Block, BreakHint, Inline};
+//! use doctora::hints::mark_break_hints;
+//! use doctora::parse_document;
+//!
+//! let mut doc = parse_document("Hyph&shy;ena&shy;tion\n").unwrap();
+//! mark_break_hints(&mut doc);
+//!
+//! let Block::Paragraph { content, .. } = &doc.blocks[0] else {
+//!     panic!("expected a paragraph");
+//! };
+//! assert_eq!(content[1], Inline::BreakHint(BreakHint::SoftHyphen));
+//! assert_eq!(content.len(), 5);
+//! ```
+
+use crate::ast::{Block, BreakHint, Document, Inline};
+use crate::resolve::substitutions;
+use crate::subs::{SubstitutionDefaults, Substitutions};
+
+/// Character references for each hint, matched case-insensitively
+const REFERENCES: [(&str, BreakHint); 6] = [
+    ("&shy;", BreakHint::SoftHyphen),
+    ("&#173;", BreakHint::SoftHyphen),
+    ("&#xad;", BreakHint::SoftHyphen),
+    ("&nbsp;", BreakHint::NoBreakSpace),
+    ("&#160;", BreakHint::NoBreakSpace),
+    ("&#xa0;", BreakHint::NoBreakSpace),
+];
+
+/// Split break hints out of the text throughout `doc` (see the module
+/// documentation)
+pub fn mark_break_hints(doc: &mut Document) {
+    mark_break_hints_with(doc, &SubstitutionDefaults::new());
+}
+
+/// [`mark_break_hints`] with the default substitutions of block kinds
+/// overridden by `defaults`
+pub(crate) fn mark_break_hints_with(doc: &mut Document, defaults: &SubstitutionDefaults) {
+    mark_blocks(&mut doc.blocks, defaults);
+}
+
+/// Split the hints out of `text`, or `None` if it has none
+///
+/// # Examples
+///
+/// ```
+/// use doctora::ast::{BreakHint, Inline};
+/// use doctora::hints::split_hints;
+///
+/// assert_eq!(
+///     split_hints("10&nbsp;km"),
+///     Some(vec![
+///         Inline::Text("10".to_string()),
+///         Inline::BreakHint(BreakHint::NoBreakSpace),
+///         Inline::Text("km".to_string()),
+///     ])
+/// );
+/// assert_eq!(split_hints("AT&T"), None);
+/// ```
+pub fn split_hints(text: &str) -> Option<Vec<Inline>> {
+    let mut nodes = Vec::new();
+    let mut start = 0;
+    let mut rest = text;
+    while let Some(offset) = rest.find(['&', '\u{00AD}', '\u{00A0}']) {
+        let at = text.len() - rest.len() + offset;
+        let (hint, len) = match hint_at(&text[at..]) {
+            Some(found) => found,
+            None => {
+                rest = &text[at + 1..];
+                continue;
+            }
+        };
+        if start < at {
+            nodes.push(Inline::Text(text[start..at].to_string()));
+        }
+        nodes.push(Inline::BreakHint(hint));
+        start = at + len;
+        rest = &text[start..];
+    }
+    if nodes.is_empty() {
+        return None;
+    }
+    if start < text.len() {
+        nodes.push(Inline::Text(text[start..].to_string()));
+    }
+    Some(nodes)
+}
+
+/// The hint at the start of `text` and its length in bytes
+fn hint_at(text: &str) -> Option<(BreakHint, usize)> {
+    if let Some(c) = text.chars().next()
+        && let Some(hint) = BreakHint::from_char(c)
+    {
+        return Some((hint, c.len_utf8()));
+    }
+    REFERENCES.iter().find_map(|(reference, hint)| {
+        text.get(..reference.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(reference))
+            .map(|_| (*hint, reference.len()))
+    })
+}
+
+fn mark_blocks(blocks: &mut [Block], defaults: &SubstitutionDefaults) {
+    for block in blocks {
+        mark_block(block, defaults);
+    }
+}
+
+fn mark_block(block: &mut Block, defaults: &SubstitutionDefaults) {
+    let enabled = substitutions(block, defaults).contains(Substitutions::REPLACEMENTS);
+    let inlines = |content: &mut Vec<Inline>| {
+        if enabled {
+            mark_inlines(content);
+        }
+    };
+    match block {
+        Block::Paragraph { content, .. } | Block::Verse { content, .. } => inlines(content),
+        Block::UnorderedList { items, .. } | Block::OrderedList { items, .. } => {
+            for item in items {
+                inlines(&mut item.content);
+                mark_blocks(&mut item.blocks, defaults);
+            }
+        }
+        Block::DescriptionList { items, .. } => {
+            for item in items {
+                inlines(&mut item.term);
+                inlines(&mut item.description);
+            }
+        }
+        Block::Table { header, rows, .. } => {
+            for row in header.iter_mut().chain(rows) {
+                for cell in &mut row.cells {
+                    inlines(&mut cell.content);
+                }
+            }
+        }
+        Block::Section { title, content, .. } => {
+            inlines(title);
+            mark_blocks(content, defaults);
+        }
+        Block::Quote { content, .. }
+        | Block::Admonition { content, .. }
+        | Block::Example { content, .. }
+        | Block::Sidebar { content, .. }
+        | Block::Open { content, .. } => mark_blocks(content, defaults),
+        Block::Listing { .. }
+        | Block::Image { .. }
+        | Block::Passthrough { .. }
+        | Block::Comment { .. }
+        | Block::AttributeEntry { .. }
+        | Block::Unknown { .. } => {}
+    }
+    // Titles get the normal substitutions, whatever the block's `subs`
+    if let Some(title) = &mut block.metadata_mut().title {
+        mark_inlines(title);
+    }
+}
+
+fn mark_inlines(content: &mut Vec<Inline>) {
+    let mut index = 0;
+    while index < content.len() {
+        match &mut content[index] {
+            Inline::Text(text) => {
+                if let Some(nodes) = split_hints(text) {
+                    let len = nodes.len();
+                    content.splice(index..=index, nodes);
+                    index += len;
+                    continue;
+                }
+            }
+            Inline::Bold(nested)
+            | Inline::Italic(nested)
+            | Inline::Monospace(nested)
+            | Inline::Highlight(nested)
+            | Inline::Span {
+                content: nested, ..
+            }
+            | Inline::Superscript(nested)
+            | Inline::Subscript(nested)
+            | Inline::Link { text: nested, .. }
+            | Inline::XRef { text: nested, .. }
+            | Inline::Footnote { text: nested, .. } => mark_inlines(nested),
+            Inline::Anchor(_)
+            | Inline::Image { .. }
+            | Inline::Icon { .. }
+            | Inline::BreakHint(_) => {}
+        }
+        index += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    fn text(text: &str) -> Inline {
+        Inline::Text(text.to_string())
+    }
+
+    #[test]
+    fn test_split_hints() {
+        let shy = Inline::BreakHint(BreakHint::SoftHyphen);
+        let nbsp = Inline::BreakHint(BreakHint::NoBreakSpace);
+        assert_eq!(
+            split_hints("&SHY;a&#173;b&#xAD;\u{00AD}"),
+            Some(vec![
+                shy.clone(),
+                text("a"),
+                shy.clone(),
+                text("b"),
+                shy.clone(),
+                shy
+            ])
+        );
+        assert_eq!(
+            split_hints("a\u{00A0}&#160;&#xa0;&nbsp;&amp; b"),
+            Some(vec![
+                text("a"),
+                nbsp.clone(),
+                nbsp.clone(),
+                nbsp.clone(),
+                nbsp,
+                text("&amp; b")
+            ])
+        );
+        assert_eq!(split_hints("a & b &nbs"), None);
+    }
+
+    #[test]
+    fn test_mark_follows_subs() {
+        let mut doc = parse_document(
+            "* *a&nbsp;b*\n\n[subs=\"-replacements\"]\n|===\n|c&nbsp;d\n|===\n\n\
+             ----\ne&nbsp;f\n----\n",
+        )
+        .unwrap();
+        let before = doc.blocks[1..].to_vec();
+        mark_break_hints(&mut doc);
+
+        let Block::UnorderedList { items, .. } = &doc.blocks[0] else {
+            panic!("expected a list, got {:?}", doc.blocks[0]);
+        };
+        assert_eq!(
+            items[0].content,
+            vec![Inline::Bold(vec![
+                text("a"),
+                Inline::BreakHint(BreakHint::NoBreakSpace),
+                text("b"),
+            ])]
+        );
+        assert_eq!(doc.blocks[1..], before);
+    }
+}
@@ -0,0 +1,888 @@
+//! Include directive expansion
+//!
+//! `include::target[]` lines are replaced with the contents of the target
+//! before the source is lexed, so the parser sees one document. Expansion is
+//! a preprocessing stage: [`expand_includes`] works on text and knows nothing
+//! about blocks, except that headings inside verbatim blocks are not
+//! headings (see Level Offsets below). Enable it for a parse with
+//! [`ParseOptions::includes`](crate::options::ParseOptions::includes).
+//!
+//! Targets are resolved against the directory of the including file, or
+//! [`IncludeOptions::new`]'s base directory for the document itself, and read
+//! through an [`IncludeResolver`]. The default, [`FileResolver`], reads from
+//! the filesystem; embedders that serve documents from memory, a database, or
+//! over the network plug in their own. URL targets are passed to the resolver
+//! as written. Attribute references in targets (`include::{partials}/a.adoc[]`)
+//! resolve against the attributes passed in and attribute entries seen so far.
+//! A directive escaped with a backslash (`\include::a.adoc[]`) is kept as text.
+//!
+//! # Selecting Lines
+//!
+//! | Attribute           | Included lines                                       |
+//! |---------------------|------------------------------------------------------|
+//! | `lines=2..4;7`      | Lines 2 to 4 and line 7 (`,` also separates ranges)   |
+//! | `lines=5..-1`       | Line 5 to the end (`5..` too)                         |
+//! | `tag=intro`         | Lines between `tag::intro[]` and `end::intro[]`       |
+//! | `tags=a;b`          | Lines in either region                                |
+//! | `tags=!debug`       | All lines except those in the `debug` region          |
+//! | `tags=*`, `tags=**` | Lines in any region; all lines                        |
+//!
+//! Tag markers can appear anywhere on a line, so they usually sit in the
+//! included file's comments (`# tag::intro[]`). When selecting by tag, the
+//! marker lines themselves are left out. Regions nest; a region that isn't
+//! named follows the region around it. `lines` takes precedence over `tag`
+//! and `tags`.
+//!
+//! # Level Offsets
+//!
+//! `leveloffset=+1` demotes the included file's section titles by one level
+//! (`== A` becomes `=== A`), `leveloffset=-1` promotes them, and
+//! `leveloffset=2` sets the offset outright. Offsets accumulate through nested
+//! includes. Lines inside listing, literal, passthrough, and comment blocks
+//! are left alone.
+//!
+//! # Non-AsciiDoc Files
+//!
+//! Only files with an AsciiDoc extension (`.adoc`, `.asciidoc`, `.ad`,
+//! `.asc`, `.txt`) are expanded themselves: the lines of any other file
+//! (`include::script.sh[]`) go in as written, with their include directives,
+//! attribute entries and headings left alone, so code samples can be included
+//! into listing blocks. `lines` and `tags` still select from them.
+//!
+//! With [`IncludeOptions::infer_languages`], a listing block (`----`) whose
+//! first include is such a file becomes a source block in the language of
+//! its extension (`.sh` is `bash`, `.rs` is `rust`), unless the block names a
+//! language already. The block's attribute line gets the language
+//! (`[source]` becomes `[source,bash]`), or one is added if it has none.
+//!
+//! ```
+//! use doctora::attributes::Attributes;
+//! use doctora::include::{IncludeOptions, expand_includes};
+//! use std::collections::HashMap;
+//! use std::path::PathBuf;
+//!
+//! let files = HashMap::from([(PathBuf::from("run.sh"), "echo == hi\n".to_string())]);
+//! let options = IncludeOptions::new(".").resolver(files).infer_languages(true);
+//! let source = ".Run it\n----\ninclude::run.sh[]\n----\n";
+//!
+//! assert_eq!(
+//!     expand_includes(source, &Attributes::new(), &options).unwrap(),
+//!     ".Run it\n[source,bash]\n----\necho == hi\n----\n"
+//! );
+//! ```
+//!
+//! # Events
+//!
+//! [`expand_includes_observed`] reports each include it reads to an
+//! [`events::Subscriber`](crate::events::Subscriber) as an
+//! [`Event::IncludeResolved`], with the resolved path and how deeply the
+//! include is nested:
+//!
+//! ```
+//! use doctora::attributes::Attributes;
+//! use doctora::events::Event;
+//! use doctora::include::{IncludeOptions, expand_includes_observed};
+//! use std::collections::HashMap;
+//! use std::path::PathBuf;
+//! use std::sync::Mutex;
+//!
+//! let files = HashMap::from([(PathBuf::from("docs/a.adoc"), "A.\n".to_string())]);
+//! let options = IncludeOptions::new("docs").resolver(files);
+//! let paths = Mutex::new(Vec::new());
+//! let subscriber = |event: &Event<'_>| {
+//!     if let Event::IncludeResolved { path, depth, .. } = event {
+//!         paths.lock().unwrap().push((path.to_path_buf(), *depth));
+//!     }
+//! };
+//!
+//! let source = "include::a.adoc[]\n";
+//! expand_includes_observed(source, &Attributes::new(), &options, "guide.adoc", &subscriber)
+//!     .unwrap();
+//! assert_eq!(paths.into_inner().unwrap(), [(PathBuf::from("docs/a.adoc"), 1)]);
+//! ```
+//!
+//! # Limits
+//!
+//! An include of a file that is already being expanded is a cycle and fails
+//! with [`IncludeError::Cycle`]. Includes nested deeper than
+//! [`IncludeOptions::max_depth`] (default [`DEFAULT_MAX_DEPTH`]) fail with
+//! [`IncludeError::TooDeep`].
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::attributes::Attributes;
+//! use doctora::include::{IncludeOptions, expand_includes};
+//! use std::collections::HashMap;
+//! use std::path::PathBuf;
+//!
+//! let files = HashMap::from([(
+//!     PathBuf::from("docs/chapters/intro.adoc"),
+//!     "= Intro\n\nWelcome.\n".to_string(),
+//! )]);
+//! let options = IncludeOptions::new("docs").resolver(files);
+//! let source = "= Guide\n\ninclude::chapters/intro.adoc[leveloffset=+1]\n";
+//!
+//! assert_eq!(
+//!     expand_includes(source, &Attributes::new(), &options).unwrap(),
+//!     "= Guide\n\n== Intro\n\nWelcome.\n"
+//! );
+//! ```
+
+use crate::attributes::{AttributeError, Attributes, parse_attribute_list};
+use crate::audit::normalize;
+use crate::events::{Event, NoopSubscriber, Subscriber};
+use crate::parser_winnow::attribute_entry;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Default limit on how deeply includes nest
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// Source of included files
+pub trait IncludeResolver: Debug + Send + Sync {
+    /// Returns the contents of `path`
+    ///
+    /// `path` is the target resolved against the including file's directory,
+    /// with `.` and `..` removed, or the URL as written.
+    fn read(&self, path: &Path) -> io::Result<String>;
+}
+
+/// Reads included files from the filesystem
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileResolver;
+
+impl IncludeResolver for FileResolver {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+}
+
+/// Files held in memory, by path
+impl IncludeResolver for HashMap<PathBuf, String> {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        self.get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+}
+
+/// Where includes are read from and how deeply they may nest
+#[derive(Debug, Clone)]
+pub struct IncludeOptions {
+    /// Directory the document's own include targets are relative to
+    base_dir: PathBuf,
+    /// Deepest allowed nesting of includes
+    max_depth: usize,
+    /// Mark listing blocks that include source files as source blocks
+    infer_languages: bool,
+    resolver: Arc<dyn IncludeResolver>,
+}
+
+impl IncludeOptions {
+    /// Creates options resolving targets against `base_dir` on the filesystem
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            infer_languages: false,
+            resolver: Arc::new(FileResolver),
+        }
+    }
+
+    /// Sets how deeply includes may nest (an include in the document itself
+    /// is depth 1)
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets whether listing blocks that include a non-AsciiDoc file become
+    /// source blocks in the file's language (see the module documentation)
+    pub fn infer_languages(mut self, infer_languages: bool) -> Self {
+        self.infer_languages = infer_languages;
+        self
+    }
+
+    /// Reads included files through `resolver` instead of the filesystem
+    pub fn resolver(mut self, resolver: impl IncludeResolver + 'static) -> Self {
+        self.resolver = Arc::new(resolver);
+        self
+    }
+}
+
+impl Default for IncludeOptions {
+    fn default() -> Self {
+        Self::new(".")
+    }
+}
+
+/// Error produced when an include cannot be expanded
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum IncludeError {
+    /// The resolver failed to read the target
+    #[error("cannot read include {}: {message}", path.display())]
+    Read {
+        /// Resolved target
+        path: PathBuf,
+        /// The resolver's error
+        message: String,
+    },
+    /// The target is already being expanded
+    #[error("include cycle: {} includes itself", path.display())]
+    Cycle {
+        /// Resolved target
+        path: PathBuf,
+    },
+    /// Includes nest deeper than [`IncludeOptions::max_depth`]
+    #[error("includes nested deeper than {max_depth} levels at {}", path.display())]
+    TooDeep {
+        /// Resolved target that would exceed the limit
+        path: PathBuf,
+        /// The configured limit
+        max_depth: usize,
+    },
+    /// An attribute entry resolves to a value longer than
+    /// [`DEFAULT_MAX_ATTRIBUTE_LENGTH`](crate::attributes::DEFAULT_MAX_ATTRIBUTE_LENGTH)
+    #[error(transparent)]
+    Attributes(#[from] AttributeError),
+}
+
+/// Replace the include directives in `source` with the files they include
+/// (see the module documentation)
+///
+/// `attributes` are used to resolve references in include targets.
+pub fn expand_includes(
+    source: &str,
+    attributes: &Attributes,
+    options: &IncludeOptions,
+) -> Result<String, IncludeError> {
+    expand_includes_observed(source, attributes, options, "", &NoopSubscriber)
+}
+
+/// [`expand_includes`], reporting each include read to `subscriber` as an
+/// [`Event::IncludeResolved`] for `document`
+pub fn expand_includes_observed(
+    source: &str,
+    attributes: &Attributes,
+    options: &IncludeOptions,
+    document: &str,
+    subscriber: &dyn Subscriber,
+) -> Result<String, IncludeError> {
+    let mut expander = Expander {
+        options,
+        document,
+        subscriber,
+        attributes: attributes.clone(),
+        stack: Vec::new(),
+        out: String::with_capacity(source.len()),
+    };
+    expander.expand(source, &options.base_dir, 0)?;
+    Ok(expander.out)
+}
+
+struct Expander<'a> {
+    options: &'a IncludeOptions,
+    /// Name of the document being expanded, for events
+    document: &'a str,
+    subscriber: &'a dyn Subscriber,
+    /// Attributes for include targets, updated by attribute entries
+    attributes: Attributes,
+    /// Files being expanded, outermost first
+    stack: Vec<PathBuf>,
+    out: String,
+}
+
+impl Expander<'_> {
+    /// Expand `source`, read from `dir`, shifting its headings by `offset`
+    fn expand(&mut self, source: &str, dir: &Path, offset: isize) -> Result<(), IncludeError> {
+        let mut fence: Option<&str> = None;
+        // Where the open listing block's delimiter starts in the output,
+        // until its language is settled
+        let mut listing: Option<usize> = None;
+        for line in source.split_inclusive('\n') {
+            let text = line.trim_end_matches(['\n', '\r']);
+            if let Some(escaped) = text.strip_prefix("\\include::")
+                && directive(escaped).is_some()
+            {
+                self.out.push_str(&line[1..]);
+                continue;
+            }
+            if let Some((target, attrlist)) = text.strip_prefix("include::").and_then(directive) {
+                if let Some(start) = listing.take() {
+                    let target = self.attributes.interpolate(target);
+                    if let Some(language) = source_language(&target) {
+                        self.set_language(start, language);
+                    }
+                }
+                self.include(target, attrlist, dir, offset)?;
+                continue;
+            }
+
+            if fence == Some(text) {
+                fence = None;
+                listing = None;
+            } else if fence.is_none() && is_verbatim_delimiter(text) {
+                fence = Some(text);
+                if self.options.infer_languages && text.starts_with('-') {
+                    listing = Some(self.out.len());
+                }
+            } else if fence.is_some() {
+                // Only an include on the block's first line names its language
+                listing = None;
+            }
+            if fence.is_some() {
+                self.out.push_str(line);
+                continue;
+            }
+            if let Some(entry) = attribute_entry(text) {
+                match entry.value {
+                    Some(value) => {
+                        self.attributes.define(entry.name, &value)?;
+                    }
+                    None => {
+                        self.attributes.unset(&entry.name);
+                    }
+                }
+            }
+            match shift_heading(text, offset) {
+                Some(heading) => {
+                    self.out.push_str(&heading);
+                    self.out.push_str(&line[text.len()..]);
+                }
+                None => self.out.push_str(line),
+            }
+        }
+        Ok(())
+    }
+
+    fn include(
+        &mut self,
+        target: &str,
+        attrlist: &str,
+        dir: &Path,
+        offset: isize,
+    ) -> Result<(), IncludeError> {
+        let target = self.attributes.interpolate(target);
+        let path = if target.contains("://") {
+            PathBuf::from(&target)
+        } else {
+            normalize(&dir.join(&target))
+        };
+        if self.stack.contains(&path) {
+            return Err(IncludeError::Cycle { path });
+        }
+        if self.stack.len() >= self.options.max_depth {
+            return Err(IncludeError::TooDeep {
+                path,
+                max_depth: self.options.max_depth,
+            });
+        }
+        let content = self
+            .options
+            .resolver
+            .read(&path)
+            .map_err(|err| IncludeError::Read {
+                path: path.clone(),
+                message: err.to_string(),
+            })?;
+        self.subscriber.on_event(&Event::IncludeResolved {
+            document: self.document,
+            target: &target,
+            path: &path,
+            depth: self.stack.len() + 1,
+        });
+
+        let attrs = parse_attribute_list(attrlist);
+        let selected = if let Some(spec) = attrs.get("lines") {
+            select_lines(&content, &line_ranges(spec))
+        } else if let Some(spec) = attrs.get("tags").or_else(|| attrs.get("tag")) {
+            select_tags(&content, &TagFilter::parse(spec))
+        } else {
+            content
+        };
+        let offset = match attrs.get("leveloffset") {
+            Some(spec) => level_offset(spec, offset),
+            None => offset,
+        };
+
+        if is_asciidoc(&path) {
+            let parent = path.parent().unwrap_or(Path::new("")).to_path_buf();
+            self.stack.push(path);
+            self.expand(&selected, &parent, offset)?;
+            self.stack.pop();
+        } else {
+            self.out.push_str(&selected);
+        }
+        if !selected.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+        Ok(())
+    }
+
+    /// Make the listing block whose delimiter starts at `start` in the output
+    /// a source block in `language`, unless it names a language or has
+    /// another style
+    fn set_language(&mut self, start: usize, language: &str) {
+        let before = self.out[..start].strip_suffix('\n');
+        let line_start = before.map_or(0, |before| before.rfind('\n').map_or(0, |i| i + 1));
+        let line = before.map_or("", |before| &before[line_start..]);
+        let Some(list) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+            .filter(|list| !list.starts_with('['))
+        else {
+            self.out
+                .insert_str(start, &format!("[source,{language}]\n"));
+            return;
+        };
+        let attrs = parse_attribute_list(list);
+        if attrs.style() != Some("source") || attrs.contains("2") || attrs.contains("language") {
+            return;
+        }
+        // The style is the first entry, so the language goes right after it
+        let style_end = line_start + 1 + list.find(',').unwrap_or(list.len());
+        self.out.insert_str(style_end, &format!(",{language}"));
+    }
+}
+
+/// True if `path` has an AsciiDoc extension, so its lines are expanded
+fn is_asciidoc(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|extension| extension.to_str()),
+        Some("adoc" | "asciidoc" | "ad" | "asc" | "txt")
+    )
+}
+
+/// Source language of the non-AsciiDoc file `target`, by its extension
+fn source_language(target: &str) -> Option<&'static str> {
+    let path = Path::new(target);
+    if is_asciidoc(path) {
+        return None;
+    }
+    let language = match path.extension()?.to_str()? {
+        "sh" | "bash" => "bash",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "cpp",
+        "cs" => "csharp",
+        "css" => "css",
+        "go" => "go",
+        "html" | "htm" => "html",
+        "java" => "java",
+        "js" | "mjs" => "javascript",
+        "json" => "json",
+        "kt" => "kotlin",
+        "py" => "python",
+        "rb" => "ruby",
+        "rs" => "rust",
+        "sql" => "sql",
+        "toml" => "toml",
+        "ts" => "typescript",
+        "xml" => "xml",
+        "yml" | "yaml" => "yaml",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// Split what follows `include::` into the target and attribute list
+pub(crate) fn directive(rest: &str) -> Option<(&str, &str)> {
+    let (target, attrlist) = rest.split_once('[')?;
+    let attrlist = attrlist.strip_suffix(']')?;
+    (!target.is_empty() && !target.contains(char::is_whitespace)).then_some((target, attrlist))
+}
+
+/// True for the delimiter of a block whose lines aren't parsed as AsciiDoc
+pub(crate) fn is_verbatim_delimiter(line: &str) -> bool {
+    line.len() >= 4
+        && ['-', '.', '+', '/']
+            .iter()
+            .any(|&c| line.chars().all(|ch| ch == c))
+}
+
+/// `line` with its section title markers shifted by `offset`, if it is a
+/// section title and `offset` moves it
+fn shift_heading(line: &str, offset: isize) -> Option<String> {
+    if offset == 0 {
+        return None;
+    }
+    let markers = line.len() - line.trim_start_matches('=').len();
+    let title = &line[markers..];
+    if markers == 0 || markers > 6 || !title.starts_with(' ') || title.trim().is_empty() {
+        return None;
+    }
+    let markers = (markers as isize + offset).max(1) as usize;
+    Some(format!("{}{}", "=".repeat(markers), title))
+}
+
+/// The offset for a `leveloffset` value, relative to `current` when signed
+fn level_offset(spec: &str, current: isize) -> isize {
+    let spec = spec.trim();
+    match spec.parse::<isize>() {
+        Ok(value) if spec.starts_with(['+', '-']) => current + value,
+        Ok(value) => value,
+        Err(_) => current,
+    }
+}
+
+/// Parse a `lines` value into 1-based inclusive ranges (`None` ends at the
+/// last line); entries that aren't numbers are skipped
+fn line_ranges(spec: &str) -> Vec<(usize, Option<usize>)> {
+    spec.split([';', ','])
+        .map(str::trim)
+        .filter_map(|entry| match entry.split_once("..") {
+            Some((start, end)) => {
+                let start = start.trim().parse().ok()?;
+                let end = match end.trim() {
+                    "" | "-1" => None,
+                    end => Some(end.parse().ok()?),
+                };
+                Some((start, end))
+            }
+            None => {
+                let line = entry.parse().ok()?;
+                Some((line, Some(line)))
+            }
+        })
+        .collect()
+}
+
+fn select_lines(content: &str, ranges: &[(usize, Option<usize>)]) -> String {
+    content
+        .split_inclusive('\n')
+        .enumerate()
+        .filter(|(index, _)| {
+            let number = index + 1;
+            ranges
+                .iter()
+                .any(|&(start, end)| number >= start && end.is_none_or(|end| number <= end))
+        })
+        .map(|(_, line)| line)
+        .collect()
+}
+
+/// Which tagged regions a `tag`/`tags` value selects
+struct TagFilter {
+    /// Whether lines outside every region are included
+    untagged: bool,
+    /// Selection of regions not named in the value, if it sets one (`*`,
+    /// `!*`)
+    wildcard: Option<bool>,
+    /// Selection of named regions
+    named: HashMap<String, bool>,
+}
+
+impl TagFilter {
+    fn parse(spec: &str) -> Self {
+        let mut filter = TagFilter {
+            untagged: true,
+            wildcard: None,
+            named: HashMap::new(),
+        };
+        let mut all_lines = false;
+        for tag in spec
+            .split([';', ','])
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+        {
+            let (name, selected) = match tag.strip_prefix('!') {
+                Some(name) => (name, false),
+                None => (tag, true),
+            };
+            match name {
+                "**" => all_lines = selected,
+                "*" => filter.wildcard = Some(selected),
+                name => {
+                    filter.named.insert(name.to_string(), selected);
+                }
+            }
+            if selected && name != "**" {
+                filter.untagged = false;
+            }
+        }
+        if all_lines {
+            filter.untagged = true;
+        }
+        filter
+    }
+}
+
+fn select_tags(content: &str, filter: &TagFilter) -> String {
+    let mut out = String::new();
+    // Open regions, innermost last, and whether each is selected
+    let mut regions: Vec<(&str, bool)> = Vec::new();
+    for line in content.split_inclusive('\n') {
+        if let Some(name) = tag_marker(line, "tag::") {
+            let outer = regions
+                .last()
+                .map_or(filter.untagged, |&(_, selected)| selected);
+            let selected = filter
+                .named
+                .get(name)
+                .copied()
+                .or(filter.wildcard)
+                .unwrap_or(outer);
+            regions.push((name, selected));
+        } else if let Some(name) = tag_marker(line, "end::") {
+            if let Some(index) = regions.iter().rposition(|&(open, _)| open == name) {
+                regions.truncate(index);
+            }
+        } else if regions
+            .last()
+            .map_or(filter.untagged, |&(_, selected)| selected)
+        {
+            out.push_str(line);
+        }
+    }
+    out
+}
+
+/// Name of the `tag::name[]` or `end::name[]` marker (`prefix`) in `line`
+fn tag_marker<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    line.match_indices(prefix).find_map(|(at, _)| {
+        let before = line[..at].chars().next_back();
+        if before.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            return None;
+        }
+        let rest = &line[at + prefix.len()..];
+        let name = rest.split_once("[]")?.0;
+        (!name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-'))
+        .then_some(name)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(entries: &[(&str, &str)]) -> HashMap<PathBuf, String> {
+        entries
+            .iter()
+            .map(|(path, content)| (PathBuf::from(path), content.to_string()))
+            .collect()
+    }
+
+    fn expand(source: &str, entries: &[(&str, &str)]) -> Result<String, IncludeError> {
+        let options = IncludeOptions::new("docs").resolver(files(entries));
+        expand_includes(source, &Attributes::new(), &options)
+    }
+
+    #[test]
+    fn test_nested_relative_paths() {
+        let expanded = expand(
+            ":parts: parts\n\ninclude::{parts}/a.adoc[]\nEnd.\n\\include::x.adoc[]\n",
+            &[
+                ("docs/parts/a.adoc", "A.\ninclude::../shared/b.adoc[]"),
+                ("docs/shared/b.adoc", "B.\r\n"),
+            ],
+        );
+        assert_eq!(
+            expanded.unwrap(),
+            ":parts: parts\n\nA.\nB.\r\nEnd.\ninclude::x.adoc[]\n"
+        );
+    }
+
+    #[test]
+    fn test_include_events() {
+        use std::sync::Mutex;
+
+        let log = Mutex::new(Vec::new());
+        let subscriber = |event: &Event<'_>| {
+            if let Event::IncludeResolved {
+                document,
+                target,
+                path,
+                depth,
+            } = event
+            {
+                log.lock()
+                    .unwrap()
+                    .push(format!("{document} {target} {} {depth}", path.display()));
+            }
+        };
+        let options = IncludeOptions::new("docs").resolver(files(&[
+            (
+                "docs/parts/a.adoc",
+                "include::../shared/b.adoc[]\ninclude::c.sh[]\n",
+            ),
+            ("docs/shared/b.adoc", "B.\n"),
+            ("docs/parts/c.sh", "include::d.adoc[]\n"),
+        ]));
+        let source = ":parts: parts\ninclude::{parts}/a.adoc[]\ninclude::missing.adoc[]\n";
+        let result =
+            expand_includes_observed(source, &Attributes::new(), &options, "guide", &subscriber);
+
+        assert!(matches!(result, Err(IncludeError::Read { .. })));
+        // Targets that fail to read, and directives in non-AsciiDoc files,
+        // are not reported
+        assert_eq!(
+            log.into_inner().unwrap(),
+            [
+                "guide parts/a.adoc docs/parts/a.adoc 1",
+                "guide ../shared/b.adoc docs/shared/b.adoc 2",
+                "guide c.sh docs/parts/c.sh 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lines() {
+        let content = "1\n2\n3\n4\n5\n6\n";
+        let expanded = expand(
+            "include::n.txt[lines=\"2..3,5\"]\n--\ninclude::n.txt[lines=5..-1]\n",
+            &[("docs/n.txt", content)],
+        );
+        assert_eq!(expanded.unwrap(), "2\n3\n5\n--\n5\n6\n");
+        assert_eq!(line_ranges("x;2..;4..y"), vec![(2, None)]);
+    }
+
+    #[test]
+    fn test_tags() {
+        let content = "top\n# tag::a[]\na\n# tag::b[]\nb\n# end::b[]\n# end::a[]\n\
+                       // tag::c[]\nc\n// end::c[]\n";
+        let select = |spec: &str| select_tags(content, &TagFilter::parse(spec));
+        assert_eq!(select("a"), "a\nb\n");
+        assert_eq!(select("a;!b"), "a\n");
+        assert_eq!(select("b;c"), "b\nc\n");
+        assert_eq!(select("!a"), "top\nc\n");
+        assert_eq!(select("*"), "a\nb\nc\n");
+        assert_eq!(select("**"), "top\na\nb\nc\n");
+        assert_eq!(select("**;!*"), "top\n");
+        assert_eq!(select("missing"), "");
+
+        let expanded = expand("include::t.rb[tag=b]\n", &[("docs/t.rb", content)]);
+        assert_eq!(expanded.unwrap(), "b\n");
+    }
+
+    #[test]
+    fn test_level_offset() {
+        let expanded = expand(
+            "include::a.adoc[leveloffset=+1]\n",
+            &[
+                (
+                    "docs/a.adoc",
+                    "= A\n\n----\n== code\n----\n\ninclude::b.adoc[leveloffset=+1]\n",
+                ),
+                ("docs/b.adoc", "== B\n=not a title\n"),
+            ],
+        );
+        assert_eq!(
+            expanded.unwrap(),
+            "== A\n\n----\n== code\n----\n\n==== B\n=not a title\n"
+        );
+        assert_eq!(level_offset("-1", 2), 1);
+        assert_eq!(level_offset("3", 2), 3);
+        assert_eq!(shift_heading("== B", -3).as_deref(), Some("= B"));
+    }
+
+    #[test]
+    fn test_non_asciidoc_files() {
+        let script = "# tag::run[]\ninclude::x.sh[]\n== not a title\n# end::run[]\n";
+        let expanded = expand(
+            "----\ninclude::run.sh[tag=run]\n----\n\ninclude::a.adoc[leveloffset=+1]\n",
+            &[("docs/run.sh", script), ("docs/a.adoc", "== A\n")],
+        );
+        assert_eq!(
+            expanded.unwrap(),
+            "----\ninclude::x.sh[]\n== not a title\n----\n\n=== A\n"
+        );
+    }
+
+    #[test]
+    fn test_infer_languages() {
+        let entries = files(&[
+            ("docs/a.rs", "fn a() {}\n"),
+            ("docs/b.adoc", "B\n"),
+            ("docs/c.xyz", "C\n"),
+        ]);
+        let infer = |source: &str, infer_languages: bool| {
+            let options = IncludeOptions::new("docs")
+                .resolver(entries.clone())
+                .infer_languages(infer_languages);
+            expand_includes(source, &Attributes::new(), &options).unwrap()
+        };
+        for (source, expected) in [
+            (
+                "----\ninclude::a.rs[]\n----\n",
+                "[source,rust]\n----\nfn a() {}\n----\n",
+            ),
+            (
+                "[source]\n----\ninclude::a.rs[]\n----\n",
+                "[source,rust]\n----\n",
+            ),
+            (
+                "[source#x,indent=0]\n----\ninclude::a.rs[]\n----\n",
+                "[source#x,rust,indent=0]\n----\n",
+            ),
+            (
+                "[source,c]\n----\ninclude::a.rs[]\n----\n",
+                "[source,c]\n----\n",
+            ),
+            (
+                "[source,language=c]\n----\ninclude::a.rs[]\n----\n",
+                "[source,language=c]\n----\n",
+            ),
+            (
+                "[literal]\n----\ninclude::a.rs[]\n----\n",
+                "[literal]\n----\n",
+            ),
+            (
+                "[[id]]\n----\ninclude::a.rs[]\n----\n",
+                "[[id]]\n[source,rust]\n----\n",
+            ),
+            // Only listing blocks, only the first line, only known extensions
+            ("....\ninclude::a.rs[]\n....\n", "....\nfn a() {}\n"),
+            ("----\nx\ninclude::a.rs[]\n----\n", "----\nx\n"),
+            (
+                "----\ninclude::b.adoc[]\ninclude::a.rs[]\n----\n",
+                "----\nB\n",
+            ),
+            ("----\ninclude::c.xyz[]\n----\n", "----\nC\n"),
+            ("include::a.rs[]\n----\n----\n", "fn a() {}\n----\n----\n"),
+        ] {
+            assert!(infer(source, true).starts_with(expected), "{source}");
+        }
+        assert_eq!(
+            infer("----\ninclude::a.rs[]\n----\n", false),
+            "----\nfn a() {}\n----\n"
+        );
+    }
+
+    #[test]
+    fn test_cycles_and_depth() {
+        let entries = [
+            ("docs/a.adoc", "include::b.adoc[]\n"),
+            ("docs/b.adoc", "include::./a.adoc[]\n"),
+        ];
+        assert_eq!(
+            expand("include::a.adoc[]\n", &entries),
+            Err(IncludeError::Cycle {
+                path: PathBuf::from("docs/a.adoc")
+            })
+        );
+
+        let options = IncludeOptions::new("docs")
+            .resolver(files(&[("docs/c.adoc", "C\n")]))
+            .max_depth(0);
+        assert_eq!(
+            expand_includes("include::c.adoc[]\n", &Attributes::new(), &options),
+            Err(IncludeError::TooDeep {
+                path: PathBuf::from("docs/c.adoc"),
+                max_depth: 0
+            })
+        );
+
+        let missing = expand("include::gone.adoc[]\n", &[]).unwrap_err();
+        assert!(
+            matches!(missing, IncludeError::Read { ref path, .. } if path == Path::new("docs/gone.adoc"))
+        );
+    }
+}
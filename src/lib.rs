@@ -22,7 +22,7 @@
 //!
 //! match result {
 //!     Ok(doc) => println!("Parsed {} blocks", doc.blocks.len()),
-//!     Err(errors) => eprintln!("Parse errors: {:?}", errors),
+//!     Err(err) => eprintln!("Parse error: {err}"),
 //! }
 //! ```
 //!
@@ -31,16 +31,95 @@
 //! - [`token`] - Lexical tokens for AsciiDoc (Logos-based lexer)
 //! - [`ast`] - Abstract Syntax Tree types
 //! - [`parser_winnow`] - Winnow-based parser for building AST from tokens
-//! - [`error_recovery`] - Custom error recovery layer (design/future implementation)
+//! - [`antora`] - Antora-style component versions, resource IDs, and site builds
+//! - [`attribute_report`] - Attribute definitions, overrides, and references across a document set
+//! - [`attributes`] - Document attribute storage and value interpolation
+//! - [`audit`] - Security audit of passthroughs, includes, and external URLs
+//! - [`batch`] - Batch parsing with abort, skip, or stub failure policies
+//! - [`clock`] - Clock abstraction and date/time attribute formatting
+//! - [`deprecations`] - Deprecated syntax diagnostics and migration to modern syntax
+//! - [`doc_coverage`] - Documentation coverage of code API symbols
+//! - [`engine`] - Shared, thread-safe engine for long-running services
+//! - [`error_recovery`] - Structured parse errors (`ParseError`) and error recovery design
+//! - [`events`] - Pipeline events and subscribers for build observability
+//! - [`exercises`] - Exercise and solution numbering for training material
+//! - [`footnotes`] - Footnote numbering for renderers
+//! - [`formatting`] - Bold and italic mark pairing with literal fallback
+//! - [`hints`] - Soft hyphens and non-breaking spaces as break hint nodes
+//! - [`include`](mod@include) - Include directive expansion before lexing
+//! - [`limits`] - Size limits for lists and tables with truncation records
+//! - [`lint`] - Structural lint rules with automatic fixes
+//! - [`metrics`] - Rendered lengths and source columns of inline content
+//! - [`options`] - Parse options (`ParseOptions`)
+//! - [`orphans`] - Detection of files and sections that never reach the output
+//! - [`outline`] - Numbered lists of figures, tables, and listings (`toc::[figures]`)
+//! - [`processor`] - Output processor trait and registry
+//! - [`preview`] - Live preview updates and their WebSocket transport
+//! - [`redirects`] - Redirect maps for section anchors that move between builds
+//! - [`render`] - Output renderers (HTML5, AsciiDoc)
+//! - [`resolve`] - Attribute reference substitution over the parsed AST
+//! - [`scheduler`] - Memory-aware parallel batch parsing
+//! - [`search`] - Regular expression search over prose, skipping code and URLs
+//! - [`schema`] - Versioned JSON serialization of the AST with migrations
+//! - [`normalize`] - Opt-in whitespace normalization of source text
+//! - [`site`] - Sitemap and navigation metadata for batch builds
+//! - [`subs`] - Substitution groups and `subs` attribute parsing
+//! - [`targets`] - Attribute-based image, icon, and cross-reference target resolution
+//! - [`theme`] - Shareable HTML themes (templates, assets, config) for batch builds
+//! - [`section_index`] - Per-section aggregations (summaries, key terms) for side panels
+//! - [`unicode`] - Opt-in Unicode normalization (NFC, NFKC, ...) of text in the AST
+//! - [`xref`] - Anchors and cross reference validation
 
+pub mod antora;
 pub mod ast;
+pub mod attribute_report;
+pub mod attributes;
+pub mod audit;
+pub mod batch;
+pub mod clock;
+pub mod deprecations;
+pub mod doc_coverage;
+pub mod engine;
 pub mod error_recovery;
+pub mod events;
+pub mod exercises;
+pub mod footnotes;
+pub mod formatting;
+pub mod hints;
+pub mod include;
+pub mod limits;
+pub mod lint;
+pub mod metrics;
+pub mod normalize;
+pub mod options;
+pub mod orphans;
+pub mod outline;
 pub mod parser_winnow;
+pub mod preview;
+pub mod processor;
+pub mod redirects;
+pub mod render;
+pub mod replacements;
+pub mod resolve;
+pub mod scheduler;
+pub mod schema;
+pub mod search;
+pub mod section_index;
+pub mod site;
+pub mod subs;
+pub mod targets;
+pub mod theme;
 pub mod token;
+pub mod unicode;
+pub mod xref;
+
+pub use error_recovery::{ParseError, Severity};
 
 use ast::Document;
-use logos::Logos;
-use token::Token;
+use error_recovery::ErrorRecovery;
+use limits::Limits;
+use options::{ParseOptions, ParserBackend};
+use subs::SubstitutionDefaults;
 
 /// Parse an AsciiDoc document from text input
 ///
@@ -54,7 +133,7 @@ use token::Token;
 ///
 /// # Returns
 ///
-/// Returns `Ok(Document)` on success, or `Err(String)` if parsing fails.
+/// Returns `Ok(Document)` on success, or a [`ParseError`] if parsing fails.
 ///
 /// # Examples
 ///
@@ -71,13 +150,14 @@ use token::Token;
 /// "#;
 ///
 /// let doc = parse_document(input).expect("parse failed");
-/// assert_eq!(doc.blocks.len(), 1); // One top-level section
+/// assert_eq!(doc.attributes.get("doctitle"), Some("Document Title"));
+/// assert_eq!(doc.blocks.len(), 2); // A paragraph and a section
 /// ```
 ///
 /// # Errors
 ///
-/// Parse errors include:
-/// - Unexpected tokens
+/// Parse errors ([`ParseError`]) include:
+/// - Unexpected tokens, with their source span
 /// - Unclosed formatting delimiters
 /// - Invalid document structure
 ///
@@ -85,12 +165,168 @@ use token::Token;
 ///
 /// Uses Winnow parser (16-45% faster than alternatives) with zero-copy design
 /// for optimal performance on documents of all sizes.
-pub fn parse_document(input: &str) -> Result<Document, String> {
-    // Step 1: Lex the input into tokens
-    let tokens: Vec<Token> = Token::lexer(input)
-        .filter_map(|result| result.ok()) // Skip lexer errors for now
-        .collect();
+pub fn parse_document(input: &str) -> Result<Document, ParseError> {
+    // Lex lazily into the parser's token buffer (text is sliced from `input`)
+    parser_winnow::parse_token_stream(token::TokenStream::new(input))
+}
+
+/// Parse an AsciiDoc document, collecting every syntax error
+///
+/// Where [`parse_document`] stops at the first error, this skips the
+/// offending stretch of source up to the next block boundary (a blank line
+/// or a heading) and parses on. It returns the blocks it could parse along
+/// with all errors, in source order, so an editor can report every problem
+/// in one pass. The document is complete if there are no errors.
+///
+/// # Examples
+///
+/// ```
+/// use doctora::{ParseError, parse_document_recovering};
+///
+/// let (doc, errors) = parse_document_recovering("One.\n\n== \n\nTwo.\n\n== \n\nThree.\n");
+/// assert_eq!(doc.blocks.len(), 3);
+/// assert_eq!(errors.len(), 2);
+/// assert!(matches!(&errors[0], ParseError::UnexpectedToken { span, .. } if *span == (6..8)));
+/// ```
+pub fn parse_document_recovering(input: &str) -> (Document, Vec<ParseError>) {
+    let tokens = token::lex_with_spans(input);
+    let mut recovery = ErrorRecovery::collecting();
+    let doc = parser_winnow::parse_tokens_with(
+        input,
+        &tokens,
+        &Limits::new(),
+        &SubstitutionDefaults::new(),
+        false,
+        &mut recovery,
+    );
+    (doc, recovery.into_errors())
+}
 
-    // Step 2: Parse tokens into AST using Winnow
-    parser_winnow::parse_document_winnow(&tokens)
+/// Parse an AsciiDoc document, returning its tokens as well
+///
+/// For tools that need both the token layer and the document structure,
+/// such as an editor that highlights tokens and shows an outline. The
+/// input is lexed once and the parser reads the same token buffer, which is
+/// then handed back. Parsing recovers from errors like
+/// [`parse_document_recovering`], and the errors are returned as the
+/// diagnostics, in source order.
+///
+/// # Examples
+///
+/// ```
+/// use doctora::parse_with_tokens;
+/// use doctora::token::Token;
+///
+/// let source = "== Setup\n\nRun *make*.\n";
+/// let (tokens, doc, diagnostics) = parse_with_tokens(source);
+/// assert_eq!(tokens[0].0, Token::Heading2);
+/// assert_eq!(doc.blocks.len(), 1);
+/// assert!(diagnostics.is_empty());
+///
+/// let (tokens, doc, diagnostics) = parse_with_tokens("One.\n\n== \n\nTwo.\n");
+/// assert_eq!(tokens.len(), 8);
+/// assert_eq!(doc.blocks.len(), 2);
+/// assert_eq!(diagnostics.len(), 1);
+/// ```
+pub fn parse_with_tokens(input: &str) -> (Vec<token::SpannedToken>, Document, Vec<ParseError>) {
+    let tokens = token::lex_with_spans(input);
+    let mut recovery = ErrorRecovery::collecting();
+    let doc = parser_winnow::parse_tokens_with(
+        input,
+        &tokens,
+        &Limits::new(),
+        &SubstitutionDefaults::new(),
+        false,
+        &mut recovery,
+    );
+    (tokens, doc, recovery.into_errors())
+}
+
+/// Parse an AsciiDoc document with explicit [`ParseOptions`]
+///
+/// Behaves like [`parse_document`], but the document starts with the
+/// attributes supplied through `options` (including environment attributes,
+/// if enabled), and the size limits in `options` are enforced (see
+/// [`limits`]). Header attribute entries can reference the supplied
+/// attributes, but cannot override those set with
+/// [`ParseOptions::attribute`].
+///
+/// # Examples
+///
+/// ```
+/// use doctora::options::ParseOptions;
+/// use doctora::parse_document_with_options;
+///
+/// let options = ParseOptions::new().attribute("version", "1.0");
+/// let doc = parse_document_with_options("Release notes.", &options).unwrap();
+/// assert_eq!(doc.attributes.get("version"), Some("1.0"));
+/// ```
+///
+/// # Errors
+///
+/// In addition to the errors of [`parse_document`], fails if the supplied
+/// attributes reference each other in a cycle or an attribute value is
+/// longer than [`Limits::max_attribute_length`] ([`ParseError::Attributes`]),
+/// or if an include cannot be expanded ([`ParseError::Include`]).
+pub fn parse_document_with_options(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<Document, ParseError> {
+    let attributes = options.option_attributes()?;
+    let expanded;
+    let input = match options.include_options() {
+        Some(includes) => {
+            let mut dated = attributes.clone();
+            options.insert_date_attributes(&mut dated, &attributes);
+            expanded = include::expand_includes(input, &dated, includes)?;
+            expanded.as_str()
+        }
+        None => input,
+    };
+    let tokens = token::lex_with_spans(input);
+    let mut doc = match options.parser_backend() {
+        ParserBackend::Winnow => parser_winnow::parse_tokens(
+            input,
+            &tokens,
+            options.size_limits(),
+            options.substitution_defaults(),
+            options.keeps_comments(),
+        )?,
+    };
+    doc.attributes = attributes;
+    let locked = |name: &str| options.sets_attribute(name);
+    let max_length = options.size_limits().max_attribute_length;
+    // The date attributes follow the header's `date-format` and
+    // `time-format`, so the header is applied once to read them, then again
+    // over the dates so its entries can reference `docdate`
+    let mut formats = doc.attributes.clone();
+    if let Some(header) = &doc.header {
+        header.define_attributes(&mut formats, locked, max_length)?;
+    }
+    options.insert_date_attributes(&mut doc.attributes, &formats);
+    if let Some(header) = &doc.header {
+        header.define_attributes(&mut doc.attributes, locked, max_length)?;
+    }
+    if options.substitutes_attributes() {
+        resolve::substitute_attributes_with(
+            &mut doc,
+            options.substitution_defaults(),
+            locked,
+            max_length,
+        )?;
+    }
+    if options.applies_replacements() {
+        replacements::apply_replacements_with(&mut doc, options.substitution_defaults());
+    }
+    if options.marks_break_hints() {
+        hints::mark_break_hints_with(&mut doc, options.substitution_defaults());
+    }
+    // After break hints, since compatibility forms fold U+00A0 into a space
+    if let Some((form, normalizer)) = options.unicode_normalization() {
+        unicode::normalize_text(&mut doc, form, normalizer);
+    }
+    if options.generates_section_ids() {
+        xref::assign_section_ids(&mut doc);
+    }
+    Ok(doc)
 }
@@ -0,0 +1,227 @@
+//! Size limits for untrusted or generated documents
+//!
+//! A generated document with a million-row table or a million-item list can
+//! exhaust the memory of a service that embeds doctora. [`Limits`] caps the
+//! size of such structures. When a structure exceeds its limit, the parser
+//! keeps the first allowed items, drops the rest, and records a
+//! [`Truncation`] in [`Document::truncations`](crate::ast::Document::truncations)
+//! so the caller can report it. Table limits count body rows; the header row
+//! is always kept.
+//!
+//! Nesting is limited differently. The parser recurses into each nested
+//! delimited block or list, so a short document that nests thousands of
+//! `====` and `****` blocks would overflow the stack and abort the process.
+//! Nesting deeper than [`Limits::max_nesting_depth`] is therefore a parse
+//! error ([`ParseError::NestingTooDeep`](crate::ParseError::NestingTooDeep)).
+//! This limit defaults to [`DEFAULT_MAX_NESTING_DEPTH`].
+//!
+//! Attribute values are limited in length for a similar reason: each entry
+//! can reference the one before twice (`:b: {a}{a}`), so a few dozen lines
+//! would expand to more memory than the machine has. A value longer than
+//! [`Limits::max_attribute_length`] is a parse error
+//! ([`AttributeError::TooLong`](crate::attributes::AttributeError::TooLong)).
+//! This limit defaults to [`DEFAULT_MAX_ATTRIBUTE_LENGTH`].
+//!
+//! Size limits are disabled by default. All limits are set through
+//! [`ParseOptions::limits`](crate::options::ParseOptions::limits):
+//!
+//! ```
+//! use doctora::limits::Limits;
+//! use doctora::options::ParseOptions;
+//!
+//! let options = ParseOptions::new().limits(
+//!     Limits::new().max_list_items(10_000).max_table_rows(50_000),
+//! );
+//! assert_eq!(options.size_limits().max_list_items, Some(10_000));
+//! ```
+
+use std::fmt;
+
+/// Default for [`Limits::max_nesting_depth`]
+///
+/// Well beyond what real documents use, and shallow enough to parse on a
+/// 2 MiB thread stack.
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 64;
+
+/// Default for [`Limits::max_attribute_length`], in bytes
+///
+/// Far longer than any value written by hand, and small enough that a
+/// document of such values fits in memory.
+pub const DEFAULT_MAX_ATTRIBUTE_LENGTH: usize = 1 << 20;
+
+/// Maximum sizes of repeated structures and attribute values, and maximum
+/// nesting depth
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of items in a single list (`None` = unlimited)
+    pub max_list_items: Option<usize>,
+    /// Maximum number of rows in a single table (`None` = unlimited)
+    pub max_table_rows: Option<usize>,
+    /// Maximum number of delimited blocks and lists nested in each other
+    /// (`None` = unlimited, which risks a stack overflow on hostile input)
+    pub max_nesting_depth: Option<usize>,
+    /// Maximum length in bytes of a resolved attribute value (`None` =
+    /// unlimited, which risks running out of memory on hostile input)
+    pub max_attribute_length: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_list_items: None,
+            max_table_rows: None,
+            max_nesting_depth: Some(DEFAULT_MAX_NESTING_DEPTH),
+            max_attribute_length: Some(DEFAULT_MAX_ATTRIBUTE_LENGTH),
+        }
+    }
+}
+
+impl Limits {
+    /// Creates limits with every size limit disabled and the default
+    /// nesting depth and attribute length
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the number of items in a single list
+    pub fn max_list_items(mut self, max: usize) -> Self {
+        self.max_list_items = Some(max);
+        self
+    }
+
+    /// Limits the number of rows in a single table
+    pub fn max_table_rows(mut self, max: usize) -> Self {
+        self.max_table_rows = Some(max);
+        self
+    }
+
+    /// Limits how deeply delimited blocks and lists nest
+    pub fn max_nesting_depth(mut self, max: usize) -> Self {
+        self.max_nesting_depth = Some(max);
+        self
+    }
+
+    /// Limits the length in bytes of resolved attribute values
+    pub fn max_attribute_length(mut self, max: usize) -> Self {
+        self.max_attribute_length = Some(max);
+        self
+    }
+}
+
+/// The kind of structure that was truncated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StructureKind {
+    /// A list (items)
+    List,
+    /// A table (rows)
+    Table,
+}
+
+impl StructureKind {
+    /// Noun for the counted elements, used in messages
+    fn unit(self) -> &'static str {
+        match self {
+            StructureKind::List => "items",
+            StructureKind::Table => "rows",
+        }
+    }
+}
+
+/// Record of a structure that was cut down to its limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Truncation {
+    /// What was truncated
+    pub kind: StructureKind,
+    /// Byte offset of the structure in the source
+    pub offset: usize,
+    /// Number of elements kept (the limit)
+    pub kept: usize,
+    /// Number of elements dropped
+    pub dropped: usize,
+}
+
+impl fmt::Display for Truncation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            StructureKind::List => "list",
+            StructureKind::Table => "table",
+        };
+        write!(
+            f,
+            "{} at byte {} truncated to {} {} ({} dropped)",
+            kind,
+            self.offset,
+            self.kept,
+            self.kind.unit(),
+            self.dropped
+        )
+    }
+}
+
+/// Truncate `items` to `max` elements, returning a record if anything was dropped
+///
+/// # Examples
+///
+/// ```
+/// use doctora::limits::{StructureKind, truncate};
+///
+/// let mut rows = vec![1, 2, 3, 4];
+/// let truncation = truncate(&mut rows, Some(2), StructureKind::Table, 0).unwrap();
+/// assert_eq!(rows, vec![1, 2]);
+/// assert_eq!(truncation.dropped, 2);
+/// ```
+pub fn truncate<T>(
+    items: &mut Vec<T>,
+    max: Option<usize>,
+    kind: StructureKind,
+    offset: usize,
+) -> Option<Truncation> {
+    let max = max?;
+    if items.len() <= max {
+        return None;
+    }
+    let dropped = items.len() - max;
+    items.truncate(max);
+    Some(Truncation {
+        kind,
+        offset,
+        kept: max,
+        dropped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited_by_default() {
+        let mut items = vec![0; 1000];
+        assert_eq!(truncate(&mut items, None, StructureKind::List, 0), None);
+        assert_eq!(items.len(), 1000);
+        assert_eq!(Limits::new(), Limits::default());
+        assert_eq!(
+            Limits::new().max_nesting_depth,
+            Some(DEFAULT_MAX_NESTING_DEPTH)
+        );
+    }
+
+    #[test]
+    fn test_within_limit() {
+        let mut items = vec![1, 2];
+        assert_eq!(truncate(&mut items, Some(2), StructureKind::List, 0), None);
+    }
+
+    #[test]
+    fn test_truncation_message() {
+        let mut items = vec![(); 5];
+        let truncation = truncate(&mut items, Some(3), StructureKind::List, 42).unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(
+            truncation.to_string(),
+            "list at byte 42 truncated to 3 items (2 dropped)"
+        );
+    }
+}
@@ -0,0 +1,422 @@
+//! Structural lint rules with optional automatic fixes
+//!
+//! Lints run over a parsed [`Document`] and report style or structure problems
+//! that are valid AsciiDoc but usually mistakes. Rules that can repair the
+//! problem mechanically also implement [`LintRule::fix`], which rewrites the
+//! AST in place.
+//!
+//! # Built-in Rules
+//!
+//! - [`HeadingLevels`] (`heading-levels`) - detects skipped heading levels,
+//!   such as a `==` section directly followed by a `====` subsection
+//! - [`UnresolvedXrefs`] (`unresolved-xrefs`) - detects cross references to
+//!   anchors the document does not define
+//! - [`WordBudgets`] (`word-budgets`) - detects sections longer than their
+//!   `max-words` budget
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::lint::{HeadingLevels, LintRule, lint};
+//! use doctora::parse_document;
+//!
+//! let mut doc = parse_document("== Overview\n\n==== Details\n").unwrap();
+//!
+//! let warnings = lint(&doc, &[&HeadingLevels]);
+//! assert_eq!(warnings.len(), 1);
+//!
+//! // Re-level the document and check again
+//! assert_eq!(HeadingLevels.fix(&mut doc), 1);
+//! assert!(lint(&doc, &[&HeadingLevels]).is_empty());
+//! ```
+
+use crate::ast::{Block, Document, Inline, plain_text};
+use crate::xref::unresolved;
+
+/// A single problem reported by a lint rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintWarning {
+    /// Name of the rule that produced the warning
+    pub rule: &'static str,
+    /// Human-readable description of the problem
+    pub message: String,
+    /// Position of the offending block, as child indices from the document root
+    pub path: Vec<usize>,
+}
+
+/// A lint rule that inspects (and optionally repairs) a document
+pub trait LintRule {
+    /// Stable rule identifier, used in reports and configuration
+    fn name(&self) -> &'static str;
+
+    /// Report every violation of this rule in `doc`
+    fn check(&self, doc: &Document) -> Vec<LintWarning>;
+
+    /// Repair violations in place, returning how many were fixed
+    ///
+    /// Rules without an automatic fix keep the default, which changes nothing.
+    fn fix(&self, _doc: &mut Document) -> usize {
+        0
+    }
+}
+
+/// Run `rules` over `doc` and collect all warnings in rule order
+pub fn lint(doc: &Document, rules: &[&dyn LintRule]) -> Vec<LintWarning> {
+    rules.iter().flat_map(|rule| rule.check(doc)).collect()
+}
+
+/// Detects skipped heading levels (e.g. `==` followed by `====`)
+///
+/// A section may be at most one level deeper than the section containing it.
+/// Top-level sections may use level 1 (document title) or level 2, since
+/// documents without a title start directly at `==`.
+///
+/// The fix re-levels each offending section to the deepest allowed level and
+/// shifts all of its subsections by the same amount, so relative structure below
+/// the skip is preserved.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeadingLevels;
+
+/// Deepest level allowed for a top-level section
+const MAX_TOP_LEVEL: u8 = 2;
+
+impl HeadingLevels {
+    /// Deepest level allowed for a child of a section at `parent_level`
+    fn max_child_level(parent_level: Option<u8>) -> u8 {
+        parent_level.map_or(MAX_TOP_LEVEL, |level| level + 1)
+    }
+
+    fn check_blocks(
+        blocks: &[Block],
+        parent_level: Option<u8>,
+        path: &mut Vec<usize>,
+        out: &mut Vec<LintWarning>,
+    ) {
+        for (index, block) in blocks.iter().enumerate() {
+            if let Block::Section {
+                level,
+                title,
+                content,
+                ..
+            } = block
+            {
+                path.push(index);
+                let max = Self::max_child_level(parent_level);
+                if *level > max {
+                    out.push(LintWarning {
+                        rule: "heading-levels",
+                        message: format!(
+                            "section {:?} is level {} but the deepest allowed level here is {}",
+                            plain_text(title),
+                            level,
+                            max
+                        ),
+                        path: path.clone(),
+                    });
+                }
+                Self::check_blocks(content, Some(*level), path, out);
+                path.pop();
+            }
+        }
+    }
+
+    fn fix_blocks(blocks: &mut [Block], parent_level: Option<u8>) -> usize {
+        let mut fixed = 0;
+        for block in blocks {
+            if let Block::Section { level, content, .. } = block {
+                let max = Self::max_child_level(parent_level);
+                if *level > max {
+                    Self::shift_levels(content, *level - max);
+                    *level = max;
+                    fixed += 1;
+                }
+                fixed += Self::fix_blocks(content, Some(*level));
+            }
+        }
+        fixed
+    }
+
+    /// Raise every nested section in `blocks` by `delta` levels
+    fn shift_levels(blocks: &mut [Block], delta: u8) {
+        for block in blocks {
+            if let Block::Section { level, content, .. } = block {
+                *level = level.saturating_sub(delta).max(1);
+                Self::shift_levels(content, delta);
+            }
+        }
+    }
+}
+
+impl LintRule for HeadingLevels {
+    fn name(&self) -> &'static str {
+        "heading-levels"
+    }
+
+    fn check(&self, doc: &Document) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
+        Self::check_blocks(&doc.blocks, None, &mut Vec::new(), &mut warnings);
+        warnings
+    }
+
+    fn fix(&self, doc: &mut Document) -> usize {
+        Self::fix_blocks(&mut doc.blocks, None)
+    }
+}
+
+/// Detects cross references (`<<id>>`) to anchors the document does not
+/// define
+///
+/// References into other documents (`<<other.adoc#id>>`) are not checked.
+/// There is no automatic fix.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnresolvedXrefs;
+
+impl LintRule for UnresolvedXrefs {
+    fn name(&self) -> &'static str {
+        "unresolved-xrefs"
+    }
+
+    fn check(&self, doc: &Document) -> Vec<LintWarning> {
+        unresolved(doc)
+            .into_iter()
+            .map(|xref| LintWarning {
+                rule: "unresolved-xrefs",
+                message: format!("cross reference to undefined anchor {:?}", xref.id),
+                path: xref.path,
+            })
+            .collect()
+    }
+}
+
+/// Detects sections with more words than their budget
+///
+/// Style guides that cap page lengths set a budget with the `max-words`
+/// attribute: on a section's attribute list line (`[max-words=300]`) for that
+/// section, or in the document header (`:max-words: 500`) for every section
+/// without its own. A section's words include its subsections but not its
+/// title; listing, passthrough and comment blocks and images are not prose
+/// and don't count. Budgets that are not a whole number are ignored. There is
+/// no automatic fix.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WordBudgets;
+
+impl WordBudgets {
+    fn check_blocks(
+        blocks: &[Block],
+        default: Option<usize>,
+        path: &mut Vec<usize>,
+        out: &mut Vec<LintWarning>,
+    ) {
+        for (index, block) in blocks.iter().enumerate() {
+            if let Block::Section {
+                title,
+                content,
+                metadata,
+                ..
+            } = block
+            {
+                path.push(index);
+                let budget = metadata
+                    .named
+                    .get("max-words")
+                    .map_or(default, |budget| budget.trim().parse().ok());
+                let words = word_count(content);
+                if let Some(budget) = budget
+                    && words > budget
+                {
+                    out.push(LintWarning {
+                        rule: "word-budgets",
+                        message: format!(
+                            "section {:?} has {} words but its budget is {}",
+                            plain_text(title),
+                            words,
+                            budget
+                        ),
+                        path: path.clone(),
+                    });
+                }
+                Self::check_blocks(content, default, path, out);
+                path.pop();
+            }
+        }
+    }
+}
+
+impl LintRule for WordBudgets {
+    fn name(&self) -> &'static str {
+        "word-budgets"
+    }
+
+    fn check(&self, doc: &Document) -> Vec<LintWarning> {
+        let default = doc
+            .attributes
+            .get("max-words")
+            .and_then(|budget| budget.trim().parse().ok());
+        let mut warnings = Vec::new();
+        Self::check_blocks(&doc.blocks, default, &mut Vec::new(), &mut warnings);
+        warnings
+    }
+}
+
+/// Count the words of prose in `blocks`, section titles included
+fn word_count(blocks: &[Block]) -> usize {
+    let words = |content: &[Inline]| plain_text(content).split_whitespace().count();
+    blocks
+        .iter()
+        .map(|block| match block {
+            Block::Section { title, content, .. } => words(title) + word_count(content),
+            Block::Paragraph { content, .. } | Block::Verse { content, .. } => words(content),
+            Block::UnorderedList { items, .. } | Block::OrderedList { items, .. } => items
+                .iter()
+                .map(|item| words(&item.content) + word_count(&item.blocks))
+                .sum(),
+            Block::DescriptionList { items, .. } => items
+                .iter()
+                .map(|item| words(&item.term) + words(&item.description))
+                .sum(),
+            Block::Quote { content, .. }
+            | Block::Admonition { content, .. }
+            | Block::Example { content, .. }
+            | Block::Sidebar { content, .. }
+            | Block::Open { content, .. } => word_count(content),
+            Block::Table { header, rows, .. } => header
+                .iter()
+                .chain(rows)
+                .flat_map(|row| &row.cells)
+                .map(|cell| words(&cell.content))
+                .sum(),
+            Block::Listing { .. }
+            | Block::Image { .. }
+            | Block::Passthrough { .. }
+            | Block::Comment { .. }
+            | Block::AttributeEntry { .. }
+            | Block::Unknown { .. } => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{BlockMetadata, Inline};
+
+    fn section(level: u8, content: Vec<Block>) -> Block {
+        Block::Section {
+            level,
+            title: vec![Inline::Text(format!("Level {}", level))],
+            content,
+            anchor: None,
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    fn levels(blocks: &[Block], out: &mut Vec<u8>) {
+        for block in blocks {
+            if let Block::Section { level, content, .. } = block {
+                out.push(*level);
+                levels(content, out);
+            }
+        }
+    }
+
+    #[test]
+    fn test_well_formed_headings() {
+        let doc =
+            Document::with_blocks(vec![section(1, vec![section(2, vec![section(3, vec![])])])]);
+        assert!(HeadingLevels.check(&doc).is_empty());
+    }
+
+    #[test]
+    fn test_top_level_may_start_at_two() {
+        let doc = Document::with_blocks(vec![section(2, vec![])]);
+        assert!(HeadingLevels.check(&doc).is_empty());
+
+        let doc = Document::with_blocks(vec![section(3, vec![])]);
+        assert_eq!(HeadingLevels.check(&doc).len(), 1);
+    }
+
+    #[test]
+    fn test_skipped_level_reported() {
+        let doc = Document::with_blocks(vec![section(2, vec![section(4, vec![])])]);
+        let warnings = HeadingLevels.check(&doc);
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].rule, "heading-levels");
+        assert_eq!(warnings[0].path, vec![0, 0]);
+        assert!(warnings[0].message.contains("level 4"));
+    }
+
+    #[test]
+    fn test_fix_preserves_relative_structure() {
+        let mut doc = Document::with_blocks(vec![section(
+            2,
+            vec![section(4, vec![section(5, vec![section(6, vec![])])])],
+        )]);
+
+        assert_eq!(HeadingLevels.fix(&mut doc), 1);
+
+        let mut found = Vec::new();
+        levels(&doc.blocks, &mut found);
+        assert_eq!(found, vec![2, 3, 4, 5]);
+        assert!(HeadingLevels.check(&doc).is_empty());
+    }
+
+    #[test]
+    fn test_fix_is_noop_on_clean_document() {
+        let mut doc = Document::with_blocks(vec![section(1, vec![section(2, vec![])])]);
+        let before = doc.clone();
+        assert_eq!(HeadingLevels.fix(&mut doc), 0);
+        assert_eq!(doc, before);
+    }
+
+    #[test]
+    fn test_lint_runs_all_rules() {
+        let doc = Document::with_blocks(vec![section(4, vec![])]);
+        let warnings = lint(&doc, &[&HeadingLevels, &HeadingLevels]);
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn test_unresolved_xrefs() {
+        let doc = crate::parse_document("[[a]]\n== A\n\nSee <<a>> and <<b>>.\n").unwrap();
+        let warnings = UnresolvedXrefs.check(&doc);
+        assert_eq!(
+            warnings,
+            vec![LintWarning {
+                rule: "unresolved-xrefs",
+                message: "cross reference to undefined anchor \"b\"".to_string(),
+                path: vec![0, 0],
+            }]
+        );
+        assert_eq!(UnresolvedXrefs.fix(&mut doc.clone()), 0);
+    }
+
+    #[test]
+    fn test_word_budgets() {
+        let messages = |source: &str| -> Vec<String> {
+            let doc = crate::parse_document(source).unwrap();
+            WordBudgets
+                .check(&doc)
+                .into_iter()
+                .map(|warning| warning.message)
+                .collect()
+        };
+        // The header budget covers subsections, but not code
+        assert_eq!(
+            messages(
+                "= Guide\n:max-words: 6\n\n== Long\n\n* one two\n* three\n\n=== Sub\n\nfour five six\n\n----\nnot counted at all\n----\n"
+            ),
+            ["section \"Long\" has 7 words but its budget is 6"]
+        );
+        // A section's own budget replaces the header's
+        assert_eq!(
+            messages(
+                "= Guide\n:max-words: 100\n\n[max-words=3]\n== Capped\n\nOne two three four.\n"
+            ),
+            ["section \"Capped\" has 4 words but its budget is 3"]
+        );
+        assert!(messages("[max-words=4]\n== Capped\n\nOne two three four.\n").is_empty());
+        // Without a budget nothing is checked
+        assert!(messages("== Long\n\nOne two three.\n").is_empty());
+    }
+}
@@ -1,100 +1,1152 @@
-use doctora::ast::{Block, Inline};
-use doctora::parse_document;
-use doctora::token::Token;
-use logos::Logos;
-
-fn main() {
-    // Example AsciiDoc input
-    let input = r#"= Document Title
-
-This is a paragraph with **bold** text and _italic_ text.
-
-== Section Heading
-
-Another paragraph here."#;
-
-    println!("AsciiDoc Parser Demo");
-    println!("{}", "=".repeat(80));
-    println!("\nInput:\n{}\n", input);
-
-    // ===== Step 1: Tokenization =====
-    println!("{}", "=".repeat(80));
-    println!("STEP 1: TOKENIZATION (Logos)");
-    println!("{}", "=".repeat(80));
-
-    let mut lexer = Token::lexer(input);
-
-    println!("\n{:<20} {:<20} {:>10}", "Token", "Text", "Span");
-    println!("{}", "-".repeat(60));
-
-    let mut token_count = 0;
-    while let Some(result) = lexer.next() {
-        match result {
-            Ok(token) => {
-                token_count += 1;
-                let span = lexer.span();
-                let text = &input[span.clone()];
-                let display_text = if text == "\n" {
-                    "\\n".to_string()
-                } else if text.starts_with("\n\n") {
-                    format!("\\n\\n ({})", text.len() - 2)
-                } else {
-                    text.to_string()
+//! The `doctora` command-line tool
+//!
+//! - `doctora parse [FILE]... [--format json|yaml|debug] [--output FILE]`
+//!   parses files (or stdin) and dumps their AST
+//! - `doctora convert [FILE]... [--backend NAME] [-a NAME=VALUE]... [--watch]`
+//!   converts files, glob patterns, or stdin with an output processor
+//! - `doctora serve FILE... [--port PORT] [-a NAME=VALUE]...` serves files
+//!   as HTML on localhost and updates open pages as their sources change
+//! - `doctora fix-deprecations FILE...` rewrites deprecated syntax in place
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use doctora::ast::{Block, Document, Inline, ListItem, plain_text};
+use doctora::clock::{Clock as _, FixedClock, SystemClock};
+use doctora::deprecations::{self, report_deprecations};
+use doctora::events::Event;
+use doctora::include::{IncludeOptions, IncludeResolver};
+use doctora::options::ParseOptions;
+use doctora::preview::{
+    CLIENT_PATH, CLIENT_SCRIPT, LIVE_PATH, PreviewUpdate, update, websocket_accept,
+    websocket_text_frame,
+};
+use doctora::processor::{Output, ProcessorError, ProcessorRegistry};
+use doctora::render::html::{HtmlOptions, HtmlProcessor, escape, render_html};
+use doctora::schema::{to_json, to_json_string, to_yaml_string};
+use doctora::{parse_document, parse_document_with_options};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io::{self, BufRead as _, BufReader, Read as _, Write as _};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Exit code for input that fails to parse
+const EXIT_PARSE: u8 = 1;
+
+/// Exit code for conversion failures and invalid option combinations
+const EXIT_CONVERT: u8 = 2;
+
+/// Exit code for files that cannot be read or written
+const EXIT_IO: u8 = 3;
+
+/// Error for an `--out-file` file given several inputs
+const OUT_FILE_INPUTS: &str = "--out-file takes a single input; use --destination-dir for several";
+
+/// How often `convert --watch` and `serve` check files for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+fn main() -> ExitCode {
+    let matches = cli().get_matches();
+    let status = match matches.subcommand() {
+        Some(("parse", args)) => parse(args),
+        Some(("convert", args)) => convert(args),
+        Some(("serve", args)) => serve(args),
+        Some(("fix-deprecations", args)) => {
+            let files: Vec<&String> = args.get_many("files").into_iter().flatten().collect();
+            fix_deprecations(&files)
+        }
+        _ => unreachable!("clap requires a known subcommand"),
+    };
+    ExitCode::from(status)
+}
+
+/// The command-line interface
+fn cli() -> Command {
+    Command::new("doctora")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Parse and process AsciiDoc documents")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            Command::new("parse")
+                .about("Parse documents and print their AST")
+                .arg(
+                    Arg::new("files")
+                        .value_name("FILE")
+                        .num_args(0..)
+                        .help("Files to parse; reads stdin when none (or `-`) is given"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .short('f')
+                        .long("format")
+                        .value_parser(["json", "yaml", "debug"])
+                        .default_value("json")
+                        .help("Output format"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Write to FILE instead of stdout"),
+                ),
+        )
+        .subcommand(
+            Command::new("convert")
+                .about("Convert documents to an output format")
+                .arg(Arg::new("files").value_name("FILE").num_args(0..).help(
+                    "Files or glob patterns (`docs/**/*.adoc`) to convert; \
+                     reads stdin when none (or `-`) is given",
+                ))
+                .arg(
+                    Arg::new("backend")
+                        .short('b')
+                        .long("backend")
+                        .value_name("NAME")
+                        .default_value("html")
+                        .help("Output format, by processor name (html, manpage, text, ...)"),
+                )
+                .arg(
+                    Arg::new("attribute")
+                        .short('a')
+                        .long("attribute")
+                        .value_name("NAME[=VALUE]")
+                        .action(ArgAction::Append)
+                        .help("Set a document attribute, overriding the document's own entry"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("out-file")
+                        .value_name("FILE")
+                        .help("Write to FILE (`-` for stdout) instead of next to the input"),
+                )
+                .arg(
+                    Arg::new("destination")
+                        .short('D')
+                        .long("destination-dir")
+                        .value_name("DIR")
+                        .conflicts_with("output")
+                        .help("Write output files to DIR"),
+                )
+                .arg(
+                    Arg::new("watch")
+                        .short('w')
+                        .long("watch")
+                        .action(ArgAction::SetTrue)
+                        .help(
+                            "Keep running and reconvert inputs when they or their includes change",
+                        ),
+                )
+                .arg(
+                    Arg::new("embedded")
+                        .short('s')
+                        .long("embedded")
+                        .action(ArgAction::SetTrue)
+                        .help("Write HTML as an embeddable fragment instead of a full page"),
+                ),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Serve documents as HTML on localhost, updating pages as they change")
+                .arg(
+                    Arg::new("files")
+                        .value_name("FILE")
+                        .required(true)
+                        .num_args(1..)
+                        .help("Files or glob patterns to serve, relative to the current directory"),
+                )
+                .arg(
+                    Arg::new("port")
+                        .short('p')
+                        .long("port")
+                        .value_name("PORT")
+                        .value_parser(clap::value_parser!(u16))
+                        .default_value("8000")
+                        .help("Port to listen on; 0 picks a free one"),
+                )
+                .arg(
+                    Arg::new("attribute")
+                        .short('a')
+                        .long("attribute")
+                        .value_name("NAME[=VALUE]")
+                        .action(ArgAction::Append)
+                        .help("Set a document attribute, overriding the document's own entry"),
+                ),
+        )
+        .subcommand(
+            Command::new("fix-deprecations")
+                .about("Rewrite deprecated syntax in place")
+                .arg(
+                    Arg::new("files")
+                        .value_name("FILE")
+                        .required(true)
+                        .action(ArgAction::Append),
+                ),
+        )
+}
+
+/// Run `doctora parse`, returning the exit code
+///
+/// Several inputs produce a JSON array, a YAML stream with one document
+/// per input, or debug dumps headed by each input's name.
+fn parse(args: &ArgMatches) -> u8 {
+    let mut inputs: Vec<&str> = args
+        .get_many::<String>("files")
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect();
+    if inputs.is_empty() {
+        inputs.push("-");
+    }
+
+    let mut docs = Vec::new();
+    for input in &inputs {
+        let name = if *input == "-" { "<stdin>" } else { input };
+        let source = match read_input(input) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("{name}: {err}");
+                return EXIT_IO;
+            }
+        };
+        match parse_document(&source) {
+            Ok(doc) => docs.push((name, doc)),
+            Err(err) => {
+                eprintln!("{name}: {err}");
+                return EXIT_PARSE;
+            }
+        }
+    }
+
+    let format = args
+        .get_one::<String>("format")
+        .map_or("json", String::as_str);
+    let output = match (format, docs.as_slice()) {
+        ("json", [(_, doc)]) => to_json_string(doc) + "\n",
+        ("json", docs) => {
+            let values: Vec<_> = docs.iter().map(|(_, doc)| to_json(doc)).collect();
+            serde_json::to_string_pretty(&values).expect("JSON values always serialize") + "\n"
+        }
+        ("yaml", [(_, doc)]) => to_yaml_string(doc),
+        ("yaml", docs) => docs
+            .iter()
+            .map(|(_, doc)| format!("---\n{}", to_yaml_string(doc)))
+            .collect(),
+        (_, docs) => {
+            let mut out = String::new();
+            for (name, doc) in docs {
+                if inputs.len() > 1 {
+                    writeln!(out, "==> {name} <==").expect("writing to a String cannot fail");
+                }
+                print_document(&mut out, doc, 0).expect("writing to a String cannot fail");
+            }
+            out
+        }
+    };
+
+    match args.get_one::<String>("output") {
+        Some(path) => {
+            if let Err(err) = fs::write(path, output) {
+                eprintln!("{path}: {err}");
+                return EXIT_IO;
+            }
+        }
+        None => print!("{output}"),
+    }
+    0
+}
+
+/// Run `doctora convert`, returning the exit code
+///
+/// Each file is written next to it (or into `--destination-dir`) with the
+/// backend's extension; stdin is converted to stdout. A failed input is
+/// reported and the others are still converted.
+fn convert(args: &ArgMatches) -> u8 {
+    let backend = args
+        .get_one::<String>("backend")
+        .map_or("html", String::as_str);
+    let mut registry = ProcessorRegistry::with_builtins();
+    if !args.get_flag("embedded") {
+        registry.register(Box::new(HtmlProcessor {
+            options: HtmlOptions::new().standalone(true),
+        }));
+    }
+    if registry.get(backend).is_none() {
+        eprintln!("{}", ProcessorError::UnknownFormat(backend.to_string()));
+        let mut names: Vec<&str> = registry.names().collect();
+        names.sort_unstable();
+        eprintln!("available backends: {}", names.join(", "));
+        return EXIT_CONVERT;
+    }
+
+    let options = parse_options(args);
+    let patterns: Vec<&str> = args
+        .get_many::<String>("files")
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect();
+    let mut inputs = Vec::new();
+    for pattern in &patterns {
+        let paths = expand_glob(pattern);
+        if paths.is_empty() {
+            eprintln!("{pattern}: no files match");
+            return EXIT_IO;
+        }
+        inputs.extend(paths);
+    }
+    let watch = args.get_flag("watch");
+    if inputs.is_empty() {
+        if watch {
+            eprintln!("--watch needs input files");
+            return EXIT_CONVERT;
+        }
+        inputs.push(PathBuf::from("-"));
+    }
+    let conversion = Conversion {
+        options,
+        registry,
+        backend,
+        output: args.get_one::<String>("output"),
+        destination: args.get_one::<String>("destination").map(Path::new),
+    };
+    if conversion.single_output() && inputs.len() > 1 {
+        eprintln!("{OUT_FILE_INPUTS}");
+        return EXIT_CONVERT;
+    }
+    if watch {
+        conversion.watch(&patterns);
+    }
+
+    let mut status = 0;
+    for input in &inputs {
+        if let Err(code) = conversion.convert_file(input, &Sources::default())
+            && status == 0
+        {
+            status = code;
+        }
+    }
+    status
+}
+
+/// Parse options for `convert` and `serve`, with the `--attribute` values
+fn parse_options(args: &ArgMatches) -> ParseOptions {
+    let mut options = ParseOptions::new()
+        .substitute_attributes(true)
+        .generate_section_ids(true);
+    for attribute in args.get_many::<String>("attribute").into_iter().flatten() {
+        let (name, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+        options = options.attribute(name, value);
+    }
+    options
+}
+
+/// `options` with includes resolved next to `input` and recorded in
+/// `sources`
+fn file_options(options: &ParseOptions, input: &Path, sources: &Sources) -> ParseOptions {
+    let dir = input.parent().unwrap_or(Path::new(""));
+    options
+        .clone()
+        .includes(IncludeOptions::new(dir).resolver(sources.clone()))
+}
+
+/// How `doctora convert` converts each input
+struct Conversion<'a> {
+    options: ParseOptions,
+    registry: ProcessorRegistry,
+    backend: &'a str,
+    /// `--out-file`
+    output: Option<&'a String>,
+    /// `--destination-dir`
+    destination: Option<&'a Path>,
+}
+
+impl Conversion<'_> {
+    /// Whether `--out-file` names a file, which takes a single input
+    fn single_output(&self) -> bool {
+        self.output.is_some_and(|output| output != "-")
+    }
+
+    /// Convert one input, reporting failures and returning their exit code
+    ///
+    /// The input and every file it includes are added to `sources`, even if
+    /// the conversion fails.
+    fn convert_file(&self, input: &Path, sources: &Sources) -> Result<(), u8> {
+        let stdin = input == Path::new("-");
+        let name = if stdin {
+            "<stdin>".to_string()
+        } else {
+            sources.record(input);
+            input.display().to_string()
+        };
+        let source = read_input(&input.to_string_lossy()).map_err(|err| {
+            eprintln!("{name}: {err}");
+            EXIT_IO
+        })?;
+        let options = if stdin {
+            self.options.clone()
+        } else {
+            file_options(&self.options, input, sources)
+        };
+        let doc = parse_document_with_options(&source, &options).map_err(|err| {
+            eprintln!("{name}: {err}");
+            EXIT_PARSE
+        })?;
+        let converted = self.registry.convert(&doc, self.backend).map_err(|err| {
+            eprintln!("{name}: {err}");
+            EXIT_CONVERT
+        })?;
+        let bytes = match &converted {
+            Output::Text(text) => text.as_bytes(),
+            Output::Binary(bytes) => bytes.as_slice(),
+        };
+
+        let target = match self.output {
+            Some(output) if output != "-" => Some(PathBuf::from(output)),
+            Some(_) => None,
+            None if stdin => None,
+            None => {
+                let extension = match self.backend {
+                    "html" | "html5" => "html",
+                    "text" | "txt" => "txt",
+                    "manpage" => doc.attributes.get("manvolnum").unwrap_or("1"),
+                    other => other,
                 };
-                println!(
-                    "{:<20} {:<20} {:>10}",
-                    format!("{:?}", token),
-                    display_text,
-                    format!("{}..{}", span.start, span.end)
-                );
+                let file = input.with_extension(extension);
+                Some(match self.destination {
+                    Some(dir) => dir.join(file.file_name().expect("inputs name a file")),
+                    None => file,
+                })
             }
-            Err(()) => {
-                let span = lexer.span();
-                let text = &input[span.clone()];
-                println!(
-                    "{:<20} {:<20} {:>10}",
-                    "ERROR",
-                    text,
-                    format!("{}..{}", span.start, span.end)
-                );
+        };
+        match target {
+            Some(path) => fs::write(&path, bytes).map_err(|err| {
+                eprintln!("{}: {err}", path.display());
+                EXIT_IO
+            }),
+            None => io::stdout().write_all(bytes).map_err(|err| {
+                eprintln!("<stdout>: {err}");
+                EXIT_IO
+            }),
+        }
+    }
+
+    /// Convert the inputs `patterns` match, then again whenever an input or
+    /// a file it includes changes, until the process is stopped
+    ///
+    /// Changes are found by polling modification times. Patterns are
+    /// expanded on every poll, so new files matching them are converted too.
+    /// With an `--out-file` file, nothing is converted while the patterns
+    /// match more than one input, as they would all write that file.
+    fn watch(&self, patterns: &[&str]) -> ! {
+        let mut watched: BTreeMap<PathBuf, Stamps> = BTreeMap::new();
+        let mut refused = false;
+        eprintln!("watching for changes (Ctrl-C to stop)");
+        loop {
+            let inputs: Vec<_> = patterns
+                .iter()
+                .flat_map(|pattern| expand_glob(pattern))
+                .collect();
+            if self.single_output() && inputs.len() > 1 {
+                if !refused {
+                    eprintln!("{OUT_FILE_INPUTS}; waiting for the patterns to match one");
+                    refused = true;
+                }
+                thread::sleep(WATCH_INTERVAL);
+                continue;
             }
+            refused = false;
+            for input in inputs {
+                if watched.get(&input).is_some_and(Stamps::current) {
+                    continue;
+                }
+                let sources = Sources::default();
+                if self.convert_file(&input, &sources).is_ok() {
+                    eprintln!("{}: converted", input.display());
+                }
+                watched.insert(input, Stamps::new(sources.files()));
+            }
+            thread::sleep(WATCH_INTERVAL);
+        }
+    }
+}
+
+/// The files read for one conversion: the input and everything it includes
+#[derive(Debug, Clone, Default)]
+struct Sources(Arc<Mutex<BTreeSet<PathBuf>>>);
+
+impl Sources {
+    fn record(&self, path: &Path) {
+        let mut files = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        files.insert(path.to_path_buf());
+    }
+
+    fn files(&self) -> BTreeSet<PathBuf> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl IncludeResolver for Sources {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        self.record(path);
+        fs::read_to_string(path)
+    }
+}
+
+/// Modification times of a conversion's files, `None` for missing ones
+struct Stamps(Vec<(PathBuf, Option<SystemTime>)>);
+
+impl Stamps {
+    fn new(files: BTreeSet<PathBuf>) -> Self {
+        Self(
+            files
+                .into_iter()
+                .map(|file| {
+                    let modified = modified(&file);
+                    (file, modified)
+                })
+                .collect(),
+        )
+    }
+
+    /// Whether no file has changed since the stamps were taken
+    fn current(&self) -> bool {
+        self.0.iter().all(|(file, stamp)| modified(file) == *stamp)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Run `doctora serve`, returning the exit code if the server cannot start
+///
+/// Each input `docs/guide.adoc` is served as `/docs/guide.html`, rendered
+/// when requested; `/` lists the inputs unless one of them is `index.adoc`.
+/// Other paths serve assets, such as images, from the inputs' directories
+/// (see [`Preview::asset`]). Requests whose `Host` is not this server on
+/// localhost are refused, so other sites cannot reach it through DNS
+/// rebinding, and so are WebSockets from other origins (see
+/// [`Preview::live`]).
+/// Pages load the live preview script (see [`doctora::preview`]), and a
+/// WebSocket per open page sends patches or reloads as its sources change.
+/// The clock is fixed when the server starts, so that `localtime` and the
+/// other date attributes do not change the page on every parse.
+fn serve(args: &ArgMatches) -> u8 {
+    let port = args.get_one::<u16>("port").copied().unwrap_or(8000);
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("port {port}: {err}");
+            return EXIT_IO;
+        }
+    };
+    let port = match listener.local_addr() {
+        Ok(address) => address.port(),
+        Err(err) => {
+            eprintln!("port {port}: {err}");
+            return EXIT_IO;
+        }
+    };
+    eprintln!(
+        "serving on http://{}:{port}/ (Ctrl-C to stop)",
+        Ipv4Addr::LOCALHOST
+    );
+    let preview = Preview {
+        options: parse_options(args).clock(FixedClock(SystemClock.now())),
+        patterns: args
+            .get_many::<String>("files")
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect(),
+        port,
+    };
+    thread::scope(|scope| {
+        for stream in listener.incoming().flatten() {
+            let preview = &preview;
+            scope.spawn(move || {
+                // A client that goes away mid-response is not an error
+                let _ = preview.respond(stream);
+            });
+        }
+    });
+    0
+}
+
+/// How `doctora serve` finds and renders pages
+struct Preview {
+    options: ParseOptions,
+    patterns: Vec<String>,
+    /// The port the server listens on
+    port: u16,
+}
+
+impl Preview {
+    /// The inputs by page path (`docs/guide.html`)
+    ///
+    /// Patterns are expanded on every call, so new files are served too.
+    /// Inputs outside the current directory are left out.
+    fn pages(&self) -> BTreeMap<PathBuf, PathBuf> {
+        self.patterns
+            .iter()
+            .flat_map(|pattern| expand_glob(pattern))
+            .filter_map(|input| {
+                let input = input.strip_prefix(".").unwrap_or(&input).to_path_buf();
+                relative(&input).then(|| (input.with_extension("html"), input))
+            })
+            .collect()
+    }
+
+    /// Parse `input`, returning the document (or the error message) and the
+    /// modification times of the files it was read from
+    fn load(&self, input: &Path) -> (Result<Document, String>, Stamps) {
+        let sources = Sources::default();
+        sources.record(input);
+        let doc = fs::read_to_string(input)
+            .map_err(|err| err.to_string())
+            .and_then(|source| {
+                parse_document_with_options(&source, &file_options(&self.options, input, &sources))
+                    .map_err(|err| err.to_string())
+            })
+            .map_err(|err| format!("{}: {err}", input.display()));
+        (doc, Stamps::new(sources.files()))
+    }
+
+    /// Answer one HTTP request
+    fn respond(&self, mut stream: TcpStream) -> io::Result<()> {
+        let Some(request) = Request::read(&stream)? else {
+            return Ok(());
+        };
+        if !request
+            .header("host")
+            .is_some_and(|host| self.is_own_host(host))
+        {
+            return send(
+                &mut stream,
+                "403 Forbidden",
+                "text/plain",
+                b"Unknown host\n",
+            );
+        }
+        if request.method != "GET" {
+            return send(
+                &mut stream,
+                "405 Method Not Allowed",
+                "text/plain",
+                b"GET only\n",
+            );
+        }
+        if request.path == LIVE_PATH {
+            return self.live(stream, &request);
+        }
+        if request.path == CLIENT_PATH {
+            return send(
+                &mut stream,
+                "200 OK",
+                "text/javascript",
+                CLIENT_SCRIPT.as_bytes(),
+            );
+        }
+
+        let Some(path) = percent_decode(&request.path)
+            .map(|path| PathBuf::from(path.trim_start_matches('/')))
+            .filter(|path| relative(path))
+        else {
+            return send(&mut stream, "404 Not Found", "text/plain", b"Not found\n");
+        };
+        let pages = self.pages();
+        let page = if path.as_os_str().is_empty() {
+            Path::new("index.html")
+        } else {
+            &path
+        };
+        if let Some(input) = pages.get(page) {
+            let (status, html) = match self.load(input).0 {
+                Ok(doc) => {
+                    let options = HtmlOptions::new()
+                        .standalone(true)
+                        .node_ids(true)
+                        .script(CLIENT_PATH);
+                    ("200 OK", render_html(&doc, &options))
+                }
+                Err(err) => ("500 Internal Server Error", error_page(&err)),
+            };
+            return send(
+                &mut stream,
+                status,
+                "text/html; charset=utf-8",
+                html.as_bytes(),
+            );
+        }
+        if path.as_os_str().is_empty() {
+            let index = index_page(&pages);
+            return send(
+                &mut stream,
+                "200 OK",
+                "text/html; charset=utf-8",
+                index.as_bytes(),
+            );
+        }
+        let asset = self.asset(&path, &pages).and_then(|content_type| {
+            let bytes = fs::read(&path).ok()?;
+            Some((content_type, bytes))
+        });
+        match asset {
+            Some((content_type, bytes)) => send(&mut stream, "200 OK", content_type, &bytes),
+            None => send(&mut stream, "404 Not Found", "text/plain", b"Not found\n"),
         }
     }
 
-    println!("\nTotal tokens: {}", token_count);
+    /// Whether a `Host` header names this server: localhost or 127.0.0.1,
+    /// on its port
+    fn is_own_host(&self, host: &str) -> bool {
+        let Some((name, port)) = host.rsplit_once(':') else {
+            return false;
+        };
+        matches!(name, "localhost" | "127.0.0.1") && port.parse() == Ok(self.port)
+    }
 
-    // ===== Step 2: Parsing =====
-    println!("\n{}", "=".repeat(80));
-    println!("STEP 2: PARSING (Winnow)");
-    println!("{}", "=".repeat(80));
+    /// Whether an `Origin` header names a page of this server
+    fn is_own_origin(&self, origin: &str) -> bool {
+        origin
+            .strip_prefix("http://")
+            .is_some_and(|host| self.is_own_host(host))
+    }
 
-    match parse_document(input) {
-        Ok(doc) => {
-            println!("\nParsing successful!");
-            println!("\nAST Structure:");
-            println!("{}", "-".repeat(80));
-            print_document(&doc, 0);
-            println!("\n{}", "=".repeat(80));
-            println!("Summary:");
-            println!("  Total blocks: {}", doc.blocks.len());
-            println!("{}", "=".repeat(80));
+    /// The content type of the asset at `path`, if it may be served
+    ///
+    /// Assets are files below the directory of a served page, with an
+    /// extension from an allowlist of web assets (images, stylesheets,
+    /// scripts, fonts) and no hidden component, so files such as `.env`,
+    /// `.git/config`, or the sources themselves are never served.
+    fn asset(&self, path: &Path, pages: &BTreeMap<PathBuf, PathBuf>) -> Option<&'static str> {
+        let hidden = path
+            .components()
+            .any(|component| component.as_os_str().to_string_lossy().starts_with('.'));
+        let below_input = pages.values().any(|input| {
+            input
+                .parent()
+                .is_some_and(|dir| path.starts_with(dir) && path != dir)
+        });
+        if hidden || !below_input {
+            return None;
         }
-        Err(error) => {
-            println!("\nParsing failed:");
-            println!("  {}", error);
+        asset_type(path)
+    }
+
+    /// Accept a live preview WebSocket and send updates for its page until
+    /// the client disconnects
+    ///
+    /// Updates are computed against the source as it was when the socket
+    /// opened; a page that fails to parse, before or after a change, is
+    /// reloaded to show the error or the fixed page.
+    ///
+    /// Browsers let any page open a WebSocket to any host, so a handshake
+    /// whose `Origin` is not this server is refused: other sites could
+    /// otherwise read the rendered documents. Clients other than browsers
+    /// send no `Origin` and are accepted.
+    fn live(&self, mut stream: TcpStream, request: &Request) -> io::Result<()> {
+        if !request
+            .header("origin")
+            .is_none_or(|origin| self.is_own_origin(origin))
+        {
+            return send(
+                &mut stream,
+                "403 Forbidden",
+                "text/plain",
+                b"Unknown origin\n",
+            );
+        }
+        let key = request.header("sec-websocket-key");
+        let upgrade = request.header("upgrade");
+        let (Some(key), Some(true)) = (key, upgrade.map(|u| u.eq_ignore_ascii_case("websocket")))
+        else {
+            return send(
+                &mut stream,
+                "400 Bad Request",
+                "text/plain",
+                b"WebSocket only\n",
+            );
+        };
+        let page = request
+            .query("page")
+            .and_then(|page| percent_decode(&page))
+            .map(|page| PathBuf::from(page.trim_start_matches('/')))
+            .map(|page| match page.as_os_str().is_empty() {
+                true => PathBuf::from("index.html"),
+                false => page,
+            });
+        let Some(input) = page.and_then(|page| self.pages().remove(&page)) else {
+            return send(&mut stream, "404 Not Found", "text/plain", b"Not found\n");
+        };
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+             Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            websocket_accept(key)
+        )?;
+
+        let (mut doc, mut stamps) = self.load(&input);
+        // Reading doubles as the poll delay and notices closed sockets;
+        // client frames (pongs, close) are not otherwise needed
+        stream.set_read_timeout(Some(WATCH_INTERVAL))?;
+        let mut buffer = [0; 512];
+        loop {
+            match stream.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(_) => continue,
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) => {}
+                Err(err) => return Err(err),
+            }
+            if stamps.current() {
+                continue;
+            }
+            let (new, new_stamps) = self.load(&input);
+            let message = match (&doc, &new) {
+                (Ok(old), Ok(new)) => update(old, new),
+                _ => Some(PreviewUpdate::Reload),
+            };
+            if let Some(message) = message {
+                stream.write_all(&websocket_text_frame(message.message()))?;
+            }
+            (doc, stamps) = (new, new_stamps);
         }
     }
 }
 
-/// Pretty-print the document AST
-fn print_document(doc: &doctora::ast::Document, indent: usize) {
+/// The parts of an HTTP request `doctora serve` uses
+struct Request {
+    method: String,
+    /// Path, without the query
+    path: String,
+    query: String,
+    /// Headers, with lowercase names
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Read a request's line and headers, or `None` for a malformed one
+    fn read(stream: &TcpStream) -> io::Result<Option<Self>> {
+        let mut reader = BufReader::new(stream.take(16 * 1024));
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Ok(None);
+        };
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let mut request = Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+            headers: Vec::new(),
+        };
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let Some((name, value)) = line.split_once(':') else {
+                return Ok(Some(request));
+            };
+            request
+                .headers
+                .push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The raw value of query parameter `name`
+    fn query(&self, name: &str) -> Option<String> {
+        self.query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.replace('+', " "))
+    }
+}
+
+/// Write a complete HTTP response and close the connection
+fn send(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+/// Whether `path` is relative and stays below the directory it starts in
+fn relative(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Decode `%XX` escapes in a URL path, or `None` if the result is not UTF-8
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = text
+            .get(i + 1..i + 3)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// The `Content-Type` of an asset `doctora serve` serves, by extension, or
+/// `None` for other files
+fn asset_type(path: &Path) -> Option<&'static str> {
+    let content_type = match path.extension()?.to_str()? {
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        _ => return None,
+    };
+    Some(content_type)
+}
+
+/// A page linking to every served page
+fn index_page(pages: &BTreeMap<PathBuf, PathBuf>) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Documents</title>\n</head>\n<body>\n<ul>\n",
+    );
+    for (page, input) in pages {
+        let _ = writeln!(
+            html,
+            "<li><a href=\"/{}\">{}</a></li>",
+            escape(&page.to_string_lossy().replace('\\', "/")),
+            escape(&input.display().to_string())
+        );
+    }
+    html.push_str("</ul>\n</body>\n</html>\n");
+    html
+}
+
+/// A page showing a parse error, reloaded once the source changes
+fn error_page(error: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Error</title>\n<script src=\"{CLIENT_PATH}\"></script>\n</head>\n\
+         <body>\n<pre>{}</pre>\n</body>\n</html>\n",
+        escape(error)
+    )
+}
+
+/// Expand a glob pattern into the paths it matches, sorted
+///
+/// In each path component `*` matches any run of characters and `?` one
+/// character; a `**` component matches any number of directories. Hidden
+/// files only match components that start with `.`. An argument without
+/// wildcards is returned as it is, even if no such file exists, so the
+/// read reports the error.
+fn expand_glob(pattern: &str) -> Vec<PathBuf> {
+    if !pattern.contains(['*', '?']) {
+        return vec![PathBuf::from(pattern)];
+    }
+    let (root, rest) = match pattern.strip_prefix('/') {
+        Some(rest) => (PathBuf::from("/"), rest),
+        None => (PathBuf::new(), pattern),
+    };
+    let components: Vec<&str> = rest.split('/').filter(|c| !c.is_empty()).collect();
+    let mut paths = Vec::new();
+    walk_glob(&root, &components, &mut paths);
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// Collect the paths below `dir` matching the pattern `components`
+fn walk_glob(dir: &Path, components: &[&str], paths: &mut Vec<PathBuf>) {
+    let Some((component, rest)) = components.split_first() else {
+        paths.push(dir.to_path_buf());
+        return;
+    };
+    if !component.contains(['*', '?']) {
+        let path = dir.join(component);
+        if (rest.is_empty() && path.exists()) || path.is_dir() {
+            walk_glob(&path, rest, paths);
+        }
+        return;
+    }
+    let read_dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let Ok(entries) = fs::read_dir(read_dir) else {
+        return;
+    };
+    if *component == "**" {
+        walk_glob(dir, rest, paths);
+    }
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') && !component.starts_with('.') {
+            continue;
+        }
+        let path = dir.join(&name);
+        if *component == "**" {
+            if path.is_dir() {
+                walk_glob(&path, components, paths);
+            }
+        } else if wildcard_match(component, &name) && (rest.is_empty() || path.is_dir()) {
+            walk_glob(&path, rest, paths);
+        }
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of
+/// characters and `?` any one character
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it is matched up to
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after, matched)) => {
+                    p = after;
+                    n = matched + 1;
+                    star = Some((after, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Read a file, or stdin for `-`
+fn read_input(input: &str) -> io::Result<String> {
+    if input == "-" {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        Ok(source)
+    } else {
+        fs::read_to_string(input)
+    }
+}
+
+/// Rewrite deprecated syntax in `files` in place, logging each change
+///
+/// Returns the process exit code: 3 if a file could not be read or written.
+fn fix_deprecations(files: &[&String]) -> u8 {
+    let log = |event: &Event<'_>| {
+        if let Event::DeprecatedSyntax {
+            document,
+            line,
+            column,
+            kind,
+            message,
+            ..
+        } = event
+        {
+            eprintln!("{document}:{line}:{column}: {kind}: {message}");
+        }
+    };
+    let mut status = 0;
+    for file in files {
+        let result = fs::read_to_string(file).and_then(|source| {
+            if report_deprecations(file, &source, &log).is_empty() {
+                return Ok(());
+            }
+            fs::write(file, deprecations::fix_deprecations(&source))
+        });
+        if let Err(err) = result {
+            eprintln!("{file}: {err}");
+            status = EXIT_IO;
+        }
+    }
+    status
+}
+
+/// Pretty-print the document AST into `out`
+fn print_document(out: &mut String, doc: &Document, indent: usize) -> fmt::Result {
+    if let Some(header) = &doc.header {
+        let indent_str = "  ".repeat(indent);
+        writeln!(out, "{}Header: {:?}", indent_str, header.title)?;
+        for author in &header.authors {
+            writeln!(
+                out,
+                "{}  Author: {:?} <{:?}>",
+                indent_str, author.name, author.email
+            )?;
+        }
+        if let Some(revision) = &header.revision {
+            writeln!(out, "{}  Revision: {:?}", indent_str, revision)?;
+        }
+        writeln!(
+            out,
+            "{}  Attribute entries: {}",
+            indent_str,
+            header.attributes.len()
+        )?;
+    }
     for (i, block) in doc.blocks.iter().enumerate() {
-        print_block(block, indent, i);
+        print_block(out, block, indent, i)?;
     }
+    Ok(())
 }
 
 /// Pretty-print a block with indentation
-fn print_block(block: &Block, indent: usize, index: usize) {
+fn print_block(out: &mut String, block: &Block, indent: usize, index: usize) -> fmt::Result {
     let indent_str = "  ".repeat(indent);
 
     match block {
@@ -102,54 +1154,279 @@ fn print_block(block: &Block, indent: usize, index: usize) {
             level,
             title,
             content,
+            anchor,
+            ..
         } => {
-            println!(
+            writeln!(
+                out,
                 "{}Block {}: Section (level {})",
                 indent_str, index, level
-            );
-            println!("{}  Title: {:?}", indent_str, title);
+            )?;
+            writeln!(out, "{}  Title: {:?}", indent_str, plain_text(title))?;
+            if let Some(anchor) = anchor {
+                writeln!(out, "{}  Anchor: {:?}", indent_str, anchor.id)?;
+            }
             if !content.is_empty() {
-                println!("{}  Content: {} nested blocks", indent_str, content.len());
+                writeln!(
+                    out,
+                    "{}  Content: {} nested blocks",
+                    indent_str,
+                    content.len()
+                )?;
                 for (i, nested) in content.iter().enumerate() {
-                    print_block(nested, indent + 2, i);
+                    print_block(out, nested, indent + 2, i)?;
                 }
             } else {
-                println!("{}  Content: (empty)", indent_str);
+                writeln!(out, "{}  Content: (empty)", indent_str)?;
             }
         }
-        Block::Paragraph { content } => {
-            println!("{}Block {}: Paragraph", indent_str, index);
-            println!(
-                "{}  Inline nodes: {} items",
-                indent_str,
-                content.len()
-            );
+        Block::Paragraph { content, .. } => {
+            writeln!(out, "{}Block {}: Paragraph", indent_str, index)?;
+            writeln!(out, "{}  Inline nodes: {} items", indent_str, content.len())?;
             for (i, inline) in content.iter().enumerate() {
-                print_inline(inline, indent + 2, i);
+                print_inline(out, inline, indent + 2, i)?;
+            }
+        }
+        Block::UnorderedList { items, .. } => {
+            writeln!(out, "{}Block {}: UnorderedList", indent_str, index)?;
+            print_items(out, items, indent)?;
+        }
+        Block::OrderedList { items, .. } => {
+            writeln!(out, "{}Block {}: OrderedList", indent_str, index)?;
+            print_items(out, items, indent)?;
+        }
+        Block::Listing {
+            language, content, ..
+        } => {
+            writeln!(
+                out,
+                "{}Block {}: Listing ({:?})",
+                indent_str, index, language
+            )?;
+            writeln!(out, "{}  Content: {:?}", indent_str, content)?;
+        }
+        Block::Passthrough {
+            content, backend, ..
+        } => {
+            writeln!(
+                out,
+                "{}Block {}: Passthrough ({})",
+                indent_str, index, backend
+            )?;
+            writeln!(out, "{}  Content: {:?}", indent_str, content)?;
+        }
+        Block::Comment {
+            content, delimited, ..
+        } => {
+            writeln!(
+                out,
+                "{}Block {}: Comment (delimited: {})",
+                indent_str, index, delimited
+            )?;
+            writeln!(out, "{}  Content: {:?}", indent_str, content)?;
+        }
+        Block::AttributeEntry { entry, .. } => {
+            writeln!(
+                out,
+                "{}Block {}: AttributeEntry ({})",
+                indent_str, index, entry.name
+            )?;
+            writeln!(out, "{}  Value: {:?}", indent_str, entry.value)?;
+        }
+        Block::Unknown { raw, span, .. } => {
+            writeln!(out, "{}Block {}: Unknown ({:?})", indent_str, index, span)?;
+            writeln!(out, "{}  Raw: {:?}", indent_str, raw)?;
+        }
+        Block::Quote {
+            attribution,
+            content,
+            ..
+        } => {
+            writeln!(
+                out,
+                "{}Block {}: Quote ({:?})",
+                indent_str, index, attribution
+            )?;
+            for (i, nested) in content.iter().enumerate() {
+                print_block(out, nested, indent + 1, i)?;
+            }
+        }
+        Block::Verse {
+            attribution,
+            content,
+            ..
+        } => {
+            writeln!(
+                out,
+                "{}Block {}: Verse ({:?})",
+                indent_str, index, attribution
+            )?;
+            for (i, inline) in content.iter().enumerate() {
+                print_inline(out, inline, indent + 1, i)?;
+            }
+        }
+        Block::Admonition { kind, content, .. } => {
+            writeln!(
+                out,
+                "{}Block {}: Admonition ({})",
+                indent_str,
+                index,
+                kind.label()
+            )?;
+            for (i, nested) in content.iter().enumerate() {
+                print_block(out, nested, indent + 1, i)?;
+            }
+        }
+        Block::Example { content, .. }
+        | Block::Sidebar { content, .. }
+        | Block::Open { content, .. } => {
+            let kind = match block {
+                Block::Example { .. } => "Example",
+                Block::Sidebar { .. } => "Sidebar",
+                _ => "Open",
+            };
+            writeln!(out, "{}Block {}: {}", indent_str, index, kind)?;
+            for (i, nested) in content.iter().enumerate() {
+                print_block(out, nested, indent + 1, i)?;
+            }
+        }
+        Block::Table { header, rows, .. } => {
+            writeln!(out, "{}Block {}: Table", indent_str, index)?;
+            for (i, row) in header.iter().chain(rows).enumerate() {
+                writeln!(out, "{}  Row {}: {} cells", indent_str, i, row.cells.len())?;
+                for (j, cell) in row.cells.iter().enumerate() {
+                    writeln!(out, "{}    Cell {}:", indent_str, j)?;
+                    for (k, inline) in cell.content.iter().enumerate() {
+                        print_inline(out, inline, indent + 3, k)?;
+                    }
+                }
+            }
+        }
+        Block::Image { target, .. } => {
+            writeln!(out, "{}Block {}: Image({:?})", indent_str, index, target)?;
+        }
+        Block::DescriptionList { items, .. } => {
+            writeln!(out, "{}Block {}: DescriptionList", indent_str, index)?;
+            for (i, item) in items.iter().enumerate() {
+                writeln!(out, "{}  Term {}:", indent_str, i)?;
+                for (j, inline) in item.term.iter().enumerate() {
+                    print_inline(out, inline, indent + 2, j)?;
+                }
+                for (j, inline) in item.description.iter().enumerate() {
+                    print_inline(out, inline, indent + 2, j)?;
+                }
             }
         }
     }
+    Ok(())
+}
+
+/// Pretty-print list items with their attached blocks
+fn print_items(out: &mut String, items: &[ListItem], indent: usize) -> fmt::Result {
+    let indent_str = "  ".repeat(indent);
+
+    for (i, item) in items.iter().enumerate() {
+        writeln!(out, "{}  Item {}:", indent_str, i)?;
+        for (j, inline) in item.content.iter().enumerate() {
+            print_inline(out, inline, indent + 2, j)?;
+        }
+        for (j, nested) in item.blocks.iter().enumerate() {
+            print_block(out, nested, indent + 2, j)?;
+        }
+    }
+    Ok(())
 }
 
 /// Pretty-print an inline node
-fn print_inline(inline: &Inline, indent: usize, index: usize) {
+fn print_inline(out: &mut String, inline: &Inline, indent: usize, index: usize) -> fmt::Result {
     let indent_str = "  ".repeat(indent);
 
     match inline {
         Inline::Text(text) => {
-            println!("{}Inline {}: Text({:?})", indent_str, index, text);
+            writeln!(out, "{}Inline {}: Text({:?})", indent_str, index, text)?;
         }
         Inline::Bold(content) => {
-            println!("{}Inline {}: Bold", indent_str, index);
+            writeln!(out, "{}Inline {}: Bold", indent_str, index)?;
             for (i, nested) in content.iter().enumerate() {
-                print_inline(nested, indent + 1, i);
+                print_inline(out, nested, indent + 1, i)?;
             }
         }
         Inline::Italic(content) => {
-            println!("{}Inline {}: Italic", indent_str, index);
+            writeln!(out, "{}Inline {}: Italic", indent_str, index)?;
+            for (i, nested) in content.iter().enumerate() {
+                print_inline(out, nested, indent + 1, i)?;
+            }
+        }
+        Inline::Monospace(content) => {
+            writeln!(out, "{}Inline {}: Monospace", indent_str, index)?;
+            for (i, nested) in content.iter().enumerate() {
+                print_inline(out, nested, indent + 1, i)?;
+            }
+        }
+        Inline::Highlight(content) => {
+            writeln!(out, "{}Inline {}: Highlight", indent_str, index)?;
+            for (i, nested) in content.iter().enumerate() {
+                print_inline(out, nested, indent + 1, i)?;
+            }
+        }
+        Inline::Span { roles, content } => {
+            writeln!(
+                out,
+                "{}Inline {}: Span ({})",
+                indent_str,
+                index,
+                roles.join(" ")
+            )?;
+            for (i, nested) in content.iter().enumerate() {
+                print_inline(out, nested, indent + 1, i)?;
+            }
+        }
+        Inline::Superscript(content) => {
+            writeln!(out, "{}Inline {}: Superscript", indent_str, index)?;
+            for (i, nested) in content.iter().enumerate() {
+                print_inline(out, nested, indent + 1, i)?;
+            }
+        }
+        Inline::Subscript(content) => {
+            writeln!(out, "{}Inline {}: Subscript", indent_str, index)?;
             for (i, nested) in content.iter().enumerate() {
-                print_inline(nested, indent + 1, i);
+                print_inline(out, nested, indent + 1, i)?;
+            }
+        }
+        Inline::Link { target, text, .. } => {
+            writeln!(out, "{}Inline {}: Link({:?})", indent_str, index, target)?;
+            for (i, nested) in text.iter().enumerate() {
+                print_inline(out, nested, indent + 1, i)?;
             }
         }
+        Inline::XRef { id, text } => {
+            writeln!(out, "{}Inline {}: XRef({:?})", indent_str, index, id)?;
+            for (i, nested) in text.iter().enumerate() {
+                print_inline(out, nested, indent + 1, i)?;
+            }
+        }
+        Inline::Anchor(anchor) => {
+            writeln!(
+                out,
+                "{}Inline {}: Anchor({:?})",
+                indent_str, index, anchor.id
+            )?;
+        }
+        Inline::Image { target, .. } => {
+            writeln!(out, "{}Inline {}: Image({:?})", indent_str, index, target)?;
+        }
+        Inline::Icon { name, .. } => {
+            writeln!(out, "{}Inline {}: Icon({:?})", indent_str, index, name)?;
+        }
+        Inline::Footnote { id, text } => {
+            writeln!(out, "{}Inline {}: Footnote({:?})", indent_str, index, id)?;
+            for (i, nested) in text.iter().enumerate() {
+                print_inline(out, nested, indent + 1, i)?;
+            }
+        }
+        Inline::BreakHint(hint) => {
+            writeln!(out, "{}Inline {}: BreakHint({:?})", indent_str, index, hint)?;
+        }
     }
+    Ok(())
 }
@@ -0,0 +1,356 @@
+//! Rendered lengths and source columns of inline content
+//!
+//! Style checks such as "heading title too long" or "line exceeds 100
+//! characters, not counting URLs" need more than raw line lengths: they need
+//! to know how long each piece of text is once formatting marks are gone, and
+//! where it sits in the source line. [`line_metrics`] parses one line of
+//! inline content and reports both for every node of the resulting tree.
+//!
+//! Lengths and columns count characters (Unicode scalar values), not bytes,
+//! and columns are 0-based from the start of the line.
+//!
+//! For AST nodes without their source, [`text_len`](crate::ast::text_len)
+//! gives the rendered length alone.
+//!
+//! # Examples
+//!
+//! Measure a line without its links:
+//!
+//! ```
+//! use doctora::metrics::line_metrics;
+//!
+//! let line = "See **https://example.com/a/long/path** for details.";
+//! let metrics = line_metrics(line);
+//!
+//! let link_len: usize = metrics
+//!     .iter()
+//!     .filter(|node| node.target.is_some())
+//!     .map(|node| node.source_len)
+//!     .sum();
+//! assert_eq!(line.chars().count() - link_len, 21);
+//!
+//! // The bold node starts at its opening `**`
+//! let bold = &metrics[1];
+//! assert_eq!((bold.column, bold.source_len, bold.text_len), (4, 35, 31));
+//! ```
+
+use crate::ast::Inline;
+use crate::formatting::{Delimiter, match_delimiters, scan_atoms, scan_delimiters};
+use crate::token::{Span, lex_with_spans};
+
+/// Lengths and position of one inline node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineMetrics {
+    /// Position of the node, as child indices from the line's top-level
+    /// content
+    ///
+    /// Indices refer to the tree [`format_inlines`](crate::formatting::format_inlines)
+    /// builds for the same line.
+    pub path: Vec<usize>,
+    /// Column of the node's first character, including any opening mark
+    pub column: usize,
+    /// Characters the node spans in the source, including its marks
+    pub source_len: usize,
+    /// Characters of the node's rendered text, without marks
+    pub text_len: usize,
+    /// Text of a plain text node, `None` for formatting nodes
+    pub text: Option<String>,
+    /// URL of a link node, `None` for other nodes
+    ///
+    /// Links, cross references, inline anchors and images are leaves here:
+    /// their text is not measured separately, and images have none.
+    pub target: Option<String>,
+}
+
+impl InlineMetrics {
+    /// Column just past the node's last character, including any closing mark
+    pub fn end_column(&self) -> usize {
+        self.column + self.source_len
+    }
+}
+
+/// Parse one line of inline content and measure each node
+///
+/// Nodes are listed in source order, each formatting node before its
+/// children. The line should hold inline content only, such as a paragraph
+/// line or a heading title without its `==` marker.
+pub fn line_metrics(line: &str) -> Vec<InlineMetrics> {
+    let tokens = lex_with_spans(line);
+    let (Some((_, first)), Some((_, last))) = (tokens.first(), tokens.last()) else {
+        return Vec::new();
+    };
+    let delimiters = scan_delimiters(line, &tokens);
+    let pairs = match_delimiters(&delimiters);
+    let atoms = scan_atoms(line, &tokens);
+    Measure::new(line, atoms).run(first.start..last.end, &delimiters, &pairs)
+}
+
+/// Walks paired delimiters the way inline formatting builds its tree
+struct Measure<'a> {
+    line: &'a str,
+    /// Links, cross references and anchors of the line, measured as single
+    /// nodes
+    atoms: Vec<(Span, Inline)>,
+    out: Vec<InlineMetrics>,
+    /// Open formatting nodes: index into `out` and number of children so far
+    stack: Vec<(usize, usize)>,
+    /// Number of top-level nodes so far
+    top_level: usize,
+}
+
+impl<'a> Measure<'a> {
+    fn new(line: &'a str, atoms: Vec<(Span, Inline)>) -> Self {
+        Self {
+            line,
+            atoms,
+            out: Vec::new(),
+            stack: Vec::new(),
+            top_level: 0,
+        }
+    }
+
+    fn run(
+        mut self,
+        range: Span,
+        delimiters: &[Delimiter],
+        pairs: &[(usize, usize)],
+    ) -> Vec<InlineMetrics> {
+        let mut events: Vec<(usize, bool, bool)> = Vec::with_capacity(pairs.len() * 2);
+        for &(open, close) in pairs {
+            let escaped = delimiters[open].escaped;
+            events.push((open, true, escaped));
+            events.push((close, false, escaped));
+        }
+        events.sort_by_key(|&(index, _, _)| delimiters[index].span.start);
+
+        let mut cursor = range.start;
+        for (index, opens, escaped) in events {
+            let span = &delimiters[index].span;
+            if escaped {
+                // Escaped marks are text; the backslash joins the text around it
+                if opens {
+                    self.text(cursor..span.start - 1);
+                    cursor = span.start;
+                }
+                continue;
+            }
+            self.text(cursor..span.start);
+            cursor = span.end;
+            if opens {
+                let node = self.push(span.start..span.start, None);
+                self.stack.push((node, 0));
+            } else {
+                let (node, _) = self.stack.pop().unwrap();
+                let start = self.out[node].column;
+                self.out[node].source_len = self.column(span.end) - start;
+            }
+        }
+        self.text(cursor..range.end);
+        self.out
+    }
+
+    /// Record the text and atom nodes of source `range`
+    fn text(&mut self, range: Span) {
+        let mut cursor = range.start;
+        let atoms: Vec<(Span, Option<String>, usize)> = self
+            .atoms
+            .iter()
+            .filter(|(span, _)| range.start <= span.start && span.end <= range.end)
+            .map(|(span, atom)| {
+                let target = match atom {
+                    Inline::Link { target, .. } => Some(target.clone()),
+                    _ => None,
+                };
+                (span.clone(), target, atom.text_len())
+            })
+            .collect();
+        for (span, target, len) in atoms {
+            self.plain_text(cursor..span.start);
+            let node = self.push(span.clone(), None);
+            self.out[node].target = target;
+            self.add_text_len(node, len);
+            cursor = span.end;
+        }
+        self.plain_text(cursor..range.end);
+    }
+
+    /// Record a text node for the non-empty source `range`
+    fn plain_text(&mut self, range: Span) {
+        if range.is_empty() {
+            return;
+        }
+        let text = self.line[range.clone()].to_string();
+        let len = text.chars().count();
+        // Text after a dropped escape backslash extends the text before it
+        let (column, end) = (self.column(range.start), self.column(range.end));
+        if let Some(last) = self.out.last_mut()
+            && last.text.is_some()
+            && last.end_column() + 1 == column
+            && self.line[..range.start].ends_with('\\')
+        {
+            last.text.as_mut().unwrap().push_str(&text);
+            last.source_len = end - last.column;
+            last.text_len += len;
+            for &(open, _) in &self.stack {
+                self.out[open].text_len += len;
+            }
+            return;
+        }
+        let node = self.push(range, Some(text));
+        self.add_text_len(node, len);
+    }
+
+    /// Set the rendered length of the leaf `node`
+    fn add_text_len(&mut self, node: usize, len: usize) {
+        // Rendered text counts towards every enclosing formatting node
+        for &(open, _) in &self.stack {
+            self.out[open].text_len += len;
+        }
+        self.out[node].text_len = len;
+    }
+
+    /// Record a node for source `range` as the next child of the innermost
+    /// open node
+    fn push(&mut self, range: Span, text: Option<String>) -> usize {
+        let mut path: Vec<usize> = self
+            .stack
+            .iter()
+            .map(|&(open, _)| *self.out[open].path.last().unwrap())
+            .collect();
+        let siblings = match self.stack.last_mut() {
+            Some((_, children)) => children,
+            None => &mut self.top_level,
+        };
+        path.push(*siblings);
+        *siblings += 1;
+
+        let column = self.column(range.start);
+        self.out.push(InlineMetrics {
+            path,
+            column,
+            source_len: self.column(range.end) - column,
+            text_len: 0,
+            text,
+            target: None,
+        });
+        self.out.len() - 1
+    }
+
+    /// Character column of byte offset `offset`
+    fn column(&self, offset: usize) -> usize {
+        self.line[..offset].chars().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Inline, text_len};
+    use crate::formatting::format_inlines;
+
+    /// Find the node at `path` in `content`
+    fn node<'a>(content: &'a [Inline], path: &[usize]) -> &'a Inline {
+        let inline = &content[path[0]];
+        match (inline, &path[1..]) {
+            (_, []) => inline,
+            (
+                Inline::Bold(nested)
+                | Inline::Italic(nested)
+                | Inline::Monospace(nested)
+                | Inline::Highlight(nested)
+                | Inline::Span {
+                    content: nested, ..
+                }
+                | Inline::Superscript(nested)
+                | Inline::Subscript(nested),
+                rest,
+            ) => node(nested, rest),
+            _ => {
+                panic!("path {:?} goes through a leaf", path)
+            }
+        }
+    }
+
+    #[test]
+    fn test_nested_formatting() {
+        let metrics = line_metrics("a **b _c_** d");
+        let summary: Vec<_> = metrics
+            .iter()
+            .map(|m| (m.path.clone(), m.column, m.source_len, m.text_len))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (vec![0], 0, 2, 2),
+                (vec![1], 2, 9, 3),
+                (vec![1, 0], 4, 2, 2),
+                (vec![1, 1], 6, 3, 1),
+                (vec![1, 1, 0], 7, 1, 1),
+                (vec![2], 11, 2, 2),
+            ]
+        );
+        assert_eq!(metrics[4].text.as_deref(), Some("c"));
+        assert_eq!(metrics[1].end_column(), 11);
+    }
+
+    #[test]
+    fn test_paths_match_formatted_tree() {
+        let line =
+            "**x** and _y_ or *z __w__*, lone * mark, *see http://a.io[*A*]*, \\*e* \\**f _g_**";
+        let content = format_inlines(line, &lex_with_spans(line));
+        for metrics in line_metrics(line) {
+            let inline = node(&content, &metrics.path);
+            assert_eq!(inline.text_len(), metrics.text_len, "{:?}", metrics.path);
+            assert_eq!(metrics.text.as_deref(), inline.as_text());
+        }
+        let top_level: usize = line_metrics(line)
+            .iter()
+            .filter(|m| m.path.len() == 1)
+            .map(|m| m.text_len)
+            .sum();
+        assert_eq!(top_level, text_len(&content));
+    }
+
+    #[test]
+    fn test_columns_count_characters() {
+        let metrics = line_metrics("日本 *語* é");
+        assert_eq!(metrics[1].column, 3);
+        assert_eq!(metrics[1].source_len, 3);
+        assert_eq!(metrics[3].column, 6);
+        assert_eq!(metrics[3].text_len, 2);
+    }
+
+    #[test]
+    fn test_links() {
+        let metrics = line_metrics("a https://b.io[_c_] d");
+        assert_eq!(metrics.len(), 3);
+        let link = &metrics[1];
+        assert_eq!(link.target.as_deref(), Some("https://b.io"));
+        assert_eq!((link.column, link.source_len, link.text_len), (2, 17, 1));
+        assert_eq!(link.text, None);
+        assert_eq!(metrics[2].column, 19);
+    }
+
+    #[test]
+    fn test_xrefs_and_anchors() {
+        let metrics = line_metrics("[[a]]see <<b,*c*>>image:d.png[e]");
+        let summary: Vec<_> = metrics
+            .iter()
+            .map(|m| (m.column, m.source_len, m.text_len, m.target.is_some()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, 5, 0, false),
+                (5, 4, 4, false),
+                (9, 9, 1, false),
+                (18, 14, 0, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_line() {
+        assert!(line_metrics("").is_empty());
+    }
+}
@@ -0,0 +1,216 @@
+//! Opt-in source normalization (whitespace hygiene)
+//!
+//! This preprocessor rewrites AsciiDoc source text before it reaches the lexer.
+//! It is never applied implicitly: callers that want repositories to enforce
+//! whitespace hygiene through doctora run [`normalize_source`] themselves and
+//! either parse the result or write it back to disk.
+//!
+//! # Rules
+//!
+//! - Trailing spaces and tabs are stripped from every line outside verbatim
+//!   blocks
+//! - Tabs are expanded to spaces (to the next tab stop), except inside verbatim
+//!   blocks where tabs may be significant
+//! - Line endings are normalized to `\n`
+//! - The output ends with exactly one trailing newline (empty input stays
+//!   empty), unless it ends inside an unclosed verbatim block, whose trailing
+//!   blank lines are content
+//!
+//! Verbatim blocks are delimited listing (`----`), literal (`....`), and
+//! passthrough (`++++`) blocks. A verbatim block is closed by a delimiter line
+//! identical to the one that opened it.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::normalize::{NormalizeOptions, normalize_source};
+//!
+//! let input = "Title  \n\tindented\n----\n\tkept  \n----\n\n\n";
+//! let output = normalize_source(input, &NormalizeOptions::default());
+//!
+//! assert_eq!(output, "Title\n    indented\n----\n\tkept  \n----\n");
+//! ```
+
+/// Options controlling [`normalize_source`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizeOptions {
+    /// Number of columns between tab stops when expanding tabs
+    pub tab_width: usize,
+    /// Strip trailing whitespace from each line outside verbatim blocks
+    pub strip_trailing_whitespace: bool,
+    /// Expand tabs to spaces outside verbatim blocks
+    pub expand_tabs: bool,
+    /// Ensure the output ends with exactly one newline
+    pub final_newline: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        Self {
+            tab_width: 4,
+            strip_trailing_whitespace: true,
+            expand_tabs: true,
+            final_newline: true,
+        }
+    }
+}
+
+/// Normalize whitespace in AsciiDoc source according to `options`
+///
+/// See the [module documentation](self) for the exact rules.
+pub fn normalize_source(input: &str, options: &NormalizeOptions) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut verbatim_delimiter: Option<&str> = None;
+
+    for line in input.lines() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let trimmed = line.trim_end_matches([' ', '\t']);
+        let stripped = if options.strip_trailing_whitespace {
+            trimmed
+        } else {
+            line
+        };
+
+        let line = match verbatim_delimiter {
+            Some(open) if trimmed == open => {
+                verbatim_delimiter = None;
+                stripped
+            }
+            // Whitespace inside verbatim blocks is content
+            Some(_) => line,
+            None => {
+                if is_verbatim_delimiter(trimmed) {
+                    verbatim_delimiter = Some(trimmed);
+                }
+                stripped
+            }
+        };
+
+        // Delimiter lines never contain tabs, so only content lines are affected
+        if options.expand_tabs && verbatim_delimiter.is_none() && line.contains('\t') {
+            expand_tabs(line, options.tab_width, &mut output);
+        } else {
+            output.push_str(line);
+        }
+        output.push('\n');
+    }
+
+    if options.final_newline {
+        if verbatim_delimiter.is_none() {
+            let trimmed = output.trim_end_matches('\n').len();
+            output.truncate(trimmed);
+            if !output.is_empty() {
+                output.push('\n');
+            }
+        }
+    } else if !input.ends_with('\n') {
+        output.pop();
+    }
+
+    output
+}
+
+/// Returns true if `line` opens or closes a verbatim delimited block
+fn is_verbatim_delimiter(line: &str) -> bool {
+    let Some(first) = line.chars().next() else {
+        return false;
+    };
+    matches!(first, '-' | '.' | '+') && line.len() >= 4 && line.chars().all(|c| c == first)
+}
+
+/// Append `line` to `out` with tabs expanded to the next multiple of `tab_width`
+fn expand_tabs(line: &str, tab_width: usize, out: &mut String) {
+    let tab_width = tab_width.max(1);
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = tab_width - column % tab_width;
+            out.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+        } else {
+            out.push(c);
+            column += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(input: &str) -> String {
+        normalize_source(input, &NormalizeOptions::default())
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert_eq!(normalize(""), "");
+        assert_eq!(normalize("\n\n"), "");
+    }
+
+    #[test]
+    fn test_strips_trailing_whitespace() {
+        assert_eq!(normalize("a  \nb\t\n"), "a\nb\n");
+    }
+
+    #[test]
+    fn test_single_trailing_newline() {
+        assert_eq!(normalize("text"), "text\n");
+        assert_eq!(normalize("text\n\n\n"), "text\n");
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        assert_eq!(normalize("a\r\nb\r\n"), "a\nb\n");
+    }
+
+    #[test]
+    fn test_tab_stops() {
+        assert_eq!(normalize("\tx"), "    x\n");
+        assert_eq!(normalize("ab\tx"), "ab  x\n");
+
+        let options = NormalizeOptions {
+            tab_width: 2,
+            ..NormalizeOptions::default()
+        };
+        assert_eq!(normalize_source("\t\tx", &options), "    x\n");
+    }
+
+    #[test]
+    fn test_tabs_preserved_in_verbatim_blocks() {
+        let input = "....\n\tliteral\n....\n\tafter\n";
+        assert_eq!(normalize(input), "....\n\tliteral\n....\n    after\n");
+    }
+
+    #[test]
+    fn test_verbatim_block_needs_matching_delimiter() {
+        // A shorter delimiter does not close a longer one
+        let input = "------\n\ta\n----\n\tb\n------\n\tc";
+        assert_eq!(normalize(input), "------\n\ta\n----\n\tb\n------\n    c\n");
+    }
+
+    #[test]
+    fn test_trailing_whitespace_kept_in_verbatim_blocks() {
+        let input = "----  \ncode   \n  \t\n----\ntext  \n";
+        assert_eq!(normalize(input), "----\ncode   \n  \t\n----\ntext\n");
+        assert_eq!(normalize("++++\n<b> \n++++ \n\n"), "++++\n<b> \n++++\n");
+    }
+
+    #[test]
+    fn test_trailing_newlines_kept_in_unclosed_verbatim_block() {
+        assert_eq!(normalize("....\na\n\n\n"), "....\na\n\n\n");
+        assert_eq!(normalize("....\na\n....\n\n\n"), "....\na\n....\n");
+    }
+
+    #[test]
+    fn test_rules_can_be_disabled() {
+        let options = NormalizeOptions {
+            tab_width: 4,
+            strip_trailing_whitespace: false,
+            expand_tabs: false,
+            final_newline: false,
+        };
+        assert_eq!(normalize_source("a \n\tb", &options), "a \n\tb");
+        assert_eq!(normalize_source("a\n\n", &options), "a\n\n");
+    }
+}
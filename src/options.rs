@@ -0,0 +1,814 @@
+//! Parse options
+//!
+//! [`ParseOptions`] configures a parse beyond the source text itself. It is
+//! passed to [`parse_document_with_options`](crate::parse_document_with_options)
+//! and built with chained methods:
+//!
+//! ```
+//! use doctora::options::ParseOptions;
+//! use doctora::parse_document_with_options;
+//!
+//! let options = ParseOptions::new()
+//!     .attribute("product", "Doctor A")
+//!     .attribute("title", "{product} Guide");
+//!
+//! let doc = parse_document_with_options("Hello.", &options).unwrap();
+//! assert_eq!(doc.attributes.get("title"), Some("Doctor A Guide"));
+//! ```
+//!
+//! # Environment Attributes
+//!
+//! In CI, values like versions and dates often come from the build environment.
+//! [`ParseOptions::env_attributes`] maps environment variables that start with a
+//! given prefix into document attributes. The rest of the variable name is
+//! lowercased and `_` becomes `-`, so with the prefix `DOCTORA_ATTR_`:
+//!
+//! | Variable                       | Attribute          |
+//! |--------------------------------|--------------------|
+//! | `DOCTORA_ATTR_VERSION`         | `version`          |
+//! | `DOCTORA_ATTR_RELEASE_DATE`    | `release-date`     |
+//!
+//! This is strictly opt-in: without a prefix no environment variable is read,
+//! so documents cannot observe secrets from the environment by accident. An
+//! empty prefix, which would select every variable, is rejected with
+//! [`AttributeError::EmptyEnvPrefix`] when parsing.
+//! Attributes set with [`ParseOptions::attribute`] take precedence over
+//! environment attributes of the same name.
+//!
+//! # Date and Time Attributes
+//!
+//! Every parse defines `localdate`, `localtime`, `localdatetime`, `localyear`
+//! and the matching `doc*` attributes from a [`Clock`]. Inject a fixed clock with
+//! [`ParseOptions::clock`] to make output reproducible (see [`crate::clock`]).
+//!
+//! # Attribute Substitution
+//!
+//! [`ParseOptions::substitute_attributes`] replaces `{name}` references in the
+//! parsed AST with attribute values, including those supplied here and the
+//! header's attribute entries (see [`crate::resolve`]).
+//!
+//! # Substitution Defaults
+//!
+//! [`ParseOptions::substitutions`] changes which substitution groups apply to
+//! a whole kind of block by default, such as attribute references in every
+//! listing, without a `subs` attribute on each block. A block's own `subs`
+//! attribute applies relative to these defaults. The groups applied while
+//! parsing (inline formatting and macros, attribute references, and with
+//! break hints, replacements) follow them; see [`crate::subs`] for the
+//! groups. Special characters are escaped by the renderer, so
+//! [`HtmlOptions::substitutions`](crate::render::html::HtmlOptions::substitutions)
+//! takes the same defaults.
+//!
+//! ```
+//! use doctora::options::ParseOptions;
+//! use doctora::parse_document_with_options;
+//! use doctora::subs::Substitutions;
+//!
+//! let options = ParseOptions::new()
+//!     .attribute("version", "1.2")
+//!     .substitute_attributes(true)
+//!     .substitutions("listing", Substitutions::VERBATIM | Substitutions::ATTRIBUTES);
+//! let doc = parse_document_with_options("----\ncargo add doctora@{version}\n----\n", &options)
+//!     .unwrap();
+//! let doctora::ast::Block::Listing { content, .. } = &doc.blocks[0] else {
+//!     panic!("expected a listing");
+//! };
+//! assert_eq!(content, "cargo add doctora@1.2");
+//! ```
+//!
+//! # Section IDs
+//!
+//! [`ParseOptions::generate_section_ids`] gives every section without an
+//! anchor the ID Asciidoctor would generate from its title (`_getting_started`),
+//! so renderers emit it and cross references to it resolve (see
+//! [`crate::xref::section_ids`]). Titles are substituted first when attribute
+//! substitution is on. The `idprefix` and `idseparator` attributes, supplied
+//! here or set in the header, shape the IDs as they do in Asciidoctor, so
+//! `:idprefix:` with `:idseparator: -` gives `getting-started`.
+//!
+//! # Includes
+//!
+//! [`ParseOptions::includes`] expands `include::` directives before the source
+//! is lexed (see [`crate::include`]). Include targets can reference the
+//! attributes supplied here. Without it, directives stay in the text.
+//!
+//! # Unicode Normalization
+//!
+//! [`ParseOptions::normalize_unicode`] rewrites the AST's text to a Unicode
+//! normalization form after parsing (see [`crate::unicode`]). The source text is
+//! not changed.
+//!
+//! # Replacements
+//!
+//! [`ParseOptions::replacements`] turns ASCII spellings like `(C)`, `--` and
+//! `...` into the characters they stand for in blocks whose substitutions
+//! include `replacements` (see [`crate::replacements`]). Like break hints, it
+//! runs after attribute substitution.
+//!
+//! # Break Hints
+//!
+//! [`ParseOptions::break_hints`] turns soft hyphens and non-breaking spaces
+//! into [`Inline::BreakHint`](crate::ast::Inline::BreakHint) nodes, so print
+//! backends can honor them when breaking lines (see [`crate::hints`]). It runs
+//! after attribute substitution, so `{nbsp}` becomes a hint too.
+//!
+//! # Comments
+//!
+//! Line (`//`) and block (`////`) comments are dropped from the AST unless
+//! [`ParseOptions::keep_comments`] is set, in which case they appear as
+//! [`Block::Comment`](crate::ast::Block::Comment) for tools that write the
+//! document back out. Renderers skip them either way.
+//!
+//! # Parser Backends
+//!
+//! [`ParseOptions::parser`] picks the parser implementation by
+//! [`ParserBackend`], so callers never name a parser module. Winnow is the
+//! only backend today and the default; the enum is non-exhaustive so
+//! benchmarking backends can be added without breaking callers.
+
+use crate::attributes::{AttributeError, Attributes, is_name_char};
+use crate::clock::{Clock, SystemClock, insert_date_attributes};
+use crate::include::IncludeOptions;
+use crate::limits::Limits;
+use crate::subs::{SubstitutionDefaults, Substitutions};
+use crate::unicode::{NormalizationForm, UnicodeNormalizer};
+use std::ffi::OsString;
+use std::sync::Arc;
+
+/// Options controlling how a document is parsed
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Attributes supplied by the caller, in insertion order
+    attributes: Vec<(String, String)>,
+    /// Prefix of environment variables mapped to attributes, if enabled
+    env_prefix: Option<String>,
+    /// Time source for date attributes (`None` means [`SystemClock`])
+    clock: Option<Arc<dyn Clock>>,
+    /// Size limits for lists and tables
+    limits: Limits,
+    /// Whether attribute references are substituted after parsing
+    substitute_attributes: bool,
+    /// Default substitutions by block kind
+    substitutions: SubstitutionDefaults,
+    /// Whether sections without an anchor get a generated ID
+    generate_section_ids: bool,
+    /// Whether textual replacements are applied after parsing
+    replacements: bool,
+    /// Whether soft hyphens and non-breaking spaces become break hints
+    break_hints: bool,
+    /// Whether comments are kept in the AST
+    keep_comments: bool,
+    /// How include directives are expanded, if they are
+    includes: Option<IncludeOptions>,
+    /// Normalization form for the AST's text, and the normalizer for it
+    unicode: Option<(NormalizationForm, Arc<dyn UnicodeNormalizer>)>,
+    /// Parser implementation
+    parser: ParserBackend,
+}
+
+/// Parser implementation used by
+/// [`parse_document_with_options`](crate::parse_document_with_options)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParserBackend {
+    /// The Winnow parser ([`crate::parser_winnow`])
+    #[default]
+    Winnow,
+}
+
+impl ParseOptions {
+    /// Creates options with no attributes and environment mapping disabled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines a document attribute before parsing begins
+    ///
+    /// Values may reference other attributes supplied through the options
+    /// (in any order) using `{name}`.
+    pub fn attribute(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.push((name.into(), value.into()));
+        self
+    }
+
+    /// Maps environment variables starting with `prefix` to attributes
+    ///
+    /// See the [module documentation](self) for the naming rules. The prefix
+    /// must not be empty; parsing fails with
+    /// [`AttributeError::EmptyEnvPrefix`] if it is. Variables whose names
+    /// are not UTF-8 are ignored, and values that are not UTF-8 are
+    /// converted lossily.
+    pub fn env_attributes(mut self, prefix: impl Into<String>) -> Self {
+        self.env_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Returns the environment variable prefix, if environment mapping is enabled
+    pub fn env_prefix(&self) -> Option<&str> {
+        self.env_prefix.as_deref()
+    }
+
+    /// Sets the time source for `localdate`, `docdate`, and related attributes
+    ///
+    /// Use a [`FixedClock`](crate::clock::FixedClock) to pin timestamps in
+    /// tests and reproducible builds.
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Sets size limits for lists and tables (see [`crate::limits`])
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Returns the configured size limits
+    pub fn size_limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Replaces attribute references in the parsed AST (see [`crate::resolve`])
+    ///
+    /// Off by default, so the AST keeps references as written.
+    pub fn substitute_attributes(mut self, enabled: bool) -> Self {
+        self.substitute_attributes = enabled;
+        self
+    }
+
+    /// Returns true if attribute references are substituted after parsing
+    pub fn substitutes_attributes(&self) -> bool {
+        self.substitute_attributes
+    }
+
+    /// Applies `subs` by default to blocks of kind `kind`, named as by
+    /// [`Block::type_name`](crate::ast::Block::type_name) (`paragraph`,
+    /// `listing`, ...); see "Substitution Defaults" above
+    pub fn substitutions(mut self, kind: impl Into<String>, subs: Substitutions) -> Self {
+        self.substitutions.set(kind, subs);
+        self
+    }
+
+    /// Returns the default substitutions by block kind
+    pub fn substitution_defaults(&self) -> &SubstitutionDefaults {
+        &self.substitutions
+    }
+
+    /// Gives sections without an anchor a generated ID (see
+    /// [`crate::xref::assign_section_ids`])
+    ///
+    /// Off by default, so only anchors written in the source appear in the
+    /// AST.
+    pub fn generate_section_ids(mut self, enabled: bool) -> Self {
+        self.generate_section_ids = enabled;
+        self
+    }
+
+    /// Returns true if sections without an anchor get a generated ID
+    pub fn generates_section_ids(&self) -> bool {
+        self.generate_section_ids
+    }
+
+    /// Replaces ASCII spellings like `(C)` and `--` with the characters they
+    /// stand for (see [`crate::replacements`])
+    ///
+    /// Off by default, so the text stays as written.
+    pub fn replacements(mut self, enabled: bool) -> Self {
+        self.replacements = enabled;
+        self
+    }
+
+    /// Returns true if textual replacements are applied after parsing
+    pub fn applies_replacements(&self) -> bool {
+        self.replacements
+    }
+
+    /// Turns soft hyphens and non-breaking spaces into break hint nodes (see
+    /// [`crate::hints`])
+    ///
+    /// Off by default, so they stay in the text as written.
+    pub fn break_hints(mut self, enabled: bool) -> Self {
+        self.break_hints = enabled;
+        self
+    }
+
+    /// Returns true if break hints are marked after parsing
+    pub fn marks_break_hints(&self) -> bool {
+        self.break_hints
+    }
+
+    /// Keeps line and block comments in the AST as
+    /// [`Block::Comment`](crate::ast::Block::Comment)
+    ///
+    /// Off by default, so comments are parsed and discarded.
+    pub fn keep_comments(mut self, enabled: bool) -> Self {
+        self.keep_comments = enabled;
+        self
+    }
+
+    /// Returns true if comments are kept in the AST
+    pub fn keeps_comments(&self) -> bool {
+        self.keep_comments
+    }
+
+    /// Expands include directives before lexing (see [`crate::include`])
+    pub fn includes(mut self, includes: IncludeOptions) -> Self {
+        self.includes = Some(includes);
+        self
+    }
+
+    /// Returns the include options, if includes are expanded
+    pub fn include_options(&self) -> Option<&IncludeOptions> {
+        self.includes.as_ref()
+    }
+
+    /// Normalizes the AST's text to `form` with `normalizer` after parsing
+    /// (see [`crate::unicode`])
+    ///
+    /// Off by default, so text keeps the code points of the source.
+    pub fn normalize_unicode(
+        mut self,
+        form: NormalizationForm,
+        normalizer: impl UnicodeNormalizer + 'static,
+    ) -> Self {
+        self.unicode = Some((form, Arc::new(normalizer)));
+        self
+    }
+
+    /// Returns the normalization form and normalizer, if text is normalized
+    pub fn unicode_normalization(&self) -> Option<(NormalizationForm, &dyn UnicodeNormalizer)> {
+        self.unicode
+            .as_ref()
+            .map(|(form, normalizer)| (*form, normalizer.as_ref()))
+    }
+
+    /// Parses with `backend` (see "Parser Backends" above)
+    pub fn parser(mut self, backend: ParserBackend) -> Self {
+        self.parser = backend;
+        self
+    }
+
+    /// Returns the parser implementation
+    pub fn parser_backend(&self) -> ParserBackend {
+        self.parser
+    }
+
+    /// Whether `name` was set with [`ParseOptions::attribute`]
+    pub(crate) fn sets_attribute(&self, name: &str) -> bool {
+        self.attributes.iter().any(|(existing, _)| existing == name)
+    }
+
+    /// Builds the attributes a document starts with: those of
+    /// [`option_attributes`](Self::option_attributes) and the date attributes
+    pub(crate) fn initial_attributes(&self) -> Result<Attributes, AttributeError> {
+        let mut attributes = self.option_attributes()?;
+        let formats = attributes.clone();
+        self.insert_date_attributes(&mut attributes, &formats);
+        Ok(attributes)
+    }
+
+    /// Builds the attributes from the environment and
+    /// [`ParseOptions::attribute`]
+    pub(crate) fn option_attributes(&self) -> Result<Attributes, AttributeError> {
+        let env_entries = match &self.env_prefix {
+            Some(prefix) if prefix.is_empty() => return Err(AttributeError::EmptyEnvPrefix),
+            Some(prefix) => env_attribute_entries(prefix, std::env::vars_os()),
+            None => Vec::new(),
+        };
+        initial_attributes_from(
+            env_entries,
+            &self.attributes,
+            self.limits.max_attribute_length,
+        )
+    }
+
+    /// Defines the date attributes from the clock, with the `date-format`
+    /// and `time-format` of `formats`
+    pub(crate) fn insert_date_attributes(&self, attributes: &mut Attributes, formats: &Attributes) {
+        let now = match &self.clock {
+            Some(clock) => clock.now(),
+            None => SystemClock.now(),
+        };
+        insert_date_attributes(attributes, now, formats);
+    }
+}
+
+/// Combine environment and explicit attributes, explicit entries winning
+fn initial_attributes_from(
+    env_entries: Vec<(String, String)>,
+    explicit: &[(String, String)],
+    max_length: Option<usize>,
+) -> Result<Attributes, AttributeError> {
+    let mut entries = env_entries;
+    for (name, value) in explicit {
+        entries.retain(|(existing, _)| existing != name);
+        entries.push((name.clone(), value.clone()));
+    }
+
+    let mut attributes = Attributes::new();
+    attributes.define_all_within(
+        entries
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str())),
+        max_length,
+    )?;
+    Ok(attributes)
+}
+
+/// Select variables starting with `prefix` and convert their names to attributes
+///
+/// Names that are not UTF-8 cannot start with `prefix` and are skipped;
+/// values that are not UTF-8 are converted lossily.
+fn env_attribute_entries<I>(prefix: &str, vars: I) -> Vec<(String, String)>
+where
+    I: IntoIterator<Item = (OsString, OsString)>,
+{
+    let mut entries: Vec<(String, String)> = vars
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let name = key
+                .to_str()?
+                .strip_prefix(prefix)?
+                .to_ascii_lowercase()
+                .replace('_', "-");
+            let valid = !name.is_empty() && name.chars().all(is_name_char);
+            valid.then(|| (name, value.to_string_lossy().into_owned()))
+        })
+        .collect();
+    entries.sort();
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{FixedClock, Timestamp};
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_env_disabled_by_default() {
+        let options = ParseOptions::new();
+        assert_eq!(options.env_prefix(), None);
+
+        let attrs = options.initial_attributes().unwrap();
+        assert!(
+            attrs
+                .iter()
+                .all(|(name, _)| name.starts_with("local") || name.starts_with("doc"))
+        );
+    }
+
+    #[test]
+    fn test_clock_pins_date_attributes() {
+        let clock = FixedClock(Timestamp::from_unix(0));
+        let attrs = ParseOptions::new()
+            .clock(clock)
+            .attribute("time-format", "%H:%M")
+            .initial_attributes()
+            .unwrap();
+
+        assert_eq!(attrs.get("docdate"), Some("1970-01-01"));
+        assert_eq!(attrs.get("localtime"), Some("00:00"));
+    }
+
+    #[test]
+    fn test_header_date_formats() {
+        let source = "= Notes\n:date-format: %d.%m.%Y\n:time-format: %H:%M\n\
+                      :stamp: {docdate} {doctime}\n\nText.\n";
+        let options = ParseOptions::new().clock(FixedClock(Timestamp::from_unix(1_709_209_805)));
+        let doc = crate::parse_document_with_options(source, &options).unwrap();
+
+        assert_eq!(doc.attributes.get("docdate"), Some("29.02.2024"));
+        assert_eq!(doc.attributes.get("localtime"), Some("12:30"));
+        assert_eq!(doc.attributes.get("stamp"), Some("29.02.2024 12:30"));
+
+        // Formats passed as options win over the header's
+        let options = options.attribute("date-format", "%Y");
+        let doc = crate::parse_document_with_options(source, &options).unwrap();
+        assert_eq!(doc.attributes.get("docdate"), Some("2024"));
+    }
+
+    #[test]
+    fn test_env_attribute_names() {
+        let entries = env_attribute_entries(
+            "DOCTORA_ATTR_",
+            vars(&[
+                ("DOCTORA_ATTR_VERSION", "1.2"),
+                ("DOCTORA_ATTR_RELEASE_DATE", "2025-01-01"),
+                ("HOME", "/root"),
+                ("DOCTORA_ATTR_", "empty name"),
+                ("DOCTORA_ATTR_BAD.NAME", "invalid"),
+            ])
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into())),
+        );
+
+        assert_eq!(
+            entries,
+            vars(&[("release-date", "2025-01-01"), ("version", "1.2")])
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_env_not_utf8() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let invalid = || OsString::from_vec(vec![b'a', 0xFF]);
+        let entries = env_attribute_entries(
+            "DOCTORA_ATTR_",
+            [
+                (invalid(), OsString::from("skipped")),
+                (OsString::from("DOCTORA_ATTR_RAW"), invalid()),
+                (OsString::from("OTHER"), invalid()),
+            ],
+        );
+        assert_eq!(entries, vars(&[("raw", "a\u{FFFD}")]));
+    }
+
+    #[test]
+    fn test_empty_env_prefix_is_error() {
+        let options = ParseOptions::new().env_attributes("");
+        assert_eq!(
+            options.initial_attributes(),
+            Err(AttributeError::EmptyEnvPrefix)
+        );
+        assert!(crate::parse_document_with_options("Text.", &options).is_err());
+    }
+
+    #[test]
+    fn test_explicit_attributes_win() {
+        let attrs = initial_attributes_from(
+            vars(&[("version", "env"), ("build", "42")]),
+            &vars(&[("version", "explicit")]),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(attrs.get("version"), Some("explicit"));
+        assert_eq!(attrs.get("build"), Some("42"));
+    }
+
+    #[test]
+    fn test_attributes_reference_each_other() {
+        let options = ParseOptions::new()
+            .attribute("url", "https://example.com/{version}")
+            .attribute("version", "v2");
+        let attrs = options.initial_attributes().unwrap();
+        assert_eq!(attrs.get("url"), Some("https://example.com/v2"));
+    }
+
+    #[test]
+    fn test_attribute_cycle_is_error() {
+        let options = ParseOptions::new()
+            .attribute("a", "{b}")
+            .attribute("b", "{a}");
+        assert!(options.initial_attributes().is_err());
+    }
+
+    #[test]
+    fn test_attribute_length_is_limited() {
+        use crate::ParseError;
+        use crate::limits::DEFAULT_MAX_ATTRIBUTE_LENGTH;
+
+        let mut source = ":a0: xxxx\n".to_string();
+        for level in 1..40 {
+            source += &format!(":a{level}: {{a{0}}}{{a{0}}}\n", level - 1);
+        }
+        let header = format!("= Title\n{source}\nText.\n");
+        let body = format!("= Title\n\n{source}\n{{a39}}\n");
+        let too_long = |name: &str, max| {
+            ParseError::Attributes(AttributeError::TooLong {
+                name: name.to_string(),
+                max,
+            })
+        };
+
+        let options = ParseOptions::new();
+        let err = crate::parse_document_with_options(&header, &options).unwrap_err();
+        assert_eq!(err, too_long("a19", DEFAULT_MAX_ATTRIBUTE_LENGTH));
+        let options = options.substitute_attributes(true);
+        let err = crate::parse_document_with_options(&body, &options).unwrap_err();
+        assert_eq!(err, too_long("a19", DEFAULT_MAX_ATTRIBUTE_LENGTH));
+
+        let options = options.limits(Limits::new().max_attribute_length(64));
+        let err = crate::parse_document_with_options(&header, &options).unwrap_err();
+        assert_eq!(err, too_long("a5", 64));
+        let options = ParseOptions::new()
+            .attribute("a", "xxxx")
+            .attribute("b", "{a}{a}")
+            .limits(Limits::new().max_attribute_length(4));
+        let err = crate::parse_document_with_options("Text.\n", &options).unwrap_err();
+        assert_eq!(err, too_long("b", 4));
+    }
+
+    #[test]
+    fn test_header_entries_do_not_override_options() {
+        let options = ParseOptions::new().attribute("version", "2.0");
+        let source = "= Notes\n:version: 1.0\n:release: v{version}\n\nText.\n";
+        let doc = crate::parse_document_with_options(source, &options).unwrap();
+
+        assert_eq!(doc.attributes.get("version"), Some("2.0"));
+        assert_eq!(doc.attributes.get("release"), Some("v2.0"));
+        assert_eq!(doc.attributes.get("doctitle"), Some("Notes"));
+        assert!(doc.attributes.contains("docdate"));
+    }
+
+    #[test]
+    fn test_substitute_attributes() {
+        let source = "= Notes\n\n{doctitle} for {version}, {docdate}.\n";
+        let options = ParseOptions::new()
+            .attribute("version", "2.0")
+            .clock(FixedClock(Timestamp::from_unix(0)));
+        let kept = crate::parse_document_with_options(source, &options).unwrap();
+        let substituted =
+            crate::parse_document_with_options(source, &options.substitute_attributes(true))
+                .unwrap();
+
+        let text = |doc: &crate::ast::Document| match &doc.blocks[0] {
+            crate::ast::Block::Paragraph { content, .. } => crate::ast::plain_text(content),
+            other => panic!("expected a paragraph, got {other:?}"),
+        };
+        assert_eq!(text(&kept), "{doctitle} for {version}, {docdate}.");
+        assert_eq!(text(&substituted), "Notes for 2.0, 1970-01-01.");
+    }
+
+    #[test]
+    fn test_generate_section_ids() {
+        let source = ":product: Engine\n\n== {product} Setup\n";
+        let options = ParseOptions::new().generate_section_ids(true);
+        let anchor = |options: &ParseOptions| {
+            let doc = crate::parse_document_with_options(source, options).unwrap();
+            doc.blocks[0].anchor().map(|anchor| anchor.id)
+        };
+        assert_eq!(anchor(&ParseOptions::new()), None);
+        assert_eq!(anchor(&options).as_deref(), Some("_product_setup"));
+        assert_eq!(
+            anchor(&options.substitute_attributes(true)).as_deref(),
+            Some("_engine_setup")
+        );
+    }
+
+    #[test]
+    fn test_section_id_attributes() {
+        let source = "= Guide\n:idprefix:\n:idseparator: -\n\n\
+                      == Quick Start\n\nSee <<quick-start>>.\n\n=== Quick Start\n";
+        let options = ParseOptions::new().generate_section_ids(true);
+        let doc = crate::parse_document_with_options(source, &options).unwrap();
+        let ids: Vec<String> = crate::xref::anchors(&doc)
+            .into_iter()
+            .map(|anchor| anchor.id)
+            .collect();
+        assert_eq!(ids, ["quick-start", "quick-start-2"]);
+        assert!(crate::xref::unresolved(&doc).is_empty());
+        let html = crate::render::html::render_html(&doc, &Default::default());
+        assert!(html.contains("<a href=\"#quick-start\">Quick Start</a>"));
+    }
+
+    #[test]
+    fn test_includes() {
+        use crate::include::IncludeOptions;
+        use std::collections::HashMap;
+        use std::path::PathBuf;
+
+        let files = HashMap::from([(
+            PathBuf::from("book/ch1.adoc"),
+            "= Chapter\n\nText.\n".to_string(),
+        )]);
+        let options = ParseOptions::new()
+            .attribute("chapters", ".")
+            .includes(IncludeOptions::new("book").resolver(files));
+        let source = "= Book\n\ninclude::{chapters}/ch1.adoc[leveloffset=+1]\n";
+        let doc = crate::parse_document_with_options(source, &options).unwrap();
+        assert!(
+            matches!(&doc.blocks[0], crate::ast::Block::Section { title, .. } if crate::ast::plain_text(title) == "Chapter")
+        );
+
+        let missing = ParseOptions::new().includes(IncludeOptions::new("book"));
+        assert!(crate::parse_document_with_options("include::none.adoc[]\n", &missing).is_err());
+    }
+
+    #[test]
+    fn test_normalize_unicode() {
+        use crate::unicode::{NormalizationForm, UnicodeNormalizer};
+
+        #[derive(Debug)]
+        struct Upper;
+
+        impl UnicodeNormalizer for Upper {
+            fn normalize(&self, text: &str, _: NormalizationForm) -> String {
+                text.to_uppercase()
+            }
+        }
+
+        let source = "Ça va.\n";
+        let options = ParseOptions::new().normalize_unicode(NormalizationForm::Nfc, Upper);
+        let doc = crate::parse_document_with_options(source, &options).unwrap();
+        assert_eq!(
+            doc.blocks[0],
+            crate::ast::Block::Paragraph {
+                content: vec![crate::ast::Inline::Text("ÇA VA.".to_string())],
+                metadata: crate::ast::BlockMetadata::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_break_hints() {
+        use crate::ast::{Block, BreakHint, Inline};
+
+        let source = "Mr.{nbsp}Smith\n";
+        let options = ParseOptions::new().break_hints(true);
+        let content =
+            |options: &ParseOptions| match crate::parse_document_with_options(source, options)
+                .unwrap()
+                .blocks
+                .remove(0)
+            {
+                Block::Paragraph { content, .. } => content,
+                other => panic!("expected a paragraph, got {other:?}"),
+            };
+        assert_eq!(content(&options).len(), 1);
+        assert_eq!(
+            content(&options.substitute_attributes(true)),
+            vec![
+                Inline::Text("Mr.".to_string()),
+                Inline::BreakHint(BreakHint::NoBreakSpace),
+                Inline::Text("Smith".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replacements() {
+        use crate::ast::{Block, Inline};
+
+        let source = ":owner: Ada (TM)\n\n(C) {owner}\n\n----\n(C)\n----\n";
+        let blocks = |options: &ParseOptions| {
+            crate::parse_document_with_options(source, options)
+                .unwrap()
+                .blocks
+        };
+        assert!(matches!(
+            &blocks(&ParseOptions::new())[0],
+            Block::Paragraph { content, .. } if content == &vec![Inline::Text("(C) {owner}".to_string())]
+        ));
+        let blocks = blocks(
+            &ParseOptions::new()
+                .substitute_attributes(true)
+                .replacements(true),
+        );
+        assert!(matches!(
+            &blocks[0],
+            Block::Paragraph { content, .. }
+                if content == &vec![Inline::Text("\u{a9} Ada \u{2122}".to_string())]
+        ));
+        assert!(matches!(&blocks[1], Block::Listing { content, .. } if content == "(C)"));
+    }
+
+    #[test]
+    fn test_keep_comments() {
+        use crate::ast::Block;
+
+        let source = "// note\nText\n\n////\nhidden\n////\n";
+        let doc = crate::parse_document_with_options(source, &ParseOptions::new()).unwrap();
+        assert_eq!(doc.blocks.len(), 1);
+
+        let options = ParseOptions::new().keep_comments(true);
+        let doc = crate::parse_document_with_options(source, &options).unwrap();
+        assert_eq!(
+            doc.blocks[0],
+            Block::Comment {
+                content: " note".to_string(),
+                delimited: false,
+                metadata: crate::ast::BlockMetadata::default()
+            }
+        );
+        assert_eq!(
+            doc.blocks[2],
+            Block::Comment {
+                content: "hidden".to_string(),
+                delimited: true,
+                metadata: crate::ast::BlockMetadata::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parser_backend() {
+        let options = ParseOptions::new();
+        assert_eq!(options.parser_backend(), ParserBackend::Winnow);
+        let source = "= Title\n\n== Section\n\nText.\n";
+        let options = options.parser(ParserBackend::Winnow);
+        assert_eq!(
+            crate::parse_document_with_options(source, &options).unwrap(),
+            crate::parse_document_with_options(source, &ParseOptions::new()).unwrap()
+        );
+    }
+}
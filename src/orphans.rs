@@ -0,0 +1,448 @@
+//! Detection of content that never reaches the output
+//!
+//! A book is a master document that pulls in its chapters with include
+//! directives. Content outside that tree is silently missing from the
+//! output, which is easy to miss in review. [`find_orphans`] walks the
+//! includes from the master document, following them the way
+//! [`expand_includes`](crate::include::expand_includes) does, and reports:
+//!
+//! | Kind                                | What is reported                                        |
+//! |-------------------------------------|---------------------------------------------------------|
+//! | [`OrphanKind::UnreachableFile`]     | Files that no include reaches from the master document  |
+//! | [`OrphanKind::UnterminatedComment`] | `////` comment blocks that run to the end of the output |
+//! | [`OrphanKind::DynamicInclude`]      | Include targets with undefined attribute references     |
+//!
+//! The walk works on the source text, so, like the expanded source, a
+//! comment block opened in an included file continues into the file that
+//! included it. Everything after an unterminated comment block is hidden,
+//! and its report lists the section titles that disappear with it. An
+//! include inside a comment block does not reach its target, since the
+//! included content would be commented out. Includes of files that are not
+//! among the inputs (code snippets, for example) are not followed.
+//!
+//! Attribute references in include targets resolve against the attributes
+//! passed in and the attribute entries seen so far. A target that still has
+//! a reference after that cannot be followed, so its file may be reported as
+//! unreachable too; the dynamic include finding says why.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::attributes::Attributes;
+//! use doctora::orphans::{OrphanKind, find_orphans};
+//!
+//! let files = [
+//!     ("book.adoc", "= Book\n\ninclude::chapters/one.adoc[]\n"),
+//!     ("chapters/one.adoc", "== One\n\n////\nTODO\n\n== Two\n"),
+//!     ("chapters/old.adoc", "== Old\n"),
+//! ];
+//! let report = find_orphans("book.adoc", files, &Attributes::new());
+//!
+//! let kinds: Vec<OrphanKind> = report.orphans.iter().map(|o| o.kind).collect();
+//! assert_eq!(
+//!     kinds,
+//!     vec![OrphanKind::UnterminatedComment, OrphanKind::UnreachableFile]
+//! );
+//! assert_eq!(report.orphans[0].line, Some(3));
+//! assert_eq!(report.orphans[1].file, "chapters/old.adoc");
+//! println!("{}", report);
+//! ```
+
+use crate::attributes::Attributes;
+use crate::audit::normalize;
+use crate::include::{DEFAULT_MAX_DEPTH, directive, is_verbatim_delimiter};
+use crate::parser_winnow::attribute_entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Category of an orphaned content finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrphanKind {
+    /// A file that no include reaches from the master document
+    UnreachableFile,
+    /// A comment block without a closing delimiter, which hides everything
+    /// after it
+    UnterminatedComment,
+    /// An include whose target has an undefined attribute reference
+    DynamicInclude,
+}
+
+impl OrphanKind {
+    /// Short identifier used in reports
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OrphanKind::UnreachableFile => "unreachable-file",
+            OrphanKind::UnterminatedComment => "unterminated-comment",
+            OrphanKind::DynamicInclude => "dynamic-include",
+        }
+    }
+}
+
+/// A single orphaned content finding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Orphan {
+    /// Category of the finding
+    pub kind: OrphanKind,
+    /// Name of the file, as passed to [`find_orphans`]
+    pub file: String,
+    /// 1-based line of the finding, `None` for a whole file
+    pub line: Option<usize>,
+    /// Explanation for the reader
+    pub detail: String,
+}
+
+/// All findings for one master document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanReport {
+    /// Name of the master document
+    pub master: String,
+    /// Findings in the order the walk met them, then the unreachable files
+    /// in input order
+    pub orphans: Vec<Orphan>,
+}
+
+impl OrphanReport {
+    /// Returns true if all content reaches the output
+    pub fn is_clean(&self) -> bool {
+        self.orphans.is_empty()
+    }
+}
+
+impl fmt::Display for OrphanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Orphaned content: {} ({} findings)",
+            self.master,
+            self.orphans.len()
+        )?;
+        for orphan in &self.orphans {
+            let location = match orphan.line {
+                Some(line) => format!("{}:{line}", orphan.file),
+                None => orphan.file.clone(),
+            };
+            writeln!(
+                f,
+                "  {location} {:<20} {}",
+                orphan.kind.as_str(),
+                orphan.detail
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Find the content of `files` that never reaches the output of `master`
+/// (see the module documentation)
+///
+/// `files` are `(name, source)` pairs, named by their path relative to a
+/// common root, like `master`. `attributes` are used to resolve references
+/// in include targets.
+pub fn find_orphans<I, N, S>(master: &str, files: I, attributes: &Attributes) -> OrphanReport
+where
+    I: IntoIterator<Item = (N, S)>,
+    N: Into<String>,
+    S: AsRef<str>,
+{
+    let files: Vec<(String, S)> = files
+        .into_iter()
+        .map(|(name, source)| (name.into(), source))
+        .collect();
+    let by_path: BTreeMap<PathBuf, (&str, &str)> = files
+        .iter()
+        .map(|(name, source)| (normalize(Path::new(name)), (name.as_str(), source.as_ref())))
+        .collect();
+
+    let mut walker = Walker {
+        files: &by_path,
+        attributes: attributes.clone(),
+        reached: BTreeSet::new(),
+        stack: Vec::new(),
+        fence: None,
+        orphans: Vec::new(),
+    };
+    walker.walk(&normalize(Path::new(master)));
+    if let Some(fence) = walker.fence.take().filter(|fence| fence.comment) {
+        let mut detail =
+            "comment block is never closed and hides the rest of the output".to_string();
+        if !fence.hidden.is_empty() {
+            let titles: Vec<String> = fence
+                .hidden
+                .iter()
+                .map(|title| format!("{title:?}"))
+                .collect();
+            detail.push_str(&format!(", including sections {}", titles.join(", ")));
+        }
+        walker.orphans.push(Orphan {
+            kind: OrphanKind::UnterminatedComment,
+            file: fence.file,
+            line: Some(fence.line),
+            detail,
+        });
+    }
+
+    let mut orphans = walker.orphans;
+    let reached = walker.reached;
+    orphans.extend(
+        files
+            .iter()
+            .filter(|(name, _)| !reached.contains(&normalize(Path::new(name))))
+            .map(|(name, _)| Orphan {
+                kind: OrphanKind::UnreachableFile,
+                file: name.clone(),
+                line: None,
+                detail: format!("not included from {master}"),
+            }),
+    );
+    OrphanReport {
+        master: master.to_string(),
+        orphans,
+    }
+}
+
+/// A verbatim block that is open at the current line of the walk
+struct Fence {
+    /// The opening delimiter line, which the closing one must match
+    delimiter: String,
+    /// True for a comment block (`////`)
+    comment: bool,
+    /// File and 1-based line where the block opened
+    file: String,
+    line: usize,
+    /// Section titles inside a comment block, which it hides
+    hidden: Vec<String>,
+}
+
+struct Walker<'a> {
+    /// Input files by normalized path: their names and sources
+    files: &'a BTreeMap<PathBuf, (&'a str, &'a str)>,
+    /// Attributes for include targets, updated by attribute entries
+    attributes: Attributes,
+    reached: BTreeSet<PathBuf>,
+    /// Files being walked, outermost first
+    stack: Vec<PathBuf>,
+    fence: Option<Fence>,
+    orphans: Vec<Orphan>,
+}
+
+impl Walker<'_> {
+    /// Walk the file at `path`, if it is one of the inputs, and the files it
+    /// includes
+    fn walk(&mut self, path: &Path) {
+        let Some(&(name, source)) = self.files.get(path) else {
+            return;
+        };
+        if self.stack.iter().any(|open| open == path) || self.stack.len() > DEFAULT_MAX_DEPTH {
+            return;
+        }
+        self.reached.insert(path.to_path_buf());
+        self.stack.push(path.to_path_buf());
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+
+        for (index, line) in source.lines().enumerate() {
+            let text = line.trim_end_matches('\r');
+            if let Some(fence) = &mut self.fence {
+                if text.trim_end() == fence.delimiter {
+                    self.fence = None;
+                } else if fence.comment {
+                    fence.hidden.extend(section_title(text).map(str::to_string));
+                } else if let Some((target, _)) = text.strip_prefix("include::").and_then(directive)
+                {
+                    // Included into a listing, so its lines are verbatim too
+                    self.include(target, &dir, name, index + 1);
+                }
+                continue;
+            }
+            if let Some((target, _)) = text.strip_prefix("include::").and_then(directive) {
+                self.include(target, &dir, name, index + 1);
+            } else if is_verbatim_delimiter(text.trim_end()) {
+                self.fence = Some(Fence {
+                    delimiter: text.trim_end().to_string(),
+                    comment: text.starts_with('/'),
+                    file: name.to_string(),
+                    line: index + 1,
+                    hidden: Vec::new(),
+                });
+            } else if let Some(entry) = attribute_entry(text) {
+                match entry.value {
+                    Some(value) => {
+                        // A value over the length limit leaves the attribute
+                        // undefined, so targets using it are not followed
+                        if self.attributes.define(entry.name.clone(), &value).is_err() {
+                            self.attributes.unset(&entry.name);
+                        }
+                    }
+                    None => {
+                        self.attributes.unset(&entry.name);
+                    }
+                }
+            }
+        }
+        self.stack.pop();
+    }
+
+    /// Follow the include of `target` on line `line` of the file `name` in
+    /// `dir`
+    fn include(&mut self, target: &str, dir: &Path, name: &str, line: usize) {
+        let target = self.attributes.interpolate(target);
+        if target.contains("://") {
+            return;
+        }
+        if target.contains('{') {
+            self.orphans.push(Orphan {
+                kind: OrphanKind::DynamicInclude,
+                file: name.to_string(),
+                line: Some(line),
+                detail: format!("include target {target} has an undefined attribute reference"),
+            });
+            return;
+        }
+        self.walk(&normalize(&dir.join(target)));
+    }
+}
+
+/// The title of a section title line (`== Title`), if `line` is one
+fn section_title(line: &str) -> Option<&str> {
+    let markers = line.len() - line.trim_start_matches('=').len();
+    let title = line[markers..].strip_prefix(' ')?.trim();
+    ((1..=6).contains(&markers) && !title.is_empty()).then_some(title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn orphans(files: &[(&str, &str)]) -> Vec<(OrphanKind, String, Option<usize>)> {
+        find_orphans("book.adoc", files.iter().copied(), &Attributes::new())
+            .orphans
+            .into_iter()
+            .map(|orphan| (orphan.kind, orphan.file, orphan.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_unreachable_files() {
+        let files = [
+            ("book.adoc", "= Book\n\ninclude::parts/a.adoc[]\n"),
+            (
+                "parts/a.adoc",
+                "include::../shared/b.adoc[leveloffset=+1]\n",
+            ),
+            ("shared/b.adoc", "== B\n"),
+            ("parts/c.adoc", "== C\n"),
+            ("parts/d.adoc", "include::c.adoc[]\n"),
+        ];
+        assert_eq!(
+            orphans(&files),
+            vec![
+                (
+                    OrphanKind::UnreachableFile,
+                    "parts/c.adoc".to_string(),
+                    None
+                ),
+                (
+                    OrphanKind::UnreachableFile,
+                    "parts/d.adoc".to_string(),
+                    None
+                ),
+            ]
+        );
+        // Every file is reachable once the master includes d
+        let mut files = files.to_vec();
+        files[0].1 = "include::parts/a.adoc[]\ninclude::parts/d.adoc[]\n";
+        assert!(orphans(&files).is_empty());
+    }
+
+    #[test]
+    fn test_includes_in_blocks_and_cycles() {
+        let files = [
+            (
+                "book.adoc",
+                "----\ninclude::code.adoc[]\n----\n\n////\ninclude::hidden.adoc[]\n////\n\n\
+                 \\include::escaped.adoc[]\ninclude::loop.adoc[]\n",
+            ),
+            ("code.adoc", "fn main() {}\n"),
+            ("hidden.adoc", "== Hidden\n"),
+            ("escaped.adoc", "== Escaped\n"),
+            ("loop.adoc", "include::book.adoc[]\n"),
+        ];
+        assert_eq!(
+            orphans(&files),
+            vec![
+                (OrphanKind::UnreachableFile, "hidden.adoc".to_string(), None),
+                (
+                    OrphanKind::UnreachableFile,
+                    "escaped.adoc".to_string(),
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_comment() {
+        let files = [
+            (
+                "book.adoc",
+                "= Book\n\ninclude::a.adoc[]\n\n== After\n\ninclude::b.adoc[]\n",
+            ),
+            ("a.adoc", "== A\n\n////\nDraft\n\n=== Draft section\n"),
+            ("b.adoc", "== B\n"),
+        ];
+        let report = find_orphans("book.adoc", files, &Attributes::new());
+        assert_eq!(
+            report.orphans,
+            vec![
+                Orphan {
+                    kind: OrphanKind::UnterminatedComment,
+                    file: "a.adoc".to_string(),
+                    line: Some(3),
+                    detail: "comment block is never closed and hides the rest of the output, \
+                             including sections \"Draft section\", \"After\""
+                        .to_string(),
+                },
+                Orphan {
+                    kind: OrphanKind::UnreachableFile,
+                    file: "b.adoc".to_string(),
+                    line: None,
+                    detail: "not included from book.adoc".to_string(),
+                },
+            ]
+        );
+        assert!(!report.is_clean());
+        assert!(report.to_string().contains("a.adoc:3 unterminated-comment"));
+
+        // A closed comment block, and an unclosed listing, hide nothing
+        let files = [("book.adoc", "////\n== Old\n////\n\n----\n== Code\n")];
+        assert!(orphans(&files).is_empty());
+    }
+
+    #[test]
+    fn test_dynamic_includes() {
+        let files = [
+            (
+                "book.adoc",
+                ":chapters: ch\n\ninclude::{chapters}/one.adoc[]\ninclude::{partials}/two.adoc[]\n",
+            ),
+            ("ch/one.adoc", "== One\n"),
+            ("partials/two.adoc", "== Two\n"),
+        ];
+        assert_eq!(
+            orphans(&files),
+            vec![
+                (OrphanKind::DynamicInclude, "book.adoc".to_string(), Some(4)),
+                (
+                    OrphanKind::UnreachableFile,
+                    "partials/two.adoc".to_string(),
+                    None
+                ),
+            ]
+        );
+
+        let mut attributes = Attributes::new();
+        attributes.set("partials", "partials");
+        let report = find_orphans("book.adoc", files, &attributes);
+        assert!(report.is_clean());
+    }
+}
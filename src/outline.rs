@@ -0,0 +1,236 @@
+//! Lists of figures, tables, and listings
+//!
+//! Formal deliverables (specifications, reports, theses) follow the table
+//! of contents with lists of their captioned blocks. [`Outline::new`]
+//! collects them from a document: every titled block image is a figure,
+//! every titled table a table, and every titled listing (literal blocks
+//! excluded) a listing. Each kind is numbered from 1 in document order,
+//! nested blocks included, and each entry keeps its caption and anchor.
+//! Untitled blocks are not numbered, as in Asciidoctor.
+//!
+//! A `toc::[figures]`, `toc::[tables]`, or `toc::[listings]` block macro
+//! marks where the list goes (see [`list_macro`]); the HTML renderer
+//! replaces it with the list, titled with the macro's block title, if any.
+//! Site shells render a list for a sidebar with
+//! [`render_caption_list`](crate::render::nav::render_caption_list).
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::ast::plain_text;
+//! use doctora::outline::{CaptionKind, Outline};
+//! use doctora::parse_document;
+//!
+//! let doc = parse_document(
+//!     ".Architecture\n[#arch]\nimage::arch.png[]\n\n\
+//!      .Limits\n|===\n|a\n|===\n\n\
+//!      .Deployment\nimage::deploy.png[]\n",
+//! )
+//! .unwrap();
+//! let outline = Outline::new(&doc);
+//!
+//! let figures = outline.entries(CaptionKind::Figure);
+//! assert_eq!(figures.len(), 2);
+//! assert_eq!(figures[0].label(), "Figure 1");
+//! assert_eq!(figures[0].anchor.as_deref(), Some("arch"));
+//! assert_eq!(plain_text(&figures[1].caption), "Deployment");
+//! assert_eq!(outline.entries(CaptionKind::Table)[0].label(), "Table 1");
+//! ```
+
+use crate::ast::visit::{Visitor, walk_block};
+use crate::ast::{Block, Document, Inline};
+use crate::attributes::parse_attribute_list;
+
+/// The kinds of captioned blocks that are listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CaptionKind {
+    /// A titled block image
+    Figure,
+    /// A titled table
+    Table,
+    /// A titled listing block
+    Listing,
+}
+
+impl CaptionKind {
+    /// All kinds, in the order their lists usually appear
+    pub const ALL: [CaptionKind; 3] = [Self::Figure, Self::Table, Self::Listing];
+
+    /// The kind of `block`, if it is a captioned figure, table, or listing
+    pub fn of(block: &Block) -> Option<Self> {
+        block.metadata().title.as_ref()?;
+        match block {
+            Block::Image { .. } => Some(Self::Figure),
+            Block::Table { .. } => Some(Self::Table),
+            Block::Listing { attributes, .. } if attributes.get("style") != Some("literal") => {
+                Some(Self::Listing)
+            }
+            _ => None,
+        }
+    }
+
+    /// The caption word: `Figure`, `Table`, or `Listing`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Figure => "Figure",
+            Self::Table => "Table",
+            Self::Listing => "Listing",
+        }
+    }
+
+    /// The `toc::[]` target naming the list: `figures`, `tables`, or
+    /// `listings`
+    pub fn list_name(self) -> &'static str {
+        match self {
+            Self::Figure => "figures",
+            Self::Table => "tables",
+            Self::Listing => "listings",
+        }
+    }
+}
+
+/// A numbered figure, table, or listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptionEntry {
+    /// What kind of block this is
+    pub kind: CaptionKind,
+    /// Number, from 1 in document order among blocks of the same kind
+    pub number: usize,
+    /// The block title
+    pub caption: Vec<Inline>,
+    /// ID of the block's anchor, if it has one
+    pub anchor: Option<String>,
+}
+
+impl CaptionEntry {
+    /// The label: `Figure 2`, `Table 1`, ...
+    pub fn label(&self) -> String {
+        format!("{} {}", self.kind.name(), self.number)
+    }
+}
+
+/// The figures, tables, and listings of a document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Outline {
+    figures: Vec<CaptionEntry>,
+    tables: Vec<CaptionEntry>,
+    listings: Vec<CaptionEntry>,
+}
+
+impl Outline {
+    /// Collect and number the captioned blocks of `doc`
+    pub fn new(doc: &Document) -> Self {
+        let mut outline = Self::default();
+        Collector(&mut outline).visit_document(doc);
+        outline
+    }
+
+    /// The entries of `kind`, in document order
+    pub fn entries(&self, kind: CaptionKind) -> &[CaptionEntry] {
+        match kind {
+            CaptionKind::Figure => &self.figures,
+            CaptionKind::Table => &self.tables,
+            CaptionKind::Listing => &self.listings,
+        }
+    }
+
+    /// Returns true if the document has no captioned blocks
+    pub fn is_empty(&self) -> bool {
+        CaptionKind::ALL
+            .iter()
+            .all(|kind| self.entries(*kind).is_empty())
+    }
+
+    fn entries_mut(&mut self, kind: CaptionKind) -> &mut Vec<CaptionEntry> {
+        match kind {
+            CaptionKind::Figure => &mut self.figures,
+            CaptionKind::Table => &mut self.tables,
+            CaptionKind::Listing => &mut self.listings,
+        }
+    }
+}
+
+/// The list a `toc::[figures]`-style block macro asks for
+///
+/// Returns `None` for other blocks, including the plain `toc::[]`.
+pub fn list_macro(block: &Block) -> Option<CaptionKind> {
+    let Block::Unknown { raw, .. } = block else {
+        return None;
+    };
+    let list = raw.trim().strip_prefix("toc::[")?.strip_suffix(']')?;
+    let attributes = parse_attribute_list(list);
+    let style = attributes.style()?;
+    CaptionKind::ALL
+        .into_iter()
+        .find(|kind| kind.list_name() == style)
+}
+
+/// Adds every captioned block to an [`Outline`]
+struct Collector<'a>(&'a mut Outline);
+
+impl Visitor for Collector<'_> {
+    fn visit_block(&mut self, block: &Block) {
+        if let (Some(kind), Some(caption)) = (CaptionKind::of(block), &block.metadata().title) {
+            let entries = self.0.entries_mut(kind);
+            entries.push(CaptionEntry {
+                kind,
+                number: entries.len() + 1,
+                caption: caption.clone(),
+                anchor: block.anchor().map(|anchor| anchor.id),
+            });
+        }
+        walk_block(self, block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::plain_text;
+    use crate::parse_document;
+
+    fn labels(outline: &Outline, kind: CaptionKind) -> Vec<String> {
+        outline
+            .entries(kind)
+            .iter()
+            .map(|entry| format!("{}. {}", entry.label(), plain_text(&entry.caption)))
+            .collect()
+    }
+
+    #[test]
+    fn test_numbering() {
+        let doc = parse_document(
+            ".First\n----\na\n----\n\n----\nuntitled\n----\n\n\
+             .Literal\n....\nb\n....\n\n== Part\n\n\
+             ====\n.Nested\n[source,rust]\n----\nc\n----\n====\n\n\
+             .Sizes\n|===\n|x\n|===\n",
+        )
+        .unwrap();
+        let outline = Outline::new(&doc);
+        assert_eq!(
+            labels(&outline, CaptionKind::Listing),
+            ["Listing 1. First", "Listing 2. Nested"]
+        );
+        assert_eq!(labels(&outline, CaptionKind::Table), ["Table 1. Sizes"]);
+        assert!(outline.entries(CaptionKind::Figure).is_empty());
+        assert!(Outline::new(&parse_document("Text.").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_list_macro() {
+        let doc = parse_document(
+            "toc::[figures]\n\ntoc::[]\n\ntoc::[tables,levels=1]\n\nvideo::v.mp4[]\n",
+        )
+        .unwrap();
+        let lists: Vec<_> = doc.blocks.iter().map(list_macro).collect();
+        assert_eq!(
+            lists,
+            [
+                Some(CaptionKind::Figure),
+                None,
+                Some(CaptionKind::Table),
+                None
+            ]
+        );
+    }
+}
@@ -10,10 +10,25 @@
 //!
 //! The parser is structured as a hierarchy of combinator functions:
 //! - `parse_document_winnow()` - Public API, entry point
-//! - `block()` - Parses sections or paragraphs (choice combinator)
+//! - `document_header()` - Parses the title, author, and revision lines and
+//!   attribute entries at the top of the document
+//! - `block()` - Parses sections, lists, or paragraphs (choice combinator)
 //! - `section()` - Parses heading + nested content (sequence combinator)
+//! - `list()` - Parses unordered/ordered lists, nesting by marker
+//! - `description_list()` - Parses `term:: description` entries
+//! - `listing()` - Parses verbatim listing and literal blocks
+//! - `table()` - Parses `|===` tables into rows of cells
+//! - `admonition()` - Parses `NOTE: text` paragraphs and `[NOTE]` blocks
+//! - `quote()` - Parses quote and verse blocks and paragraphs
+//! - `compound_block()` - Parses example and sidebar blocks
+//! - `open_block()` - Parses `--` open blocks with their attributes
+//! - `image()` - Parses `image::target[...]` block images
+//! - `unknown()` - Keeps other block macros and unmodeled delimited blocks
+//!   as written
+//! - `body_attribute_entry()` - Parses `:name: value` lines after the header
 //! - `paragraph()` - Parses inline formatted text
-//! - `inline()` - Parses text, bold, italic (recursive combinator)
+//! - `inlines()` - Parses one line of text, bold, and italic (formatting
+//!   marks are paired by [`crate::formatting`])
 //!
 //! # Winnow Patterns Used
 //!
@@ -21,7 +36,9 @@
 //! ```ignore
 //! fn parser(input: &mut Input<'_>) -> winnow::Result<Output>
 //! ```
-//! - **Input**: Mutable reference to token slice (`&mut &[Token]`)
+//! - **Input**: Mutable reference to a stateful slice of `(Token, span)` pairs;
+//!   the state carries the source text so parsers can slice real content
+//!   (`Inline::Text`, section titles) out of it
 //! - **Output**: `winnow::Result<T>` = `Result<T, ErrMode<ContextError>>`
 //! - **Mutable**: Parser consumes tokens by advancing the slice
 //!
@@ -76,8 +93,9 @@
 //! - **Cut**: Commit to current parser branch
 //! - **Incomplete**: Need more input (streaming)
 //!
-//! Current implementation maps all errors to `String` for simplicity.
-//! Future: Implement custom error recovery with precise locations.
+//! A failed parse is reported as a [`ParseError`] pointing at the token the
+//! parser stopped at. Future: collect several errors and recover (see
+//! [`crate::error_recovery`]).
 //!
 //! ## 5. Type Annotations
 //!
@@ -98,16 +116,14 @@
 //!
 //! ```
 //! use doctora::parser_winnow::parse_document_winnow;
-//! use doctora::token::Token;
-//! use logos::Logos;
+//! use doctora::token::lex_with_spans;
 //!
 //! let input = "= Hello\n\nParagraph **bold** text.";
-//! let tokens: Vec<Token> = Token::lexer(input)
-//!     .filter_map(Result::ok)
-//!     .collect();
+//! let tokens = lex_with_spans(input);
 //!
-//! let doc = parse_document_winnow(&tokens).expect("parse failed");
-//! assert_eq!(doc.blocks.len(), 1);  // One section
+//! let doc = parse_document_winnow(input, &tokens).expect("parse failed");
+//! assert_eq!(doc.header.unwrap().title.as_deref(), Some("Hello"));
+//! assert_eq!(doc.blocks.len(), 1);  // One paragraph
 //! ```
 //!
 //! # References
@@ -116,66 +132,772 @@
 //! - **Combinator Guide**: <https://github.com/winnow-rs/winnow/blob/main/examples/>
 //! - **Benchmark Results**: `docs/BENCHMARK_RESULTS.md`
 
-use crate::ast::{Block, Document, Inline};
-use crate::token::Token;
-use winnow::combinator::{alt, delimited, opt, repeat, terminated};
+use crate::ast::{
+    AdmonitionKind, Anchor, AttributeEntry, Author, Block, BlockMetadata, ColumnSpec,
+    DescriptionItem, Document, DocumentHeader, Inline, ListItem, Revision, TableCell, TableRow,
+};
+use crate::attributes::{Attributes, is_name_char, parse_attribute_list};
+use crate::error_recovery::{ErrorRecovery, ParseError};
+use crate::formatting::{format_inlines, format_inlines_with, image_attributes, parse_anchor};
+use crate::limits::{Limits, StructureKind, Truncation};
+use crate::resolve::substitutions;
+use crate::subs::{SubstitutionDefaults, Substitutions};
+use crate::token::{Span, SpannedToken, Token, TokenStream, parse_cell_spec};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use winnow::combinator::{alt, not, opt, preceded, repeat};
+use winnow::error::{ContextError, StrContext};
 use winnow::prelude::*;
-use winnow::token::any;
+use winnow::stream::{Stateful, Stream};
+use winnow::token::{any, take_while};
+
+type Spanned = SpannedToken;
+
+/// Source text and parse settings, shared by all parsers
+///
+/// Tokens only carry spans; parsers slice `text` to recover the original
+/// content, including the whitespace between tokens.
+#[derive(Debug, Clone, Copy)]
+struct Source<'a> {
+    text: &'a str,
+    /// Size limits to enforce while parsing
+    limits: Limits,
+    /// Structures cut down by `limits`, moved into the document at the end
+    truncations: &'a RefCell<Vec<Truncation>>,
+    /// Number of delimited blocks and lists enclosing the current position
+    depth: usize,
+    /// The delimiter that closes the innermost enclosing delimited block
+    closing: Option<&'a str>,
+    /// Default substitutions by block kind
+    defaults: &'a SubstitutionDefaults,
+    /// Substitutions of the block being parsed, which decide the inline
+    /// formatting of its content
+    subs: Substitutions,
+    /// Substitutions of the blocks parsed so far, by the offset they start
+    /// at, so parsing a block again does not parse its nested blocks twice
+    block_subs: &'a RefCell<HashMap<usize, Substitutions>>,
+    /// Set when nesting goes past `limits`; parsing then stops
+    too_deep: &'a RefCell<Option<ParseError>>,
+}
 
 /// Input type for Winnow parser
-type Input<'a> = &'a [Token];
+type Input<'a> = Stateful<&'a [Spanned], Source<'a>>;
 
 /// Parse a complete AsciiDoc document
 ///
 /// Entry point for parsing. Parses a sequence of blocks and returns a Document.
+/// `tokens` must come from [`lex_with_spans`](crate::token::lex_with_spans)
+/// on `source`, since text content is sliced from `source`.
 ///
 /// # Examples
 ///
 /// ```
 /// use doctora::parser_winnow::parse_document_winnow;
-/// use doctora::token::Token;
+/// use doctora::token::lex_with_spans;
 ///
-/// let tokens = vec![
-///     Token::Heading1,
-///     Token::Word,
-///     Token::BlankLine,
-///     Token::Word,
-/// ];
+/// let source = "= Title\n\nHello world";
+/// let tokens = lex_with_spans(source);
 ///
-/// let result = parse_document_winnow(&tokens);
+/// let result = parse_document_winnow(source, &tokens);
 /// assert!(result.is_ok());
 /// ```
-pub fn parse_document_winnow(input: &[Token]) -> Result<Document, String> {
-    let mut parser = terminated(repeat(0.., block), winnow::combinator::eof);
+pub fn parse_document_winnow(
+    source: &str,
+    tokens: &[(Token, Span)],
+) -> Result<Document, ParseError> {
+    parse_document_winnow_with_limits(source, tokens, &Limits::new())
+}
+
+/// Parse a complete AsciiDoc document from a lazy [`TokenStream`]
+///
+/// Lexing streams, but the parser backtracks over the tokens of a block, so
+/// the stream is read into one token buffer before parsing. That buffer is
+/// the only full-size intermediate: the lexer itself holds one line at a
+/// time.
+///
+/// # Examples
+///
+/// ```
+/// use doctora::parser_winnow::parse_token_stream;
+/// use doctora::token::TokenStream;
+///
+/// let doc = parse_token_stream(TokenStream::new("= Title\n\nHello world")).unwrap();
+/// assert_eq!(doc.blocks.len(), 1);
+/// ```
+pub fn parse_token_stream(stream: TokenStream<'_>) -> Result<Document, ParseError> {
+    let source = stream.source();
+    let tokens: Vec<_> = stream.collect();
+    parse_document_winnow(source, &tokens)
+}
+
+/// Parse a complete AsciiDoc document, enforcing size `limits`
+///
+/// Like [`parse_document_winnow`], but lists longer than the limit are cut
+/// down and recorded in [`Document::truncations`].
+///
+/// # Examples
+///
+/// ```
+/// use doctora::limits::Limits;
+/// use doctora::parser_winnow::parse_document_winnow_with_limits;
+/// use doctora::token::lex_with_spans;
+///
+/// let source = "* a\n* b\n* c";
+/// let tokens = lex_with_spans(source);
+///
+/// let limits = Limits::new().max_list_items(2);
+/// let doc = parse_document_winnow_with_limits(source, &tokens, &limits).unwrap();
+/// assert_eq!(doc.truncations[0].dropped, 1);
+/// ```
+pub fn parse_document_winnow_with_limits(
+    source: &str,
+    tokens: &[(Token, Span)],
+    limits: &Limits,
+) -> Result<Document, ParseError> {
+    parse_tokens(source, tokens, limits, &SubstitutionDefaults::new(), false)
+}
+
+/// Parse a document, keeping line and block comments as [`Block::Comment`]
+/// if `keep_comments` is set
+///
+/// Comments are always parsed, so they end paragraphs and lists the same
+/// way whether they are kept or not. The `quotes` and `macros`
+/// substitutions of each block, from `defaults` and its `subs` attribute,
+/// decide whether its text is formatted.
+pub(crate) fn parse_tokens(
+    source: &str,
+    tokens: &[(Token, Span)],
+    limits: &Limits,
+    defaults: &SubstitutionDefaults,
+    keep_comments: bool,
+) -> Result<Document, ParseError> {
+    let mut recovery = ErrorRecovery::new();
+    let doc = parse_tokens_with(
+        source,
+        tokens,
+        limits,
+        defaults,
+        keep_comments,
+        &mut recovery,
+    );
+    match recovery.into_errors().into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(doc),
+    }
+}
+
+/// Parse a document, recording syntax errors in `recovery`
+///
+/// After an error, a collecting `recovery` skips to the next block boundary
+/// (see [`synchronize`]) and parses on, so the document holds every block
+/// outside the skipped stretches. A fail-fast one stops at the first error.
+pub(crate) fn parse_tokens_with(
+    source: &str,
+    tokens: &[(Token, Span)],
+    limits: &Limits,
+    defaults: &SubstitutionDefaults,
+    keep_comments: bool,
+    recovery: &mut ErrorRecovery,
+) -> Document {
+    let truncations = RefCell::new(Vec::new());
+    let block_subs = RefCell::new(HashMap::new());
+    let too_deep = RefCell::new(None);
+    let mut input = Input {
+        input: tokens,
+        state: Source {
+            text: source,
+            limits: *limits,
+            truncations: &truncations,
+            depth: 0,
+            closing: None,
+            defaults,
+            subs: Substitutions::NORMAL,
+            block_subs: &block_subs,
+            too_deep: &too_deep,
+        },
+    };
+    let offset = |input: &Input<'_>| tokens.len() - input.input.len();
+
+    let start = input.checkpoint();
+    let header = opt(document_header)
+        .parse_next(&mut input)
+        .unwrap_or_else(|err| {
+            recovery.record_error(syntax_error(tokens, offset(&input), err));
+            input.reset(&start);
+            None
+        });
+    let mut blocks = Vec::new();
+    loop {
+        // `repeat` stops at the first token no block starts with
+        let parsed: winnow::Result<Vec<Block>> = repeat(0.., block).parse_next(&mut input);
+        let err = match parsed {
+            Ok(parsed) => {
+                blocks.extend(parsed);
+                ContextError::new()
+            }
+            Err(err) => err,
+        };
+        if let Some(err) = too_deep.take() {
+            // Synchronizing would only find more of the same nesting
+            recovery.record_error(err);
+            break;
+        }
+        if input.input.is_empty() {
+            break;
+        }
+        recovery.record_error(syntax_error(tokens, offset(&input), err));
+        if recovery.is_fail_fast() {
+            break;
+        }
+        synchronize(&mut input);
+    }
+    if !keep_comments {
+        drop_comments(&mut blocks);
+    }
+    let mut doc = Document::with_blocks(blocks);
+    if let Some(header) = &header
+        && let Err(err) =
+            header.define_attributes(&mut doc.attributes, |_| false, limits.max_attribute_length)
+    {
+        recovery.record_error(err.into());
+    }
+    doc.header = header;
+    doc.truncations = truncations.into_inner();
+    doc
+}
+
+/// The error for a parse that stopped at token `offset`
+fn syntax_error(tokens: &[Spanned], offset: usize, err: ContextError) -> ParseError {
+    let expected: Vec<String> = err
+        .context()
+        .filter_map(|context| match context {
+            StrContext::Expected(value) => Some(value.to_string()),
+            _ => None,
+        })
+        .collect();
+    let expected = if expected.is_empty() {
+        "a block".to_string()
+    } else {
+        expected.join(" or ")
+    };
+    match tokens.get(offset) {
+        Some((token, span)) => ParseError::UnexpectedToken {
+            span: span.clone(),
+            expected,
+            got: token.description().to_string(),
+        },
+        None => ParseError::UnexpectedEof { expected },
+    }
+}
+
+/// Skip past the token a parse failed at, to the next block boundary: just
+/// after a blank line, or at a heading
+fn synchronize(input: &mut Input<'_>) {
+    while let Some(((token, _), rest)) = input.input.split_first() {
+        input.input = rest;
+        match token {
+            Token::BlankLine => return,
+            Token::Newline if rest.first().is_some_and(|(next, _)| heading(next)) => return,
+            _ => {}
+        }
+    }
+}
+
+/// Returns true for the heading markers `=` to `======`
+fn heading(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Heading1
+            | Token::Heading2
+            | Token::Heading3
+            | Token::Heading4
+            | Token::Heading5
+            | Token::Heading6
+    )
+}
+
+/// Remove comment blocks at any nesting depth
+fn drop_comments(blocks: &mut Vec<Block>) {
+    blocks.retain(|block| !matches!(block, Block::Comment { .. }));
+    for block in blocks {
+        match block {
+            Block::Section { content, .. }
+            | Block::Quote { content, .. }
+            | Block::Admonition { content, .. }
+            | Block::Example { content, .. }
+            | Block::Sidebar { content, .. }
+            | Block::Open { content, .. } => drop_comments(content),
+            Block::UnorderedList { items, .. } | Block::OrderedList { items, .. } => {
+                for item in items {
+                    drop_comments(&mut item.blocks);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parse the document header (see [`DocumentHeader`])
+///
+/// The title line must end with a line break. The line after the title is
+/// the author line and the one after that the revision line, if they start
+/// with a word. The header ends at the first blank line, or at the
+/// first line after the title that is not part of it; a document with
+/// neither a title nor leading attribute entries has no header. Line
+/// comments before the title and between attribute entries are skipped, and
+/// are not kept even with [`ParseOptions::keep_comments`](crate::options::ParseOptions::keep_comments).
+fn document_header(input: &mut Input<'_>) -> winnow::Result<DocumentHeader> {
+    let mut header = DocumentHeader::default();
+    let mut ended = false;
+    skip_line_comments(input)?;
+    if let Some((Token::Heading1, _)) = input.input.first() {
+        let _ = any.parse_next(input)?;
+        let title = take_while(1.., |(t, _): Spanned| {
+            !matches!(t, Token::Newline | Token::BlankLine)
+        })
+        .parse_next(input)?;
+        header.title = Some(source_between(input, title).trim().to_string());
+        let (end, _) = any.parse_next(input)?;
+        ended = end == Token::BlankLine;
+
+        for line_number in 0..2 {
+            // Author and revision lines start with text, unlike attribute
+            // entries and block syntax such as `== Section`
+            if ended || !matches!(input.input.first(), Some((Token::Word, _))) {
+                break;
+            }
+            let Some((line, end)) = header_line(input)? else {
+                break;
+            };
+            if line_number == 0 {
+                header.authors = authors(line);
+            } else {
+                header.revision = Some(revision(line));
+            }
+            ended = end;
+        }
+    }
+    while !ended {
+        if let Some((Token::LineComment, _)) = input.input.first() {
+            let _ = any.parse_next(input)?;
+            let end: Option<Token> = opt(token(Token::Newline)).parse_next(input)?;
+            ended = end.is_none();
+            continue;
+        }
+        let start = input.checkpoint();
+        match header_line(input)? {
+            Some((line, end)) if let Some(entry) = attribute_entry(line) => {
+                header.attributes.push(entry);
+                ended = end;
+            }
+            _ => {
+                input.reset(&start);
+                break;
+            }
+        }
+    }
+    if header.title.is_none() && header.attributes.is_empty() {
+        return Err(ContextError::new());
+    }
+    skip_line_breaks(input)?;
+    Ok(header)
+}
+
+/// Skip line comments and the line breaks after them
+fn skip_line_comments(input: &mut Input<'_>) -> winnow::Result<()> {
+    while let Some((Token::LineComment, _)) = input.input.first() {
+        let _ = any.parse_next(input)?;
+        skip_line_breaks(input)?;
+    }
+    Ok(())
+}
+
+/// Take the next non-empty line and its line break
+///
+/// Returns the line's text and whether it ends the header (a blank line or
+/// the end of the document follows), or `None` at the end of the document.
+fn header_line<'a>(input: &mut Input<'a>) -> winnow::Result<Option<(&'a str, bool)>> {
+    let tokens = take_while(0.., |(t, _): Spanned| {
+        !matches!(t, Token::Newline | Token::BlankLine)
+    })
+    .parse_next(input)?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+    let line = source_between(input, tokens);
+    let end: Option<Token> = opt(token(Token::Newline)).parse_next(input)?;
+    Ok(Some((line, end.is_none())))
+}
+
+/// Recognize an attribute entry line (`:name: value`, `:name!:`, `:!name:`)
+pub(crate) fn attribute_entry(line: &str) -> Option<AttributeEntry> {
+    let (name, value) = line.strip_prefix(':')?.split_once(':')?;
+    if !value.is_empty() && !value.starts_with([' ', '\t']) {
+        return None;
+    }
+    let (name, unset) = match (name.strip_prefix('!'), name.strip_suffix('!')) {
+        (Some(name), _) | (_, Some(name)) => (name, true),
+        _ => (name, false),
+    };
+    if name.is_empty() || !name.chars().all(is_name_char) {
+        return None;
+    }
+    Some(AttributeEntry {
+        name: name.to_string(),
+        value: (!unset).then(|| value.trim().to_string()),
+    })
+}
 
-    parser
-        .parse(input)
-        .map(Document::with_blocks)
-        .map_err(|err| format!("Parse error: {:?}", err))
+/// Parse an attribute entry line after the header, kept as
+/// [`Block::AttributeEntry`]
+fn body_attribute_entry(input: &mut Input<'_>) -> winnow::Result<Block> {
+    let line = take_while(1.., |(t, _): Spanned| {
+        !matches!(t, Token::Newline | Token::BlankLine)
+    })
+    .parse_next(input)?;
+    if !starts_line(input, line) {
+        return Err(ContextError::new());
+    }
+    let entry = attribute_entry(source_between(input, line)).ok_or_else(ContextError::new)?;
+    skip_line_breaks(input)?;
+    Ok(Block::AttributeEntry {
+        entry,
+        metadata: BlockMetadata::default(),
+    })
+}
+
+/// Parse an author line: `;`-separated names, each with an optional
+/// `<email>`
+fn authors(line: &str) -> Vec<Author> {
+    line.split(';')
+        .map(str::trim)
+        .filter(|author| !author.is_empty())
+        .map(|author| match author.split_once('<') {
+            Some((name, email)) => Author {
+                name: name.trim().to_string(),
+                email: Some(email.trim_end_matches('>').trim().to_string()),
+            },
+            None => Author {
+                name: author.to_string(),
+                email: None,
+            },
+        })
+        .collect()
+}
+
+/// Parse a revision line: `number, date: remark`, where every part is
+/// optional
+///
+/// Without a comma, the line is the revision number if it starts with `v`
+/// and a digit, and the date otherwise.
+fn revision(line: &str) -> Revision {
+    let (main, remark) = match line.split_once(':') {
+        Some((main, remark)) => (main, Some(remark)),
+        None => (line, None),
+    };
+    let is_number = |text: &str| {
+        let mut chars = text.chars();
+        chars.next() == Some('v') && chars.next().is_some_and(|c| c.is_ascii_digit())
+    };
+    let (number, date) = match main.split_once(',') {
+        Some((number, date)) => (Some(number), Some(date)),
+        None if is_number(main.trim()) => (Some(main), None),
+        None => (None, Some(main)),
+    };
+    let non_empty = |text: &str| (!text.is_empty()).then(|| text.to_string());
+    Revision {
+        number: number
+            .and_then(|n| non_empty(n.trim().trim_start_matches(|c: char| !c.is_ascii_digit()))),
+        date: date.and_then(|d| non_empty(d.trim())),
+        remark: remark.and_then(|r| non_empty(r.trim())),
+    }
 }
 
-/// Parse a block-level element (section or paragraph)
+/// Parse a block-level element, formatting its text with its own
+/// substitutions (see [`parse_tokens`])
+///
+/// The substitutions come from the block's `subs` attribute, read ahead of
+/// the block, relative to the normal ones. Only once the block is parsed is
+/// its kind known; if the defaults of that kind make a difference, the block
+/// is parsed again. The substitutions found are remembered, so nested blocks
+/// get theirs right the second time and every block is parsed at most twice.
 fn block(input: &mut Input<'_>) -> winnow::Result<Block> {
-    // Try to parse a section first, then fall back to paragraph
-    alt((section, paragraph)).parse_next(input)
+    let start = input.checkpoint();
+    let offset = input
+        .input
+        .first()
+        .map_or(input.state.text.len(), |(_, span)| span.start);
+    let truncated = input.state.truncations.borrow().len();
+    let known = input.state.block_subs.borrow().get(&offset).copied();
+    let guess = known.unwrap_or_else(|| declared_subs(input));
+    let outer = std::mem::replace(&mut input.state.subs, guess);
+    let mut parsed = titled_block(input);
+    if let Ok(block) = &parsed
+        && formats_text(block)
+    {
+        let subs = substitutions(block, input.state.defaults);
+        input.state.block_subs.borrow_mut().insert(offset, subs);
+        let formatting = Substitutions::QUOTES | Substitutions::MACROS;
+        if subs & formatting != guess & formatting {
+            input.reset(&start);
+            input.state.truncations.borrow_mut().truncate(truncated);
+            input.state.subs = subs;
+            parsed = titled_block(input);
+        }
+    }
+    input.state.subs = outer;
+    parsed
+}
+
+/// The substitutions the `subs` attribute of the block ahead asks for,
+/// relative to the normal ones, without consuming any input
+fn declared_subs(input: &mut Input<'_>) -> Substitutions {
+    let start = input.checkpoint();
+    let attributes = preceded(
+        (opt(block_title), repeat::<_, _, (), _, _>(0.., anchor_line)),
+        attribute_line,
+    )
+    .parse_next(input)
+    .ok();
+    input.reset(&start);
+    attributes
+        .as_ref()
+        .and_then(|attributes| attributes.get("subs"))
+        .and_then(|spec| Substitutions::parse(spec, Substitutions::NORMAL).ok())
+        .unwrap_or(Substitutions::NORMAL)
+}
+
+/// Whether the parser formats text of `block`'s own, outside its title and
+/// nested blocks
+fn formats_text(block: &Block) -> bool {
+    matches!(
+        block,
+        Block::Paragraph { .. }
+            | Block::UnorderedList { .. }
+            | Block::OrderedList { .. }
+            | Block::DescriptionList { .. }
+            | Block::Table { .. }
+            | Block::Quote { .. }
+            | Block::Verse { .. }
+            | Block::Admonition { .. }
+    )
+}
+
+/// Parse a block-level element, with an optional `.Title` line
+///
+/// The title goes into the block's [`BlockMetadata::title`]. A title line
+/// that no block follows is a paragraph. Once nesting has gone too deep,
+/// no block parses, so the parse unwinds to the top.
+fn titled_block(input: &mut Input<'_>) -> winnow::Result<Block> {
+    if input.state.too_deep.borrow().is_some() {
+        return Err(ContextError::new());
+    }
+    let start = input.checkpoint();
+    let Some(title) = opt(block_title).parse_next(input)? else {
+        return anchored_block(input);
+    };
+    match opt(anchored_block).parse_next(input)? {
+        Some(mut block) => {
+            block.metadata_mut().title = Some(title);
+            Ok(block)
+        }
+        None => {
+            input.reset(&start);
+            paragraph(input)
+        }
+    }
+}
+
+/// Parse a block title line: a `.` directly followed by the title text
+///
+/// `. item` (an ordered list item) and `..`, `....` lines are not titles.
+fn block_title(input: &mut Input<'_>) -> winnow::Result<Vec<Inline>> {
+    let text = input.state.text;
+    let line = take_while(2.., |(t, _): Spanned| {
+        !matches!(t, Token::Newline | Token::BlankLine)
+    })
+    .parse_next(input)?;
+    token(Token::Newline).parse_next(input)?;
+    let (dot, next) = (&line[0], &line[1]);
+    let titled = starts_line(input, line)
+        && dot.0 == Token::Punctuation
+        && &text[dot.1.clone()] == "."
+        && next.1.start == dot.1.end
+        && !text[next.1.clone()].starts_with('.');
+    if !titled {
+        return Err(ContextError::new());
+    }
+    Ok(format_inlines(text, &line[1..]))
+}
+
+/// Parse a block-level element, with optional `[[id]]` anchor lines
+///
+/// The anchor names a section, a listing, table, open block or image (as
+/// its `id` and `reftext` attributes), or any other block (as its
+/// [`BlockMetadata::id`] and `reftext`), unless the block sets its own id.
+/// Of several anchor lines in a row, the last one wins. An anchor line that
+/// no block follows, or that precedes a comment, is a paragraph of its own.
+fn anchored_block(input: &mut Input<'_>) -> winnow::Result<Block> {
+    let checkpoint = input.checkpoint();
+    let anchors: Vec<Anchor> = repeat(0.., anchor_line).parse_next(input)?;
+    let Some(anchor) = anchors.into_iter().last() else {
+        return attributed_block(input);
+    };
+    let mut block = match opt(attributed_block).parse_next(input)? {
+        Some(block) if !matches!(block, Block::Comment { .. }) => block,
+        _ => {
+            input.reset(&checkpoint);
+            return paragraph(input);
+        }
+    };
+    match &mut block {
+        Block::Section { anchor: own, .. } => {
+            own.get_or_insert(anchor);
+        }
+        Block::Listing { attributes, .. }
+        | Block::Table { attributes, .. }
+        | Block::Open { attributes, .. }
+        | Block::Image { attributes, .. } => {
+            *attributes = anchored(std::mem::take(attributes), anchor);
+        }
+        _ => {
+            let metadata = block.metadata_mut();
+            if metadata.id.is_none() {
+                metadata.id = Some(anchor.id);
+                if let Some(reftext) = anchor.reftext {
+                    metadata.named.insert("reftext".to_string(), reftext);
+                }
+            }
+        }
+    }
+    Ok(block)
+}
+
+/// Parse a block with an optional attribute list line, kept as its
+/// [`BlockMetadata`]
+///
+/// Sections, listings, tables, quotes, delimited blocks and images read the
+/// attribute line themselves, since it can decide what kind of block follows
+/// (`[verse]`, `[source]`). Before any other block the line is skipped here.
+/// A line that is followed by no block at all stays a paragraph, whatever
+/// its style.
+fn attributed_block(input: &mut Input<'_>) -> winnow::Result<Block> {
+    let start = input.checkpoint();
+    let Some(attributes) = opt(attribute_line).parse_next(input)? else {
+        return unanchored_block(input);
+    };
+    input.reset(&start);
+    let styled = opt(alt((
+        section,
+        listing,
+        passthrough,
+        table,
+        admonition,
+        quote,
+        compound_block,
+        open_block,
+        image,
+    )))
+    .parse_next(input)?;
+    let mut block = match styled {
+        Some(block) => block,
+        None => match opt(preceded(attribute_line, unanchored_block)).parse_next(input)? {
+            Some(block) => block,
+            None => return unanchored_block(input),
+        },
+    };
+    *block.metadata_mut() = BlockMetadata::from(&attributes);
+    Ok(block)
+}
+
+/// Parse a line holding only a block anchor (`[[id]]` or `[[id,reftext]]`)
+fn anchor_line(input: &mut Input<'_>) -> winnow::Result<Anchor> {
+    let line: Vec<Spanned> = repeat(
+        4..,
+        any.verify(|(t, _): &Spanned| !matches!(t, Token::Newline | Token::BlankLine)),
+    )
+    .parse_next(input)?;
+    token(Token::Newline).parse_next(input)?;
+    if !starts_line(input, &line) {
+        return Err(ContextError::new());
+    }
+
+    let text = source_between(input, &line);
+    text.strip_prefix("[[")
+        .and_then(|t| t.strip_suffix("]]"))
+        .and_then(parse_anchor)
+        .ok_or_else(ContextError::new)
+}
+
+/// Give `attributes` the `id` and `reftext` of `anchor`, unless the block's
+/// own attribute list sets an id
+fn anchored(mut attributes: Attributes, anchor: Anchor) -> Attributes {
+    if attributes.id().is_none() {
+        attributes.set("id", anchor.id);
+        if let Some(reftext) = anchor.reftext {
+            attributes.set("reftext", reftext);
+        }
+    }
+    attributes
+}
+
+/// Parse a block-level element without an anchor line
+fn unanchored_block(input: &mut Input<'_>) -> winnow::Result<Block> {
+    // Try to parse a section first, then lists, then fall back to paragraph
+    alt((
+        comment,
+        body_attribute_entry,
+        section,
+        listing,
+        passthrough,
+        table,
+        admonition,
+        quote,
+        compound_block,
+        open_block,
+        image,
+        unknown,
+        list,
+        description_list,
+        paragraph,
+    ))
+    .parse_next(input)
 }
 
 /// Parse a section (heading with optional nested content)
+///
+/// An attribute list line before the heading may give the section an id
+/// (`[#id]` or `[id=...]`) and a `reftext`.
 fn section(input: &mut Input<'_>) -> winnow::Result<Block> {
+    let attributes = opt(attribute_line).parse_next(input)?;
+    let anchor = attributes.and_then(|attributes| {
+        let mut anchor = Anchor::new(attributes.id()?);
+        anchor.reftext = attributes.get("reftext").map(str::to_string);
+        Some(anchor)
+    });
+
     // Parse heading marker and get level
     let level = heading_level.parse_next(input)?;
 
-    // Parse heading title (one or more words)
-    let _title_words: Vec<Token> = repeat(1.., token(Token::Word)).parse_next(input)?;
-
-    // For POC, use placeholder title (same as Chumsky)
-    let title = "Section".to_string();
+    // The title is the rest of the heading line, with inline formatting
+    let title_tokens: Vec<Spanned> = repeat(
+        1..,
+        any.verify(|(t, _): &Spanned| !matches!(t, Token::Newline | Token::BlankLine)),
+    )
+    .parse_next(input)?;
+    let title = format_inlines(input.state.text, &title_tokens);
 
     // Consume newline or blank line after heading
     alt((token(Token::Newline), token(Token::BlankLine))).parse_next(input)?;
 
-    // Parse nested blocks
-    let content: Vec<Block> = repeat(0.., block).parse_next(input)?;
+    // Parse nested blocks, up to the end of an enclosing delimited block or
+    // the next heading at this level or above
+    let content: Vec<Block> = repeat(
+        0..,
+        preceded((not(closing_delimiter), not(heading_up_to(level))), block),
+    )
+    .parse_next(input)?;
 
     // Skip trailing blank lines
     let _: Vec<Token> = repeat(0.., token(Token::BlankLine)).parse_next(input)?;
@@ -184,12 +906,31 @@ fn section(input: &mut Input<'_>) -> winnow::Result<Block> {
         level,
         title,
         content,
+        anchor,
+        metadata: BlockMetadata::default(),
     })
 }
 
+/// Parse the start of a section whose level is `level` or above (a lower
+/// number), which ends the section being parsed
+///
+/// The heading may follow a block title, anchor lines and an attribute
+/// line, as in [`titled_block`].
+fn heading_up_to<'a>(level: u8) -> impl Parser<Input<'a>, u8, ContextError> {
+    let titled = any.verify(|(t, _): &Spanned| !matches!(t, Token::Newline | Token::BlankLine));
+    (
+        opt(block_title),
+        repeat::<_, _, (), _, _>(0.., anchor_line),
+        opt(attribute_line),
+        heading_level.verify(move |next: &u8| *next <= level),
+        titled,
+    )
+        .map(|(_, _, _, next, _)| next)
+}
+
 /// Parse heading level from heading token
 fn heading_level(input: &mut Input<'_>) -> winnow::Result<u8> {
-    any.verify_map(|token| match token {
+    any.verify_map(|(token, _): Spanned| match token {
         Token::Heading1 => Some(1u8),
         Token::Heading2 => Some(2u8),
         Token::Heading3 => Some(3u8),
@@ -201,10 +942,1018 @@ fn heading_level(input: &mut Input<'_>) -> winnow::Result<u8> {
     .parse_next(input)
 }
 
+/// Parse a listing or literal block, with an optional attribute list line
+///
+/// The lexer delivers the block content as a single verbatim token.
+fn listing(input: &mut Input<'_>) -> winnow::Result<Block> {
+    let mut attributes = opt(attribute_line).parse_next(input)?.unwrap_or_default();
+    let delimiter = any
+        .verify_map(|(t, _): Spanned| {
+            matches!(t, Token::ListingDelimiter | Token::LiteralDelimiter).then_some(t)
+        })
+        .parse_next(input)?;
+    let _: Option<Token> = opt(token(Token::Newline)).parse_next(input)?;
+    let content = opt(any.verify(|(t, _): &Spanned| *t == Token::Verbatim))
+        .parse_next(input)?
+        .map(|(_, span)| input.state.text[span].to_string())
+        .unwrap_or_default();
+    let _: Option<Token> = opt(token(Token::Newline)).parse_next(input)?;
+    // A block without a closing delimiter runs to the end of the document
+    let _: Option<Token> = opt(token(delimiter.clone())).parse_next(input)?;
+    skip_line_breaks(input)?;
+
+    let style = match attributes.style() {
+        Some(style) => style.to_string(),
+        None if delimiter == Token::LiteralDelimiter => "literal".to_string(),
+        None => "listing".to_string(),
+    };
+    let language = match attributes.get("language") {
+        Some(language) => Some(language.to_string()),
+        None if style == "source" || attributes.style().is_none() => {
+            attributes.get("2").map(str::to_string)
+        }
+        None => None,
+    };
+    attributes.set("style", style);
+
+    Ok(Block::Listing {
+        language,
+        content,
+        attributes,
+        metadata: BlockMetadata::default(),
+    })
+}
+
+/// Parse a passthrough block (`++++`), with an optional attribute list line
+///
+/// The `backend` attribute names the backend the content is for, `html` if
+/// it is missing. Like a listing, the content arrives as a single verbatim
+/// token.
+fn passthrough(input: &mut Input<'_>) -> winnow::Result<Block> {
+    let attributes = opt(attribute_line).parse_next(input)?.unwrap_or_default();
+    token(Token::PassthroughDelimiter).parse_next(input)?;
+    let _: Option<Token> = opt(token(Token::Newline)).parse_next(input)?;
+    let content = opt(any.verify(|(t, _): &Spanned| *t == Token::Verbatim))
+        .parse_next(input)?
+        .map(|(_, span)| input.state.text[span].to_string())
+        .unwrap_or_default();
+    let _: Option<Token> = opt(token(Token::Newline)).parse_next(input)?;
+    // A block without a closing delimiter runs to the end of the document
+    let _: Option<Token> = opt(token(Token::PassthroughDelimiter)).parse_next(input)?;
+    skip_line_breaks(input)?;
+    Ok(Block::Passthrough {
+        content,
+        backend: attributes
+            .get("backend")
+            .unwrap_or("html")
+            .to_ascii_lowercase(),
+        metadata: BlockMetadata::default(),
+    })
+}
+
+/// Parse a line comment (`// text`) or a comment block (`////`)
+///
+/// A line comment's content is the text after the slashes, including any
+/// leading space. Like a listing, a comment block's content arrives as a
+/// single verbatim token.
+fn comment(input: &mut Input<'_>) -> winnow::Result<Block> {
+    let (delimiter, span) = any
+        .verify(|(t, _): &Spanned| matches!(t, Token::LineComment | Token::CommentDelimiter))
+        .parse_next(input)?;
+    if delimiter == Token::LineComment {
+        skip_line_breaks(input)?;
+        return Ok(Block::Comment {
+            content: input.state.text[span][2..].to_string(),
+            delimited: false,
+            metadata: BlockMetadata::default(),
+        });
+    }
+    let _: Option<Token> = opt(token(Token::Newline)).parse_next(input)?;
+    let content = opt(any.verify(|(t, _): &Spanned| *t == Token::Verbatim))
+        .parse_next(input)?
+        .map(|(_, span)| input.state.text[span].to_string())
+        .unwrap_or_default();
+    let _: Option<Token> = opt(token(Token::Newline)).parse_next(input)?;
+    // A block without a closing delimiter runs to the end of the document
+    let _: Option<Token> = opt(token(Token::CommentDelimiter)).parse_next(input)?;
+    skip_line_breaks(input)?;
+    Ok(Block::Comment {
+        content,
+        delimited: true,
+        metadata: BlockMetadata::default(),
+    })
+}
+
+/// Parse a table, with an optional attribute list line
+///
+/// Cells are filled into rows left to right; a row ends when its columns are
+/// used up, counting cells from earlier rows that span into it. Text before
+/// the first cell separator is ignored, and a table without a closing
+/// delimiter runs to the end of the document. Rows beyond
+/// [`Limits::max_table_rows`] are dropped, without parsing their cells, and
+/// recorded as a truncation.
+fn table(input: &mut Input<'_>) -> winnow::Result<Block> {
+    let attributes = opt(attribute_line).parse_next(input)?.unwrap_or_default();
+    let (_, open) = any
+        .verify(|(t, _): &Spanned| *t == Token::TableDelimiter)
+        .parse_next(input)?;
+    skip_line_breaks(input)?;
+    let body = take_while(0.., |(t, _): Spanned| t != Token::TableDelimiter).parse_next(input)?;
+    let _: Option<Token> = opt(token(Token::TableDelimiter)).parse_next(input)?;
+    skip_line_breaks(input)?;
+
+    let (cells, first_line) = table_cells(&input.state, body);
+    let columns = match attributes.get("cols") {
+        Some(cols) => column_specs(cols),
+        None => {
+            let width = cells[..first_line].iter().map(|cell| cell.colspan).sum();
+            vec![ColumnSpec { width: 1 }; width]
+        }
+    };
+
+    // Without an explicit option, a first line of cells followed by a blank
+    // line is the header
+    let implicit_header = first_line > 0
+        && body
+            .iter()
+            .find(|(t, _)| matches!(t, Token::Newline | Token::BlankLine))
+            .is_some_and(|(t, _)| *t == Token::BlankLine);
+    let options = attributes.options();
+    let has_header =
+        options.contains(&"header") || implicit_header && !options.contains(&"noheader");
+
+    // The header row is kept on top of the limit, which counts body rows
+    let max_rows = input.state.limits.max_table_rows;
+    let (rows, dropped) = table_rows(
+        cells,
+        columns.len(),
+        max_rows.map(|max| max + usize::from(has_header)),
+    );
+    if dropped > 0 {
+        input.state.truncations.borrow_mut().push(Truncation {
+            kind: StructureKind::Table,
+            offset: open.start,
+            kept: max_rows.unwrap_or_default(),
+            dropped,
+        });
+    }
+    // Only the kept cells have their content parsed
+    let mut rows = rows.into_iter().map(|row| TableRow {
+        cells: row
+            .into_iter()
+            .map(|cell| TableCell {
+                content: trimmed_inlines(&input.state, &body[cell.tokens]),
+                colspan: cell.colspan,
+                rowspan: cell.rowspan,
+            })
+            .collect(),
+    });
+    let header = if has_header { rows.next() } else { None };
+    let rows = rows.collect();
+
+    Ok(Block::Table {
+        columns,
+        header,
+        rows,
+        attributes,
+        metadata: BlockMetadata::default(),
+    })
+}
+
+/// Parse a quote or verse block, with an optional attribute list line
+///
+/// Either a `____` delimited block, or a paragraph styled `[quote]` or
+/// `[verse]` (its lines up to the next blank line or delimiter line). The
+/// attribution and
+/// citation are the second and third positional attributes, or the named
+/// `attribution` and `citetitle` attributes.
+fn quote(input: &mut Input<'_>) -> winnow::Result<Block> {
+    let attributes = opt(attribute_line).parse_next(input)?.unwrap_or_default();
+    let style = attributes.style();
+    let delimited = input.input.first().is_some_and(|token| {
+        token.0 == Token::QuoteDelimiter && !closes_block(&input.state, token)
+    });
+    if !delimited && !matches!(style, Some("quote" | "verse")) {
+        return Err(ContextError::new());
+    }
+    let verse = style == Some("verse");
+    let named_or_positional = |name: &str, position: &str| {
+        attributes
+            .get(name)
+            .or_else(|| attributes.get(position))
+            .map(str::to_string)
+    };
+    let attribution = named_or_positional("attribution", "2");
+    let citation = named_or_positional("citetitle", "3");
+
+    // The tokens of the content, for verse and styled paragraphs
+    let text = input.state.text;
+    let tokens = if delimited {
+        let (_, open) = any.parse_next(input)?;
+        let delimiter = &text[open.clone()];
+        if !verse {
+            let content = delimited_blocks(input, &Token::QuoteDelimiter, open)?;
+            return Ok(Block::Quote {
+                attribution,
+                citation,
+                content,
+                metadata: BlockMetadata::default(),
+            });
+        }
+        let tokens = take_while(0.., |(t, span): Spanned| {
+            t != Token::QuoteDelimiter || text[span] != *delimiter
+        })
+        .parse_next(input)?;
+        let _: Option<Spanned> = opt(any).parse_next(input)?;
+        tokens
+    } else {
+        let tokens = take_while(0.., |(t, _): Spanned| {
+            t != Token::BlankLine && !is_block_delimiter(&t)
+        })
+        .parse_next(input)?;
+        // Like any attribute list line, one with no paragraph after it is
+        // text
+        if tokens.is_empty() {
+            return Err(ContextError::new());
+        }
+        tokens
+    };
+    skip_line_breaks(input)?;
+
+    let content = trimmed_inlines(&input.state, tokens);
+    Ok(if verse {
+        Block::Verse {
+            attribution,
+            citation,
+            content,
+            metadata: BlockMetadata::default(),
+        }
+    } else {
+        Block::Quote {
+            attribution,
+            citation,
+            content: vec![Block::Paragraph {
+                content,
+                metadata: BlockMetadata::default(),
+            }],
+            metadata: BlockMetadata::default(),
+        }
+    })
+}
+
+/// Parse an admonition, with an optional attribute list line
+///
+/// Either an example block or paragraph styled with an admonition label
+/// (`[NOTE]`), or a paragraph whose first line starts with the label, a
+/// colon and a space (`NOTE: text`). Like a styled quote paragraph, the
+/// paragraph runs to the next blank line or delimiter line.
+fn admonition(input: &mut Input<'_>) -> winnow::Result<Block> {
+    let attributes = opt(attribute_line).parse_next(input)?.unwrap_or_default();
+    let source = input.state;
+    let text = source.text;
+    let kind = match attributes.style().and_then(AdmonitionKind::from_label) {
+        Some(kind) => {
+            let open = opt(any.verify(|token: &Spanned| {
+                token.0 == Token::ExampleDelimiter && !closes_block(&source, token)
+            }))
+            .parse_next(input)?;
+            if let Some((_, open)) = open {
+                let content = delimited_blocks(input, &Token::ExampleDelimiter, open)?;
+                return Ok(Block::Admonition {
+                    kind,
+                    content,
+                    metadata: BlockMetadata::default(),
+                });
+            }
+            kind
+        }
+        None => {
+            let [(Token::Word, label), (Token::Punctuation, colon), ..] = input.input else {
+                return Err(ContextError::new());
+            };
+            let kind = AdmonitionKind::from_label(&text[label.clone()])
+                .filter(|_| starts_line(input, &input.input[..1]))
+                .filter(|_| colon.start == label.end && &text[colon.clone()] == ":")
+                .filter(|_| {
+                    let rest = text[colon.end..].lines().next().unwrap_or_default();
+                    rest.starts_with([' ', '\t']) && !rest.trim().is_empty()
+                })
+                .ok_or_else(ContextError::new)?;
+            let _: (Spanned, Spanned) = (any, any).parse_next(input)?;
+            kind
+        }
+    };
+    let tokens = take_while(0.., |(t, _): Spanned| {
+        t != Token::BlankLine && !is_block_delimiter(&t)
+    })
+    .parse_next(input)?;
+    // Like any attribute list line, one with no paragraph after it is text,
+    // and so is a label with only trailing whitespace after it
+    let content = trimmed_inlines(&input.state, tokens);
+    if content.is_empty() {
+        return Err(ContextError::new());
+    }
+    skip_line_breaks(input)?;
+    Ok(Block::Admonition {
+        kind,
+        content: vec![Block::Paragraph {
+            content,
+            metadata: BlockMetadata::default(),
+        }],
+        metadata: BlockMetadata::default(),
+    })
+}
+
+/// Parse an example (`====`) or sidebar (`****`) block
+///
+/// The delimiter that closes an enclosing block opens none.
+fn compound_block(input: &mut Input<'_>) -> winnow::Result<Block> {
+    let source = input.state;
+    let (kind, open) = any
+        .verify(|token: &Spanned| {
+            matches!(token.0, Token::ExampleDelimiter | Token::SidebarDelimiter)
+                && !closes_block(&source, token)
+        })
+        .parse_next(input)?;
+    let content = delimited_blocks(input, &kind, open)?;
+    Ok(match kind {
+        Token::ExampleDelimiter => Block::Example {
+            content,
+            metadata: BlockMetadata::default(),
+        },
+        _ => Block::Sidebar {
+            content,
+            metadata: BlockMetadata::default(),
+        },
+    })
+}
+
+/// Parse an open block (`--`), with an optional attribute list line
+///
+/// Open blocks do not nest: the first `--` line inside one closes it.
+fn open_block(input: &mut Input<'_>) -> winnow::Result<Block> {
+    let attributes = opt(attribute_line).parse_next(input)?.unwrap_or_default();
+    let source = input.state;
+    let (_, open) = any
+        .verify(|token: &Spanned| token.0 == Token::OpenDelimiter && !closes_block(&source, token))
+        .parse_next(input)?;
+    let content = delimited_blocks(input, &Token::OpenDelimiter, open)?;
+    Ok(Block::Open {
+        content,
+        attributes,
+        metadata: BlockMetadata::default(),
+    })
+}
+
+/// Parse a block image (`image::target[alt,width,height]`), with an optional
+/// attribute list line
+///
+/// The macro must fill its line. The attribute list line contributes its
+/// named attributes, id and roles; the macro's own attributes win.
+fn image(input: &mut Input<'_>) -> winnow::Result<Block> {
+    let line_attributes = opt(attribute_line).parse_next(input)?.unwrap_or_default();
+    let line: Vec<Spanned> = repeat(
+        1..,
+        any.verify(|(t, _): &Spanned| !matches!(t, Token::Newline | Token::BlankLine)),
+    )
+    .parse_next(input)?;
+    let (target, list) = source_between(input, &line)
+        .strip_prefix("image::")
+        .and_then(|macro_| macro_.strip_suffix(']')?.split_once('['))
+        .filter(|(target, _)| !target.is_empty() && !target.contains(char::is_whitespace))
+        .ok_or_else(ContextError::new)?;
+    let _: Option<Token> = opt(token(Token::Newline)).parse_next(input)?;
+    let _: Vec<Token> = repeat(0.., token(Token::BlankLine)).parse_next(input)?;
+
+    let mut attributes = image_attributes(list);
+    if let Some(id) = line_attributes.id()
+        && !attributes.contains("id")
+    {
+        attributes.set("id", id);
+    }
+    let roles = line_attributes.roles().join(" ");
+    if !roles.is_empty() && !attributes.contains("role") {
+        attributes.set("role", roles);
+    }
+    for (name, value) in line_attributes.iter() {
+        let positional = name.parse::<usize>().is_ok();
+        if !positional && !attributes.contains(name) {
+            attributes.set(name, value);
+        }
+    }
+    Ok(Block::Image {
+        target: target.to_string(),
+        attributes,
+        metadata: BlockMetadata::default(),
+    })
+}
+
+/// Parse syntax that the AST does not model, kept as [`Block::Unknown`]
+///
+/// This is a block macro line other than `image::` (`video::intro.mp4[]`,
+/// `toc::[]`), or a block delimited by a `` ``` `` fence (optionally
+/// followed by a language), `,===` or `:===`, up to its closing line. A
+/// block without a closing line runs to the end of the document.
+fn unknown(input: &mut Input<'_>) -> winnow::Result<Block> {
+    let text = input.state.text;
+    let line: Vec<Spanned> = repeat(
+        1..,
+        any.verify(|(t, _): &Spanned| !matches!(t, Token::Newline | Token::BlankLine)),
+    )
+    .parse_next(input)?;
+    let first = source_between(input, &line);
+    let start = line[0].1.start;
+    let line_end = line[line.len() - 1].1.end;
+    let mut end = if is_block_macro(first) {
+        line_end
+    } else if let Some(closing) = unknown_delimiter(first) {
+        let last = input.input.last().map_or(line_end, |(_, span)| span.end);
+        closing_line(text, line_end, closing)
+            .unwrap_or_else(|| text[..last].trim_end().len().max(line_end))
+    } else {
+        return Err(ContextError::new());
+    };
+    // A `----` line inside a fence starts verbatim content that can run
+    // past the closing line; the block then ends with that content, so no
+    // source is lost
+    while let Some((_, span)) = input.input.first().filter(|(_, span)| span.start < end) {
+        end = end.max(text[..span.end].trim_end().len());
+        let _ = any.parse_next(input)?;
+    }
+    skip_line_breaks(input)?;
+    Ok(Block::Unknown {
+        raw: text[start..end].to_string(),
+        span: start..end,
+        metadata: BlockMetadata::default(),
+    })
+}
+
+/// True for a block macro line (`name::target[attributes]`) the AST has no
+/// block for
+fn is_block_macro(line: &str) -> bool {
+    let Some((name, rest)) = line.split_once("::") else {
+        return false;
+    };
+    let named = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    let target = rest
+        .strip_suffix(']')
+        .and_then(|rest| rest.split_once('['))
+        .map(|(target, _)| target);
+    named && name != "image" && target.is_some_and(|t| !t.contains(char::is_whitespace))
+}
+
+/// The closing line of an unmodeled delimited block opened by `line`
+fn unknown_delimiter(line: &str) -> Option<&str> {
+    if let Some(language) = line.strip_prefix("```") {
+        let plain = language.chars().all(|c| !c.is_whitespace() && c != '`');
+        return plain.then_some("```");
+    }
+    let table =
+        line.len() >= 4 && line.starts_with([',', ':']) && line[1..].bytes().all(|b| b == b'=');
+    table.then_some(line)
+}
+
+/// End offset of the first line after `from` that reads `closing`, ignoring
+/// trailing whitespace
+fn closing_line(text: &str, from: usize, closing: &str) -> Option<usize> {
+    let mut offset = from;
+    for line in text[from..].split_inclusive('\n') {
+        let content = line.trim_end();
+        if offset > from && content == closing {
+            return Some(offset + content.len());
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Parse the blocks of a compound block up to its closing delimiter
+///
+/// The closing delimiter is a `kind` token with the same text as the opening
+/// delimiter at `open`, so blocks of the same kind nest when their
+/// delimiters differ in length. A block without a closing delimiter runs to
+/// the end of the document. A section inside the block ends at the closing
+/// delimiter.
+fn delimited_blocks(input: &mut Input<'_>, kind: &Token, open: Span) -> winnow::Result<Vec<Block>> {
+    let delimiter = &input.state.text[open.clone()];
+    nested(input, open, |input| {
+        let outer = input.state.closing.replace(delimiter);
+        let mut content = Vec::new();
+        loop {
+            skip_line_breaks(input)?;
+            match input.input.first() {
+                None => break,
+                Some((t, span)) if t == kind && input.state.text[span.clone()] == *delimiter => {
+                    let _ = any.parse_next(input)?;
+                    break;
+                }
+                _ => match block(input) {
+                    Ok(block) => content.push(block),
+                    Err(err) => {
+                        input.state.closing = outer;
+                        return Err(err);
+                    }
+                },
+            }
+        }
+        input.state.closing = outer;
+        skip_line_breaks(input)?;
+        Ok(content)
+    })
+}
+
+/// Parse the delimiter that closes the innermost enclosing delimited block
+fn closing_delimiter(input: &mut Input<'_>) -> winnow::Result<Spanned> {
+    let source = input.state;
+    any.verify(|token: &Spanned| closes_block(&source, token))
+        .parse_next(input)
+}
+
+/// Whether `token` is the delimiter that closes the innermost enclosing
+/// delimited block
+fn closes_block(source: &Source<'_>, (t, span): &Spanned) -> bool {
+    matches!(
+        t,
+        Token::QuoteDelimiter
+            | Token::ExampleDelimiter
+            | Token::SidebarDelimiter
+            | Token::OpenDelimiter
+    ) && source.closing == Some(&source.text[span.clone()])
+}
+
+/// Run `parse` one nesting level deeper, for the block that starts at
+/// `span`
+///
+/// Past [`Limits::max_nesting_depth`] this records a
+/// [`ParseError::NestingTooDeep`] and fails, so hostile nesting cannot
+/// overflow the stack.
+fn nested<'a, T>(
+    input: &mut Input<'a>,
+    span: Span,
+    parse: impl FnOnce(&mut Input<'a>) -> winnow::Result<T>,
+) -> winnow::Result<T> {
+    if let Some(max) = input.state.limits.max_nesting_depth
+        && input.state.depth >= max
+    {
+        let err = ParseError::NestingTooDeep { span, max };
+        input.state.too_deep.borrow_mut().get_or_insert(err);
+        return Err(ContextError::new());
+    }
+    input.state.depth += 1;
+    let parsed = parse(input);
+    input.state.depth -= 1;
+    parsed
+}
+
+/// A table cell before its content is parsed
+#[derive(Clone)]
+struct CellSpan {
+    /// Range of the cell's content in the table body tokens
+    tokens: Range<usize>,
+    colspan: usize,
+    rowspan: usize,
+}
+
+/// Split table body tokens into cells
+///
+/// Returns the cells, with duplicated cells (`3*|`) repeated, and how many
+/// of them start on the first line.
+fn table_cells(source: &Source<'_>, body: &[Spanned]) -> (Vec<CellSpan>, usize) {
+    let text = source.text;
+    let mut cells = Vec::new();
+    let mut first_line = None;
+    let starts = body
+        .iter()
+        .enumerate()
+        .filter(|(_, (t, _))| *t == Token::CellSeparator);
+    let mut starts = starts.peekable();
+
+    while let Some((start, (_, separator))) = starts.next() {
+        let end = starts.peek().map_or(body.len(), |&(next, _)| next);
+
+        let (colspan, rowspan, repeat) = parse_cell_spec(&text[separator.start..separator.end - 1])
+            .map_or((1, 1, 1), |spec| (spec.colspan, spec.rowspan, spec.repeat));
+        let cell = CellSpan {
+            tokens: start + 1..end,
+            colspan,
+            rowspan,
+        };
+        cells.extend(std::iter::repeat_n(cell, repeat));
+
+        let is_line_break = |(t, _): &Spanned| matches!(t, Token::Newline | Token::BlankLine);
+        if first_line.is_none() && body[start..end].iter().any(is_line_break) {
+            first_line = Some(cells.len());
+        }
+    }
+    let first_line = first_line.unwrap_or(cells.len());
+    (cells, first_line)
+}
+
+/// Arrange `cells` into rows of `columns` columns
+///
+/// Rows beyond `max_rows` are not kept; returns the kept rows and how many
+/// were dropped.
+fn table_rows(
+    cells: Vec<CellSpan>,
+    columns: usize,
+    max_rows: Option<usize>,
+) -> (Vec<Vec<CellSpan>>, usize) {
+    if columns == 0 {
+        return (Vec::new(), 0);
+    }
+    let mut rows = Vec::new();
+    let mut dropped = 0;
+    let mut end_row = |row: Vec<CellSpan>| {
+        if max_rows.is_none_or(|max| rows.len() < max) {
+            rows.push(row);
+        } else {
+            dropped += 1;
+        }
+    };
+    let mut row = Vec::new();
+    // Rows each column is still covered by a cell from an earlier row
+    let mut covered = vec![0usize; columns];
+    let mut column = 0;
+
+    for cell in cells {
+        loop {
+            while column < columns && covered[column] > 0 {
+                column += 1;
+            }
+            if column < columns {
+                break;
+            }
+            end_row(std::mem::take(&mut row));
+            covered
+                .iter_mut()
+                .for_each(|rows| *rows = rows.saturating_sub(1));
+            column = 0;
+        }
+        let span = cell.colspan.min(columns - column);
+        covered[column..column + span].fill(cell.rowspan);
+        column += span;
+        row.push(cell);
+    }
+    if !row.is_empty() {
+        end_row(row);
+    }
+    (rows, dropped)
+}
+
+/// Parse a `cols` attribute (`"1,2,3"`, `"3*"`, `"2*,1"`, or a column count)
+///
+/// Each entry may repeat (`3*2`), and alignment (`<^>`) and style letters
+/// are accepted but only the width is kept.
+fn column_specs(cols: &str) -> Vec<ColumnSpec> {
+    if let Ok(count) = cols.trim().parse() {
+        return vec![ColumnSpec { width: 1 }; count];
+    }
+    let mut columns = Vec::new();
+    for entry in cols.split([',', ';']).map(str::trim) {
+        let (repeat, spec) = match entry.split_once('*') {
+            Some((count, spec)) => (count.parse().unwrap_or(1), spec),
+            None => (1, entry),
+        };
+        let width: String = spec
+            .trim_start_matches(['<', '^', '>', '.'])
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .collect();
+        let width = width.parse().ok().filter(|&w| w > 0).unwrap_or(1);
+        columns.extend(std::iter::repeat_n(ColumnSpec { width }, repeat));
+    }
+    columns
+}
+
+/// Parse a block attribute list line (`[source,rust]`)
+fn attribute_line(input: &mut Input<'_>) -> winnow::Result<Attributes> {
+    let line: Vec<Spanned> = repeat(
+        2..,
+        any.verify(|(t, _): &Spanned| !matches!(t, Token::Newline | Token::BlankLine)),
+    )
+    .parse_next(input)?;
+    token(Token::Newline).parse_next(input)?;
+    if !starts_line(input, &line) {
+        return Err(ContextError::new());
+    }
+
+    let text = source_between(input, &line);
+    // `[[` opens an anchor, so a line like `[[id]] text]` is a paragraph
+    match text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        Some(list) if !list.starts_with('[') => Ok(parse_attribute_list(list)),
+        _ => Err(ContextError::new()),
+    }
+}
+
+/// Parse a top-level list
+fn list(input: &mut Input<'_>) -> winnow::Result<Block> {
+    list_within(input, &[])
+}
+
+/// Parse a list whose marker is not used by an enclosing list
+///
+/// `enclosing` holds the markers of the lists this one is nested in. An item
+/// with one of those markers ends this list and continues the enclosing one;
+/// an item with a new marker starts a list nested in the preceding item.
+fn list_within(input: &mut Input<'_>, enclosing: &[&str]) -> winnow::Result<Block> {
+    let Some((kind, span)) = peek_marker(input) else {
+        return Err(ContextError::new());
+    };
+    let marker = &input.state.text[span.clone()];
+    if enclosing.contains(&marker) {
+        return Err(ContextError::new());
+    }
+    let markers = [enclosing, &[marker]].concat();
+    let items = nested(input, span.clone(), |input| {
+        let mut items = LimitedItems::new(input);
+        loop {
+            items.push(list_item(input, &markers)?);
+
+            // Items of one list may be separated by blank lines
+            let checkpoint = input.checkpoint();
+            skip_line_breaks(input)?;
+            match peek_marker(input) {
+                Some((next, next_span))
+                    if next == kind && input.state.text[next_span.clone()] == *marker => {}
+                _ => {
+                    input.reset(&checkpoint);
+                    break;
+                }
+            }
+        }
+        Ok(items.finish(input, span.start))
+    })?;
+    skip_line_breaks(input)?;
+
+    Ok(match kind {
+        Token::OrderedMarker => Block::OrderedList {
+            items,
+            metadata: BlockMetadata::default(),
+        },
+        _ => Block::UnorderedList {
+            items,
+            metadata: BlockMetadata::default(),
+        },
+    })
+}
+
+/// Parse a list item: marker, text lines, and attached blocks
+///
+/// Text continues on following lines until a blank line or a line with
+/// other syntax. A list continuation (`+` line) attaches the next paragraph
+/// or list on the following line; a marker not in `markers` starts a
+/// nested list.
+fn list_item(input: &mut Input<'_>, markers: &[&str]) -> winnow::Result<ListItem> {
+    any.verify(|(t, _): &Spanned| matches!(t, Token::UnorderedMarker | Token::OrderedMarker))
+        .parse_next(input)?;
+    let mut item = ListItem::new(inlines(input)?);
+
+    // Following text lines belong to the item
+    loop {
+        let checkpoint = input.checkpoint();
+        if opt(token(Token::Newline)).parse_next(input)?.is_some() && starts_inline(input) {
+            let line = inlines(input)?;
+            push_line(&mut item.content, line);
+        } else {
+            input.reset(&checkpoint);
+            break;
+        }
+    }
+
+    loop {
+        let checkpoint = input.checkpoint();
+        let _: Option<Token> = opt(token(Token::Newline)).parse_next(input)?;
+        if opt(token(Token::ListContinuation))
+            .parse_next(input)?
+            .is_some()
+        {
+            let attached = opt(preceded(
+                token(Token::Newline),
+                alt((|i: &mut Input<'_>| list_within(i, markers), paragraph)),
+            ))
+            .parse_next(input)?;
+            if let Some(block) = attached {
+                item.blocks.push(block);
+                continue;
+            }
+            // A continuation that attaches nothing ends the list and is
+            // read as text
+            input.reset(&checkpoint);
+            break;
+        }
+
+        input.reset(&checkpoint);
+        skip_line_breaks(input)?;
+        match peek_marker(input) {
+            Some((_, span)) if !markers.contains(&&input.state.text[span.clone()]) => {
+                item.blocks.push(list_within(input, markers)?);
+            }
+            _ => {
+                input.reset(&checkpoint);
+                break;
+            }
+        }
+    }
+
+    Ok(item)
+}
+
+/// Parse a description list (`term:: description` entries)
+///
+/// Entries may be separated by blank lines and continue as long as they use
+/// the separator of the first entry.
+fn description_list(input: &mut Input<'_>) -> winnow::Result<Block> {
+    if !line_has_separator(input) {
+        return Err(ContextError::new());
+    }
+    let offset = input.input.first().map_or(0, |(_, span)| span.start);
+    let (first, separator) = description_item(input)?;
+    let mut items = LimitedItems::new(input);
+    items.push(first);
+
+    loop {
+        let checkpoint = input.checkpoint();
+        skip_line_breaks(input)?;
+        match description_item(input) {
+            Ok((item, next)) if next == separator => items.push(item),
+            _ => {
+                input.reset(&checkpoint);
+                break;
+            }
+        }
+    }
+
+    let items = items.finish(input, offset);
+    skip_line_breaks(input)?;
+    Ok(Block::DescriptionList {
+        items,
+        separator: separator.to_string(),
+        metadata: BlockMetadata::default(),
+    })
+}
+
+/// Parse one description list entry, returning it with its separator
+///
+/// The description starts after the separator or, if the rest of the line is
+/// empty, on the next line, and continues until a blank line or the next
+/// term.
+fn description_item<'a>(input: &mut Input<'a>) -> winnow::Result<(DescriptionItem, &'a str)> {
+    if !line_has_separator(input) {
+        return Err(ContextError::new());
+    }
+    let term = inlines(input)?;
+    let (_, span) = any
+        .verify(|(t, _): &Spanned| *t == Token::DescriptionSeparator)
+        .parse_next(input)?;
+    let separator = &input.state.text[span];
+    let mut description = opt(inlines).parse_next(input)?.unwrap_or_default();
+
+    loop {
+        let checkpoint = input.checkpoint();
+        if opt(token(Token::Newline)).parse_next(input)?.is_some()
+            && starts_inline(input)
+            && !line_has_separator(input)
+        {
+            let line = inlines(input)?;
+            if description.is_empty() {
+                description = line;
+            } else {
+                push_line(&mut description, line);
+            }
+        } else {
+            input.reset(&checkpoint);
+            break;
+        }
+    }
+
+    Ok((DescriptionItem { term, description }, separator))
+}
+
+/// Collects the items of one list up to the `max_list_items` limit
+struct LimitedItems<T> {
+    items: Vec<T>,
+    max: Option<usize>,
+    dropped: usize,
+}
+
+impl<T> LimitedItems<T> {
+    fn new(input: &Input<'_>) -> Self {
+        Self {
+            items: Vec::new(),
+            max: input.state.limits.max_list_items,
+            dropped: 0,
+        }
+    }
+
+    /// Keep `item`, or count it as dropped once the limit is reached
+    fn push(&mut self, item: T) {
+        if self.max.is_none_or(|max| self.items.len() < max) {
+            self.items.push(item);
+        } else {
+            self.dropped += 1;
+        }
+    }
+
+    /// The kept items; records a truncation for the list at `offset` if any
+    /// items were dropped
+    fn finish(self, input: &Input<'_>, offset: usize) -> Vec<T> {
+        if self.dropped > 0 {
+            input.state.truncations.borrow_mut().push(Truncation {
+                kind: StructureKind::List,
+                offset,
+                kept: self.items.len(),
+                dropped: self.dropped,
+            });
+        }
+        self.items
+    }
+}
+
+/// True if the rest of the current line contains a description list separator
+fn line_has_separator(input: &Input<'_>) -> bool {
+    input
+        .input
+        .iter()
+        .take_while(|(t, _)| !matches!(t, Token::Newline | Token::BlankLine))
+        .any(|(t, _)| *t == Token::DescriptionSeparator)
+}
+
+/// The next token, if it is a list marker
+fn peek_marker(input: &Input<'_>) -> Option<(Token, Span)> {
+    match input.input.first() {
+        Some((kind @ (Token::UnorderedMarker | Token::OrderedMarker), span)) => {
+            Some((kind.clone(), span.clone()))
+        }
+        _ => None,
+    }
+}
+
+/// True if the next token can start inline content
+fn starts_inline(input: &Input<'_>) -> bool {
+    matches!(
+        input.input.first(),
+        Some((
+            Token::Word
+                | Token::Punctuation
+                | Token::BoldDelimiter
+                | Token::ItalicDelimiter
+                | Token::MonospaceDelimiter
+                | Token::HighlightDelimiter
+                | Token::SuperscriptDelimiter
+                | Token::SubscriptDelimiter
+                | Token::Url,
+            _
+        ))
+    )
+}
+
+/// Whether `token` opens or closes a delimited block
+fn is_block_delimiter(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::ListingDelimiter
+            | Token::LiteralDelimiter
+            | Token::CommentDelimiter
+            | Token::PassthroughDelimiter
+            | Token::QuoteDelimiter
+            | Token::ExampleDelimiter
+            | Token::SidebarDelimiter
+            | Token::OpenDelimiter
+            | Token::TableDelimiter
+    )
+}
+
+/// Skip any newlines and blank lines
+fn skip_line_breaks(input: &mut Input<'_>) -> winnow::Result<()> {
+    let _: Vec<Token> =
+        repeat(0.., alt((token(Token::Newline), token(Token::BlankLine)))).parse_next(input)?;
+    Ok(())
+}
+
+/// Append another source line of inline content, joined by a line feed
+fn push_line(content: &mut Vec<Inline>, line: Vec<Inline>) {
+    let mut line = line.into_iter();
+    match (content.last_mut(), line.next()) {
+        (Some(Inline::Text(last)), Some(Inline::Text(first))) => {
+            last.push('\n');
+            last.push_str(&first);
+        }
+        (_, Some(first)) => {
+            content.push(Inline::Text("\n".to_string()));
+            content.push(first);
+        }
+        (_, None) => {}
+    }
+    content.extend(line);
+}
+
 /// Parse a paragraph (inline content until blank line)
 fn paragraph(input: &mut Input<'_>) -> winnow::Result<Block> {
     // Parse one or more inline elements
-    let content: Vec<Inline> = repeat(1.., inline).parse_next(input)?;
+    let content = inlines(input)?;
 
     // Optional trailing newline
     let _: Option<Token> = opt(token(Token::Newline)).parse_next(input)?;
@@ -212,366 +1961,1854 @@ fn paragraph(input: &mut Input<'_>) -> winnow::Result<Block> {
     // Skip trailing blank lines
     let _: Vec<Token> = repeat(0.., token(Token::BlankLine)).parse_next(input)?;
 
-    Ok(Block::Paragraph { content })
-}
-
-/// Parse inline content (text, bold, italic)
-fn inline(input: &mut Input<'_>) -> winnow::Result<Inline> {
-    alt((bold, italic, text)).parse_next(input)
+    Ok(Block::Paragraph {
+        content,
+        metadata: BlockMetadata::default(),
+    })
 }
 
-/// Parse plain text (word token)
-fn text(input: &mut Input<'_>) -> winnow::Result<Inline> {
-    token(Token::Word)
-        .map(|_| Inline::Text("word".to_string()))
-        .parse_next(input)
+/// Parse the inline content of one line (text, formatting marks, links)
+///
+/// Formatting marks are paired by [`format_inlines`], which keeps unpaired
+/// and overlapping marks as literal text. The content is sliced from the
+/// source, from the first to the last inline token of the line. Past the
+/// first token, `=` runs are text too (`a = b`, `[text,role=x]`). A `+` line
+/// that no list item takes as a continuation is text as well.
+fn inlines(input: &mut Input<'_>) -> winnow::Result<Vec<Inline>> {
+    if let Some((Token::ListContinuation, _)) = input.input.first() {
+        let (_, span) = any.parse_next(input)?;
+        return Ok(vec![Inline::Text(input.state.text[span].to_string())]);
+    }
+    let is_inline = |t: &Token| {
+        matches!(
+            t,
+            Token::Word
+                | Token::Punctuation
+                | Token::BoldDelimiter
+                | Token::ItalicDelimiter
+                | Token::MonospaceDelimiter
+                | Token::HighlightDelimiter
+                | Token::SuperscriptDelimiter
+                | Token::SubscriptDelimiter
+                | Token::Url
+        )
+    };
+    let first = any
+        .verify(|(t, _): &Spanned| is_inline(t))
+        .parse_next(input)?;
+    let rest: Vec<Spanned> = repeat(
+        0..,
+        any.verify(|(t, _): &Spanned| is_inline(t) || heading_token(t)),
+    )
+    .parse_next(input)?;
+    let mut tokens = Vec::with_capacity(rest.len() + 1);
+    tokens.push(first);
+    tokens.extend(rest);
+
+    Ok(format_inlines_with(
+        input.state.text,
+        &tokens,
+        input.state.subs,
+    ))
 }
 
-/// Parse bold formatting: ** content **
-fn bold(input: &mut Input<'_>) -> winnow::Result<Inline> {
-    delimited(
-        token(Token::BoldDelimiter),
-        repeat::<_, _, Vec<Inline>, _, _>(1.., inline),
-        token(Token::BoldDelimiter),
+/// True for the `=` run tokens (level 1-6 heading markers)
+fn heading_token(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Heading1
+            | Token::Heading2
+            | Token::Heading3
+            | Token::Heading4
+            | Token::Heading5
+            | Token::Heading6
     )
-    .map(Inline::Bold)
-    .parse_next(input)
 }
 
-/// Parse italic formatting: _ content _
-fn italic(input: &mut Input<'_>) -> winnow::Result<Inline> {
-    delimited(
-        token(Token::ItalicDelimiter),
-        repeat::<_, _, Vec<Inline>, _, _>(1.., inline),
-        token(Token::ItalicDelimiter),
-    )
-    .map(Inline::Italic)
-    .parse_next(input)
+/// Format inline `tokens` that may span lines, ignoring leading and trailing
+/// line breaks
+fn trimmed_inlines(source: &Source<'_>, tokens: &[Spanned]) -> Vec<Inline> {
+    let is_content = |(t, _): &Spanned| !matches!(t, Token::Newline | Token::BlankLine);
+    match (
+        tokens.iter().position(is_content),
+        tokens.iter().rposition(is_content),
+    ) {
+        (Some(first), Some(last)) => {
+            format_inlines_with(source.text, &tokens[first..=last], source.subs)
+        }
+        _ => Vec::new(),
+    }
 }
 
 /// Helper: Match a specific token
 fn token<'a>(expected: Token) -> impl Parser<Input<'a>, Token, winnow::error::ContextError> {
-    any.verify(move |t: &Token| *t == expected)
+    any.verify_map(move |(t, _): Spanned| (t == expected).then_some(t))
+}
+
+/// Source text from the start of the first to the end of the last token
+fn source_between<'a>(input: &Input<'a>, tokens: &[Spanned]) -> &'a str {
+    match (tokens.first(), tokens.last()) {
+        (Some((_, first)), Some((_, last))) => &input.state.text[first.start..last.end],
+        _ => "",
+    }
+}
+
+/// Whether `tokens` start a line, with no whitespace before them
+///
+/// Block attribute, anchor and title lines must; an indented one is text.
+fn starts_line(input: &Input<'_>, tokens: &[Spanned]) -> bool {
+    tokens.first().is_some_and(|(_, span)| {
+        input.state.text[..span.start]
+            .chars()
+            .next_back()
+            .is_none_or(|c| c == '\n')
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::token::Token;
+    use crate::limits::DEFAULT_MAX_NESTING_DEPTH;
+    use crate::token::lex_with_spans;
+
+    /// Lex `source` with spans and parse it
+    fn parse(source: &str) -> Result<Document, ParseError> {
+        parse_document_winnow(source, &lex_with_spans(source))
+    }
+
+    fn text(s: &str) -> Inline {
+        Inline::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_empty_document() {
+        let result = parse("");
+        assert!(result.is_ok());
+        let doc = result.unwrap();
+        assert_eq!(doc.blocks.len(), 0);
+    }
+
+    #[test]
+    fn test_simple_paragraph() {
+        let doc = parse("Hello world").unwrap();
+        assert_eq!(doc.blocks.len(), 1);
+
+        if let Block::Paragraph { content, .. } = &doc.blocks[0] {
+            assert_eq!(content, &vec![text("Hello world")]);
+        } else {
+            panic!("Expected Paragraph");
+        }
+    }
+
+    #[test]
+    fn test_punctuation_is_kept_verbatim() {
+        let doc = parse("Don't panic, e.g. (really)!").unwrap();
+        if let Block::Paragraph { content, .. } = &doc.blocks[0] {
+            assert_eq!(content, &vec![text("Don't panic, e.g. (really)!")]);
+        } else {
+            panic!("Expected Paragraph");
+        }
+    }
+
+    #[test]
+    fn test_bold_text() {
+        let doc = parse("**strong**").unwrap();
+        assert_eq!(doc.blocks.len(), 1);
+
+        if let Block::Paragraph { content, .. } = &doc.blocks[0] {
+            assert_eq!(content, &vec![Inline::Bold(vec![text("strong")])]);
+        } else {
+            panic!("Expected Paragraph");
+        }
+    }
+
+    #[test]
+    fn test_italic_text() {
+        let doc = parse("_emphasis_").unwrap();
+        assert_eq!(doc.blocks.len(), 1);
+
+        if let Block::Paragraph { content, .. } = &doc.blocks[0] {
+            assert_eq!(content, &vec![Inline::Italic(vec![text("emphasis")])]);
+        } else {
+            panic!("Expected Paragraph");
+        }
+    }
+
+    #[test]
+    fn test_monospace_text() {
+        let doc = parse("Run `cargo *test*` or ``x``s.").unwrap();
+
+        if let Block::Paragraph { content, .. } = &doc.blocks[0] {
+            assert_eq!(
+                content,
+                &vec![
+                    text("Run "),
+                    Inline::Monospace(vec![text("cargo "), Inline::Bold(vec![text("test")])]),
+                    text(" or "),
+                    Inline::Monospace(vec![text("x")]),
+                    text("s."),
+                ]
+            );
+        } else {
+            panic!("Expected Paragraph");
+        }
+    }
+
+    #[test]
+    fn test_highlight_superscript_subscript() {
+        let doc = parse("A #key# point: H~2~O, E=mc^2^ and ~/a b~.").unwrap();
+
+        if let Block::Paragraph { content, .. } = &doc.blocks[0] {
+            assert_eq!(
+                content,
+                &vec![
+                    text("A "),
+                    Inline::Highlight(vec![text("key")]),
+                    text(" point: H"),
+                    Inline::Subscript(vec![text("2")]),
+                    text("O, E=mc"),
+                    Inline::Superscript(vec![text("2")]),
+                    text(" and ~/a b~."),
+                ]
+            );
+        } else {
+            panic!("Expected Paragraph");
+        }
+    }
+
+    #[test]
+    fn test_mixed_formatting() {
+        let doc = parse("This is **bold** and _italic_.").unwrap();
+        assert_eq!(doc.blocks.len(), 1);
+
+        if let Block::Paragraph { content, .. } = &doc.blocks[0] {
+            assert_eq!(
+                content,
+                &vec![
+                    text("This is "),
+                    Inline::Bold(vec![text("bold")]),
+                    text(" and "),
+                    Inline::Italic(vec![text("italic")]),
+                    text("."),
+                ]
+            );
+        } else {
+            panic!("Expected Paragraph");
+        }
+    }
+
+    #[test]
+    fn test_links() {
+        let doc = parse("* See https://example.com[the *docs*].").unwrap();
+        let Block::UnorderedList { items, .. } = &doc.blocks[0] else {
+            panic!("Expected UnorderedList");
+        };
+        assert_eq!(
+            items[0].content,
+            vec![
+                text("See "),
+                Inline::Link {
+                    target: "https://example.com".to_string(),
+                    text: vec![text("the "), Inline::Bold(vec![text("docs")])],
+                    attributes: Attributes::new(),
+                },
+                text("."),
+            ]
+        );
+
+        let doc = parse("https://example.com\n").unwrap();
+        let Block::Paragraph { content, .. } = &doc.blocks[0] else {
+            panic!("Expected Paragraph");
+        };
+        assert!(content[0].is_link());
+    }
+
+    #[test]
+    fn test_equals_signs_in_text() {
+        let doc = parse("x=1 and a == b\n").unwrap();
+        assert_eq!(
+            doc.blocks,
+            vec![Block::Paragraph {
+                content: vec![text("x=1 and a == b")],
+                metadata: BlockMetadata::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_simple_heading() {
+        let doc = parse("= Title\n").unwrap();
+        assert!(doc.blocks.is_empty());
+        assert_eq!(doc.header.unwrap().title.as_deref(), Some("Title"));
+        assert_eq!(doc.attributes.get("doctitle"), Some("Title"));
+
+        // Past the header, `=` starts a level 1 section (a book part)
+        let doc = parse("Intro.\n\n= Part\n").unwrap();
+        assert!(doc.header.is_none());
+        if let Block::Section {
+            level,
+            title,
+            content,
+            ..
+        } = &doc.blocks[1]
+        {
+            assert_eq!(*level, 1);
+            assert_eq!(title, &vec![text("Part")]);
+            assert_eq!(content.len(), 0);
+        } else {
+            panic!("Expected Section");
+        }
+    }
+
+    #[test]
+    fn test_syntax_error_points_at_token() {
+        let err = parse("Intro.\n\n== \n").unwrap_err();
+        assert_eq!(
+            err,
+            ParseError::UnexpectedToken {
+                span: 8..10,
+                expected: "a block".to_string(),
+                got: "level 2 heading (==)".to_string(),
+            }
+        );
+        assert_eq!(err.span(), Some(8..10));
+    }
+
+    #[test]
+    fn test_recovery_resumes_at_block_boundaries() {
+        let source = "Intro.\n\n== \nlost\n== Part\n\nBody.\n\n== \n\nEnd.\n";
+        let tokens = lex_with_spans(source);
+        let mut recovery = ErrorRecovery::collecting();
+        let doc = parse_tokens_with(
+            source,
+            &tokens,
+            &Limits::new(),
+            &SubstitutionDefaults::new(),
+            false,
+            &mut recovery,
+        );
+
+        let spans: Vec<_> = recovery
+            .errors()
+            .iter()
+            .filter_map(ParseError::span)
+            .collect();
+        assert_eq!(spans, vec![8..10, 33..35]);
+        // The error ends the section; parsing resumes at the top level
+        assert_eq!(doc.blocks.len(), 3);
+        assert_eq!(doc.blocks[1].title_text().as_deref(), Some("Part"));
+        let Block::Section { content, .. } = &doc.blocks[1] else {
+            panic!("expected a section, got {:?}", doc.blocks[1]);
+        };
+        assert_eq!(content.len(), 1);
+        assert!(matches!(&doc.blocks[2], Block::Paragraph { .. }));
+
+        // Fail-fast parsing reports the first of them
+        assert_eq!(parse(source).unwrap_err(), recovery.errors()[0]);
+    }
+
+    #[test]
+    fn test_heading_title_is_full_line() {
+        let doc = parse("==  Hello, AsciiDoc **world**!  \n").unwrap();
+        if let Block::Section { title, .. } = &doc.blocks[0] {
+            assert_eq!(
+                title,
+                &vec![
+                    text("Hello, AsciiDoc "),
+                    Inline::Bold(vec![text("world")]),
+                    text("!")
+                ]
+            );
+        } else {
+            panic!("Expected Section");
+        }
+        assert_eq!(
+            doc.blocks[0].title_text().as_deref(),
+            Some("Hello, AsciiDoc world!")
+        );
+    }
+
+    #[test]
+    fn test_section_with_paragraph() {
+        let doc = parse("== Title\n\nword word").unwrap();
+        assert_eq!(doc.blocks.len(), 1);
+
+        if let Block::Section { level, content, .. } = &doc.blocks[0] {
+            assert_eq!(*level, 2);
+            assert_eq!(content.len(), 1);
+
+            // Check nested paragraph
+            if let Block::Paragraph {
+                content: para_content,
+                ..
+            } = &content[0]
+            {
+                assert_eq!(para_content, &vec![text("word word")]);
+            } else {
+                panic!("Expected nested Paragraph");
+            }
+        } else {
+            panic!("Expected Section");
+        }
+    }
+
+    #[test]
+    fn test_multiple_blocks() {
+        let doc = parse("first\n\n  second").unwrap();
+        assert_eq!(doc.blocks.len(), 2);
+
+        assert_eq!(
+            doc.blocks[0],
+            Block::Paragraph {
+                content: vec![text("first")],
+                metadata: BlockMetadata::default()
+            }
+        );
+        assert_eq!(
+            doc.blocks[1],
+            Block::Paragraph {
+                content: vec![text("second")],
+                metadata: BlockMetadata::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_nested_sections() {
+        let doc = parse("== H2\n\n=== H3\n").unwrap();
+        assert_eq!(doc.blocks.len(), 1);
+
+        if let Block::Section { level, content, .. } = &doc.blocks[0] {
+            assert_eq!(*level, 2);
+            assert_eq!(content.len(), 1);
+
+            // Check nested section
+            if let Block::Section {
+                level: nested_level,
+                title,
+                ..
+            } = &content[0]
+            {
+                assert_eq!(*nested_level, 3);
+                assert_eq!(title, &vec![text("H3")]);
+            } else {
+                panic!("Expected nested Section");
+            }
+        } else {
+            panic!("Expected Section");
+        }
+    }
 
     #[test]
-    fn test_empty_document() {
-        let tokens = vec![];
-        let result = parse_document_winnow(&tokens);
-        assert!(result.is_ok());
-        let doc = result.unwrap();
-        assert_eq!(doc.blocks.len(), 0);
+    fn test_sibling_sections() {
+        // Each section ends at the next heading at its level or above
+        fn outline(blocks: &[Block]) -> String {
+            blocks
+                .iter()
+                .map(|block| match block {
+                    Block::Section {
+                        level,
+                        title,
+                        content,
+                        ..
+                    } => format!(
+                        "{level}:{}({})",
+                        crate::ast::plain_text(title),
+                        outline(content)
+                    ),
+                    _ => "p".to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+        let doc = parse(
+            "== A\n\na\n\n== B\n\n=== B1\n\nb\n\n=== B2\n\n== C\n\n\
+             ==== C1\n\n[#d]\n== D\n\n=== D1\n\n.T\n[[e]]\n== E\n\n= P\n\n== F\n\n[verse]\n=\n",
+        )
+        .unwrap();
+        assert_eq!(
+            outline(&doc.blocks),
+            "2:A(p) 2:B(3:B1(p) 3:B2()) 2:C(4:C1()) 2:D(3:D1()) 2:E() 1:P(2:F(p))"
+        );
     }
 
     #[test]
-    fn test_simple_paragraph() {
-        // "word word"
-        let tokens = vec![Token::Word, Token::Word];
-        let result = parse_document_winnow(&tokens);
-        assert!(result.is_ok());
+    fn test_complex_document() {
+        let doc = parse("= Title\n\nword **bold** _italic_\n\n== Section\n\nword").unwrap();
+        assert_eq!(doc.header.unwrap().title.as_deref(), Some("Title"));
+        assert_eq!(doc.blocks.len(), 2); // paragraph + section
 
-        let doc = result.unwrap();
-        assert_eq!(doc.blocks.len(), 1);
+        // First block should be paragraph
+        assert!(matches!(doc.blocks[0], Block::Paragraph { .. }));
 
-        if let Block::Paragraph { content } = &doc.blocks[0] {
-            assert_eq!(content.len(), 2);
-            assert!(content[0].is_text());
-            assert!(content[1].is_text());
+        // Second block should be section
+        if let Block::Section { level, content, .. } = &doc.blocks[1] {
+            assert_eq!(*level, 2);
+            assert_eq!(content.len(), 1); // one paragraph in section
         } else {
-            panic!("Expected Paragraph");
+            panic!("Expected Section");
         }
     }
 
     #[test]
-    fn test_bold_text() {
-        // "**word**"
-        let tokens = vec![Token::BoldDelimiter, Token::Word, Token::BoldDelimiter];
-        let result = parse_document_winnow(&tokens);
-        assert!(result.is_ok());
-
-        let doc = result.unwrap();
+    fn test_nested_formatting() {
+        let doc = parse("**word _italic_**").unwrap();
         assert_eq!(doc.blocks.len(), 1);
 
-        if let Block::Paragraph { content } = &doc.blocks[0] {
-            assert_eq!(content.len(), 1);
-            assert!(content[0].is_bold());
+        if let Block::Paragraph { content, .. } = &doc.blocks[0] {
+            assert_eq!(
+                content,
+                &vec![Inline::Bold(vec![
+                    text("word "),
+                    Inline::Italic(vec![text("italic")]),
+                ])]
+            );
         } else {
             panic!("Expected Paragraph");
         }
     }
 
     #[test]
-    fn test_italic_text() {
-        // "_word_"
-        let tokens = vec![Token::ItalicDelimiter, Token::Word, Token::ItalicDelimiter];
-        let result = parse_document_winnow(&tokens);
-        assert!(result.is_ok());
+    fn test_paragraph_with_newline() {
+        let doc = parse("word word  \n").unwrap();
+        assert_eq!(doc.blocks.len(), 1);
+        assert_eq!(
+            doc.blocks[0],
+            Block::Paragraph {
+                content: vec![text("word word")],
+                metadata: BlockMetadata::default()
+            }
+        );
+    }
 
-        let doc = result.unwrap();
+    #[test]
+    fn test_unicode_text() {
+        let doc = parse("Café 東京 😀").unwrap();
+        assert_eq!(
+            doc.blocks[0],
+            Block::Paragraph {
+                content: vec![text("Café 東京 😀")],
+                metadata: BlockMetadata::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_multiple_headings() {
+        let doc = parse("= H1\n== H2\n=== H3\n").unwrap();
+        // H1 is the document title, H2 contains H3 (nested structure)
+        assert_eq!(doc.header.unwrap().title.as_deref(), Some("H1"));
         assert_eq!(doc.blocks.len(), 1);
 
-        if let Block::Paragraph { content } = &doc.blocks[0] {
-            assert_eq!(content.len(), 1);
-            assert!(content[0].is_italic());
+        if let Block::Section {
+            level,
+            title,
+            content,
+            ..
+        } = &doc.blocks[0]
+        {
+            assert_eq!(*level, 2);
+            assert_eq!(title, &vec![text("H2")]);
+            assert!(!content.is_empty());
         } else {
-            panic!("Expected Paragraph");
+            panic!("Expected Section");
         }
     }
 
     #[test]
-    fn test_mixed_formatting() {
-        // "word **word** word _word_"
-        let tokens = vec![
-            Token::Word,
-            Token::BoldDelimiter,
-            Token::Word,
-            Token::BoldDelimiter,
-            Token::Word,
-            Token::ItalicDelimiter,
-            Token::Word,
-            Token::ItalicDelimiter,
-        ];
-        let result = parse_document_winnow(&tokens);
-        assert!(result.is_ok());
+    fn test_document_header() {
+        let doc = parse(
+            "= The Guide\nAda King Lovelace <ada@example.com>; Charles Babbage\n\
+             v1.2, 2024-05-01: Draft\n:toc:\n:product: Engine\n:full: {product} {revnumber}\n\
+             :sectnums!:\n\nBody.\n",
+        )
+        .unwrap();
+        let header = doc.header.unwrap();
+        assert_eq!(header.title.as_deref(), Some("The Guide"));
+        assert_eq!(
+            header.authors,
+            vec![
+                Author {
+                    name: "Ada King Lovelace".to_string(),
+                    email: Some("ada@example.com".to_string()),
+                },
+                Author {
+                    name: "Charles Babbage".to_string(),
+                    email: None,
+                },
+            ]
+        );
+        assert_eq!(
+            header.revision,
+            Some(Revision {
+                number: Some("1.2".to_string()),
+                date: Some("2024-05-01".to_string()),
+                remark: Some("Draft".to_string()),
+            })
+        );
+        assert_eq!(header.attributes.len(), 4);
+        assert_eq!(header.attributes[3].value, None);
+
+        let attrs = &doc.attributes;
+        assert_eq!(attrs.get("author"), Some("Ada King Lovelace"));
+        assert_eq!(attrs.get("firstname"), Some("Ada"));
+        assert_eq!(attrs.get("middlename"), Some("King"));
+        assert_eq!(attrs.get("lastname"), Some("Lovelace"));
+        assert_eq!(attrs.get("authorinitials"), Some("AKL"));
+        assert_eq!(attrs.get("email"), Some("ada@example.com"));
+        assert_eq!(
+            attrs.get("authors"),
+            Some("Ada King Lovelace, Charles Babbage")
+        );
+        assert_eq!(attrs.get("toc"), Some(""));
+        assert_eq!(attrs.get("full"), Some("Engine 1.2"));
+        assert!(!attrs.contains("sectnums"));
+
+        assert_eq!(
+            doc.blocks,
+            vec![Block::Paragraph {
+                content: vec![text("Body.")],
+                metadata: BlockMetadata::default()
+            }]
+        );
+    }
 
-        let doc = result.unwrap();
+    #[test]
+    fn test_header_without_title() {
+        let doc = parse(":toc: left\nIntro.\n").unwrap();
+        let header = doc.header.unwrap();
+        assert_eq!(header.title, None);
+        assert_eq!(doc.attributes.get("toc"), Some("left"));
         assert_eq!(doc.blocks.len(), 1);
 
-        if let Block::Paragraph { content } = &doc.blocks[0] {
-            assert_eq!(content.len(), 4);
-            assert!(content[0].is_text());
-            assert!(content[1].is_bold());
-            assert!(content[2].is_text());
-            assert!(content[3].is_italic());
-        } else {
-            panic!("Expected Paragraph");
-        }
+        // A blank line ends the header: later entries are body text
+        let doc = parse("= T\n\n:toc:\n").unwrap();
+        assert!(doc.header.unwrap().attributes.is_empty());
+        assert!(!doc.attributes.contains("toc"));
     }
 
     #[test]
-    fn test_simple_heading() {
-        // "= Title\n"
-        let tokens = vec![Token::Heading1, Token::Word, Token::Newline];
-        let result = parse_document_winnow(&tokens);
-        assert!(result.is_ok());
+    fn test_body_attribute_entries() {
+        let doc = parse("= Title\n\nText.\n\n:late: x\n:gone!:\n:flag:\n== S\n").unwrap();
+        assert_eq!(doc.header.unwrap().attributes, vec![]);
+        let entry = |name: &str, value: Option<&str>| Block::AttributeEntry {
+            entry: AttributeEntry {
+                name: name.to_string(),
+                value: value.map(str::to_string),
+            },
+            metadata: BlockMetadata::default(),
+        };
+        assert_eq!(
+            doc.blocks[1..4],
+            [
+                entry("late", Some("x")),
+                entry("gone", None),
+                entry("flag", Some(""))
+            ]
+        );
+        assert!(matches!(doc.blocks[4], Block::Section { .. }));
+        // Not an entry: no space before the value
+        assert!(matches!(
+            parse("Text.\n\n:a:b\n").unwrap().blocks[1],
+            Block::Paragraph { .. }
+        ));
+    }
 
-        let doc = result.unwrap();
-        assert_eq!(doc.blocks.len(), 1);
+    #[test]
+    fn test_revision_line_forms() {
+        assert_eq!(
+            revision("v2.0"),
+            Revision {
+                number: Some("2.0".to_string()),
+                ..Revision::default()
+            }
+        );
+        assert_eq!(
+            revision("2024-05-01"),
+            Revision {
+                date: Some("2024-05-01".to_string()),
+                ..Revision::default()
+            }
+        );
+        assert_eq!(attribute_entry(":a:b"), None);
+        assert_eq!(attribute_entry(":bad name: x"), None);
+        assert_eq!(
+            attribute_entry(":!name:"),
+            Some(AttributeEntry {
+                name: "name".to_string(),
+                value: None,
+            })
+        );
+    }
 
-        if let Block::Section { level, content, .. } = &doc.blocks[0] {
-            assert_eq!(*level, 1);
-            assert_eq!(content.len(), 0);
-        } else {
-            panic!("Expected Section");
+    /// Items of `block`, which must be a list
+    fn items(block: &Block) -> &[ListItem] {
+        match block {
+            Block::UnorderedList { items, .. } | Block::OrderedList { items, .. } => items,
+            _ => panic!("Expected list, got {:?}", block),
         }
     }
 
     #[test]
-    fn test_section_with_paragraph() {
-        // "= Title\n\nword word"
-        let tokens = vec![
-            Token::Heading1,
-            Token::Word,
-            Token::BlankLine,
-            Token::Word,
-            Token::Word,
-        ];
-        let result = parse_document_winnow(&tokens);
-        assert!(result.is_ok());
+    fn test_unordered_list() {
+        let doc = parse("* First\n* Second **bold**\n\nText.\n\n- Dash\n").unwrap();
+        assert_eq!(
+            doc.blocks,
+            vec![
+                Block::UnorderedList {
+                    items: vec![
+                        ListItem::new(vec![text("First")]),
+                        ListItem::new(vec![text("Second "), Inline::Bold(vec![text("bold")])]),
+                    ],
+                    metadata: BlockMetadata::default()
+                },
+                Block::Paragraph {
+                    content: vec![text("Text.")],
+                    metadata: BlockMetadata::default()
+                },
+                Block::UnorderedList {
+                    items: vec![ListItem::new(vec![text("Dash")])],
+                    metadata: BlockMetadata::default()
+                },
+            ]
+        );
+    }
 
-        let doc = result.unwrap();
+    #[test]
+    fn test_ordered_list() {
+        let doc = parse(". One\n\n. Two\n").unwrap();
         assert_eq!(doc.blocks.len(), 1);
+        assert!(matches!(doc.blocks[0], Block::OrderedList { .. }));
+        assert_eq!(items(&doc.blocks[0]).len(), 2);
+    }
 
-        if let Block::Section { level, content, .. } = &doc.blocks[0] {
-            assert_eq!(*level, 1);
-            assert_eq!(content.len(), 1);
+    #[test]
+    fn test_nested_lists() {
+        let doc = parse("* a\n** a1\n*** a1x\n** a2\n* b\n. ordered\n").unwrap();
+        assert_eq!(doc.blocks.len(), 1);
+        let top = items(&doc.blocks[0]);
+        assert_eq!(top.len(), 2);
 
-            // Check nested paragraph
-            if let Block::Paragraph { content: para_content } = &content[0] {
-                assert_eq!(para_content.len(), 2);
-            } else {
-                panic!("Expected nested Paragraph");
-            }
-        } else {
-            panic!("Expected Section");
+        let nested = items(&top[0].blocks[0]);
+        assert_eq!(nested.len(), 2);
+        assert_eq!(items(&nested[0].blocks[0])[0].content, vec![text("a1x")]);
+        assert_eq!(nested[1].content, vec![text("a2")]);
+
+        // A new marker nests even when it changes the list type
+        assert!(matches!(top[1].blocks[0], Block::OrderedList { .. }));
+    }
+
+    #[test]
+    fn test_star_marker_or_bold() {
+        let para = |content| Block::Paragraph {
+            content,
+            metadata: BlockMetadata::default(),
+        };
+        let list = |items| Block::UnorderedList {
+            items,
+            metadata: BlockMetadata::default(),
+        };
+        let bold = |s| Inline::Bold(vec![text(s)]);
+        let nested = |outer: &str, inner| {
+            let mut item = ListItem::new(vec![text(outer)]);
+            item.blocks.push(list(vec![ListItem::new(inner)]));
+            list(vec![item])
+        };
+        let cases = [
+            // `*` then whitespace at line start: a marker
+            ("* item", list(vec![ListItem::new(vec![text("item")])])),
+            ("*\titem", list(vec![ListItem::new(vec![text("item")])])),
+            // `*` glued to text at line start: bold
+            ("*bold* text", para(vec![bold("bold"), text(" text")])),
+            ("**bold** text", para(vec![bold("bold"), text(" text")])),
+            // Mid-line: bold, or a literal `*`
+            (
+                "a *bold* b",
+                para(vec![text("a "), bold("bold"), text(" b")]),
+            ),
+            ("2 * 3", para(vec![text("2 * 3")])),
+            // Bold inside an item
+            (
+                "* *bold* item",
+                list(vec![ListItem::new(vec![bold("bold"), text(" item")])]),
+            ),
+            (
+                "* a * b *c*",
+                list(vec![ListItem::new(vec![text("a * b "), bold("c")])]),
+            ),
+            // `**` then whitespace: a nested marker, not bold
+            ("* a\n** b", nested("a", vec![text("b")])),
+            ("* a\n** **b** c", nested("a", vec![bold("b"), text(" c")])),
+            // A bold line after an item continues the item
+            (
+                "* a\n*b* c",
+                list(vec![ListItem::new(vec![
+                    text("a"),
+                    text("\n"),
+                    bold("b"),
+                    text(" c"),
+                ])]),
+            ),
+            // Too deep for a marker, or nothing after it: literal
+            ("****** x", para(vec![text("****** x")])),
+            ("*not closed", para(vec![text("*not closed")])),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse(source).unwrap().blocks, vec![expected], "{source:?}");
         }
     }
 
     #[test]
-    fn test_multiple_blocks() {
-        // "word\n\nword"
-        let tokens = vec![Token::Word, Token::BlankLine, Token::Word];
-        let result = parse_document_winnow(&tokens);
-        assert!(result.is_ok());
+    fn test_multiline_list_item() {
+        let doc = parse("* first line\n  second line\n* next").unwrap();
+        let items = items(&doc.blocks[0]);
+        assert_eq!(items[0].content, vec![text("first line\nsecond line")]);
+        assert_eq!(items.len(), 2);
+    }
 
-        let doc = result.unwrap();
+    #[test]
+    fn test_list_continuation() {
+        let doc = parse("* item\n+\nAttached paragraph.\n* next\n\nAfter.").unwrap();
+        assert_eq!(doc.blocks.len(), 2);
+        let list = items(&doc.blocks[0]);
+        assert_eq!(
+            list[0].blocks,
+            vec![Block::Paragraph {
+                content: vec![text("Attached paragraph.")],
+                metadata: BlockMetadata::default()
+            }]
+        );
+        assert_eq!(list[1].content, vec![text("next")]);
+        assert_eq!(
+            doc.blocks[1],
+            Block::Paragraph {
+                content: vec![text("After.")],
+                metadata: BlockMetadata::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_stray_list_continuations() {
+        let plus = || Block::Paragraph {
+            content: vec![text("+")],
+            metadata: BlockMetadata::default(),
+        };
+        assert_eq!(parse("+\n").unwrap().blocks, vec![plus()]);
+        assert_eq!(parse("Para\n\n+\n").unwrap().blocks[1], plus());
+        let doc = parse("Line one\n+\nmore\n").unwrap();
+        assert_eq!(doc.blocks.len(), 3);
+        assert_eq!(doc.blocks[1], plus());
+
+        // A continuation with nothing to attach ends the list
+        let doc = parse("* item\n+\n").unwrap();
         assert_eq!(doc.blocks.len(), 2);
+        assert!(items(&doc.blocks[0])[0].blocks.is_empty());
+        assert_eq!(doc.blocks[1], plus());
+        let doc = parse("* item\n+\n\nAfter.\n").unwrap();
+        assert_eq!(doc.blocks.len(), 3);
+        assert_eq!(doc.blocks[1], plus());
+    }
 
-        assert!(matches!(doc.blocks[0], Block::Paragraph { .. }));
+    #[test]
+    fn test_list_in_section() {
+        let doc = parse("== Steps\n\n. one\n. two\n\nDone.").unwrap();
+        let Block::Section { content, .. } = &doc.blocks[0] else {
+            panic!("Expected Section");
+        };
+        assert_eq!(content.len(), 2);
+        assert_eq!(items(&content[0]).len(), 2);
+    }
+
+    #[test]
+    fn test_nesting_depth_limit() {
+        // Delimiters of alternating kinds nest without closing each other
+        let nest = |depth: usize| ["====\n", "****\n"].repeat(depth.div_ceil(2))[..depth].concat();
+        let limits = Limits::new().max_nesting_depth(3);
+        let source = nest(3) + "Deep.\n";
+        assert!(
+            parse_document_winnow_with_limits(&source, &lex_with_spans(&source), &limits).is_ok()
+        );
+        let source = nest(4) + "Deep.\n";
+        assert_eq!(
+            parse_document_winnow_with_limits(&source, &lex_with_spans(&source), &limits),
+            Err(ParseError::NestingTooDeep {
+                span: 15..19,
+                max: 3
+            })
+        );
+        let source = "* a\n** b\n*** c\n**** d\n";
+        assert_eq!(
+            parse_document_winnow_with_limits(source, &lex_with_spans(source), &limits),
+            Err(ParseError::NestingTooDeep {
+                span: 15..19,
+                max: 3
+            })
+        );
+
+        // The default limit stops nesting that would overflow the stack
+        let source = nest(DEFAULT_MAX_NESTING_DEPTH) + "Deep.\n";
+        assert!(parse(&source).is_ok());
+        let source = nest(2000);
+        let err = parse(&source).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::NestingTooDeep {
+                max: DEFAULT_MAX_NESTING_DEPTH,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_list_item_limit() {
+        let source = "* a\n** a1\n** a2\n* b\n* c";
+        let limits = Limits::new().max_list_items(1);
+        let doc =
+            parse_document_winnow_with_limits(source, &lex_with_spans(source), &limits).unwrap();
+
+        let top = items(&doc.blocks[0]);
+        assert_eq!(top.len(), 1);
+        assert_eq!(items(&top[0].blocks[0]).len(), 1);
+        assert_eq!(
+            doc.truncations,
+            vec![
+                Truncation {
+                    kind: StructureKind::List,
+                    offset: 4,
+                    kept: 1,
+                    dropped: 1
+                },
+                Truncation {
+                    kind: StructureKind::List,
+                    offset: 0,
+                    kept: 1,
+                    dropped: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_description_list() {
+        let doc =
+            parse("CPU:: The brain\nRAM ::\n  Short-term\n  memory\n\nDisk:: Storage").unwrap();
+        assert_eq!(
+            doc.blocks,
+            vec![Block::DescriptionList {
+                items: vec![
+                    DescriptionItem {
+                        term: vec![text("CPU")],
+                        description: vec![text("The brain")],
+                    },
+                    DescriptionItem {
+                        term: vec![text("RAM")],
+                        description: vec![text("Short-term\nmemory")],
+                    },
+                    DescriptionItem {
+                        term: vec![text("Disk")],
+                        description: vec![text("Storage")],
+                    },
+                ],
+                separator: "::".to_string(),
+                metadata: BlockMetadata::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_description_list_separators() {
+        // A different separator starts a new list
+        let doc = parse("a:: one\nb;; two\n").unwrap();
+        assert_eq!(doc.blocks.len(), 2);
+        assert!(matches!(
+            &doc.blocks[1],
+            Block::DescriptionList { separator, .. } if separator == ";;"
+        ));
+
+        // A term without description, followed by a paragraph
+        let doc = parse("**Term**::\n\nText.").unwrap();
+        assert_eq!(
+            doc.blocks[0],
+            Block::DescriptionList {
+                items: vec![DescriptionItem {
+                    term: vec![Inline::Bold(vec![text("Term")])],
+                    description: vec![],
+                }],
+                separator: "::".to_string(),
+                metadata: BlockMetadata::default()
+            }
+        );
         assert!(matches!(doc.blocks[1], Block::Paragraph { .. }));
     }
 
     #[test]
-    fn test_nested_sections() {
-        // "= H1\n\n== H2\n"
-        let tokens = vec![
-            Token::Heading1,
-            Token::Word,
-            Token::BlankLine,
-            Token::Heading2,
-            Token::Word,
-            Token::Newline,
-        ];
-        let result = parse_document_winnow(&tokens);
-        assert!(result.is_ok());
+    fn test_listing_block() {
+        let doc =
+            parse("[source,rust]\n----\nfn main() {\n\n    **x**\n}\n----\n\nAfter.").unwrap();
+        let Block::Listing {
+            language,
+            content,
+            attributes,
+            ..
+        } = &doc.blocks[0]
+        else {
+            panic!("Expected Listing, got {:?}", doc.blocks[0]);
+        };
+        assert_eq!(language.as_deref(), Some("rust"));
+        assert_eq!(content, "fn main() {\n\n    **x**\n}");
+        assert_eq!(attributes.get("style"), Some("source"));
+        assert_eq!(
+            doc.blocks[1],
+            Block::Paragraph {
+                content: vec![text("After.")],
+                metadata: BlockMetadata::default()
+            }
+        );
+    }
 
-        let doc = result.unwrap();
-        assert_eq!(doc.blocks.len(), 1);
+    #[test]
+    fn test_literal_block() {
+        let doc = parse("....\nliteral   _text_\n....").unwrap();
+        let Block::Listing {
+            language,
+            content,
+            attributes,
+            ..
+        } = &doc.blocks[0]
+        else {
+            panic!("Expected Listing");
+        };
+        assert_eq!(*language, None);
+        assert_eq!(content, "literal   _text_");
+        assert_eq!(attributes.get("style"), Some("literal"));
+    }
 
-        if let Block::Section { level, content, .. } = &doc.blocks[0] {
-            assert_eq!(*level, 1);
-            assert_eq!(content.len(), 1);
+    #[test]
+    fn test_block_metadata() {
+        let doc = parse(
+            "[.lead#intro%hardbreaks]\nA paragraph.\n\n[,ruby,role=x]\n----\nputs 1\n----\n\n[.steps]\n* a\n\nPlain.\n\n[.orphan]",
+        )
+        .unwrap();
+        let paragraph = doc.blocks[0].metadata();
+        assert_eq!(paragraph.id.as_deref(), Some("intro"));
+        assert_eq!(paragraph.roles, vec!["lead"]);
+        assert!(paragraph.has_option("hardbreaks"));
+        assert!(paragraph.positional.is_empty());
+        assert_eq!(
+            doc.blocks[0].anchor().map(|anchor| anchor.id),
+            Some("intro".to_string())
+        );
+
+        let listing = doc.blocks[1].metadata();
+        assert_eq!(listing.positional, vec!["", "ruby"]);
+        assert_eq!(listing.style(), None);
+        assert_eq!(listing.named.get("role").map(String::as_str), Some("x"));
+
+        assert!(matches!(&doc.blocks[2], Block::UnorderedList { .. }));
+        assert_eq!(doc.blocks[2].roles(), vec!["steps"]);
+        assert!(doc.blocks[3].metadata().is_empty());
+        // A line with no block after it is text
+        assert_eq!(
+            doc.blocks[4],
+            Block::Paragraph {
+                content: vec![text("[.orphan]")],
+                metadata: BlockMetadata::default()
+            }
+        );
+    }
 
-            // Check nested section
-            if let Block::Section {
-                level: nested_level,
-                ..
-            } = &content[0]
-            {
-                assert_eq!(*nested_level, 2);
-            } else {
-                panic!("Expected nested Section");
+    #[test]
+    fn test_attribute_line_without_block() {
+        // Whatever the style, a line with no block after it is text
+        for line in ["[source]", "[.lead]", "[quote]", "[verse,Blake]", "[#id]"] {
+            for source in [format!("{line}\n"), format!("Para\n\n{line}\n")] {
+                let doc = parse(&source).unwrap();
+                assert_eq!(
+                    doc.blocks.last(),
+                    Some(&Block::Paragraph {
+                        content: vec![text(line)],
+                        metadata: BlockMetadata::default()
+                    }),
+                    "{source:?}"
+                );
             }
-        } else {
-            panic!("Expected Section");
         }
     }
 
     #[test]
-    fn test_complex_document() {
-        // "= Title\n\nword **bold** _italic_\n\n== Section\n\nword"
-        let tokens = vec![
-            Token::Heading1,
-            Token::Word,
-            Token::BlankLine,
-            Token::Word,
-            Token::BoldDelimiter,
-            Token::Word,
-            Token::BoldDelimiter,
-            Token::ItalicDelimiter,
-            Token::Word,
-            Token::ItalicDelimiter,
-            Token::BlankLine,
-            Token::Heading2,
-            Token::Word,
-            Token::BlankLine,
-            Token::Word,
-        ];
-        let result = parse_document_winnow(&tokens);
-        assert!(result.is_ok());
+    fn test_block_titles() {
+        let doc = parse(
+            ".Main *loop*\n[#main,ruby]\n----\nloop\n----\n\n.Totals\n|===\n|1\n|===\n\n.Not a title\n\n. item\n\n..x\n",
+        )
+        .unwrap();
+        let listing = doc.blocks[0].metadata();
+        assert_eq!(
+            listing.title,
+            Some(vec![text("Main "), Inline::Bold(vec![text("loop")])])
+        );
+        assert_eq!(listing.id.as_deref(), Some("main"));
+        assert!(matches!(&doc.blocks[0], Block::Listing { .. }));
+        assert!(matches!(&doc.blocks[1], Block::Table { .. }));
+        assert_eq!(doc.blocks[1].metadata().title, Some(vec![text("Totals")]));
+        // Without a block after it, the line is a paragraph
+        assert_eq!(
+            doc.blocks[2],
+            Block::Paragraph {
+                content: vec![text(".Not a title")],
+                metadata: BlockMetadata::default()
+            }
+        );
+        assert!(matches!(&doc.blocks[3], Block::OrderedList { .. }));
+        assert!(doc.blocks[4].metadata().title.is_none());
+    }
 
-        let doc = result.unwrap();
-        assert_eq!(doc.blocks.len(), 1);
+    #[test]
+    fn test_passthrough_blocks() {
+        let doc = parse("++++\n<b>*raw*</b>\n\n+\n++++\n\n[backend=LaTeX]\n++++\n\\LaTeX\n++++\n")
+            .unwrap();
+        assert_eq!(
+            doc.blocks[0],
+            Block::Passthrough {
+                content: "<b>*raw*</b>\n\n+".to_string(),
+                backend: "html".to_string(),
+                metadata: BlockMetadata::default(),
+            }
+        );
+        assert_eq!(doc.blocks[1].passthrough_for("html"), None);
+        assert_eq!(doc.blocks[1].passthrough_for("latex"), Some("\\LaTeX"));
+        assert_eq!(doc.blocks.len(), 2);
+    }
 
-        if let Block::Section { level, content, .. } = &doc.blocks[0] {
-            assert_eq!(*level, 1);
-            assert_eq!(content.len(), 2); // paragraph + nested section
+    #[test]
+    fn test_comments() {
+        let source = "* a\n// between\n* b\n\n////\n* not a list\n////\nText\n";
+        let doc = parse_tokens(
+            source,
+            &lex_with_spans(source),
+            &Limits::new(),
+            &SubstitutionDefaults::new(),
+            true,
+        )
+        .unwrap();
+        assert_eq!(doc.blocks.len(), 5);
+        assert_eq!(
+            doc.blocks[1],
+            Block::Comment {
+                content: " between".to_string(),
+                delimited: false,
+                metadata: BlockMetadata::default()
+            }
+        );
+        assert!(matches!(&doc.blocks[2], Block::UnorderedList { .. }));
+        assert_eq!(
+            doc.blocks[3],
+            Block::Comment {
+                content: "* not a list".to_string(),
+                delimited: true,
+                metadata: BlockMetadata::default()
+            }
+        );
 
-            // First nested block should be paragraph
-            assert!(matches!(content[0], Block::Paragraph { .. }));
+        // Dropped by default, but the lists stay separate
+        let doc = parse(source).unwrap();
+        assert_eq!(doc.blocks.len(), 3);
+    }
 
-            // Second nested block should be section
-            if let Block::Section {
-                level: nested_level,
-                content: nested_content,
-                ..
-            } = &content[1]
-            {
-                assert_eq!(*nested_level, 2);
-                assert_eq!(nested_content.len(), 1); // one paragraph in subsection
-            } else {
-                panic!("Expected nested Section");
+    #[test]
+    fn test_comments_in_header() {
+        let doc = parse("// leading\n= Title\n// note\n:a: 1\n\nText").unwrap();
+        let header = doc.header.unwrap();
+        assert_eq!(header.title.as_deref(), Some("Title"));
+        assert_eq!(header.attributes.len(), 1);
+        assert_eq!(doc.blocks.len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_blocks() {
+        let source = "video::intro.mp4[width=640]\n\n[.wide]\ntoc::[]\n\n\
+                      ```ruby\nputs 1\n\n* x\n```\n\n,===\na,b\n,===\n\n\
+                      image::a.png[]\n\nSee http://x[]\n";
+        let doc = parse(source).unwrap();
+        let raws: Vec<&str> = doc
+            .blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Unknown { raw, span, .. } => {
+                    assert_eq!(&source[span.clone()], raw);
+                    Some(raw.as_str())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            raws,
+            [
+                "video::intro.mp4[width=640]",
+                "toc::[]",
+                "```ruby\nputs 1\n\n* x\n```",
+                ",===\na,b\n,==="
+            ]
+        );
+        assert_eq!(doc.blocks[1].metadata().roles, ["wide"]);
+        assert!(matches!(&doc.blocks[4], Block::Image { .. }));
+        assert!(matches!(&doc.blocks[5], Block::Paragraph { .. }));
+        assert_eq!(doc.blocks.len(), 6);
+    }
+
+    #[test]
+    fn test_unclosed_unknown_block() {
+        let doc = parse("Text\n\n```\n== Not a heading\n\n").unwrap();
+        assert_eq!(
+            doc.blocks[1],
+            Block::Unknown {
+                raw: "```\n== Not a heading".to_string(),
+                span: 6..26,
+                metadata: BlockMetadata::default(),
             }
-        } else {
+        );
+        assert_eq!(doc.blocks.len(), 2);
+
+        // The listing delimiter inside the fence takes the rest of the
+        // document as verbatim content, which stays part of the block
+        let source = "```\n----\n```\n\nText\n";
+        let doc = parse(source).unwrap();
+        assert!(
+            matches!(&doc.blocks[..], [Block::Unknown { raw, .. }] if raw == source.trim_end())
+        );
+    }
+
+    #[test]
+    fn test_unclosed_listing_block() {
+        let doc = parse("== S\n\n----\nrest\n\n== Not a heading").unwrap();
+        let Block::Section { content, .. } = &doc.blocks[0] else {
             panic!("Expected Section");
-        }
+        };
+        assert!(matches!(
+            &content[0],
+            Block::Listing { content, .. } if content == "rest\n\n== Not a heading"
+        ));
+    }
+
+    /// Cell texts of each row, for compact assertions
+    fn row_texts(rows: &[TableRow]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| {
+                row.cells
+                    .iter()
+                    .map(|cell| crate::ast::plain_text(&cell.content))
+                    .collect()
+            })
+            .collect()
     }
 
     #[test]
-    fn test_nested_formatting() {
-        // "**word _italic_**"
-        let tokens = vec![
-            Token::BoldDelimiter,
-            Token::Word,
-            Token::ItalicDelimiter,
-            Token::Word,
-            Token::ItalicDelimiter,
-            Token::BoldDelimiter,
-        ];
-        let result = parse_document_winnow(&tokens);
-        assert!(result.is_ok());
+    fn test_table_with_implicit_header() {
+        let doc = parse("|===\n|Name |Role\n\n|Ada |**Math**\n|Alan |CS\n|===\n\nAfter.").unwrap();
+        let Block::Table {
+            columns,
+            header,
+            rows,
+            ..
+        } = &doc.blocks[0]
+        else {
+            panic!("Expected Table, got {:?}", doc.blocks[0]);
+        };
+        assert_eq!(columns, &vec![ColumnSpec { width: 1 }; 2]);
+        assert_eq!(
+            row_texts(std::slice::from_ref(header.as_ref().unwrap())),
+            [["Name", "Role"]]
+        );
+        assert_eq!(row_texts(rows), [["Ada", "Math"], ["Alan", "CS"]]);
+        assert_eq!(
+            rows[0].cells[1].content,
+            vec![Inline::Bold(vec![text("Math")])]
+        );
+        assert_eq!(
+            doc.blocks[1],
+            Block::Paragraph {
+                content: vec![text("After.")],
+                metadata: BlockMetadata::default()
+            }
+        );
+    }
 
-        let doc = result.unwrap();
-        assert_eq!(doc.blocks.len(), 1);
+    #[test]
+    fn test_table_cols_and_header_option() {
+        let doc = parse("[cols=\"1,2,3\", options=\"header\"]\n|===\n|a\n|b\n|c\n|1 |2 |3\n|===")
+            .unwrap();
+        let Block::Table {
+            columns,
+            header,
+            rows,
+            attributes,
+            ..
+        } = &doc.blocks[0]
+        else {
+            panic!("Expected Table");
+        };
+        let widths: Vec<u32> = columns.iter().map(|column| column.width).collect();
+        assert_eq!(widths, [1, 2, 3]);
+        assert_eq!(
+            row_texts(std::slice::from_ref(header.as_ref().unwrap())),
+            [["a", "b", "c"]]
+        );
+        assert_eq!(row_texts(rows), [["1", "2", "3"]]);
+        assert_eq!(attributes.get("cols"), Some("1,2,3"));
+
+        // Without a blank line after the first line there is no header
+        let doc = parse("|===\n|a |b\n|c |d\n|===").unwrap();
+        assert!(
+            matches!(&doc.blocks[0], Block::Table { header: None, rows, .. } if rows.len() == 2)
+        );
+
+        // `%noheader` suppresses the implicit header
+        let doc = parse("[%noheader]\n|===\n|a |b\n\n|c |d\n|===").unwrap();
+        assert!(
+            matches!(&doc.blocks[0], Block::Table { header: None, rows, .. } if rows.len() == 2)
+        );
+    }
 
-        if let Block::Paragraph { content } = &doc.blocks[0] {
-            assert_eq!(content.len(), 1);
+    #[test]
+    fn test_table_cell_spans() {
+        let doc = parse("[cols=\"3*\"]\n|===\n.2+|tall 2+|wide\n|x |y\n3*|same\n|===").unwrap();
+        let Block::Table { rows, .. } = &doc.blocks[0] else {
+            panic!("Expected Table");
+        };
+        assert_eq!(
+            row_texts(rows),
+            vec![
+                vec!["tall", "wide"],
+                vec!["x", "y"],
+                vec!["same", "same", "same"],
+            ]
+        );
+        assert_eq!((rows[0].cells[0].colspan, rows[0].cells[0].rowspan), (1, 2));
+        assert_eq!((rows[0].cells[1].colspan, rows[0].cells[1].rowspan), (2, 1));
+    }
 
-            // Should have bold with nested content
-            if let Inline::Bold(bold_content) = &content[0] {
-                assert_eq!(bold_content.len(), 2); // text + italic
-                assert!(bold_content[0].is_text());
-                assert!(bold_content[1].is_italic());
-            } else {
-                panic!("Expected Bold");
+    #[test]
+    fn test_table_multiline_cells_and_unclosed() {
+        let doc = parse("[cols=2]\n|===\n|first\nline |_b_\n").unwrap();
+        let Block::Table { rows, .. } = &doc.blocks[0] else {
+            panic!("Expected Table");
+        };
+        assert_eq!(row_texts(rows), [["first\nline", "b"]]);
+    }
+
+    #[test]
+    fn test_table_row_limit() {
+        let source = "|===\n|a\n|b\n|c\n|===";
+        let limits = Limits::new().max_table_rows(2);
+        let doc =
+            parse_document_winnow_with_limits(source, &lex_with_spans(source), &limits).unwrap();
+        let Block::Table { rows, .. } = &doc.blocks[0] else {
+            panic!("Expected Table");
+        };
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            doc.truncations,
+            vec![Truncation {
+                kind: StructureKind::Table,
+                offset: 0,
+                kept: 2,
+                dropped: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_table_row_limit_keeps_header() {
+        let source = "[cols=2]\n|===\n|h1 |h2\n\n|a |b\n|c |d\n|e |f\n|g\n|===";
+        let limits = Limits::new().max_table_rows(1);
+        let doc =
+            parse_document_winnow_with_limits(source, &lex_with_spans(source), &limits).unwrap();
+        let Block::Table { header, rows, .. } = &doc.blocks[0] else {
+            panic!("Expected Table");
+        };
+        assert_eq!(row_texts(header.as_slice()), [["h1", "h2"]]);
+        assert_eq!(row_texts(rows), [["a", "b"]]);
+        assert_eq!(
+            doc.truncations,
+            vec![Truncation {
+                kind: StructureKind::Table,
+                offset: 9,
+                kept: 1,
+                dropped: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_quote_block() {
+        let doc =
+            parse("[quote, Ada Lovelace, Notes]\n____\nFirst _para_.\n\n* item\n____\n\nAfter.")
+                .unwrap();
+        assert_eq!(
+            doc.blocks[0],
+            Block::Quote {
+                attribution: Some("Ada Lovelace".to_string()),
+                citation: Some("Notes".to_string()),
+                content: vec![
+                    Block::Paragraph {
+                        content: vec![
+                            text("First "),
+                            Inline::Italic(vec![text("para")]),
+                            text(".")
+                        ],
+                        metadata: BlockMetadata::default()
+                    },
+                    Block::UnorderedList {
+                        items: vec![ListItem::new(vec![text("item")])],
+                        metadata: BlockMetadata::default()
+                    },
+                ],
+                metadata: BlockMetadata::from(&parse_attribute_list("quote, Ada Lovelace, Notes"))
             }
-        } else {
-            panic!("Expected Paragraph");
-        }
+        );
+        assert_eq!(
+            doc.blocks[1],
+            Block::Paragraph {
+                content: vec![text("After.")],
+                metadata: BlockMetadata::default()
+            }
+        );
     }
 
     #[test]
-    fn test_paragraph_with_newline() {
-        // "word word\n"
-        let tokens = vec![Token::Word, Token::Word, Token::Newline];
-        let result = parse_document_winnow(&tokens);
-        assert!(result.is_ok());
+    fn test_nested_and_styled_quotes() {
+        let doc = parse("____\nOuter\n\n______\nInner\n______\n____").unwrap();
+        let Block::Quote { content, .. } = &doc.blocks[0] else {
+            panic!("Expected Quote, got {:?}", doc.blocks[0]);
+        };
+        assert_eq!(content.len(), 2);
+        assert!(matches!(&content[1], Block::Quote { content, .. } if content.len() == 1));
+
+        // A styled paragraph keeps its lines together
+        let doc = parse("[quote, attribution=Someone]\nTo be\nor not\n\nAfter.").unwrap();
+        assert_eq!(
+            doc.blocks[0],
+            Block::Quote {
+                attribution: Some("Someone".to_string()),
+                citation: None,
+                content: vec![Block::Paragraph {
+                    content: vec![text("To be\nor not")],
+                    metadata: BlockMetadata::default()
+                }],
+                metadata: BlockMetadata::from(&parse_attribute_list("quote, attribution=Someone"))
+            }
+        );
+        assert_eq!(doc.blocks.len(), 2);
+    }
 
-        let doc = result.unwrap();
-        assert_eq!(doc.blocks.len(), 1);
+    #[test]
+    fn test_verse_block() {
+        let doc =
+            parse("[verse, Poet]\n____\nThe fog comes\n\non **little** cat feet.\n____").unwrap();
+        assert_eq!(
+            doc.blocks[0],
+            Block::Verse {
+                attribution: Some("Poet".to_string()),
+                citation: None,
+                content: vec![
+                    text("The fog comes\n\non "),
+                    Inline::Bold(vec![text("little")]),
+                    text(" cat feet."),
+                ],
+                metadata: BlockMetadata::from(&parse_attribute_list("verse, Poet"))
+            }
+        );
+
+        let doc = parse("[verse]\nline one\nline two").unwrap();
+        assert!(matches!(
+            &doc.blocks[0],
+            Block::Verse { content, .. } if content == &vec![text("line one\nline two")]
+        ));
+
+        // A delimiter line ends a styled paragraph
+        let doc = parse("[verse]\nline one\n____\nQuoted.\n____").unwrap();
+        assert!(matches!(
+            &doc.blocks[..],
+            [Block::Verse { content, .. }, Block::Quote { .. }] if content == &vec![text("line one")]
+        ));
     }
 
     #[test]
-    fn test_multiple_headings() {
-        // "= H1\n== H2\n=== H3\n"
-        let tokens = vec![
-            Token::Heading1,
-            Token::Word,
-            Token::Newline,
-            Token::Heading2,
-            Token::Word,
-            Token::Newline,
-            Token::Heading3,
-            Token::Word,
-            Token::Newline,
-        ];
-        let result = parse_document_winnow(&tokens);
-        assert!(result.is_ok());
+    fn test_admonitions() {
+        let paragraph = |content| Block::Paragraph {
+            content,
+            metadata: BlockMetadata::default(),
+        };
+        let doc = parse(
+            "NOTE: Back up\n*first*.\n\n.Careful\n[WARNING]\n====\nOne.\n\nTwo.\n====\n\n\
+             [TIP]\nStyled.\n\nNOTE:: term\n\nNOTE:tight\n\nnote: lowercase\n\nNOTE: \nnext line\n",
+        )
+        .unwrap();
+        assert_eq!(
+            doc.blocks[0],
+            Block::Admonition {
+                kind: AdmonitionKind::Note,
+                content: vec![paragraph(vec![
+                    text("Back up\n"),
+                    Inline::Bold(vec![text("first")]),
+                    text("."),
+                ])],
+                metadata: BlockMetadata::default(),
+            }
+        );
+        let Block::Admonition {
+            kind,
+            content,
+            metadata,
+        } = &doc.blocks[1]
+        else {
+            panic!("expected an admonition, got {:?}", doc.blocks[1]);
+        };
+        assert_eq!(*kind, AdmonitionKind::Warning);
+        assert_eq!(
+            *content,
+            vec![paragraph(vec![text("One.")]), paragraph(vec![text("Two.")])]
+        );
+        assert_eq!(metadata.title, Some(vec![text("Careful")]));
+        assert!(matches!(
+            &doc.blocks[2],
+            Block::Admonition { kind: AdmonitionKind::Tip, content, .. }
+                if *content == vec![paragraph(vec![text("Styled.")])]
+        ));
+        // Not admonitions: a description list, no space, not upper case,
+        // nothing after the label on its line
+        assert!(matches!(doc.blocks[3], Block::DescriptionList { .. }));
+        assert!(
+            doc.blocks[4..]
+                .iter()
+                .all(|block| matches!(block, Block::Paragraph { .. }))
+        );
+        assert_eq!(doc.blocks.len(), 8);
+    }
 
-        let doc = result.unwrap();
-        // H1 contains H2, H2 contains H3 (nested structure)
-        assert_eq!(doc.blocks.len(), 1);
+    #[test]
+    fn test_example_and_sidebar_blocks() {
+        let doc = parse("====\nAn _example_.\n\n*****\nAside.\n\n======\nNested.\n======\n*****\n====\n\nAfter.").unwrap();
+        assert_eq!(
+            doc.blocks,
+            vec![
+                Block::Example {
+                    content: vec![
+                        Block::Paragraph {
+                            content: vec![
+                                text("An "),
+                                Inline::Italic(vec![text("example")]),
+                                text("."),
+                            ],
+                            metadata: BlockMetadata::default()
+                        },
+                        Block::Sidebar {
+                            content: vec![
+                                Block::Paragraph {
+                                    content: vec![text("Aside.")],
+                                    metadata: BlockMetadata::default()
+                                },
+                                Block::Example {
+                                    content: vec![Block::Paragraph {
+                                        content: vec![text("Nested.")],
+                                        metadata: BlockMetadata::default()
+                                    }],
+                                    metadata: BlockMetadata::default()
+                                },
+                            ],
+                            metadata: BlockMetadata::default()
+                        },
+                    ],
+                    metadata: BlockMetadata::default()
+                },
+                Block::Paragraph {
+                    content: vec![text("After.")],
+                    metadata: BlockMetadata::default()
+                },
+            ]
+        );
+    }
 
-        if let Block::Section { level, content, .. } = &doc.blocks[0] {
-            assert_eq!(*level, 1);
-            assert!(content.len() > 0);
-        } else {
-            panic!("Expected Section");
+    #[test]
+    fn test_unclosed_sidebar() {
+        let doc = parse("****\n* item\n").unwrap();
+        assert_eq!(
+            doc.blocks,
+            vec![Block::Sidebar {
+                content: vec![Block::UnorderedList {
+                    items: vec![ListItem::new(vec![text("item")])],
+                    metadata: BlockMetadata::default()
+                }],
+                metadata: BlockMetadata::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_open_block() {
+        let doc = parse("[abstract]\n--\nFirst.\n\n* item\n--\n\n--\nPlain.\n--\n").unwrap();
+        let Block::Open {
+            content,
+            attributes,
+            ..
+        } = &doc.blocks[0]
+        else {
+            panic!("expected an open block, got {:?}", doc.blocks[0]);
+        };
+        assert_eq!(attributes.get("1"), Some("abstract"));
+        assert_eq!(
+            content,
+            &vec![
+                Block::Paragraph {
+                    content: vec![text("First.")],
+                    metadata: BlockMetadata::default()
+                },
+                Block::UnorderedList {
+                    items: vec![ListItem::new(vec![text("item")])],
+                    metadata: BlockMetadata::default()
+                },
+            ]
+        );
+        assert_eq!(
+            doc.blocks[1],
+            Block::Open {
+                content: vec![Block::Paragraph {
+                    content: vec![text("Plain.")],
+                    metadata: BlockMetadata::default()
+                }],
+                attributes: Attributes::default(),
+                metadata: BlockMetadata::default()
+            }
+        );
+        assert_eq!(doc.blocks.len(), 2);
+    }
+
+    #[test]
+    fn test_block_anchors() {
+        let doc = parse(
+            "[[intro,Introduction]]\n== Intro\n\n[#code]\n----\nx\n----\n\n\
+             [[p1]]\nText.\n\n[[l1]]\n* item\n",
+        )
+        .unwrap();
+        let Block::Section {
+            anchor, content, ..
+        } = &doc.blocks[0]
+        else {
+            panic!("expected a section, got {:?}", doc.blocks[0]);
+        };
+        assert_eq!(
+            anchor.as_ref(),
+            Some(&Anchor {
+                id: "intro".to_string(),
+                reftext: Some("Introduction".to_string()),
+            })
+        );
+        assert_eq!(content[0].anchor(), Some(Anchor::new("code")));
+        // Other blocks keep the anchor in their metadata
+        assert_eq!(
+            content[1],
+            Block::Paragraph {
+                content: vec![text("Text.")],
+                metadata: BlockMetadata {
+                    id: Some("p1".to_string()),
+                    ..BlockMetadata::default()
+                }
+            }
+        );
+        assert!(matches!(content[2], Block::UnorderedList { .. }));
+        assert_eq!(content[2].anchor(), Some(Anchor::new("l1")));
+
+        // `[#id]` before a heading, and an anchor line that is not an anchor
+        let doc = parse("[#setup.wide]\n== Setup\n\n[[not an id]]\n").unwrap();
+        assert_eq!(doc.blocks[0].anchor(), Some(Anchor::new("setup")));
+        let Block::Section { content, .. } = &doc.blocks[0] else {
+            panic!("expected a section, got {:?}", doc.blocks[0]);
+        };
+        assert_eq!(
+            content[0],
+            Block::Paragraph {
+                content: vec![text("[[not an id]]")],
+                metadata: BlockMetadata::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_dangling_block_anchors() {
+        // An anchor line with no block after it is a paragraph
+        let anchor_paragraph = |id: &str| Block::Paragraph {
+            content: vec![Inline::Anchor(Anchor::new(id))],
+            metadata: BlockMetadata::default(),
+        };
+        let doc = parse("[[id]]\n").unwrap();
+        assert_eq!(doc.blocks, vec![anchor_paragraph("id")]);
+        let doc = parse("Para\n\n[[end]]\n").unwrap();
+        assert_eq!(doc.blocks.len(), 2);
+        assert_eq!(doc.blocks[1], anchor_paragraph("end"));
+        let doc = parse("[[a]]\n[[b]]\n").unwrap();
+        assert_eq!(
+            doc.blocks,
+            vec![anchor_paragraph("a"), anchor_paragraph("b")]
+        );
+    }
+
+    #[test]
+    fn test_subs_quotes_and_macros() {
+        let doc = parse(
+            "[subs=-quotes]\n*a* https://x.y\n\n[subs=none]\n* *a* <<b>>\n\n\
+             [subs=quotes]\n|===\n| *a* image:i.png[]\n|===\n\n*a*\n",
+        )
+        .unwrap();
+        let Block::Paragraph { content, .. } = &doc.blocks[0] else {
+            panic!("expected a paragraph, got {:?}", doc.blocks[0]);
+        };
+        assert_eq!(content[0], text("*a* "));
+        assert!(matches!(content[1], Inline::Link { .. }));
+        let Block::UnorderedList { items, .. } = &doc.blocks[1] else {
+            panic!("expected a list, got {:?}", doc.blocks[1]);
+        };
+        assert_eq!(items[0].content, vec![text("*a* <<b>>")]);
+        let Block::Table { rows, .. } = &doc.blocks[2] else {
+            panic!("expected a table, got {:?}", doc.blocks[2]);
+        };
+        assert_eq!(
+            rows[0].cells[0].content,
+            vec![Inline::Bold(vec![text("a")]), text(" image:i.png[]")]
+        );
+        // Substitutions are the block's own
+        assert!(matches!(&doc.blocks[3], Block::Paragraph { content, .. } if content[0].is_bold()));
+
+        // Defaults by kind, under the titles
+        let mut defaults = SubstitutionDefaults::new();
+        defaults.set("paragraph", Substitutions::NORMAL - Substitutions::QUOTES);
+        let source = ".*T*\n*a*\n\n____\n*b*\n____\n";
+        let doc = parse_tokens(
+            source,
+            &lex_with_spans(source),
+            &Limits::new(),
+            &defaults,
+            false,
+        )
+        .unwrap();
+        assert!(doc.blocks[0].metadata().title.as_ref().unwrap()[0].is_bold());
+        assert!(
+            matches!(&doc.blocks[0], Block::Paragraph { content, .. } if content == &vec![text("*a*")])
+        );
+        let Block::Quote { content, .. } = &doc.blocks[1] else {
+            panic!("expected a quote, got {:?}", doc.blocks[1]);
+        };
+        assert_eq!(
+            content[0],
+            Block::Paragraph {
+                content: vec![text("*b*")],
+                metadata: BlockMetadata::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_nested_subs_parse_once() {
+        // Every level's `subs` differs from what its kind's defaults give;
+        // parsing each level twice per enclosing level would never finish
+        let depth = 40;
+        let mut source = String::new();
+        for level in 0..depth {
+            source.push_str(&format!("[subs=\"-quotes\"]\n{}\n", "_".repeat(4 + level)));
         }
+        source.push_str("*a*\n");
+        for level in (0..depth).rev() {
+            source.push_str(&format!("{}\n", "_".repeat(4 + level)));
+        }
+        let mut defaults = SubstitutionDefaults::new();
+        defaults.set("quote", Substitutions::NORMAL - Substitutions::MACROS);
+        let doc = parse_tokens(
+            &source,
+            &lex_with_spans(&source),
+            &Limits::new(),
+            &defaults,
+            false,
+        )
+        .unwrap();
+        let mut block = &doc.blocks[0];
+        for _ in 0..depth {
+            let Block::Quote { content, .. } = block else {
+                panic!("expected a quote, got {block:?}");
+            };
+            block = &content[0];
+        }
+        assert!(matches!(block, Block::Paragraph { content, .. } if content[0].is_bold()));
+    }
+
+    #[test]
+    fn test_stacked_block_anchors() {
+        // The last anchor line wins, and none of them is an attribute list
+        let doc = parse("[[a]]\n[[b,Bee]]\n== S\n").unwrap();
+        assert_eq!(
+            doc.blocks[0].anchor(),
+            Some(Anchor {
+                id: "b".to_string(),
+                reftext: Some("Bee".to_string()),
+            })
+        );
+        assert!(doc.blocks[0].metadata().is_empty());
+
+        let doc = parse("[[a]]\n[[b,Bee]]\nPara.").unwrap();
+        assert_eq!(
+            doc.blocks,
+            vec![Block::Paragraph {
+                content: vec![text("Para.")],
+                metadata: BlockMetadata {
+                    id: Some("b".to_string()),
+                    named: [("reftext".to_string(), "Bee".to_string())].into(),
+                    ..BlockMetadata::default()
+                }
+            }]
+        );
+        // An id in the attribute list wins over the anchor
+        let doc = parse("[[a]]\n[#own]\nPara.").unwrap();
+        assert_eq!(doc.blocks[0].anchor(), Some(Anchor::new("own")));
+
+        // A line opening with an inline anchor is text, even ending in `]`
+        let doc = parse("[#c]\n[[d]] footnote:[n]\n== S\n").unwrap();
+        assert!(
+            matches!(&doc.blocks[0], Block::Paragraph { metadata, .. } if metadata.id.as_deref() == Some("c"))
+        );
+    }
+
+    #[test]
+    fn test_block_images() {
+        let doc = parse(
+            "image::diagrams/flow_chart.png[Flow, 640, 480]\n\n\
+             [#fig.wide,link=big.png]\nimage::b.png[role=framed]\n\n\
+             [[anchored]]\nimage::c.png[]\n\n\
+             image::d.png[] trailing text\n\n\
+             image::has space.png[]",
+        )
+        .unwrap();
+        let Block::Image {
+            target, attributes, ..
+        } = &doc.blocks[0]
+        else {
+            panic!("expected an image, got {:?}", doc.blocks[0]);
+        };
+        assert_eq!(target, "diagrams/flow_chart.png");
+        let named = |name| attributes.get(name);
+        assert_eq!(
+            (named("alt"), named("width"), named("height")),
+            (Some("Flow"), Some("640"), Some("480"))
+        );
+
+        let Block::Image { attributes, .. } = &doc.blocks[1] else {
+            panic!("expected an image, got {:?}", doc.blocks[1]);
+        };
+        assert_eq!(attributes.id(), Some("fig"));
+        assert_eq!(attributes.roles(), vec!["framed"]);
+        assert_eq!(attributes.get("link"), Some("big.png"));
+        assert!(!attributes.contains("1"));
+        assert_eq!(doc.blocks[2].anchor(), Some(Anchor::new("anchored")));
+
+        // Not alone on its line, or not a valid target: paragraphs
+        assert!(matches!(doc.blocks[3], Block::Paragraph { .. }));
+        assert!(matches!(doc.blocks[4], Block::Paragraph { .. }));
     }
 }
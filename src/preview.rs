@@ -0,0 +1,264 @@
+//! Live preview: page updates and their WebSocket transport
+//!
+//! A preview server renders each page once with node IDs
+//! ([`HtmlOptions::node_ids`](crate::render::html::HtmlOptions::node_ids))
+//! and loads [`CLIENT_SCRIPT`] into it (served at [`CLIENT_PATH`]). The
+//! script opens a WebSocket to [`LIVE_PATH`]`?page=<path>`; when the page's
+//! source changes, the server sends the [`PreviewUpdate`] from [`update`].
+//!
+//! An update is a DOM patch script ([`crate::render::patch`]) when the
+//! patch shows the new rendering exactly, or a full reload otherwise: when
+//! document attributes change (they may show anywhere, such as in the page
+//! title), or when either version has content numbered across the whole
+//! document, which a patched block would lose: footnotes, exercises (see
+//! [`crate::exercises`]), or lists of figures, tables, or listings (see
+//! [`crate::outline`]).
+//!
+//! The transport needs only the server side of the WebSocket handshake
+//! ([`websocket_accept`]) and unmasked text frames
+//! ([`websocket_text_frame`]), since the client never sends data.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::parse_document;
+//! use doctora::preview::{PreviewUpdate, update};
+//!
+//! let old = parse_document("First.\n").unwrap();
+//! let edited = parse_document("First, edited.\n").unwrap();
+//! let Some(PreviewUpdate::Patch(script)) = update(&old, &edited) else {
+//!     panic!("an edited paragraph is patched");
+//! };
+//! assert!(script.contains("First, edited."));
+//!
+//! let retitled = parse_document(":product: Doctora\n\nFirst.\n").unwrap();
+//! assert_eq!(update(&old, &retitled), Some(PreviewUpdate::Reload));
+//! assert_eq!(update(&old, &old), None);
+//! ```
+
+use crate::ast::visit::{Visitor, walk_block};
+use crate::ast::{Block, Document};
+use crate::exercises::ExerciseKind;
+use crate::footnotes::Footnotes;
+use crate::outline::list_macro;
+use crate::render::patch::{diff, to_script};
+
+/// Path of the WebSocket endpoint pages connect to
+pub const LIVE_PATH: &str = "/_doctora/live";
+
+/// Path the server serves [`CLIENT_SCRIPT`] at
+pub const CLIENT_PATH: &str = "/_doctora/live.js";
+
+/// Browser side of live preview
+///
+/// Connects to [`LIVE_PATH`] for the current page and applies each message:
+/// `reload` reloads the page, anything else is a patch script applied to
+/// `document.body`.
+pub const CLIENT_SCRIPT: &str = r#"(function () {
+  const url = new URL("/_doctora/live", location.href);
+  url.protocol = location.protocol === "https:" ? "wss:" : "ws:";
+  url.searchParams.set("page", location.pathname);
+  const socket = new WebSocket(url);
+  socket.onmessage = (event) => {
+    if (event.data === "reload") {
+      location.reload();
+    } else {
+      (0, eval)(event.data)(document.body);
+    }
+  };
+})();
+"#;
+
+/// How a preview brings a page up to date
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreviewUpdate {
+    /// Run this patch script (see [`to_script`])
+    Patch(String),
+    /// Reload the whole page
+    Reload,
+}
+
+impl PreviewUpdate {
+    /// The message sent to [`CLIENT_SCRIPT`]
+    pub fn message(&self) -> &str {
+        match self {
+            PreviewUpdate::Patch(script) => script,
+            PreviewUpdate::Reload => "reload",
+        }
+    }
+}
+
+/// The update that turns the preview of `old` into that of `new`, or
+/// `None` if the rendering is unchanged (see the module documentation)
+pub fn update(old: &Document, new: &Document) -> Option<PreviewUpdate> {
+    if old.attributes != new.attributes || numbered(old) || numbered(new) {
+        return (old != new).then_some(PreviewUpdate::Reload);
+    }
+    let ops = diff(old, new);
+    (!ops.is_empty()).then(|| PreviewUpdate::Patch(to_script(&ops)))
+}
+
+/// Returns true if `doc` has footnotes, exercises, or caption lists
+fn numbered(doc: &Document) -> bool {
+    let mut finder = NumberedFinder(false);
+    finder.visit_document(doc);
+    finder.0 || !Footnotes::new(doc).is_empty()
+}
+
+/// Looks for exercises, solutions, and caption list macros
+struct NumberedFinder(bool);
+
+impl Visitor for NumberedFinder {
+    fn visit_block(&mut self, block: &Block) {
+        if ExerciseKind::of(block).is_some() || list_macro(block).is_some() {
+            self.0 = true;
+        } else if !self.0 {
+            walk_block(self, block);
+        }
+    }
+}
+
+/// The `Sec-WebSocket-Accept` header value answering a client's
+/// `Sec-WebSocket-Key` (RFC 6455, section 4.2.2)
+pub fn websocket_accept(key: &str) -> String {
+    const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+    base64(&sha1(format!("{}{GUID}", key.trim()).as_bytes()))
+}
+
+/// An unfragmented, unmasked WebSocket text frame holding `text`
+pub fn websocket_text_frame(text: &str) -> Vec<u8> {
+    let payload = text.as_bytes();
+    let mut frame = vec![0x81];
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// SHA-1 digest of `data`, as the handshake requires
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut words = [0u32; 80];
+        for (word, bytes) in words.iter_mut().zip(chunk.chunks(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..80 {
+            words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in words.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, value) in digest.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+/// Standard base64 with padding
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    #[test]
+    fn test_handshake() {
+        // The example handshake of RFC 6455, section 1.3
+        assert_eq!(
+            websocket_accept("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+    }
+
+    #[test]
+    fn test_text_frames() {
+        assert_eq!(websocket_text_frame("reload"), b"\x81\x06reload");
+        let frame = websocket_text_frame(&"x".repeat(300));
+        assert_eq!(&frame[..4], [0x81, 126, 0x01, 0x2C]);
+        assert_eq!(frame.len(), 304);
+        let frame = websocket_text_frame(&"x".repeat(70_000));
+        assert_eq!(&frame[..10], [0x81, 127, 0, 0, 0, 0, 0, 0x01, 0x11, 0x70]);
+    }
+
+    #[test]
+    fn test_reload_cases() {
+        let plain = parse_document("Text.\n").unwrap();
+        let footnote = parse_document("Text.footnote:[Note.]\n").unwrap();
+        assert_eq!(update(&plain, &footnote), Some(PreviewUpdate::Reload));
+        assert_eq!(update(&footnote, &footnote), None);
+
+        let exercise = parse_document("Text.\n\n[exercise]\n====\nA.\n====\n").unwrap();
+        assert_eq!(update(&plain, &exercise), Some(PreviewUpdate::Reload));
+        let figures = parse_document("toc::[figures]\n\nText.\n").unwrap();
+        assert_eq!(update(&plain, &figures), Some(PreviewUpdate::Reload));
+        let image = parse_document("Text.\n\n.Arch\nimage::a.png[]\n").unwrap();
+        assert!(matches!(
+            update(&plain, &image),
+            Some(PreviewUpdate::Patch(_))
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+//! Output processors and the processor registry
+//!
+//! A [`Processor`] turns a parsed [`Document`] into an output format. Processors
+//! are collected in a [`ProcessorRegistry`], which picks one by format name.
+//! Custom formats plug in by implementing the trait and registering it:
+//!
+//! ```
+//! use doctora::ast::Document;
+//! use doctora::parse_document;
+//! use doctora::processor::{Output, Processor, ProcessorContext, ProcessorError, ProcessorRegistry};
+//!
+//! /// Counts top-level blocks
+//! struct BlockCount;
+//!
+//! impl Processor for BlockCount {
+//!     fn name(&self) -> &str {
+//!         "block-count"
+//!     }
+//!
+//!     fn can_handle(&self, format: &str) -> bool {
+//!         format == "count"
+//!     }
+//!
+//!     fn process(&self, doc: &Document, _: &ProcessorContext<'_>) -> Result<Output, ProcessorError> {
+//!         Ok(Output::Text(doc.blocks.len().to_string()))
+//!     }
+//! }
+//!
+//! let mut registry = ProcessorRegistry::with_builtins();
+//! registry.register(Box::new(BlockCount));
+//!
+//! let doc = parse_document("One.\n\nTwo.").unwrap();
+//! assert_eq!(registry.convert(&doc, "count").unwrap(), Output::Text("2".to_string()));
+//! assert!(registry.convert(&doc, "html").is_ok());
+//! ```
+//!
+//! # Sub-processing
+//!
+//! Processors receive a [`ProcessorContext`] that gives access to the
+//! registry, so a processor can delegate part of its work to another format
+//! (e.g. a PDF processor rendering embedded diagrams through an image
+//! processor).
+
+use crate::ast::Document;
+use thiserror::Error;
+
+/// Result of running a processor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Output {
+    /// Textual output (HTML, Markdown, ...)
+    Text(String),
+    /// Binary output (PDF, images, ...)
+    Binary(Vec<u8>),
+}
+
+impl Output {
+    /// Returns the text, if this is textual output
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Output::Text(text) => Some(text),
+            Output::Binary(_) => None,
+        }
+    }
+}
+
+/// Error produced while selecting or running a processor
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ProcessorError {
+    /// No registered processor handles the format
+    #[error("no processor registered for format `{0}`")]
+    UnknownFormat(String),
+    /// A processor failed to convert the document
+    #[error("processor `{processor}` failed: {message}")]
+    Failed { processor: String, message: String },
+}
+
+/// Converts documents into an output format
+pub trait Processor: Send + Sync {
+    /// Unique processor name; registering another processor with the same
+    /// name replaces this one
+    fn name(&self) -> &str;
+
+    /// Returns true if this processor produces `format`
+    fn can_handle(&self, format: &str) -> bool;
+
+    /// Convert `doc`
+    fn process(
+        &self,
+        doc: &Document,
+        context: &ProcessorContext<'_>,
+    ) -> Result<Output, ProcessorError>;
+}
+
+/// Services available to a processor while it runs
+#[derive(Clone, Copy)]
+pub struct ProcessorContext<'a> {
+    registry: &'a ProcessorRegistry,
+}
+
+impl<'a> ProcessorContext<'a> {
+    /// The registry the running processor was selected from
+    pub fn registry(&self) -> &'a ProcessorRegistry {
+        self.registry
+    }
+
+    /// Convert `doc` to `format` with another registered processor
+    pub fn process(&self, doc: &Document, format: &str) -> Result<Output, ProcessorError> {
+        self.registry.convert(doc, format)
+    }
+}
+
+/// The set of available processors
+#[derive(Default)]
+pub struct ProcessorRegistry {
+    /// Registered processors; later registrations are consulted first
+    processors: Vec<Box<dyn Processor>>,
+}
+
+impl ProcessorRegistry {
+    /// Creates an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a registry with the built-in processors (HTML, man page, plain
+    /// text, and PDF with the `pdf` feature)
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(crate::render::html::HtmlProcessor::default()));
+        registry.register(Box::new(crate::render::manpage::ManpageProcessor));
+        registry.register(Box::new(crate::render::text::TextProcessor::default()));
+        #[cfg(feature = "pdf")]
+        registry.register(Box::new(crate::render::pdf::PdfProcessor::default()));
+        registry
+    }
+
+    /// Adds `processor`, replacing any processor with the same name
+    ///
+    /// When several processors handle a format, the most recently registered
+    /// one wins, so user processors can override built-ins.
+    pub fn register(&mut self, processor: Box<dyn Processor>) {
+        self.processors.retain(|p| p.name() != processor.name());
+        self.processors.push(processor);
+    }
+
+    /// Returns the processor for `format`, if any
+    pub fn get(&self, format: &str) -> Option<&dyn Processor> {
+        self.processors
+            .iter()
+            .rev()
+            .find(|p| p.can_handle(format))
+            .map(|p| p.as_ref())
+    }
+
+    /// Names of the registered processors, in registration order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.processors.iter().map(|p| p.name())
+    }
+
+    /// Convert `doc` to `format`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProcessorError::UnknownFormat`] if no processor handles
+    /// `format`, or the processor's own error.
+    pub fn convert(&self, doc: &Document, format: &str) -> Result<Output, ProcessorError> {
+        let processor = self
+            .get(format)
+            .ok_or_else(|| ProcessorError::UnknownFormat(format.to_string()))?;
+        processor.process(doc, &ProcessorContext { registry: self })
+    }
+}
+
+impl std::fmt::Debug for ProcessorRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    /// Wraps the HTML output of another processor
+    struct Wrapper;
+
+    impl Processor for Wrapper {
+        fn name(&self) -> &str {
+            "wrapper"
+        }
+
+        fn can_handle(&self, format: &str) -> bool {
+            format == "wrapped"
+        }
+
+        fn process(
+            &self,
+            doc: &Document,
+            context: &ProcessorContext<'_>,
+        ) -> Result<Output, ProcessorError> {
+            let inner = context.process(doc, "html")?;
+            Ok(Output::Text(format!("[{}]", inner.as_text().unwrap())))
+        }
+    }
+
+    #[test]
+    fn test_builtin_html() {
+        let registry = ProcessorRegistry::with_builtins();
+        let doc = parse_document("Hi.").unwrap();
+        assert_eq!(
+            registry.convert(&doc, "html").unwrap(),
+            Output::Text("<p>Hi.</p>\n".to_string())
+        );
+        let names: Vec<_> = registry.names().collect();
+        if cfg!(feature = "pdf") {
+            assert_eq!(names, vec!["html", "manpage", "text", "pdf"]);
+        } else {
+            assert_eq!(names, vec!["html", "manpage", "text"]);
+        }
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        let registry = ProcessorRegistry::new();
+        let err = registry.convert(&Document::new(), "pdf").unwrap_err();
+        assert_eq!(err, ProcessorError::UnknownFormat("pdf".to_string()));
+        assert_eq!(err.to_string(), "no processor registered for format `pdf`");
+    }
+
+    #[test]
+    fn test_sub_processing() {
+        let mut registry = ProcessorRegistry::with_builtins();
+        registry.register(Box::new(Wrapper));
+        let doc = parse_document("Hi.").unwrap();
+        assert_eq!(
+            registry.convert(&doc, "wrapped").unwrap().as_text(),
+            Some("[<p>Hi.</p>\n]")
+        );
+    }
+
+    #[test]
+    fn test_register_replaces_by_name() {
+        let mut registry = ProcessorRegistry::new();
+        registry.register(Box::new(Wrapper));
+        registry.register(Box::new(Wrapper));
+        assert_eq!(registry.names().count(), 1);
+    }
+}
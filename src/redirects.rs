@@ -0,0 +1,509 @@
+//! Redirect maps for section anchors that move between builds
+//!
+//! Deep links into a documentation site (`guide/install.html#_proxy_settings`)
+//! break when a section's ID changes or the section moves to another page.
+//! To keep them working, record the section anchors of every build as an
+//! [`AnchorHistory`], store it with the output ([`AnchorHistory::to_json`]),
+//! and compare the previous build's history with the current one using
+//! [`redirect_map`]. Section IDs are the anchors written in the source or
+//! the generated ones (see [`crate::xref::section_ids`]).
+//!
+//! An anchor of the previous build that is gone from its page is matched to
+//! an anchor that is new in the current build, trying in order:
+//!
+//! 1. **The same ID** on another page (the section moved).
+//! 2. **The same title**, preferring the same page (the ID was changed).
+//! 3. **The same position** on the same page (the title was edited, which
+//!    changes a generated ID).
+//!
+//! Anchors without a match get no redirect. A page that is gone as a whole
+//! redirects to the page most of its anchors moved to.
+//!
+//! The map is available as JSON ([`RedirectMap::to_json`]), for servers and
+//! CDNs that apply redirects themselves, and as stub pages
+//! ([`RedirectMap::stub_pages`]) to write in place of the pages that are
+//! gone. A stub forwards with `<meta http-equiv="refresh">`, and a script
+//! first sends deep links straight to the anchor's new place, since the
+//! fragment never reaches the server.
+//!
+//! Only the previous build is compared, so redirects from earlier builds
+//! must be kept by the caller.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::batch::{FailurePolicy, parse_batch};
+//! use doctora::redirects::{AnchorHistory, redirect_map};
+//! use doctora::site::SiteOptions;
+//!
+//! let before = parse_batch(
+//!     [("guide.adoc", "= Guide\n\n== Install\n\n== Proxy Settings\n")],
+//!     FailurePolicy::Abort,
+//! )
+//! .unwrap();
+//! let after = parse_batch(
+//!     [
+//!         ("guide.adoc", "= Guide\n\n== Installing\n"),
+//!         ("network.adoc", "= Network\n\n== Proxy Settings\n"),
+//!     ],
+//!     FailurePolicy::Abort,
+//! )
+//! .unwrap();
+//!
+//! let map = redirect_map(&AnchorHistory::from_batch(&before), &AnchorHistory::from_batch(&after));
+//! let json = map.to_json(&SiteOptions::new("https://docs.example.com"));
+//! assert_eq!(
+//!     json["https://docs.example.com/guide.html#_install"],
+//!     "https://docs.example.com/guide.html#_installing"
+//! );
+//! assert_eq!(
+//!     json["https://docs.example.com/guide.html#_proxy_settings"],
+//!     "https://docs.example.com/network.html#_proxy_settings"
+//! );
+//! ```
+
+use crate::batch::BatchResult;
+use crate::render::html::escape;
+use crate::site::SiteOptions;
+use crate::xref::section_ids;
+use serde_json::{Map, Value, json};
+use thiserror::Error;
+
+/// Errors reading a stored [`AnchorHistory`]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RedirectError {
+    /// The JSON does not have the layout [`AnchorHistory::to_json`] writes
+    #[error("invalid anchor history: {0}")]
+    InvalidHistory(String),
+}
+
+/// A section anchor in one build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnchorRecord {
+    /// The section ID
+    pub id: String,
+    /// The section title
+    pub title: String,
+    /// Position of the section, as child indices from the document root
+    pub path: Vec<usize>,
+}
+
+/// The section anchors of one page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRecord {
+    /// Name the document was submitted to the batch under
+    pub name: String,
+    /// Section anchors in document order
+    pub anchors: Vec<AnchorRecord>,
+}
+
+/// The section anchors of every page of one build
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnchorHistory {
+    /// Pages in batch order
+    pub pages: Vec<PageRecord>,
+}
+
+impl AnchorHistory {
+    /// Record the section anchors of a batch
+    ///
+    /// Stub documents are left out, like in [`crate::site::navigation`], so
+    /// a page that failed to build does not count as removed.
+    pub fn from_batch(result: &BatchResult) -> Self {
+        let pages = result
+            .documents
+            .iter()
+            .filter(|document| !document.stub)
+            .map(|document| PageRecord {
+                name: document.name.clone(),
+                anchors: section_ids(&document.document)
+                    .into_iter()
+                    .map(|section| AnchorRecord {
+                        id: section.id,
+                        title: section.title,
+                        path: section.path,
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self { pages }
+    }
+
+    /// Serialize the history for the next build to compare against
+    pub fn to_json(&self) -> Value {
+        let pages: Vec<Value> = self
+            .pages
+            .iter()
+            .map(|page| {
+                let anchors: Vec<Value> = page
+                    .anchors
+                    .iter()
+                    .map(|anchor| {
+                        json!({
+                            "id": anchor.id,
+                            "title": anchor.title,
+                            "path": anchor.path,
+                        })
+                    })
+                    .collect();
+                json!({ "name": page.name, "anchors": anchors })
+            })
+            .collect();
+        json!({ "pages": pages })
+    }
+
+    /// Read a history written by [`AnchorHistory::to_json`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RedirectError::InvalidHistory`] if a field is missing or
+    /// has the wrong type.
+    pub fn from_json(value: &Value) -> Result<Self, RedirectError> {
+        let invalid = |what: &str| RedirectError::InvalidHistory(what.to_string());
+        let string = |value: &Value, field: &str| {
+            value[field]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| invalid(&format!("`{field}` must be a string")))
+        };
+        let array = |value: &Value, field: &str| -> Result<Vec<Value>, RedirectError> {
+            value[field]
+                .as_array()
+                .cloned()
+                .ok_or_else(|| invalid(&format!("`{field}` must be an array")))
+        };
+
+        let mut pages = Vec::new();
+        for page in array(value, "pages")? {
+            let mut anchors = Vec::new();
+            for anchor in array(&page, "anchors")? {
+                let path = array(&anchor, "path")?
+                    .iter()
+                    .map(|index| index.as_u64().map(|index| index as usize))
+                    .collect::<Option<Vec<usize>>>()
+                    .ok_or_else(|| invalid("`path` must hold indices"))?;
+                anchors.push(AnchorRecord {
+                    id: string(&anchor, "id")?,
+                    title: string(&anchor, "title")?,
+                    path,
+                });
+            }
+            pages.push(PageRecord {
+                name: string(&page, "name")?,
+                anchors,
+            });
+        }
+        Ok(Self { pages })
+    }
+
+    fn page(&self, name: &str) -> Option<&PageRecord> {
+        self.pages.iter().find(|page| page.name == name)
+    }
+
+    fn contains(&self, page: &str, id: &str) -> bool {
+        self.page(page)
+            .is_some_and(|page| page.anchors.iter().any(|anchor| anchor.id == id))
+    }
+}
+
+/// A moved anchor or page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    /// Page name in the previous build
+    pub from_page: String,
+    /// Anchor ID in the previous build; `None` for the whole page
+    pub from_id: Option<String>,
+    /// Page name in the current build
+    pub to_page: String,
+    /// Anchor ID in the current build; `None` for the top of the page
+    pub to_id: Option<String>,
+}
+
+/// Redirects from one build to the next
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedirectMap {
+    /// Anchor redirects in the previous build's order, then page redirects
+    pub redirects: Vec<Redirect>,
+}
+
+/// A page to write at the path of a page that is gone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StubPage {
+    /// Output path relative to the site root (see [`SiteOptions::page_path`])
+    pub path: String,
+    /// The complete HTML page
+    pub html: String,
+}
+
+/// Compare the anchors of two builds (see the module documentation)
+pub fn redirect_map(previous: &AnchorHistory, current: &AnchorHistory) -> RedirectMap {
+    // Anchors of the current build that the previous one did not have
+    let added: Vec<(&str, &AnchorRecord)> = current
+        .pages
+        .iter()
+        .flat_map(|page| {
+            page.anchors
+                .iter()
+                .map(|anchor| (page.name.as_str(), anchor))
+        })
+        .filter(|(page, anchor)| !previous.contains(page, &anchor.id))
+        .collect();
+
+    let mut redirects = Vec::new();
+    for page in &previous.pages {
+        for anchor in &page.anchors {
+            if current.contains(&page.name, &anchor.id) {
+                continue;
+            }
+            let found = added
+                .iter()
+                .find(|(_, new)| new.id == anchor.id)
+                .or_else(|| {
+                    let titled: Vec<&(&str, &AnchorRecord)> = added
+                        .iter()
+                        .filter(|(_, new)| new.title == anchor.title)
+                        .collect();
+                    titled
+                        .iter()
+                        .find(|(name, _)| *name == page.name)
+                        .or_else(|| titled.first())
+                        .copied()
+                })
+                .or_else(|| {
+                    added
+                        .iter()
+                        .find(|(name, new)| *name == page.name && new.path == anchor.path)
+                });
+            if let Some((to_page, to)) = found {
+                redirects.push(Redirect {
+                    from_page: page.name.clone(),
+                    from_id: Some(anchor.id.clone()),
+                    to_page: to_page.to_string(),
+                    to_id: Some(to.id.clone()),
+                });
+            }
+        }
+    }
+
+    // Pages that are gone follow most of their anchors
+    for page in &previous.pages {
+        if current.page(&page.name).is_some() {
+            continue;
+        }
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for redirect in redirects.iter().filter(|r| r.from_page == page.name) {
+            match counts
+                .iter_mut()
+                .find(|(name, _)| *name == redirect.to_page)
+            {
+                Some((_, count)) => *count += 1,
+                None => counts.push((&redirect.to_page, 1)),
+            }
+        }
+        // The first page wins ties
+        let target = counts
+            .iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .map(|(name, _)| name.to_string());
+        if let Some(to_page) = target {
+            redirects.push(Redirect {
+                from_page: page.name.clone(),
+                from_id: None,
+                to_page,
+                to_id: None,
+            });
+        }
+    }
+    RedirectMap { redirects }
+}
+
+impl RedirectMap {
+    /// Returns true if nothing moved
+    pub fn is_empty(&self) -> bool {
+        self.redirects.is_empty()
+    }
+
+    /// The map as a JSON object from old URLs to new URLs
+    pub fn to_json(&self, options: &SiteOptions) -> Value {
+        let map: Map<String, Value> = self
+            .redirects
+            .iter()
+            .map(|redirect| {
+                (
+                    url(options, &redirect.from_page, redirect.from_id.as_deref()),
+                    Value::String(url(options, &redirect.to_page, redirect.to_id.as_deref())),
+                )
+            })
+            .collect();
+        Value::Object(map)
+    }
+
+    /// Stub pages for the pages of the previous build that are gone
+    pub fn stub_pages(&self, options: &SiteOptions) -> Vec<StubPage> {
+        self.redirects
+            .iter()
+            .filter(|redirect| redirect.from_id.is_none())
+            .map(|page| {
+                let target = url(options, &page.to_page, None);
+                let anchors: Map<String, Value> = self
+                    .redirects
+                    .iter()
+                    .filter(|redirect| redirect.from_page == page.from_page)
+                    .filter_map(|redirect| {
+                        let to = url(options, &redirect.to_page, redirect.to_id.as_deref());
+                        Some((redirect.from_id.clone()?, Value::String(to)))
+                    })
+                    .collect();
+                StubPage {
+                    path: options.page_path(&page.from_page),
+                    html: stub_html(&target, &anchors),
+                }
+            })
+            .collect()
+    }
+}
+
+/// URL of anchor `id` on the page built from `page`
+fn url(options: &SiteOptions, page: &str, id: Option<&str>) -> String {
+    let url = options.page_url(page);
+    match id {
+        Some(id) => format!("{url}#{id}"),
+        None => url,
+    }
+}
+
+/// A page that forwards to `target`, or to the new place of the anchor in
+/// its URL fragment
+fn stub_html(target: &str, anchors: &Map<String, Value>) -> String {
+    let target_attr = escape(target);
+    // `</` would end the script element early
+    let anchors = Value::Object(anchors.clone())
+        .to_string()
+        .replace("</", "<\\/");
+    let fallback = Value::String(target.to_string())
+        .to_string()
+        .replace("</", "<\\/");
+    format!(
+        "<!DOCTYPE html>\n\
+         <html lang=\"en\">\n\
+         <head>\n\
+         <meta charset=\"UTF-8\">\n\
+         <meta http-equiv=\"refresh\" content=\"0; url={target_attr}\">\n\
+         <link rel=\"canonical\" href=\"{target_attr}\">\n\
+         <title>Page moved</title>\n\
+         <script>\n\
+         const anchors = {anchors};\n\
+         location.replace(anchors[decodeURIComponent(location.hash.slice(1))] || {fallback});\n\
+         </script>\n\
+         </head>\n\
+         <body>\n\
+         <p>This page has moved to <a href=\"{target_attr}\">{target_attr}</a>.</p>\n\
+         </body>\n\
+         </html>\n"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::{FailurePolicy, parse_batch};
+
+    fn history(pages: &[(&str, &str)]) -> AnchorHistory {
+        AnchorHistory::from_batch(
+            &parse_batch(pages.iter().copied(), FailurePolicy::Abort).unwrap(),
+        )
+    }
+
+    fn summary(map: &RedirectMap) -> Vec<String> {
+        map.redirects
+            .iter()
+            .map(|r| {
+                format!(
+                    "{}#{} -> {}#{}",
+                    r.from_page,
+                    r.from_id.as_deref().unwrap_or(""),
+                    r.to_page,
+                    r.to_id.as_deref().unwrap_or("")
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matching_rules() {
+        let previous = history(&[
+            (
+                "a.adoc",
+                "[[keep]]\n== Keep\n\n[[old]]\n== Renamed ID\n\n== Old Title\n",
+            ),
+            ("b.adoc", "[[moved]]\n== Moved\n\n== Dropped\n"),
+        ]);
+        let current = history(&[
+            (
+                "a.adoc",
+                "[[keep]]\n== Keep\n\n[[new]]\n== Renamed ID\n\n== New Title\n\n[[moved]]\n== Moved\n",
+            ),
+            ("b.adoc", "== Other\n"),
+        ]);
+        assert_eq!(
+            summary(&redirect_map(&previous, &current)),
+            [
+                "a.adoc#old -> a.adoc#new",
+                "a.adoc#_old_title -> a.adoc#_new_title",
+                "b.adoc#moved -> a.adoc#moved",
+            ]
+        );
+        assert!(redirect_map(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_removed_page_and_stub() {
+        let previous = history(&[
+            ("old.adoc", "== One\n\n== Two\n\n== Three\n"),
+            ("x.adoc", "= X\n"),
+            ("y.adoc", "= Y\n"),
+        ]);
+        let current = history(&[("x.adoc", "== One\n"), ("y.adoc", "== Two\n\n== Three\n")]);
+        let map = redirect_map(&previous, &current);
+        assert_eq!(
+            summary(&map),
+            [
+                "old.adoc#_one -> x.adoc#_one",
+                "old.adoc#_two -> y.adoc#_two",
+                "old.adoc#_three -> y.adoc#_three",
+                "old.adoc# -> y.adoc#",
+            ]
+        );
+
+        let options = SiteOptions::new("https://d.io");
+        let stubs = map.stub_pages(&options);
+        assert_eq!(stubs.len(), 1);
+        assert_eq!(stubs[0].path, "old.html");
+        let html = &stubs[0].html;
+        assert!(
+            html.contains("<meta http-equiv=\"refresh\" content=\"0; url=https://d.io/y.html\">")
+        );
+        assert!(html.contains("\"_one\":\"https://d.io/x.html#_one\""));
+        assert_eq!(
+            map.to_json(&options)["https://d.io/old.html"],
+            "https://d.io/y.html"
+        );
+    }
+
+    #[test]
+    fn test_history_json_round_trip() {
+        let history = history(&[("a.adoc", "[[s]]\n== S\n\n=== T\n")]);
+        let json = history.to_json();
+        assert_eq!(json["pages"][0]["anchors"][1]["path"], json!([0, 0]));
+        assert_eq!(AnchorHistory::from_json(&json), Ok(history));
+
+        let error = AnchorHistory::from_json(&json!({ "pages": [{ "name": "a" }] }));
+        assert_eq!(
+            error,
+            Err(RedirectError::InvalidHistory(
+                "`anchors` must be an array".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,23 @@
+//! Output renderers
+//!
+//! Renderers walk a parsed [`Document`](crate::ast::Document) and produce an
+//! output format. Each format lives in its own submodule:
+//!
+//! - [`asciidoc`] - Canonical AsciiDoc source, for saving programmatic edits
+//! - [`html`] - Semantic HTML5, as a standalone page or an embeddable fragment
+//! - [`manpage`] - roff for `man`, following the AsciiDoc manpage doctype
+//! - [`measure`] - Pluggable text measurement for layout-aware backends
+//! - [`nav`] - Navigation sidebar partials for site shells
+//! - [`patch`] - Incremental HTML updates (DOM patches) for live preview
+//! - [`text`] - Plain text with word wrapping, for terminals and search indexing
+//! - `pdf` - Paged PDF output with basic layout (`pdf` feature)
+
+pub mod asciidoc;
+pub mod html;
+pub mod manpage;
+pub mod measure;
+pub mod nav;
+pub mod patch;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+pub mod text;
@@ -0,0 +1,1497 @@
+//! AsciiDoc output, for writing a changed document back to source
+//!
+//! [`render_asciidoc`] writes a [`Document`] as canonical AsciiDoc. Together
+//! with the parser this allows programmatic edits, such as renaming a
+//! section or inserting a block, that are saved as source again. Parsing the
+//! output gives back the same AST for any document the parser produced (see
+//! "Text" below for ASTs built in code).
+//!
+//! The canonical form is:
+//!
+//! - The header: title, author and revision lines, then attribute entries.
+//!   A revision is only written after an author line, since without one the
+//!   parser would read it as the authors.
+//! - Blocks separated by a blank line, each preceded by its title line
+//!   (`.Title`) and attribute list line when its [`BlockMetadata`] has them.
+//!   The attribute list starts with the style and its `#id`, `.role` and
+//!   `%option` shorthands, followed by the other positional attributes and
+//!   the named ones in name order.
+//! - `*` and `.` list markers, repeated once per enclosing list of the same
+//!   kind. Nested lists follow their item directly; other attached blocks
+//!   come after a `+` line, and a blank line before it when they follow a
+//!   nested list. A list that directly follows another list, of
+//!   any kind, is kept apart from it with an empty `//` comment line.
+//! - Delimited quote and verse blocks (`____`), `----` listings (`....` for
+//!   literal blocks without a style), and one table row per line, with cell
+//!   specifiers (`2+|`, `.2+|`) for spans. Delimiters grow longer as blocks
+//!   nest, and when a line of verbatim content looks like the delimiter.
+//! - Constrained formatting marks (`*bold*`) where they pair, unconstrained
+//!   ones (`**bo**ld`) inside words, around text with edge whitespace, and
+//!   where a mark character in or next to the text would pair in their
+//!   place (`**&*&**`).
+//! - Description list items with the separator they were read with (`::`,
+//!   `;;`), after a space when the term ends in punctuation. A description
+//!   that would make its line a block macro starts on the next line.
+//! - Paragraph and list item lines that would read as block syntax (an
+//!   attribute list, anchor, block title, comment or attribute entry)
+//!   indented by a space.
+//! - An empty `[]` after a bare URL when the text after it starts with `[`,
+//!   and an empty `[]` line before a level 0 section that starts a document
+//!   without a header.
+//!
+//! Details the AST does not keep are normalized: `-` list markers become
+//! `*`, an `[[id]]` line before a paragraph, list or other block without
+//! block attributes becomes an `#id` in its attribute list, and `[quote]`
+//! and `[verse]` paragraphs become delimited blocks, except a `[quote]`
+//! paragraph whose lines would read back as other blocks there. Comments are written if the
+//! parser kept them (see
+//! [`ParseOptions::keep_comments`](crate::options::ParseOptions::keep_comments)).
+//! Syntax the parser does not model ([`Block::Unknown`]) is written exactly
+//! as it was read.
+//!
+//! Fields that the parser derives from the attribute list line, such as a
+//! listing's language, a quote's attribution or a passthrough's backend, are
+//! added to the attribute list when the metadata lacks them, so blocks built
+//! in code keep them.
+//!
+//! # Text
+//!
+//! Formatting marks in text that would pair are escaped with a backslash
+//! before the opening mark, so the text of an escaped `\*literal*` pair is
+//! written as it was read and comes back as text. Other text is written as
+//! is. Text built in code that reads as block markup, such as a paragraph
+//! line starting with `* `, comes back as that markup, and a block after a
+//! section in the same list of blocks is read back as part of the section.
+//! Neither occurs in the ASTs the parser produces (sections contain all
+//! blocks after them).
+//!
+//! # Examples
+//!
+//! Renaming a section and adding a paragraph to it:
+//!
+//! ```
+//! use doctora::ast::{Block, BlockMetadata, Inline};
+//! use doctora::parse_document;
+//! use doctora::render::asciidoc::render_asciidoc;
+//!
+//! let mut doc = parse_document("= Guide\n\n== Setup\n\nRun *make*.\n").unwrap();
+//! let Block::Section { title, content, .. } = &mut doc.blocks[0] else {
+//!     panic!("expected a section");
+//! };
+//! *title = vec![Inline::Text("Installation".to_string())];
+//! content.push(Block::Paragraph {
+//!     content: vec![Inline::Text("Then run the tests.".to_string())],
+//!     metadata: BlockMetadata::default(),
+//! });
+//!
+//! assert_eq!(
+//!     render_asciidoc(&doc),
+//!     "= Guide\n\n== Installation\n\nRun *make*.\n\nThen run the tests.\n"
+//! );
+//! ```
+
+use crate::ast::{
+    Anchor, AttributeEntry, Block, BlockMetadata, BreakHint, Document, DocumentHeader, Inline,
+    ListItem, Revision, TableRow,
+};
+use crate::attributes::Attributes;
+use crate::formatting::{attribute_list_start, format_inlines, match_delimiters, scan_delimiters};
+use crate::parse_document;
+use crate::parser_winnow::attribute_entry;
+use crate::token::{Token, lex_with_spans};
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Render a document as AsciiDoc source
+pub fn render_asciidoc(doc: &Document) -> String {
+    let mut out = String::new();
+    if let Some(header) = &doc.header {
+        push_header(header, &mut out);
+        if !out.is_empty() && !doc.blocks.is_empty() {
+            out.push('\n');
+        }
+    }
+    let start = out.len();
+    push_blocks(&doc.blocks, Nesting::default(), &mut out);
+    // A level 0 section first in a document without a header would be read
+    // as its title; an empty attribute list line keeps it a section
+    if doc.header.is_none() && out[start..].starts_with("= ") {
+        out.insert_str(start, "[]\n");
+    }
+    out
+}
+
+/// How deeply a block is nested, which sets the length of its delimiters
+/// and list markers
+#[derive(Debug, Clone, Copy, Default)]
+struct Nesting {
+    /// Enclosing delimited blocks
+    delimited: usize,
+    /// Enclosing unordered lists, within the innermost delimited block
+    unordered: usize,
+    /// Enclosing ordered lists, within the innermost delimited block
+    ordered: usize,
+}
+
+impl Nesting {
+    /// The nesting of the content of a delimited block: lists start over
+    fn delimited(self) -> Self {
+        Self {
+            delimited: self.delimited + 1,
+            ..Self::default()
+        }
+    }
+}
+
+fn push_header(header: &DocumentHeader, out: &mut String) {
+    if let Some(title) = &header.title {
+        out.push_str(&format!("= {title}\n"));
+        if !header.authors.is_empty() {
+            let authors: Vec<String> = header
+                .authors
+                .iter()
+                .map(|author| match &author.email {
+                    Some(email) => format!("{} <{email}>", author.name),
+                    None => author.name.clone(),
+                })
+                .collect();
+            out.push_str(&authors.join("; "));
+            out.push('\n');
+            if let Some(line) = header.revision.as_ref().and_then(revision_line) {
+                out.push_str(&line);
+                out.push('\n');
+            }
+        }
+    }
+    for entry in &header.attributes {
+        push_entry(entry, out);
+    }
+}
+
+/// Push an attribute entry line (`:name: value`, `:name!:`)
+fn push_entry(entry: &AttributeEntry, out: &mut String) {
+    match &entry.value {
+        Some(value) if value.is_empty() => out.push_str(&format!(":{}:\n", entry.name)),
+        Some(value) => out.push_str(&format!(":{}: {value}\n", entry.name)),
+        None => out.push_str(&format!(":{}!:\n", entry.name)),
+    }
+}
+
+/// The revision line (`v1.2, 2024-05-01: remark`), if the revision has a
+/// number or date to start it
+fn revision_line(revision: &Revision) -> Option<String> {
+    let mut line = match (&revision.number, &revision.date) {
+        (Some(number), Some(date)) => format!("v{number}, {date}"),
+        (Some(number), None) => format!("v{number}"),
+        (None, Some(date)) => date.clone(),
+        (None, None) => return None,
+    };
+    if let Some(remark) = &revision.remark {
+        line.push_str(&format!(": {remark}"));
+    }
+    Some(line)
+}
+
+fn push_blocks(blocks: &[Block], nesting: Nesting, out: &mut String) {
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+            if follows_list(&blocks[i - 1], block) {
+                out.push_str("//\n\n");
+            }
+        }
+        push_block(block, nesting, out);
+    }
+}
+
+/// Returns true if `next`, written after `previous`, would be read as part
+/// of that list: more of its items, or a list nested in its last item
+fn follows_list(previous: &Block, next: &Block) -> bool {
+    let is_list = |block: &Block| {
+        matches!(
+            block,
+            Block::UnorderedList { .. } | Block::OrderedList { .. } | Block::DescriptionList { .. }
+        )
+    };
+    is_list(previous) && is_list(next) && next.metadata().is_empty()
+}
+
+fn push_block(block: &Block, nesting: Nesting, out: &mut String) {
+    let metadata = written_metadata(block);
+    if let Some(title) = &metadata.title {
+        out.push_str(&format!(".{}\n", inlines(title)));
+    }
+    if let Some(anchor) = anchor_line(block) {
+        out.push_str(&format!("{}\n", anchor_macro(&anchor)));
+    }
+    if let Some(list) = attribute_list(&metadata) {
+        out.push_str(&format!("[{list}]\n"));
+    }
+
+    match block {
+        Block::Section {
+            level,
+            title,
+            content,
+            ..
+        } => {
+            out.push_str(&format!(
+                "{} {}\n",
+                "=".repeat(*level as usize),
+                inlines(title)
+            ));
+            if !content.is_empty() {
+                out.push('\n');
+                push_blocks(content, nesting, out);
+            }
+        }
+        Block::Paragraph { content, .. } => {
+            push_text_lines(&inlines(content), out);
+        }
+        Block::UnorderedList { items, .. } => push_list(items, false, nesting, out),
+        Block::OrderedList { items, .. } => push_list(items, true, nesting, out),
+        Block::DescriptionList {
+            items, separator, ..
+        } => {
+            for item in items {
+                let mut line = inlines(&item.term);
+                // A separator right after punctuation would continue it
+                let bold = lex_with_spans(&line)
+                    .last()
+                    .is_some_and(|(token, _)| *token == Token::BoldDelimiter);
+                if line.ends_with(is_punctuation) && !bold {
+                    line.push(' ');
+                }
+                line.push_str(separator);
+                if !item.description.is_empty() {
+                    let description = inlines(&item.description);
+                    // A description closing a `name::target[` term would
+                    // make the line a block macro; start it on the next line
+                    let first = description.lines().next().unwrap_or_default();
+                    let macro_ = reads_as_block_macro(&format!("{line} {first}"));
+                    line.push(if macro_ { '\n' } else { ' ' });
+                    line.push_str(&description);
+                }
+                push_text_lines(&line, out);
+            }
+        }
+        Block::Listing {
+            content,
+            attributes,
+            ..
+        } => {
+            let literal = metadata.style().is_none() && attributes.get("style") == Some("literal");
+            push_verbatim(content, if literal { '.' } else { '-' }, out);
+        }
+        Block::Quote { .. } if let Some(content) = quoted_paragraph(block) => {
+            out.push_str(&block_inlines(content));
+            out.push('\n');
+        }
+        Block::Quote { content, .. } => {
+            let delimiter = "_".repeat(4 + nesting.delimited);
+            out.push_str(&format!("{delimiter}\n"));
+            push_blocks(content, nesting.delimited(), out);
+            out.push_str(&format!("{delimiter}\n"));
+        }
+        Block::Verse { content, .. } => {
+            let delimiter = "_".repeat(4 + nesting.delimited);
+            out.push_str(&format!("{delimiter}\n"));
+            if !content.is_empty() {
+                out.push_str(&block_inlines(content));
+                out.push('\n');
+            }
+            out.push_str(&format!("{delimiter}\n"));
+        }
+        Block::Admonition { kind, content, .. } => match admonition_paragraph(block) {
+            Some(paragraph) if metadata.style() == Some(kind.label()) => {
+                out.push_str(&block_inlines(paragraph));
+                out.push('\n');
+            }
+            Some(paragraph) => {
+                let text = paired_inlines(paragraph, Pairing::Across { indented: true });
+                out.push_str(&format!("{}: {text}\n", kind.label()));
+            }
+            None => push_compound(content, '=', nesting, out),
+        },
+        Block::Example { content, .. } => push_compound(content, '=', nesting, out),
+        Block::Sidebar { content, .. } => push_compound(content, '*', nesting, out),
+        Block::Open { content, .. } => {
+            out.push_str("--\n");
+            push_blocks(content, nesting.delimited(), out);
+            out.push_str("--\n");
+        }
+        Block::Table { header, rows, .. } => {
+            out.push_str("|===\n");
+            if let Some(header) = header {
+                push_row(header, out);
+                if !metadata.has_option("header") {
+                    out.push('\n');
+                }
+            }
+            for row in rows {
+                push_row(row, out);
+            }
+            out.push_str("|===\n");
+        }
+        Block::Image {
+            target, attributes, ..
+        } => {
+            // The attribute list line's attributes are written there
+            let from_line = |name: &str, value: &str| match name {
+                "id" => metadata.id.as_deref() == Some(value),
+                "role" => metadata.roles.join(" ") == value,
+                _ => metadata.named.get(name).is_some_and(|named| named == value),
+            };
+            let list = macro_attributes(attributes, from_line);
+            out.push_str(&format!("image::{target}[{list}]\n"));
+        }
+        Block::Passthrough { content, .. } => push_verbatim(content, '+', out),
+        Block::Comment {
+            content, delimited, ..
+        } => {
+            if *delimited {
+                push_verbatim(content, '/', out);
+            } else {
+                out.push_str(&format!("//{content}\n"));
+            }
+        }
+        Block::AttributeEntry { entry, .. } => push_entry(entry, out),
+        Block::Unknown { raw, .. } => {
+            out.push_str(raw);
+            out.push('\n');
+        }
+    }
+}
+
+/// The block's metadata, with the attributes its own fields stand for added
+/// if it lacks them
+fn written_metadata(block: &Block) -> Cow<'_, BlockMetadata> {
+    let metadata = block.metadata();
+    match block {
+        Block::Listing {
+            language: Some(language),
+            ..
+        } if metadata.positional.is_empty() => Cow::Owned(BlockMetadata {
+            positional: vec!["source".to_string(), language.clone()],
+            ..metadata.clone()
+        }),
+        Block::Quote {
+            attribution,
+            citation,
+            ..
+        } => {
+            let styled = quoted_paragraph(block).is_some();
+            quote_metadata(metadata, "quote", styled, attribution, citation)
+        }
+        Block::Verse {
+            attribution,
+            citation,
+            ..
+        } => quote_metadata(metadata, "verse", true, attribution, citation),
+        // A paragraph can be written as `NOTE: text`, a block needs the label
+        Block::Admonition { kind, .. }
+            if metadata.style() != Some(kind.label()) && admonition_paragraph(block).is_none() =>
+        {
+            let mut metadata = metadata.clone();
+            match metadata.positional.first_mut() {
+                Some(style) => *style = kind.label().to_string(),
+                None => metadata.positional.push(kind.label().to_string()),
+            }
+            Cow::Owned(metadata)
+        }
+        Block::Passthrough { backend, .. }
+            if backend != "html" && !metadata.named.contains_key("backend") =>
+        {
+            let mut metadata = metadata.clone();
+            metadata
+                .named
+                .insert("backend".to_string(), backend.clone());
+            Cow::Owned(metadata)
+        }
+        Block::Table {
+            columns,
+            header,
+            rows,
+            ..
+        } => {
+            // Without `cols`, the cells of the first line set the columns
+            let width: usize = header
+                .iter()
+                .chain(rows)
+                .next()
+                .map_or(0, |row| row.cells.iter().map(|cell| cell.colspan).sum());
+            let inferred = columns.len() == width && columns.iter().all(|column| column.width == 1);
+            if inferred || metadata.named.contains_key("cols") {
+                return Cow::Borrowed(metadata);
+            }
+            let widths: Vec<String> = columns
+                .iter()
+                .map(|column| column.width.to_string())
+                .collect();
+            let mut metadata = metadata.clone();
+            metadata.named.insert("cols".to_string(), widths.join(","));
+            Cow::Owned(metadata)
+        }
+        _ => Cow::Borrowed(metadata),
+    }
+}
+
+/// The paragraph of a quote that is written as a `[quote]` paragraph: its
+/// only block, without metadata, when the lines of a delimited quote would
+/// read back as other blocks
+///
+/// The parser only gives a paragraph several lines in a `[quote]` paragraph;
+/// in a delimited quote each line is a paragraph of its own.
+fn quoted_paragraph(block: &Block) -> Option<&[Inline]> {
+    let Block::Quote { content, .. } = block else {
+        return None;
+    };
+    let [Block::Paragraph { content, metadata }] = content.as_slice() else {
+        return None;
+    };
+    if !metadata.is_empty() {
+        return None;
+    }
+    let mut lines = String::new();
+    push_text_lines(&inlines(content), &mut lines);
+    let delimited_reads_back = parse_document(&lines).is_ok_and(|doc| {
+        matches!(
+            doc.blocks.as_slice(),
+            [Block::Paragraph { content: read, metadata }] if read == content && metadata.is_empty()
+        )
+    });
+    (!delimited_reads_back).then_some(content)
+}
+
+/// The paragraph of an admonition that is written as `NOTE: text` or a
+/// `[NOTE]` paragraph: its only block, without metadata
+fn admonition_paragraph(block: &Block) -> Option<&[Inline]> {
+    match block {
+        Block::Admonition { content, .. } => match content.as_slice() {
+            [Block::Paragraph { content, metadata }] if metadata.is_empty() => Some(content),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The metadata of a quote or verse block, with its style, attribution and
+/// citation filled in
+///
+/// A verse, and a quote written as a `[quote]` paragraph (`styled`), always
+/// need their style; a delimited quote only needs one to hold an attribution
+/// or citation.
+fn quote_metadata<'a>(
+    metadata: &'a BlockMetadata,
+    style: &str,
+    styled: bool,
+    attribution: &Option<String>,
+    citation: &Option<String>,
+) -> Cow<'a, BlockMetadata> {
+    let missing = |value: &Option<String>, name: &str, position: usize| {
+        value.clone().filter(|_| {
+            !metadata.named.contains_key(name)
+                && metadata
+                    .positional
+                    .get(position)
+                    .is_none_or(String::is_empty)
+        })
+    };
+    let attribution = missing(attribution, "attribution", 1);
+    let citation = missing(citation, "citetitle", 2);
+    let restyle = styled && metadata.style() != Some(style);
+    if attribution.is_none() && citation.is_none() && !restyle {
+        return Cow::Borrowed(metadata);
+    }
+    let mut metadata = metadata.clone();
+    let positional = &mut metadata.positional;
+    if positional.is_empty() {
+        positional.push(String::new());
+    }
+    if restyle || positional[0].is_empty() {
+        positional[0] = style.to_string();
+    }
+    for (position, value) in [(1, attribution), (2, citation)] {
+        if let Some(value) = value {
+            if positional.len() <= position {
+                positional.resize(position + 1, String::new());
+            }
+            positional[position] = value;
+        }
+    }
+    Cow::Owned(metadata)
+}
+
+/// The anchor to write on a line of its own, for anchors the attribute list
+/// line does not give
+fn anchor_line(block: &Block) -> Option<Anchor> {
+    let metadata = block.metadata();
+    match block {
+        Block::Section {
+            anchor: Some(anchor),
+            ..
+        } if metadata.id.as_ref() != Some(&anchor.id) => Some(anchor.clone()),
+        Block::Listing { attributes, .. }
+        | Block::Table { attributes, .. }
+        | Block::Open { attributes, .. }
+            if metadata.id.is_none() =>
+        {
+            Some(Anchor {
+                id: attributes.id()?.to_string(),
+                reftext: attributes.get("reftext").map(str::to_string),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// The attribute list (without brackets) of `metadata`, if it has any
+/// attributes
+fn attribute_list(metadata: &BlockMetadata) -> Option<String> {
+    // Roles and options from the named attributes come after the shorthand
+    // ones; only the shorthand ones go into the first positional attribute
+    let named_roles = metadata
+        .named
+        .get("role")
+        .map_or(0, |roles| roles.split_whitespace().count());
+    let named_options: usize = ["options", "opts"]
+        .into_iter()
+        .filter_map(|name| metadata.named.get(name))
+        .map(|list| {
+            list.split(',')
+                .filter(|option| !option.trim().is_empty())
+                .count()
+        })
+        .sum();
+
+    let mut first = metadata.style().unwrap_or_default().to_string();
+    if let Some(id) = &metadata.id
+        && metadata.named.get("id") != Some(id)
+    {
+        first.push_str(&format!("#{id}"));
+    }
+    let roles = metadata.roles.len().saturating_sub(named_roles);
+    for role in &metadata.roles[..roles] {
+        first.push_str(&format!(".{role}"));
+    }
+    let options = metadata.options.len().saturating_sub(named_options);
+    for option in &metadata.options[..options] {
+        first.push_str(&format!("%{option}"));
+    }
+
+    let mut entries = Vec::new();
+    if !first.is_empty() || metadata.positional.len() > 1 {
+        entries.push(first);
+    }
+    entries.extend(
+        metadata
+            .positional
+            .iter()
+            .skip(1)
+            .map(|value| quoted(value).into_owned()),
+    );
+    entries.extend(
+        metadata
+            .named
+            .iter()
+            .map(|(name, value)| format!("{name}={}", quoted(value))),
+    );
+    (!entries.is_empty()).then(|| entries.join(","))
+}
+
+/// `value` as an attribute list entry, in double quotes if it would
+/// otherwise be split, trimmed or read as a named attribute
+fn quoted(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '=']) || value.trim() != value {
+        Cow::Owned(format!("\"{value}\""))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// The attribute list of an image macro: `alt`, `width` and `height` as
+/// positional attributes, then the named ones except those `skip` accepts
+fn macro_attributes(attributes: &Attributes, skip: impl Fn(&str, &str) -> bool) -> String {
+    let mut entries: Vec<String> = ["alt", "width", "height"]
+        .into_iter()
+        .map(|name| {
+            attributes
+                .get(name)
+                .map(quoted)
+                .unwrap_or_default()
+                .into_owned()
+        })
+        .collect();
+    while entries.last().is_some_and(String::is_empty) {
+        entries.pop();
+    }
+    entries.extend(
+        attributes
+            .iter()
+            .filter(|&(name, value)| {
+                !matches!(name, "alt" | "width" | "height") && !skip(name, value)
+            })
+            .map(|(name, value)| format!("{name}={}", quoted(value))),
+    );
+    entries.join(",")
+}
+
+fn push_list(items: &[ListItem], ordered: bool, nesting: Nesting, out: &mut String) {
+    let mut nesting = nesting;
+    let (mark, depth) = if ordered {
+        (".", &mut nesting.ordered)
+    } else {
+        ("*", &mut nesting.unordered)
+    };
+    *depth += 1;
+    let marker = mark.repeat(*depth);
+    for item in items {
+        push_text_lines(&format!("{marker} {}", inlines(&item.content)), out);
+        let is_list = |block: &Block| {
+            matches!(
+                block,
+                Block::UnorderedList { .. } | Block::OrderedList { .. }
+            )
+        };
+        for (i, block) in item.blocks.iter().enumerate() {
+            if !is_list(block) {
+                // Right after a nested list, the continuation would attach
+                // to its last item
+                if i > 0 && is_list(&item.blocks[i - 1]) {
+                    out.push('\n');
+                }
+                out.push_str("+\n");
+            }
+            push_block(block, nesting, out);
+        }
+    }
+}
+
+/// Push an example or sidebar block, its delimiter `mark` repeated
+fn push_compound(content: &[Block], mark: char, nesting: Nesting, out: &mut String) {
+    let delimiter = mark.to_string().repeat(4 + nesting.delimited);
+    out.push_str(&format!("{delimiter}\n"));
+    push_blocks(content, nesting.delimited(), out);
+    out.push_str(&format!("{delimiter}\n"));
+}
+
+/// Push a block with verbatim content between delimiters of `mark`, long
+/// enough that no content line closes the block early
+fn push_verbatim(content: &str, mark: char, out: &mut String) {
+    let mut delimiter = mark.to_string().repeat(4);
+    while content
+        .lines()
+        .any(|line| line.trim_end_matches([' ', '\t', '\r']) == delimiter)
+    {
+        delimiter.push(mark);
+    }
+    out.push_str(&format!("{delimiter}\n"));
+    if !content.is_empty() {
+        out.push_str(content);
+        out.push('\n');
+    }
+    out.push_str(&format!("{delimiter}\n"));
+}
+
+/// Push the lines of a paragraph's or list item's text, each indented by a
+/// space if it would otherwise read as a line of block syntax: a comment, an
+/// attribute list or anchor, a block title or an attribute entry
+fn push_text_lines(text: &str, out: &mut String) {
+    for line in text.split('\n') {
+        if reads_as_block_line(line) {
+            out.push(' ');
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+}
+
+/// Whether `line`, at the start of a source line, is block syntax that an
+/// indented line is not
+fn reads_as_block_line(line: &str) -> bool {
+    let line = line.trim_end();
+    (line.starts_with("//") && !line.starts_with("///"))
+        || (line.starts_with('[') && line.ends_with(']'))
+        || line
+            .strip_prefix('.')
+            .is_some_and(|rest| rest.starts_with(|c: char| !c.is_whitespace() && c != '.'))
+        || attribute_entry(line).is_some()
+}
+
+/// Whether `line` has the `name::target[attributes]` shape of a block macro
+fn reads_as_block_macro(line: &str) -> bool {
+    line.split_once("::").is_some_and(|(name, rest)| {
+        name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            && rest
+                .strip_suffix(']')
+                .and_then(|rest| rest.split_once('['))
+                .is_some_and(|(target, _)| !target.contains(char::is_whitespace))
+    })
+}
+
+fn push_row(row: &TableRow, out: &mut String) {
+    let cells: Vec<String> = row
+        .cells
+        .iter()
+        .map(|cell| {
+            let spec = match (cell.colspan, cell.rowspan) {
+                (1, 1) => String::new(),
+                (colspan, 1) => format!("{colspan}+"),
+                (1, rowspan) => format!(".{rowspan}+"),
+                (colspan, rowspan) => format!("{colspan}.{rowspan}+"),
+            };
+            let content = inlines(&cell.content);
+            if content.is_empty() {
+                format!("{spec}|")
+            } else {
+                format!("{spec}| {content}")
+            }
+        })
+        .collect();
+    out.push_str(&cells.join(" "));
+    out.push('\n');
+}
+
+/// Render inline content as it would be written on its own line
+///
+/// Formatting marks in text that would pair are escaped (see "Text" in the
+/// module documentation). The marks are paired across each whole line, since
+/// a mark in one text node can pair with a mark in another, but not across
+/// lines, since the parser pairs the marks of each line on its own.
+fn inlines(content: &[Inline]) -> String {
+    paired_inlines(content, Pairing::PerLine)
+}
+
+/// Render the inline content of a verse or `[quote]` paragraph, whose
+/// formatting marks pair across its lines
+///
+/// The first line is indented, like [`push_text_lines`] does, if it would
+/// otherwise read as a comment; the parser keeps the indentation of the
+/// other lines.
+fn block_inlines(content: &[Inline]) -> String {
+    let text = paired_inlines(content, Pairing::Across { indented: false });
+    if format_inlines(&text, &lex_with_spans(&text)) == content {
+        return text;
+    }
+    format!(
+        " {}",
+        paired_inlines(content, Pairing::Across { indented: true })
+    )
+}
+
+fn paired_inlines(content: &[Inline], pairing: Pairing) -> String {
+    let mut out = String::new();
+    let mut texts = Vec::new();
+    push_inlines(content, pairing, &mut out, &mut texts);
+    escape_marks(&mut out, &texts, pairing);
+    out
+}
+
+/// Where the parser pairs the formatting marks of content that spans lines
+#[derive(Debug, Clone, Copy)]
+enum Pairing {
+    /// Within each line
+    PerLine,
+    /// Across all lines, the first one `indented` by a space
+    Across { indented: bool },
+}
+
+/// Push inline content; the spans of `out` written from text nodes are added
+/// to `texts`
+fn push_inlines(
+    content: &[Inline],
+    pairing: Pairing,
+    out: &mut String,
+    texts: &mut Vec<Range<usize>>,
+) {
+    for (i, inline) in content.iter().enumerate() {
+        let next = content[i + 1..].iter().find_map(first_char);
+        push_inline(inline, next, pairing, out, texts);
+    }
+}
+
+/// Push one inline node; `next` is the first character written after it
+fn push_inline(
+    inline: &Inline,
+    next: Option<char>,
+    pairing: Pairing,
+    out: &mut String,
+    texts: &mut Vec<Range<usize>>,
+) {
+    match inline {
+        Inline::Text(text) => {
+            texts.push(out.len()..out.len() + text.len());
+            out.push_str(text);
+        }
+        Inline::Bold(content) => push_marked(inline, content, '*', next, pairing, out, texts),
+        Inline::Italic(content) => push_marked(inline, content, '_', next, pairing, out, texts),
+        Inline::Monospace(content) => push_marked(inline, content, '`', next, pairing, out, texts),
+        Inline::Highlight(content) => {
+            escape_attribute_list(out, texts);
+            push_marked(inline, content, '#', next, pairing, out, texts)
+        }
+        Inline::Span { roles, content } => {
+            out.push_str(&role_list(roles));
+            push_marked(inline, content, '#', next, pairing, out, texts)
+        }
+        Inline::Superscript(content) => {
+            out.push('^');
+            push_inlines(content, pairing, out, texts);
+            out.push('^');
+        }
+        Inline::Subscript(content) => {
+            out.push('~');
+            push_inlines(content, pairing, out, texts);
+            out.push('~');
+        }
+        Inline::Link {
+            target,
+            text,
+            attributes,
+        } => push_link(target, text, attributes, next, out),
+        Inline::XRef { id, text } if text.is_empty() => out.push_str(&format!("<<{id}>>")),
+        Inline::XRef { id, text } => out.push_str(&format!("<<{id},{}>>", inlines(text))),
+        Inline::Anchor(anchor) => out.push_str(&anchor_macro(anchor)),
+        Inline::Image { target, attributes } => {
+            let list = macro_attributes(attributes, |_, _| false);
+            out.push_str(&format!("image:{target}[{list}]"));
+        }
+        Inline::Icon { name, attributes } => {
+            let list = macro_attributes(attributes, |_, _| false);
+            out.push_str(&format!("icon:{name}[{list}]"));
+        }
+        Inline::Footnote { id, text } => out.push_str(&format!(
+            "footnote:{}[{}]",
+            id.as_deref().unwrap_or_default(),
+            inlines(text)
+        )),
+        Inline::BreakHint(BreakHint::SoftHyphen) => out.push_str("&shy;"),
+        Inline::BreakHint(BreakHint::NoBreakSpace) => out.push_str("&nbsp;"),
+    }
+}
+
+/// Escape the pairs of formatting marks in `content` that open in text (the
+/// spans `texts`), with a backslash before the opening mark
+///
+/// Escaping keeps the pairing of the other marks, so the formatting written
+/// for the AST still applies, inside escaped pairs too. Lines are lexed as
+/// they are read after other text on the line, as after a list marker or
+/// the space that [`push_text_lines`] indents them with, so a leading `//`
+/// is not a comment and a leading `**` is not a list marker.
+fn escape_marks(content: &mut String, texts: &[Range<usize>], pairing: Pairing) {
+    let mut openers = Vec::new();
+    let mut line_start = 0;
+    let (lines, indent) = match pairing {
+        Pairing::PerLine => (content.split('\n').collect(), "a "),
+        Pairing::Across { indented } => (vec![content.as_str()], if indented { "a " } else { "" }),
+    };
+    for line in lines {
+        let source = format!("{indent}{line}");
+        let delimiters = scan_delimiters(&source, &lex_with_spans(&source));
+        openers.extend(
+            match_delimiters(&delimiters)
+                .into_iter()
+                .map(|(open, _)| line_start + delimiters[open].span.start - indent.len())
+                .filter(|start| texts.iter().any(|text| text.contains(start))),
+        );
+        line_start += line.len() + 1;
+    }
+    openers.sort_unstable();
+    for start in openers.into_iter().rev() {
+        content.insert(start, '\\');
+    }
+}
+
+/// Push `inline`, formatted `content` between marks: constrained
+/// (`*text*`) if that form reads back as `inline` where it stands, and
+/// unconstrained (`**text**`) otherwise
+///
+/// A mark character next to the pair, in the content, or earlier on the
+/// line could pair or close in place of the marks written, so the
+/// constrained form is only used if the line reads back with `inline` at
+/// its end.
+fn push_marked(
+    inline: &Inline,
+    content: &[Inline],
+    mark: char,
+    next: Option<char>,
+    pairing: Pairing,
+    out: &mut String,
+    texts: &mut Vec<Range<usize>>,
+) {
+    let before = out.chars().next_back();
+    let start = out.len();
+    // Written after the mark, which is what precedes the content in the
+    // output either way
+    out.push(mark);
+    let first_text = texts.len();
+    push_inlines(content, pairing, out, texts);
+    let inner = &out[start + mark.len_utf8()..];
+    let constrained = before.is_none_or(|c| !is_word(c) && !matches!(c, ';' | ':' | '}'))
+        && inner.starts_with(|c: char| !c.is_whitespace())
+        && inner.ends_with(|c: char| !c.is_whitespace())
+        && next.is_none_or(|c| !is_word(c) && c != mark);
+    out.push(mark);
+    if constrained && reads_back(inline, out, texts, pairing) {
+        return;
+    }
+    out.pop();
+    out.insert(start, mark);
+    for text in &mut texts[first_text..] {
+        *text = text.start + mark.len_utf8()..text.end + mark.len_utf8();
+    }
+    out.push(mark);
+    out.push(mark);
+}
+
+/// Whether the content written so far, `out` with the text spans `texts`,
+/// parses to content ending in `inline` once its text is escaped (its last
+/// line, if marks pair per line)
+fn reads_back(inline: &Inline, out: &str, texts: &[Range<usize>], pairing: Pairing) -> bool {
+    let mut content = out.to_string();
+    escape_marks(&mut content, texts, pairing);
+    let source = match pairing {
+        Pairing::PerLine => format!(" {}", content.rsplit('\n').next().unwrap_or_default()),
+        Pairing::Across { indented: true } => format!(" {content}"),
+        Pairing::Across { indented: false } => content,
+    };
+    format_inlines(&source, &lex_with_spans(&source)).last() == Some(inline)
+}
+
+/// Push a link: a bare URL, or a link macro with its text and attributes
+///
+/// A `window=_blank` attribute is written as a `^` after the text, the way
+/// the parser reads it. A bare URL gets empty brackets unless `next`, the
+/// character written after it, ends it.
+fn push_link(
+    target: &str,
+    text: &[Inline],
+    attributes: &Attributes,
+    next: Option<char>,
+    out: &mut String,
+) {
+    let caret = attributes.get("window") == Some("_blank")
+        && attributes.get("1").is_none_or(|first| first.ends_with('^'));
+    let named: Vec<String> = attributes
+        .iter()
+        .filter(|&(name, _)| name != "1" && !(caret && name == "window"))
+        .map(|(name, value)| format!("{name}={}", quoted(value)))
+        .collect();
+    let mut first = inlines(text);
+    if caret {
+        first.push('^');
+    }
+    out.push_str(target);
+    let ends_url = next.is_none_or(|c| c.is_whitespace() || matches!(c, ']' | '<' | '>' | '"'));
+    if first.is_empty() && named.is_empty() && ends_url {
+        return;
+    }
+    // Without named attributes the brackets hold the text as is
+    let list = match (first.is_empty(), named.is_empty()) {
+        (_, true) => first,
+        (true, false) => named.join(","),
+        (false, false) => format!("{},{}", quoted(&first), named.join(",")),
+    };
+    out.push_str(&format!("[{list}]"));
+}
+
+/// Escape an attribute list that ends the text written so far, so that a
+/// highlight written next does not read it as its roles (`\\[x]#text#`)
+fn escape_attribute_list(out: &mut String, texts: &mut [Range<usize>]) {
+    let Some(text) = texts.last_mut().filter(|text| text.end == out.len()) else {
+        return;
+    };
+    if let Some(open) = attribute_list_start(&out[text.clone()]) {
+        out.insert(text.start + open, '\\');
+        text.end += 1;
+    }
+}
+
+/// The attribute list of a span: `.role` shorthands, or a `role` attribute
+/// for roles a shorthand cannot hold
+fn role_list(roles: &[String]) -> String {
+    let shorthand = |role: &String| {
+        !role.is_empty()
+            && !role.contains(|c: char| {
+                c.is_whitespace() || matches!(c, '.' | '#' | '%' | ',' | '=' | '"' | '[' | ']')
+            })
+    };
+    if roles.iter().all(shorthand) {
+        format!("[.{}]", roles.join("."))
+    } else {
+        format!("[role=\"{}\"]", roles.join(" "))
+    }
+}
+
+/// `[[id]]` or `[[id,reftext]]`
+fn anchor_macro(anchor: &Anchor) -> String {
+    match &anchor.reftext {
+        Some(reftext) => format!("[[{},{reftext}]]", anchor.id),
+        None => format!("[[{}]]", anchor.id),
+    }
+}
+
+/// The first character `inline` is written with, if it writes any
+fn first_char(inline: &Inline) -> Option<char> {
+    match inline {
+        Inline::Text(text) => text.chars().next(),
+        Inline::Bold(_) => Some('*'),
+        Inline::Italic(_) => Some('_'),
+        Inline::Monospace(_) => Some('`'),
+        Inline::Highlight(_) => Some('#'),
+        Inline::Span { .. } => Some('['),
+        Inline::Superscript(_) => Some('^'),
+        Inline::Subscript(_) => Some('~'),
+        Inline::Link { target, .. } => target.chars().next(),
+        Inline::XRef { .. } => Some('<'),
+        Inline::Anchor(_) => Some('['),
+        Inline::Image { .. } | Inline::Icon { .. } => Some('i'),
+        Inline::Footnote { .. } => Some('f'),
+        Inline::BreakHint(_) => Some('&'),
+    }
+}
+
+/// Characters the lexer reads as punctuation, which runs on with an adjacent
+/// `::` or `;;`
+fn is_punctuation(c: char) -> bool {
+    matches!(c, '!' | '"' | '$'..='/' | ':'..='<' | '>'..='@' | '['..=']' | '{'..='}')
+}
+
+/// Word characters, which constrained marks must not touch on the outside
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::visit::{VisitorMut, walk_block_mut};
+    use crate::ast::{ColumnSpec, TableCell};
+    use crate::parse_document;
+    use std::fs;
+    use std::path::Path;
+
+    /// Render `source`, and check that the output parses to the same AST and
+    /// renders to itself
+    fn round_trip(source: &str) -> String {
+        let doc = parse_document(source).unwrap();
+        let rendered = render_asciidoc(&doc);
+        let reparsed = parse_document(&rendered)
+            .unwrap_or_else(|err| panic!("{err} in rendered source:\n{rendered}"));
+        assert_eq!(reparsed, doc, "rendered source:\n{rendered}");
+        assert_eq!(render_asciidoc(&reparsed), rendered);
+        rendered
+    }
+
+    #[test]
+    fn test_round_trip_golden_fixtures() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "adoc") {
+                round_trip(&fs::read_to_string(&path).unwrap());
+            }
+        }
+    }
+
+    /// A xorshift generator, so the round trip property needs no extra
+    /// dependencies and failures reproduce from the seed
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+
+        fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+            items[self.below(items.len())]
+        }
+    }
+
+    /// Line prefixes that read as block syntax or change how the line is
+    /// read, and lines of block attributes
+    const LINE_STARTS: &[&str] = &[
+        "",
+        "",
+        "",
+        "",
+        "* ",
+        "** ",
+        ". ",
+        ".. ",
+        "- ",
+        ".",
+        "== ",
+        "=== ",
+        " ",
+        "  ",
+        "//",
+        "// ",
+        " //",
+        "+",
+        ":v: ",
+        ":w!:",
+        "Term:: ",
+        "T;; ",
+        "NOTE: ",
+        "TIP:",
+        "image::i.png[]",
+        "\\",
+        "[[a]]\n",
+        "[[b,Bee]]\n",
+        "[#c]\n",
+        "[.r]\n",
+        "[quote,a,b]\n",
+        "[verse]\n",
+        "[source,rust]\n",
+        "[cols=2]\n",
+    ];
+
+    /// Delimiters of blocks, written in pairs around other lines
+    const DELIMITERS: &[&str] = &["----", "....", "____", "====", "****", "--", "++++", "////"];
+
+    /// Lines that stand on their own
+    const BLOCK_LINES: &[&str] = &["+", "", "'''", "|===\n| a | b\n2+| c\n|==="];
+
+    /// Words and marks a line is made of
+    const WORDS: &[&str] = &[
+        "a",
+        "Version",
+        "1.",
+        "x",
+        "*",
+        "**",
+        "_",
+        "__",
+        "`",
+        "#",
+        "^",
+        "~",
+        "[[c]]",
+        "[[d,Dee]]",
+        "x [.r]#h#",
+        "x [y]",
+        "<<a>>",
+        "<<a,t>>",
+        "https://x.y",
+        "https://x.y[t]",
+        "https://x.y[][..]",
+        "::",
+        ";;",
+        ":",
+        "//",
+        "{v}",
+        "\\",
+        "image:i.png[]",
+        "footnote:[n]",
+        "|",
+        "+",
+        "a*b*",
+        "_i_",
+        "*b*",
+        ".",
+        ",",
+        "x:",
+        "&",
+        "-",
+        "=",
+        "==",
+        "<",
+        ">",
+    ];
+
+    /// A random line built from the fragments above
+    fn random_line(rng: &mut Rng, source: &mut String) {
+        source.push_str(rng.pick(LINE_STARTS));
+        for i in 0..rng.below(6) {
+            if i > 0 && rng.below(3) > 0 {
+                source.push(' ');
+            }
+            source.push_str(rng.pick(WORDS));
+        }
+    }
+
+    /// Up to `lines` random lines and blocks, with delimited blocks nested
+    /// up to `depth` deep
+    fn random_blocks(rng: &mut Rng, lines: usize, depth: usize, source: &mut String) {
+        for _ in 0..=rng.below(lines) {
+            match rng.below(8) {
+                0 if depth > 0 => {
+                    let delimiter = rng.pick(DELIMITERS);
+                    source.push_str(delimiter);
+                    source.push('\n');
+                    random_blocks(rng, 3, depth - 1, source);
+                    source.push_str(delimiter);
+                }
+                1 => source.push_str(rng.pick(BLOCK_LINES)),
+                _ => random_line(rng, source),
+            }
+            source.push_str(if rng.below(3) == 0 { "\n\n" } else { "\n" });
+        }
+    }
+
+    /// A random document: a paragraph, so that what follows is not read as
+    /// the header, then up to 12 random lines and blocks
+    fn random_document(rng: &mut Rng) -> String {
+        let mut source = "Intro\n\n".to_string();
+        random_blocks(rng, 12, 2, &mut source);
+        source
+    }
+
+    /// `doc` with the source spans of unmodeled blocks cleared, since they
+    /// move when the rendered source differs in length
+    fn without_spans(mut doc: Document) -> Document {
+        struct ClearSpans;
+        impl VisitorMut for ClearSpans {
+            fn visit_block_mut(&mut self, block: &mut Block) {
+                if let Block::Unknown { span, .. } = block {
+                    *span = 0..0;
+                }
+                walk_block_mut(self, block);
+            }
+        }
+        ClearSpans.visit_document_mut(&mut doc);
+        doc
+    }
+
+    #[test]
+    fn test_round_trip_random_documents() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let mut failures = Vec::new();
+        for _ in 0..5_000 {
+            let source = random_document(&mut rng);
+            let Ok(doc) = parse_document(&source) else {
+                continue;
+            };
+            let rendered = render_asciidoc(&doc);
+            match parse_document(&rendered) {
+                Ok(reparsed)
+                    if without_spans(reparsed.clone()) == without_spans(doc)
+                        && render_asciidoc(&reparsed) == rendered => {}
+                _ => failures.push((source, rendered)),
+            }
+        }
+        assert!(
+            failures.is_empty(),
+            "{} documents did not round-trip, first: {:?}",
+            failures.len(),
+            failures.first()
+        );
+    }
+
+    #[test]
+    fn test_canonical_form() {
+        let source = "= Title\nAda Lovelace <ada@example.com>; Charles\nv1.2, 2024-05-01: Draft\n\
+                      :toc:\n:icons!:\n\n\
+                      - one\n** nested\n. ordered\n+\ncontinued\n\n\
+                      [[intro,Intro]]\n== Intro\n\n\
+                      [quote, Ada]\nQuoted.\n\n\
+                      [source.wide%linenums,rust,role=dark]\n----\nfn main() {}\n----\n\n\
+                      |===\n|a |b\n\n2+|span\n|===\n";
+        assert_eq!(
+            round_trip(source),
+            "= Title\nAda Lovelace <ada@example.com>; Charles\nv1.2, 2024-05-01: Draft\n\
+             :toc:\n:icons!:\n\n\
+             * one\n** nested\n. ordered\n+\ncontinued\n\n\
+             [[intro,Intro]]\n== Intro\n\n\
+             [quote,Ada]\n____\nQuoted.\n____\n\n\
+             [source.wide%linenums,rust,role=dark]\n----\nfn main() {}\n----\n\n\
+             |===\n| a | b\n\n2+| span\n|===\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_blocks() {
+        round_trip(
+            ".Steps\n[#main.lead]\nA paragraph\nover two lines.\n\n\
+             [[para]]Anchored paragraph.\n\n\
+             * a\n* b\n\n//\n\n* c\n\n//\n\n. d\n\n//\n\nTerm:: x\n\n//\n\n* e\n\n\
+             //\n\nTerm:: Desc\nEmpty::\n\n\
+             ....\nliteral\n....\n\n\
+             [[code]]\n----\n----\nnested delimiter\n----\n\n\
+             [verse,Blake,Songs]\n____\nTyger\nburning\n____\n\n\
+             ====\nOuter\n\n=====\nInner\n=====\n====\n\n\
+             ****\n--\nOpen *in* sidebar\n--\n****\n\n\
+             [cols=\"1,2\",options=header]\n|===\n|h1 |h2\n.2+|tall |x\n|y\n|===\n\n\
+             [.frame]\nimage::a.png[\"Alt, text\",40,link=https://example.com]\n\n\
+             [backend=latex]\n++++\n\\relax\n++++\n",
+        );
+    }
+
+    #[test]
+    fn test_round_trip_unknown_blocks() {
+        let source = ".Intro\n[width=640]\nvideo::intro.mp4[start=10]\n\n\
+                      ```rust\nlet  x = 1;\n```\n\n,===\na,b\n,===\n";
+        assert_eq!(round_trip(source), source);
+    }
+
+    #[test]
+    fn test_round_trip_inline() {
+        round_trip(
+            "*b* **bo**ld _i_ __it__alic `m` ``mo``no #h# ##hi##gh ^sup^ ~sub~ \
+             *_nested_ bold* a**b**c\n\n\
+             See https://example.com, https://example.com[docs], \
+             https://example.com[new^], https://example.com[docs,role=ext,window=x] \
+             and https://example.com[_styled_ text].\n\n\
+             <<a>> <<b,*text*>> [[c]] [[d,Ref]] image:i.png[Icon,16] image:j.png[] \
+             footnote:[Note] footnote:n[Named *note*] footnote:n[]\n",
+        );
+    }
+
+    #[test]
+    fn test_round_trip_mixed_lists() {
+        let rendered = round_trip("* a\n// c\n. b\n\nTerm:: d\n");
+        assert_eq!(rendered, "* a\n\n//\n\n. b\n\n//\n\nTerm:: d\n");
+    }
+
+    #[test]
+    fn test_round_trip_description_separators() {
+        let source = "CPU:: The brain\n\n//\n\nRAM;; Memory\n\n//\n\nDisk::: Storage\n";
+        assert_eq!(round_trip(source), source);
+    }
+
+    #[test]
+    fn test_round_trip_marks_in_text() {
+        // Mark characters next to a pair, or in it, must not pair in its place
+        let rendered = round_trip(
+            "**&*** and **&****, ***a*** and **&*&**\n\n\
+             *&**&**\n\n&******\n\n*****&*\n\n___a__ #a##\n",
+        );
+        assert_eq!(
+            rendered,
+            "**&*** and **&****, ***a*** and **&*&**\n\n\
+             *&**&**\n\n&******\n\n*****&*\n\n___a__ #a##\n"
+        );
+        let bold = |text: &str| Inline::Bold(vec![Inline::Text(text.to_string())]);
+        assert_eq!(inlines(&[bold("&*&")]), "**&*&**");
+        assert_eq!(
+            inlines(&[bold("&"), Inline::Text("*".to_string())]),
+            "**&***"
+        );
+    }
+
+    #[test]
+    fn test_escaped_marks() {
+        let rendered = round_trip(
+            "Escaped \\*not bold* and \\**kw**args, \\_x_ \\#y# \\^z^ with *real* bold.\n\n\
+             a * b * c, snake_case_name, C:\\_data and \\*nested _em_*\n",
+        );
+        assert_eq!(
+            rendered,
+            "Escaped \\*not bold* and \\**kw**args, \\_x_ \\#y# \\^z^ with *real* bold.\n\n\
+             a * b * c, snake_case_name, C:\\_data and \\*nested _em_*\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_role_spans() {
+        let rendered = round_trip(
+            "[.underline]#u# [line-through]##s##x a[.small]##t## and [x]\\#not# [y]\\[z]#h#\n",
+        );
+        assert_eq!(
+            rendered,
+            "[.underline]#u# [.line-through]##s##x a[.small]##t## and [x]\\#not# [y]\\[z]#h#\n"
+        );
+        let span = Inline::Span {
+            roles: vec!["a.b".to_string()],
+            content: vec![Inline::Text("t".to_string())],
+        };
+        assert_eq!(inlines(&[span]), "[role=\"a.b\"]#t#");
+    }
+
+    #[test]
+    fn test_round_trip_admonitions() {
+        let source = "NOTE: Read\nthis.\n\n[TIP]\nStyled.\n\n\
+                      [#w.loud]\nWARNING: Anchored.\n\n\
+                      [CAUTION]\n====\nFirst.\n\nSecond.\n====\n";
+        assert_eq!(round_trip(source), source);
+    }
+
+    #[test]
+    fn test_formatting_marks() {
+        let bold = |text: &str| Inline::Bold(vec![Inline::Text(text.to_string())]);
+        let text = |text: &str| Inline::Text(text.to_string());
+        assert_eq!(inlines(&[text("a "), bold("b"), text(".")]), "a *b*.");
+        assert_eq!(inlines(&[text("a"), bold("b"), text("c")]), "a**b**c");
+        assert_eq!(inlines(&[bold(" b")]), "** b**");
+        assert_eq!(inlines(&[text("x:"), bold("b")]), "x:**b**");
+        assert_eq!(
+            inlines(&[Inline::Italic(vec![bold("b"), text("c")])]),
+            "_**b**c_"
+        );
+    }
+
+    #[test]
+    fn test_blocks_built_in_code() {
+        let metadata = BlockMetadata::default;
+        let text = |text: &str| vec![Inline::Text(text.to_string())];
+        let doc = Document::with_blocks(vec![
+            Block::Listing {
+                language: Some("rust".to_string()),
+                content: "let x = 1;".to_string(),
+                attributes: Attributes::new(),
+                metadata: metadata(),
+            },
+            Block::Quote {
+                attribution: Some("Ada".to_string()),
+                citation: Some("Notes".to_string()),
+                content: vec![Block::Paragraph {
+                    content: text("Quoted."),
+                    metadata: metadata(),
+                }],
+                metadata: metadata(),
+            },
+            Block::Verse {
+                attribution: None,
+                citation: None,
+                content: text("Line"),
+                metadata: metadata(),
+            },
+            Block::Passthrough {
+                content: "\\relax".to_string(),
+                backend: "latex".to_string(),
+                metadata: metadata(),
+            },
+            Block::Table {
+                columns: vec![ColumnSpec { width: 1 }, ColumnSpec { width: 3 }],
+                header: None,
+                rows: vec![TableRow {
+                    cells: vec![TableCell::new(text("a")), TableCell::new(text("b"))],
+                }],
+                attributes: Attributes::new(),
+                metadata: metadata(),
+            },
+        ]);
+        let rendered = render_asciidoc(&doc);
+        assert_eq!(
+            rendered,
+            "[source,rust]\n----\nlet x = 1;\n----\n\n\
+             [quote,Ada,Notes]\n____\nQuoted.\n____\n\n\
+             [verse]\n____\nLine\n____\n\n\
+             [backend=latex]\n++++\n\\relax\n++++\n\n\
+             [cols=\"1,3\"]\n|===\n| a | b\n|===\n"
+        );
+
+        let reparsed = parse_document(&rendered).unwrap();
+        assert!(matches!(
+            &reparsed.blocks[0],
+            Block::Listing { language: Some(language), .. } if language == "rust"
+        ));
+        assert!(matches!(
+            &reparsed.blocks[1],
+            Block::Quote { attribution: Some(a), citation: Some(c), .. } if a == "Ada" && c == "Notes"
+        ));
+        assert!(matches!(&reparsed.blocks[2], Block::Verse { .. }));
+        assert_eq!(reparsed.blocks[3].passthrough_for("latex"), Some("\\relax"));
+        let Block::Table { columns, .. } = &reparsed.blocks[4] else {
+            panic!("expected a table");
+        };
+        assert_eq!(columns, &[ColumnSpec { width: 1 }, ColumnSpec { width: 3 }]);
+    }
+}
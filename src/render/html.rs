@@ -0,0 +1,2614 @@
+//! HTML5 renderer
+//!
+//! Converts the AST to semantic HTML5:
+//!
+//! | AST node          | HTML                                    |
+//! |-------------------|-----------------------------------------|
+//! | Document title    | `<h1>`                                  |
+//! | Level 1 section   | `<h1>` followed by its content          |
+//! | Level 2-6 section | `<section>` with an `<h2>`-`<h6>` title |
+//! | Paragraph         | `<p>`, with its roles as classes        |
+//! | Unordered list    | `<ul>` with `<li>` items                |
+//! | Ordered list      | `<ol>` with `<li>` items                |
+//! | Description list  | `<dl>` with `<dt>`/`<dd>` pairs         |
+//! | Listing block     | `<pre><code>` (`class="language-*"`)    |
+//! | Literal block     | `<pre>`                                 |
+//! | Source line       | `<span class="line">` with `linenums`   |
+//! |                   | or `highlight` (`highlighted` class)    |
+//! | Table             | `<table>` with `<thead>` and `<tbody>`  |
+//! | Quote block       | `<blockquote>`, in a `<figure>` with a  |
+//! |                   | `<figcaption>` when attributed          |
+//! | Verse block       | `<pre class="verse">` in a quote        |
+//! | Example block     | `<div class="example">`                 |
+//! | Admonition        | `<div class="admonitionblock note">`    |
+//! |                   | (see "Admonitions" below)               |
+//! | Exercise/solution | `<figure>` with a numbered caption, or  |
+//! |                   | `<details>` (see "Exercises" below)     |
+//! | Sidebar block     | `<aside>`                               |
+//! | Open block        | `<div class="openblock">` (plus style)  |
+//! | Block image       | `<figure>` around an `<img>`            |
+//! | Passthrough block | its content, unescaped (see below)      |
+//! | `toc::[figures]`  | `<nav>` around the list of figures (and |
+//! |                   | likewise for tables and listings)       |
+//! | Block title       | `<figcaption>`, or `<caption>` on a     |
+//! |                   | table; a titled listing is a `<figure>` |
+//! | Bold / italic     | `<strong>` / `<em>`                     |
+//! | Monospace         | `<code>`                                |
+//! | Highlight         | `<mark>`                                |
+//! | Span with roles   | `<s>`, `<u>` or `<small>` for the roles |
+//! |                   | `line-through`, `underline` and `small`,|
+//! |                   | else `<span>`; the roles are classes    |
+//! | Super / subscript | `<sup>` / `<sub>`                       |
+//! | Link              | `<a href>` (`class="bare"` for URLs)    |
+//! | Cross reference   | `<a href="#id">`                        |
+//! | Inline anchor     | `<a id>` (empty)                        |
+//! | Inline image      | `<img>`                                 |
+//! | Footnote          | `<sup>` marker linking to the list      |
+//! | Break hint        | `&shy;` / `&nbsp;`                      |
+//!
+//! The document title comes from the header. Level 1 sections in the body
+//! (book parts) are rendered like it, not wrapped in a `<section>`. All text
+//! is HTML-escaped.
+//!
+//! Footnotes are numbered in the order they are rendered and listed after the
+//! body in `<div id="footnotes">`. Each marker links to its entry
+//! (`#_footnotedef_N`) and each entry links back to the marker where the
+//! footnote is defined (`#_footnoteref_N`); see [`crate::footnotes`]. A
+//! reference to an unknown named footnote renders as its ID in brackets.
+//! [`HtmlOptions::footnote_placement`] lists them at the end of each chapter
+//! or section instead (see "Footnote Placement" below).
+//! Patches ([`crate::render::patch`]) list no footnotes, since a patch holds
+//! part of a document, and number exercises from 1.
+//!
+//! By default the output is an embeddable fragment (no `<html>`, `<head>`, or
+//! `<body>`), suitable for inserting into a site template. Set
+//! [`HtmlOptions::standalone`] to produce a complete page, whose `<title>` is
+//! the document title and whose `lang` comes from the `lang` attribute
+//! (default `en`).
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::parse_document;
+//! use doctora::render::html::{HtmlOptions, render_html};
+//!
+//! let doc = parse_document("Some **bold** text.").unwrap();
+//! let html = render_html(&doc, &HtmlOptions::new());
+//! assert_eq!(html, "<p>Some <strong>bold</strong> text.</p>\n");
+//! ```
+//!
+//! Images without `alt` text get one from the file name, like Asciidoctor
+//! (`image:release_notes-v2.png[]` has the alt text `release notes v2`).
+//! Relative image targets are resolved against `imagesdir`, and icon macros
+//! (`icon:heart[2x]`) follow the `icons` attribute: `font` renders a Font
+//! Awesome `<i>` element, any other value an image under `iconsdir`, and
+//! without it the icon falls back to its alt text in brackets.
+//!
+//! Anchored blocks carry their anchor as an `id` attribute: on the heading
+//! of a section, and on the outermost element otherwise. A cross reference
+//! without text shows the reference text or title of its target (see
+//! [`References`]).
+//!
+//! # Node IDs
+//!
+//! With [`HtmlOptions::node_ids`], every block element carries a
+//! `data-node` attribute holding the block's path in the AST (`"0"` for the
+//! first top-level block, `"0.2"` for the third block inside it), and the
+//! document title's `<h1>` carries `"title"`. Lists and tables are leaves:
+//! blocks nested in list items get no ID. Live preview
+//! uses these IDs to patch the page in place (see [`crate::render::patch`]).
+//!
+//! # Role Templates
+//!
+//! Design systems often give a role its own markup, such as a card for
+//! `[.api-endpoint]` blocks. Instead of forking the renderer, register a
+//! [`BlockTemplate`] for the role with [`HtmlOptions::template`]. Before
+//! rendering a block, the renderer tries the templates of the block's roles
+//! (see [`Attributes::roles`](crate::attributes::Attributes::roles)) in
+//! order; the first one that returns HTML replaces the default rendering,
+//! and a template returning `None` falls back to it. Only blocks with an
+//! attribute list (listings, tables, open blocks, and block images) have
+//! roles.
+//!
+//! ```
+//! use doctora::ast::Block;
+//! use doctora::parse_document;
+//! use doctora::render::html::{HtmlOptions, TemplateContext, render_html};
+//!
+//! let card = |block: &Block, context: &TemplateContext<'_>| match block {
+//!     Block::Open { content, .. } => Some(format!(
+//!         "<div class=\"card\">\n{}</div>\n",
+//!         context.render_blocks(content)
+//!     )),
+//!     _ => None,
+//! };
+//! let options = HtmlOptions::new().template("api-endpoint", card);
+//!
+//! let doc = parse_document("[.api-endpoint]\n--\n`GET /users`\n--\n").unwrap();
+//! assert_eq!(
+//!     render_html(&doc, &options),
+//!     "<div class=\"card\">\n<p><code>GET /users</code></p>\n</div>\n"
+//! );
+//! ```
+//!
+//! Live preview patches ([`crate::render::patch`]) always use the default
+//! rendering.
+//!
+//! # Asset Fingerprinting
+//!
+//! Standalone pages link the stylesheets and scripts added with
+//! [`HtmlOptions::stylesheet`] and [`HtmlOptions::script`]. Sites served
+//! through a cache-busting CDN publish assets under content-hashed names
+//! (`site.3f9a2c.css`), so the references have to match. Set an
+//! [`AssetHook`] with [`HtmlOptions::asset_hook`] and every asset reference
+//! the renderer emits, image and icon sources included, goes through it
+//! first:
+//!
+//! ```
+//! use doctora::parse_document;
+//! use doctora::render::html::{AssetKind, HtmlOptions, render_html};
+//!
+//! let fingerprint = |kind: AssetKind, path: &str| match (kind, path) {
+//!     (AssetKind::Stylesheet, "site.css") => "site.3f9a2c.css".to_string(),
+//!     _ => path.to_string(),
+//! };
+//! let options = HtmlOptions::new()
+//!     .standalone(true)
+//!     .stylesheet("site.css")
+//!     .script("search.js")
+//!     .asset_hook(fingerprint);
+//!
+//! let page = render_html(&parse_document("Text.").unwrap(), &options);
+//! assert!(page.contains("<link rel=\"stylesheet\" href=\"site.3f9a2c.css\">\n</head>"));
+//! assert!(page.contains("<script src=\"search.js\"></script>\n</body>"));
+//! ```
+//!
+//! # Attribute Hooks
+//!
+//! To attach `data-*` attributes, tracking IDs or test hooks to particular
+//! blocks without templating them, set an [`AttributeHook`] with
+//! [`HtmlOptions::attribute_hook`]. It is called for every block the renderer
+//! emits (nested ones included) with the attributes of the block's outer
+//! element, which already hold its `id`, `data-node` and built-in `class`,
+//! and may change them. Values are escaped; names that are not valid
+//! attribute names are ignored (see [`HtmlAttrs::set`]):
+//!
+//! ```
+//! use doctora::ast::Block;
+//! use doctora::parse_document;
+//! use doctora::render::html::{HtmlAttrs, HtmlOptions, render_html};
+//!
+//! let hook = |block: &Block, attrs: &mut HtmlAttrs| match block {
+//!     Block::Paragraph { .. } => attrs.set("data-testid", "para"),
+//!     Block::Example { .. } => attrs.add_class("tracked"),
+//!     _ => {}
+//! };
+//! let options = HtmlOptions::new().attribute_hook(hook);
+//!
+//! let doc = parse_document("====\nText.\n====\n").unwrap();
+//! assert_eq!(
+//!     render_html(&doc, &options),
+//!     "<div class=\"example tracked\">\n<p data-testid=\"para\">Text.</p>\n</div>\n"
+//! );
+//! ```
+//!
+//! For sections below level 1 the `id` stays on the heading and the other
+//! attributes go on the `<section>`. Blocks rendered by a role template get
+//! no hook call: the template owns their markup.
+//!
+//! # Content Security Policy
+//!
+//! Sites served with a strict `Content-Security-Policy` (no `'unsafe-inline'`)
+//! can't use inline `style` attributes. With [`HtmlOptions::strict_csp`] the
+//! renderer emits none: table column widths become classes
+//! (`<col class="colwidth-33">`, rounded to whole percents) defined in the
+//! stylesheet returned by [`csp_stylesheet`], which the site serves as a file
+//! and links with [`HtmlOptions::stylesheet`]. Scripts are always external.
+//!
+//! ```
+//! use doctora::parse_document;
+//! use doctora::render::html::{HtmlOptions, csp_stylesheet, render_html};
+//!
+//! let doc = parse_document("[cols=\"1,2\"]\n|===\n|a |b\n|===").unwrap();
+//! let options = HtmlOptions::new().strict_csp(true).stylesheet("doctora-csp.css");
+//! let html = render_html(&doc, &options);
+//! assert!(html.contains("<col class=\"colwidth-33\">\n<col class=\"colwidth-67\">"));
+//! assert!(!html.contains("style="));
+//! assert!(csp_stylesheet().contains("col.colwidth-67 { width: 67%; }"));
+//! ```
+//!
+//! Output of role templates is up to the template. Live preview patches
+//! ([`crate::render::patch`]) always use the default rendering.
+//!
+//! # Passthrough Blocks
+//!
+//! A `++++` block is written to the output as is, but only if it is meant for
+//! HTML (see [`Block::passthrough_for`]): `[backend=latex]` blocks are
+//! skipped. Documents from untrusted authors must not inject markup, so with
+//! [`HtmlOptions::safe`] the content is escaped instead and shown as text in
+//! a `<pre class="passthrough">`.
+//!
+//! ```
+//! use doctora::parse_document;
+//! use doctora::render::html::{HtmlOptions, render_html};
+//!
+//! let doc = parse_document("++++\n<b>raw</b>\n++++\n\n[backend=latex]\n++++\n\\LaTeX\n++++\n")
+//!     .unwrap();
+//! assert_eq!(render_html(&doc, &HtmlOptions::new()), "<b>raw</b>\n");
+//! assert_eq!(
+//!     render_html(&doc, &HtmlOptions::new().safe(true)),
+//!     "<pre class=\"passthrough\">&lt;b&gt;raw&lt;/b&gt;</pre>\n"
+//! );
+//! ```
+//!
+//! # Special Characters
+//!
+//! Text is escaped for HTML unless its block's substitutions leave out
+//! `specialcharacters`, as `[subs="-specialchars"]` or `[subs=none]` do; the
+//! text is then written as is, like a passthrough block. The defaults of
+//! block kinds come from [`HtmlOptions::substitutions`], which should match
+//! the [`ParseOptions`](crate::options::ParseOptions) the document was parsed
+//! with. Block titles are always escaped, and so is all text with
+//! [`HtmlOptions::safe`].
+//!
+//! ```
+//! use doctora::parse_document;
+//! use doctora::render::html::{HtmlOptions, render_html};
+//!
+//! let doc = parse_document("[subs=-specialchars]\n<b>raw</b> *bold*\n").unwrap();
+//! assert_eq!(
+//!     render_html(&doc, &HtmlOptions::new()),
+//!     "<p><b>raw</b> <strong>bold</strong></p>\n"
+//! );
+//! assert_eq!(
+//!     render_html(&doc, &HtmlOptions::new().safe(true)),
+//!     "<p>&lt;b&gt;raw&lt;/b&gt; <strong>bold</strong></p>\n"
+//! );
+//! ```
+//!
+//! # Source Languages
+//!
+//! A `[source]` block that names no language gets the document's
+//! `source-language` attribute, or else [`HtmlOptions::source_language`].
+//! Plain `----` listings get neither. Languages then go through the alias
+//! map set with [`HtmlOptions::language_alias`], so a doc set that mixes
+//! `js` and `javascript` gets one `language-*` class for both.
+//!
+//! ```
+//! use doctora::parse_document;
+//! use doctora::render::html::{HtmlOptions, render_html};
+//!
+//! let doc = parse_document(":source-language: js\n\n[source]\n----\nf()\n----\n").unwrap();
+//! let options = HtmlOptions::new().language_alias("js", "javascript");
+//! assert_eq!(
+//!     render_html(&doc, &options),
+//!     "<pre><code class=\"language-javascript\">f()</code></pre>\n"
+//! );
+//! ```
+//!
+//! # Inline Snippets
+//!
+//! Applications often store short AsciiDoc snippets outside any document:
+//! titles, captions, or tooltips in a database. [`inline_to_html`] converts
+//! one such snippet without the block parser, so `* item` or `. note` stay
+//! text, and replaces attribute references with values from the attributes
+//! it is given (the intrinsic ones such as `{nbsp}` are always defined).
+//!
+//! ```
+//! use doctora::attributes::Attributes;
+//! use doctora::render::html::inline_to_html;
+//!
+//! let mut attributes = Attributes::new();
+//! attributes.set("product", "Doctora");
+//! assert_eq!(
+//!     inline_to_html("Welcome to *{product}* & {unknown}", &attributes),
+//!     "Welcome to <strong>Doctora</strong> &amp; {unknown}"
+//! );
+//! ```
+//!
+//! Cross references have no document to point into, so they link to
+//! `#id`, and footnotes render as markers only.
+//!
+//! # Exercises
+//!
+//! Example blocks styled `[exercise]` and `[solution]` are numbered (see
+//! [`crate::exercises`]) and rendered as a `<figure>` with the classes
+//! `example exercise` or `example solution`, captioned with their label
+//! and title. Training material often hides the answers until the reader
+//! asks for them: with [`HtmlOptions::collapse_solutions`], solutions are
+//! closed `<details>` elements whose `<summary>` is the caption.
+//!
+//! ```
+//! use doctora::parse_document;
+//! use doctora::render::html::{HtmlOptions, render_html};
+//!
+//! let doc = parse_document(
+//!     ".Sum\n[exercise]\n====\nAdd 2 and 3.\n====\n\n[solution]\n====\n5\n====\n",
+//! )
+//! .unwrap();
+//! assert_eq!(
+//!     render_html(&doc, &HtmlOptions::new().collapse_solutions(true)),
+//!     "<figure class=\"example exercise\">\n<figcaption>Exercise 1. Sum</figcaption>\n\
+//!      <p>Add 2 and 3.</p>\n</figure>\n\
+//!      <details class=\"example solution\">\n<summary>Solution 1</summary>\n\
+//!      <p>5</p>\n</details>\n"
+//! );
+//! ```
+//!
+//! # Admonitions
+//!
+//! An admonition is a `<div>` with the classes `admonitionblock` and its
+//! kind (`note`, `tip`, ...), holding a `<div class="label">` and its
+//! content in a `<div class="content">`. The label follows the `icons`
+//! attribute, like icon macros: without it the caption is text (`Note`,
+//! or the `note-caption` attribute), `font` renders a Font Awesome `<i>`
+//! element, and any other value the image `note.png` under `iconsdir`.
+//! [`HtmlOptions::admonition_icons`] sets the mode for every kind, and
+//! [`HtmlOptions::admonition_icon`] for one kind, whatever the document
+//! says (see [`AdmonitionIcons`]).
+//!
+//! ```
+//! use doctora::ast::AdmonitionKind;
+//! use doctora::parse_document;
+//! use doctora::render::html::{AdmonitionIcons, HtmlOptions, render_html};
+//!
+//! let doc = parse_document(":icons: font\n\nNOTE: Back up first.\n\nTIP: Use `-n`.\n").unwrap();
+//! let options =
+//!     HtmlOptions::new().admonition_icon(AdmonitionKind::Tip, AdmonitionIcons::Text);
+//! assert_eq!(
+//!     render_html(&doc, &options),
+//!     "<div class=\"admonitionblock note\">\n\
+//!      <div class=\"label\"><i class=\"fa icon-note\" title=\"Note\"></i></div>\n\
+//!      <div class=\"content\">\n<p>Back up first.</p>\n</div>\n</div>\n\
+//!      <div class=\"admonitionblock tip\">\n<div class=\"label\">Tip</div>\n\
+//!      <div class=\"content\">\n<p>Use <code>-n</code>.</p>\n</div>\n</div>\n"
+//! );
+//! ```
+//!
+//! # Footnote Placement
+//!
+//! Long documents read better with footnotes near their text. With
+//! [`FootnotePlacement::Chapter`], each chapter (level 2 section) lists its
+//! footnotes before it closes, in a `<div class="footnotes">`, and numbers
+//! them from 1; [`FootnotePlacement::Section`] does the same for every
+//! section, so a section's list leaves out those of its subsections.
+//! Footnotes outside any such section stay at the end of the document. The
+//! element IDs carry the number of the listing section
+//! (`#_footnotedef_2_1`) to stay unique, and a named footnote can only be
+//! referenced within the section that lists it.
+//!
+//! ```
+//! use doctora::parse_document;
+//! use doctora::render::html::{FootnotePlacement, HtmlOptions, render_html};
+//!
+//! let doc = parse_document("== A\n\nX.footnote:[One.]\n").unwrap();
+//! let options = HtmlOptions::new().footnote_placement(FootnotePlacement::Chapter);
+//! assert!(render_html(&doc, &options).contains(
+//!     "<div class=\"footnotes\">\n<hr>\n<div class=\"footnote\" id=\"_footnotedef_1_1\">\n\
+//!      <a href=\"#_footnoteref_1_1\">1</a>. One.\n</div>\n</div>\n</section>\n"
+//! ));
+//! ```
+//!
+//! The renderer is also available as the `html` [`Processor`] ([`HtmlProcessor`]),
+//! which [`ProcessorRegistry::with_builtins`](crate::processor::ProcessorRegistry::with_builtins)
+//! registers.
+
+use crate::ast::{
+    AdmonitionKind, Block, BlockMetadata, BreakHint, ColumnSpec, DescriptionItem, Document, Inline,
+    ListItem, TableRow,
+};
+use crate::attributes::Attributes;
+use crate::exercises::{ExerciseKind, Exercises};
+use crate::footnotes::{FootnoteRef, Footnotes};
+use crate::formatting::format_inlines;
+use crate::outline::{Outline, list_macro};
+use crate::processor::{Output, Processor, ProcessorContext, ProcessorError};
+use crate::render::nav::render_caption_list;
+use crate::resolve::{substitute_inlines, substitutions};
+use crate::subs::{SubstitutionDefaults, Substitutions};
+use crate::targets::{icon_target, image_target};
+use crate::token::lex_with_spans;
+use crate::xref::References;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Options for [`render_html`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HtmlOptions {
+    /// Emit a complete HTML page instead of an embeddable fragment
+    pub standalone: bool,
+    /// Add `data-node` IDs to block elements (see "Node IDs" above)
+    pub node_ids: bool,
+    /// Custom rendering by role (see "Role Templates" above)
+    pub templates: RoleTemplates,
+    /// Stylesheets linked from standalone pages, in order
+    pub stylesheets: Vec<String>,
+    /// Scripts loaded at the end of standalone pages, in order
+    pub scripts: Vec<String>,
+    /// Routing of asset references (see "Asset Fingerprinting" above)
+    pub assets: AssetUrls,
+    /// Avoid inline styles (see "Content Security Policy" above)
+    pub strict_csp: bool,
+    /// Extra attributes by block (see "Attribute Hooks" above)
+    pub attributes: NodeAttributes,
+    /// Escape passthrough content and all text (see "Passthrough Blocks"
+    /// and "Special Characters" above)
+    pub safe: bool,
+    /// Default substitutions by block kind (see "Special Characters" above)
+    pub substitutions: SubstitutionDefaults,
+    /// Language of `[source]` blocks that name none, unless the document
+    /// sets `source-language` (see "Source Languages" above)
+    pub source_language: Option<String>,
+    /// Languages by alias (see "Source Languages" above)
+    pub language_aliases: BTreeMap<String, String>,
+    /// Render solutions collapsed (see "Exercises" above)
+    pub collapse_solutions: bool,
+    /// Where footnotes are listed (see "Footnote Placement" above)
+    pub footnote_placement: FootnotePlacement,
+    /// How admonitions show their kind, by kind; kinds without an entry
+    /// follow the `icons` attribute (see "Admonitions" above)
+    pub admonition_icons: BTreeMap<AdmonitionKind, AdmonitionIcons>,
+}
+
+impl HtmlOptions {
+    /// Creates options for an embeddable fragment
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether to emit a complete HTML page
+    pub fn standalone(mut self, standalone: bool) -> Self {
+        self.standalone = standalone;
+        self
+    }
+
+    /// Sets whether to add `data-node` IDs to block elements
+    pub fn node_ids(mut self, node_ids: bool) -> Self {
+        self.node_ids = node_ids;
+        self
+    }
+
+    /// Renders blocks with `role` using `template`
+    pub fn template(
+        mut self,
+        role: impl Into<String>,
+        template: impl BlockTemplate + 'static,
+    ) -> Self {
+        self.templates.register(role, template);
+        self
+    }
+
+    /// Links the stylesheet at `href` from standalone pages
+    pub fn stylesheet(mut self, href: impl Into<String>) -> Self {
+        self.stylesheets.push(href.into());
+        self
+    }
+
+    /// Loads the script at `src` at the end of standalone pages
+    pub fn script(mut self, src: impl Into<String>) -> Self {
+        self.scripts.push(src.into());
+        self
+    }
+
+    /// Routes every emitted asset reference through `hook`
+    pub fn asset_hook(mut self, hook: impl AssetHook + 'static) -> Self {
+        self.assets = AssetUrls::new(hook);
+        self
+    }
+
+    /// Passes the attributes of every rendered block through `hook`
+    pub fn attribute_hook(mut self, hook: impl AttributeHook + 'static) -> Self {
+        self.attributes = NodeAttributes::new(hook);
+        self
+    }
+
+    /// Sets whether to avoid inline styles for strict CSP headers
+    pub fn strict_csp(mut self, strict_csp: bool) -> Self {
+        self.strict_csp = strict_csp;
+        self
+    }
+
+    /// Sets whether to escape passthrough content from untrusted documents
+    pub fn safe(mut self, safe: bool) -> Self {
+        self.safe = safe;
+        self
+    }
+
+    /// Applies `subs` by default to blocks of kind `kind`, as
+    /// [`ParseOptions::substitutions`](crate::options::ParseOptions::substitutions)
+    /// does while parsing
+    pub fn substitutions(mut self, kind: impl Into<String>, subs: Substitutions) -> Self {
+        self.substitutions.set(kind, subs);
+        self
+    }
+
+    /// Sets the language of `[source]` blocks that name none, for documents
+    /// without a `source-language` attribute
+    pub fn source_language(mut self, language: impl Into<String>) -> Self {
+        self.source_language = Some(language.into());
+        self
+    }
+
+    /// Renders source blocks in `alias` as `language`
+    pub fn language_alias(mut self, alias: impl Into<String>, language: impl Into<String>) -> Self {
+        self.language_aliases.insert(alias.into(), language.into());
+        self
+    }
+
+    /// Sets whether to render solutions as closed `<details>` elements
+    pub fn collapse_solutions(mut self, collapse_solutions: bool) -> Self {
+        self.collapse_solutions = collapse_solutions;
+        self
+    }
+
+    /// Sets where footnotes are listed
+    pub fn footnote_placement(mut self, placement: FootnotePlacement) -> Self {
+        self.footnote_placement = placement;
+        self
+    }
+
+    /// Shows the kind of every admonition with `icons`, whatever the
+    /// `icons` attribute says
+    pub fn admonition_icons(mut self, icons: AdmonitionIcons) -> Self {
+        for kind in AdmonitionKind::ALL {
+            self.admonition_icons.insert(kind, icons);
+        }
+        self
+    }
+
+    /// Shows the kind of `kind` admonitions with `icons`
+    pub fn admonition_icon(mut self, kind: AdmonitionKind, icons: AdmonitionIcons) -> Self {
+        self.admonition_icons.insert(kind, icons);
+        self
+    }
+}
+
+/// How [`render_html`] shows the kind of an admonition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AdmonitionIcons {
+    /// The caption as text (`Note`)
+    Text,
+    /// A Font Awesome icon (`<i class="fa icon-note">`)
+    Font,
+    /// An image from `iconsdir` (`./images/icons/note.png`)
+    Image,
+}
+
+impl AdmonitionIcons {
+    /// The mode the `icons` attribute in `attributes` asks for: `font` for
+    /// font icons, any other value for images, and text when it is unset
+    pub fn from_attributes(attributes: &Attributes) -> Self {
+        match attributes.get("icons") {
+            Some("font") => AdmonitionIcons::Font,
+            Some(_) => AdmonitionIcons::Image,
+            None => AdmonitionIcons::Text,
+        }
+    }
+}
+
+/// Where [`render_html`] lists footnotes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FootnotePlacement {
+    /// After the body, numbered through the whole document
+    #[default]
+    Document,
+    /// At the end of each chapter (level 2 section), numbered from 1 in each
+    Chapter,
+    /// At the end of each section, numbered from 1 in each; a section's
+    /// subsections list their own
+    Section,
+}
+
+/// The stylesheet defining the classes [`HtmlOptions::strict_csp`] output
+/// uses in place of inline styles
+pub fn csp_stylesheet() -> String {
+    (1..=100)
+        .map(|percent| format!("col.colwidth-{percent} {{ width: {percent}%; }}\n"))
+        .collect()
+}
+
+/// Custom HTML for blocks with a given role
+///
+/// Closures taking a block and a [`TemplateContext`] implement this trait.
+pub trait BlockTemplate: Send + Sync {
+    /// Render `block`, or return `None` to use the default rendering
+    fn render(&self, block: &Block, context: &TemplateContext<'_>) -> Option<String>;
+}
+
+impl<F> BlockTemplate for F
+where
+    F: Fn(&Block, &TemplateContext<'_>) -> Option<String> + Send + Sync,
+{
+    fn render(&self, block: &Block, context: &TemplateContext<'_>) -> Option<String> {
+        self(block, context)
+    }
+}
+
+/// Rendering services available to a [`BlockTemplate`]
+pub struct TemplateContext<'a> {
+    context: &'a Context<'a>,
+    id: Option<&'a str>,
+}
+
+impl TemplateContext<'_> {
+    /// The block's node ID, if node IDs are enabled
+    ///
+    /// Templates should put it in a `data-node` attribute on their outermost
+    /// element.
+    pub fn node_id(&self) -> Option<&str> {
+        self.id
+    }
+
+    /// Render nested blocks, applying role templates to them as well
+    pub fn render_blocks(&self, blocks: &[Block]) -> String {
+        let mut out = String::new();
+        for block in blocks {
+            render_block(block, None, self.context, &mut out);
+        }
+        out
+    }
+
+    /// Render inline content
+    pub fn render_inlines(&self, content: &[Inline]) -> String {
+        let mut out = String::new();
+        render_inlines(content, self.context, &mut out);
+        out
+    }
+}
+
+/// What rendering a block needs beyond the block itself
+struct Context<'a> {
+    templates: &'a RoleTemplates,
+    references: &'a References,
+    /// Emit classes instead of inline styles
+    strict_csp: bool,
+    /// Escape passthrough content, and all text
+    safe: bool,
+    /// Default substitutions by block kind
+    substitutions: &'a SubstitutionDefaults,
+    /// Whether the block being rendered escapes its text
+    special_characters: Cell<bool>,
+    /// Language of `[source]` blocks that name none
+    source_language: Option<&'a str>,
+    /// Document attributes, for `imagesdir` and the other target attributes
+    document: &'a Attributes,
+    /// Routing of asset references
+    assets: &'a AssetUrls,
+    /// Languages by alias
+    language_aliases: &'a BTreeMap<String, String>,
+    attributes: &'a NodeAttributes,
+    /// Footnotes of the current scope in the order they are rendered
+    footnotes: RefCell<Footnotes>,
+    /// Where footnotes are listed
+    footnote_placement: FootnotePlacement,
+    /// Footnote scope being rendered, 0 for the document
+    footnote_scope: Cell<usize>,
+    /// Footnote scopes opened so far
+    footnote_scopes: Cell<usize>,
+    /// Exercises in the order they are rendered
+    exercises: RefCell<Exercises>,
+    /// Render solutions as `<details>`
+    collapse_solutions: bool,
+    /// Figures, tables, and listings for `toc::[figures]`-style lists
+    outline: &'a Outline,
+    /// Admonition icon modes set in the options, by kind
+    admonition_icons: &'a BTreeMap<AdmonitionKind, AdmonitionIcons>,
+}
+
+impl Context<'_> {
+    /// `text` as it goes in the output, escaped unless the block being
+    /// rendered leaves out `specialcharacters`
+    fn text(&self, text: &str) -> String {
+        if self.special_characters.get() || self.safe {
+            escape(text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Whether a section of `level` lists its own footnotes
+    fn lists_footnotes(&self, level: u8) -> bool {
+        match self.footnote_placement {
+            FootnotePlacement::Document => false,
+            FootnotePlacement::Chapter => level == 2,
+            FootnotePlacement::Section => level >= 2,
+        }
+    }
+
+    /// Suffix of the element IDs of footnote `number` in the current scope
+    /// (`1`, or `3_1` in the third section that lists footnotes)
+    fn footnote_key(&self, number: usize) -> String {
+        match self.footnote_scope.get() {
+            0 => number.to_string(),
+            scope => format!("{scope}_{number}"),
+        }
+    }
+}
+
+/// Block templates by role
+#[derive(Clone, Default)]
+pub struct RoleTemplates {
+    /// Roles and their templates, in registration order
+    templates: Vec<(String, Arc<dyn BlockTemplate>)>,
+}
+
+impl RoleTemplates {
+    /// Creates an empty set of templates
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renders blocks with `role` using `template`, replacing any template
+    /// registered for the same role
+    pub fn register(&mut self, role: impl Into<String>, template: impl BlockTemplate + 'static) {
+        let role = role.into();
+        self.templates.retain(|(existing, _)| *existing != role);
+        self.templates.push((role, Arc::new(template)));
+    }
+
+    /// Returns the template for `role`, if any
+    pub fn get(&self, role: &str) -> Option<&dyn BlockTemplate> {
+        self.templates
+            .iter()
+            .find(|(existing, _)| existing == role)
+            .map(|(_, template)| template.as_ref())
+    }
+
+    /// Roles with a template, in registration order
+    pub fn roles(&self) -> impl Iterator<Item = &str> {
+        self.templates.iter().map(|(role, _)| role.as_str())
+    }
+
+    /// Render `block` with the template of its first role that produces HTML
+    fn render(&self, block: &Block, id: Option<&str>, context: &Context<'_>) -> Option<String> {
+        if self.templates.is_empty() {
+            return None;
+        }
+        let context = TemplateContext { context, id };
+        block
+            .roles()
+            .into_iter()
+            .filter_map(|role| self.get(role))
+            .find_map(|template| template.render(block, &context))
+    }
+}
+
+impl std::fmt::Debug for RoleTemplates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.roles()).finish()
+    }
+}
+
+/// Template sets are equal if they map the same roles to the same template
+/// instances
+impl PartialEq for RoleTemplates {
+    fn eq(&self, other: &Self) -> bool {
+        self.templates.len() == other.templates.len()
+            && self.templates.iter().zip(&other.templates).all(
+                |((a, a_template), (b, b_template))| a == b && Arc::ptr_eq(a_template, b_template),
+            )
+    }
+}
+
+impl Eq for RoleTemplates {}
+
+/// Kind of asset referenced from HTML output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AssetKind {
+    /// A stylesheet (`<link rel="stylesheet">`)
+    Stylesheet,
+    /// A script (`<script src>`)
+    Script,
+    /// An image or icon (`<img src>`), after `imagesdir` or `iconsdir`
+    /// applies
+    Image,
+}
+
+/// Rewrites asset references before they are emitted
+///
+/// Closures taking an [`AssetKind`] and the reference as written implement
+/// this trait.
+pub trait AssetHook: Send + Sync {
+    /// URL to emit for the asset referenced as `path`
+    fn url(&self, kind: AssetKind, path: &str) -> String;
+}
+
+impl<F> AssetHook for F
+where
+    F: Fn(AssetKind, &str) -> String + Send + Sync,
+{
+    fn url(&self, kind: AssetKind, path: &str) -> String {
+        self(kind, path)
+    }
+}
+
+/// The asset hook of [`HtmlOptions`], if one is set
+#[derive(Clone, Default)]
+pub struct AssetUrls {
+    hook: Option<Arc<dyn AssetHook>>,
+}
+
+impl AssetUrls {
+    /// Routes asset references through `hook`
+    pub fn new(hook: impl AssetHook + 'static) -> Self {
+        Self {
+            hook: Some(Arc::new(hook)),
+        }
+    }
+
+    /// URL to emit for the asset referenced as `path`
+    ///
+    /// Without a hook, `path` is emitted unchanged.
+    pub fn url(&self, kind: AssetKind, path: &str) -> String {
+        match &self.hook {
+            Some(hook) => hook.url(kind, path),
+            None => path.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Debug for AssetUrls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AssetUrls")
+            .field("hook", &self.hook.is_some())
+            .finish()
+    }
+}
+
+/// Asset routing is equal if both use the same hook instance, or neither has
+/// one
+impl PartialEq for AssetUrls {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.hook, &other.hook) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+impl Eq for AssetUrls {}
+
+/// HTML attributes of an element, in output order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HtmlAttrs {
+    attrs: Vec<(String, String)>,
+}
+
+impl HtmlAttrs {
+    /// Creates an empty attribute list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Value of the attribute `name`, if set
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the attribute `name`, keeping its position if it is already set
+    ///
+    /// Names that are not valid HTML attribute names (empty, or containing
+    /// whitespace, quotes, `<`, `>`, `/`, `=` or control characters) are
+    /// ignored, so a hook cannot break or inject markup.
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let (name, value) = (name.into(), value.into());
+        if !valid_attribute_name(&name) {
+            return;
+        }
+        match self.attrs.iter_mut().find(|(key, _)| *key == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.attrs.push((name, value)),
+        }
+    }
+
+    /// Removes the attribute `name`, returning its value
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let index = self.attrs.iter().position(|(key, _)| key == name)?;
+        Some(self.attrs.remove(index).1)
+    }
+
+    /// Appends `class` to the `class` attribute
+    pub fn add_class(&mut self, class: &str) {
+        match self.attrs.iter_mut().find(|(key, _)| key == "class") {
+            Some((_, classes)) if !classes.is_empty() => {
+                classes.push(' ');
+                classes.push_str(class);
+            }
+            _ => self.set("class", class),
+        }
+    }
+
+    /// The attributes as `(name, value)` pairs, in output order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.attrs
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// The attributes as they appear in a start tag, each with a leading
+    /// space
+    fn render(&self) -> String {
+        self.iter()
+            .map(|(name, value)| format!(" {name}=\"{}\"", escape(value)))
+            .collect()
+    }
+}
+
+/// Whether `name` can be written as an attribute name in a start tag
+fn valid_attribute_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.chars().any(|c| {
+            c.is_whitespace() || c.is_control() || matches!(c, '"' | '\'' | '<' | '>' | '/' | '=')
+        })
+}
+
+/// Adds or changes the HTML attributes of rendered blocks
+///
+/// Closures taking a block and its [`HtmlAttrs`] implement this trait.
+pub trait AttributeHook: Send + Sync {
+    /// Adjust `attrs`, the attributes of the outer element of `block`
+    fn attributes(&self, block: &Block, attrs: &mut HtmlAttrs);
+}
+
+impl<F> AttributeHook for F
+where
+    F: Fn(&Block, &mut HtmlAttrs) + Send + Sync,
+{
+    fn attributes(&self, block: &Block, attrs: &mut HtmlAttrs) {
+        self(block, attrs)
+    }
+}
+
+/// The attribute hook of [`HtmlOptions`], if one is set
+#[derive(Clone, Default)]
+pub struct NodeAttributes {
+    hook: Option<Arc<dyn AttributeHook>>,
+}
+
+impl NodeAttributes {
+    /// Passes block attributes through `hook`
+    pub fn new(hook: impl AttributeHook + 'static) -> Self {
+        Self {
+            hook: Some(Arc::new(hook)),
+        }
+    }
+
+    /// Apply the hook, if any, to the attributes of `block`
+    pub fn apply(&self, block: &Block, attrs: &mut HtmlAttrs) {
+        if let Some(hook) = &self.hook {
+            hook.attributes(block, attrs);
+        }
+    }
+}
+
+impl std::fmt::Debug for NodeAttributes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeAttributes")
+            .field("hook", &self.hook.is_some())
+            .finish()
+    }
+}
+
+/// Attribute hooks are equal if both use the same hook instance, or neither
+/// has one
+impl PartialEq for NodeAttributes {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.hook, &other.hook) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+impl Eq for NodeAttributes {}
+
+/// Render `doc` as HTML5
+pub fn render_html(doc: &Document, options: &HtmlOptions) -> String {
+    let mut body = String::new();
+    if let Some(title) = doc
+        .header
+        .as_ref()
+        .and_then(|header| header.title.as_deref())
+    {
+        body.push_str(&render_title(title, options.node_ids));
+    }
+    let references = References::new(doc);
+    let outline = Outline::new(doc);
+    let context = Context {
+        templates: &options.templates,
+        references: &references,
+        strict_csp: options.strict_csp,
+        safe: options.safe,
+        substitutions: &options.substitutions,
+        special_characters: Cell::new(true),
+        source_language: doc
+            .attributes
+            .get("source-language")
+            .or(options.source_language.as_deref()),
+        document: &doc.attributes,
+        assets: &options.assets,
+        language_aliases: &options.language_aliases,
+        attributes: &options.attributes,
+        footnotes: RefCell::default(),
+        footnote_placement: options.footnote_placement,
+        footnote_scope: Cell::new(0),
+        footnote_scopes: Cell::new(0),
+        exercises: RefCell::default(),
+        collapse_solutions: options.collapse_solutions,
+        outline: &outline,
+        admonition_icons: &options.admonition_icons,
+    };
+    for (index, block) in doc.blocks.iter().enumerate() {
+        let id = options.node_ids.then(|| index.to_string());
+        render_block(block, id.as_deref(), &context, &mut body);
+    }
+    render_footnotes(&context, &mut body);
+
+    if !options.standalone {
+        return body;
+    }
+
+    let lang = doc.attributes.get("lang").unwrap_or("en");
+    let title = document_title(doc).unwrap_or("Untitled");
+    let mut out = String::with_capacity(body.len() + 160);
+    out.push_str("<!DOCTYPE html>\n");
+    out.push_str(&format!("<html lang=\"{}\">\n", escape(lang)));
+    out.push_str("<head>\n<meta charset=\"UTF-8\">\n");
+    out.push_str(&format!("<title>{}</title>\n", escape(title)));
+    out.push_str(&stylesheet_links(options));
+    out.push_str("</head>\n<body>\n");
+    out.push_str(&body);
+    out.push_str(&script_tags(options));
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// The `<link>` elements for the stylesheets of standalone pages
+pub(crate) fn stylesheet_links(options: &HtmlOptions) -> String {
+    let mut out = String::new();
+    for href in &options.stylesheets {
+        let href = options.assets.url(AssetKind::Stylesheet, href);
+        out.push_str(&format!(
+            "<link rel=\"stylesheet\" href=\"{}\">\n",
+            escape(&href)
+        ));
+    }
+    out
+}
+
+/// The `<script>` elements for the scripts of standalone pages
+pub(crate) fn script_tags(options: &HtmlOptions) -> String {
+    let mut out = String::new();
+    for src in &options.scripts {
+        let src = options.assets.url(AssetKind::Script, src);
+        out.push_str(&format!("<script src=\"{}\"></script>\n", escape(&src)));
+    }
+    out
+}
+
+/// The built-in `html` processor
+///
+/// Handles the formats `html` and `html5`.
+#[derive(Debug, Clone, Default)]
+pub struct HtmlProcessor {
+    /// Options passed to [`render_html`]
+    pub options: HtmlOptions,
+}
+
+impl Processor for HtmlProcessor {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn can_handle(&self, format: &str) -> bool {
+        matches!(format, "html" | "html5")
+    }
+
+    fn process(
+        &self,
+        doc: &Document,
+        _context: &ProcessorContext<'_>,
+    ) -> Result<Output, ProcessorError> {
+        Ok(Output::Text(render_html(doc, &self.options)))
+    }
+}
+
+/// The `doctitle` attribute, or the title in the document header
+pub(crate) fn document_title(doc: &Document) -> Option<&str> {
+    doc.attributes
+        .get("doctitle")
+        .or_else(|| doc.header.as_ref()?.title.as_deref())
+}
+
+/// Render the document title heading, with the node ID `title` if `node_ids`
+pub(crate) fn render_title(title: &str, node_ids: bool) -> String {
+    let attr = if node_ids { " data-node=\"title\"" } else { "" };
+    format!("<h1{attr}>{}</h1>\n", escape(title))
+}
+
+/// Render one line of inline markup as an HTML fragment, replacing
+/// attribute references with `attributes` (see "Inline Snippets" above)
+pub fn inline_to_html(text: &str, attributes: &Attributes) -> String {
+    let mut content = format_inlines(text, &lex_with_spans(text));
+    substitute_inlines(&mut content, attributes);
+    let context = Context {
+        templates: &RoleTemplates::new(),
+        references: &References::default(),
+        strict_csp: false,
+        safe: false,
+        substitutions: &SubstitutionDefaults::new(),
+        special_characters: Cell::new(true),
+        source_language: None,
+        document: attributes,
+        assets: &AssetUrls::default(),
+        language_aliases: &BTreeMap::new(),
+        attributes: &NodeAttributes::default(),
+        footnotes: RefCell::default(),
+        footnote_placement: FootnotePlacement::Document,
+        footnote_scope: Cell::new(0),
+        footnote_scopes: Cell::new(0),
+        exercises: RefCell::default(),
+        collapse_solutions: false,
+        outline: &Outline::default(),
+        admonition_icons: &BTreeMap::new(),
+    };
+    let mut out = String::new();
+    render_inlines(&content, &context, &mut out);
+    out
+}
+
+/// Render `block` with the node ID `id` (see "Node IDs" above), resolving
+/// cross references with `references` and image targets with the document
+/// `attributes`
+pub(crate) fn render_node(
+    block: &Block,
+    id: &str,
+    references: &References,
+    attributes: &Attributes,
+) -> String {
+    let mut out = String::new();
+    let context = Context {
+        templates: &RoleTemplates::new(),
+        references,
+        strict_csp: false,
+        safe: false,
+        substitutions: &SubstitutionDefaults::new(),
+        special_characters: Cell::new(true),
+        source_language: None,
+        document: attributes,
+        assets: &AssetUrls::default(),
+        language_aliases: &BTreeMap::new(),
+        attributes: &NodeAttributes::default(),
+        footnotes: RefCell::default(),
+        footnote_placement: FootnotePlacement::Document,
+        footnote_scope: Cell::new(0),
+        footnote_scopes: Cell::new(0),
+        exercises: RefCell::default(),
+        collapse_solutions: false,
+        outline: &Outline::default(),
+        admonition_icons: &BTreeMap::new(),
+    };
+    render_block(block, Some(id), &context, &mut out);
+    out
+}
+
+/// Render `block`; `id` is its node ID, if IDs are enabled
+fn render_block(block: &Block, id: Option<&str>, context: &Context<'_>, out: &mut String) {
+    let subs = substitutions(block, context.substitutions);
+    let outer = context
+        .special_characters
+        .replace(subs.contains(Substitutions::SPECIAL_CHARACTERS));
+    render_block_with_subs(block, id, context, out);
+    context.special_characters.set(outer);
+}
+
+/// [`render_block`], with the block's substitutions in `context`
+fn render_block_with_subs(
+    block: &Block,
+    id: Option<&str>,
+    context: &Context<'_>,
+    out: &mut String,
+) {
+    if let Some(html) = context.templates.render(block, id, context) {
+        out.push_str(&html);
+        return;
+    }
+    let mut attrs = HtmlAttrs::new();
+    if let Some(anchor) = block.anchor() {
+        attrs.set("id", &anchor.id);
+    }
+    if let Some(id) = id {
+        attrs.set("data-node", id);
+    }
+    match block {
+        // Alignment and indent roles (`[.text-center]`) style paragraphs
+        Block::Paragraph { metadata, .. } => {
+            for role in &metadata.roles {
+                attrs.add_class(role);
+            }
+        }
+        Block::Admonition { kind, metadata, .. } => {
+            attrs.add_class("admonitionblock");
+            attrs.add_class(kind.name());
+            for role in &metadata.roles {
+                attrs.add_class(role);
+            }
+        }
+        Block::Example { .. } => {
+            attrs.add_class("example");
+            if let Some(kind) = ExerciseKind::of(block) {
+                attrs.add_class(kind.style());
+            }
+        }
+        Block::Passthrough { .. } if context.safe => attrs.add_class("passthrough"),
+        Block::Open { attributes, .. } => {
+            attrs.add_class("openblock");
+            if let Some(style) = attributes.style() {
+                attrs.add_class(style);
+            }
+        }
+        _ => {}
+    }
+    context.attributes.apply(block, &mut attrs);
+    let attr = attrs.render();
+    // Titles have the normal substitutions, whatever the block's `subs`
+    let caption = block.metadata().title.as_ref().map(|title| {
+        let outer = context.special_characters.replace(true);
+        let mut caption = String::new();
+        render_inlines(title, context, &mut caption);
+        context.special_characters.set(outer);
+        caption
+    });
+    match block {
+        Block::Section {
+            level: 1,
+            title,
+            content,
+            ..
+        } => {
+            out.push_str(&format!("<h1{attr}>"));
+            render_inlines(title, context, out);
+            out.push_str("</h1>\n");
+            render_children(content, id, context, out);
+        }
+        Block::Section {
+            level,
+            title,
+            content,
+            ..
+        } => {
+            let tag = format!("h{}", (*level).clamp(2, 6));
+            // The anchor goes on the heading, everything else on the section
+            let mut heading = HtmlAttrs::new();
+            if let Some(anchor) = attrs.remove("id") {
+                heading.set("id", anchor);
+            }
+            out.push_str(&format!("<section{}>\n", attrs.render()));
+            // A section that lists its footnotes numbers them afresh
+            let outer = context.lists_footnotes(*level).then(|| {
+                let scope = context.footnote_scopes.get() + 1;
+                context.footnote_scopes.set(scope);
+                (
+                    context.footnotes.take(),
+                    context.footnote_scope.replace(scope),
+                )
+            });
+            out.push_str(&format!("<{tag}{}>", heading.render()));
+            render_inlines(title, context, out);
+            out.push_str(&format!("</{tag}>\n"));
+            render_children(content, id, context, out);
+            if let Some((footnotes, scope)) = outer {
+                render_footnotes(context, out);
+                context.footnotes.replace(footnotes);
+                context.footnote_scope.set(scope);
+            }
+            out.push_str("</section>\n");
+        }
+        // Comments, attribute entries and unsupported syntax are kept for
+        // round-tripping, never rendered; list macros are replaced by their
+        // list
+        Block::Comment { .. } | Block::AttributeEntry { .. } => {}
+        Block::Unknown { .. } => {
+            let list = list_macro(block).map(|kind| render_caption_list(context.outline, kind));
+            if let Some(list) = list.filter(|list| !list.is_empty()) {
+                out.push_str(&format!("<nav{attr}>\n"));
+                if let Some(caption) = &caption {
+                    out.push_str(&format!("<p class=\"title\">{caption}</p>\n"));
+                }
+                out.push_str(&list);
+                out.push_str("</nav>\n");
+            }
+        }
+        Block::Passthrough { .. } => match block.passthrough_for("html") {
+            Some(content) if context.safe => {
+                out.push_str(&format!("<pre{attr}>{}</pre>\n", escape(content)));
+            }
+            Some(content) => {
+                out.push_str(content);
+                out.push('\n');
+            }
+            None => {}
+        },
+        Block::Paragraph { content, .. } => {
+            out.push_str(&format!("<p{attr}>"));
+            render_inlines(content, context, out);
+            out.push_str("</p>\n");
+        }
+        Block::UnorderedList { items, .. } => render_list("ul", &attr, items, context, out),
+        Block::OrderedList { items, .. } => render_list("ol", &attr, items, context, out),
+        Block::DescriptionList { items, .. } => render_description_list(&attr, items, context, out),
+        Block::Listing {
+            language,
+            content,
+            attributes,
+            metadata,
+        } => {
+            let code = code_lines(content, metadata, context);
+            // A titled listing is a figure; the attributes move to it
+            let attr = match &caption {
+                Some(caption) => {
+                    out.push_str(&format!(
+                        "<figure{attr}>\n<figcaption>{caption}</figcaption>\n"
+                    ));
+                    String::new()
+                }
+                None => attr,
+            };
+            if attributes.get("style") == Some("literal") {
+                out.push_str(&format!("<pre{attr}>{code}</pre>\n"));
+            } else {
+                let language = match language {
+                    Some(language) => Some(language.as_str()),
+                    None if attributes.get("style") == Some("source") => context.source_language,
+                    None => None,
+                };
+                let class = language
+                    .map(|language| {
+                        let language = context
+                            .language_aliases
+                            .get(language)
+                            .map_or(language, String::as_str);
+                        format!(" class=\"language-{}\"", escape(language))
+                    })
+                    .unwrap_or_default();
+                out.push_str(&format!("<pre{attr}><code{class}>{code}</code></pre>\n"));
+            }
+            if caption.is_some() {
+                out.push_str("</figure>\n");
+            }
+        }
+        Block::Table {
+            columns,
+            header,
+            rows,
+            ..
+        } => render_table(
+            &attr,
+            caption.as_deref(),
+            columns,
+            header.as_ref(),
+            rows,
+            context,
+            out,
+        ),
+        Block::Quote {
+            attribution,
+            citation,
+            content,
+            ..
+        } => render_quote(&attr, attribution, citation, out, |out| {
+            for block in content {
+                render_block(block, None, context, out);
+            }
+        }),
+        Block::Admonition { kind, content, .. } => {
+            out.push_str(&format!(
+                "<div{attr}>\n<div class=\"label\">{}</div>\n<div class=\"content\">\n",
+                admonition_label(*kind, context)
+            ));
+            if let Some(caption) = &caption {
+                out.push_str(&format!("<p class=\"title\">{caption}</p>\n"));
+            }
+            for block in content {
+                render_block(block, None, context, out);
+            }
+            out.push_str("</div>\n</div>\n");
+        }
+        Block::Example { content, .. } => {
+            let label = context.exercises.borrow_mut().register(block);
+            let (tag, caption_tag) = match label {
+                None => ("div", None),
+                Some(label)
+                    if label.kind == ExerciseKind::Solution && context.collapse_solutions =>
+                {
+                    ("details", Some("summary"))
+                }
+                Some(_) => ("figure", Some("figcaption")),
+            };
+            out.push_str(&format!("<{tag}{attr}>\n"));
+            if let (Some(label), Some(caption_tag)) = (label, caption_tag) {
+                let mut text = escape(&label.caption());
+                if let Some(caption) = &caption {
+                    text.push_str(&format!(". {caption}"));
+                }
+                out.push_str(&format!("<{caption_tag}>{text}</{caption_tag}>\n"));
+            }
+            for block in content {
+                render_block(block, None, context, out);
+            }
+            out.push_str(&format!("</{tag}>\n"));
+        }
+        Block::Open { content, .. } => {
+            out.push_str(&format!("<div{attr}>\n"));
+            for block in content {
+                render_block(block, None, context, out);
+            }
+            out.push_str("</div>\n");
+        }
+        Block::Sidebar { content, .. } => {
+            out.push_str(&format!("<aside{attr}>\n"));
+            for block in content {
+                render_block(block, None, context, out);
+            }
+            out.push_str("</aside>\n");
+        }
+        Block::Verse {
+            attribution,
+            citation,
+            content,
+            ..
+        } => render_quote(&attr, attribution, citation, out, |out| {
+            out.push_str("<pre class=\"verse\">");
+            render_inlines(content, context, out);
+            out.push_str("</pre>\n");
+        }),
+        Block::Image {
+            target, attributes, ..
+        } => {
+            out.push_str(&format!(
+                "<figure{attr}>\n{}\n",
+                image(target, attributes, context)
+            ));
+            if let Some(caption) = &caption {
+                out.push_str(&format!("<figcaption>{caption}</figcaption>\n"));
+            }
+            out.push_str("</figure>\n");
+        }
+    }
+}
+
+/// Render the content of a section whose node ID is `parent`
+fn render_children(
+    content: &[Block],
+    parent: Option<&str>,
+    context: &Context<'_>,
+    out: &mut String,
+) {
+    for (index, block) in content.iter().enumerate() {
+        let id = parent.map(|parent| format!("{parent}.{index}"));
+        render_block(block, id.as_deref(), context, out);
+    }
+}
+
+fn render_description_list(
+    attr: &str,
+    items: &[DescriptionItem],
+    context: &Context<'_>,
+    out: &mut String,
+) {
+    out.push_str(&format!("<dl{attr}>\n"));
+    for item in items {
+        out.push_str("<dt>");
+        render_inlines(&item.term, context, out);
+        out.push_str("</dt>\n");
+        if !item.description.is_empty() {
+            out.push_str("<dd>");
+            render_inlines(&item.description, context, out);
+            out.push_str("</dd>\n");
+        }
+    }
+    out.push_str("</dl>\n");
+}
+
+fn render_list(tag: &str, attr: &str, items: &[ListItem], context: &Context<'_>, out: &mut String) {
+    out.push_str(&format!("<{tag}{attr}>\n"));
+    for item in items {
+        out.push_str("<li>");
+        render_inlines(&item.content, context, out);
+        if !item.blocks.is_empty() {
+            out.push('\n');
+            for block in &item.blocks {
+                render_block(block, None, context, out);
+            }
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str(&format!("</{tag}>\n"));
+}
+
+/// Render a `<blockquote>` around the output of `inner`, captioned with the
+/// attribution and citation if there are any
+fn render_quote(
+    attr: &str,
+    attribution: &Option<String>,
+    citation: &Option<String>,
+    out: &mut String,
+    inner: impl FnOnce(&mut String),
+) {
+    if attribution.is_none() && citation.is_none() {
+        out.push_str(&format!("<blockquote{attr}>\n"));
+        inner(out);
+        out.push_str("</blockquote>\n");
+        return;
+    }
+    out.push_str(&format!("<figure{attr}>\n<blockquote>\n"));
+    inner(out);
+    out.push_str("</blockquote>\n<figcaption>");
+    let attribution = attribution
+        .as_ref()
+        .map(|a| format!("&#8212; {}", escape(a)));
+    let citation = citation
+        .as_ref()
+        .map(|c| format!("<cite>{}</cite>", escape(c)));
+    let caption: Vec<String> = attribution.into_iter().chain(citation).collect();
+    out.push_str(&caption.join(", "));
+    out.push_str("</figcaption>\n</figure>\n");
+}
+
+/// The lines of a listing, escaped unless its substitutions leave out
+/// `specialcharacters`, each in a `<span class="line">` if the block numbers
+/// or highlights lines
+///
+/// Highlighted lines get the `highlighted` class; with line numbers, each
+/// line starts with a `<span class="linenum">`.
+fn code_lines(content: &str, metadata: &BlockMetadata, context: &Context<'_>) -> String {
+    let numbered = metadata.line_numbers();
+    let highlighted = metadata.highlight_lines();
+    if !numbered && highlighted.is_empty() {
+        return context.text(content);
+    }
+    content
+        .split('\n')
+        .enumerate()
+        .map(|(index, line)| {
+            let number = index + 1;
+            let class = if highlighted.binary_search(&number).is_ok() {
+                "line highlighted"
+            } else {
+                "line"
+            };
+            let linenum = if numbered {
+                format!("<span class=\"linenum\">{number}</span>")
+            } else {
+                String::new()
+            };
+            format!(
+                "<span class=\"{class}\">{linenum}{}</span>",
+                context.text(line)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_table(
+    attr: &str,
+    caption: Option<&str>,
+    columns: &[ColumnSpec],
+    header: Option<&TableRow>,
+    rows: &[TableRow],
+    context: &Context<'_>,
+    out: &mut String,
+) {
+    out.push_str(&format!("<table{attr}>\n"));
+    if let Some(caption) = caption {
+        out.push_str(&format!("<caption>{caption}</caption>\n"));
+    }
+    if !columns.is_empty() {
+        let total: u32 = columns.iter().map(|column| column.width).sum();
+        out.push_str("<colgroup>\n");
+        for column in columns {
+            if context.strict_csp {
+                let percent = (f64::from(column.width) * 100.0 / f64::from(total)).round();
+                let class = (percent as u32).clamp(1, 100);
+                out.push_str(&format!("<col class=\"colwidth-{class}\">\n"));
+                continue;
+            }
+            let percent = format!("{:.4}", f64::from(column.width) * 100.0 / f64::from(total));
+            let percent = percent.trim_end_matches('0').trim_end_matches('.');
+            out.push_str(&format!("<col style=\"width: {percent}%;\">\n"));
+        }
+        out.push_str("</colgroup>\n");
+    }
+    if let Some(header) = header {
+        out.push_str("<thead>\n");
+        render_row(header, "th", context, out);
+        out.push_str("</thead>\n");
+    }
+    if !rows.is_empty() {
+        out.push_str("<tbody>\n");
+        for row in rows {
+            render_row(row, "td", context, out);
+        }
+        out.push_str("</tbody>\n");
+    }
+    out.push_str("</table>\n");
+}
+
+fn render_row(row: &TableRow, tag: &str, context: &Context<'_>, out: &mut String) {
+    out.push_str("<tr>\n");
+    for cell in &row.cells {
+        out.push_str(&format!("<{tag}"));
+        if cell.colspan > 1 {
+            out.push_str(&format!(" colspan=\"{}\"", cell.colspan));
+        }
+        if cell.rowspan > 1 {
+            out.push_str(&format!(" rowspan=\"{}\"", cell.rowspan));
+        }
+        out.push('>');
+        render_inlines(&cell.content, context, out);
+        out.push_str(&format!("</{tag}>\n"));
+    }
+    out.push_str("</tr>\n");
+}
+
+/// Render the footnotes registered so far in the current scope, each
+/// linking back to its marker
+fn render_footnotes(context: &Context<'_>, out: &mut String) {
+    if context.footnotes.borrow().is_empty() {
+        return;
+    }
+    if context.footnote_scope.get() == 0 {
+        out.push_str("<div id=\"footnotes\">\n<hr>\n");
+    } else {
+        out.push_str("<div class=\"footnotes\">\n<hr>\n");
+    }
+    // Rendering a footnote's text may register more footnotes, so the
+    // borrow must end before it does
+    let mut index = 0;
+    loop {
+        let entry = context.footnotes.borrow().entries().get(index).cloned();
+        let Some(entry) = entry else { break };
+        let number = entry.number;
+        let key = context.footnote_key(number);
+        out.push_str(&format!(
+            "<div class=\"footnote\" id=\"_footnotedef_{key}\">\n\
+             <a href=\"#_footnoteref_{key}\">{number}</a>. "
+        ));
+        render_inlines(&entry.text, context, out);
+        out.push_str("\n</div>\n");
+        index += 1;
+    }
+    out.push_str("</div>\n");
+}
+
+fn render_inlines(content: &[Inline], context: &Context<'_>, out: &mut String) {
+    for inline in content {
+        match inline {
+            Inline::Text(text) => out.push_str(&context.text(text)),
+            Inline::Bold(nested) => {
+                out.push_str("<strong>");
+                render_inlines(nested, context, out);
+                out.push_str("</strong>");
+            }
+            Inline::Italic(nested) => {
+                out.push_str("<em>");
+                render_inlines(nested, context, out);
+                out.push_str("</em>");
+            }
+            Inline::Monospace(nested) => {
+                out.push_str("<code>");
+                render_inlines(nested, context, out);
+                out.push_str("</code>");
+            }
+            Inline::Highlight(nested) => {
+                out.push_str("<mark>");
+                render_inlines(nested, context, out);
+                out.push_str("</mark>");
+            }
+            Inline::Span { roles, content } => render_span(roles, content, context, out),
+            Inline::Superscript(nested) => {
+                out.push_str("<sup>");
+                render_inlines(nested, context, out);
+                out.push_str("</sup>");
+            }
+            Inline::Subscript(nested) => {
+                out.push_str("<sub>");
+                render_inlines(nested, context, out);
+                out.push_str("</sub>");
+            }
+            Inline::Link {
+                target,
+                text,
+                attributes,
+            } => render_link(target, text, attributes, context, out),
+            Inline::XRef { id, text } => {
+                let href = context.references.href(id);
+                out.push_str(&format!("<a href=\"{}\">", escape(&href)));
+                if text.is_empty() {
+                    out.push_str(&escape(&context.references.label(id)));
+                } else {
+                    render_inlines(text, context, out);
+                }
+                out.push_str("</a>");
+            }
+            Inline::Anchor(anchor) => {
+                out.push_str(&format!("<a id=\"{}\"></a>", escape(&anchor.id)));
+            }
+            Inline::Image { target, attributes } => {
+                out.push_str(&image(target, attributes, context));
+            }
+            Inline::Icon { name, attributes } => out.push_str(&icon(name, attributes, context)),
+            Inline::BreakHint(BreakHint::SoftHyphen) => out.push_str("&shy;"),
+            Inline::BreakHint(BreakHint::NoBreakSpace) => out.push_str("&nbsp;"),
+            Inline::Footnote { id, text } => {
+                let registered = context.footnotes.borrow_mut().register(id.as_deref(), text);
+                match registered {
+                    Some(FootnoteRef { number, first }) => {
+                        let key = context.footnote_key(number);
+                        let (class, back) = if first {
+                            ("footnote", format!(" id=\"_footnoteref_{key}\""))
+                        } else {
+                            ("footnoteref", String::new())
+                        };
+                        out.push_str(&format!(
+                            "<sup class=\"{class}\">[<a{back} class=\"footnote\" \
+                             href=\"#_footnotedef_{key}\" title=\"View footnote.\">\
+                             {number}</a>]</sup>"
+                        ));
+                    }
+                    None => out.push_str(&format!(
+                        "<sup class=\"footnoteref unresolved\">[{}]</sup>",
+                        escape(id.as_deref().unwrap_or_default())
+                    )),
+                }
+            }
+        }
+    }
+}
+
+/// Elements for the well-known decoration roles, so the decoration shows
+/// without a stylesheet
+const DECORATIONS: [(&str, &str); 3] = [
+    ("line-through", "s"),
+    ("underline", "u"),
+    ("small", "small"),
+];
+
+/// Render a span with roles: an element per decoration role, nested in the
+/// order the roles are given, or a `<span>` if there is none; the outermost
+/// element carries all the roles as classes
+fn render_span(roles: &[String], content: &[Inline], context: &Context<'_>, out: &mut String) {
+    let mut elements: Vec<&str> = roles
+        .iter()
+        .filter_map(|role| {
+            DECORATIONS
+                .iter()
+                .find(|(name, _)| name == role)
+                .map(|(_, element)| *element)
+        })
+        .collect();
+    elements.dedup();
+    if elements.is_empty() {
+        elements.push("span");
+    }
+    let class = escape(&roles.join(" "));
+    for (index, element) in elements.iter().enumerate() {
+        if index == 0 {
+            out.push_str(&format!("<{element} class=\"{class}\">"));
+        } else {
+            out.push_str(&format!("<{element}>"));
+        }
+    }
+    render_inlines(content, context, out);
+    for element in elements.iter().rev() {
+        out.push_str(&format!("</{element}>"));
+    }
+}
+
+/// Render a link; links without text show their URL (`class="bare"`)
+fn render_link(
+    target: &str,
+    text: &[Inline],
+    attributes: &Attributes,
+    context: &Context<'_>,
+    out: &mut String,
+) {
+    let mut classes = attributes.roles();
+    if text.is_empty() {
+        classes.insert(0, "bare");
+    }
+    out.push_str(&format!("<a href=\"{}\"", escape(target)));
+    if !classes.is_empty() {
+        out.push_str(&format!(" class=\"{}\"", escape(&classes.join(" "))));
+    }
+    if let Some(window) = attributes.get("window") {
+        out.push_str(&format!(" target=\"{}\"", escape(window)));
+        if window == "_blank" {
+            out.push_str(" rel=\"noopener\"");
+        }
+    }
+    out.push('>');
+    if text.is_empty() {
+        out.push_str(&escape(target));
+    } else {
+        render_inlines(text, context, out);
+    }
+    out.push_str("</a>");
+}
+
+/// The `<img>` element for an image macro, with the target resolved against
+/// `imagesdir`
+fn image(target: &str, attributes: &Attributes, context: &Context<'_>) -> String {
+    let alt = match attributes.get("alt") {
+        Some(alt) => alt.to_string(),
+        None => default_alt(target),
+    };
+    let src = image_target(target, context.document);
+    img(&src, &alt, attributes, context)
+}
+
+/// The element for an icon macro: an `<img>` from `iconsdir` if the
+/// document sets `icons`, a Font Awesome `<i>` if it sets `icons` to
+/// `font`, and the name in brackets otherwise
+fn icon(name: &str, attributes: &Attributes, context: &Context<'_>) -> String {
+    let alt = attributes.get("alt").unwrap_or(name);
+    let icon = match context.document.get("icons") {
+        Some("font") => {
+            let size = attributes
+                .get("size")
+                .map(|size| format!(" fa-{}", escape(size)))
+                .unwrap_or_default();
+            format!(
+                "<i class=\"fa fa-{}{size}\" title=\"{}\"></i>",
+                escape(name),
+                escape(alt)
+            )
+        }
+        Some(_) => img(
+            &icon_target(name, context.document),
+            alt,
+            attributes,
+            context,
+        ),
+        None => format!("[{}]", escape(alt)),
+    };
+    format!("<span class=\"icon\">{icon}</span>")
+}
+
+/// The label of a `kind` admonition: its caption, from the `*-caption`
+/// attribute (`note-caption`) if set, as text or as an icon
+fn admonition_label(kind: AdmonitionKind, context: &Context<'_>) -> String {
+    let caption = context
+        .document
+        .get(&format!("{}-caption", kind.name()))
+        .unwrap_or(kind.caption());
+    let icons = context
+        .admonition_icons
+        .get(&kind)
+        .copied()
+        .unwrap_or_else(|| AdmonitionIcons::from_attributes(context.document));
+    match icons {
+        AdmonitionIcons::Text => escape(caption),
+        AdmonitionIcons::Font => format!(
+            "<i class=\"fa icon-{}\" title=\"{}\"></i>",
+            kind.name(),
+            escape(caption)
+        ),
+        AdmonitionIcons::Image => img(
+            &icon_target(kind.name(), context.document),
+            caption,
+            &Attributes::new(),
+            context,
+        ),
+    }
+}
+
+/// An `<img>` element for the image at `src`, routed through the asset hook
+fn img(src: &str, alt: &str, attributes: &Attributes, context: &Context<'_>) -> String {
+    let src = context.assets.url(AssetKind::Image, src);
+    let mut out = format!("<img src=\"{}\" alt=\"{}\"", escape(&src), escape(alt));
+    for name in ["width", "height"] {
+        if let Some(value) = attributes.get(name) {
+            out.push_str(&format!(" {name}=\"{}\"", escape(value)));
+        }
+    }
+    out.push('>');
+    out
+}
+
+/// Alt text derived from an image's file name, without directories and
+/// extension and with `_` and `-` as spaces
+fn default_alt(target: &str) -> String {
+    let name = target.rsplit('/').next().unwrap_or(target);
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    stem.replace(['_', '-'], " ")
+}
+
+/// Escape text for use in HTML content and double-quoted attribute values
+///
+/// ```
+/// use doctora::render::html::escape;
+///
+/// assert_eq!(escape(r#"<a href="x">&</a>"#), "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;");
+/// ```
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    fn html(source: &str) -> String {
+        render_html(&parse_document(source).unwrap(), &HtmlOptions::new())
+    }
+
+    #[test]
+    fn test_paragraph_formatting() {
+        assert_eq!(
+            html("A **bold _and italic_** word."),
+            "<p>A <strong>bold <em>and italic</em></strong> word.</p>\n"
+        );
+        assert_eq!(
+            html("Call `f(<T>)`."),
+            "<p>Call <code>f(&lt;T&gt;)</code>.</p>\n"
+        );
+        assert_eq!(
+            html("A #key# term: H~2~O and E=mc^2^."),
+            "<p>A <mark>key</mark> term: H<sub>2</sub>O and E=mc<sup>2</sup>.</p>\n"
+        );
+        assert_eq!(
+            html("[line-through]#old# [.underline.small]#new# [.term]#word#"),
+            "<p><s class=\"line-through\">old</s> \
+             <u class=\"underline small\"><small>new</small></u> \
+             <span class=\"term\">word</span></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_special_characters_subs() {
+        assert_eq!(
+            html(
+                "[subs=-specialchars]\n<b>a</b> & *b*\n\n.<T>\n[subs=none]\n----\n<i>\n----\n\n<b>\n"
+            ),
+            "<p><b>a</b> & <strong>b</strong></p>\n\
+             <figure>\n<figcaption>&lt;T&gt;</figcaption>\n<pre><code><i></code></pre>\n</figure>\n\
+             <p>&lt;b&gt;</p>\n"
+        );
+
+        // Defaults by kind; safe output escapes all the same
+        let doc = parse_document("<b>a</b>\n\n* <b>\n").unwrap();
+        let options = HtmlOptions::new().substitutions(
+            "paragraph",
+            Substitutions::NORMAL - Substitutions::SPECIAL_CHARACTERS,
+        );
+        assert_eq!(
+            render_html(&doc, &options),
+            "<p><b>a</b></p>\n<ul>\n<li>&lt;b&gt;</li>\n</ul>\n"
+        );
+        assert_eq!(
+            render_html(&doc, &options.safe(true)),
+            "<p>&lt;b&gt;a&lt;/b&gt;</p>\n<ul>\n<li>&lt;b&gt;</li>\n</ul>\n"
+        );
+    }
+
+    #[test]
+    fn test_paragraph_roles() {
+        assert_eq!(
+            html("[.text-center]\nRelease 2.0\n\n[.text-right.indent-2]\nSigned.\n"),
+            "<p class=\"text-center\">Release 2.0</p>\n\
+             <p class=\"text-right indent-2\">Signed.</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_block_titles() {
+        assert_eq!(
+            html(
+                ".The _logo_\nimage::logo.png[]\n\n.Sizes\n|===\n|1\n|===\n\n\
+                 .Setup\n[source#setup,sh]\n----\nmake\n----\n"
+            ),
+            "<figure>\n<img src=\"logo.png\" alt=\"logo\">\n\
+             <figcaption>The <em>logo</em></figcaption>\n</figure>\n\
+             <table>\n<caption>Sizes</caption>\n<colgroup>\n<col style=\"width: 100%;\">\n</colgroup>\n\
+             <tbody>\n<tr>\n<td>1</td>\n</tr>\n</tbody>\n</table>\n\
+             <figure id=\"setup\">\n<figcaption>Setup</figcaption>\n\
+             <pre><code class=\"language-sh\">make</code></pre>\n</figure>\n"
+        );
+    }
+
+    #[test]
+    fn test_links() {
+        assert_eq!(
+            html("See https://a.io/x?y=1&z, https://b.io[_B_ site^] or http://c.io[C,role=ext]."),
+            "<p>See <a href=\"https://a.io/x?y=1&amp;z\" class=\"bare\">https://a.io/x?y=1&amp;z</a>, \
+             <a href=\"https://b.io\" target=\"_blank\" rel=\"noopener\"><em>B</em> site</a> or \
+             <a href=\"http://c.io\" class=\"ext\">C</a>.</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_xrefs_and_anchors() {
+        assert_eq!(
+            html(
+                "[[start]]\n== Start\n\n[[code,Code sample]]\n----\nx\n----\n\n\
+                 See <<start>>, <<code>>, <<start,_here_>>, <<nowhere>> [[here]].\n"
+            ),
+            "<section>\n<h2 id=\"start\">Start</h2>\n\
+             <pre id=\"code\"><code>x</code></pre>\n\
+             <p>See <a href=\"#start\">Start</a>, <a href=\"#code\">Code sample</a>, \
+             <a href=\"#start\"><em>here</em></a>, <a href=\"#nowhere\">[nowhere]</a> \
+             <a id=\"here\"></a>.</p>\n</section>\n"
+        );
+        assert_eq!(
+            html(":relfileprefix: ../\n\n<<guide.adoc#setup,Setup>>\n"),
+            "<p><a href=\"../guide.html#setup\">Setup</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_images() {
+        assert_eq!(
+            html(
+                "[#fig]\nimage::img/release_notes-v2.png[]\n\nA image:a.png[\"<A>\",16,8] icon.\n"
+            ),
+            "<figure id=\"fig\">\n<img src=\"img/release_notes-v2.png\" alt=\"release notes v2\">\n</figure>\n\
+             <p>A <img src=\"a.png\" alt=\"&lt;A&gt;\" width=\"16\" height=\"8\"> icon.</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_image_targets() {
+        assert_eq!(
+            html(
+                ":imagesdir: img\n\nimage::a.png[]\n\nimage:https://x.org/b.png[B] icon:heart[2x]\n"
+            ),
+            "<figure>\n<img src=\"img/a.png\" alt=\"a\">\n</figure>\n\
+             <p><img src=\"https://x.org/b.png\" alt=\"B\"> <span class=\"icon\">[heart]</span></p>\n"
+        );
+        assert_eq!(
+            html(":imagesdir: img\n:icons:\n\nicon:heart[]\n"),
+            "<p><span class=\"icon\"><img src=\"img/icons/heart.png\" alt=\"heart\"></span></p>\n"
+        );
+        assert_eq!(
+            html(":icons: font\n\nicon:heart[2x]\n"),
+            "<p><span class=\"icon\"><i class=\"fa fa-heart fa-2x\" title=\"heart\"></i></span></p>\n"
+        );
+
+        let doc =
+            parse_document(":imagesdir: img\n:icons:\n\nimage::a.png[]\n\nicon:b[]\n").unwrap();
+        let hook = |kind: AssetKind, path: &str| format!("/cdn/{path}?kind={kind:?}");
+        let routed = render_html(&doc, &HtmlOptions::new().asset_hook(hook));
+        assert!(
+            routed.contains("src=\"/cdn/img/a.png?kind=Image\""),
+            "{routed}"
+        );
+        assert!(
+            routed.contains("src=\"/cdn/img/icons/b.png?kind=Image\""),
+            "{routed}"
+        );
+    }
+
+    #[test]
+    fn test_sections() {
+        assert_eq!(
+            html("= Title\n\nIntro.\n\n== Part\n\nBody.\n"),
+            "<h1>Title</h1>\n<p>Intro.</p>\n<section>\n<h2>Part</h2>\n<p>Body.</p>\n</section>\n"
+        );
+        assert_eq!(
+            html("== Using *doctora* & `x`\n"),
+            "<section>\n<h2>Using <strong>doctora</strong> &amp; <code>x</code></h2>\n</section>\n"
+        );
+    }
+
+    #[test]
+    fn test_lists() {
+        assert_eq!(
+            html("* One\n** Nested\n* Two\n"),
+            "<ul>\n<li>One\n<ul>\n<li>Nested</li>\n</ul>\n</li>\n<li>Two</li>\n</ul>\n"
+        );
+        assert_eq!(html(". First\n"), "<ol>\n<li>First</li>\n</ol>\n");
+    }
+
+    #[test]
+    fn test_description_list() {
+        assert_eq!(
+            html("CPU:: The **brain**\nRAM::\n"),
+            "<dl>\n<dt>CPU</dt>\n<dd>The <strong>brain</strong></dd>\n<dt>RAM</dt>\n</dl>\n"
+        );
+    }
+
+    #[test]
+    fn test_listing_blocks() {
+        assert_eq!(
+            html("[source,rust]\n----\nlet x = a < b;\n----\n\n....\n**raw**\n....\n"),
+            "<pre><code class=\"language-rust\">let x = a &lt; b;</code></pre>\n\
+             <pre>**raw**</pre>\n"
+        );
+    }
+
+    #[test]
+    fn test_source_languages() {
+        let blocks = "[source]\n----\na\n----\n\n[source,js]\n----\nb\n----\n\n----\nc\n----\n";
+        let options = HtmlOptions::new()
+            .source_language("python")
+            .language_alias("js", "javascript");
+        let render = |source: &str| render_html(&parse_document(source).unwrap(), &options);
+        assert_eq!(
+            render(blocks),
+            "<pre><code class=\"language-python\">a</code></pre>\n\
+             <pre><code class=\"language-javascript\">b</code></pre>\n\
+             <pre><code>c</code></pre>\n"
+        );
+        // The document's attribute wins over the option, and is aliased too
+        let with_attribute = render(&format!(":source-language: js\n\n{blocks}"));
+        assert!(
+            with_attribute.starts_with("<pre><code class=\"language-javascript\">a</code></pre>\n")
+        );
+        // Without options, bare source blocks have no language
+        assert!(html(blocks).starts_with("<pre><code>a</code></pre>\n"));
+    }
+
+    #[test]
+    fn test_passthrough_blocks() {
+        let source =
+            "++++\n<video src=\"a.mp4\"></video>\n++++\n\n[backend=latex]\n++++\n\\newpage\n++++\n";
+        assert_eq!(html(source), "<video src=\"a.mp4\"></video>\n");
+        let doc = parse_document(source).unwrap();
+        assert_eq!(
+            render_html(&doc, &HtmlOptions::new().safe(true).node_ids(true)),
+            "<pre data-node=\"0\" class=\"passthrough\">&lt;video src=&quot;a.mp4&quot;&gt;&lt;/video&gt;</pre>\n"
+        );
+    }
+
+    #[test]
+    fn test_listing_line_numbers_and_highlight() {
+        assert_eq!(
+            html("[source%linenums,sh,highlight=2..3]\n----\na\n<b>\nc\nd\n----\n"),
+            "<pre><code class=\"language-sh\">\
+             <span class=\"line\"><span class=\"linenum\">1</span>a</span>\n\
+             <span class=\"line highlighted\"><span class=\"linenum\">2</span>&lt;b&gt;</span>\n\
+             <span class=\"line highlighted\"><span class=\"linenum\">3</span>c</span>\n\
+             <span class=\"line\"><span class=\"linenum\">4</span>d</span></code></pre>\n"
+        );
+        assert_eq!(
+            html("[source,highlight=1]\n----\nx\ny\n----\n"),
+            "<pre><code><span class=\"line highlighted\">x</span>\n<span class=\"line\">y</span></code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        assert_eq!(
+            html("[cols=\"1,2\"]\n|===\n|Name |Notes\n\n|a |**b**\n2+|wide\n|==="),
+            "<table>\n<colgroup>\n<col style=\"width: 33.3333%;\">\n\
+             <col style=\"width: 66.6667%;\">\n</colgroup>\n\
+             <thead>\n<tr>\n<th>Name</th>\n<th>Notes</th>\n</tr>\n</thead>\n\
+             <tbody>\n<tr>\n<td>a</td>\n<td><strong>b</strong></td>\n</tr>\n\
+             <tr>\n<td colspan=\"2\">wide</td>\n</tr>\n</tbody>\n</table>\n"
+        );
+    }
+
+    #[test]
+    fn test_quote_blocks() {
+        assert_eq!(
+            html("____\nWise _words_.\n____\n"),
+            "<blockquote>\n<p>Wise <em>words</em>.</p>\n</blockquote>\n"
+        );
+        assert_eq!(
+            html("[verse, Poet, Poem]\n____\nRoses\n  are red\n____\n"),
+            "<figure>\n<blockquote>\n<pre class=\"verse\">Roses\n  are red</pre>\n\
+             </blockquote>\n<figcaption>&#8212; Poet, <cite>Poem</cite></figcaption>\n\
+             </figure>\n"
+        );
+    }
+
+    #[test]
+    fn test_example_and_sidebar() {
+        assert_eq!(
+            html("====\nSee:\n\n****\nAside.\n****\n====\n"),
+            "<div class=\"example\">\n<p>See:</p>\n<aside>\n<p>Aside.</p>\n</aside>\n</div>\n"
+        );
+    }
+
+    #[test]
+    fn test_open_block() {
+        assert_eq!(
+            html("[abstract]\n--\nSummary.\n--\n"),
+            "<div class=\"openblock abstract\">\n<p>Summary.</p>\n</div>\n"
+        );
+    }
+
+    #[test]
+    fn test_role_templates() {
+        let doc = parse_document(
+            "[.card.wide]\n--\nA.\n--\n\n[source.wide,rust]\n----\nx\n----\n\n[.plain]\n--\nB.\n--\n",
+        )
+        .unwrap();
+        let options = HtmlOptions::new()
+            .node_ids(true)
+            .template("wide", |block: &Block, context: &TemplateContext<'_>| {
+                let Block::Open { content, .. } = block else {
+                    return None;
+                };
+                Some(format!(
+                    "<article data-node=\"{}\">\n{}</article>\n",
+                    context.node_id().unwrap_or_default(),
+                    context.render_blocks(content)
+                ))
+            })
+            .template("card", |_: &Block, _: &TemplateContext<'_>| None);
+
+        assert_eq!(
+            render_html(&doc, &options),
+            "<article data-node=\"0\">\n<p>A.</p>\n</article>\n\
+             <pre data-node=\"1\"><code class=\"language-rust\">x</code></pre>\n\
+             <div data-node=\"2\" class=\"openblock\">\n<p>B.</p>\n</div>\n"
+        );
+        assert_eq!(
+            options.templates.roles().collect::<Vec<_>>(),
+            ["wide", "card"]
+        );
+        assert_eq!(options.clone(), options);
+    }
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(
+            html("a < b & \"c\""),
+            "<p>a &lt; b &amp; &quot;c&quot;</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_standalone_page() {
+        let mut doc = parse_document("= My <Doc>\n\nText.").unwrap();
+        doc.attributes.set("lang", "de");
+        let page = render_html(&doc, &HtmlOptions::new().standalone(true));
+
+        assert!(page.starts_with("<!DOCTYPE html>\n<html lang=\"de\">\n"));
+        assert!(page.contains("<title>My &lt;Doc&gt;</title>"));
+        assert!(page.contains("<body>\n<h1>My &lt;Doc&gt;</h1>\n<p>Text.</p>\n</body>"));
+        assert!(page.ends_with("</html>\n"));
+    }
+
+    #[test]
+    fn test_node_ids() {
+        let doc = parse_document("= T\n\nA.\n\n== S\n\nB.\n\n* x\n").unwrap();
+        assert_eq!(
+            render_html(&doc, &HtmlOptions::new().node_ids(true)),
+            "<h1 data-node=\"title\">T</h1>\n<p data-node=\"0\">A.</p>\n\
+             <section data-node=\"1\">\n<h2>S</h2>\n<p data-node=\"1.0\">B.</p>\n\
+             <ul data-node=\"1.1\">\n<li>x</li>\n</ul>\n</section>\n"
+        );
+    }
+
+    #[test]
+    fn test_asset_hook() {
+        let doc = parse_document("Text.").unwrap();
+        let options = HtmlOptions::new()
+            .standalone(true)
+            .stylesheet("css/a b.css")
+            .script("app.js");
+        let plain = render_html(&doc, &options);
+        assert!(plain.contains("<link rel=\"stylesheet\" href=\"css/a b.css\">\n"));
+        assert!(plain.contains("<p>Text.</p>\n<script src=\"app.js\"></script>\n</body>"));
+
+        let hook = |kind: AssetKind, path: &str| format!("/cdn/{path}?v=1&kind={kind:?}");
+        let routed = render_html(&doc, &options.clone().asset_hook(hook));
+        assert!(routed.contains("href=\"/cdn/css/a b.css?v=1&amp;kind=Stylesheet\">"));
+        assert!(routed.contains("<script src=\"/cdn/app.js?v=1&amp;kind=Script\">"));
+
+        assert_eq!(options.clone(), options);
+        assert_ne!(options.clone().asset_hook(hook), options);
+        assert_eq!(
+            render_html(&doc, &options.standalone(false)),
+            "<p>Text.</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_attribute_hook() {
+        let doc = parse_document("[[s]]\n== S\n\n[abstract]\n--\nA.\n--\n").unwrap();
+        let hook = |block: &Block, attrs: &mut HtmlAttrs| match block {
+            Block::Section { .. } => attrs.set("data-track", "a\"b"),
+            Block::Open { .. } => {
+                attrs.add_class("x");
+                attrs.set("data-node", "custom");
+            }
+            _ => {}
+        };
+        let options = HtmlOptions::new().node_ids(true).attribute_hook(hook);
+        assert_eq!(
+            render_html(&doc, &options),
+            "<section data-node=\"0\" data-track=\"a&quot;b\">\n<h2 id=\"s\">S</h2>\n\
+             <div data-node=\"custom\" class=\"openblock abstract x\">\n<p>A.</p>\n</div>\n\
+             </section>\n"
+        );
+        assert_ne!(options.clone().attribute_hook(hook), options);
+
+        let mut attrs = HtmlAttrs::new();
+        attrs.add_class("a");
+        attrs.set("id", "x");
+        attrs.set("class", "b");
+        assert_eq!(
+            attrs.iter().collect::<Vec<_>>(),
+            [("class", "b"), ("id", "x")]
+        );
+        assert_eq!(attrs.remove("id").as_deref(), Some("x"));
+        assert_eq!(attrs.get("id"), None);
+
+        let mut attrs = HtmlAttrs::new();
+        for name in ["", "a b", "x\"y", "on=1", "a>b", "a/b", "a\nb", "data-ok"] {
+            attrs.set(name, "v");
+        }
+        assert_eq!(attrs.render(), " data-ok=\"v\"");
+    }
+
+    #[test]
+    fn test_footnotes() {
+        let doc =
+            parse_document("A.footnote:n[Note *one*.]\n\nB.footnote:n[] C.footnote:m[]\n").unwrap();
+        assert_eq!(
+            render_html(&doc, &HtmlOptions::new()),
+            "<p>A.<sup class=\"footnote\">[<a id=\"_footnoteref_1\" class=\"footnote\" \
+             href=\"#_footnotedef_1\" title=\"View footnote.\">1</a>]</sup></p>\n\
+             <p>B.<sup class=\"footnoteref\">[<a class=\"footnote\" href=\"#_footnotedef_1\" \
+             title=\"View footnote.\">1</a>]</sup> C.<sup class=\"footnoteref unresolved\">[m]</sup></p>\n\
+             <div id=\"footnotes\">\n<hr>\n<div class=\"footnote\" id=\"_footnotedef_1\">\n\
+             <a href=\"#_footnoteref_1\">1</a>. Note <strong>one</strong>.\n</div>\n</div>\n"
+        );
+
+        let doc = parse_document("Plain.\n").unwrap();
+        assert!(!render_html(&doc, &HtmlOptions::new()).contains("footnotes"));
+    }
+
+    #[test]
+    fn test_footnote_placement() {
+        let doc = parse_document(
+            "A.footnote:t[Top.]\n\n== One\n\nB.footnote:[First.] footnote:t[]\n\n\
+             === Sub\n\nC.footnote:[Nested.]\n",
+        )
+        .unwrap();
+        let render =
+            |placement| render_html(&doc, &HtmlOptions::new().footnote_placement(placement));
+        let list = |class: &str, entries: &[(&str, usize, &str)]| {
+            let mut out = format!("<div {class}>\n<hr>\n");
+            for (key, number, text) in entries {
+                out.push_str(&format!(
+                    "<div class=\"footnote\" id=\"_footnotedef_{key}\">\n\
+                     <a href=\"#_footnoteref_{key}\">{number}</a>. {text}\n</div>\n"
+                ));
+            }
+            out + "</div>\n"
+        };
+
+        let document = render(FootnotePlacement::Document);
+        assert!(document.ends_with(&format!(
+            "</section>\n</section>\n{}",
+            list(
+                "id=\"footnotes\"",
+                &[("1", 1, "Top."), ("2", 2, "First."), ("3", 3, "Nested.")]
+            )
+        )));
+        assert!(
+            document.contains("href=\"#_footnotedef_1\" title=\"View footnote.\">1</a>]</sup></p>")
+        );
+
+        // The chapter numbers from 1 and keeps its subsection's footnotes
+        let chapter = render(FootnotePlacement::Chapter);
+        assert!(chapter.contains(
+            "<p>B.<sup class=\"footnote\">[<a id=\"_footnoteref_1_1\" class=\"footnote\" \
+             href=\"#_footnotedef_1_1\" title=\"View footnote.\">1</a>]</sup> \
+             <sup class=\"footnoteref unresolved\">[t]</sup></p>"
+        ));
+        assert!(chapter.ends_with(&format!(
+            "</section>\n{}</section>\n{}",
+            list(
+                "class=\"footnotes\"",
+                &[("1_1", 1, "First."), ("1_2", 2, "Nested.")]
+            ),
+            list("id=\"footnotes\"", &[("1", 1, "Top.")])
+        )));
+
+        // The subsection lists its own footnotes, before its parent's
+        let section = render(FootnotePlacement::Section);
+        assert!(section.ends_with(&format!(
+            "{}</section>\n{}</section>\n{}",
+            list("class=\"footnotes\"", &[("2_1", 1, "Nested.")]),
+            list("class=\"footnotes\"", &[("1_1", 1, "First.")]),
+            list("id=\"footnotes\"", &[("1", 1, "Top.")])
+        )));
+    }
+
+    #[test]
+    fn test_footnote_placement_in_sibling_chapters() {
+        let doc = parse_document(
+            "== One\n\nA.footnote:[First.]\n\n== Two\n\nB.footnote:[Second.]\n\n\
+             === Sub\n\nC.footnote:[Third.]\n",
+        )
+        .unwrap();
+        let entry = |key: &str, number: usize, text: &str| {
+            format!(
+                "<div class=\"footnote\" id=\"_footnotedef_{key}\">\n\
+                 <a href=\"#_footnoteref_{key}\">{number}</a>. {text}\n</div>\n"
+            )
+        };
+        let marker = |key: &str, number: usize| {
+            format!(
+                "<sup class=\"footnote\">[<a id=\"_footnoteref_{key}\" class=\"footnote\" \
+                 href=\"#_footnotedef_{key}\" title=\"View footnote.\">{number}</a>]</sup>"
+            )
+        };
+        let options = HtmlOptions::new().footnote_placement(FootnotePlacement::Chapter);
+        assert_eq!(
+            render_html(&doc, &options),
+            format!(
+                "<section>\n<h2>One</h2>\n<p>A.{}</p>\n\
+                 <div class=\"footnotes\">\n<hr>\n{}</div>\n</section>\n\
+                 <section>\n<h2>Two</h2>\n<p>B.{}</p>\n\
+                 <section>\n<h3>Sub</h3>\n<p>C.{}</p>\n</section>\n\
+                 <div class=\"footnotes\">\n<hr>\n{}{}</div>\n</section>\n",
+                marker("1_1", 1),
+                entry("1_1", 1, "First."),
+                marker("2_1", 1),
+                marker("2_2", 2),
+                entry("2_1", 1, "Second."),
+                entry("2_2", 2, "Third."),
+            )
+        );
+
+        let options = HtmlOptions::new().footnote_placement(FootnotePlacement::Section);
+        assert!(render_html(&doc, &options).ends_with(&format!(
+            "<p>C.{}</p>\n<div class=\"footnotes\">\n<hr>\n{}</div>\n</section>\n\
+             <div class=\"footnotes\">\n<hr>\n{}</div>\n</section>\n",
+            marker("3_1", 1),
+            entry("3_1", 1, "Third."),
+            entry("2_1", 1, "Second."),
+        )));
+    }
+
+    #[test]
+    fn test_nested_footnotes() {
+        // Rendering the outer footnote's text registers the inner one
+        let doc = parse_document("A.footnote:[a footnote:id[] b]\n").unwrap();
+        assert!(render_html(&doc, &HtmlOptions::new()).contains(
+            "<a href=\"#_footnoteref_1\">1</a>. a <sup class=\"footnoteref unresolved\">[id]</sup> b\n"
+        ));
+        let doc = parse_document("A.footnote:[a footnote:inner[Inner.] b]\n").unwrap();
+        assert!(render_html(&doc, &HtmlOptions::new()).contains(
+            "<div class=\"footnote\" id=\"_footnotedef_2\">\n\
+             <a href=\"#_footnoteref_2\">2</a>. Inner.\n</div>\n"
+        ));
+    }
+
+    #[test]
+    fn test_exercises() {
+        let doc = parse_document(
+            ".Reverse\n[exercise#rev]\n====\nDo it.\n====\n\n\
+             [solution,exercise=rev]\n====\nDone.\n====\n\n====\nPlain.\n====\n",
+        )
+        .unwrap();
+        assert_eq!(
+            render_html(&doc, &HtmlOptions::new()),
+            "<figure id=\"rev\" class=\"example exercise\">\n\
+             <figcaption>Exercise 1. Reverse</figcaption>\n<p>Do it.</p>\n</figure>\n\
+             <figure class=\"example solution\">\n<figcaption>Solution 1</figcaption>\n\
+             <p>Done.</p>\n</figure>\n\
+             <div class=\"example\">\n<p>Plain.</p>\n</div>\n"
+        );
+        assert!(
+            render_html(&doc, &HtmlOptions::new().collapse_solutions(true)).contains(
+                "<details class=\"example solution\">\n<summary>Solution 1</summary>\n\
+                 <p>Done.</p>\n</details>\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_admonitions() {
+        let doc = parse_document(
+            ":icons: image\n:iconsdir: /icons\n:warning-caption: Careful\n\n\
+             .Backups\n[#b.loud]\nNOTE: Back up.\n\n[WARNING]\n====\nOne.\n\nTwo.\n====\n",
+        )
+        .unwrap();
+        assert_eq!(
+            render_html(&doc, &HtmlOptions::new()),
+            "<div id=\"b\" class=\"admonitionblock note loud\">\n\
+             <div class=\"label\"><img src=\"/icons/note.png\" alt=\"Note\"></div>\n\
+             <div class=\"content\">\n<p class=\"title\">Backups</p>\n<p>Back up.</p>\n</div>\n</div>\n\
+             <div class=\"admonitionblock warning\">\n\
+             <div class=\"label\"><img src=\"/icons/warning.png\" alt=\"Careful\"></div>\n\
+             <div class=\"content\">\n<p>One.</p>\n<p>Two.</p>\n</div>\n</div>\n"
+        );
+        let html = render_html(
+            &doc,
+            &HtmlOptions::new()
+                .admonition_icons(AdmonitionIcons::Text)
+                .admonition_icon(AdmonitionKind::Warning, AdmonitionIcons::Font),
+        );
+        assert!(html.contains("<div class=\"label\">Note</div>"));
+        assert!(html.contains("<i class=\"fa icon-warning\" title=\"Careful\"></i>"));
+        let plain = parse_document("CAUTION: Hot.\n").unwrap();
+        assert!(
+            render_html(&plain, &HtmlOptions::new()).contains("<div class=\"label\">Caution</div>")
+        );
+    }
+
+    #[test]
+    fn test_caption_lists() {
+        let doc = parse_document(
+            ".Figures\n[#lof]\ntoc::[figures]\n\ntoc::[tables]\n\ntoc::[]\n\n\
+             .Arch\n[#arch]\nimage::a.png[]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            render_html(&doc, &HtmlOptions::new()),
+            "<nav id=\"lof\">\n<p class=\"title\">Figures</p>\n\
+             <ul class=\"list-of-figures\">\n<li><a href=\"#arch\">Figure 1. Arch</a></li>\n</ul>\n\
+             </nav>\n\
+             <figure id=\"arch\">\n<img src=\"a.png\" alt=\"a\">\n<figcaption>Arch</figcaption>\n</figure>\n"
+        );
+    }
+
+    #[test]
+    fn test_inline_to_html() {
+        let mut attributes = Attributes::new();
+        attributes.set("name", "<b>");
+        assert_eq!(
+            inline_to_html("* _{name}_{sp}see <<setup>>", &attributes),
+            "* <em>&lt;b&gt;</em> see <a href=\"#setup\">[setup]</a>"
+        );
+        assert_eq!(inline_to_html("", &attributes), "");
+    }
+
+    #[test]
+    fn test_break_hints() {
+        let options = crate::options::ParseOptions::new().break_hints(true);
+        let doc = crate::parse_document_with_options("10&#160;km, co&shy;op\n", &options).unwrap();
+        assert_eq!(
+            render_html(&doc, &HtmlOptions::new()),
+            "<p>10&nbsp;km, co&shy;op</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_strict_csp() {
+        let doc = parse_document("[cols=\"1,200\"]\n|===\n|a |b\n|===").unwrap();
+        let options = HtmlOptions::new()
+            .standalone(true)
+            .strict_csp(true)
+            .stylesheet("csp.css");
+        let page = render_html(&doc, &options);
+        assert!(page.contains("<col class=\"colwidth-1\">\n<col class=\"colwidth-100\">\n"));
+        assert!(page.contains("<link rel=\"stylesheet\" href=\"csp.css\">"));
+        assert!(!page.contains("style="));
+
+        let css = csp_stylesheet();
+        assert_eq!(css.lines().count(), 100);
+        assert!(css.starts_with("col.colwidth-1 { width: 1%; }\n"));
+    }
+
+    #[test]
+    fn test_standalone_untitled() {
+        let page = render_html(&Document::new(), &HtmlOptions::new().standalone(true));
+        assert!(page.contains("<title>Untitled</title>"));
+    }
+}
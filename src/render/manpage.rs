@@ -0,0 +1,600 @@
+//! Man page renderer
+//!
+//! Writes a document as a roff source file for the `man` macro package,
+//! following the conventions of the AsciiDoc `manpage` doctype:
+//!
+//! - The document title names the page and its volume: `= git-log(1)`. The
+//!   `manname` and `manvolnum` attributes override the two parts, and
+//!   `mansource` and `manmanual` fill the footer and header of the page
+//!   (`.TH`), together with `revdate` (or `docdate`).
+//! - Level 2 sections become `.SH` headings in upper case, deeper ones `.SS`
+//!   subheadings. The first section is usually `NAME`, with a single
+//!   paragraph `name - purpose`; a document without one gets it from the
+//!   `manpurpose` attribute, if set.
+//! - A `SYNOPSIS` section is written like any other; `[verse]` paragraphs
+//!   keep its line breaks.
+//! - The authors from the header are listed in an `AUTHOR` section, unless
+//!   the document has one, and footnotes in a `NOTES` section at the end.
+//!
+//! | AST node           | roff                                                |
+//! |--------------------|-----------------------------------------------------|
+//! | Paragraph          | `.sp` and the filled text                           |
+//! | Lists              | Items indented with `.RS`, with a bullet or number  |
+//! | Description list   | The term, then the description indented             |
+//! | Listing / verse    | No-fill text (`.nf`), listings in constant width    |
+//! | Table              | A `tbl` table, header row in bold; spans not merged |
+//! | Quote / example    | Indented, with the attribution below quotes         |
+//! | Admonition         | Its caption in bold, then its content indented      |
+//! | Block image        | Its alt text in brackets                            |
+//! | Bold / italic      | `\fB` and `\fI`, `\f(BI` when nested                |
+//! | Monospace          | `\f(CR`                                             |
+//! | Link               | Its text followed by the URL in angle brackets      |
+//! | Cross reference    | Its text, or the target's title                     |
+//!
+//! Passthrough blocks for the `manpage` backend are copied as raw roff;
+//! other passthroughs and comments are skipped. Text is escaped so that
+//! backslashes, hyphens, and lines starting with `.` or `'` come out
+//! literally.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::render::manpage::render_manpage;
+//!
+//! let source = "= greet(1)\n:mansource: Greet 1.0\n\n\
+//!               == NAME\n\ngreet - print a greeting\n\n\
+//!               == OPTIONS\n\n`--loud`:: Shout.\n";
+//! let doc = doctora::parse_document(source).unwrap();
+//! let roff = render_manpage(&doc);
+//!
+//! assert!(roff.contains(".TH \"GREET\" \"1\" \"\" \"Greet 1.0\" \"\"\n"));
+//! assert!(roff.contains(".SH \"NAME\"\n.sp\ngreet \\- print a greeting\n"));
+//! assert!(roff.contains("\\f(CR\\-\\-loud\\fR\n.RS 4\nShout.\n.RE\n"));
+//! ```
+
+use crate::ast::{Block, Document, Inline, ListItem, TableRow, plain_text};
+use crate::footnotes::Footnotes;
+use crate::processor::{Output, Processor, ProcessorContext, ProcessorError};
+use crate::xref::References;
+
+/// Render `doc` as a man page in roff
+pub fn render_manpage(doc: &Document) -> String {
+    let mut writer = Writer {
+        out: String::new(),
+        references: References::new(doc),
+        footnotes: Footnotes::default(),
+        tables: false,
+    };
+    let title = doc.header.as_ref().and_then(|h| h.title.as_deref());
+    let (title_name, title_volume) = title.map_or((None, None), split_title);
+    let attribute = |name: &str| doc.attributes.get(name).filter(|v| !v.is_empty());
+    let name = attribute("manname").or(title_name).unwrap_or_default();
+    let volume = attribute("manvolnum").or(title_volume).unwrap_or("1");
+
+    let has_section = |wanted: &[&str]| {
+        doc.blocks.iter().any(|block| match block {
+            Block::Section { title, .. } => {
+                wanted.contains(&plain_text(title).trim().to_uppercase().as_str())
+            }
+            _ => false,
+        })
+    };
+    if !has_section(&["NAME"])
+        && let Some(purpose) = attribute("manpurpose")
+    {
+        writer.out.push_str(".SH \"NAME\"\n");
+        writer.line(&format!("{} \\- {}", escape(name), escape(purpose)));
+    }
+    writer.blocks(&doc.blocks);
+    if let Some(header) = &doc.header
+        && !header.authors.is_empty()
+        && !has_section(&["AUTHOR", "AUTHORS"])
+    {
+        let heading = if header.authors.len() == 1 {
+            "AUTHOR"
+        } else {
+            "AUTHORS"
+        };
+        writer.out.push_str(&format!(".SH \"{heading}\"\n"));
+        for author in &header.authors {
+            let line = match &author.email {
+                Some(email) => format!("{} <{email}>", author.name),
+                None => author.name.clone(),
+            };
+            writer.out.push_str(".sp\n");
+            writer.line(&escape(&line));
+        }
+    }
+    if !writer.footnotes.is_empty() {
+        writer.out.push_str(".SH \"NOTES\"\n");
+        for entry in writer.footnotes.entries().to_vec() {
+            writer
+                .out
+                .push_str(&format!(".IP \"{:2}.\" 4\n", entry.number));
+            let text = writer.inlines(&entry.text);
+            writer.text(&text);
+        }
+    }
+
+    let date = attribute("revdate")
+        .or(attribute("docdate"))
+        .unwrap_or_default();
+    let source = attribute("mansource").unwrap_or_default();
+    let manual = attribute("manmanual").unwrap_or_default();
+    let mut page = String::new();
+    if writer.tables {
+        // Tells man to run the page through tbl
+        page.push_str("'\\\" t\n");
+    }
+    page.push_str(&format!(".\\\"     Title: {name}\n"));
+    page.push_str(".\\\" Generator: doctora\n");
+    page.push_str(&format!(".\\\"      Date: {date}\n"));
+    page.push_str(&format!(".\\\"    Manual: {manual}\n"));
+    page.push_str(&format!(".\\\"    Source: {source}\n"));
+    page.push_str(".\\\"\n");
+    page.push_str(&format!(
+        ".TH \"{}\" \"{}\" \"{}\" \"{}\" \"{}\"\n",
+        escape(&name.to_uppercase()),
+        escape(volume),
+        escape(date),
+        escape(source),
+        escape(manual)
+    ));
+    // No hyphenation, ragged right
+    page.push_str(".nh\n.ad l\n");
+    page.push_str(&writer.out);
+    page
+}
+
+/// The built-in `manpage` processor
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ManpageProcessor;
+
+impl Processor for ManpageProcessor {
+    fn name(&self) -> &str {
+        "manpage"
+    }
+
+    fn can_handle(&self, format: &str) -> bool {
+        format == "manpage"
+    }
+
+    fn process(
+        &self,
+        doc: &Document,
+        _context: &ProcessorContext<'_>,
+    ) -> Result<Output, ProcessorError> {
+        Ok(Output::Text(render_manpage(doc)))
+    }
+}
+
+/// Split a `name(volume)` title into its parts
+fn split_title(title: &str) -> (Option<&str>, Option<&str>) {
+    let title = title.trim();
+    match title
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once('('))
+    {
+        Some((name, volume)) => (Some(name.trim()), Some(volume.trim())),
+        None => (Some(title), None),
+    }
+}
+
+/// Font state of inline text
+#[derive(Debug, Clone, Copy, Default)]
+struct Font {
+    bold: bool,
+    italic: bool,
+    monospace: bool,
+}
+
+impl Font {
+    /// The escape that selects this font
+    fn escape(self) -> &'static str {
+        match (self.monospace, self.bold, self.italic) {
+            (true, true, _) => "\\f(CB",
+            (true, false, _) => "\\f(CR",
+            (false, true, true) => "\\f(BI",
+            (false, true, false) => "\\fB",
+            (false, false, true) => "\\fI",
+            (false, false, false) => "\\fR",
+        }
+    }
+}
+
+struct Writer {
+    out: String,
+    references: References,
+    footnotes: Footnotes,
+    /// Set once a table is written, so the page asks for `tbl`
+    tables: bool,
+}
+
+impl Writer {
+    fn blocks(&mut self, blocks: &[Block]) {
+        for block in blocks {
+            self.block(block);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        if let Some(title) = &block.metadata().title
+            && !matches!(block, Block::Section { .. })
+        {
+            let title = self.inlines(title);
+            self.out.push_str(".sp\n");
+            self.line(&format!("\\fB{title}\\fR"));
+            self.out.push_str(".br\n");
+        }
+        match block {
+            Block::Section {
+                level,
+                title,
+                content,
+                ..
+            } => {
+                let title = self.inlines(title).replace('"', "\\(dq");
+                if *level <= 2 {
+                    self.out
+                        .push_str(&format!(".SH \"{}\"\n", title.to_uppercase()));
+                } else {
+                    self.out.push_str(&format!(".SS \"{title}\"\n"));
+                }
+                self.blocks(content);
+            }
+            Block::Paragraph { content, .. } => {
+                let text = self.inlines(content);
+                self.out.push_str(".sp\n");
+                self.text(&text);
+            }
+            Block::UnorderedList { items, .. } => {
+                for item in items {
+                    self.item(item, "\\(bu", "\\h'-04'\\(bu\\h'+03'", 2.3);
+                }
+            }
+            Block::OrderedList { items, .. } => {
+                for (index, item) in items.iter().enumerate() {
+                    let number = format!("{:2}.", index + 1);
+                    self.item(
+                        item,
+                        &format!("\"{number}\""),
+                        &format!("\\h'-04'{number}\\h'+01'"),
+                        4.2,
+                    );
+                }
+            }
+            Block::DescriptionList { items, .. } => {
+                for item in items {
+                    let term = self.inlines(&item.term);
+                    self.out.push_str(".sp\n");
+                    self.text(&term);
+                    if !item.description.is_empty() {
+                        let description = self.inlines(&item.description);
+                        self.out.push_str(".RS 4\n");
+                        self.text(&description);
+                        self.out.push_str(".RE\n");
+                    }
+                }
+            }
+            Block::Listing { content, .. } => {
+                self.out.push_str(".sp\n.if n .RS 4\n.nf\n.fam C\n");
+                self.no_fill(&escape(content));
+                self.out.push_str(".fam\n.fi\n.if n .RE\n");
+            }
+            Block::Verse {
+                attribution,
+                citation,
+                content,
+                ..
+            } => {
+                let text = self.inlines(content);
+                self.out.push_str(".sp\n.nf\n");
+                self.no_fill(&text);
+                self.out.push_str(".fi\n");
+                self.attribution(attribution.as_deref(), citation.as_deref());
+            }
+            Block::Quote {
+                attribution,
+                citation,
+                content,
+                ..
+            } => {
+                self.out.push_str(".RS 3\n");
+                self.blocks(content);
+                self.out.push_str(".RE\n");
+                self.attribution(attribution.as_deref(), citation.as_deref());
+            }
+            Block::Admonition { kind, content, .. } => {
+                self.out.push_str(".sp\n");
+                self.line(&format!("\\fB{}\\fR", kind.caption()));
+                self.out.push_str(".br\n.RS 4\n");
+                self.blocks(content);
+                self.out.push_str(".RE\n");
+            }
+            Block::Example { content, .. } | Block::Sidebar { content, .. } => {
+                self.out.push_str(".RS 4\n");
+                self.blocks(content);
+                self.out.push_str(".RE\n");
+            }
+            Block::Open { content, .. } => self.blocks(content),
+            Block::Table {
+                columns,
+                header,
+                rows,
+                ..
+            } => self.table(columns.len(), header.as_ref(), rows),
+            Block::Image {
+                target, attributes, ..
+            } => {
+                let alt = attributes.get("alt").unwrap_or(target);
+                self.out.push_str(".sp\n");
+                self.line(&format!("[{}]", escape(alt)));
+            }
+            Block::Passthrough { .. } => {
+                if let Some(content) = block.passthrough_for("manpage") {
+                    self.out.push_str(content);
+                    self.out.push('\n');
+                }
+            }
+            Block::Comment { .. } | Block::AttributeEntry { .. } | Block::Unknown { .. } => {}
+        }
+    }
+
+    /// Write a list item: a `marker` in the margin (`nroff` draws it
+    /// with motions, `troff` with an indented paragraph of `width` ens),
+    /// then its text and attached blocks
+    fn item(&mut self, item: &ListItem, marker: &str, motions: &str, width: f32) {
+        let text = self.inlines(&item.content);
+        self.out.push_str(".sp\n.RS 4\n.ie n \\{\\\n");
+        self.out
+            .push_str(&format!("{motions}\\c\n.\\}}\n.el \\{{\\\n"));
+        self.out
+            .push_str(&format!(".  sp -1\n.  IP {marker} {width}\n.\\}}\n"));
+        self.text(&text);
+        self.blocks(&item.blocks);
+        self.out.push_str(".RE\n");
+    }
+
+    /// Write the attribution line under a quote or verse
+    fn attribution(&mut self, attribution: Option<&str>, citation: Option<&str>) {
+        let line = match (attribution, citation) {
+            (Some(attribution), Some(citation)) => format!("{attribution}, {citation}"),
+            (Some(text), None) | (None, Some(text)) => text.to_string(),
+            (None, None) => return,
+        };
+        self.out.push_str(".RS 5\n");
+        self.line(&format!("\\(em {}", escape(&line)));
+        self.out.push_str(".RE\n");
+    }
+
+    /// Write a `tbl` table with `columns` columns (or as many as the widest
+    /// row)
+    fn table(&mut self, columns: usize, header: Option<&TableRow>, rows: &[TableRow]) {
+        self.tables = true;
+        let width = header
+            .into_iter()
+            .chain(rows)
+            .map(|row| row.cells.len())
+            .max()
+            .unwrap_or(0)
+            .max(columns)
+            .max(1);
+        let format = |font: &str| vec![format!("lt{font}"); width].join(" ");
+        self.out.push_str(".TS\nallbox tab(:);\n");
+        if header.is_some() {
+            self.out.push_str(&format!("{}\n", format("B")));
+        }
+        self.out.push_str(&format!("{}.\n", format("")));
+        for row in header.into_iter().chain(rows) {
+            let mut cells: Vec<String> = row
+                .cells
+                .iter()
+                .map(|cell| self.inlines(&cell.content))
+                .collect();
+            cells.resize(width, String::new());
+            for (index, cell) in cells.iter().enumerate() {
+                if index > 0 {
+                    self.out.push(':');
+                }
+                self.out.push_str("T{\n");
+                self.text(cell);
+                self.out.push_str("T}");
+            }
+            self.out.push('\n');
+        }
+        self.out.push_str(".TE\n.sp\n");
+    }
+
+    /// Write filled text, one output line per source line
+    fn text(&mut self, text: &str) {
+        for line in text.lines().map(str::trim_start).filter(|l| !l.is_empty()) {
+            self.line(line);
+        }
+    }
+
+    /// Write text in no-fill mode, keeping empty lines and indentation
+    fn no_fill(&mut self, text: &str) {
+        for line in text.lines() {
+            self.line(line);
+        }
+    }
+
+    /// Write one text line, guarding a leading `.` or `'` that would make it
+    /// a request
+    fn line(&mut self, line: &str) {
+        if line.starts_with(['.', '\'']) {
+            self.out.push_str("\\&");
+        }
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    /// Escaped roff text for `content`
+    fn inlines(&mut self, content: &[Inline]) -> String {
+        let mut out = String::new();
+        self.push_inlines(content, Font::default(), &mut out);
+        out
+    }
+
+    fn push_inlines(&mut self, content: &[Inline], font: Font, out: &mut String) {
+        for inline in content {
+            let styled = match inline {
+                Inline::Bold(_) => Font { bold: true, ..font },
+                Inline::Italic(_) => Font {
+                    italic: true,
+                    ..font
+                },
+                Inline::Monospace(_) => Font {
+                    monospace: true,
+                    ..font
+                },
+                _ => font,
+            };
+            match inline {
+                Inline::Text(text) => out.push_str(&escape(text)),
+                Inline::Bold(nested) | Inline::Italic(nested) | Inline::Monospace(nested) => {
+                    out.push_str(styled.escape());
+                    self.push_inlines(nested, styled, out);
+                    out.push_str(font.escape());
+                }
+                Inline::Highlight(nested)
+                | Inline::Span {
+                    content: nested, ..
+                }
+                | Inline::Superscript(nested)
+                | Inline::Subscript(nested) => self.push_inlines(nested, font, out),
+                Inline::Link { target, text, .. } if text.is_empty() => {
+                    out.push_str(&escape(target))
+                }
+                Inline::Link { target, text, .. } => {
+                    self.push_inlines(text, font, out);
+                    out.push_str(&format!(" <{}>", escape(target)));
+                }
+                Inline::XRef { id, text } if text.is_empty() => {
+                    out.push_str(&escape(&self.references.label(id)))
+                }
+                Inline::XRef { text, .. } => self.push_inlines(text, font, out),
+                Inline::Anchor(_) => {}
+                Inline::Image { target, attributes } => {
+                    let alt = attributes.get("alt").unwrap_or(target);
+                    out.push_str(&format!("[{}]", escape(alt)));
+                }
+                Inline::Icon { name, attributes } => {
+                    let alt = attributes.get("alt").unwrap_or(name);
+                    out.push_str(&format!("[{}]", escape(alt)));
+                }
+                Inline::Footnote { id, text } => {
+                    let marker = match self.footnotes.register(id.as_deref(), text) {
+                        Some(footnote) => footnote.number.to_string(),
+                        None => id.clone().unwrap_or_default(),
+                    };
+                    out.push_str(&format!("[{}]", escape(&marker)));
+                }
+                Inline::BreakHint(hint) => out.push_str(match hint {
+                    crate::ast::BreakHint::SoftHyphen => "\\%",
+                    crate::ast::BreakHint::NoBreakSpace => "\\ ",
+                }),
+            }
+        }
+    }
+}
+
+/// Escape text so roff prints it literally
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\(rs"),
+            '-' => out.push_str("\\-"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+    use crate::processor::ProcessorRegistry;
+
+    /// The page body, after the `.TH` line and the fill settings
+    fn body(source: &str) -> String {
+        let roff = render_manpage(&parse_document(source).unwrap());
+        roff.split_once(".nh\n.ad l\n").unwrap().1.to_string()
+    }
+
+    #[test]
+    fn test_header() {
+        let doc = parse_document(
+            "= git-log(1)\n:revdate: 2024-05-01\n:mansource: Git 2.45\n:manmanual: Git Manual\n",
+        )
+        .unwrap();
+        let roff = render_manpage(&doc);
+        assert!(roff.starts_with(".\\\"     Title: git-log\n"), "{roff}");
+        assert!(roff.contains(
+            ".TH \"GIT\\-LOG\" \"1\" \"2024\\-05\\-01\" \"Git 2.45\" \"Git Manual\"\n.nh\n.ad l\n"
+        ));
+
+        let doc = parse_document("= tool\n:manvolnum: 8\n:manpurpose: do things\n").unwrap();
+        let roff = render_manpage(&doc);
+        assert!(roff.contains(".TH \"TOOL\" \"8\""));
+        assert!(
+            roff.ends_with(".SH \"NAME\"\ntool \\- do things\n"),
+            "{roff}"
+        );
+    }
+
+    #[test]
+    fn test_sections_and_text() {
+        assert_eq!(
+            body(
+                "= t(1)\n\n== Synopsis\n\n[verse]\n*t* [_-v_]\n  _file_...\n\n\
+                 === Notes\n\n.Leading dot\nStart\n'tis *_kept_* \\ `x`\n"
+            ),
+            ".SH \"SYNOPSIS\"\n.sp\n.nf\n\\fBt\\fR [\\fI\\-v\\fR]\n  \\fIfile\\fR...\n.fi\n\
+             .SS \"Notes\"\n.sp\n\\fBLeading dot\\fR\n.br\n.sp\nStart\n.sp\n\
+             \\&'tis \\fB\\f(BIkept\\fB\\fR \\(rs \\f(CRx\\fR\n"
+        );
+    }
+
+    #[test]
+    fn test_lists_and_listing() {
+        assert_eq!(
+            body("* one\n+\nmore\n\n//\n\n. first\n\n-v:: Verbose.\n\n----\n.x\n----\n"),
+            ".sp\n.RS 4\n.ie n \\{\\\n\\h'-04'\\(bu\\h'+03'\\c\n.\\}\n.el \\{\\\n\
+             .  sp -1\n.  IP \\(bu 2.3\n.\\}\none\n.sp\nmore\n.RE\n\
+             .sp\n.RS 4\n.ie n \\{\\\n\\h'-04' 1.\\h'+01'\\c\n.\\}\n.el \\{\\\n\
+             .  sp -1\n.  IP \" 1.\" 4.2\n.\\}\nfirst\n.RE\n\
+             .sp\n\\-v\n.RS 4\nVerbose.\n.RE\n\
+             .sp\n.if n .RS 4\n.nf\n.fam C\n\\&.x\n.fam\n.fi\n.if n .RE\n"
+        );
+    }
+
+    #[test]
+    fn test_table_and_footnotes() {
+        let roff = render_manpage(
+            &parse_document(
+                "= t(1)\nAda Lovelace <ada@example.com>\n\n\
+                 [options=header]\n|===\n|Exit |Meaning\n|0 |OK footnote:[Always.]\n|===\n",
+            )
+            .unwrap(),
+        );
+        assert!(roff.starts_with("'\\\" t\n"));
+        assert!(roff.ends_with(
+            ".TS\nallbox tab(:);\nltB ltB\nlt lt.\n\
+             T{\nExit\nT}:T{\nMeaning\nT}\nT{\n0\nT}:T{\nOK [1]\nT}\n.TE\n.sp\n\
+             .SH \"AUTHOR\"\n.sp\nAda Lovelace <ada@example.com>\n\
+             .SH \"NOTES\"\n.IP \" 1.\" 4\nAlways.\n"
+        ));
+    }
+
+    #[test]
+    fn test_processor() {
+        let mut registry = ProcessorRegistry::new();
+        registry.register(Box::new(ManpageProcessor));
+        let doc = parse_document("= t(1)\n\n== NAME\n\nt - test\n").unwrap();
+        let output = registry.convert(&doc, "manpage").unwrap();
+        assert_eq!(output.as_text(), Some(render_manpage(&doc).as_str()));
+    }
+}
@@ -0,0 +1,314 @@
+//! Text measurement for layout-aware renderers
+//!
+//! Renderers that lay out text themselves (PDF pages, terminal output) need
+//! to know how wide a run of text is to break lines, size table columns, and
+//! align content. Instead of hard-coding character-count approximations,
+//! layout code asks a [`TextMeasurer`], so embedders can supply real font
+//! metrics for the fonts they ship.
+//!
+//! Widths are in the backend's unit: points for a PDF page, character cells
+//! for a terminal. [`MonospaceMeasurer`] measures in cells and is the
+//! default for terminal output.
+//!
+//! # Examples
+//!
+//! Plug in custom metrics and wrap a paragraph with them:
+//!
+//! ```
+//! use doctora::render::measure::{TextMeasurer, TextStyle, wrap_lines};
+//!
+//! /// A font where bold text is 20% wider
+//! struct MyFont;
+//!
+//! impl TextMeasurer for MyFont {
+//!     fn width(&self, text: &str, style: TextStyle) -> f32 {
+//!         let scale = if style.bold { 1.2 } else { 1.0 };
+//!         text.chars().count() as f32 * 0.5 * style.size * scale
+//!     }
+//!
+//!     fn line_height(&self, style: TextStyle) -> f32 {
+//!         style.size * 1.2
+//!     }
+//! }
+//!
+//! let style = TextStyle::new(10.0);
+//! let lines = wrap_lines(&MyFont, "one two three four", style, 50.0);
+//! assert_eq!(lines, vec!["one two", "three four"]);
+//! ```
+//!
+//! Lines of a paragraph with an alignment role (`[.text-center]`) are placed
+//! with [`align_offset`].
+
+use crate::ast::TextAlign;
+
+/// Font style of a run of text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    /// Font size, in the backend's unit
+    pub size: f32,
+    /// Bold weight
+    pub bold: bool,
+    /// Italic slant
+    pub italic: bool,
+    /// Monospace font (code)
+    pub monospace: bool,
+}
+
+impl TextStyle {
+    /// Creates a regular style of the given size
+    pub fn new(size: f32) -> Self {
+        Self {
+            size,
+            bold: false,
+            italic: false,
+            monospace: false,
+        }
+    }
+
+    /// Sets bold weight
+    pub fn bold(mut self, bold: bool) -> Self {
+        self.bold = bold;
+        self
+    }
+
+    /// Sets italic slant
+    pub fn italic(mut self, italic: bool) -> Self {
+        self.italic = italic;
+        self
+    }
+
+    /// Sets the monospace font
+    pub fn monospace(mut self, monospace: bool) -> Self {
+        self.monospace = monospace;
+        self
+    }
+}
+
+/// Font metrics used by layout code
+pub trait TextMeasurer {
+    /// Advance width of `text` set in `style`
+    ///
+    /// `text` contains no line breaks.
+    fn width(&self, text: &str, style: TextStyle) -> f32;
+
+    /// Distance between the baselines of consecutive lines in `style`
+    fn line_height(&self, style: TextStyle) -> f32;
+}
+
+impl<M: TextMeasurer + ?Sized> TextMeasurer for &M {
+    fn width(&self, text: &str, style: TextStyle) -> f32 {
+        (**self).width(text, style)
+    }
+
+    fn line_height(&self, style: TextStyle) -> f32 {
+        (**self).line_height(style)
+    }
+}
+
+/// Measures text in terminal character cells
+///
+/// Every character takes one cell regardless of style, except wide East
+/// Asian characters and emoji (two cells) and combining marks and zero-width
+/// characters (none). The font size is ignored: a line is one cell high.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MonospaceMeasurer;
+
+impl TextMeasurer for MonospaceMeasurer {
+    fn width(&self, text: &str, _style: TextStyle) -> f32 {
+        text.chars().map(cell_width).sum::<usize>() as f32
+    }
+
+    fn line_height(&self, _style: TextStyle) -> f32 {
+        1.0
+    }
+}
+
+/// Number of terminal cells `c` occupies
+fn cell_width(c: char) -> usize {
+    match c {
+        '\u{0300}'..='\u{036F}' | '\u{200B}'..='\u{200F}' | '\u{FE00}'..='\u{FE0F}' => 0,
+        '\u{1100}'..='\u{115F}'
+        | '\u{2E80}'..='\u{303E}'
+        | '\u{3041}'..='\u{33FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{A000}'..='\u{A4CF}'
+        | '\u{AC00}'..='\u{D7A3}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FE30}'..='\u{FE4F}'
+        | '\u{FF00}'..='\u{FF60}'
+        | '\u{FFE0}'..='\u{FFE6}'
+        | '\u{1F300}'..='\u{1F64F}'
+        | '\u{1F900}'..='\u{1F9FF}'
+        | '\u{20000}'..='\u{3FFFD}' => 2,
+        _ => 1,
+    }
+}
+
+/// Break `text` into lines no wider than `max_width`, at spaces
+///
+/// Words are kept whole, except at soft hyphens (U+00AD): a word that
+/// doesn't fit breaks at the last one that leaves room for a hyphen, and soft
+/// hyphens are dropped elsewhere. A word wider than `max_width` that can't be
+/// broken gets a line of its own. Non-breaking spaces (U+00A0) join words;
+/// other runs of whitespace, including line feeds, collapse to single
+/// spaces. [`Inline::BreakHint`](crate::ast::Inline::BreakHint) nodes carry
+/// these characters in the AST (see [`crate::hints`]).
+pub fn wrap_lines(
+    measurer: &impl TextMeasurer,
+    text: &str,
+    style: TextStyle,
+    max_width: f32,
+) -> Vec<String> {
+    let space = measurer.width(" ", style);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_width = 0.0;
+
+    let words = text
+        .split(|c: char| c.is_whitespace() && c != NO_BREAK_SPACE)
+        .filter(|word| !word.is_empty());
+    for mut word in words {
+        loop {
+            let lead = if line.is_empty() { 0.0 } else { space };
+            let whole = word.replace(SOFT_HYPHEN, "");
+            let width = measurer.width(&whole, style);
+            let room = max_width - line_width - lead;
+            let (piece, rest) = if width <= room {
+                (whole, None)
+            } else if let Some((head, tail)) = hyphenate(measurer, word, style, room) {
+                (head, Some(tail))
+            } else if line.is_empty() {
+                (whole, None)
+            } else {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0.0;
+                continue;
+            };
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line_width += lead + measurer.width(&piece, style);
+            line.push_str(&piece);
+            match rest {
+                Some(tail) => {
+                    lines.push(std::mem::take(&mut line));
+                    line_width = 0.0;
+                    word = tail;
+                }
+                None => break,
+            }
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Horizontal offset of `line` within a column `max_width` wide, for
+/// [`TextAlign`]
+///
+/// Justified lines start at the left edge; the backend spreads the leftover
+/// space over the line's spaces. A line wider than the column is not moved.
+pub fn align_offset(
+    measurer: &impl TextMeasurer,
+    line: &str,
+    style: TextStyle,
+    max_width: f32,
+    align: TextAlign,
+) -> f32 {
+    let room = (max_width - measurer.width(line, style)).max(0.0);
+    match align {
+        TextAlign::Left | TextAlign::Justify => 0.0,
+        TextAlign::Center => room / 2.0,
+        TextAlign::Right => room,
+    }
+}
+
+const SOFT_HYPHEN: char = '\u{00AD}';
+const NO_BREAK_SPACE: char = '\u{00A0}';
+
+/// Break `word` at its last soft hyphen whose head, hyphen included, fits in
+/// `room`, returning the hyphenated head and the rest
+fn hyphenate<'a>(
+    measurer: &impl TextMeasurer,
+    word: &'a str,
+    style: TextStyle,
+    room: f32,
+) -> Option<(String, &'a str)> {
+    word.rmatch_indices(SOFT_HYPHEN).find_map(|(at, hyphen)| {
+        let head = format!("{}-", word[..at].replace(SOFT_HYPHEN, ""));
+        (at > 0 && measurer.width(&head, style) <= room).then(|| (head, &word[at + hyphen.len()..]))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monospace_cells() {
+        let style = TextStyle::new(12.0);
+        assert_eq!(MonospaceMeasurer.width("hello", style), 5.0);
+        assert_eq!(MonospaceMeasurer.width("日本語", style), 6.0);
+        assert_eq!(MonospaceMeasurer.width("e\u{0301}", style), 1.0);
+        assert_eq!(MonospaceMeasurer.width("bold", style.bold(true)), 4.0);
+        assert_eq!(MonospaceMeasurer.line_height(style), 1.0);
+    }
+
+    #[test]
+    fn test_wrap_lines() {
+        let style = TextStyle::new(1.0);
+        let wrap = |text, width| wrap_lines(&MonospaceMeasurer, text, style, width);
+
+        assert_eq!(
+            wrap("the quick brown fox", 10.0),
+            ["the quick", "brown fox"]
+        );
+        assert_eq!(wrap("a  b\nc", 80.0), ["a b c"]);
+        assert_eq!(
+            wrap("tiny enormousword x", 5.0),
+            ["tiny", "enormousword", "x"]
+        );
+        assert_eq!(wrap("日本 語", 4.0), ["日本", "語"]);
+        assert!(wrap("", 10.0).is_empty());
+    }
+
+    #[test]
+    fn test_wrap_lines_break_hints() {
+        let style = TextStyle::new(1.0);
+        let wrap = |text: &str, width| wrap_lines(&MonospaceMeasurer, text, style, width);
+        let shy = |text: &str| text.replace('^', "\u{00AD}");
+
+        assert_eq!(
+            wrap(&shy("a hy^phen^ation"), 8.0),
+            ["a hy-", "phen-", "ation"]
+        );
+        assert_eq!(wrap(&shy("a hy^phen^ation"), 20.0), ["a hyphenation"]);
+        assert_eq!(wrap(&shy("xy^z"), 2.0), ["xyz"]);
+        assert_eq!(
+            wrap("Mr.\u{00A0}Smith is here", 10.0),
+            ["Mr.\u{00A0}Smith", "is here"]
+        );
+    }
+
+    #[test]
+    fn test_align_offset() {
+        let style = TextStyle::new(1.0);
+        let offset = |line, align| align_offset(&MonospaceMeasurer, line, style, 10.0, align);
+
+        assert_eq!(offset("abcd", TextAlign::Left), 0.0);
+        assert_eq!(offset("abcd", TextAlign::Center), 3.0);
+        assert_eq!(offset("abcd", TextAlign::Right), 6.0);
+        assert_eq!(offset("abcd", TextAlign::Justify), 0.0);
+        assert_eq!(offset("far too long a line", TextAlign::Right), 0.0);
+    }
+
+    #[test]
+    fn test_measurer_by_reference() {
+        let measurer: &dyn TextMeasurer = &MonospaceMeasurer;
+        let lines = wrap_lines(&measurer, "ab cd", TextStyle::new(1.0), 2.0);
+        assert_eq!(lines, ["ab", "cd"]);
+    }
+}
@@ -0,0 +1,301 @@
+//! Navigation sidebar partials
+//!
+//! Site shells lay out their own pages and only need the navigation as an
+//! HTML fragment to include in a sidebar. The partials here are nested
+//! `<ul>` lists, unstyled apart from their classes:
+//!
+//! - [`render_outline`] lists the sections of one document, for single-file
+//!   builds. Sections with an anchor link to it (`<a href="#id">`); the
+//!   others are plain `<span>`s, since the HTML renderer gives them no `id`.
+//! - [`Sidebar`] lists the pages of a multi-file build ([`Navigation`]),
+//!   nested under their index pages. The page being rendered is marked
+//!   `class="active"` with `aria-current="page"`, the pages above it
+//!   `class="open"` so the shell can keep their subtrees expanded, and the
+//!   page's own outline can be nested under it.
+//!
+//! - [`render_caption_list`] lists the figures, tables, or listings of one
+//!   document ([`Outline`]), linking those with an anchor like the outline.
+//!
+//! Titles are plain text: formatting in section titles is dropped.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::batch::{FailurePolicy, parse_batch};
+//! use doctora::render::nav::{Sidebar, render_outline};
+//! use doctora::site::{SiteOptions, navigation};
+//!
+//! let doc = doctora::parse_document("[#setup]\n== Setup\n\nSteps.\n").unwrap();
+//! assert_eq!(
+//!     render_outline(&doc),
+//!     "<ul class=\"nav\">\n<li><a href=\"#setup\">Setup</a></li>\n</ul>\n"
+//! );
+//!
+//! let inputs = [
+//!     ("index.adoc", "= Home\n\nWelcome.\n"),
+//!     ("install.adoc", "= Installing\n\nSteps.\n"),
+//! ];
+//! let result = parse_batch(inputs, FailurePolicy::Abort).unwrap();
+//! let nav = navigation(&result, &SiteOptions::new("/docs"));
+//! let html = Sidebar::new(&nav).active(1).render();
+//! assert!(html.contains(
+//!     "<li class=\"active\"><a href=\"/docs/install.html\" aria-current=\"page\">Installing</a></li>"
+//! ));
+//! ```
+
+use crate::ast::{Block, Document, plain_text};
+use crate::outline::{CaptionKind, Outline};
+use crate::render::html::escape;
+use crate::site::Navigation;
+
+/// Render the sections of `doc` as a nested list
+///
+/// Returns an empty string for a document without sections.
+pub fn render_outline(doc: &Document) -> String {
+    let mut out = String::new();
+    push_sections(&doc.blocks, "nav", &mut out);
+    out
+}
+
+/// Render the figures, tables, or listings (by `kind`) of `outline` as a
+/// list with class `list-of-figures` (`-tables`, `-listings`)
+///
+/// Each entry reads `Figure 1. Caption`. Returns an empty string if there
+/// are none.
+pub fn render_caption_list(outline: &Outline, kind: CaptionKind) -> String {
+    let entries = outline.entries(kind);
+    if entries.is_empty() {
+        return String::new();
+    }
+    let mut out = format!("<ul class=\"list-of-{}\">\n", kind.list_name());
+    for entry in entries {
+        let text = escape(&format!(
+            "{}. {}",
+            entry.label(),
+            plain_text(&entry.caption)
+        ));
+        match &entry.anchor {
+            Some(id) => out.push_str(&format!(
+                "<li><a href=\"#{}\">{text}</a></li>\n",
+                escape(id)
+            )),
+            None => out.push_str(&format!("<li><span>{text}</span></li>\n")),
+        }
+    }
+    out.push_str("</ul>\n");
+    out
+}
+
+/// Sidebar partial for one page of a multi-file build
+#[derive(Debug, Clone, Copy)]
+pub struct Sidebar<'a> {
+    nav: &'a Navigation,
+    /// Index of the page being rendered
+    active: Option<usize>,
+    /// Sections of the active page
+    outline: Option<&'a Document>,
+}
+
+impl<'a> Sidebar<'a> {
+    /// Creates a sidebar listing the pages of `nav`, none of them active
+    pub fn new(nav: &'a Navigation) -> Self {
+        Self {
+            nav,
+            active: None,
+            outline: None,
+        }
+    }
+
+    /// Marks the page at `index` in [`Navigation::pages`] as the current one
+    pub fn active(mut self, index: usize) -> Self {
+        self.active = Some(index);
+        self
+    }
+
+    /// Nests the sections of `doc`, the active page's document, under the
+    /// active page
+    pub fn outline(mut self, doc: &'a Document) -> Self {
+        self.outline = Some(doc);
+        self
+    }
+
+    /// Render the sidebar as a nested list
+    pub fn render(&self) -> String {
+        let mut out = String::from("<ul class=\"nav\">\n");
+        for index in self.nav.roots() {
+            self.push_page(index, &mut out);
+        }
+        out.push_str("</ul>\n");
+        out
+    }
+
+    fn push_page(&self, index: usize, out: &mut String) {
+        let page = &self.nav.pages[index];
+        let active = self.active == Some(index);
+        let class = if active {
+            " class=\"active\""
+        } else if self.is_ancestor(index) {
+            " class=\"open\""
+        } else {
+            ""
+        };
+        let current = if active { " aria-current=\"page\"" } else { "" };
+        out.push_str(&format!(
+            "<li{class}><a href=\"{}\"{current}>{}</a>",
+            escape(&page.url),
+            escape(&page.title)
+        ));
+        let outline = self.outline.filter(|_| active);
+        if page.children.is_empty() && outline.is_none() {
+            out.push_str("</li>\n");
+            return;
+        }
+        out.push('\n');
+        if let Some(doc) = outline {
+            push_sections(&doc.blocks, "outline", out);
+        }
+        if !page.children.is_empty() {
+            out.push_str("<ul>\n");
+            for &child in &page.children {
+                self.push_page(child, out);
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</li>\n");
+    }
+
+    /// Returns true if the active page is below the page at `index`
+    fn is_ancestor(&self, index: usize) -> bool {
+        let mut page = self.active.and_then(|active| self.nav.pages[active].parent);
+        while let Some(parent) = page {
+            if parent == index {
+                return true;
+            }
+            page = self.nav.pages[parent].parent;
+        }
+        false
+    }
+}
+
+/// Push the sections in `blocks` as a list with class `class`, if there are
+/// any; nested lists have no class
+fn push_sections(blocks: &[Block], class: &str, out: &mut String) {
+    let sections: Vec<_> = blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Section {
+                title,
+                content,
+                anchor,
+                ..
+            } => Some((title, content, anchor)),
+            _ => None,
+        })
+        .collect();
+    if sections.is_empty() {
+        return;
+    }
+    if class.is_empty() {
+        out.push_str("<ul>\n");
+    } else {
+        out.push_str(&format!("<ul class=\"{class}\">\n"));
+    }
+    for (title, content, anchor) in sections {
+        let title = escape(&plain_text(title));
+        match anchor {
+            Some(anchor) => out.push_str(&format!(
+                "<li><a href=\"#{}\">{title}</a>",
+                escape(&anchor.id)
+            )),
+            None => out.push_str(&format!("<li><span>{title}</span>")),
+        }
+        let mut nested = String::new();
+        push_sections(content, "", &mut nested);
+        if !nested.is_empty() {
+            out.push('\n');
+            out.push_str(&nested);
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ul>\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::{FailurePolicy, parse_batch};
+    use crate::parse_document;
+    use crate::site::{SiteOptions, navigation};
+
+    #[test]
+    fn test_caption_list() {
+        let doc =
+            parse_document(".Setup <1>\n[#setup]\n----\nrun\n----\n\n.Check\n----\ntest\n----\n")
+                .unwrap();
+        let outline = Outline::new(&doc);
+        assert_eq!(
+            render_caption_list(&outline, CaptionKind::Listing),
+            "<ul class=\"list-of-listings\">\n\
+             <li><a href=\"#setup\">Listing 1. Setup &lt;1&gt;</a></li>\n\
+             <li><span>Listing 2. Check</span></li>\n</ul>\n"
+        );
+        assert_eq!(render_caption_list(&outline, CaptionKind::Figure), "");
+    }
+
+    #[test]
+    fn test_outline() {
+        let doc = parse_document(
+            "Intro.\n\n[#a]\n== A & B\n\n=== Nested *bold*\n\nText.\n\n[#c]\n==== C\n",
+        )
+        .unwrap();
+        assert_eq!(
+            render_outline(&doc),
+            "<ul class=\"nav\">\n\
+             <li><a href=\"#a\">A &amp; B</a>\n\
+             <ul>\n\
+             <li><span>Nested bold</span>\n\
+             <ul>\n\
+             <li><a href=\"#c\">C</a></li>\n\
+             </ul>\n\
+             </li>\n\
+             </ul>\n\
+             </li>\n\
+             </ul>\n"
+        );
+        assert_eq!(render_outline(&parse_document("Text.").unwrap()), "");
+    }
+
+    #[test]
+    fn test_sidebar_marks_active_page() {
+        let inputs = [
+            ("index.adoc", "= Home\n\nA.\n"),
+            ("guide/index.adoc", "= Guide\n\nB.\n"),
+            ("guide/install.adoc", "= Install\n\n[#linux]\n== Linux\n"),
+            ("about.adoc", "= About\n\nC.\n"),
+        ];
+        let result = parse_batch(inputs, FailurePolicy::Abort).unwrap();
+        let nav = navigation(&result, &SiteOptions::new("/"));
+        let doc = &result.documents[2].document;
+        assert_eq!(
+            Sidebar::new(&nav).active(2).outline(doc).render(),
+            "<ul class=\"nav\">\n\
+             <li class=\"open\"><a href=\"/index.html\">Home</a>\n\
+             <ul>\n\
+             <li class=\"open\"><a href=\"/guide/index.html\">Guide</a>\n\
+             <ul>\n\
+             <li class=\"active\"><a href=\"/guide/install.html\" aria-current=\"page\">Install</a>\n\
+             <ul class=\"outline\">\n\
+             <li><a href=\"#linux\">Linux</a></li>\n\
+             </ul>\n\
+             </li>\n\
+             </ul>\n\
+             </li>\n\
+             <li><a href=\"/about.html\">About</a></li>\n\
+             </ul>\n\
+             </li>\n\
+             </ul>\n"
+        );
+        // Without an active page nothing is marked
+        assert!(!Sidebar::new(&nav).render().contains("class=\"active\""));
+    }
+}
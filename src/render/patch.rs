@@ -0,0 +1,487 @@
+//! Incremental HTML updates for live preview
+//!
+//! Reloading the whole page after every keystroke makes live preview of large
+//! documents sluggish. Instead, a preview server renders the page once with
+//! node IDs ([`HtmlOptions::node_ids`](super::html::HtmlOptions::node_ids)),
+//! then for each edit diffs the previous and current AST with [`diff`] and
+//! sends the resulting [`PatchOp`]s to the browser as a script
+//! ([`to_script`]).
+//!
+//! The document title heading is compared on its own. The diff then works on
+//! the blocks of one container (the document or a section) at a time. Unchanged leading and trailing blocks are kept. Blocks changed
+//! in place are replaced one by one, and sections that keep their level and
+//! title are diffed recursively, so typing in one paragraph of a huge
+//! document produces a single replacement. When blocks are inserted or
+//! removed, the node IDs of the following siblings shift, so those siblings
+//! are re-rendered as well.
+//!
+//! Document attributes are not diffed; a preview that shows them (e.g. in a
+//! standalone page title) must reload when they change. Likewise, unchanged
+//! blocks keep the text of cross references that show their target's title,
+//! so renaming a section leaves such references stale until a reload.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::parse_document;
+//! use doctora::render::patch::{PatchOp, diff};
+//!
+//! let old = parse_document("= Guide\n\nFirst.\n\nSecond.\n").unwrap();
+//! let new = parse_document("= Guide\n\nFirst.\n\nSecond, edited.\n").unwrap();
+//!
+//! assert_eq!(
+//!     diff(&old, &new),
+//!     vec![PatchOp::Replace {
+//!         id: "1".to_string(),
+//!         html: "<p data-node=\"1\">Second, edited.</p>\n".to_string(),
+//!     }]
+//! );
+//! ```
+
+use super::html::{render_node, render_title};
+use crate::ast::{Block, Document};
+use crate::attributes::Attributes;
+use crate::xref::References;
+use std::fmt::Write as _;
+
+/// Where [`PatchOp::Insert`] places new elements
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Position {
+    /// At the start of the root element
+    Start,
+    /// After the element with this node ID
+    After(String),
+    /// After the title heading of the `<section>` with this node ID
+    AfterTitle(String),
+}
+
+/// A single DOM update
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchOp {
+    /// Replace the element with node ID `id` by `html`
+    Replace { id: String, html: String },
+    /// Remove the element with node ID `id`
+    Remove { id: String },
+    /// Insert `html` at `position`
+    Insert { position: Position, html: String },
+}
+
+/// Compute the DOM updates that turn the rendering of `old` into that of `new`
+///
+/// Both renderings use [`HtmlOptions::node_ids`](super::html::HtmlOptions::node_ids).
+/// Apply the operations in order; an empty result means nothing changed.
+pub fn diff(old: &Document, new: &Document) -> Vec<PatchOp> {
+    let mut ops = Vec::new();
+    let new_title = document_title(new);
+    match (document_title(old), new_title) {
+        (Some(old_title), Some(new_title)) if old_title != new_title => {
+            ops.push(PatchOp::Replace {
+                id: "title".to_string(),
+                html: render_title(new_title, true),
+            });
+        }
+        (Some(_), None) => ops.push(PatchOp::Remove {
+            id: "title".to_string(),
+        }),
+        (None, Some(new_title)) => ops.push(PatchOp::Insert {
+            position: Position::Start,
+            html: render_title(new_title, true),
+        }),
+        _ => {}
+    }
+    let parent = Parent::Document {
+        titled: new_title.is_some(),
+    };
+    let references = References::new(new);
+    diff_children(
+        parent,
+        &old.blocks,
+        &new.blocks,
+        &references,
+        &new.attributes,
+        &mut ops,
+    );
+    ops
+}
+
+/// Render `ops` as a JavaScript function expression
+///
+/// Call the function with the element containing the rendered fragment, e.g.
+/// `eval(script)(document.body)`.
+///
+/// # Examples
+///
+/// ```
+/// use doctora::render::patch::{PatchOp, to_script};
+///
+/// let script = to_script(&[PatchOp::Remove { id: "2".to_string() }]);
+/// assert!(script.contains("node(\"2\").remove();"));
+/// ```
+pub fn to_script(ops: &[PatchOp]) -> String {
+    let mut script = String::from(
+        "(function (root) {\n  const node = (id) => root.querySelector('[data-node=\"' + id + '\"]');\n",
+    );
+    for op in ops {
+        match op {
+            PatchOp::Replace { id, html } => {
+                writeln!(
+                    script,
+                    "  node({}).outerHTML = {};",
+                    js_string(id),
+                    js_string(html)
+                )
+            }
+            PatchOp::Remove { id } => writeln!(script, "  node({}).remove();", js_string(id)),
+            PatchOp::Insert { position, html } => {
+                let target = match position {
+                    Position::Start => {
+                        writeln!(
+                            script,
+                            "  root.insertAdjacentHTML(\"afterbegin\", {});",
+                            js_string(html)
+                        )
+                        .unwrap();
+                        continue;
+                    }
+                    Position::After(id) => format!("node({})", js_string(id)),
+                    Position::AfterTitle(id) => {
+                        format!("node({}).firstElementChild", js_string(id))
+                    }
+                };
+                writeln!(
+                    script,
+                    "  {target}.insertAdjacentHTML(\"afterend\", {});",
+                    js_string(html)
+                )
+            }
+        }
+        .unwrap();
+    }
+    script.push_str("})\n");
+    script
+}
+
+/// A container of blocks
+#[derive(Debug, Clone, Copy)]
+enum Parent<'a> {
+    /// The document, and whether it has a title heading
+    Document { titled: bool },
+    /// A section, by node ID and level
+    Section { id: &'a str, level: u8 },
+}
+
+/// Title in the header of `doc`
+fn document_title(doc: &Document) -> Option<&str> {
+    doc.header.as_ref()?.title.as_deref()
+}
+
+/// Diff the blocks of one container
+fn diff_children(
+    parent: Parent<'_>,
+    old: &[Block],
+    new: &[Block],
+    references: &References,
+    attributes: &Attributes,
+    ops: &mut Vec<PatchOp>,
+) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_changed = &old[prefix..old.len() - suffix];
+    let new_changed = &new[prefix..new.len() - suffix];
+
+    if old_changed.len() == new_changed.len()
+        && old_changed
+            .iter()
+            .zip(new_changed)
+            .all(|(a, b)| is_level_one(a) == is_level_one(b))
+    {
+        for (offset, (a, b)) in old_changed.iter().zip(new_changed).enumerate() {
+            let id = child_id(parent, prefix + offset);
+            diff_node(&id, a, b, references, attributes, ops);
+        }
+        return;
+    }
+
+    // Blocks were inserted or removed: every following sibling gets a new ID
+    for (index, block) in old.iter().enumerate().skip(prefix) {
+        for id in dom_nodes(child_id(parent, index), block) {
+            ops.push(PatchOp::Remove { id });
+        }
+    }
+    let html: String = new
+        .iter()
+        .enumerate()
+        .skip(prefix)
+        .map(|(index, block)| render_node(block, &child_id(parent, index), references, attributes))
+        .collect();
+    if html.is_empty() {
+        return;
+    }
+    let position = match (prefix.checked_sub(1), parent) {
+        (Some(last), _) => {
+            let nodes = dom_nodes(child_id(parent, last), &old[last]);
+            Position::After(nodes.last().cloned().unwrap_or_default())
+        }
+        (None, Parent::Document { titled: false }) => Position::Start,
+        (None, Parent::Document { titled: true }) => Position::After("title".to_string()),
+        (None, Parent::Section { id, level: 1 }) => Position::After(id.to_string()),
+        (None, Parent::Section { id, .. }) => Position::AfterTitle(id.to_string()),
+    };
+    ops.push(PatchOp::Insert { position, html });
+}
+
+/// Diff two blocks at the same position
+fn diff_node(
+    id: &str,
+    old: &Block,
+    new: &Block,
+    references: &References,
+    attributes: &Attributes,
+    ops: &mut Vec<PatchOp>,
+) {
+    match (old, new) {
+        _ if old == new => {}
+        (
+            Block::Section {
+                level: old_level,
+                title: old_title,
+                content: old_content,
+                anchor: old_anchor,
+                metadata: old_metadata,
+            },
+            Block::Section {
+                level,
+                title,
+                content,
+                anchor,
+                metadata,
+            },
+        ) if old_level == level
+            && old_anchor == anchor
+            && old_metadata == metadata
+            && (old_title == title || *level == 1) =>
+        {
+            if old_title != title {
+                // A level 1 section is an `<h1>` followed by its content, so
+                // only the heading is replaced
+                let heading = Block::Section {
+                    level: 1,
+                    title: title.clone(),
+                    content: Vec::new(),
+                    anchor: anchor.clone(),
+                    metadata: metadata.clone(),
+                };
+                ops.push(PatchOp::Replace {
+                    id: id.to_string(),
+                    html: render_node(&heading, id, references, attributes),
+                });
+            }
+            diff_children(
+                Parent::Section { id, level: *level },
+                old_content,
+                content,
+                references,
+                attributes,
+                ops,
+            );
+        }
+        _ => ops.push(PatchOp::Replace {
+            id: id.to_string(),
+            html: render_node(new, id, references, attributes),
+        }),
+    }
+}
+
+/// True if `block` renders as a heading followed by sibling elements
+fn is_level_one(block: &Block) -> bool {
+    matches!(block, Block::Section { level: 1, .. })
+}
+
+/// Node ID of the child at `index` of `parent`
+fn child_id(parent: Parent<'_>, index: usize) -> String {
+    match parent {
+        Parent::Section { id, .. } => format!("{id}.{index}"),
+        Parent::Document { .. } => index.to_string(),
+    }
+}
+
+/// IDs of the top-level elements that `block` renders as, in document order
+fn dom_nodes(id: String, block: &Block) -> Vec<String> {
+    match block {
+        Block::Section {
+            level: 1, content, ..
+        } => {
+            let mut nodes = Vec::new();
+            for (index, child) in content.iter().enumerate() {
+                nodes.extend(dom_nodes(format!("{id}.{index}"), child));
+            }
+            nodes.insert(0, id);
+            nodes
+        }
+        _ => vec![id],
+    }
+}
+
+/// Quote `text` as a JavaScript string literal that is safe inside `<script>`
+fn js_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '<' => out.push_str("\\u003c"),
+            '\u{2028}' => out.push_str("\\u2028"),
+            '\u{2029}' => out.push_str("\\u2029"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    fn patch(old: &str, new: &str) -> Vec<PatchOp> {
+        diff(&parse_document(old).unwrap(), &parse_document(new).unwrap())
+    }
+
+    fn remove(id: &str) -> PatchOp {
+        PatchOp::Remove { id: id.to_string() }
+    }
+
+    #[test]
+    fn test_unchanged_document() {
+        assert!(patch("= T\n\nA.\n\n== S\n\nB.\n", "= T\n\nA.\n\n== S\n\nB.\n").is_empty());
+    }
+
+    #[test]
+    fn test_edit_in_nested_section() {
+        let ops = patch(
+            "= T\n\nA.\n\n== S\n\nB.\n\nC.\n",
+            "= T\n\nA.\n\n== S\n\nB!\n\nC.\n",
+        );
+        assert_eq!(
+            ops,
+            vec![PatchOp::Replace {
+                id: "1.0".to_string(),
+                html: "<p data-node=\"1.0\">B!</p>\n".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_insert_shifts_following_siblings() {
+        let ops = patch("== S\n\nA.\n\nB.\n", "== S\n\nNew.\n\nA.\n\nB.\n");
+        assert_eq!(
+            ops,
+            vec![
+                remove("0.0"),
+                remove("0.1"),
+                PatchOp::Insert {
+                    position: Position::AfterTitle("0".to_string()),
+                    html: "<p data-node=\"0.0\">New.</p>\n<p data-node=\"0.1\">A.</p>\n\
+                           <p data-node=\"0.2\">B.</p>\n"
+                        .to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remove_last_block() {
+        let ops = patch("= T\n\nA.\n\nB.\n", "= T\n\nA.\n");
+        assert_eq!(ops, vec![remove("1")]);
+    }
+
+    #[test]
+    fn test_document_title_change() {
+        let ops = patch("= Old\n\nA.\n", "= New\n\nA.\n");
+        assert_eq!(
+            ops,
+            vec![PatchOp::Replace {
+                id: "title".to_string(),
+                html: "<h1 data-node=\"title\">New</h1>\n".to_string(),
+            }]
+        );
+
+        let ops = patch("= T\n\nA.\n", "A.\n");
+        assert_eq!(ops, vec![remove("title")]);
+    }
+
+    #[test]
+    fn test_insert_after_document_title() {
+        let ops = patch("= T\n\nA.\n", "= T\n\nNew.\n\nA.\n");
+        assert_eq!(
+            ops,
+            vec![
+                remove("0"),
+                PatchOp::Insert {
+                    position: Position::After("title".to_string()),
+                    html: "<p data-node=\"0\">New.</p>\n<p data-node=\"1\">A.</p>\n".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_level_one_title_change_keeps_content() {
+        let ops = patch("P.\n\n= Old\n\nA.\n", "P.\n\n= New\n\nA.\n");
+        assert_eq!(
+            ops,
+            vec![PatchOp::Replace {
+                id: "1".to_string(),
+                html: "<h1 data-node=\"1\">New</h1>\n".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_level_one_section_replaced_with_its_content() {
+        // The paragraph after the part title is a sibling element of the `<h1>`
+        let ops = patch("P.\n\n= T\n\nA.\n", "P.\n\nIntro.\n");
+        assert_eq!(
+            ops,
+            vec![
+                remove("1"),
+                remove("1.0"),
+                PatchOp::Insert {
+                    position: Position::After("0".to_string()),
+                    html: "<p data-node=\"1\">Intro.</p>\n".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_script() {
+        let script = to_script(&[
+            PatchOp::Replace {
+                id: "0".to_string(),
+                html: "<p data-node=\"0\">a</p>\n".to_string(),
+            },
+            PatchOp::Insert {
+                position: Position::AfterTitle("1".to_string()),
+                html: "</script>".to_string(),
+            },
+        ]);
+        assert!(script.starts_with("(function (root) {\n"));
+        assert!(script.contains(
+            "  node(\"0\").outerHTML = \"\\u003cp data-node=\\\"0\\\">a\\u003c/p>\\n\";\n"
+        ));
+        assert!(script.contains(
+            "  node(\"1\").firstElementChild.insertAdjacentHTML(\"afterend\", \"\\u003c/script>\");\n"
+        ));
+        assert!(script.ends_with("})\n"));
+    }
+}
@@ -0,0 +1,1235 @@
+//! PDF renderer
+//!
+//! Lays out a document on pages and writes it as a PDF file, without any
+//! external typesetting tool. Text is set in the PDF standard fonts
+//! (Helvetica for prose, Courier for code), which every viewer provides, so
+//! nothing is embedded and the output stays small. Layout is basic:
+//!
+//! | AST node          | Layout                                               |
+//! |-------------------|------------------------------------------------------|
+//! | Document title    | Large bold heading, with the authors below           |
+//! | Section           | Bold heading sized by level, kept with the next line |
+//! | Paragraph         | Wrapped text, aligned by a `text-*` role             |
+//! | Lists             | Bullets or numbers, items indented                   |
+//! | Description list  | Bold term, indented description                      |
+//! | Listing block     | Courier on a gray background, long lines broken      |
+//! | Table             | Ruled grid with column widths from `cols`            |
+//! | Quote / verse     | Indented, with the attribution below                 |
+//! | Example / sidebar | Indented                                             |
+//! | Admonition        | Its caption in bold, then its content indented       |
+//! | Block image       | Its alt text in brackets (images are not embedded)   |
+//! | Block title       | Bold line above the block                            |
+//! | Bold / italic     | Bold and oblique fonts, combined when nested         |
+//! | Monospace         | Courier                                              |
+//! | Link              | Blue text with a clickable link area                 |
+//! | Cross reference   | Its text, or the target's title                      |
+//! | Footnote          | A `[N]` marker, with the notes after the body        |
+//!
+//! Passthrough and comment blocks are skipped. Highlight, superscript, and
+//! subscript text is set like the text around it, and soft hyphens are
+//! dropped rather than used to break words. The standard fonts cover
+//! Windows-1252 only; other characters are written as `?`.
+//!
+//! Pages are numbered in their footer. The title and first author also go
+//! into the PDF's document information, which viewers show as its
+//! properties. Output is deterministic: the same document and options always
+//! give the same bytes.
+//!
+//! This module is only built with the `pdf` feature. [`StandardFonts`]
+//! measures text the way the pages set it, for layout code of its own.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::render::pdf::{PageSize, PdfOptions, render_pdf};
+//!
+//! let doc = doctora::parse_document("= Report\n\n== Summary\n\nAll *good*.\n").unwrap();
+//! let options = PdfOptions::new().page_size(PageSize::LETTER).font_size(11.0);
+//! let pdf = render_pdf(&doc, &options);
+//!
+//! assert!(pdf.starts_with(b"%PDF-1.4"));
+//! assert!(pdf.ends_with(b"%%EOF\n"));
+//! ```
+
+use crate::ast::{Block, Document, Inline, ListItem, TableRow, TextAlign};
+use crate::footnotes::Footnotes;
+use crate::processor::{Output, Processor, ProcessorContext, ProcessorError};
+use crate::render::measure::{TextMeasurer, TextStyle};
+use crate::xref::References;
+
+/// Page dimensions, in points (1/72 inch)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageSize {
+    /// Page width
+    pub width: f32,
+    /// Page height
+    pub height: f32,
+}
+
+impl PageSize {
+    /// ISO A4, 210 × 297 mm
+    pub const A4: PageSize = PageSize {
+        width: 595.28,
+        height: 841.89,
+    };
+
+    /// US Letter, 8.5 × 11 in
+    pub const LETTER: PageSize = PageSize {
+        width: 612.0,
+        height: 792.0,
+    };
+}
+
+/// Options for [`render_pdf`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfOptions {
+    /// Size of every page
+    pub page_size: PageSize,
+    /// Blank space around the text on each side, in points
+    pub margin: f32,
+    /// Size of body text, in points; headings and code scale from it
+    pub font_size: f32,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::A4,
+            margin: 56.0,
+            font_size: 10.5,
+        }
+    }
+}
+
+impl PdfOptions {
+    /// Creates options for A4 pages with 10.5 point text
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the page size
+    pub fn page_size(mut self, page_size: PageSize) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Sets the margin on each side of the page
+    pub fn margin(mut self, margin: f32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Sets the size of body text
+    pub fn font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+}
+
+/// Render `doc` as a PDF file
+pub fn render_pdf(doc: &Document, options: &PdfOptions) -> Vec<u8> {
+    let mut layout = Layout::new(doc, options);
+    layout.document(doc);
+    layout.number_pages();
+    write_pdf(doc, options, &layout.pages)
+}
+
+/// The built-in `pdf` processor
+#[derive(Debug, Clone, Default)]
+pub struct PdfProcessor {
+    /// Options passed to [`render_pdf`]
+    pub options: PdfOptions,
+}
+
+impl Processor for PdfProcessor {
+    fn name(&self) -> &str {
+        "pdf"
+    }
+
+    fn can_handle(&self, format: &str) -> bool {
+        format == "pdf"
+    }
+
+    fn process(
+        &self,
+        doc: &Document,
+        _context: &ProcessorContext<'_>,
+    ) -> Result<Output, ProcessorError> {
+        Ok(Output::Binary(render_pdf(doc, &self.options)))
+    }
+}
+
+/// Metrics of the standard fonts the PDF renderer sets text in
+///
+/// Widths are those of Helvetica (regular and bold; the oblique faces
+/// share them) and Courier, in points. Characters outside ASCII get an
+/// average width.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StandardFonts;
+
+impl TextMeasurer for StandardFonts {
+    fn width(&self, text: &str, style: TextStyle) -> f32 {
+        let units: u32 = text.chars().map(|c| char_width(c, style)).sum();
+        units as f32 * style.size / 1000.0
+    }
+
+    fn line_height(&self, style: TextStyle) -> f32 {
+        style.size * 1.3
+    }
+}
+
+/// Advance widths of ASCII `' '..='~'` in Helvetica, in 1/1000 em
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Advance widths of ASCII `' '..='~'` in Helvetica Bold, in 1/1000 em
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667,
+    611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556,
+    278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+/// Width of `c` in the font for `style`, in 1/1000 em
+fn char_width(c: char, style: TextStyle) -> u32 {
+    if style.monospace {
+        return 600;
+    }
+    let table = if style.bold {
+        &HELVETICA_BOLD
+    } else {
+        &HELVETICA
+    };
+    match c {
+        ' '..='~' => table[c as usize - 0x20] as u32,
+        SOFT_HYPHEN => 0,
+        NO_BREAK_SPACE => 278,
+        '\u{2022}' => 350,
+        '\u{2018}' | '\u{2019}' | '\u{201A}' => 278,
+        '\u{2014}' | '\u{2026}' | '\u{2030}' => 1000,
+        _ => 556,
+    }
+}
+
+const SOFT_HYPHEN: char = '\u{00AD}';
+const NO_BREAK_SPACE: char = '\u{00A0}';
+
+/// Text color of links, as PDF RGB components
+const LINK_COLOR: &str = "0 0.2 0.6";
+
+/// Background of listing blocks, as a PDF gray level
+const LISTING_GRAY: &str = "0.94";
+
+/// Indentation of list items and nested blocks, in points
+const INDENT: f32 = 18.0;
+
+/// A run of text in one style
+#[derive(Debug, Clone, PartialEq)]
+struct Span {
+    text: String,
+    style: TextStyle,
+    /// Target of the link the text is in
+    link: Option<String>,
+}
+
+/// A word, or a forced line break in verses
+enum Token {
+    Word {
+        pieces: Vec<Span>,
+        /// True if a space separates the word from the previous one
+        space: bool,
+    },
+    Break,
+}
+
+/// A laid out line: spans with their offsets from the line start
+struct Line {
+    pieces: Vec<(f32, Span)>,
+    width: f32,
+    /// Largest font size on the line, which sets its height
+    size: f32,
+}
+
+/// A link area on a page: rectangle (left, bottom, right, top) and target
+struct LinkArea {
+    rect: [f32; 4],
+    target: String,
+}
+
+/// One page: its content stream and link areas
+#[derive(Default)]
+struct Page {
+    content: String,
+    links: Vec<LinkArea>,
+}
+
+/// Places the blocks of a document on pages
+struct Layout<'a> {
+    options: &'a PdfOptions,
+    references: References,
+    footnotes: Footnotes,
+    pages: Vec<Page>,
+    /// Top of the free space on the current page, from the bottom edge
+    y: f32,
+}
+
+impl<'a> Layout<'a> {
+    fn new(doc: &Document, options: &'a PdfOptions) -> Self {
+        Self {
+            options,
+            references: References::new(doc),
+            footnotes: Footnotes::default(),
+            pages: vec![Page::default()],
+            y: options.page_size.height - options.margin,
+        }
+    }
+
+    fn base(&self) -> TextStyle {
+        TextStyle::new(self.options.font_size)
+    }
+
+    fn top(&self) -> f32 {
+        self.options.page_size.height - self.options.margin
+    }
+
+    fn left(&self) -> f32 {
+        self.options.margin
+    }
+
+    fn text_width(&self) -> f32 {
+        self.options.page_size.width - 2.0 * self.options.margin
+    }
+
+    fn page(&mut self) -> &mut Page {
+        self.pages.last_mut().expect("there is always a page")
+    }
+
+    /// Start a new page unless `height` fits on this one, or this one is
+    /// still empty
+    fn ensure(&mut self, height: f32) {
+        if self.y - height < self.options.margin && self.y < self.top() {
+            self.pages.push(Page::default());
+            self.y = self.top();
+        }
+    }
+
+    /// Leave vertical space before a block, except at the top of a page
+    fn gap(&mut self, scale: f32) {
+        if self.y < self.top() {
+            self.y -= self.options.font_size * scale;
+        }
+    }
+
+    fn document(&mut self, doc: &Document) {
+        let (x, width) = (self.left(), self.text_width());
+        let title = doc
+            .attributes
+            .get("doctitle")
+            .or_else(|| doc.header.as_ref()?.title.as_deref());
+        if let Some(title) = title {
+            let style = self.base().bold(true);
+            let style = TextStyle {
+                size: style.size * 2.0,
+                ..style
+            };
+            let lines = self.wrap(&[plain(title, style)], width, false);
+            self.lines(&lines, x, width, None);
+        }
+        if let Some(header) = &doc.header
+            && !header.authors.is_empty()
+        {
+            let names: Vec<&str> = header.authors.iter().map(|a| a.name.as_str()).collect();
+            let lines = self.wrap(
+                &[plain(&names.join(", "), self.base().italic(true))],
+                width,
+                false,
+            );
+            self.y -= self.options.font_size * 0.3;
+            self.lines(&lines, x, width, None);
+        }
+        self.blocks(&doc.blocks, x, width);
+
+        if self.footnotes.is_empty() {
+            return;
+        }
+        self.gap(1.5);
+        self.ensure(self.options.font_size * 2.0);
+        let rule_y = self.y;
+        self.page().content.push_str(&format!(
+            "0.5 w {} {} m {} {} l S\n",
+            num(x),
+            num(rule_y),
+            num(x + width / 3.0),
+            num(rule_y)
+        ));
+        self.y -= self.options.font_size * 0.5;
+        let style = TextStyle {
+            size: self.options.font_size * 0.85,
+            ..self.base()
+        };
+        let entries = self.footnotes.entries().to_vec();
+        for entry in entries {
+            let mut spans = vec![plain(&format!("{}. ", entry.number), style)];
+            self.spans(&entry.text, style, None, &mut spans);
+            let lines = self.wrap(&spans, width, false);
+            self.lines(&lines, x, width, None);
+        }
+    }
+
+    fn blocks(&mut self, blocks: &[Block], x: f32, width: f32) {
+        for block in blocks {
+            self.block(block, x, width);
+        }
+    }
+
+    fn block(&mut self, block: &Block, x: f32, width: f32) {
+        if matches!(
+            block,
+            Block::Passthrough { .. }
+                | Block::Comment { .. }
+                | Block::AttributeEntry { .. }
+                | Block::Unknown { .. }
+        ) {
+            return;
+        }
+        self.gap(0.75);
+        if let Some(title) = &block.metadata().title
+            && !matches!(block, Block::Section { .. })
+        {
+            let mut spans = Vec::new();
+            self.spans(title, self.base().bold(true), None, &mut spans);
+            let lines = self.wrap(&spans, width, false);
+            self.lines(&lines, x, width, None);
+            self.y -= self.options.font_size * 0.25;
+        }
+        let base = self.base();
+        match block {
+            Block::Section {
+                level,
+                title,
+                content,
+                ..
+            } => {
+                let scale = match level {
+                    0 | 1 => 1.9,
+                    2 => 1.5,
+                    3 => 1.3,
+                    4 => 1.15,
+                    _ => 1.05,
+                };
+                let style = TextStyle {
+                    size: base.size * scale,
+                    ..base.bold(true)
+                };
+                self.gap(0.75);
+                let mut spans = Vec::new();
+                self.spans(title, style, None, &mut spans);
+                let lines = self.wrap(&spans, width, false);
+                // Keep the heading with the first line after it
+                let height: f32 = lines.iter().map(|line| line_height(line.size)).sum();
+                self.ensure(height + line_height(base.size));
+                self.lines(&lines, x, width, None);
+                self.blocks(content, x, width);
+            }
+            Block::Paragraph { content, metadata } => {
+                let mut spans = Vec::new();
+                self.spans(content, base, None, &mut spans);
+                let lines = self.wrap(&spans, width, false);
+                self.lines(&lines, x, width, metadata.text_align());
+            }
+            Block::UnorderedList { items, .. } => {
+                self.list(items, x, width, |_| "\u{2022}".to_string());
+            }
+            Block::OrderedList { items, .. } => {
+                self.list(items, x, width, |index| format!("{}.", index + 1));
+            }
+            Block::DescriptionList { items, .. } => {
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        self.y -= base.size * 0.4;
+                    }
+                    let mut spans = Vec::new();
+                    self.spans(&item.term, base.bold(true), None, &mut spans);
+                    let lines = self.wrap(&spans, width, false);
+                    self.lines(&lines, x, width, None);
+                    let mut spans = Vec::new();
+                    self.spans(&item.description, base, None, &mut spans);
+                    let lines = self.wrap(&spans, width - INDENT, false);
+                    self.lines(&lines, x + INDENT, width - INDENT, None);
+                }
+            }
+            Block::Listing { content, .. } => self.listing(content, x, width),
+            Block::Table {
+                columns,
+                header,
+                rows,
+                ..
+            } => {
+                let total: u32 = columns.iter().map(|column| column.width.max(1)).sum();
+                let widths: Vec<f32> = columns
+                    .iter()
+                    .map(|column| width * column.width.max(1) as f32 / total.max(1) as f32)
+                    .collect();
+                let mut spans_down = vec![0; widths.len()];
+                if let Some(header) = header {
+                    self.row(header, &widths, &mut spans_down, x, base.bold(true));
+                }
+                for row in rows {
+                    self.row(row, &widths, &mut spans_down, x, base);
+                }
+            }
+            Block::Quote {
+                attribution,
+                citation,
+                content,
+                ..
+            } => {
+                self.blocks(content, x + INDENT, width - INDENT);
+                self.attribution(
+                    attribution.as_deref(),
+                    citation.as_deref(),
+                    x + INDENT,
+                    width - INDENT,
+                );
+            }
+            Block::Verse {
+                attribution,
+                citation,
+                content,
+                ..
+            } => {
+                let mut spans = Vec::new();
+                self.spans(content, base.italic(true), None, &mut spans);
+                let lines = self.wrap(&spans, width - INDENT, true);
+                self.lines(&lines, x + INDENT, width - INDENT, None);
+                self.attribution(
+                    attribution.as_deref(),
+                    citation.as_deref(),
+                    x + INDENT,
+                    width - INDENT,
+                );
+            }
+            Block::Admonition { kind, content, .. } => {
+                let lines = self.wrap(&[plain(kind.caption(), base.bold(true))], width, false);
+                self.lines(&lines, x, width, None);
+                self.blocks(content, x + INDENT, width - INDENT);
+            }
+            Block::Example { content, .. } | Block::Sidebar { content, .. } => {
+                self.blocks(content, x + INDENT, width - INDENT);
+            }
+            Block::Open { content, .. } => self.blocks(content, x, width),
+            Block::Image {
+                target, attributes, ..
+            } => {
+                let alt = attributes.get("alt").unwrap_or(target);
+                let text = format!("[{alt}]");
+                let lines = self.wrap(&[plain(&text, base.italic(true))], width, false);
+                self.lines(&lines, x, width, Some(TextAlign::Center));
+            }
+            Block::Passthrough { .. }
+            | Block::Comment { .. }
+            | Block::AttributeEntry { .. }
+            | Block::Unknown { .. } => {}
+        }
+    }
+
+    /// Lay out list items with the markers `marker` gives by index
+    fn list(&mut self, items: &[ListItem], x: f32, width: f32, marker: impl Fn(usize) -> String) {
+        let base = self.base();
+        for (index, item) in items.iter().enumerate() {
+            if index > 0 {
+                self.y -= base.size * 0.3;
+            }
+            let mut spans = Vec::new();
+            self.spans(&item.content, base, None, &mut spans);
+            let lines = self.wrap(&spans, width - INDENT, false);
+            if let Some(first) = lines.first() {
+                self.ensure(line_height(first.size));
+                let baseline = self.y - first.size;
+                let span = plain(&marker(index), base);
+                self.text(x, baseline, &span);
+            }
+            self.lines(&lines, x + INDENT, width - INDENT, None);
+            self.blocks(&item.blocks, x + INDENT, width - INDENT);
+        }
+    }
+
+    /// Lay out a listing block's lines in Courier, breaking lines wider
+    /// than the block
+    fn listing(&mut self, content: &str, x: f32, width: f32) {
+        let style = TextStyle {
+            size: self.options.font_size * 0.9,
+            ..self.base().monospace(true)
+        };
+        let padding = style.size * 0.5;
+        let columns = (((width - 2.0 * padding) / StandardFonts.width("m", style)) as usize).max(1);
+        let mut lines = Vec::new();
+        for line in content.split('\n') {
+            let chars: Vec<char> = line.chars().collect();
+            if chars.is_empty() {
+                lines.push(String::new());
+            }
+            lines.extend(chars.chunks(columns).map(|chunk| chunk.iter().collect()));
+        }
+        let height = line_height(style.size);
+        let count = lines.len();
+        for (index, line) in lines.into_iter().enumerate() {
+            // The first and last lines carry the block's padding
+            let above = if index == 0 { padding } else { 0.0 };
+            let below = if index + 1 == count { padding } else { 0.0 };
+            self.ensure(above + height + below);
+            let baseline = self.y - above - style.size;
+            let bottom = self.y - above - height - below;
+            let box_height = self.y - bottom;
+            self.y = bottom;
+            self.page().content.push_str(&format!(
+                "{LISTING_GRAY} g {} {} {} {} re f 0 g\n",
+                num(x),
+                num(bottom),
+                num(width),
+                num(box_height)
+            ));
+            self.text(x + padding, baseline, &plain(&line, style));
+        }
+    }
+
+    /// Lay out one table row; `spans_down` counts, per column, the rows
+    /// that a cell above still covers
+    fn row(
+        &mut self,
+        row: &TableRow,
+        widths: &[f32],
+        spans_down: &mut [usize],
+        x: f32,
+        style: TextStyle,
+    ) {
+        let padding = style.size * 0.4;
+        let mut cells = Vec::new();
+        let mut column = 0;
+        for cell in &row.cells {
+            while column < widths.len() && spans_down[column] > 0 {
+                column += 1;
+            }
+            if column >= widths.len() {
+                break;
+            }
+            let end = (column + cell.colspan.max(1)).min(widths.len());
+            let left = x + widths[..column].iter().sum::<f32>();
+            let width: f32 = widths[column..end].iter().sum();
+            let mut spans = Vec::new();
+            self.spans(&cell.content, style, None, &mut spans);
+            let lines = self.wrap(&spans, width - 2.0 * padding, false);
+            for covered in &mut spans_down[column..end] {
+                *covered = cell.rowspan.max(1);
+            }
+            cells.push((left, width, lines));
+            column = end;
+        }
+        for covered in spans_down.iter_mut() {
+            *covered = covered.saturating_sub(1);
+        }
+
+        let height = cells
+            .iter()
+            .map(|(_, _, lines)| lines.iter().map(|line| line_height(line.size)).sum::<f32>())
+            .fold(line_height(style.size), f32::max)
+            + 2.0 * padding;
+        self.ensure(height);
+        let top = self.y;
+        for (left, width, lines) in &cells {
+            self.page().content.push_str(&format!(
+                "0.5 w {} {} {} {} re S\n",
+                num(*left),
+                num(top - height),
+                num(*width),
+                num(height)
+            ));
+            self.y = top - padding;
+            self.lines(lines, left + padding, width - 2.0 * padding, None);
+        }
+        self.y = top - height;
+    }
+
+    /// Lay out a quote's attribution line, if it has one
+    fn attribution(
+        &mut self,
+        attribution: Option<&str>,
+        citation: Option<&str>,
+        x: f32,
+        width: f32,
+    ) {
+        let text = match (attribution, citation) {
+            (Some(who), Some(work)) => format!("\u{2014} {who}, {work}"),
+            (Some(text), None) | (None, Some(text)) => format!("\u{2014} {text}"),
+            (None, None) => return,
+        };
+        let style = TextStyle {
+            size: self.options.font_size * 0.9,
+            ..self.base()
+        };
+        self.y -= style.size * 0.4;
+        let lines = self.wrap(&[plain(&text, style)], width, false);
+        self.lines(&lines, x, width, None);
+    }
+
+    /// Flatten `content` into spans, starting from `style`
+    fn spans(
+        &mut self,
+        content: &[Inline],
+        style: TextStyle,
+        link: Option<&str>,
+        out: &mut Vec<Span>,
+    ) {
+        let span = |text: String| Span {
+            text,
+            style,
+            link: link.map(str::to_string),
+        };
+        for inline in content {
+            match inline {
+                Inline::Text(text) => out.push(span(text.clone())),
+                Inline::BreakHint(hint) => out.push(span(hint.as_char().to_string())),
+                Inline::Image { target, attributes } => {
+                    let alt = attributes.get("alt").unwrap_or(target);
+                    out.push(span(format!("[{alt}]")))
+                }
+                Inline::Icon { name, attributes } => {
+                    let alt = attributes.get("alt").unwrap_or(name);
+                    out.push(span(format!("[{alt}]")))
+                }
+                Inline::XRef { id, text } if text.is_empty() => {
+                    out.push(span(self.references.label(id)))
+                }
+                Inline::Link { target, text, .. } if text.is_empty() => out.push(Span {
+                    text: target.clone(),
+                    style,
+                    link: Some(target.clone()),
+                }),
+                Inline::Anchor(_) => {}
+                Inline::Footnote { id, text } => {
+                    let marker = match self.footnotes.register(id.as_deref(), text) {
+                        Some(footnote) => footnote.number.to_string(),
+                        None => id.clone().unwrap_or_default(),
+                    };
+                    out.push(span(format!("[{marker}]")))
+                }
+                Inline::Bold(nested) => self.spans(nested, style.bold(true), link, out),
+                Inline::Italic(nested) => self.spans(nested, style.italic(true), link, out),
+                Inline::Monospace(nested) => self.spans(nested, style.monospace(true), link, out),
+                Inline::Highlight(nested)
+                | Inline::Span {
+                    content: nested, ..
+                }
+                | Inline::Superscript(nested)
+                | Inline::Subscript(nested)
+                | Inline::XRef { text: nested, .. } => self.spans(nested, style, link, out),
+                Inline::Link { target, text, .. } => self.spans(text, style, Some(target), out),
+            }
+        }
+    }
+
+    /// Break `spans` into lines no wider than `width`, at spaces, and at
+    /// line feeds too if `preserve` is set
+    fn wrap(&self, spans: &[Span], width: f32, preserve: bool) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let mut line = Line::new();
+        for token in tokens(spans, preserve) {
+            let (pieces, space) = match token {
+                Token::Break => {
+                    if line.pieces.is_empty() {
+                        line.size = self.options.font_size;
+                    }
+                    lines.push(std::mem::replace(&mut line, Line::new()));
+                    continue;
+                }
+                Token::Word { pieces, space } => (pieces, space),
+            };
+            let word_width: f32 = pieces
+                .iter()
+                .map(|piece| StandardFonts.width(&piece.text, piece.style))
+                .sum();
+            let lead = if space && !line.pieces.is_empty() {
+                StandardFonts.width(" ", pieces[0].style)
+            } else {
+                0.0
+            };
+            if !line.pieces.is_empty() && line.width + lead + word_width > width {
+                lines.push(std::mem::replace(&mut line, Line::new()));
+            }
+            line.push(pieces, lead);
+        }
+        if !line.pieces.is_empty() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Set `lines` from the current position, `align`ed within `width`
+    fn lines(&mut self, lines: &[Line], x: f32, width: f32, align: Option<TextAlign>) {
+        for line in lines {
+            let height = line_height(line.size);
+            self.ensure(height);
+            let baseline = self.y - line.size;
+            // As `align_offset`, for a line whose width is already known
+            let room = (width - line.width).max(0.0);
+            let offset = match align {
+                Some(TextAlign::Center) => room / 2.0,
+                Some(TextAlign::Right) => room,
+                Some(TextAlign::Left | TextAlign::Justify) | None => 0.0,
+            };
+            for (left, span) in &line.pieces {
+                self.text(x + offset + left, baseline, span);
+            }
+            self.y -= height;
+        }
+    }
+
+    /// Set `span` with its baseline at `y`
+    fn text(&mut self, x: f32, y: f32, span: &Span) {
+        if span.text.is_empty() {
+            return;
+        }
+        let color = if span.link.is_some() {
+            LINK_COLOR
+        } else {
+            "0 0 0"
+        };
+        let op = format!(
+            "BT /{} {} Tf {color} rg {} {} Td ({}) Tj ET\n",
+            font_name(span.style),
+            num(span.style.size),
+            num(x),
+            num(y),
+            pdf_string(&span.text)
+        );
+        let page = self.page();
+        page.content.push_str(&op);
+        if let Some(target) = &span.link {
+            let width = StandardFonts.width(&span.text, span.style);
+            page.links.push(LinkArea {
+                rect: [
+                    x,
+                    y - span.style.size * 0.25,
+                    x + width,
+                    y + span.style.size * 0.85,
+                ],
+                target: target.clone(),
+            });
+        }
+    }
+
+    /// Write the page number in the footer of every page
+    fn number_pages(&mut self) {
+        let style = TextStyle {
+            size: self.options.font_size * 0.8,
+            ..self.base()
+        };
+        let (width, y) = (self.options.page_size.width, self.options.margin / 2.0);
+        for index in 0..self.pages.len() {
+            let text = (index + 1).to_string();
+            let x = (width - StandardFonts.width(&text, style)) / 2.0;
+            self.pages[index].content.push_str(&format!(
+                "BT /{} {} Tf 0 0 0 rg {} {} Td ({text}) Tj ET\n",
+                font_name(style),
+                num(style.size),
+                num(x),
+                num(y)
+            ));
+        }
+    }
+}
+
+impl Line {
+    fn new() -> Self {
+        Self {
+            pieces: Vec::new(),
+            width: 0.0,
+            size: 0.0,
+        }
+    }
+
+    /// Append a word's pieces after `lead` points of space, merging each
+    /// piece into the previous one when their styles match
+    fn push(&mut self, pieces: Vec<Span>, lead: f32) {
+        for (index, piece) in pieces.into_iter().enumerate() {
+            let lead = if index == 0 { lead } else { 0.0 };
+            let width = StandardFonts.width(&piece.text, piece.style);
+            self.size = self.size.max(piece.style.size);
+            match self.pieces.last_mut() {
+                Some((_, last)) if last.style == piece.style && last.link == piece.link => {
+                    if lead > 0.0 {
+                        last.text.push(' ');
+                    }
+                    last.text.push_str(&piece.text);
+                }
+                _ => self.pieces.push((self.width + lead, piece)),
+            }
+            self.width += lead + width;
+        }
+    }
+}
+
+fn line_height(size: f32) -> f32 {
+    StandardFonts.line_height(TextStyle::new(size))
+}
+
+fn plain(text: &str, style: TextStyle) -> Span {
+    Span {
+        text: text.to_string(),
+        style,
+        link: None,
+    }
+}
+
+/// Split `spans` into words at whitespace other than non-breaking spaces,
+/// with a break at each line feed if `preserve` is set
+fn tokens(spans: &[Span], preserve: bool) -> Vec<Token> {
+    fn finish(word: &mut Vec<Span>, space: &mut bool, tokens: &mut Vec<Token>) {
+        if !word.is_empty() {
+            tokens.push(Token::Word {
+                pieces: std::mem::take(word),
+                space: *space,
+            });
+            *space = false;
+        }
+    }
+
+    let mut tokens = Vec::new();
+    let mut word: Vec<Span> = Vec::new();
+    let mut space = false;
+    for span in spans {
+        for c in span.text.chars() {
+            if c == '\n' && preserve {
+                finish(&mut word, &mut space, &mut tokens);
+                tokens.push(Token::Break);
+                space = false;
+            } else if c.is_whitespace() && c != NO_BREAK_SPACE {
+                finish(&mut word, &mut space, &mut tokens);
+                space = true;
+            } else if c != SOFT_HYPHEN {
+                match word.last_mut() {
+                    Some(last) if last.style == span.style && last.link == span.link => {
+                        last.text.push(c)
+                    }
+                    _ => word.push(Span {
+                        text: c.to_string(),
+                        ..span.clone()
+                    }),
+                }
+            }
+        }
+    }
+    finish(&mut word, &mut space, &mut tokens);
+    tokens
+}
+
+/// Name of the font resource for `style`
+fn font_name(style: TextStyle) -> &'static str {
+    match (style.monospace, style.bold, style.italic) {
+        (false, false, false) => "F1",
+        (false, true, false) => "F2",
+        (false, false, true) => "F3",
+        (false, true, true) => "F4",
+        (true, false, false) => "F5",
+        (true, true, false) => "F6",
+        (true, false, true) => "F7",
+        (true, true, true) => "F8",
+    }
+}
+
+/// Base fonts of the resources `F1` to `F8`
+const FONTS: [&str; 8] = [
+    "Helvetica",
+    "Helvetica-Bold",
+    "Helvetica-Oblique",
+    "Helvetica-BoldOblique",
+    "Courier",
+    "Courier-Bold",
+    "Courier-Oblique",
+    "Courier-BoldOblique",
+];
+
+/// A number for a content stream, with at most two decimals
+fn num(value: f32) -> String {
+    let text = format!("{value:.2}");
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// `text` as the contents of a PDF literal string in WinAnsiEncoding
+///
+/// Parentheses and backslashes are escaped, bytes outside ASCII are written
+/// as octal escapes, and characters the encoding lacks become `?`.
+fn pdf_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match win_ansi(c) {
+            Some(b'(') => out.push_str("\\("),
+            Some(b')') => out.push_str("\\)"),
+            Some(b'\\') => out.push_str("\\\\"),
+            Some(byte @ 0x20..=0x7E) => out.push(byte as char),
+            Some(byte) => out.push_str(&format!("\\{byte:03o}")),
+            None => out.push('?'),
+        }
+    }
+    out
+}
+
+/// The Windows-1252 byte for `c`, if it has one
+fn win_ansi(c: char) -> Option<u8> {
+    let byte = match c {
+        '\t' => b' ',
+        ' '..='~' | '\u{A0}'..='\u{FF}' => c as u8,
+        '\u{20AC}' => 0x80,
+        '\u{201A}' => 0x82,
+        '\u{0192}' => 0x83,
+        '\u{201E}' => 0x84,
+        '\u{2026}' => 0x85,
+        '\u{2020}' => 0x86,
+        '\u{2021}' => 0x87,
+        '\u{02C6}' => 0x88,
+        '\u{2030}' => 0x89,
+        '\u{0160}' => 0x8A,
+        '\u{2039}' => 0x8B,
+        '\u{0152}' => 0x8C,
+        '\u{017D}' => 0x8E,
+        '\u{2018}' => 0x91,
+        '\u{2019}' => 0x92,
+        '\u{201C}' => 0x93,
+        '\u{201D}' => 0x94,
+        '\u{2022}' => 0x95,
+        '\u{2013}' => 0x96,
+        '\u{2014}' => 0x97,
+        '\u{02DC}' => 0x98,
+        '\u{2122}' => 0x99,
+        '\u{0161}' => 0x9A,
+        '\u{203A}' => 0x9B,
+        '\u{0153}' => 0x9C,
+        '\u{017E}' => 0x9E,
+        '\u{0178}' => 0x9F,
+        _ => return None,
+    };
+    Some(byte)
+}
+
+/// Serialize `pages` as a PDF file, with the document information of `doc`
+fn write_pdf(doc: &Document, options: &PdfOptions, pages: &[Page]) -> Vec<u8> {
+    // Objects 1-2 are the catalog and page tree, 3-10 the fonts, 11 the
+    // document information; each page follows with its content stream and
+    // link annotations
+    let mut objects: Vec<String> = vec![String::new(); 11];
+    objects[0] = "<< /Type /Catalog /Pages 2 0 R >>".to_string();
+    for (index, font) in FONTS.iter().enumerate() {
+        objects[2 + index] = format!(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /{font} /Encoding /WinAnsiEncoding >>"
+        );
+    }
+    let mut info = String::from("<< /Producer (doctora)");
+    let title = doc
+        .attributes
+        .get("doctitle")
+        .or_else(|| doc.header.as_ref()?.title.as_deref());
+    if let Some(title) = title {
+        info.push_str(&format!(" /Title ({})", pdf_string(title)));
+    }
+    if let Some(author) = doc
+        .header
+        .as_ref()
+        .and_then(|header| header.authors.first())
+    {
+        info.push_str(&format!(" /Author ({})", pdf_string(&author.name)));
+    }
+    info.push_str(" >>");
+    objects[10] = info;
+
+    let fonts: Vec<String> = (0..FONTS.len())
+        .map(|index| format!("/F{} {} 0 R", index + 1, index + 3))
+        .collect();
+    let resources = format!("<< /Font << {} >> >>", fonts.join(" "));
+    let mut kids = Vec::new();
+    for page in pages {
+        let number = objects.len() + 1;
+        kids.push(format!("{number} 0 R"));
+        let annotations: Vec<String> = (0..page.links.len())
+            .map(|index| format!("{} 0 R", number + 2 + index))
+            .collect();
+        let annots = if annotations.is_empty() {
+            String::new()
+        } else {
+            format!(" /Annots [{}]", annotations.join(" "))
+        };
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources {resources} /Contents {} 0 R{annots} >>",
+            num(options.page_size.width),
+            num(options.page_size.height),
+            number + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            page.content.len(),
+            page.content
+        ));
+        for link in &page.links {
+            let [left, bottom, right, top] = link.rect;
+            objects.push(format!(
+                "<< /Type /Annot /Subtype /Link /Rect [{} {} {} {}] /Border [0 0 0] /A << /S /URI /URI ({}) >> >>",
+                num(left),
+                num(bottom),
+                num(right),
+                num(top),
+                pdf_string(&link.target)
+            ));
+        }
+    }
+    objects[1] = format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        pages.len()
+    );
+
+    let mut out: Vec<u8> = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n{object}\nendobj\n", index + 1).as_bytes());
+    }
+    let xref = out.len();
+    out.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        out.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+    }
+    out.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info 11 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .as_bytes(),
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+    use crate::processor::ProcessorRegistry;
+
+    fn render(source: &str) -> String {
+        let doc = parse_document(source).unwrap();
+        String::from_utf8_lossy(&render_pdf(&doc, &PdfOptions::new())).into_owned()
+    }
+
+    #[test]
+    fn test_file_structure() {
+        let doc = parse_document("= Title\nAda Lovelace\n\nHello.\n").unwrap();
+        let pdf = render_pdf(&doc, &PdfOptions::new());
+        let text = String::from_utf8_lossy(&pdf);
+
+        // The cross reference table points at every object
+        let start: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|tail| tail.lines().next())
+            .and_then(|offset| offset.parse().ok())
+            .unwrap();
+        assert!(pdf[start..].starts_with(b"xref\n0 14\n"));
+        let table = String::from_utf8_lossy(&pdf[start..]);
+        for (index, entry) in table.lines().skip(3).take(13).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", index + 1).as_bytes()));
+        }
+        assert!(text.contains("/Title (Title) /Author (Ada Lovelace)"));
+        assert!(text.contains("/Count 1 >>"));
+
+        // The same input always gives the same bytes
+        assert_eq!(render_pdf(&doc, &PdfOptions::new()), pdf);
+    }
+
+    #[test]
+    fn test_text_and_styles() {
+        let pdf = render("== Setup\n\nRun *the tool* with `--fast` (quickly).\n");
+        assert!(pdf.contains("/F2 15.75 Tf 0 0 0 rg 56 ") && pdf.contains("(Setup) Tj"));
+        assert!(pdf.contains("(Run) Tj"));
+        assert!(pdf.contains("/F2 10.5 Tf 0 0 0 rg") && pdf.contains("(the tool) Tj"));
+        assert!(pdf.contains("/F5 10.5 Tf") && pdf.contains("(--fast) Tj"));
+        assert!(pdf.contains("(\\(quickly\\).) Tj"));
+    }
+
+    #[test]
+    fn test_wrapping_and_pages() {
+        let sentence = "The quick brown fox jumps over the lazy dog. ".repeat(12);
+        let doc = parse_document(&sentence).unwrap();
+        let options = PdfOptions::new();
+        let mut layout = Layout::new(&doc, &options);
+        let spans = [plain(sentence.trim(), layout.base())];
+        let lines = layout.wrap(&spans, layout.text_width(), false);
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|line| line.width <= layout.text_width()));
+        layout.document(&doc);
+        assert_eq!(layout.pages.len(), 1);
+
+        let long = format!("{sentence}\n\n").repeat(40);
+        let pdf = render(&long);
+        let pages = pdf.matches("/Type /Page ").count();
+        assert!(pages > 1);
+        assert!(pdf.contains(&format!("({pages}) Tj")));
+    }
+
+    #[test]
+    fn test_blocks() {
+        let pdf = render(
+            "* one\n* two\n\n. first\n\n----\nfn main() {}\n----\n\n\
+             |===\n|A |B\n\n|1 |2\n|===\n\n[quote, Ada]\nWords.\n\n\
+             image::diagram.png[Flow]\n\nicon:heart[alt=Love]\n\n++++\n<hidden>\n++++\n",
+        );
+        assert!(pdf.contains("(\\225) Tj"));
+        assert!(pdf.contains("(1.) Tj"));
+        assert!(pdf.contains("/F5 9.45 Tf") && pdf.contains("(fn main\\(\\) {}) Tj"));
+        assert!(pdf.contains("0.94 g"));
+        assert!(pdf.contains("re S"));
+        assert!(pdf.contains("(\\227 Ada) Tj"));
+        assert!(pdf.contains("([Flow]) Tj"));
+        assert!(pdf.contains("([Love]) Tj"));
+        assert!(!pdf.contains("hidden"));
+    }
+
+    #[test]
+    fn test_links_and_footnotes() {
+        let pdf = render("See https://example.com[the site].footnote:[A note.]\n");
+        assert!(pdf.contains("0 0.2 0.6 rg") && pdf.contains("(the site) Tj"));
+        assert!(pdf.contains("/A << /S /URI /URI (https://example.com) >>"));
+        assert!(pdf.contains("/Annots ["));
+        assert!(pdf.contains("(.[1]) Tj"));
+        assert!(pdf.contains("(1. A note.) Tj"));
+    }
+
+    #[test]
+    fn test_pdf_strings() {
+        assert_eq!(pdf_string("a(b)\\"), "a\\(b\\)\\\\");
+        assert_eq!(pdf_string("café \u{2014} \u{4E2D}"), "caf\\351 \\227 ?");
+        assert_eq!(num(12.0), "12");
+        assert_eq!(num(0.5), "0.5");
+        assert_eq!(num(-0.001), "0");
+    }
+
+    #[test]
+    fn test_processor() {
+        let mut registry = ProcessorRegistry::new();
+        registry.register(Box::new(PdfProcessor::default()));
+        let doc = parse_document("Text.").unwrap();
+        let output = registry.convert(&doc, "pdf").unwrap();
+        assert!(matches!(output, Output::Binary(bytes) if bytes.starts_with(b"%PDF")));
+    }
+}
@@ -0,0 +1,595 @@
+//! Plain-text renderer
+//!
+//! Writes a document as readable plain text, for terminal help output and
+//! search indexing. Formatting marks are dropped and paragraphs are wrapped
+//! to [`TextOptions::width`] columns, measured in terminal cells
+//! ([`MonospaceMeasurer`]):
+//!
+//! | AST node          | Text                                                |
+//! |-------------------|-----------------------------------------------------|
+//! | Document title    | Underlined with `=`                                 |
+//! | Section           | Title underlined with `-` (level 1 and 2 only), its |
+//! |                   | content indented by [`TextOptions::section_indent`] |
+//! | Paragraph         | Wrapped text                                        |
+//! | Lists             | Items with a bullet or number and hanging indent    |
+//! | Description list  | The term, then the description indented             |
+//! | Listing block     | Indented, lines kept as they are                    |
+//! | Verse block       | Lines kept, attribution below                       |
+//! | Quote / example / | Indented, with the attribution below quotes         |
+//! | sidebar           |                                                     |
+//! | Exercise/solution | Indented below its numbered caption                 |
+//! | Admonition        | Indented below its caption (`Note:`)                |
+//! | Table             | Aligned columns, header row underlined with `-`     |
+//! | Block image       | Its alt text in brackets                            |
+//! | Link              | Its text followed by the URL in angle brackets      |
+//! | Cross reference   | Its text, or the target's title                     |
+//! | Footnote          | `[N]` marker, listed after the body                 |
+//!
+//! Bullets of unordered lists cycle through [`TextOptions::bullets`] by
+//! nesting depth. Passthrough blocks for the `text` backend are copied as
+//! they are; other passthroughs and comments are skipped. Soft hyphens are
+//! used as break points and non-breaking spaces keep words on one line
+//! (see [`wrap_lines`]).
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::parse_document;
+//! use doctora::render::text::{TextOptions, render_text};
+//!
+//! let doc = parse_document(
+//!     "= Guide\n\n== Install\n\nRun the *installer* and follow the prompts.\n\n* one\n* two\n",
+//! )
+//! .unwrap();
+//! assert_eq!(
+//!     render_text(&doc, &TextOptions::new().width(24)),
+//!     "Guide\n=====\n\nInstall\n-------\n\n  Run the installer and\n  follow the prompts.\n\n  * one\n  * two\n"
+//! );
+//! ```
+//!
+//! The renderer is also available as the `text` [`Processor`] ([`TextProcessor`]),
+//! which [`ProcessorRegistry::with_builtins`](crate::processor::ProcessorRegistry::with_builtins)
+//! registers.
+
+use crate::ast::{Block, Document, Inline, ListItem, TableRow};
+use crate::exercises::Exercises;
+use crate::footnotes::Footnotes;
+use crate::processor::{Output, Processor, ProcessorContext, ProcessorError};
+use crate::render::measure::{MonospaceMeasurer, TextMeasurer, TextStyle, wrap_lines};
+use crate::xref::References;
+
+/// Extra indentation of the content of quote, example, and sidebar blocks,
+/// listings, and descriptions
+const BLOCK_INDENT: usize = 4;
+
+/// Options for [`render_text`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextOptions {
+    /// Maximum line width in terminal cells (listings and tables may exceed
+    /// it)
+    pub width: usize,
+    /// Indentation of a section's content relative to its title
+    pub section_indent: usize,
+    /// Bullets of unordered lists by nesting depth, repeated for deeper
+    /// lists
+    pub bullets: Vec<String>,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            section_indent: 2,
+            bullets: vec!["*".to_string(), "-".to_string()],
+        }
+    }
+}
+
+impl TextOptions {
+    /// Creates options for 80 columns
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum line width
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Sets the indentation of section content
+    pub fn section_indent(mut self, indent: usize) -> Self {
+        self.section_indent = indent;
+        self
+    }
+
+    /// Sets the bullets of unordered lists, outermost first
+    pub fn bullets<I, S>(mut self, bullets: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.bullets = bullets.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+/// Render `doc` as plain text
+pub fn render_text(doc: &Document, options: &TextOptions) -> String {
+    let mut writer = Writer {
+        out: String::new(),
+        options,
+        references: References::new(doc),
+        footnotes: Footnotes::default(),
+        exercises: Exercises::default(),
+        indent: 0,
+        depth: 0,
+    };
+    if let Some(title) = doc
+        .header
+        .as_ref()
+        .and_then(|header| header.title.as_deref())
+    {
+        writer.heading(title, Some('='));
+    }
+    writer.blocks(&doc.blocks);
+    if !writer.footnotes.is_empty() {
+        writer.separate();
+        for entry in writer.footnotes.entries().to_vec() {
+            let text = writer.inlines(&entry.text);
+            writer.wrapped(&text, &format!("[{}] ", entry.number));
+        }
+    }
+    writer.out
+}
+
+/// The built-in `text` processor
+///
+/// Handles the formats `text` and `txt`.
+#[derive(Debug, Clone, Default)]
+pub struct TextProcessor {
+    /// Options passed to [`render_text`]
+    pub options: TextOptions,
+}
+
+impl Processor for TextProcessor {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn can_handle(&self, format: &str) -> bool {
+        matches!(format, "text" | "txt")
+    }
+
+    fn process(
+        &self,
+        doc: &Document,
+        _context: &ProcessorContext<'_>,
+    ) -> Result<Output, ProcessorError> {
+        Ok(Output::Text(render_text(doc, &self.options)))
+    }
+}
+
+/// Width of `text` in terminal cells
+fn cells(text: &str) -> usize {
+    MonospaceMeasurer.width(text, TextStyle::new(1.0)) as usize
+}
+
+struct Writer<'a> {
+    out: String,
+    options: &'a TextOptions,
+    references: References,
+    footnotes: Footnotes,
+    exercises: Exercises,
+    /// Indentation of the current block
+    indent: usize,
+    /// Nesting depth of unordered lists
+    depth: usize,
+}
+
+impl Writer<'_> {
+    fn blocks(&mut self, blocks: &[Block]) {
+        for block in blocks {
+            self.separate();
+            self.block(block);
+        }
+    }
+
+    /// Run `write` with the indentation increased by `by`
+    fn indented(&mut self, by: usize, write: impl FnOnce(&mut Self)) {
+        self.indent += by;
+        write(self);
+        self.indent -= by;
+    }
+
+    fn block(&mut self, block: &Block) {
+        let title = match &block.metadata().title {
+            Some(title) if !matches!(block, Block::Section { .. }) => Some(self.inlines(title)),
+            _ => None,
+        };
+        let caption = match (self.exercises.register(block), title) {
+            (Some(label), Some(title)) => Some(format!("{}. {title}", label.caption())),
+            (Some(label), None) => Some(label.caption()),
+            (None, title) => title,
+        };
+        if let Some(caption) = caption {
+            self.wrapped(&caption, "");
+        }
+        match block {
+            Block::Section {
+                level,
+                title,
+                content,
+                ..
+            } => {
+                let title = self.inlines(title);
+                let underline = match level {
+                    1 => Some('='),
+                    2 => Some('-'),
+                    _ => None,
+                };
+                self.heading(&title, underline);
+                self.indented(self.options.section_indent, |writer| writer.blocks(content));
+            }
+            Block::Paragraph { content, .. } => {
+                let text = self.inlines(content);
+                self.wrapped(&text, "");
+            }
+            Block::UnorderedList { items, .. } => {
+                let bullet = match self.options.bullets.len() {
+                    0 => "*".to_string(),
+                    len => self.options.bullets[self.depth % len].clone(),
+                };
+                self.depth += 1;
+                let loose = is_loose(items);
+                for (index, item) in items.iter().enumerate() {
+                    if loose && index > 0 {
+                        self.separate();
+                    }
+                    self.item(item, &format!("{bullet} "));
+                }
+                self.depth -= 1;
+            }
+            Block::OrderedList { items, .. } => {
+                let loose = is_loose(items);
+                let digits = items.len().to_string().len();
+                for (index, item) in items.iter().enumerate() {
+                    if loose && index > 0 {
+                        self.separate();
+                    }
+                    self.item(item, &format!("{:>digits$}. ", index + 1));
+                }
+            }
+            Block::DescriptionList { items, .. } => {
+                for item in items {
+                    let term = self.inlines(&item.term);
+                    self.wrapped(&term, "");
+                    if !item.description.is_empty() {
+                        let description = self.inlines(&item.description);
+                        self.indented(BLOCK_INDENT, |writer| writer.wrapped(&description, ""));
+                    }
+                }
+            }
+            Block::Listing { content, .. } => {
+                self.indented(BLOCK_INDENT, |writer| {
+                    for line in content.lines() {
+                        writer.line(line);
+                    }
+                });
+            }
+            Block::Verse {
+                attribution,
+                citation,
+                content,
+                ..
+            } => {
+                let text = self.inlines(content);
+                for line in text.lines() {
+                    self.line(line);
+                }
+                self.attribution(attribution.as_deref(), citation.as_deref());
+            }
+            Block::Quote {
+                attribution,
+                citation,
+                content,
+                ..
+            } => self.indented(BLOCK_INDENT, |writer| {
+                writer.blocks(content);
+                writer.attribution(attribution.as_deref(), citation.as_deref());
+            }),
+            Block::Admonition { kind, content, .. } => {
+                self.wrapped(&format!("{}:", kind.caption()), "");
+                self.indented(BLOCK_INDENT, |writer| writer.blocks(content));
+            }
+            Block::Example { content, .. } | Block::Sidebar { content, .. } => {
+                self.indented(BLOCK_INDENT, |writer| writer.blocks(content));
+            }
+            Block::Open { content, .. } => self.blocks(content),
+            Block::Table { header, rows, .. } => self.table(header.as_ref(), rows),
+            Block::Image {
+                target, attributes, ..
+            } => {
+                let alt = attributes.get("alt").unwrap_or(target);
+                self.wrapped(&format!("[{alt}]"), "");
+            }
+            Block::Passthrough { .. } => {
+                if let Some(content) = block.passthrough_for("text") {
+                    for line in content.lines() {
+                        self.line(line);
+                    }
+                }
+            }
+            Block::Comment { .. } | Block::AttributeEntry { .. } | Block::Unknown { .. } => {}
+        }
+    }
+
+    /// Write a title, underlined with `underline` if given
+    fn heading(&mut self, title: &str, underline: Option<char>) {
+        self.separate();
+        self.line(title);
+        if let Some(c) = underline {
+            self.line(&c.to_string().repeat(cells(title)));
+        }
+    }
+
+    /// Write a list item: `marker` then the text, with its attached blocks
+    /// and continuation lines aligned after the marker
+    fn item(&mut self, item: &ListItem, marker: &str) {
+        let text = self.inlines(&item.content);
+        self.wrapped(&text, marker);
+        self.indented(cells(marker), |writer| {
+            for block in &item.blocks {
+                if !matches!(
+                    block,
+                    Block::UnorderedList { .. } | Block::OrderedList { .. }
+                ) {
+                    writer.separate();
+                }
+                writer.block(block);
+            }
+        });
+    }
+
+    /// Write the attribution line under a quote or verse
+    fn attribution(&mut self, attribution: Option<&str>, citation: Option<&str>) {
+        let line = match (attribution, citation) {
+            (Some(attribution), Some(citation)) => format!("{attribution}, {citation}"),
+            (Some(text), None) | (None, Some(text)) => text.to_string(),
+            (None, None) => return,
+        };
+        self.wrapped(&format!("\u{2014} {line}"), "");
+    }
+
+    /// Write a table with its columns aligned; spans are not merged
+    fn table(&mut self, header: Option<&TableRow>, rows: &[TableRow]) {
+        let mut text_rows: Vec<Vec<String>> = Vec::new();
+        for row in header.into_iter().chain(rows) {
+            let cells = row
+                .cells
+                .iter()
+                .map(|cell| {
+                    let text = self.inlines(&cell.content);
+                    text.split_whitespace().collect::<Vec<_>>().join(" ")
+                })
+                .collect();
+            text_rows.push(cells);
+        }
+        let columns = text_rows.iter().map(Vec::len).max().unwrap_or(0);
+        let widths: Vec<usize> = (0..columns)
+            .map(|column| {
+                text_rows
+                    .iter()
+                    .filter_map(|row| row.get(column))
+                    .map(|cell| cells(cell))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        for (index, row) in text_rows.iter().enumerate() {
+            let mut line = String::new();
+            for (column, cell) in row.iter().enumerate() {
+                if column > 0 {
+                    line.push_str("  ");
+                }
+                line.push_str(cell);
+                line.push_str(&" ".repeat(widths[column] - cells(cell)));
+            }
+            self.line(&line);
+            if index == 0 && header.is_some() {
+                let rules: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+                self.line(&rules.join("  "));
+            }
+        }
+    }
+
+    /// Write `text` wrapped to the line width, the first line starting with
+    /// `marker` and the others indented to align with the text after it
+    fn wrapped(&mut self, text: &str, marker: &str) {
+        let hang = cells(marker);
+        let room = self.options.width.saturating_sub(self.indent + hang).max(1);
+        let lines = wrap_lines(&MonospaceMeasurer, text, TextStyle::new(1.0), room as f32);
+        for (index, line) in lines.iter().enumerate() {
+            let line = line.replace('\u{00A0}', " ");
+            if index == 0 {
+                self.line(&format!("{marker}{line}"));
+            } else {
+                self.line(&format!("{}{line}", " ".repeat(hang)));
+            }
+        }
+        if lines.is_empty() && !marker.is_empty() {
+            self.line(marker);
+        }
+    }
+
+    /// Write one line at the current indentation, without trailing spaces
+    fn line(&mut self, line: &str) {
+        let line = line.trim_end();
+        if !line.is_empty() {
+            self.out.push_str(&" ".repeat(self.indent));
+            self.out.push_str(line);
+        }
+        self.out.push('\n');
+    }
+
+    /// End the output with an empty line, unless it is empty or already
+    /// does
+    fn separate(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    /// Plain text of `content`
+    fn inlines(&mut self, content: &[Inline]) -> String {
+        let mut out = String::new();
+        self.push_inlines(content, &mut out);
+        out
+    }
+
+    fn push_inlines(&mut self, content: &[Inline], out: &mut String) {
+        for inline in content {
+            match inline {
+                Inline::Text(text) => out.push_str(text),
+                Inline::Bold(nested)
+                | Inline::Italic(nested)
+                | Inline::Monospace(nested)
+                | Inline::Highlight(nested)
+                | Inline::Span {
+                    content: nested, ..
+                }
+                | Inline::Superscript(nested)
+                | Inline::Subscript(nested) => self.push_inlines(nested, out),
+                Inline::Link { target, text, .. } if text.is_empty() => out.push_str(target),
+                Inline::Link { target, text, .. } => {
+                    self.push_inlines(text, out);
+                    out.push_str(&format!(" <{target}>"));
+                }
+                Inline::XRef { id, text } if text.is_empty() => {
+                    out.push_str(&self.references.label(id))
+                }
+                Inline::XRef { text, .. } => self.push_inlines(text, out),
+                Inline::Anchor(_) => {}
+                Inline::Image { target, attributes } => {
+                    let alt = attributes.get("alt").unwrap_or(target);
+                    out.push_str(&format!("[{alt}]"));
+                }
+                Inline::Icon { name, attributes } => {
+                    let alt = attributes.get("alt").unwrap_or(name);
+                    out.push_str(&format!("[{alt}]"));
+                }
+                Inline::Footnote { id, text } => {
+                    let marker = match self.footnotes.register(id.as_deref(), text) {
+                        Some(footnote) => footnote.number.to_string(),
+                        None => id.clone().unwrap_or_default(),
+                    };
+                    out.push_str(&format!("[{marker}]"));
+                }
+                Inline::BreakHint(hint) => out.push(hint.as_char()),
+            }
+        }
+    }
+}
+
+/// True if items of a list have blocks other than nested lists attached,
+/// so the items need empty lines between them
+fn is_loose(items: &[ListItem]) -> bool {
+    items.iter().any(|item| {
+        item.blocks.iter().any(|block| {
+            !matches!(
+                block,
+                Block::UnorderedList { .. } | Block::OrderedList { .. }
+            )
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    fn text(source: &str, options: &TextOptions) -> String {
+        render_text(&parse_document(source).unwrap(), options)
+    }
+
+    #[test]
+    fn test_wrapping_and_sections() {
+        let source = "= Guide\n\nIntro text that is long enough to wrap.\n\n\
+                      == Setup\n\nSetup text.\n\n=== Details\n\nDetail text.\n";
+        assert_eq!(
+            text(source, &TextOptions::new().width(20)),
+            "Guide\n=====\n\nIntro text that is\nlong enough to wrap.\n\n\
+             Setup\n-----\n\n  Setup text.\n\n  Details\n\n    Detail text.\n"
+        );
+        assert_eq!(
+            text(source, &TextOptions::new().section_indent(0)),
+            "Guide\n=====\n\nIntro text that is long enough to wrap.\n\n\
+             Setup\n-----\n\nSetup text.\n\nDetails\n\nDetail text.\n"
+        );
+    }
+
+    #[test]
+    fn test_lists() {
+        let source = "* one\n** nested item with more words\n* two\n\n\
+                      term:: described here\n\n. first\n. second\n";
+        assert_eq!(
+            text(source, &TextOptions::new().width(16).bullets(["•", "◦"])),
+            "• one\n  ◦ nested item\n    with more\n    words\n• two\n\n\
+             term\n    described\n    here\n\n1. first\n2. second\n"
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        let source = ".Example\n----\nfn main() {}\n  indented\n----\n\n\
+                      [quote, Ada]\n____\nQuoted.\n____\n\n\
+                      |===\n|Name |Value\n\n|a |long value\n|===\n\n\
+                      image::diagram.png[Flow]\n";
+        assert_eq!(
+            text(source, &TextOptions::new()),
+            "Example\n    fn main() {}\n      indented\n\n\
+             \x20   Quoted.\n    \u{2014} Ada\n\n\
+             Name  Value\n----  ----------\na     long value\n\n[Flow]\n"
+        );
+    }
+
+    #[test]
+    fn test_admonitions() {
+        let source = "NOTE: Back up.\n\n[TIP]\n====\nOne.\n\nTwo.\n====\n";
+        assert_eq!(
+            text(source, &TextOptions::new()),
+            "Note:\n\n    Back up.\n\nTip:\n\n    One.\n\n    Two.\n"
+        );
+    }
+
+    #[test]
+    fn test_exercises() {
+        let source = ".Sum\n[exercise]\n====\nAdd 2 and 3.\n====\n\n[solution]\n====\n5\n====\n";
+        assert_eq!(
+            text(source, &TextOptions::new()),
+            "Exercise 1. Sum\n\n    Add 2 and 3.\n\nSolution 1\n\n    5\n"
+        );
+    }
+
+    #[test]
+    fn test_inlines_and_footnotes() {
+        let source = "[[top]]\n== Top\n\nSee https://example.com[the *site*], \
+                      <<top>> and a note.footnote:[Details.]\n";
+        assert_eq!(
+            text(source, &TextOptions::new().section_indent(0)),
+            "Top\n---\n\nSee the site <https://example.com>, Top and a note.[1]\n\n\
+             [1] Details.\n"
+        );
+    }
+
+    #[test]
+    fn test_processor() {
+        let registry = crate::processor::ProcessorRegistry::with_builtins();
+        let doc = parse_document("Hi.").unwrap();
+        assert_eq!(
+            registry.convert(&doc, "txt").unwrap(),
+            Output::Text("Hi.\n".to_string())
+        );
+    }
+}
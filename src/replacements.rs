@@ -0,0 +1,300 @@
+//! Textual replacements: typographic characters for their ASCII spellings
+//!
+//! The `replacements` substitution group turns the ASCII spellings authors
+//! type into the characters they stand for, like Asciidoctor:
+//!
+//! | Source         | Result                                            |
+//! |----------------|---------------------------------------------------|
+//! | `(C)`          | `©`                                               |
+//! | `(R)`          | `®`                                               |
+//! | `(TM)`         | `™`                                               |
+//! | `a--b`         | `—` and a zero-width space, between word characters |
+//! | `a -- b`       | `—` between thin spaces, for a spaced `--`        |
+//! | `...`          | `…` and a zero-width space                        |
+//! | `->`, `=>`     | `→`, `⇒`                                          |
+//! | `<-`, `<=`     | `←`, `⇐`                                          |
+//! | `it's`         | `’` between word characters                       |
+//!
+//! A backslash in front keeps the spelling as written (`\(C)` is `(C)`).
+//! [`apply_replacements`] rewrites the text of every block whose
+//! substitutions include `replacements` (see [`crate::subs`]), so listing
+//! blocks and blocks with `subs="-replacements"` keep their text. Run it
+//! after [`substitute_attributes`](crate::resolve::substitute_attributes), so
+//! replacements apply to attribute values too, and before
+//! [`mark_break_hints`](crate::hints::mark_break_hints), the other half of
+//! the group; [`ParseOptions::replacements`] does so.
+//!
+//! [`ParseOptions::replacements`]: crate::options::ParseOptions::replacements
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::ast::{Block, Inline};
+//! use doctora::parse_document;
+//! use doctora::replacements::apply_replacements;
+//!
+//! let mut doc = parse_document("(C) 2024 Ada's Tools -- all rights reserved...\n").unwrap();
+//! apply_replacements(&mut doc);
+//!
+//! let Block::Paragraph { content, .. } = &doc.blocks[0] else {
+//!     panic!("expected a paragraph");
+//! };
+//! assert_eq!(
+//!     content,
+//!     &vec![Inline::Text(
+//!         "© 2024 Ada’s Tools\u{2009}—\u{2009}all rights reserved…\u{200B}".to_string()
+//!     )]
+//! );
+//! ```
+
+use crate::ast::{Block, Document, Inline};
+use crate::resolve::substitutions;
+use crate::subs::{SubstitutionDefaults, Substitutions};
+
+/// Spellings replaced wherever they appear, with their replacements
+const FIXED: [(&str, &str); 8] = [
+    ("(C)", "\u{00A9}"),
+    ("(R)", "\u{00AE}"),
+    ("(TM)", "\u{2122}"),
+    ("...", "\u{2026}\u{200B}"),
+    ("->", "\u{2192}"),
+    ("=>", "\u{21D2}"),
+    ("<-", "\u{2190}"),
+    ("<=", "\u{21D0}"),
+];
+
+/// Apply the replacements throughout `doc` (see the module documentation)
+pub fn apply_replacements(doc: &mut Document) {
+    apply_replacements_with(doc, &SubstitutionDefaults::new());
+}
+
+/// [`apply_replacements`] with the default substitutions of block kinds
+/// overridden by `defaults`
+pub(crate) fn apply_replacements_with(doc: &mut Document, defaults: &SubstitutionDefaults) {
+    replace_blocks(&mut doc.blocks, defaults);
+}
+
+/// `text` with the replacements applied, or `None` if it has none
+///
+/// # Examples
+///
+/// ```
+/// use doctora::replacements::replace_text;
+///
+/// assert_eq!(replace_text("a -> b").as_deref(), Some("a \u{2192} b"));
+/// assert_eq!(replace_text(r"\(C) -").as_deref(), Some("(C) -"));
+/// assert_eq!(replace_text("a - b"), None);
+/// ```
+pub fn replace_text(text: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut changed = false;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let before = text[..text.len() - rest.len()].chars().next_back();
+        if c == '\\'
+            && let Some(len) = spelling_at(&rest[1..])
+        {
+            out.push_str(&rest[1..1 + len]);
+            rest = &rest[1 + len..];
+            changed = true;
+            continue;
+        }
+        if let Some((spelling, replacement)) = FIXED.iter().find(|(s, _)| rest.starts_with(s)) {
+            out.push_str(replacement);
+            rest = &rest[spelling.len()..];
+            changed = true;
+            continue;
+        }
+        if let Some(after) = rest.strip_prefix("--") {
+            let next = after.chars().next();
+            if before.is_some_and(is_word_char) && next.is_some_and(is_word_char) {
+                out.push_str("\u{2014}\u{200B}");
+                rest = after;
+                changed = true;
+                continue;
+            }
+            if before.is_none_or(|c| c == ' ') && next.is_none_or(|c| c == ' ') {
+                // The spaces around the dash become thin spaces
+                if out.ends_with(' ') {
+                    out.pop();
+                }
+                out.push_str("\u{2009}\u{2014}\u{2009}");
+                rest = after.strip_prefix(' ').unwrap_or(after);
+                changed = true;
+                continue;
+            }
+        }
+        if c == '\''
+            && before.is_some_and(is_word_char)
+            && rest[1..].chars().next().is_some_and(is_word_char)
+        {
+            out.push('\u{2019}');
+            rest = &rest[1..];
+            changed = true;
+            continue;
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    changed.then_some(out)
+}
+
+/// Length of the replaceable spelling at the start of `text`, if any
+fn spelling_at(text: &str) -> Option<usize> {
+    FIXED
+        .iter()
+        .map(|(spelling, _)| *spelling)
+        .chain(["--"])
+        .find(|spelling| text.starts_with(spelling))
+        .map(str::len)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn replace_blocks(blocks: &mut [Block], defaults: &SubstitutionDefaults) {
+    for block in blocks {
+        replace_block(block, defaults);
+    }
+}
+
+fn replace_block(block: &mut Block, defaults: &SubstitutionDefaults) {
+    let enabled = substitutions(block, defaults).contains(Substitutions::REPLACEMENTS);
+    let inlines = |content: &mut Vec<Inline>| {
+        if enabled {
+            replace_inlines(content);
+        }
+    };
+    match block {
+        Block::Paragraph { content, .. } | Block::Verse { content, .. } => inlines(content),
+        Block::UnorderedList { items, .. } | Block::OrderedList { items, .. } => {
+            for item in items {
+                inlines(&mut item.content);
+                replace_blocks(&mut item.blocks, defaults);
+            }
+        }
+        Block::DescriptionList { items, .. } => {
+            for item in items {
+                inlines(&mut item.term);
+                inlines(&mut item.description);
+            }
+        }
+        Block::Table { header, rows, .. } => {
+            for row in header.iter_mut().chain(rows) {
+                for cell in &mut row.cells {
+                    inlines(&mut cell.content);
+                }
+            }
+        }
+        Block::Section { title, content, .. } => {
+            inlines(title);
+            replace_blocks(content, defaults);
+        }
+        Block::Quote { content, .. }
+        | Block::Admonition { content, .. }
+        | Block::Example { content, .. }
+        | Block::Sidebar { content, .. }
+        | Block::Open { content, .. } => replace_blocks(content, defaults),
+        Block::Listing { .. }
+        | Block::Image { .. }
+        | Block::Passthrough { .. }
+        | Block::Comment { .. }
+        | Block::AttributeEntry { .. }
+        | Block::Unknown { .. } => {}
+    }
+    // Titles get the normal substitutions, whatever the block's `subs`
+    if let Some(title) = &mut block.metadata_mut().title {
+        replace_inlines(title);
+    }
+}
+
+fn replace_inlines(content: &mut [Inline]) {
+    for inline in content {
+        match inline {
+            Inline::Text(text) => {
+                if let Some(replaced) = replace_text(text) {
+                    *text = replaced;
+                }
+            }
+            Inline::Bold(nested)
+            | Inline::Italic(nested)
+            | Inline::Monospace(nested)
+            | Inline::Highlight(nested)
+            | Inline::Span {
+                content: nested, ..
+            }
+            | Inline::Superscript(nested)
+            | Inline::Subscript(nested)
+            | Inline::Link { text: nested, .. }
+            | Inline::XRef { text: nested, .. }
+            | Inline::Footnote { text: nested, .. } => replace_inlines(nested),
+            Inline::Anchor(_)
+            | Inline::Image { .. }
+            | Inline::Icon { .. }
+            | Inline::BreakHint(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    #[test]
+    fn test_replace_text() {
+        for (text, expected) in [
+            ("(C) (R) (TM)", "\u{a9} \u{ae} \u{2122}"),
+            ("a--b", "a\u{2014}\u{200B}b"),
+            ("a -- b", "a\u{2009}\u{2014}\u{2009}b"),
+            (
+                "-- a --",
+                "\u{2009}\u{2014}\u{2009}a\u{2009}\u{2014}\u{2009}",
+            ),
+            ("Wait...", "Wait\u{2026}\u{200B}"),
+            (
+                "a -> b => c <- d <= e",
+                "a \u{2192} b \u{21D2} c \u{2190} d \u{21D0} e",
+            ),
+            ("it's 'quoted'", "it\u{2019}s 'quoted'"),
+            (r"\(C) \-- a\--b \...", "(C) -- a--b ..."),
+        ] {
+            assert_eq!(replace_text(text).as_deref(), Some(expected), "{text}");
+        }
+        for text in ["a - b", "a---", "x --y", "(c)", "'a", "a\\b"] {
+            assert_eq!(replace_text(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_replacements_follow_subs() {
+        let mut doc = parse_document(
+            ".A (C)\n* *a (C)*\n\n[subs=\"-replacements\"]\n(C)\n\n----\n(C)\n----\n\n\
+             |===\n|a->b\n|===\n",
+        )
+        .unwrap();
+        let before = doc.blocks[1..3].to_vec();
+        apply_replacements(&mut doc);
+
+        let Block::UnorderedList { items, metadata } = &doc.blocks[0] else {
+            panic!("expected a list, got {:?}", doc.blocks[0]);
+        };
+        assert_eq!(
+            metadata.title,
+            Some(vec![Inline::Text("A \u{a9}".to_string())])
+        );
+        assert_eq!(
+            items[0].content,
+            vec![Inline::Bold(vec![Inline::Text("a \u{a9}".to_string())])]
+        );
+        assert_eq!(doc.blocks[1..3], before);
+        let Block::Table { rows, .. } = &doc.blocks[3] else {
+            panic!("expected a table, got {:?}", doc.blocks[3]);
+        };
+        assert_eq!(
+            rows[0].cells[0].content,
+            vec![Inline::Text("a\u{2192}b".to_string())]
+        );
+    }
+}
@@ -0,0 +1,504 @@
+//! Attribute reference substitution over the parsed AST
+//!
+//! The parser keeps attribute references (`{version}`) in the text as
+//! written, so tools that edit or lint the source see what the author typed.
+//! [`substitute_attributes`] is the pass that replaces them with values from
+//! [`Document::attributes`] before conversion, in paragraph text, section
+//! titles, list items, table cells, image targets and alt text, and the
+//! other places where AsciiDoc applies the `attributes` substitution group.
+//!
+//! The pass follows each block's substitutions: its kind's defaults, changed
+//! by a `subs` attribute where the block has an attribute list (see
+//! [`crate::subs`]). So listing blocks stay verbatim unless they ask for
+//! `subs="attributes+"`. Nested blocks follow their own substitutions.
+//!
+//! # Values
+//!
+//! References resolve against the document attributes: those supplied
+//! through [`ParseOptions`](crate::options::ParseOptions) (including
+//! `docdate` and the other date attributes), then header attribute entries
+//! and the attributes derived from the header (`doctitle`, `author`,
+//! `revnumber`, ...). An entry such as `:draft!:` unsets an attribute, and
+//! references to it are left alone. After those come the intrinsic
+//! character attributes ([`intrinsic`]), such as `{nbsp}` and `{startsb}`.
+//!
+//! An attribute entry after the header (`:name: value` on a line of its
+//! own, [`Block::AttributeEntry`]) changes the value from that point on:
+//! references before it still see the earlier value.
+//!
+//! References to undefined attributes are kept verbatim, and `\{name}`
+//! yields the literal `{name}`.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::ast::{Block, BlockMetadata, Inline};
+//! use doctora::options::ParseOptions;
+//! use doctora::parse_document_with_options;
+//!
+//! let source = "= Guide\n:version: 1.2\n\nThe {doctitle} for v{version}.\n";
+//! let options = ParseOptions::new().substitute_attributes(true);
+//! let doc = parse_document_with_options(source, &options).unwrap();
+//!
+//! assert_eq!(
+//!     doc.blocks[0],
+//!     Block::Paragraph {
+//!         content: vec![Inline::Text("The Guide for v1.2.".to_string())],
+//!         metadata: BlockMetadata::default(),
+//!     }
+//! );
+//! ```
+
+use crate::ast::{Block, Document, Inline};
+use crate::attributes::{
+    AttributeError, Attributes, DEFAULT_MAX_ATTRIBUTE_LENGTH, interpolate_with,
+};
+use crate::subs::{SubstitutionDefaults, Substitutions};
+
+/// Character replacement attributes that are always defined
+const INTRINSICS: [(&str, &str); 29] = [
+    ("empty", ""),
+    ("blank", ""),
+    ("sp", " "),
+    ("nbsp", "\u{00A0}"),
+    ("zwsp", "\u{200B}"),
+    ("wj", "\u{2060}"),
+    ("apos", "'"),
+    ("quot", "\""),
+    ("lsquo", "\u{2018}"),
+    ("rsquo", "\u{2019}"),
+    ("ldquo", "\u{201C}"),
+    ("rdquo", "\u{201D}"),
+    ("deg", "\u{00B0}"),
+    ("plus", "+"),
+    ("brvbar", "\u{00A6}"),
+    ("vbar", "|"),
+    ("amp", "&"),
+    ("lt", "<"),
+    ("gt", ">"),
+    ("startsb", "["),
+    ("endsb", "]"),
+    ("caret", "^"),
+    ("asterisk", "*"),
+    ("tilde", "~"),
+    ("backslash", "\\"),
+    ("backtick", "`"),
+    ("two-colons", "::"),
+    ("two-semicolons", ";;"),
+    ("cpp", "C++"),
+];
+
+/// Value of the intrinsic attribute `name`, if there is one
+///
+/// These are Asciidoctor's character replacement attributes, which let
+/// authors write characters that would otherwise be read as markup
+/// (`{startsb}` for `[`, `{plus}` for `+`). Document attributes of the same
+/// name take precedence.
+pub fn intrinsic(name: &str) -> Option<&'static str> {
+    INTRINSICS
+        .iter()
+        .find(|(intrinsic, _)| *intrinsic == name)
+        .map(|(_, value)| *value)
+}
+
+/// Replace attribute references throughout `doc` (see the module
+/// documentation)
+///
+/// # Errors
+///
+/// Returns [`AttributeError::TooLong`] if an attribute entry in the body
+/// resolves to more than [`DEFAULT_MAX_ATTRIBUTE_LENGTH`] bytes. The blocks
+/// before the entry have been substituted.
+pub fn substitute_attributes(doc: &mut Document) -> Result<(), AttributeError> {
+    substitute_attributes_with(
+        doc,
+        &SubstitutionDefaults::new(),
+        |_| false,
+        Some(DEFAULT_MAX_ATTRIBUTE_LENGTH),
+    )
+}
+
+/// [`substitute_attributes`] with the default substitutions of block kinds
+/// overridden by `defaults`
+///
+/// Attribute entries in the body leave attributes for which `locked`
+/// returns true unchanged, like those in the header, and define values of
+/// at most `max_length` bytes.
+pub(crate) fn substitute_attributes_with(
+    doc: &mut Document,
+    defaults: &SubstitutionDefaults,
+    locked: impl Fn(&str) -> bool,
+    max_length: Option<usize>,
+) -> Result<(), AttributeError> {
+    let mut resolver = Resolver {
+        attributes: doc.attributes.clone(),
+        defaults,
+        locked: &locked,
+        max_length,
+        error: None,
+    };
+    resolver.blocks(&mut doc.blocks);
+    resolver.error.map_or(Ok(()), Err)
+}
+
+/// Substitutions that apply to `block`'s own content
+///
+/// The `subs` attribute is read from the block's attribute list, whatever
+/// the block kind, and applies relative to the kind's entry in `defaults`.
+/// An invalid one leaves the defaults in place.
+pub(crate) fn substitutions(block: &Block, defaults: &SubstitutionDefaults) -> Substitutions {
+    let defaults = defaults.get(block);
+    block
+        .metadata()
+        .named
+        .get("subs")
+        .map(String::as_str)
+        .or_else(|| block.attributes()?.get("subs"))
+        .and_then(|spec| Substitutions::parse(spec, defaults).ok())
+        .unwrap_or(defaults)
+}
+
+/// Walks the blocks in document order, so attribute entries in the body
+/// apply to the blocks after them
+struct Resolver<'a> {
+    /// The attributes defined at the current block
+    attributes: Attributes,
+    defaults: &'a SubstitutionDefaults,
+    locked: &'a dyn Fn(&str) -> bool,
+    max_length: Option<usize>,
+    /// The first attribute entry that failed to resolve; the walk stops there
+    error: Option<AttributeError>,
+}
+
+impl Resolver<'_> {
+    fn blocks(&mut self, blocks: &mut [Block]) {
+        for block in blocks {
+            if self.error.is_some() {
+                return;
+            }
+            self.block(block);
+        }
+    }
+
+    fn block(&mut self, block: &mut Block) {
+        let enabled = substitutions(block, self.defaults).contains(Substitutions::ATTRIBUTES);
+        match block {
+            Block::Section { title, content, .. } => {
+                self.inlines(enabled, title);
+                self.blocks(content);
+            }
+            Block::Paragraph { content, .. } | Block::Verse { content, .. } => {
+                self.inlines(enabled, content);
+            }
+            Block::UnorderedList { items, .. } | Block::OrderedList { items, .. } => {
+                for item in items {
+                    self.inlines(enabled, &mut item.content);
+                    self.blocks(&mut item.blocks);
+                }
+            }
+            Block::DescriptionList { items, .. } => {
+                for item in items {
+                    self.inlines(enabled, &mut item.term);
+                    self.inlines(enabled, &mut item.description);
+                }
+            }
+            Block::Listing { content, .. } => self.text(enabled, content),
+            Block::Passthrough { .. } | Block::Comment { .. } | Block::Unknown { .. } => {}
+            Block::AttributeEntry { entry, .. } => {
+                if !(self.locked)(&entry.name) {
+                    match &entry.value {
+                        Some(value) => {
+                            if let Err(err) = self.attributes.define_within(
+                                entry.name.clone(),
+                                value,
+                                self.max_length,
+                            ) {
+                                self.error = Some(err);
+                            }
+                        }
+                        None => {
+                            self.attributes.unset(&entry.name);
+                        }
+                    }
+                }
+            }
+            Block::Table { header, rows, .. } => {
+                for row in header.iter_mut().chain(rows) {
+                    for cell in &mut row.cells {
+                        self.inlines(enabled, &mut cell.content);
+                    }
+                }
+            }
+            Block::Quote {
+                attribution,
+                citation,
+                content,
+                ..
+            } => {
+                for text in attribution.iter_mut().chain(citation) {
+                    self.text(enabled, text);
+                }
+                self.blocks(content);
+            }
+            Block::Admonition { content, .. }
+            | Block::Example { content, .. }
+            | Block::Sidebar { content, .. }
+            | Block::Open { content, .. } => self.blocks(content),
+            Block::Image {
+                target,
+                attributes: own,
+                ..
+            } => {
+                if enabled {
+                    substitute_image(target, own, &self.attributes);
+                }
+            }
+        }
+        if let Block::Verse {
+            attribution,
+            citation,
+            ..
+        } = block
+        {
+            for text in attribution.iter_mut().chain(citation) {
+                self.text(enabled, text);
+            }
+        }
+        // Titles get the normal substitutions, whatever the block's `subs`
+        if let Some(title) = &mut block.metadata_mut().title {
+            substitute_inlines(title, &self.attributes);
+        }
+    }
+
+    fn text(&self, enabled: bool, text: &mut String) {
+        if enabled {
+            *text = substitute(text, &self.attributes);
+        }
+    }
+
+    fn inlines(&self, enabled: bool, content: &mut [Inline]) {
+        if enabled {
+            substitute_inlines(content, &self.attributes);
+        }
+    }
+}
+
+pub(crate) fn substitute_inlines(content: &mut [Inline], attributes: &Attributes) {
+    for inline in content {
+        match inline {
+            Inline::Text(text) => *text = substitute(text, attributes),
+            Inline::Bold(nested)
+            | Inline::Italic(nested)
+            | Inline::Monospace(nested)
+            | Inline::Highlight(nested)
+            | Inline::Span {
+                content: nested, ..
+            }
+            | Inline::Superscript(nested)
+            | Inline::Subscript(nested)
+            | Inline::XRef { text: nested, .. }
+            | Inline::Footnote { text: nested, .. } => substitute_inlines(nested, attributes),
+            Inline::Link { target, text, .. } => {
+                *target = substitute(target, attributes);
+                substitute_inlines(text, attributes);
+            }
+            Inline::Image {
+                target,
+                attributes: own,
+            } => substitute_image(target, own, attributes),
+            Inline::Icon { name, .. } => *name = substitute(name, attributes),
+            Inline::Anchor(_) | Inline::BreakHint(_) => {}
+        }
+    }
+}
+
+/// Substitute the target and alt text of an image macro
+fn substitute_image(target: &mut String, own: &mut Attributes, attributes: &Attributes) {
+    *target = substitute(target, attributes);
+    if let Some(alt) = own.get("alt") {
+        let alt = substitute(alt, attributes);
+        own.set("alt", alt);
+    }
+}
+
+/// Replace the references in `text`, falling back to intrinsic attributes
+fn substitute(text: &str, attributes: &Attributes) -> String {
+    if !text.contains('{') {
+        return text.to_string();
+    }
+    interpolate_with(text, |name| {
+        attributes
+            .get(name)
+            .or_else(|| intrinsic(name))
+            .map(str::to_string)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{BlockMetadata, plain_text};
+    use crate::options::ParseOptions;
+
+    fn resolved(source: &str) -> Document {
+        let options = ParseOptions::new().substitute_attributes(true);
+        crate::parse_document_with_options(source, &options).unwrap()
+    }
+
+    fn paragraph(text: &str) -> Block {
+        Block::Paragraph {
+            content: vec![Inline::Text(text.to_string())],
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn test_paragraphs_and_titles() {
+        let doc = resolved(
+            "= Manual\n:product: Engine\n:draft!:\n\n== {product} setup\n\n\
+             *{product}* is {draft}, see \\{product} and {startsb}1{endsb}.\n",
+        );
+        let Block::Section { title, content, .. } = &doc.blocks[0] else {
+            panic!("expected a section, got {:?}", doc.blocks[0]);
+        };
+        assert_eq!(plain_text(title), "Engine setup");
+        assert_eq!(
+            content[0],
+            Block::Paragraph {
+                content: vec![
+                    Inline::Bold(vec![Inline::Text("Engine".to_string())]),
+                    Inline::Text(" is {draft}, see {product} and [1].".to_string()),
+                ],
+                metadata: BlockMetadata::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_listing_follows_subs() {
+        let doc = resolved(
+            ":v: 2\n\n----\nv{v}\n----\n\n[source,subs=\"attributes+\"]\n----\nv{v}\n----\n",
+        );
+        let contents: Vec<&str> = doc
+            .blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Listing { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(contents, ["v{v}", "v2"]);
+    }
+
+    #[test]
+    fn test_paragraph_follows_subs() {
+        let doc = resolved(":x: val\n\n[subs=\"-attributes\"]\nHello {x}\n\nHello {x}\n");
+        let texts: Vec<String> = doc
+            .blocks
+            .iter()
+            .map(|block| match block {
+                Block::Paragraph { content, .. } => plain_text(content),
+                other => panic!("expected a paragraph, got {other:?}"),
+            })
+            .collect();
+        assert_eq!(texts, ["Hello {x}", "Hello val"]);
+
+        let html = crate::render::html::render_html(&doc, &Default::default());
+        assert!(html.contains("<p>Hello {x}</p>"), "{html}");
+    }
+
+    #[test]
+    fn test_substitution_defaults() {
+        let options = ParseOptions::new()
+            .substitute_attributes(true)
+            .substitutions(
+                "paragraph",
+                Substitutions::NORMAL - Substitutions::ATTRIBUTES,
+            );
+        let doc = crate::parse_document_with_options(
+            ":x: val\n\nHello {x}\n\n[subs=\"+attributes\"]\nHello {x}\n\n* Item {x}\n",
+            &options,
+        )
+        .unwrap();
+        let texts: Vec<String> = doc.blocks[..2]
+            .iter()
+            .map(|block| match block {
+                Block::Paragraph { content, .. } => plain_text(content),
+                other => panic!("expected a paragraph, got {other:?}"),
+            })
+            .collect();
+        assert_eq!(texts, ["Hello {x}", "Hello val"]);
+        let Block::UnorderedList { items, .. } = &doc.blocks[2] else {
+            panic!("expected a list, got {:?}", doc.blocks[2]);
+        };
+        assert_eq!(items[0].content, vec![Inline::Text("Item val".to_string())]);
+    }
+
+    #[test]
+    fn test_image_targets() {
+        let doc = resolved(
+            ":dir: img\n:v: 2\n\nimage::{dir}/a.png[Version {v}]\n\nimage:{dir}/b.png[]\n",
+        );
+        let Block::Image {
+            target, attributes, ..
+        } = &doc.blocks[0]
+        else {
+            panic!("expected an image, got {:?}", doc.blocks[0]);
+        };
+        assert_eq!(target, "img/a.png");
+        assert_eq!(attributes.get("alt"), Some("Version 2"));
+        let Block::Paragraph { content, .. } = &doc.blocks[1] else {
+            panic!("expected a paragraph, got {:?}", doc.blocks[1]);
+        };
+        assert!(matches!(&content[0], Inline::Image { target, .. } if target == "img/b.png"));
+    }
+
+    #[test]
+    fn test_nested_blocks() {
+        let doc = resolved(":who: Ada\n\n====\n* Hi {who}\n\n|===\n|{who}\n|===\n====\n");
+        let Block::Example { content, .. } = &doc.blocks[0] else {
+            panic!("expected an example, got {:?}", doc.blocks[0]);
+        };
+        let Block::UnorderedList { items, .. } = &content[0] else {
+            panic!("expected a list, got {:?}", content[0]);
+        };
+        assert_eq!(items[0].content, vec![Inline::Text("Hi Ada".to_string())]);
+        let Block::Table { rows, .. } = &content[1] else {
+            panic!("expected a table, got {:?}", content[1]);
+        };
+        assert_eq!(
+            rows[0].cells[0].content,
+            vec![Inline::Text("Ada".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_body_attribute_entries() {
+        let doc = resolved(
+            ":v: 1\n\nEarly {late} v{v}.\n\n:late: x\n:v!:\n\n== {late}\n\n:late: y{late}\nNow {late} v{v}.\n",
+        );
+        assert_eq!(doc.blocks[0], paragraph("Early {late} v1."));
+        let Block::Section { title, content, .. } = &doc.blocks[3] else {
+            panic!("expected a section, got {:?}", doc.blocks[3]);
+        };
+        assert_eq!(plain_text(title), "x");
+        assert_eq!(content[1], paragraph("Now yx v{v}."));
+        // The document attributes are those of the header
+        assert_eq!(doc.attributes.get("late"), None);
+        assert_eq!(doc.attributes.get("v"), Some("1"));
+
+        // Entries do not override attributes set through the options
+        let options = ParseOptions::new()
+            .substitute_attributes(true)
+            .attribute("v", "api");
+        let doc =
+            crate::parse_document_with_options("Para.\n\n:v: doc\n\n{v}\n", &options).unwrap();
+        assert_eq!(doc.blocks[2], paragraph("api"));
+    }
+
+    #[test]
+    fn test_document_attributes_shadow_intrinsics() {
+        let doc = resolved(":sp: space\n\n{sp}|{nbsp}|{missing}\n");
+        assert_eq!(doc.blocks[0], paragraph("space|\u{00A0}|{missing}"));
+    }
+}
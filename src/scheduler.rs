@@ -0,0 +1,334 @@
+//! Memory-aware parallel batch parsing
+//!
+//! Bounding a parallel batch by thread count alone is not enough when a
+//! corpus mixes tiny pages with a few huge generated reference files: eight
+//! threads that happen to pick up eight 50 MB files at once can run the
+//! build out of memory. A [`Scheduler`] bounds concurrent parses by their
+//! estimated memory instead, with the thread count as a second limit.
+//!
+//! A parse is estimated to need [`Scheduler::expansion`] bytes per byte of
+//! source (the AST is several times larger than its source). Documents start
+//! in input order, each as soon as a thread is free and its estimate fits in
+//! what remains of [`Scheduler::memory_budget`]. A document whose estimate
+//! exceeds the whole budget runs on its own, so every batch completes.
+//!
+//! Results are the same as [`parse_batch`](crate::batch::parse_batch) would
+//! return for the same inputs, in input order. Under
+//! [`FailurePolicy::Abort`] no new documents start after a failure; the
+//! error is the first failing document in input order.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::batch::FailurePolicy;
+//! use doctora::scheduler::Scheduler;
+//!
+//! let inputs = [("a.adoc", "First."), ("b.adoc", "Second.")];
+//! let scheduler = Scheduler::new().threads(4).memory_budget(64 << 20);
+//! let result = scheduler.parse_batch(inputs, FailurePolicy::Abort).unwrap();
+//!
+//! assert_eq!(result.documents[1].name, "b.adoc");
+//! assert_eq!(scheduler.estimate("First."), 6 * 16);
+//! ```
+
+use crate::ast::Document;
+use crate::batch::{BatchError, BatchResult, FailurePolicy, parse_observed};
+use crate::events::{NoopSubscriber, Subscriber};
+use std::sync::{Condvar, Mutex, PoisonError};
+use std::thread;
+
+/// Default memory budget: 1 GiB
+const DEFAULT_MEMORY_BUDGET: usize = 1 << 30;
+
+/// Default estimated bytes of memory per byte of source
+const DEFAULT_EXPANSION: usize = 16;
+
+/// Runs batch parses in parallel within a memory budget
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scheduler {
+    /// Maximum number of concurrent parses
+    threads: usize,
+    /// Total estimated memory of concurrent parses, in bytes
+    memory_budget: usize,
+    /// Estimated bytes of memory per byte of source
+    expansion: usize,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self {
+            threads: thread::available_parallelism().map_or(1, usize::from),
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            expansion: DEFAULT_EXPANSION,
+        }
+    }
+}
+
+impl Scheduler {
+    /// Creates a scheduler with one thread per available CPU and a 1 GiB
+    /// budget
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of concurrent parses (at least 1)
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Sets the total estimated memory of concurrent parses, in bytes
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = bytes;
+        self
+    }
+
+    /// Sets the estimated bytes of memory a parse needs per byte of source
+    pub fn expansion(mut self, factor: usize) -> Self {
+        self.expansion = factor;
+        self
+    }
+
+    /// Estimated memory needed to parse `source`, in bytes
+    pub fn estimate(&self, source: &str) -> usize {
+        source.len().saturating_mul(self.expansion)
+    }
+
+    /// Parse `(name, source)` pairs in parallel, handling failures according
+    /// to `policy`
+    ///
+    /// # Errors
+    ///
+    /// Same as [`parse_batch`](crate::batch::parse_batch).
+    pub fn parse_batch<I, N, S>(
+        &self,
+        inputs: I,
+        policy: FailurePolicy,
+    ) -> Result<BatchResult, BatchError>
+    where
+        I: IntoIterator<Item = (N, S)>,
+        N: Into<String>,
+        S: AsRef<str> + Sync,
+    {
+        self.parse_batch_observed(inputs, policy, &NoopSubscriber)
+    }
+
+    /// Like [`Scheduler::parse_batch`], reporting each document's progress to
+    /// `subscriber`
+    ///
+    /// Each document's events arrive in the order described for
+    /// [`parse_batch_observed`](crate::batch::parse_batch_observed), but
+    /// events of documents parsed at the same time interleave.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`parse_batch`](crate::batch::parse_batch).
+    pub fn parse_batch_observed<I, N, S>(
+        &self,
+        inputs: I,
+        policy: FailurePolicy,
+        subscriber: &dyn Subscriber,
+    ) -> Result<BatchResult, BatchError>
+    where
+        I: IntoIterator<Item = (N, S)>,
+        N: Into<String>,
+        S: AsRef<str> + Sync,
+    {
+        let inputs: Vec<(String, S)> = inputs
+            .into_iter()
+            .map(|(name, source)| (name.into(), source))
+            .collect();
+        let costs: Vec<usize> = inputs
+            .iter()
+            .map(|(_, source)| self.estimate(source.as_ref()))
+            .collect();
+        let outcomes: Vec<Mutex<Option<Result<Document, String>>>> =
+            inputs.iter().map(|_| Mutex::new(None)).collect();
+        let quota = Quota::new(self.memory_budget, policy == FailurePolicy::Abort);
+
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(inputs.len()) {
+                scope.spawn(|| {
+                    while let Some(index) = quota.acquire(&costs) {
+                        let (name, source) = &inputs[index];
+                        let parsed = parse_observed(name, source.as_ref(), subscriber);
+                        let failed = parsed.is_err();
+                        *lock(&outcomes[index]) = Some(parsed);
+                        quota.release(costs[index], failed);
+                    }
+                });
+            }
+        });
+
+        let mut result = BatchResult::default();
+        for ((name, _), outcome) in inputs.into_iter().zip(outcomes) {
+            // Documents after an abort never started
+            let Some(parsed) = outcome.into_inner().unwrap_or_else(PoisonError::into_inner) else {
+                break;
+            };
+            result.record(name, parsed, policy)?;
+        }
+        Ok(result)
+    }
+}
+
+/// Lock `mutex`, ignoring poisoning (parser panics are caught before they
+/// could poison it)
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Shared progress of a scheduled batch
+#[derive(Debug)]
+struct Quota {
+    budget: usize,
+    /// Stop starting documents after a failure
+    abort_on_failure: bool,
+    state: Mutex<QuotaState>,
+    /// Signalled when a running parse finishes
+    released: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct QuotaState {
+    /// Index of the next document to start
+    next: usize,
+    /// Estimated memory of the running parses
+    in_use: usize,
+    /// Set once a document failed under [`FailurePolicy::Abort`]
+    stopped: bool,
+}
+
+impl Quota {
+    fn new(budget: usize, abort_on_failure: bool) -> Self {
+        Self {
+            budget,
+            abort_on_failure,
+            state: Mutex::new(QuotaState::default()),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Wait until the next document fits in the budget and claim it, or
+    /// return `None` when there is nothing left to start
+    fn acquire(&self, costs: &[usize]) -> Option<usize> {
+        let mut state = lock(&self.state);
+        loop {
+            if state.stopped || state.next == costs.len() {
+                return None;
+            }
+            let cost = costs[state.next];
+            // An oversized document runs once nothing else does
+            if state.in_use == 0 || state.in_use.saturating_add(cost) <= self.budget {
+                let index = state.next;
+                state.next += 1;
+                state.in_use += cost;
+                return Some(index);
+            }
+            state = self
+                .released
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Return the memory of a finished parse
+    fn release(&self, cost: usize, failed: bool) {
+        let mut state = lock(&self.state);
+        state.in_use -= cost;
+        state.stopped |= failed && self.abort_on_failure;
+        self.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::parse_batch;
+    use crate::events::Event;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const INPUTS: [(&str, &str); 4] = [
+        ("a.adoc", "First."),
+        ("bad.adoc", "= No newline"),
+        ("c.adoc", "Third."),
+        ("d.adoc", "Fourth."),
+    ];
+
+    #[test]
+    fn test_matches_sequential_batch() {
+        let scheduler = Scheduler::new().threads(3);
+        for policy in [FailurePolicy::Skip, FailurePolicy::Stub] {
+            assert_eq!(
+                scheduler.parse_batch(INPUTS, policy),
+                parse_batch(INPUTS, policy)
+            );
+        }
+
+        let err = scheduler
+            .parse_batch(INPUTS, FailurePolicy::Abort)
+            .unwrap_err();
+        assert_eq!(err, parse_batch(INPUTS, FailurePolicy::Abort).unwrap_err());
+    }
+
+    #[test]
+    fn test_memory_budget_bounds_concurrency() {
+        let sources: Vec<(String, String)> = (0..12)
+            .map(|i| {
+                let size = if i % 4 == 0 { 1000 } else { 10 };
+                (format!("{i}.adoc"), "x".repeat(size))
+            })
+            .collect();
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let big_running = AtomicUsize::new(0);
+        let big_peak = AtomicUsize::new(0);
+        let subscriber = |event: &Event<'_>| {
+            let big = |document: &str| {
+                document.trim_end_matches(".adoc").parse::<usize>().unwrap() % 4 == 0
+            };
+            match event {
+                Event::DocumentStarted { document } => {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    if big(document) {
+                        let now = big_running.fetch_add(1, Ordering::SeqCst) + 1;
+                        big_peak.fetch_max(now, Ordering::SeqCst);
+                    }
+                }
+                Event::ConversionFinished { document, .. } => {
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    if big(document) {
+                        big_running.fetch_sub(1, Ordering::SeqCst);
+                    }
+                }
+                _ => {}
+            }
+        };
+
+        // Room for one large document (1000 bytes) plus a few small ones
+        let scheduler = Scheduler::new().threads(4).expansion(1).memory_budget(1500);
+        let result = scheduler
+            .parse_batch_observed(sources.clone(), FailurePolicy::Abort, &subscriber)
+            .unwrap();
+
+        let names: Vec<&str> = result.documents.iter().map(|d| d.name.as_str()).collect();
+        let expected: Vec<&str> = sources.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, expected);
+        assert_eq!(big_peak.into_inner(), 1);
+        assert!(peak.into_inner() <= 4);
+    }
+
+    #[test]
+    fn test_oversized_document_runs_alone() {
+        let scheduler = Scheduler::new().threads(2).expansion(1).memory_budget(4);
+        let result = scheduler
+            .parse_batch(
+                [("huge.adoc", "Much too large."), ("b.adoc", "B.")],
+                FailurePolicy::Abort,
+            )
+            .unwrap();
+        assert_eq!(result.documents.len(), 2);
+        assert_eq!(Scheduler::new().threads(0).threads, 1);
+    }
+}
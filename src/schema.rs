@@ -0,0 +1,901 @@
+//! Versioned JSON serialization of the AST
+//!
+//! External tools (editors, linters, site generators) consume parsed
+//! documents as JSON. [`to_json`] produces a stable representation whose
+//! layout is described by the JSON Schema in
+//! `docs/schema/ast-v1.schema.json`, and every serialized document carries a
+//! top-level `schema_version`.
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "header": null,
+//!   "attributes": { "author": "Ada" },
+//!   "truncations": [],
+//!   "blocks": [
+//!     { "type": "paragraph", "content": [{ "type": "text", "text": "Hi" }] }
+//!   ]
+//! }
+//! ```
+//!
+//! Blocks and inlines are objects tagged with a snake_case `type`. The JSON
+//! layout is deliberately independent of the Rust type names, so AST
+//! refactorings don't change the output. A section's `title` is its plain
+//! text; the formatted title is in `title_content`. A block written after an
+//! attribute list line (`[source.wide,ruby]`) or a block title (`.Title`) has
+//! a `metadata` object with their parts; other blocks omit it.
+//!
+//! # Content Hashes
+//!
+//! Every block carries a `hash`: 16 hex digits of a 64-bit FNV-1a hash of
+//! the block's serialization (see [`content_hash`]). It changes exactly when
+//! the block's content, metadata, or nested blocks do, so incremental
+//! renderers (a CMS storing rendered fragments, say) can compare hashes
+//! between document versions instead of whole subtrees. Hashes depend only
+//! on the serialized layout, so they are stable across runs and platforms
+//! for the same [`SCHEMA_VERSION`].
+//!
+//! # YAML
+//!
+//! [`to_yaml_string`] writes the same layout as block-style YAML for
+//! readers who prefer it; parsing the YAML gives back exactly [`to_json`]'s
+//! value. Strings that YAML would read as something else (`true`, `1.0`,
+//! `- item`, and under YAML 1.1 `2024-01-01` or `0x1F`) are double-quoted.
+//!
+//! # Compatibility Policy
+//!
+//! - **Additive changes keep the version.** New block or inline `type`s, new
+//!   fields on existing objects, and new attribute names may appear in any
+//!   release. Consumers must ignore unknown fields, and should skip (or
+//!   render generically) objects with an unknown `type`.
+//! - **Breaking changes bump the version.** Removing or renaming a field,
+//!   changing a field's type or meaning, or restructuring a node increments
+//!   [`SCHEMA_VERSION`], publishes a new `ast-vN.schema.json`, and adds a
+//!   migration from the previous version.
+//! - **Older documents can be upgraded.** [`migrate`] upgrades JSON written
+//!   by any earlier schema version to the current one, step by step. JSON
+//!   from a newer version is rejected rather than guessed at.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::parse_document;
+//! use doctora::schema::{SCHEMA_VERSION, migrate, to_json};
+//!
+//! let doc = parse_document("Hello, **world**!").unwrap();
+//! let json = to_json(&doc);
+//! assert_eq!(json["schema_version"], SCHEMA_VERSION);
+//! assert_eq!(json["blocks"][0]["type"], "paragraph");
+//!
+//! // Consumers upgrade stored JSON before reading it
+//! let current = migrate(json.clone()).unwrap();
+//! assert_eq!(current, json);
+//! ```
+
+use crate::ast::{
+    Block, BreakHint, DescriptionItem, Document, DocumentHeader, Inline, ListItem, TableCell,
+    TableRow, plain_text,
+};
+use crate::attributes::Attributes;
+use crate::limits::{StructureKind, Truncation};
+use serde_json::{Map, Value, json};
+use thiserror::Error;
+
+/// Current version of the serialized AST layout
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// An upgrade step from one schema version to the next
+///
+/// Index `i` of [`MIGRATIONS`] upgrades version `i + 1` to `i + 2`. The step
+/// rewrites the document in place; [`migrate`] updates `schema_version`.
+type Migration = fn(&mut Map<String, Value>);
+
+/// Upgrade steps, oldest first (none yet: version 1 is the first release)
+const MIGRATIONS: [Migration; (SCHEMA_VERSION - 1) as usize] = [];
+
+/// Error produced while migrating serialized JSON
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SchemaError {
+    /// The value is not an object with an integer `schema_version` of 1 or more
+    #[error("not a serialized doctora document (missing or invalid `schema_version`)")]
+    MissingVersion,
+
+    /// The document was written by a newer, unknown schema version
+    #[error("schema version {found} is newer than the supported version {supported}")]
+    Unsupported { found: u64, supported: u64 },
+}
+
+/// Serialize `doc` in the current schema version
+pub fn to_json(doc: &Document) -> Value {
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "header": doc.header.as_ref().map(header),
+        "attributes": attributes(&doc.attributes),
+        "truncations": doc.truncations.iter().map(truncation).collect::<Vec<_>>(),
+        "blocks": blocks(&doc.blocks),
+    })
+}
+
+/// Serialize `doc` as pretty-printed JSON text
+pub fn to_json_string(doc: &Document) -> String {
+    serde_json::to_string_pretty(&to_json(doc)).expect("JSON values always serialize")
+}
+
+/// Serialize `doc` in the current schema version as YAML text
+pub fn to_yaml_string(doc: &Document) -> String {
+    yaml(&to_json(doc))
+}
+
+/// Render a JSON value as block-style YAML
+fn yaml(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Object(map) if !map.is_empty() => yaml_mapping(&mut out, map, 0, false),
+        Value::Array(items) if !items.is_empty() => yaml_sequence(&mut out, items, 0),
+        scalar => {
+            out.push_str(&yaml_scalar(scalar));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Write `key: value` lines at `indent`; `inline_first` continues a `- `
+fn yaml_mapping(out: &mut String, map: &Map<String, Value>, indent: usize, inline_first: bool) {
+    for (i, (key, value)) in map.iter().enumerate() {
+        if i > 0 || !inline_first {
+            out.push_str(&" ".repeat(indent));
+        }
+        out.push_str(&yaml_string(key));
+        out.push(':');
+        match value {
+            Value::Object(map) if !map.is_empty() => {
+                out.push('\n');
+                yaml_mapping(out, map, indent + 2, false);
+            }
+            Value::Array(items) if !items.is_empty() => {
+                out.push('\n');
+                yaml_sequence(out, items, indent + 2);
+            }
+            scalar => {
+                out.push(' ');
+                out.push_str(&yaml_scalar(scalar));
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// Write `- item` lines at `indent`, with mappings starting on the dash line
+fn yaml_sequence(out: &mut String, items: &[Value], indent: usize) {
+    for item in items {
+        out.push_str(&" ".repeat(indent));
+        out.push('-');
+        match item {
+            Value::Object(map) if !map.is_empty() => {
+                out.push(' ');
+                yaml_mapping(out, map, indent + 2, true);
+            }
+            Value::Array(items) if !items.is_empty() => {
+                out.push('\n');
+                yaml_sequence(out, items, indent + 2);
+            }
+            scalar => {
+                out.push(' ');
+                out.push_str(&yaml_scalar(scalar));
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// A scalar or empty container in flow style
+fn yaml_scalar(value: &Value) -> String {
+    match value {
+        Value::String(text) => yaml_string(text),
+        Value::Object(_) => "{}".to_string(),
+        Value::Array(_) => "[]".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// A string, plain when YAML reads it back unchanged and double-quoted otherwise
+///
+/// Text starting with a digit, `+`, `-` or `.` is always quoted: YAML 1.1
+/// loaders read such scalars as dates (`2024-01-01`), hex, octal or binary
+/// numbers (`0x1F`), or numbers with `_` separators (`1_000`).
+fn yaml_string(text: &str) -> String {
+    let plain = text.starts_with(|c: char| {
+        (c.is_alphanumeric() && !c.is_ascii_digit()) || matches!(c, '_' | '/')
+    }) && !text.ends_with(' ')
+        && text.chars().all(|c| {
+            c.is_alphanumeric() || matches!(c, ' ' | '_' | '-' | '.' | '/' | '+' | '(' | ')')
+        })
+        && text.parse::<f64>().is_err()
+        && !matches!(
+            text.to_ascii_lowercase().as_str(),
+            "true" | "false" | "null" | "yes" | "no" | "on" | "off" | "y" | "n"
+        );
+    if plain {
+        text.to_string()
+    } else {
+        Value::String(text.to_string()).to_string()
+    }
+}
+
+/// Upgrade serialized JSON from any earlier schema version to the current one
+///
+/// JSON already at [`SCHEMA_VERSION`] is returned unchanged.
+pub fn migrate(value: Value) -> Result<Value, SchemaError> {
+    migrate_with(value, &MIGRATIONS)
+}
+
+/// [`migrate`] with an explicit list of upgrade steps
+fn migrate_with(mut value: Value, migrations: &[Migration]) -> Result<Value, SchemaError> {
+    let supported = migrations.len() as u64 + 1;
+    let object = value.as_object_mut().ok_or(SchemaError::MissingVersion)?;
+    let found = object
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .filter(|&version| version >= 1)
+        .ok_or(SchemaError::MissingVersion)?;
+    if found > supported {
+        return Err(SchemaError::Unsupported { found, supported });
+    }
+
+    for (step, migration) in migrations.iter().enumerate().skip(found as usize - 1) {
+        migration(object);
+        object.insert("schema_version".to_string(), json!(step as u64 + 2));
+    }
+    Ok(value)
+}
+
+fn header(header: &DocumentHeader) -> Value {
+    json!({
+        "title": header.title,
+        "authors": header.authors.iter().map(|author| json!({
+            "name": author.name,
+            "email": author.email,
+        })).collect::<Vec<_>>(),
+        "revision": header.revision.as_ref().map(|revision| json!({
+            "number": revision.number,
+            "date": revision.date,
+            "remark": revision.remark,
+        })),
+        "attributes": header.attributes.iter().map(|entry| json!({
+            "name": entry.name,
+            "value": entry.value,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn attributes(attrs: &Attributes) -> Value {
+    Value::Object(
+        attrs
+            .iter()
+            .map(|(name, value)| (name.to_string(), json!(value)))
+            .collect(),
+    )
+}
+
+fn truncation(truncation: &Truncation) -> Value {
+    let kind = match truncation.kind {
+        StructureKind::List => "list",
+        StructureKind::Table => "table",
+    };
+    json!({
+        "kind": kind,
+        "offset": truncation.offset,
+        "kept": truncation.kept,
+        "dropped": truncation.dropped,
+    })
+}
+
+fn blocks(blocks: &[Block]) -> Vec<Value> {
+    blocks.iter().map(block).collect()
+}
+
+/// Hash of `block`'s content and metadata, nested blocks included
+///
+/// This is the `hash` of the block in [`to_json`] (see "Content Hashes"
+/// above), as a number.
+///
+/// ```
+/// use doctora::parse_document;
+/// use doctora::schema::{content_hash, to_json};
+///
+/// let old = parse_document("Intro.\n\n* one\n").unwrap();
+/// let new = parse_document("Intro.\n\n* one\n* two\n").unwrap();
+/// assert_eq!(content_hash(&old.blocks[0]), content_hash(&new.blocks[0]));
+/// assert_ne!(content_hash(&old.blocks[1]), content_hash(&new.blocks[1]));
+///
+/// let hash = format!("{:016x}", content_hash(&new.blocks[1]));
+/// assert_eq!(to_json(&new)["blocks"][1]["hash"], hash);
+/// ```
+pub fn content_hash(block: &Block) -> u64 {
+    fnv1a(unhashed_block(block).to_string().as_bytes())
+}
+
+/// 64-bit FNV-1a, which unlike the standard library's hashers is fixed
+/// across Rust releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn block(block: &Block) -> Value {
+    let mut value = unhashed_block(block);
+    let hash = fnv1a(value.to_string().as_bytes());
+    value["hash"] = json!(format!("{hash:016x}"));
+    value
+}
+
+/// The serialization of `block` without its own `hash`
+fn unhashed_block(block: &Block) -> Value {
+    let mut value = block_fields(block);
+    let metadata = block.metadata();
+    if !metadata.is_empty() {
+        value["metadata"] = json!({
+            "title": metadata.title.as_deref().map(inlines),
+            "id": metadata.id,
+            "roles": metadata.roles,
+            "options": metadata.options,
+            "positional": metadata.positional,
+            "named": metadata.named,
+        });
+    }
+    value
+}
+
+/// The `type` and fields of `block`, without its metadata
+fn block_fields(block: &Block) -> Value {
+    match block {
+        Block::Section {
+            level,
+            title,
+            content,
+            anchor,
+            ..
+        } => json!({
+            "type": "section",
+            "level": level,
+            "title": plain_text(title),
+            "title_content": inlines(title),
+            "anchor": anchor.as_ref().map(|anchor| json!({
+                "id": anchor.id,
+                "reftext": anchor.reftext,
+            })),
+            "content": blocks(content),
+        }),
+        Block::Paragraph { content, .. } => json!({
+            "type": "paragraph",
+            "content": inlines(content),
+        }),
+        Block::UnorderedList { items, .. } => json!({
+            "type": "unordered_list",
+            "items": items.iter().map(list_item).collect::<Vec<_>>(),
+        }),
+        Block::OrderedList { items, .. } => json!({
+            "type": "ordered_list",
+            "items": items.iter().map(list_item).collect::<Vec<_>>(),
+        }),
+        Block::DescriptionList {
+            items, separator, ..
+        } => json!({
+            "type": "description_list",
+            "separator": separator,
+            "items": items.iter().map(description_item).collect::<Vec<_>>(),
+        }),
+        Block::Listing {
+            language,
+            content,
+            attributes: attrs,
+            ..
+        } => json!({
+            "type": "listing",
+            "language": language,
+            "content": content,
+            "attributes": attributes(attrs),
+        }),
+        Block::Passthrough {
+            content, backend, ..
+        } => json!({
+            "type": "passthrough",
+            "content": content,
+            "backend": backend,
+        }),
+        Block::Comment {
+            content, delimited, ..
+        } => json!({
+            "type": "comment",
+            "content": content,
+            "delimited": delimited,
+        }),
+        Block::AttributeEntry { entry, .. } => json!({
+            "type": "attribute_entry",
+            "name": entry.name,
+            "value": entry.value,
+        }),
+        Block::Unknown { raw, span, .. } => json!({
+            "type": "unknown",
+            "raw": raw,
+            "span": { "start": span.start, "end": span.end },
+        }),
+        Block::Quote {
+            attribution,
+            citation,
+            content,
+            ..
+        } => json!({
+            "type": "quote",
+            "attribution": attribution,
+            "citation": citation,
+            "content": blocks(content),
+        }),
+        Block::Verse {
+            attribution,
+            citation,
+            content,
+            ..
+        } => json!({
+            "type": "verse",
+            "attribution": attribution,
+            "citation": citation,
+            "content": inlines(content),
+        }),
+        Block::Admonition { kind, content, .. } => json!({
+            "type": "admonition",
+            "kind": kind.name(),
+            "content": blocks(content),
+        }),
+        Block::Example { content, .. } => json!({
+            "type": "example",
+            "content": blocks(content),
+        }),
+        Block::Open {
+            content,
+            attributes: attrs,
+            ..
+        } => json!({
+            "type": "open",
+            "content": blocks(content),
+            "attributes": attributes(attrs),
+        }),
+        Block::Image {
+            target,
+            attributes: attrs,
+            ..
+        } => json!({
+            "type": "image",
+            "target": target,
+            "attributes": attributes(attrs),
+        }),
+        Block::Sidebar { content, .. } => json!({
+            "type": "sidebar",
+            "content": blocks(content),
+        }),
+        Block::Table {
+            columns,
+            header,
+            rows,
+            attributes: attrs,
+            ..
+        } => json!({
+            "type": "table",
+            "columns": columns
+                .iter()
+                .map(|column| json!({ "width": column.width }))
+                .collect::<Vec<_>>(),
+            "header": header.as_ref().map(table_row),
+            "rows": rows.iter().map(table_row).collect::<Vec<_>>(),
+            "attributes": attributes(attrs),
+        }),
+    }
+}
+
+fn table_row(row: &TableRow) -> Value {
+    json!({ "cells": row.cells.iter().map(table_cell).collect::<Vec<_>>() })
+}
+
+fn table_cell(cell: &TableCell) -> Value {
+    json!({
+        "content": inlines(&cell.content),
+        "colspan": cell.colspan,
+        "rowspan": cell.rowspan,
+    })
+}
+
+fn list_item(item: &ListItem) -> Value {
+    json!({
+        "content": inlines(&item.content),
+        "blocks": blocks(&item.blocks),
+    })
+}
+
+fn description_item(item: &DescriptionItem) -> Value {
+    json!({
+        "term": inlines(&item.term),
+        "description": inlines(&item.description),
+    })
+}
+
+fn inlines(content: &[Inline]) -> Vec<Value> {
+    content.iter().map(inline).collect()
+}
+
+fn inline(inline: &Inline) -> Value {
+    match inline {
+        Inline::Text(text) => json!({ "type": "text", "text": text }),
+        Inline::Bold(content) => json!({ "type": "bold", "content": inlines(content) }),
+        Inline::Italic(content) => json!({ "type": "italic", "content": inlines(content) }),
+        Inline::Monospace(content) => json!({ "type": "monospace", "content": inlines(content) }),
+        Inline::Highlight(content) => json!({ "type": "highlight", "content": inlines(content) }),
+        Inline::Span { roles, content } => json!({
+            "type": "span",
+            "roles": roles,
+            "content": inlines(content),
+        }),
+        Inline::Superscript(content) => {
+            json!({ "type": "superscript", "content": inlines(content) })
+        }
+        Inline::Subscript(content) => json!({ "type": "subscript", "content": inlines(content) }),
+        Inline::Link {
+            target,
+            text,
+            attributes: attrs,
+        } => json!({
+            "type": "link",
+            "target": target,
+            "content": inlines(text),
+            "attributes": attributes(attrs),
+        }),
+        Inline::XRef { id, text } => json!({
+            "type": "xref",
+            "id": id,
+            "content": inlines(text),
+        }),
+        Inline::Anchor(anchor) => json!({
+            "type": "anchor",
+            "id": anchor.id,
+            "reftext": anchor.reftext,
+        }),
+        Inline::Image {
+            target,
+            attributes: attrs,
+        } => json!({
+            "type": "inline_image",
+            "target": target,
+            "attributes": attributes(attrs),
+        }),
+        Inline::Icon {
+            name,
+            attributes: attrs,
+        } => json!({
+            "type": "icon",
+            "name": name,
+            "attributes": attributes(attrs),
+        }),
+        Inline::Footnote { id, text } => json!({
+            "type": "footnote",
+            "id": id,
+            "content": inlines(text),
+        }),
+        Inline::BreakHint(hint) => json!({
+            "type": "break_hint",
+            "hint": match hint {
+                BreakHint::SoftHyphen => "soft_hyphen",
+                BreakHint::NoBreakSpace => "no_break_space",
+            },
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_document_layout() {
+        let doc = parse_document("= Title\nAda\n\n== Part\n\nSome _text_.\n\n* one\n").unwrap();
+        let mut json = to_json(&doc);
+        remove_hashes(&mut json);
+        assert_eq!(
+            json,
+            json!({
+                "schema_version": 1,
+                "header": {
+                    "title": "Title",
+                    "authors": [{ "name": "Ada", "email": null }],
+                    "revision": null,
+                    "attributes": [],
+                },
+                "attributes": {
+                    "author": "Ada",
+                    "authorinitials": "A",
+                    "authors": "Ada",
+                    "doctitle": "Title",
+                    "firstname": "Ada",
+                },
+                "truncations": [],
+                "blocks": [{
+                    "type": "section",
+                    "level": 2,
+                    "title": "Part",
+                    "title_content": [{ "type": "text", "text": "Part" }],
+                    "anchor": null,
+                    "content": [
+                        {
+                            "type": "paragraph",
+                            "content": [
+                                { "type": "text", "text": "Some " },
+                                { "type": "italic", "content": [{ "type": "text", "text": "text" }] },
+                                { "type": "text", "text": "." },
+                            ],
+                        },
+                        {
+                            "type": "unordered_list",
+                            "items": [{
+                                "content": [{ "type": "text", "text": "one" }],
+                                "blocks": [],
+                            }],
+                        },
+                    ],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_yaml_layout() {
+        let value = json!({
+            "title": "Guide",
+            "version": 1,
+            "header": null,
+            "empty": {},
+            "tags": [],
+            "quoted": ["true", "1.5", "- dash", "a: b", "", "line\nbreak"],
+            "numeric": ["2024-01-01", "0x1F", "0o17", "017", "0b101", "1_000", "+1", "-1", ".5", "2nd", "v2"],
+            "blocks": [
+                { "type": "paragraph", "content": [{ "type": "text", "text": "Hi there" }] },
+                [1, 2],
+            ],
+        });
+        assert_eq!(
+            yaml(&value),
+            "blocks:\n  \
+               - content:\n      \
+                   - text: Hi there\n        \
+                     type: text\n    \
+                 type: paragraph\n  \
+               -\n    \
+                 - 1\n    \
+                 - 2\n\
+             empty: {}\n\
+             header: null\n\
+             numeric:\n  \
+               - \"2024-01-01\"\n  \
+               - \"0x1F\"\n  \
+               - \"0o17\"\n  \
+               - \"017\"\n  \
+               - \"0b101\"\n  \
+               - \"1_000\"\n  \
+               - \"+1\"\n  \
+               - \"-1\"\n  \
+               - \".5\"\n  \
+               - \"2nd\"\n  \
+               - v2\n\
+             quoted:\n  \
+               - \"true\"\n  \
+               - \"1.5\"\n  \
+               - \"- dash\"\n  \
+               - \"a: b\"\n  \
+               - \"\"\n  \
+               - \"line\\nbreak\"\n\
+             tags: []\n\
+             title: Guide\n\
+             version: 1\n"
+        );
+
+        let doc = crate::parse_document("Hello.").unwrap();
+        let yaml = to_yaml_string(&doc);
+        assert!(yaml.ends_with("schema_version: 1\ntruncations: []\n"));
+        assert!(yaml.contains("    type: paragraph\n"));
+    }
+
+    #[test]
+    fn test_block_metadata() {
+        let doc =
+            crate::parse_document("[source#main.wide,ruby]\n----\nx\n----\n\nPlain.\n").unwrap();
+        let json = to_json(&doc);
+        assert_eq!(
+            json["blocks"][0]["metadata"],
+            json!({
+                "title": null,
+                "id": "main",
+                "roles": ["wide"],
+                "options": [],
+                "positional": ["source", "ruby"],
+                "named": {},
+            })
+        );
+        assert!(json["blocks"][1].get("metadata").is_none());
+
+        let doc = crate::parse_document(".Totals\n|===\n|1\n|===\n").unwrap();
+        let json = to_json(&doc);
+        assert_eq!(
+            json["blocks"][0]["metadata"]["title"],
+            json!([{ "type": "text", "text": "Totals" }])
+        );
+    }
+
+    /// Drop the `hash` of every block under `value`
+    fn remove_hashes(value: &mut Value) {
+        match value {
+            Value::Array(values) => values.iter_mut().for_each(remove_hashes),
+            Value::Object(object) => {
+                object.remove("hash");
+                object.values_mut().for_each(remove_hashes);
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_content_hashes() {
+        let hashes = |source: &str| -> Vec<u64> {
+            parse_document(source)
+                .unwrap()
+                .blocks
+                .iter()
+                .map(content_hash)
+                .collect()
+        };
+        let old = hashes("Intro.\n\n== Part\n\nText.\n");
+        assert_eq!(old, hashes("Intro.\n\n== Part\n\nText.\n"));
+        // A change deep inside a section changes the section's hash
+        let new = hashes("Intro.\n\n== Part\n\nText, edited.\n");
+        assert_eq!(old[0], new[0]);
+        assert_ne!(old[1], new[1]);
+        // So does a change to the block's metadata alone
+        assert_ne!(hashes("Intro.\n")[0], hashes("[.lead]\nIntro.\n")[0]);
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    /// Every `type` the serializer emits is declared in the published schema
+    #[test]
+    fn test_published_schema_lists_all_types() {
+        let schema: Value =
+            serde_json::from_str(include_str!("../docs/schema/ast-v1.schema.json")).unwrap();
+        assert_eq!(
+            schema["properties"]["schema_version"]["const"],
+            SCHEMA_VERSION
+        );
+        let declared = |definition: &str| -> BTreeSet<String> {
+            schema["$defs"][definition]["oneOf"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|variant| {
+                    let name = variant["$ref"]
+                        .as_str()
+                        .unwrap()
+                        .rsplit('/')
+                        .next()
+                        .unwrap();
+                    schema["$defs"][name]["properties"]["type"]["const"]
+                        .as_str()
+                        .unwrap()
+                        .to_string()
+                })
+                .collect()
+        };
+
+        let options = crate::options::ParseOptions::new()
+            .break_hints(true)
+            .keep_comments(true);
+        let doc =
+            crate::parse_document_with_options("[[t]]\n== T\n\nA **b** _c_ `m` #h# [.small]#sm# ^p^ ~s~ footnote:[f] a&shy;b https://d.org[e] <<t>> [[p]] image:i.png[] icon:heart[].\n\n* x\n\n. y\n\nterm:: def\n\n----\ncode\n----\n\n|===\n|cell\n|===\n\n____\nQ\n____\n\n[verse]\nV\n\nNOTE: N\n\n====\nE\n====\n\n****\nS\n****\n\n--\nO\n--\n\nimage::f.png[]\n\n++++\n<br>\n++++\n\n// c\n\n:a: b\n\ntoc::[]\n", &options)
+                .unwrap();
+        let json = to_json(&doc);
+        let mut block_types = BTreeSet::new();
+        let mut inline_types = BTreeSet::new();
+        collect_types(&json["blocks"], &mut block_types, &mut inline_types);
+
+        assert_eq!(block_types, declared("block"));
+        assert_eq!(inline_types, declared("inline"));
+    }
+
+    /// Gather the `type`s of blocks and inlines anywhere under `value`
+    fn collect_types(value: &Value, blocks: &mut BTreeSet<String>, inlines: &mut BTreeSet<String>) {
+        match value {
+            Value::Array(values) => {
+                for value in values {
+                    collect_types(value, blocks, inlines);
+                }
+            }
+            Value::Object(object) => {
+                if let Some(Value::String(kind)) = object.get("type") {
+                    let is_inline = matches!(
+                        kind.as_str(),
+                        "text"
+                            | "bold"
+                            | "italic"
+                            | "monospace"
+                            | "highlight"
+                            | "span"
+                            | "superscript"
+                            | "subscript"
+                            | "link"
+                            | "xref"
+                            | "anchor"
+                            | "inline_image"
+                            | "icon"
+                            | "footnote"
+                            | "break_hint"
+                    );
+                    if is_inline {
+                        &mut *inlines
+                    } else {
+                        &mut *blocks
+                    }
+                    .insert(kind.clone());
+                }
+                for value in object.values() {
+                    collect_types(value, blocks, inlines);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_migrate_current_version_is_unchanged() {
+        let json = to_json(&parse_document("Text.").unwrap());
+        assert_eq!(migrate(json.clone()), Ok(json));
+    }
+
+    #[test]
+    fn test_migrate_rejects_unknown_versions() {
+        assert_eq!(migrate(json!([])), Err(SchemaError::MissingVersion));
+        assert_eq!(
+            migrate(json!({ "blocks": [] })),
+            Err(SchemaError::MissingVersion)
+        );
+        assert_eq!(
+            migrate(json!({ "schema_version": 0 })),
+            Err(SchemaError::MissingVersion)
+        );
+        assert_eq!(
+            migrate(json!({ "schema_version": SCHEMA_VERSION + 1 })),
+            Err(SchemaError::Unsupported {
+                found: SCHEMA_VERSION + 1,
+                supported: SCHEMA_VERSION,
+            })
+        );
+    }
+
+    #[test]
+    fn test_migrations_run_in_order_from_found_version() {
+        fn rename_blocks(doc: &mut Map<String, Value>) {
+            let blocks = doc.remove("nodes").unwrap_or_default();
+            doc.insert("blocks".to_string(), blocks);
+        }
+        fn add_attributes(doc: &mut Map<String, Value>) {
+            doc.insert("attributes".to_string(), json!({}));
+        }
+        let steps: [Migration; 2] = [rename_blocks, add_attributes];
+
+        assert_eq!(
+            migrate_with(json!({ "schema_version": 1, "nodes": [] }), &steps),
+            Ok(json!({ "schema_version": 3, "blocks": [], "attributes": {} }))
+        );
+        assert_eq!(
+            migrate_with(json!({ "schema_version": 2, "blocks": [] }), &steps),
+            Ok(json!({ "schema_version": 3, "blocks": [], "attributes": {} }))
+        );
+    }
+}
@@ -0,0 +1,400 @@
+//! Regular expression search over the prose of a document
+//!
+//! "Find in docs" should find words a reader sees, not the contents of code
+//! listings, attribute values, or URLs. [`search`] (or
+//! [`Document::search`] with a pattern string) runs a regular expression over
+//! the prose text only:
+//!
+//! - Titles, paragraphs, verses, list items, description lists, and table
+//!   cells are searched, including the text of links, cross references, and
+//!   footnotes.
+//! - Listing, passthrough, comment, and image blocks, monospace text, link
+//!   targets, inline images, attribute lists, and header attributes are not.
+//!
+//! Text joined by formatting is searched as one string, so `very important`
+//! matches `*very* important`. Skipped nodes split it instead: a match never
+//! runs across monospace text, a bare URL, or an image, and footnote text is
+//! searched on its own, after the text around it. Non-breaking spaces read as plain spaces and soft
+//! hyphens are ignored. Empty matches are not reported.
+//!
+//! Each [`SearchMatch`] refers to the block holding it, the part of the block
+//! ([`Field`]), and the text nodes it covers with a byte range in each, so
+//! tools can highlight or replace the match in the AST.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::search::Field;
+//!
+//! let doc = doctora::parse_document(
+//!     "== Install\n\nRun the *install* script.\n\n----\n./install.sh\n----\n",
+//! )
+//! .unwrap();
+//! let matches = doc.search("(?i)install").unwrap();
+//! assert_eq!(matches.len(), 2);
+//!
+//! assert_eq!(matches[0].path, vec![0]);
+//! assert_eq!(matches[0].field, Field::Heading);
+//!
+//! // The word in bold, not the listing
+//! assert_eq!(matches[1].path, vec![0, 0]);
+//! assert_eq!(matches[1].text, "install");
+//! assert_eq!(matches[1].nodes[0].path, vec![1, 0]);
+//! assert_eq!(matches[1].nodes[0].range, 0..7);
+//! ```
+
+use crate::ast::{Block, BreakHint, Document, Inline};
+use std::ops::Range;
+
+pub use regex::Regex;
+
+impl Document {
+    /// Search the prose of the document for `pattern`, a regular expression
+    /// in the syntax of the [`regex`] crate
+    ///
+    /// See the [`search`](crate::search) module for what counts as prose.
+    /// Returns an error if the pattern does not compile.
+    pub fn search(&self, pattern: &str) -> Result<Vec<SearchMatch<'_>>, regex::Error> {
+        Ok(search(self, &Regex::new(pattern)?))
+    }
+}
+
+/// One match of a search
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchMatch<'a> {
+    /// The block holding the match
+    pub block: &'a Block,
+    /// Position of the block, as child indices from the document root
+    ///
+    /// The children of a list are its items, and the children of an item
+    /// are its attached blocks: `[2, 1, 0]` is the first block attached to
+    /// the second item of the list at `[2]`.
+    pub path: Vec<usize>,
+    /// The part of the block holding the match
+    pub field: Field,
+    /// The matched text
+    pub text: String,
+    /// The text nodes the match covers, in order
+    pub nodes: Vec<MatchedNode<'a>>,
+}
+
+/// A text node covered by a [`SearchMatch`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedNode<'a> {
+    /// The node, an [`Inline::Text`]
+    pub inline: &'a Inline,
+    /// Position of the node, as child indices from the field's content
+    /// through nested formatting, links, and footnotes
+    pub path: Vec<usize>,
+    /// Byte range of the matched part of the node's text
+    pub range: Range<usize>,
+}
+
+/// The inline content of a block that a match is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    /// The block title, from a `.Title` line
+    Title,
+    /// The title of a section
+    Heading,
+    /// The text of a paragraph or verse
+    Content,
+    /// The text of the list item at this index
+    Item(usize),
+    /// The term of the description list item at this index
+    Term(usize),
+    /// The description of the description list item at this index
+    Description(usize),
+    /// A table cell; rows are counted from the header row, if there is one
+    Cell {
+        /// Row index
+        row: usize,
+        /// Cell index within the row
+        column: usize,
+    },
+}
+
+/// Every match of `regex` in the prose of `doc`, in document order
+pub fn search<'a>(doc: &'a Document, regex: &Regex) -> Vec<SearchMatch<'a>> {
+    let mut searcher = Searcher {
+        regex,
+        matches: Vec::new(),
+    };
+    searcher.blocks(&doc.blocks, &mut Vec::new());
+    searcher.matches
+}
+
+/// Collects the matches of one search
+struct Searcher<'a, 'r> {
+    regex: &'r Regex,
+    matches: Vec<SearchMatch<'a>>,
+}
+
+impl<'a> Searcher<'a, '_> {
+    fn blocks(&mut self, blocks: &'a [Block], path: &mut Vec<usize>) {
+        for (index, block) in blocks.iter().enumerate() {
+            path.push(index);
+            self.block(block, path);
+            path.pop();
+        }
+    }
+
+    fn block(&mut self, block: &'a Block, path: &mut Vec<usize>) {
+        if let Some(title) = &block.metadata().title {
+            self.field(block, path, Field::Title, title);
+        }
+        match block {
+            Block::Section { title, content, .. } => {
+                self.field(block, path, Field::Heading, title);
+                self.blocks(content, path);
+            }
+            Block::Paragraph { content, .. } | Block::Verse { content, .. } => {
+                self.field(block, path, Field::Content, content)
+            }
+            Block::UnorderedList { items, .. } | Block::OrderedList { items, .. } => {
+                for (index, item) in items.iter().enumerate() {
+                    self.field(block, path, Field::Item(index), &item.content);
+                    path.push(index);
+                    self.blocks(&item.blocks, path);
+                    path.pop();
+                }
+            }
+            Block::DescriptionList { items, .. } => {
+                for (index, item) in items.iter().enumerate() {
+                    self.field(block, path, Field::Term(index), &item.term);
+                    self.field(block, path, Field::Description(index), &item.description);
+                }
+            }
+            Block::Table { header, rows, .. } => {
+                for (row, cells) in header.iter().chain(rows).enumerate() {
+                    for (column, cell) in cells.cells.iter().enumerate() {
+                        self.field(block, path, Field::Cell { row, column }, &cell.content);
+                    }
+                }
+            }
+            Block::Quote { content, .. }
+            | Block::Admonition { content, .. }
+            | Block::Example { content, .. }
+            | Block::Sidebar { content, .. }
+            | Block::Open { content, .. } => self.blocks(content, path),
+            Block::Listing { .. }
+            | Block::Image { .. }
+            | Block::Passthrough { .. }
+            | Block::Comment { .. }
+            | Block::AttributeEntry { .. }
+            | Block::Unknown { .. } => {}
+        }
+    }
+
+    /// Search the prose runs of one field of `block`
+    fn field(&mut self, block: &'a Block, path: &[usize], field: Field, content: &'a [Inline]) {
+        let mut runs = vec![Run::default()];
+        let mut footnotes = Vec::new();
+        collect(content, &mut Vec::new(), &mut runs, &mut footnotes);
+        for run in runs.iter().chain(&footnotes) {
+            for found in self.regex.find_iter(&run.text) {
+                if found.is_empty() {
+                    continue;
+                }
+                let nodes = run
+                    .pieces
+                    .iter()
+                    .filter(|piece| piece.start < found.end() && piece.end > found.start())
+                    .map(|piece| MatchedNode {
+                        inline: piece.inline,
+                        path: piece.path.clone(),
+                        range: found.start().max(piece.start) - piece.start
+                            ..found.end().min(piece.end) - piece.start,
+                    })
+                    .collect();
+                self.matches.push(SearchMatch {
+                    block,
+                    path: path.to_vec(),
+                    field,
+                    text: found.as_str().to_string(),
+                    nodes,
+                });
+            }
+        }
+    }
+}
+
+/// Prose text searched as one string
+#[derive(Default)]
+struct Run<'a> {
+    text: String,
+    pieces: Vec<Piece<'a>>,
+}
+
+/// A text node within a [`Run`]
+struct Piece<'a> {
+    inline: &'a Inline,
+    path: Vec<usize>,
+    /// Byte range of the node's text in the run
+    start: usize,
+    end: usize,
+}
+
+/// Append the prose of `content` to the last of `runs`, starting a new run
+/// at each skipped node; footnote text goes to runs of its own in
+/// `footnotes`
+fn collect<'a>(
+    content: &'a [Inline],
+    path: &mut Vec<usize>,
+    runs: &mut Vec<Run<'a>>,
+    footnotes: &mut Vec<Run<'a>>,
+) {
+    for (index, inline) in content.iter().enumerate() {
+        path.push(index);
+        match inline {
+            Inline::Text(text) => {
+                let run = runs.last_mut().expect("there is always a run");
+                let start = run.text.len();
+                run.text.push_str(text);
+                run.pieces.push(Piece {
+                    inline,
+                    path: path.clone(),
+                    start,
+                    end: run.text.len(),
+                });
+            }
+            Inline::Bold(nested)
+            | Inline::Italic(nested)
+            | Inline::Highlight(nested)
+            | Inline::Span {
+                content: nested, ..
+            }
+            | Inline::Superscript(nested)
+            | Inline::Subscript(nested) => collect(nested, path, runs, footnotes),
+            Inline::Link { text, .. } | Inline::XRef { text, .. } if !text.is_empty() => {
+                collect(text, path, runs, footnotes)
+            }
+            Inline::Footnote { text, .. } => {
+                let mut own = vec![Run::default()];
+                collect(text, path, &mut own, footnotes);
+                footnotes.extend(own);
+            }
+            Inline::Monospace(_)
+            | Inline::Link { .. }
+            | Inline::XRef { .. }
+            | Inline::Image { .. }
+            | Inline::Icon { .. } => split(runs),
+            Inline::BreakHint(BreakHint::NoBreakSpace) => runs
+                .last_mut()
+                .expect("there is always a run")
+                .text
+                .push(' '),
+            Inline::BreakHint(BreakHint::SoftHyphen) | Inline::Anchor(_) => {}
+        }
+        path.pop();
+    }
+}
+
+/// End the last of `runs`, unless it is still empty
+fn split(runs: &mut Vec<Run<'_>>) {
+    if runs.last().is_some_and(|run| !run.text.is_empty()) {
+        runs.push(Run::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hints::mark_break_hints;
+    use crate::parse_document;
+
+    fn texts(matches: &[SearchMatch<'_>]) -> Vec<String> {
+        matches.iter().map(|found| found.text.clone()).collect()
+    }
+
+    #[test]
+    fn test_skips_code_attributes_and_urls() {
+        let doc = parse_document(
+            ":product: widget\n\n\
+             [source,widget]\n----\nwidget()\n----\n\n\
+             ++++\n<b>widget</b>\n++++\n\n\
+             ////\nwidget\n////\n\n\
+             image::widget.png[widget]\n\n\
+             The `widget` at https://widget.example and \
+             https://example.com[the widget site] image:widget.png[].\n",
+        )
+        .unwrap();
+        let matches = doc.search("widget").unwrap();
+        assert_eq!(texts(&matches), ["widget"]);
+        assert_eq!(matches[0].path, vec![3]);
+        let Inline::Text(text) = matches[0].nodes[0].inline else {
+            panic!("expected a text node");
+        };
+        assert_eq!(text, "the widget site");
+        assert_eq!(matches[0].nodes[0].range, 4..10);
+    }
+
+    #[test]
+    fn test_match_across_formatting() {
+        let doc = parse_document("A *very* important _note_.").unwrap();
+        let matches = doc.search(r"very important \w+").unwrap();
+        assert_eq!(texts(&matches), ["very important note"]);
+        let nodes: Vec<_> = matches[0]
+            .nodes
+            .iter()
+            .map(|node| (node.path.clone(), node.range.clone()))
+            .collect();
+        assert_eq!(
+            nodes,
+            [(vec![1, 0], 0..4), (vec![2], 0..11), (vec![3, 0], 0..4)]
+        );
+
+        // Monospace text splits the prose, footnotes are searched alone
+        let doc = parse_document("one `x` two footnote:[three] four").unwrap();
+        assert!(doc.search("one two").unwrap().is_empty());
+        assert!(doc.search("two three").unwrap().is_empty());
+        assert_eq!(
+            texts(&doc.search(r"two\s+four|three").unwrap()),
+            ["two  four", "three"]
+        );
+    }
+
+    #[test]
+    fn test_fields_and_paths() {
+        let doc = parse_document(
+            "== Intro\n\n.Note title\nNote text.\n\n\
+             * first note\n+\nattached note\n\n\
+             term note:: description note\n\n\
+             |===\n|Head |Other\n\n|cell |note cell\n|===\n",
+        )
+        .unwrap();
+        let found: Vec<_> = doc
+            .search("(?i)note")
+            .unwrap()
+            .into_iter()
+            .map(|found| (found.path, found.field))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (vec![0, 0], Field::Title),
+                (vec![0, 0], Field::Content),
+                (vec![0, 1], Field::Item(0)),
+                (vec![0, 1, 0, 0], Field::Content),
+                (vec![0, 2], Field::Term(0)),
+                (vec![0, 2], Field::Description(0)),
+                (vec![0, 3], Field::Cell { row: 1, column: 1 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_break_hints_and_empty_matches() {
+        let mut doc = parse_document("hy&shy;phen and no&nbsp;break").unwrap();
+        mark_break_hints(&mut doc);
+        assert_eq!(texts(&doc.search("hyphen").unwrap()), ["hyphen"]);
+        assert_eq!(texts(&doc.search("no break").unwrap()), ["no break"]);
+        assert!(doc.search("x*").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let doc = parse_document("Text.").unwrap();
+        assert!(doc.search("(unclosed").is_err());
+    }
+}
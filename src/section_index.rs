@@ -0,0 +1,323 @@
+//! Per-section aggregations for documentation portals
+//!
+//! Documentation portals often render side panels or link previews for each
+//! section: a short summary, the key terms the section introduces, and so on.
+//! This module walks the AST once and exposes those aggregations as plain data,
+//! so callers do not have to re-walk the tree themselves.
+//!
+//! # Aggregations
+//!
+//! For every section (at any depth), [`SectionSummary`] records:
+//! - `summary` - plain text of the first paragraph directly inside the section
+//! - `key_terms` - plain text of each bold phrase in the section's own paragraphs,
+//!   deduplicated in order of first appearance
+//! - `links` - outbound links ([`Inline::Link`]) and cross references
+//!   ([`Inline::XRef`]) anywhere in the section's own blocks (lists, tables,
+//!   block titles, and footnotes included), deduplicated by target in order
+//!   of first appearance
+//!
+//! Content of nested subsections is attributed to the subsection, not its parent.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::ast::{Block, BlockMetadata, Document, Inline};
+//! use doctora::section_index::summarize_sections;
+//!
+//! let doc = Document::with_blocks(vec![Block::Section {
+//!     level: 1,
+//!     title: vec![Inline::Text("Intro".to_string())],
+//!     content: vec![Block::Paragraph {
+//!         content: vec![
+//!             Inline::Text("A ".to_string()),
+//!             Inline::Bold(vec![Inline::Text("lexer".to_string())]),
+//!             Inline::Text(" splits text.".to_string()),
+//!         ],
+//!         metadata: BlockMetadata::default(),
+//!     }],
+//!     anchor: None,
+//!     metadata: BlockMetadata::default(),
+//! }]);
+//!
+//! let sections = summarize_sections(&doc);
+//! assert_eq!(sections[0].summary.as_deref(), Some("A lexer splits text."));
+//! assert_eq!(sections[0].key_terms, vec!["lexer".to_string()]);
+//! ```
+
+use crate::ast::visit::{Visitor, walk_block, walk_inline};
+use crate::ast::{Block, Document, Inline, plain_text};
+
+/// Aggregated information about a single section
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionSummary {
+    /// Section title
+    pub title: String,
+    /// Heading level (1-6)
+    pub level: u8,
+    /// Position of the section in the tree, as child indices from the document root
+    ///
+    /// For example `[0, 2]` is the third block inside the first top-level block.
+    pub path: Vec<usize>,
+    /// Plain text of the first paragraph directly inside the section
+    pub summary: Option<String>,
+    /// Bold phrases used in the section's own paragraphs (deduplicated)
+    pub key_terms: Vec<String>,
+    /// Links and cross references in the section's own blocks (deduplicated)
+    pub links: Vec<SectionLink>,
+}
+
+/// An outbound link from a section
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionLink {
+    /// URL of a link, or ID of a cross reference's target
+    pub target: String,
+    /// Whether this is a cross reference within the document set
+    pub xref: bool,
+    /// Plain text of the link text, empty when none was given
+    pub text: String,
+}
+
+/// Collect a [`SectionSummary`] for every section in document order
+///
+/// Sections are listed depth-first, so a parent always precedes its subsections.
+pub fn summarize_sections(doc: &Document) -> Vec<SectionSummary> {
+    let mut summaries = Vec::new();
+    let mut path = Vec::new();
+    collect(&doc.blocks, &mut path, &mut summaries);
+    summaries
+}
+
+/// Recursively summarize sections found in `blocks`
+fn collect(blocks: &[Block], path: &mut Vec<usize>, out: &mut Vec<SectionSummary>) {
+    for (index, block) in blocks.iter().enumerate() {
+        if let Block::Section {
+            level,
+            title,
+            content,
+            ..
+        } = block
+        {
+            path.push(index);
+            out.push(summarize(*level, &plain_text(title), content, path));
+            collect(content, path, out);
+            path.pop();
+        }
+    }
+}
+
+/// Build the summary for one section from its direct content
+fn summarize(level: u8, title: &str, content: &[Block], path: &[usize]) -> SectionSummary {
+    let mut summary = None;
+    let mut key_terms = Vec::new();
+    let mut links = LinkCollector(Vec::new());
+
+    for block in content {
+        if let Block::Paragraph { content, .. } = block {
+            if summary.is_none() {
+                summary = Some(plain_text(content));
+            }
+            collect_key_terms(content, &mut key_terms);
+        }
+        links.visit_block(block);
+    }
+
+    SectionSummary {
+        title: title.to_string(),
+        level,
+        path: path.to_vec(),
+        summary,
+        key_terms,
+        links: links.0,
+    }
+}
+
+/// Push the plain text of every bold phrase in `content`, skipping duplicates
+fn collect_key_terms(content: &[Inline], terms: &mut Vec<String>) {
+    for inline in content {
+        match inline {
+            Inline::Bold(nested) => {
+                let term = plain_text(nested);
+                if !term.is_empty() && !terms.contains(&term) {
+                    terms.push(term);
+                }
+            }
+            Inline::Italic(nested)
+            | Inline::Monospace(nested)
+            | Inline::Highlight(nested)
+            | Inline::Span {
+                content: nested, ..
+            }
+            | Inline::Superscript(nested)
+            | Inline::Subscript(nested)
+            | Inline::Link { text: nested, .. } => collect_key_terms(nested, terms),
+            Inline::XRef { text: nested, .. } => collect_key_terms(nested, terms),
+            Inline::Text(_)
+            | Inline::Anchor(_)
+            | Inline::Image { .. }
+            | Inline::Icon { .. }
+            | Inline::Footnote { .. }
+            | Inline::BreakHint(_) => {}
+        }
+    }
+}
+
+/// Collects the links of a section's own blocks, skipping subsections
+struct LinkCollector(Vec<SectionLink>);
+
+impl Visitor for LinkCollector {
+    fn visit_block(&mut self, block: &Block) {
+        if !matches!(block, Block::Section { .. }) {
+            walk_block(self, block);
+        }
+    }
+
+    fn visit_inline(&mut self, inline: &Inline) {
+        let link = match inline {
+            Inline::Link { target, text, .. } => Some((target, false, text)),
+            Inline::XRef { id, text } => Some((id, true, text)),
+            _ => None,
+        };
+        if let Some((target, xref, text)) = link
+            && !self
+                .0
+                .iter()
+                .any(|link| link.target == *target && link.xref == xref)
+        {
+            self.0.push(SectionLink {
+                target: target.clone(),
+                xref,
+                text: plain_text(text),
+            });
+        }
+        walk_inline(self, inline);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::BlockMetadata;
+
+    fn text(s: &str) -> Inline {
+        Inline::Text(s.to_string())
+    }
+
+    fn para(content: Vec<Inline>) -> Block {
+        Block::Paragraph {
+            content,
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    fn section(level: u8, title: &str, content: Vec<Block>) -> Block {
+        Block::Section {
+            level,
+            title: vec![Inline::Text(title.to_string())],
+            content,
+            anchor: None,
+            metadata: BlockMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn test_empty_document() {
+        assert!(summarize_sections(&Document::new()).is_empty());
+    }
+
+    #[test]
+    fn test_summary_uses_first_paragraph() {
+        let doc = Document::with_blocks(vec![section(
+            1,
+            "Title",
+            vec![para(vec![text("First.")]), para(vec![text("Second.")])],
+        )]);
+
+        let sections = summarize_sections(&doc);
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].summary.as_deref(), Some("First."));
+        assert_eq!(sections[0].path, vec![0]);
+    }
+
+    #[test]
+    fn test_section_without_paragraph() {
+        let doc = Document::with_blocks(vec![section(1, "Empty", vec![])]);
+        let sections = summarize_sections(&doc);
+        assert_eq!(sections[0].summary, None);
+        assert!(sections[0].key_terms.is_empty());
+        assert!(sections[0].links.is_empty());
+    }
+
+    #[test]
+    fn test_nested_sections_are_separate() {
+        let doc = Document::with_blocks(vec![section(
+            1,
+            "Parent",
+            vec![
+                para(vec![Inline::Bold(vec![text("parser")])]),
+                section(
+                    2,
+                    "Child",
+                    vec![para(vec![Inline::Bold(vec![text("lexer")])])],
+                ),
+            ],
+        )]);
+
+        let sections = summarize_sections(&doc);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].title, "Parent");
+        assert_eq!(sections[0].key_terms, vec!["parser".to_string()]);
+        assert_eq!(sections[1].title, "Child");
+        assert_eq!(sections[1].level, 2);
+        assert_eq!(sections[1].path, vec![0, 1]);
+        assert_eq!(sections[1].key_terms, vec!["lexer".to_string()]);
+    }
+
+    #[test]
+    fn test_key_terms_deduplicated_and_nested() {
+        let doc = Document::with_blocks(vec![section(
+            1,
+            "Terms",
+            vec![
+                para(vec![
+                    Inline::Bold(vec![text("AST")]),
+                    Inline::Italic(vec![Inline::Bold(vec![text("token")])]),
+                ]),
+                para(vec![Inline::Bold(vec![text("AST")])]),
+            ],
+        )]);
+
+        let sections = summarize_sections(&doc);
+        assert_eq!(
+            sections[0].key_terms,
+            vec!["AST".to_string(), "token".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_links() {
+        let doc = crate::parse_document(
+            "== Setup\n\nSee https://example.com[the site] and <<install>>.\n\n\
+             * Again https://example.com[docs]\n* <<install,Install>> https://rust-lang.org\n\n\
+             === Install\n\nhttps://nested.example\n",
+        )
+        .unwrap();
+        let sections = summarize_sections(&doc);
+        let link = |target: &str, xref, text: &str| SectionLink {
+            target: target.to_string(),
+            xref,
+            text: text.to_string(),
+        };
+        assert_eq!(
+            sections[0].links,
+            [
+                link("https://example.com", false, "the site"),
+                link("install", true, ""),
+                link("https://rust-lang.org", false, ""),
+            ]
+        );
+        assert_eq!(
+            sections[1].links,
+            [link("https://nested.example", false, "")]
+        );
+    }
+}
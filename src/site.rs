@@ -0,0 +1,364 @@
+//! Sitemap and navigation metadata for batch builds
+//!
+//! A static site built from a [`parse_batch`](crate::batch::parse_batch) run
+//! needs more than the pages themselves: search engines read `sitemap.xml`
+//! and navigation components (sidebars, breadcrumbs, next/previous links)
+//! need the page hierarchy. [`navigation`] derives both from a
+//! [`BatchResult`]:
+//!
+//! - **Titles** come from the document header, then the first section, then
+//!   the file name.
+//! - **URLs** are the document names with the AsciiDoc extension (`.adoc`,
+//!   `.asciidoc`, `.asc`) replaced by [`SiteOptions::extension`], joined to
+//!   [`SiteOptions::base_url`].
+//! - **Hierarchy** follows directories: `index.adoc` is the parent of the
+//!   other pages in its directory, and of index pages in subdirectories
+//!   without an index of their own in between.
+//! - **Previous/next** follow batch order, which is the reading order.
+//!
+//! Stub documents from [`FailurePolicy::Stub`](crate::batch::FailurePolicy)
+//! are left out, so a failed page never appears in navigation.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::batch::{FailurePolicy, parse_batch};
+//! use doctora::site::{SiteOptions, navigation};
+//!
+//! let inputs = [
+//!     ("index.adoc", "= Home\n\nWelcome.\n"),
+//!     ("guide/install.adoc", "= Installing\n\nSteps.\n"),
+//! ];
+//! let result = parse_batch(inputs, FailurePolicy::Abort).unwrap();
+//! let nav = navigation(&result, &SiteOptions::new("https://docs.example.com/"));
+//!
+//! let install = &nav.pages[1];
+//! assert_eq!(install.title, "Installing");
+//! assert_eq!(install.url, "https://docs.example.com/guide/install.html");
+//! assert_eq!(install.parent, Some(0));
+//! assert_eq!(install.prev, Some(0));
+//! assert!(nav.sitemap_xml().contains("<loc>https://docs.example.com/index.html</loc>"));
+//! ```
+
+use crate::ast::Block;
+use crate::batch::{BatchDocument, BatchResult};
+use crate::render::html::escape;
+use serde_json::{Value, json};
+
+/// Source file extensions replaced in page URLs
+const SOURCE_EXTENSIONS: [&str; 3] = [".adoc", ".asciidoc", ".asc"];
+
+/// Options controlling page URLs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiteOptions {
+    /// URL the site is published under, without a trailing `/`
+    base_url: String,
+    /// Extension of output pages, including the dot
+    extension: String,
+}
+
+impl SiteOptions {
+    /// Creates options for a site published under `base_url`
+    ///
+    /// Pages get the `.html` extension.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        Self {
+            base_url,
+            extension: ".html".to_string(),
+        }
+    }
+
+    /// Sets the extension of output pages (`".html"`, `".htm"`, or `""` for
+    /// extensionless URLs)
+    pub fn extension(mut self, extension: impl Into<String>) -> Self {
+        self.extension = extension.into();
+        self
+    }
+
+    /// Returns the base URL, without a trailing `/`
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// URL of the page built from the document `name`
+    pub fn page_url(&self, name: &str) -> String {
+        format!("{}/{}", self.base_url, self.page_path(name))
+    }
+
+    /// Path of the page built from the document `name`, relative to the
+    /// site root (`guide/install.html`)
+    pub fn page_path(&self, name: &str) -> String {
+        let name = name.trim_start_matches("./").replace('\\', "/");
+        let stem = SOURCE_EXTENSIONS
+            .iter()
+            .find_map(|extension| name.strip_suffix(extension))
+            .unwrap_or(&name);
+        format!("{stem}{}", self.extension)
+    }
+}
+
+/// One page of the site
+///
+/// Related pages are referenced by index into [`Navigation::pages`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NavPage {
+    /// Name the document was submitted to the batch under
+    pub name: String,
+    /// Page title
+    pub title: String,
+    /// Absolute page URL
+    pub url: String,
+    /// Index page this page belongs under, if any
+    pub parent: Option<usize>,
+    /// Pages that have this page as their parent, in reading order
+    pub children: Vec<usize>,
+    /// Previous page in reading order
+    pub prev: Option<usize>,
+    /// Next page in reading order
+    pub next: Option<usize>,
+}
+
+/// Pages of a site with their hierarchy and reading order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Navigation {
+    /// Pages in reading order
+    pub pages: Vec<NavPage>,
+}
+
+/// Build navigation metadata for the documents of a batch
+pub fn navigation(result: &BatchResult, options: &SiteOptions) -> Navigation {
+    let mut pages: Vec<NavPage> = result
+        .documents
+        .iter()
+        .filter(|document| !document.stub)
+        .map(|document| NavPage {
+            name: document.name.clone(),
+            title: page_title(document),
+            url: options.page_url(&document.name),
+            parent: None,
+            children: Vec::new(),
+            prev: None,
+            next: None,
+        })
+        .collect();
+
+    let count = pages.len();
+    for index in 0..count {
+        let parent = parent_index(&pages, index);
+        let page = &mut pages[index];
+        page.parent = parent;
+        page.prev = index.checked_sub(1);
+        page.next = (index + 1 < count).then_some(index + 1);
+        if let Some(parent) = parent {
+            pages[parent].children.push(index);
+        }
+    }
+
+    Navigation { pages }
+}
+
+impl Navigation {
+    /// Indexes of the pages without a parent, in reading order
+    pub fn roots(&self) -> Vec<usize> {
+        (0..self.pages.len())
+            .filter(|&index| self.pages[index].parent.is_none())
+            .collect()
+    }
+
+    /// Render the pages as a `sitemap.xml` document
+    pub fn sitemap_xml(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+        );
+        for page in &self.pages {
+            xml.push_str(&format!("  <url><loc>{}</loc></url>\n", escape(&page.url)));
+        }
+        xml.push_str("</urlset>\n");
+        xml
+    }
+
+    /// Serialize the navigation for client-side components
+    ///
+    /// `pages` lists every page in reading order, referring to related pages
+    /// by URL. `tree` nests the same pages under their parents.
+    pub fn to_json(&self) -> Value {
+        let url = |index: Option<usize>| index.map(|index| self.pages[index].url.as_str());
+        let pages: Vec<Value> = self
+            .pages
+            .iter()
+            .map(|page| {
+                json!({
+                    "name": page.name,
+                    "title": page.title,
+                    "url": page.url,
+                    "parent": url(page.parent),
+                    "prev": url(page.prev),
+                    "next": url(page.next),
+                })
+            })
+            .collect();
+        let tree: Vec<Value> = self
+            .roots()
+            .into_iter()
+            .map(|index| self.tree(index))
+            .collect();
+        json!({ "pages": pages, "tree": tree })
+    }
+
+    /// Serialize the navigation as pretty-printed JSON text
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string_pretty(&self.to_json()).expect("JSON values always serialize")
+    }
+
+    fn tree(&self, index: usize) -> Value {
+        let page = &self.pages[index];
+        let children: Vec<Value> = page
+            .children
+            .iter()
+            .map(|&child| self.tree(child))
+            .collect();
+        json!({ "title": page.title, "url": page.url, "children": children })
+    }
+}
+
+/// Title of a page: header title, first section title, or file name
+fn page_title(document: &BatchDocument) -> String {
+    let header = document
+        .document
+        .header
+        .as_ref()
+        .and_then(|header| header.title.clone());
+    let section = || document.document.blocks.iter().find_map(Block::title_text);
+    header.or_else(section).unwrap_or_else(|| {
+        let (_, file) = split_path(&document.name);
+        file.split('.').next().unwrap_or(file).to_string()
+    })
+}
+
+/// Split a document name into its directory (with trailing `/`) and file name
+fn split_path(name: &str) -> (&str, &str) {
+    match name.rfind(['/', '\\']) {
+        Some(slash) => name.split_at(slash + 1),
+        None => ("", name),
+    }
+}
+
+/// Returns true if `file` is a directory index page
+fn is_index(file: &str) -> bool {
+    SOURCE_EXTENSIONS
+        .iter()
+        .any(|extension| file.strip_suffix(extension) == Some("index"))
+}
+
+/// Nearest index page above the page at `index`
+///
+/// A page's own directory is searched first, except for index pages, which
+/// belong to the index of an enclosing directory.
+fn parent_index(pages: &[NavPage], index: usize) -> Option<usize> {
+    let (mut directory, file) = split_path(&pages[index].name);
+    if is_index(file) {
+        directory = parent_directory(directory)?;
+    }
+    loop {
+        let found = pages.iter().position(|page| {
+            let (page_directory, page_file) = split_path(&page.name);
+            page_directory == directory && is_index(page_file)
+        });
+        if found.is_some() {
+            return found;
+        }
+        directory = parent_directory(directory)?;
+    }
+}
+
+/// `a/b/` to `a/`, `a/` to the root, and the root to `None`
+fn parent_directory(directory: &str) -> Option<&str> {
+    let trimmed = directory.strip_suffix(['/', '\\'])?;
+    Some(split_path(trimmed).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::{FailurePolicy, parse_batch};
+
+    fn site(inputs: &[(&str, &str)]) -> Navigation {
+        let result = parse_batch(inputs.iter().copied(), FailurePolicy::Stub).unwrap();
+        navigation(&result, &SiteOptions::new("https://example.com/docs/"))
+    }
+
+    #[test]
+    fn test_titles_and_urls() {
+        let nav = site(&[
+            ("index.adoc", "= Home\n\nWelcome.\n"),
+            ("./guide/setup.asciidoc", "== Setting up\n\nSteps.\n"),
+            ("notes.txt", "Plain."),
+        ]);
+        let pages: Vec<(&str, &str)> = nav
+            .pages
+            .iter()
+            .map(|page| (page.title.as_str(), page.url.as_str()))
+            .collect();
+        assert_eq!(
+            pages,
+            [
+                ("Home", "https://example.com/docs/index.html"),
+                ("Setting up", "https://example.com/docs/guide/setup.html"),
+                ("notes", "https://example.com/docs/notes.txt.html"),
+            ]
+        );
+
+        let options = SiteOptions::new("https://example.com").extension("");
+        assert_eq!(options.page_url("a/b.adoc"), "https://example.com/a/b");
+        assert_eq!(options.page_path("./a\\b.asc"), "a/b");
+    }
+
+    #[test]
+    fn test_hierarchy_and_reading_order() {
+        let nav = site(&[
+            ("index.adoc", "= Home\n\nA.\n"),
+            ("guide/index.adoc", "= Guide\n\nB.\n"),
+            ("guide/install.adoc", "= Install\n\nC.\n"),
+            ("bad.adoc", "= No newline"),
+            ("ref/api/calls.adoc", "= Calls\n\nD.\n"),
+        ]);
+        let parents: Vec<Option<usize>> = nav.pages.iter().map(|page| page.parent).collect();
+        assert_eq!(parents, [None, Some(0), Some(1), Some(0)]);
+        assert_eq!(nav.pages[0].children, [1, 3]);
+        assert_eq!(nav.roots(), [0]);
+        assert_eq!((nav.pages[3].prev, nav.pages[3].next), (Some(2), None));
+        assert_eq!((nav.pages[0].prev, nav.pages[0].next), (None, Some(1)));
+    }
+
+    #[test]
+    fn test_sitemap_and_json() {
+        let nav = site(&[
+            ("index.adoc", "= Home\n\nA.\n"),
+            ("a&b.adoc", "= Q&A\n\nB.\n"),
+        ]);
+        assert_eq!(
+            nav.sitemap_xml(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n  \
+             <url><loc>https://example.com/docs/index.html</loc></url>\n  \
+             <url><loc>https://example.com/docs/a&amp;b.html</loc></url>\n\
+             </urlset>\n"
+        );
+
+        let json = nav.to_json();
+        assert_eq!(json["pages"][1]["title"], "Q&A");
+        assert_eq!(
+            json["pages"][1]["parent"],
+            "https://example.com/docs/index.html"
+        );
+        assert_eq!(
+            json["pages"][1]["prev"],
+            "https://example.com/docs/index.html"
+        );
+        assert_eq!(json["pages"][1]["next"], Value::Null);
+        assert_eq!(json["tree"][0]["children"][0]["title"], "Q&A");
+        assert_eq!(json["tree"].as_array().map(Vec::len), Some(1));
+    }
+}
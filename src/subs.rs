@@ -0,0 +1,311 @@
+//! Substitution groups and the `subs` attribute syntax
+//!
+//! AsciiDoc applies a series of text substitutions to block content before it is
+//! converted: escaping special characters, inline formatting (quotes), attribute
+//! references, textual replacements, macros, and post replacements (line breaks).
+//! Which groups run depends on the block kind (normal vs verbatim) and can be
+//! customized per block with the `subs` attribute.
+//!
+//! [`Substitutions`] is a bitflags set of those groups. It parses the `subs`
+//! attribute syntax, including incremental modifiers:
+//!
+//! | Spec                  | Meaning                                      |
+//! |-----------------------|----------------------------------------------|
+//! | `quotes,macros`       | Exactly these groups (replaces the defaults) |
+//! | `attributes+`         | Defaults plus `attributes`                   |
+//! | `+attributes`         | Defaults plus `attributes`                   |
+//! | `-quotes`             | Defaults without `quotes`                    |
+//! | `normal`, `verbatim`  | Named presets                                |
+//!
+//! [`SubstitutionDefaults`] replaces the defaults of whole block kinds for a
+//! parse (see
+//! [`ParseOptions::substitutions`](crate::options::ParseOptions::substitutions)).
+//! A block's `subs` attribute still applies, relative to those defaults.
+//!
+//! Because this is a set, the application order is always the spec order
+//! (special characters, quotes, attributes, replacements, macros, post
+//! replacements); prepend (`+x`) and append (`x+`) modifiers are equivalent.
+//!
+//! The parser applies `quotes` and `macros` as it formats text (see
+//! [`format_inlines_with`](crate::formatting::format_inlines_with)),
+//! [`crate::resolve`] applies `attributes`, [`crate::replacements`] and
+//! [`crate::hints`] between them `replacements`, and the HTML renderer `specialcharacters` (see
+//! "Special Characters" in [`crate::render::html`]). Hard line breaks are
+//! not supported, so `post_replacements` changes nothing.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::subs::Substitutions;
+//!
+//! // `[source,subs="attributes+"]` on a listing block
+//! let subs = Substitutions::parse("attributes+", Substitutions::VERBATIM).unwrap();
+//! assert_eq!(
+//!     subs,
+//!     Substitutions::SPECIAL_CHARACTERS | Substitutions::ATTRIBUTES
+//! );
+//!
+//! // A plain list replaces the defaults entirely
+//! let subs = Substitutions::parse("quotes", Substitutions::NORMAL).unwrap();
+//! assert_eq!(subs, Substitutions::QUOTES);
+//! ```
+
+use crate::ast::Block;
+use bitflags::bitflags;
+use std::collections::BTreeMap;
+use std::fmt;
+use thiserror::Error;
+
+bitflags! {
+    /// Set of substitution groups applied to block content
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Substitutions: u8 {
+        /// Escape `<`, `>`, and `&` (`specialcharacters`, short `c`)
+        const SPECIAL_CHARACTERS = 1 << 0;
+        /// Inline formatting such as bold and italic (`quotes`, short `q`)
+        const QUOTES = 1 << 1;
+        /// Attribute references like `{name}` (`attributes`, short `a`)
+        const ATTRIBUTES = 1 << 2;
+        /// Textual replacements like `(C)` and `--` (`replacements`, short `r`)
+        const REPLACEMENTS = 1 << 3;
+        /// Inline macros such as links and images (`macros`, short `m`)
+        const MACROS = 1 << 4;
+        /// Hard line breaks (`post_replacements`, short `p`)
+        const POST_REPLACEMENTS = 1 << 5;
+    }
+}
+
+/// Group names in application order, paired with their short aliases
+const GROUPS: [(&str, &str, Substitutions); 6] = [
+    ("specialcharacters", "c", Substitutions::SPECIAL_CHARACTERS),
+    ("quotes", "q", Substitutions::QUOTES),
+    ("attributes", "a", Substitutions::ATTRIBUTES),
+    ("replacements", "r", Substitutions::REPLACEMENTS),
+    ("macros", "m", Substitutions::MACROS),
+    ("post_replacements", "p", Substitutions::POST_REPLACEMENTS),
+];
+
+/// Error produced when a `subs` spec cannot be parsed
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SubstitutionError {
+    /// The spec names a substitution group that does not exist
+    #[error("unknown substitution: {0}")]
+    Unknown(String),
+}
+
+impl Substitutions {
+    /// All groups; the default for paragraphs and other normal blocks
+    pub const NORMAL: Self = Self::all();
+
+    /// Only special characters; the default for listing and literal blocks
+    pub const VERBATIM: Self = Self::SPECIAL_CHARACTERS;
+
+    /// Special characters and attributes; used for header and attribute values
+    pub const HEADER: Self = Self::SPECIAL_CHARACTERS.union(Self::ATTRIBUTES);
+
+    /// Look up a single group or preset by name
+    ///
+    /// Accepts long names (`quotes`), short aliases (`q`), the alias
+    /// `specialchars`, and the presets `normal` (`n`), `verbatim` (`v`), and
+    /// `none`.
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "normal" | "n" => Some(Self::NORMAL),
+            "verbatim" | "v" => Some(Self::VERBATIM),
+            "none" => Some(Self::empty()),
+            "specialchars" => Some(Self::SPECIAL_CHARACTERS),
+            _ => GROUPS
+                .iter()
+                .find(|(long, short, _)| *long == name || *short == name)
+                .map(|(_, _, flag)| *flag),
+        }
+    }
+
+    /// Parse a `subs` attribute value relative to the block's `defaults`
+    ///
+    /// If any entry carries an incremental modifier (`+x`, `x+`, `-x`), the
+    /// result starts from `defaults`; otherwise the listed groups replace them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SubstitutionError::Unknown`] if an entry does not name a group
+    /// or preset.
+    pub fn parse(spec: &str, defaults: Self) -> Result<Self, SubstitutionError> {
+        let entries: Vec<&str> = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .collect();
+
+        let incremental = entries
+            .iter()
+            .any(|entry| entry.starts_with(['+', '-']) || entry.ends_with('+'));
+        let mut subs = if incremental { defaults } else { Self::empty() };
+
+        for entry in entries {
+            let (name, remove) = if let Some(name) = entry.strip_prefix('-') {
+                (name, true)
+            } else {
+                let name = entry.strip_prefix('+').unwrap_or(entry);
+                (name.strip_suffix('+').unwrap_or(name), false)
+            };
+
+            let flags =
+                Self::named(name).ok_or_else(|| SubstitutionError::Unknown(name.to_string()))?;
+            if remove {
+                subs.remove(flags);
+            } else {
+                subs.insert(flags);
+            }
+        }
+
+        Ok(subs)
+    }
+
+    /// Long names of the enabled groups, in application order
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        GROUPS
+            .iter()
+            .filter(move |(_, _, flag)| self.contains(*flag))
+            .map(|(name, _, _)| *name)
+    }
+}
+
+impl fmt::Display for Substitutions {
+    /// Formats the set as a `subs` spec, e.g. `specialcharacters,attributes`
+    ///
+    /// The empty set is formatted as `none`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("none");
+        }
+        for (index, name) in self.names().enumerate() {
+            if index > 0 {
+                f.write_str(",")?;
+            }
+            f.write_str(name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Default substitutions by block kind, where they differ from
+/// [`Block::default_substitutions`]
+///
+/// Kinds are named by [`Block::type_name`] (`paragraph`, `listing`, ...).
+///
+/// ```
+/// use doctora::parse_document;
+/// use doctora::subs::{SubstitutionDefaults, Substitutions};
+///
+/// let mut defaults = SubstitutionDefaults::new();
+/// defaults.set("listing", Substitutions::VERBATIM | Substitutions::ATTRIBUTES);
+///
+/// let doc = parse_document("----\ncode\n----\n\nText.\n").unwrap();
+/// assert!(defaults.get(&doc.blocks[0]).contains(Substitutions::ATTRIBUTES));
+/// assert_eq!(defaults.get(&doc.blocks[1]), Substitutions::NORMAL);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubstitutionDefaults {
+    overrides: BTreeMap<String, Substitutions>,
+}
+
+impl SubstitutionDefaults {
+    /// Creates defaults with no overrides
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `subs` by default for blocks of kind `kind`
+    pub fn set(&mut self, kind: impl Into<String>, subs: Substitutions) {
+        self.overrides.insert(kind.into(), subs);
+    }
+
+    /// The default substitutions of `block`
+    pub fn get(&self, block: &Block) -> Substitutions {
+        self.overrides
+            .get(block.type_name())
+            .copied()
+            .unwrap_or_else(|| block.default_substitutions())
+    }
+
+    /// Returns true if no kind is overridden
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        assert_eq!(Substitutions::NORMAL, Substitutions::all());
+        assert_eq!(Substitutions::VERBATIM, Substitutions::SPECIAL_CHARACTERS);
+        assert_eq!(Substitutions::named("none"), Some(Substitutions::empty()));
+        assert_eq!(Substitutions::named("v"), Some(Substitutions::VERBATIM));
+    }
+
+    #[test]
+    fn test_named_aliases() {
+        assert_eq!(Substitutions::named("q"), Some(Substitutions::QUOTES));
+        assert_eq!(
+            Substitutions::named("specialchars"),
+            Some(Substitutions::SPECIAL_CHARACTERS)
+        );
+        assert_eq!(Substitutions::named("bogus"), None);
+    }
+
+    #[test]
+    fn test_parse_replaces_defaults() {
+        let subs = Substitutions::parse("quotes, macros", Substitutions::NORMAL).unwrap();
+        assert_eq!(subs, Substitutions::QUOTES | Substitutions::MACROS);
+    }
+
+    #[test]
+    fn test_parse_incremental() {
+        let verbatim = Substitutions::VERBATIM;
+        let expected = Substitutions::SPECIAL_CHARACTERS | Substitutions::ATTRIBUTES;
+
+        assert_eq!(
+            Substitutions::parse("attributes+", verbatim).unwrap(),
+            expected
+        );
+        assert_eq!(
+            Substitutions::parse("+attributes", verbatim).unwrap(),
+            expected
+        );
+
+        let subs = Substitutions::parse("-quotes,-macros", Substitutions::NORMAL).unwrap();
+        assert!(!subs.contains(Substitutions::QUOTES));
+        assert!(!subs.contains(Substitutions::MACROS));
+        assert!(subs.contains(Substitutions::ATTRIBUTES));
+    }
+
+    #[test]
+    fn test_parse_mixed_entries_start_from_defaults() {
+        let subs =
+            Substitutions::parse("quotes,-specialcharacters", Substitutions::VERBATIM).unwrap();
+        assert_eq!(subs, Substitutions::QUOTES);
+    }
+
+    #[test]
+    fn test_parse_unknown() {
+        let err = Substitutions::parse("attributes+,sparkles", Substitutions::NORMAL).unwrap_err();
+        assert_eq!(err, SubstitutionError::Unknown("sparkles".to_string()));
+        assert_eq!(err.to_string(), "unknown substitution: sparkles");
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Substitutions::empty().to_string(), "none");
+        assert_eq!(
+            Substitutions::HEADER.to_string(),
+            "specialcharacters,attributes"
+        );
+        let roundtrip =
+            Substitutions::parse(&Substitutions::NORMAL.to_string(), Substitutions::empty());
+        assert_eq!(roundtrip, Ok(Substitutions::NORMAL));
+    }
+}
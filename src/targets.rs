@@ -0,0 +1,191 @@
+//! Attribute-based resolution of image, icon, and inter-document targets
+//!
+//! Multi-directory documentation sites rarely write full paths by hand.
+//! Instead, documents set attributes that converters apply when emitting
+//! references:
+//!
+//! | Attribute        | Applies to                          | Default              |
+//! |------------------|-------------------------------------|----------------------|
+//! | `imagesdir`      | Relative image targets              | (none)               |
+//! | `iconsdir`       | Icon macros and admonition icons    | `{imagesdir}/icons`  |
+//! | `icontype`       | Icon file extension                 | `png`                |
+//! | `relfileprefix`  | Inter-document cross references     | (none)               |
+//! | `relfilesuffix`  | Inter-document cross references     | `{outfilesuffix}`    |
+//! | `outfilesuffix`  | Fallback for `relfilesuffix`        | `.html`              |
+//!
+//! Targets that are URLs (`https://...`), data URIs, or absolute paths are never
+//! prefixed.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::attributes::Attributes;
+//! use doctora::targets::{image_target, xref_document_target};
+//!
+//! let mut attrs = Attributes::new();
+//! attrs.set("imagesdir", "assets/img");
+//! attrs.set("relfileprefix", "../");
+//!
+//! assert_eq!(image_target("logo.png", &attrs), "assets/img/logo.png");
+//! assert_eq!(image_target("https://example.com/a.png", &attrs), "https://example.com/a.png");
+//! assert_eq!(xref_document_target("guide/setup.adoc#install", &attrs), "../guide/setup.html#install");
+//! ```
+
+use crate::attributes::Attributes;
+
+/// Resolve an image macro target against `imagesdir`
+pub fn image_target(target: &str, attributes: &Attributes) -> String {
+    match attributes.get("imagesdir") {
+        Some(dir) if !is_absolute(target) => join(dir, target),
+        _ => target.to_string(),
+    }
+}
+
+/// Resolve the path of the icon `name` against `iconsdir` and `icontype`
+///
+/// When `iconsdir` is not set, icons live in an `icons` folder below
+/// `imagesdir` (or `./images/icons` without `imagesdir`).
+pub fn icon_target(name: &str, attributes: &Attributes) -> String {
+    let dir = match (attributes.get("iconsdir"), attributes.get("imagesdir")) {
+        (Some(dir), _) => dir.to_string(),
+        (None, Some(images)) => join(images, "icons"),
+        (None, None) => "./images/icons".to_string(),
+    };
+    let icontype = attributes.get("icontype").unwrap_or("png");
+    join(&dir, &format!("{}.{}", name, icontype))
+}
+
+/// Resolve an inter-document cross reference target (`other.adoc#anchor`)
+///
+/// The AsciiDoc extension is replaced by `relfilesuffix` (falling back to
+/// `outfilesuffix`, then `.html`) and `relfileprefix` is prepended. The fragment
+/// is preserved. Absolute targets are returned unchanged.
+pub fn xref_document_target(target: &str, attributes: &Attributes) -> String {
+    if is_absolute(target) {
+        return target.to_string();
+    }
+
+    let (path, fragment) = match target.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment)),
+        None => (target, None),
+    };
+    let stem = strip_asciidoc_extension(path);
+    let suffix = attributes
+        .get("relfilesuffix")
+        .or_else(|| attributes.get("outfilesuffix"))
+        .unwrap_or(".html");
+    let prefix = attributes.get("relfileprefix").unwrap_or("");
+
+    let mut out = format!("{}{}{}", prefix, stem, suffix);
+    if let Some(fragment) = fragment {
+        out.push('#');
+        out.push_str(fragment);
+    }
+    out
+}
+
+/// Returns true for URLs, data URIs, and absolute paths
+fn is_absolute(target: &str) -> bool {
+    target.starts_with('/') || target.starts_with("data:") || target.contains("://")
+}
+
+/// Remove a trailing `.adoc` / `.asciidoc` / `.asc` / `.ad` extension
+fn strip_asciidoc_extension(path: &str) -> &str {
+    [".adoc", ".asciidoc", ".asc", ".ad"]
+        .iter()
+        .find_map(|ext| path.strip_suffix(ext))
+        .unwrap_or(path)
+}
+
+/// Join a directory and a relative path with exactly one `/`
+fn join(dir: &str, path: &str) -> String {
+    if dir.is_empty() {
+        return path.to_string();
+    }
+    format!("{}/{}", dir.trim_end_matches('/'), path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attrs(pairs: &[(&str, &str)]) -> Attributes {
+        let mut attributes = Attributes::new();
+        for (name, value) in pairs {
+            attributes.set(*name, *value);
+        }
+        attributes
+    }
+
+    #[test]
+    fn test_image_without_imagesdir() {
+        assert_eq!(image_target("a.png", &Attributes::new()), "a.png");
+    }
+
+    #[test]
+    fn test_image_with_imagesdir() {
+        let attributes = attrs(&[("imagesdir", "images/")]);
+        assert_eq!(image_target("a.png", &attributes), "images/a.png");
+        assert_eq!(image_target("/abs/a.png", &attributes), "/abs/a.png");
+        assert_eq!(
+            image_target("data:image/png;base64,AAA", &attributes),
+            "data:image/png;base64,AAA"
+        );
+    }
+
+    #[test]
+    fn test_empty_imagesdir() {
+        let attributes = attrs(&[("imagesdir", "")]);
+        assert_eq!(image_target("a.png", &attributes), "a.png");
+    }
+
+    #[test]
+    fn test_icon_defaults() {
+        assert_eq!(
+            icon_target("note", &Attributes::new()),
+            "./images/icons/note.png"
+        );
+
+        let attributes = attrs(&[("imagesdir", "img")]);
+        assert_eq!(icon_target("tip", &attributes), "img/icons/tip.png");
+    }
+
+    #[test]
+    fn test_icon_overrides() {
+        let attributes = attrs(&[("iconsdir", "/static/icons"), ("icontype", "svg")]);
+        assert_eq!(
+            icon_target("warning", &attributes),
+            "/static/icons/warning.svg"
+        );
+    }
+
+    #[test]
+    fn test_xref_default_suffix() {
+        assert_eq!(
+            xref_document_target("setup.adoc", &Attributes::new()),
+            "setup.html"
+        );
+        assert_eq!(
+            xref_document_target("setup.adoc#a", &Attributes::new()),
+            "setup.html#a"
+        );
+    }
+
+    #[test]
+    fn test_xref_suffix_precedence() {
+        let attributes = attrs(&[("outfilesuffix", ".xhtml")]);
+        assert_eq!(xref_document_target("a.adoc", &attributes), "a.xhtml");
+
+        let attributes = attrs(&[("outfilesuffix", ".xhtml"), ("relfilesuffix", "/")]);
+        assert_eq!(xref_document_target("a.adoc", &attributes), "a/");
+    }
+
+    #[test]
+    fn test_xref_absolute_unchanged() {
+        let attributes = attrs(&[("relfileprefix", "../")]);
+        assert_eq!(
+            xref_document_target("https://example.com/a.adoc", &attributes),
+            "https://example.com/a.adoc"
+        );
+    }
+}
@@ -0,0 +1,596 @@
+//! Shareable HTML themes for batch builds
+//!
+//! A theme packages the look of a documentation site in one directory, so
+//! several sites can share it, like an Antora UI bundle:
+//!
+//! ```text
+//! acme-theme/
+//!   theme.json         name, stylesheets and scripts
+//!   page.html          page template (optional)
+//!   templates/         role templates, one `<role>.html` per role (optional)
+//!   static/            assets copied to the site as is (optional)
+//! ```
+//!
+//! `theme.json` names the theme and lists the stylesheets and scripts every
+//! page loads, as paths inside `static/`:
+//!
+//! ```json
+//! { "name": "acme", "stylesheets": ["css/site.css"], "scripts": ["js/site.js"] }
+//! ```
+//!
+//! [`Theme::load`] reads the directory; [`build_site`] then renders every
+//! page of a [`BatchResult`] with it and lists the files of the site, which
+//! [`write_site`] writes out. The files of `static/` are published under
+//! [`ASSET_DIR`] at the site root, and stylesheet and script references point
+//! there.
+//!
+//! # Page Templates
+//!
+//! Without `page.html` pages are standalone [`render_html`] output. With it,
+//! each page is the template with these placeholders replaced:
+//!
+//! | Placeholder       | Replaced with                                    |
+//! |-------------------|--------------------------------------------------|
+//! | `{{title}}`       | Document title (`Untitled` without one), escaped |
+//! | `{{lang}}`        | `lang` attribute (default `en`), escaped         |
+//! | `{{stylesheets}}` | `<link>` elements for the theme's stylesheets    |
+//! | `{{nav}}`         | Navigation sidebar ([`Sidebar`])                 |
+//! | `{{content}}`     | The rendered document                            |
+//! | `{{scripts}}`     | `<script>` elements for the theme's scripts      |
+//! | `{{ui}}`          | URL of the published `static/` directory         |
+//!
+//! Other `{{...}}` text is kept as is.
+//!
+//! # Role Templates
+//!
+//! `templates/api.html` becomes the
+//! [`BlockTemplate`](crate::render::html::BlockTemplate) for the `api`
+//! role, with `{{content}}` replaced by the block's rendered content. Only
+//! blocks holding other blocks (open, example, sidebar and quote blocks) use
+//! it; other blocks with the role get the default rendering.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::batch::{FailurePolicy, parse_batch};
+//! use doctora::render::html::HtmlOptions;
+//! use doctora::site::SiteOptions;
+//! use doctora::theme::{Theme, build_site};
+//!
+//! let theme = Theme::from_config(r#"{"name": "acme", "stylesheets": ["site.css"]}"#)
+//!     .unwrap()
+//!     .page_template("<title>{{title}}</title>\n{{stylesheets}}<main>\n{{content}}</main>\n")
+//!     .asset("site.css", "body { margin: 0; }");
+//!
+//! let result = parse_batch([("index.adoc", "= Home\n\nWelcome.\n")], FailurePolicy::Abort).unwrap();
+//! let files = build_site(&result, &theme, &SiteOptions::new("/docs"), &HtmlOptions::new());
+//!
+//! assert_eq!(files[0].path, "index.html");
+//! assert_eq!(
+//!     String::from_utf8_lossy(&files[0].contents),
+//!     "<title>Home</title>\n\
+//!      <link rel=\"stylesheet\" href=\"/docs/_/site.css\">\n\
+//!      <main>\n<h1>Home</h1>\n<p>Welcome.</p>\n</main>\n"
+//! );
+//! assert_eq!(files[1].path, "_/site.css");
+//! ```
+
+use crate::ast::{Block, Document};
+use crate::batch::BatchResult;
+use crate::render::html::{
+    HtmlOptions, TemplateContext, document_title, escape, render_html, script_tags,
+    stylesheet_links,
+};
+use crate::render::nav::Sidebar;
+use crate::site::{SiteOptions, navigation};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Directory of the site the theme's assets are published under
+pub const ASSET_DIR: &str = "_";
+
+/// Error produced when a theme cannot be loaded or a site cannot be written
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ThemeError {
+    /// A file could not be read or written
+    #[error("cannot access {}: {message}", path.display())]
+    Io { path: PathBuf, message: String },
+    /// `theme.json` is not valid
+    #[error("invalid theme config: {message}")]
+    Config { message: String },
+}
+
+/// A file of a static asset directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeAsset {
+    /// Path relative to `static/`, with `/` separators
+    pub path: String,
+    /// File contents
+    pub contents: Vec<u8>,
+}
+
+/// A theme (see the module documentation)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Theme {
+    /// Name from `theme.json`
+    pub name: String,
+    /// Stylesheets every page links, relative to `static/`
+    pub stylesheets: Vec<String>,
+    /// Scripts every page loads, relative to `static/`
+    pub scripts: Vec<String>,
+    /// Contents of `page.html`
+    pub page: Option<String>,
+    /// Role templates by role
+    pub templates: BTreeMap<String, String>,
+    /// Files of `static/`, sorted by path
+    pub assets: Vec<ThemeAsset>,
+}
+
+impl Theme {
+    /// Loads the theme in `dir`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ThemeError::Io`] if `theme.json` or another theme file
+    /// cannot be read, and [`ThemeError::Config`] if `theme.json` is invalid.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, ThemeError> {
+        let dir = dir.as_ref();
+        let config = dir.join("theme.json");
+        let mut theme = Self::from_config(&read_to_string(&config)?)?;
+
+        let page = dir.join("page.html");
+        if page.is_file() {
+            theme.page = Some(read_to_string(&page)?);
+        }
+        let templates = dir.join("templates");
+        if templates.is_dir() {
+            for path in list_files(&templates).map_err(|(dir, err)| io_error(&dir, err))? {
+                if path
+                    .extension()
+                    .is_some_and(|extension| extension == "html")
+                    && let Some(role) = path.file_stem().and_then(|stem| stem.to_str())
+                {
+                    theme
+                        .templates
+                        .insert(role.to_string(), read_to_string(&path)?);
+                }
+            }
+        }
+        let assets = dir.join("static");
+        if assets.is_dir() {
+            for path in list_files(&assets).map_err(|(dir, err)| io_error(&dir, err))? {
+                let contents = fs::read(&path).map_err(|err| io_error(&path, err))?;
+                let relative = path.strip_prefix(&assets).unwrap_or(&path);
+                theme.assets.push(ThemeAsset {
+                    path: relative.to_string_lossy().replace('\\', "/"),
+                    contents,
+                });
+            }
+        }
+        Ok(theme)
+    }
+
+    /// Creates a theme from the contents of `theme.json`, without templates
+    /// or assets
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ThemeError::Config`] if `json` is not an object with a
+    /// string `name` and string arrays `stylesheets` and `scripts` (both
+    /// optional).
+    pub fn from_config(json: &str) -> Result<Self, ThemeError> {
+        let config: Value = serde_json::from_str(json).map_err(|err| ThemeError::Config {
+            message: err.to_string(),
+        })?;
+        let name =
+            config
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or_else(|| ThemeError::Config {
+                    message: "missing string field `name`".to_string(),
+                })?;
+        Ok(Self {
+            name: name.to_string(),
+            stylesheets: string_list(&config, "stylesheets")?,
+            scripts: string_list(&config, "scripts")?,
+            ..Self::default()
+        })
+    }
+
+    /// Sets the page template
+    pub fn page_template(mut self, template: impl Into<String>) -> Self {
+        self.page = Some(template.into());
+        self
+    }
+
+    /// Renders blocks with `role` using `template`
+    pub fn role_template(mut self, role: impl Into<String>, template: impl Into<String>) -> Self {
+        self.templates.insert(role.into(), template.into());
+        self
+    }
+
+    /// Adds a static asset at `path`, relative to `static/`
+    pub fn asset(mut self, path: impl Into<String>, contents: impl Into<Vec<u8>>) -> Self {
+        let path = path.into();
+        self.assets.retain(|asset| asset.path != path);
+        self.assets.push(ThemeAsset {
+            path,
+            contents: contents.into(),
+        });
+        self.assets.sort_by(|a, b| a.path.cmp(&b.path));
+        self
+    }
+
+    /// URL of the published `static/` directory of a site
+    pub fn ui_url(&self, site: &SiteOptions) -> String {
+        format!("{}/{ASSET_DIR}", site.base_url())
+    }
+
+    /// `options` with the theme's stylesheets, scripts and role templates
+    /// added
+    ///
+    /// The options' own stylesheets and scripts come first, and their role
+    /// templates win over the theme's.
+    pub fn html_options(&self, options: &HtmlOptions, site: &SiteOptions) -> HtmlOptions {
+        let ui = self.ui_url(site);
+        let mut options = options.clone();
+        for href in &self.stylesheets {
+            options.stylesheets.push(format!("{ui}/{href}"));
+        }
+        for src in &self.scripts {
+            options.scripts.push(format!("{ui}/{src}"));
+        }
+        for (role, template) in &self.templates {
+            if options.templates.get(role).is_none() {
+                options
+                    .templates
+                    .register(role.clone(), role_template(template.clone()));
+            }
+        }
+        options
+    }
+
+    /// Render `doc` as a page of a site
+    ///
+    /// `options` should come from [`Theme::html_options`]; `nav` is the
+    /// sidebar HTML for `{{nav}}`.
+    pub fn render_page(
+        &self,
+        doc: &Document,
+        options: &HtmlOptions,
+        site: &SiteOptions,
+        nav: &str,
+    ) -> String {
+        let Some(page) = &self.page else {
+            return render_html(doc, &options.clone().standalone(true));
+        };
+        let content = render_html(doc, &options.clone().standalone(false));
+        let title = document_title(doc).unwrap_or("Untitled");
+        let lang = doc.attributes.get("lang").unwrap_or("en");
+        fill(page, |name| match name {
+            "title" => Some(escape(title)),
+            "lang" => Some(escape(lang)),
+            "stylesheets" => Some(stylesheet_links(options)),
+            "nav" => Some(nav.to_string()),
+            "content" => Some(content.clone()),
+            "scripts" => Some(script_tags(options)),
+            "ui" => Some(escape(&self.ui_url(site))),
+            _ => None,
+        })
+    }
+}
+
+/// A file of a built site
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiteFile {
+    /// Path relative to the site root, with `/` separators
+    pub path: String,
+    /// File contents
+    pub contents: Vec<u8>,
+}
+
+/// Render the documents of `result` with `theme`
+///
+/// Returns one page per document, at [`SiteOptions::page_path`] and in batch
+/// order, followed by the theme's assets under [`ASSET_DIR`]. Each page's
+/// `{{nav}}` is a [`Sidebar`] with the page active and its outline nested;
+/// stub documents are rendered too but have no sidebar entry.
+pub fn build_site(
+    result: &BatchResult,
+    theme: &Theme,
+    site: &SiteOptions,
+    options: &HtmlOptions,
+) -> Vec<SiteFile> {
+    let nav = navigation(result, site);
+    let options = theme.html_options(options, site);
+    let mut files = Vec::new();
+    for document in &result.documents {
+        let mut sidebar = Sidebar::new(&nav);
+        if let Some(index) = nav.pages.iter().position(|page| page.name == document.name) {
+            sidebar = sidebar.active(index).outline(&document.document);
+        }
+        let html = theme.render_page(&document.document, &options, site, &sidebar.render());
+        files.push(SiteFile {
+            path: site.page_path(&document.name),
+            contents: html.into_bytes(),
+        });
+    }
+    for asset in &theme.assets {
+        files.push(SiteFile {
+            path: format!("{ASSET_DIR}/{}", asset.path),
+            contents: asset.contents.clone(),
+        });
+    }
+    files
+}
+
+/// Write `files` under `dir`, creating directories as needed
+///
+/// # Errors
+///
+/// Returns [`ThemeError::Io`] for the first file or directory that cannot be
+/// written.
+pub fn write_site(dir: impl AsRef<Path>, files: &[SiteFile]) -> Result<(), ThemeError> {
+    let dir = dir.as_ref();
+    for file in files {
+        let path = dir.join(&file.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| io_error(parent, err))?;
+        }
+        fs::write(&path, &file.contents).map_err(|err| io_error(&path, err))?;
+    }
+    Ok(())
+}
+
+/// A role template wrapping the rendered content of container blocks
+fn role_template(
+    template: String,
+) -> impl Fn(&Block, &TemplateContext<'_>) -> Option<String> + Send + Sync {
+    move |block, context| {
+        let (Block::Open { content, .. }
+        | Block::Admonition { content, .. }
+        | Block::Example { content, .. }
+        | Block::Sidebar { content, .. }
+        | Block::Quote { content, .. }) = block
+        else {
+            return None;
+        };
+        let content = context.render_blocks(content);
+        Some(fill(&template, |name| {
+            (name == "content").then(|| content.clone())
+        }))
+    }
+}
+
+/// Replace the `{{name}}` placeholders in `template` that `value` knows
+fn fill(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let replaced = after.find("}}").and_then(|end| {
+            let text = value(after[..end].trim())?;
+            Some((text, &after[end + 2..]))
+        });
+        match replaced {
+            Some((text, remainder)) => {
+                out.push_str(&text);
+                rest = remainder;
+            }
+            None => {
+                out.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The strings in the array field `field` of `config`
+fn string_list(config: &Value, field: &str) -> Result<Vec<String>, ThemeError> {
+    let Some(value) = config.get(field) else {
+        return Ok(Vec::new());
+    };
+    value
+        .as_array()
+        .and_then(|items| {
+            items
+                .iter()
+                .map(|item| item.as_str().map(str::to_string))
+                .collect()
+        })
+        .ok_or_else(|| ThemeError::Config {
+            message: format!("`{field}` must be an array of strings"),
+        })
+}
+
+/// All files under `dir`, sorted by path; on failure, the directory that
+/// could not be read and the error
+pub(crate) fn list_files(dir: &Path) -> Result<Vec<PathBuf>, (PathBuf, std::io::Error)> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) => return Err((dir, err)),
+        };
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(err) => return Err((dir, err)),
+            };
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn read_to_string(path: &Path) -> Result<String, ThemeError> {
+    fs::read_to_string(path).map_err(|err| io_error(path, err))
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> ThemeError {
+    ThemeError::Io {
+        path: path.to_path_buf(),
+        message: err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::{FailurePolicy, parse_batch};
+
+    fn page(files: &[SiteFile], path: &str) -> String {
+        let file = files.iter().find(|file| file.path == path).unwrap();
+        String::from_utf8(file.contents.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_config() {
+        let theme = Theme::from_config(
+            r#"{"name": "acme", "stylesheets": ["a.css"], "scripts": ["b.js"]}"#,
+        )
+        .unwrap();
+        assert_eq!(theme.name, "acme");
+        assert_eq!(theme.stylesheets, ["a.css"]);
+        assert_eq!(theme.scripts, ["b.js"]);
+
+        assert!(matches!(
+            Theme::from_config("{}"),
+            Err(ThemeError::Config { .. })
+        ));
+        assert!(matches!(
+            Theme::from_config(r#"{"name": "acme", "scripts": [1]}"#),
+            Err(ThemeError::Config { .. })
+        ));
+        assert!(matches!(
+            Theme::from_config("name: acme"),
+            Err(ThemeError::Config { .. })
+        ));
+    }
+
+    #[test]
+    fn test_default_page() {
+        let theme = Theme::from_config(
+            r#"{"name": "acme", "stylesheets": ["site.css"], "scripts": ["site.js"]}"#,
+        )
+        .unwrap();
+        let result = parse_batch([("a.adoc", "Text.\n")], FailurePolicy::Abort).unwrap();
+        let options = HtmlOptions::new().stylesheet("extra.css");
+        let files = build_site(&result, &theme, &SiteOptions::new(""), &options);
+
+        let html = page(&files, "a.html");
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains(
+            "<link rel=\"stylesheet\" href=\"extra.css\">\n<link rel=\"stylesheet\" href=\"/_/site.css\">\n</head>"
+        ));
+        assert!(html.contains("<script src=\"/_/site.js\"></script>\n</body>"));
+    }
+
+    #[test]
+    fn test_page_template() {
+        let theme = Theme::from_config(r#"{"name": "acme"}"#)
+            .unwrap()
+            .page_template(
+                "<html lang=\"{{lang}}\">{{ title }}|{{ui}}|{{unknown}}|{{nav}}{{content}}{{",
+            );
+        let inputs = [
+            ("index.adoc", "= A & B\n:lang: de\n\n== Setup\n\nText.\n"),
+            ("other.adoc", "= Other\n"),
+        ];
+        let result = parse_batch(inputs, FailurePolicy::Abort).unwrap();
+        let files = build_site(
+            &result,
+            &theme,
+            &SiteOptions::new("/docs"),
+            &HtmlOptions::new(),
+        );
+
+        let html = page(&files, "index.html");
+        assert!(
+            html.starts_with("<html lang=\"de\">A &amp; B|/docs/_|{{unknown}}|<ul class=\"nav\">")
+        );
+        assert!(html.contains(
+            "<li class=\"active\"><a href=\"/docs/index.html\" aria-current=\"page\">A &amp; B</a>"
+        ));
+        assert!(html.contains("<p>Text.</p>\n"));
+        assert!(html.ends_with("{{"));
+        assert!(page(&files, "other.html").contains("aria-current=\"page\">Other</a>"));
+    }
+
+    #[test]
+    fn test_role_templates() {
+        let theme = Theme::from_config(r#"{"name": "acme"}"#)
+            .unwrap()
+            .role_template("card", "<div class=\"card\">\n{{content}}</div>\n");
+        let doc = crate::parse_document("[.card]\n--\nInside.\n--\n").unwrap();
+        let options = theme.html_options(&HtmlOptions::new(), &SiteOptions::new(""));
+        assert_eq!(
+            render_html(&doc, &options),
+            "<div class=\"card\">\n<p>Inside.</p>\n</div>\n"
+        );
+
+        // Templates set on the options win over the theme's
+        let own = HtmlOptions::new().template("card", |_: &Block, _: &TemplateContext<'_>| {
+            Some("own\n".to_string())
+        });
+        let options = theme.html_options(&own, &SiteOptions::new(""));
+        assert_eq!(render_html(&doc, &options), "own\n");
+    }
+
+    #[test]
+    fn test_load_and_write() {
+        let root = std::env::temp_dir().join(format!("doctora-theme-{}", std::process::id()));
+        let theme_dir = root.join("theme");
+        fs::create_dir_all(theme_dir.join("static/css")).unwrap();
+        fs::create_dir_all(theme_dir.join("templates")).unwrap();
+        fs::write(
+            theme_dir.join("theme.json"),
+            r#"{"name": "acme", "stylesheets": ["css/site.css"]}"#,
+        )
+        .unwrap();
+        fs::write(theme_dir.join("page.html"), "{{title}}\n{{content}}").unwrap();
+        fs::write(theme_dir.join("templates/card.html"), "{{content}}").unwrap();
+        fs::write(theme_dir.join("static/css/site.css"), "body {}").unwrap();
+        fs::write(theme_dir.join("static/logo.svg"), "<svg/>").unwrap();
+
+        let theme = Theme::load(&theme_dir).unwrap();
+        assert_eq!(theme.name, "acme");
+        assert_eq!(theme.page.as_deref(), Some("{{title}}\n{{content}}"));
+        assert!(theme.templates.contains_key("card"));
+        let paths: Vec<&str> = theme
+            .assets
+            .iter()
+            .map(|asset| asset.path.as_str())
+            .collect();
+        assert_eq!(paths, ["css/site.css", "logo.svg"]);
+
+        let result = parse_batch([("guide/a.adoc", "= A\n")], FailurePolicy::Abort).unwrap();
+        let files = build_site(&result, &theme, &SiteOptions::new(""), &HtmlOptions::new());
+        let out = root.join("out");
+        write_site(&out, &files).unwrap();
+        assert_eq!(
+            fs::read_to_string(out.join("guide/a.html")).unwrap(),
+            "A\n<h1>A</h1>\n"
+        );
+        assert_eq!(
+            fs::read_to_string(out.join("_/css/site.css")).unwrap(),
+            "body {}"
+        );
+
+        let missing = Theme::load(root.join("missing"));
+        fs::remove_dir_all(&root).unwrap();
+        assert!(matches!(missing, Err(ThemeError::Io { .. })));
+    }
+}
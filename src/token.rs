@@ -4,13 +4,160 @@
 //! For the POC, we support:
 //! - Headings (= through ======)
 //! - Paragraph text
-//! - Bold formatting (**)
+//! - Bold formatting (* and **)
 //! - Italic formatting (_)
+//! - Monospace formatting (`)
+//! - Highlight (#), superscript (^) and subscript (~) formatting
 //! - Newlines and whitespace
 //!
 //! See `docs/design/features/core-parser.md` for the full token specification.
+//!
+//! # Tokenization Stability
+//!
+//! AsciiDoc keeps growing new delimiters (`|`, `#`, `` ` ``, `[`, `]`, ...). To make
+//! sure adding one can never silently change how existing documents tokenize,
+//! the lexer follows two rules:
+//!
+//! 1. [`Token::Word`] never contains ASCII punctuation. Every ASCII punctuation
+//!    character is either part of a dedicated delimiter token or lexed on its own
+//!    as [`Token::Punctuation`]. Adding a delimiter therefore only re-labels
+//!    characters that were already separate tokens; word boundaries never move.
+//! 2. The lexer is total: every input character is either skipped inline
+//!    whitespace (space, tab, carriage return) or part of a token, so lexing never
+//!    produces errors and never drops text.
+//!
+//! Any intended change in tokenization shows up in the token snapshot suite
+//! (`tests/token_snapshots.rs`), which lexes the documents in `tests/corpus/` and
+//! compares against checked-in `.tokens` files.
+//!
+//! # List Markers
+//!
+//! Whether `*` starts a list item or is just punctuation depends on its
+//! position in the line, which a Logos lexer cannot see. [`lex_with_spans`]
+//! therefore re-labels the tokens at the start of each line: a run of `*`
+//! (or a single `-`) followed by whitespace becomes
+//! [`Token::UnorderedMarker`], a run of `.` becomes [`Token::OrderedMarker`],
+//! and a line containing only `+` becomes [`Token::ListContinuation`]. The
+//! marker's span covers the whole run, so the parser can tell `*` from `**`.
+//!
+//! A `*` run only becomes a marker when whitespace follows it, so `*bold*`
+//! and `**bold**` at the start of a line stay formatting marks, while
+//! `** item` is a nested list item. A run of more than five `*` followed by
+//! whitespace is neither: it stays literal text. Mid-line, `*` is never a
+//! marker.
+//!
+//! The same pass finds description list separators: the first `::`, `:::`,
+//! `::::`, or `;;` on a line that starts with text becomes
+//! [`Token::DescriptionSeparator`] when it is followed by whitespace or the end
+//! of the line. `image::file.png[]` and `a::b` are left alone.
+//!
+//! A line of four or more `_`, `=`, or `*` becomes
+//! [`Token::QuoteDelimiter`], [`Token::ExampleDelimiter`], or
+//! [`Token::SidebarDelimiter`], and a line of exactly `--` becomes
+//! [`Token::OpenDelimiter`]. Unlike listing blocks, these blocks contain
+//! regular AsciiDoc, so their content is lexed normally.
+//!
+//! # Tables
+//!
+//! A line consisting of `|===` becomes [`Token::TableDelimiter`], and between
+//! two such lines every `|` becomes [`Token::CellSeparator`]. A cell
+//! specifier written directly before the `|` (`2+|` spans two columns, `.3+|`
+//! three rows, `2.3+|` both, `3*|` repeats the cell) is included in the
+//! separator's span. List markers and description list separators are not
+//! recognized inside tables.
+//!
+//! # Delimited Blocks
+//!
+//! The content of listing (`----`), literal (`....`), comment (`////`), and
+//! passthrough (`++++`) blocks is verbatim, so [`lex_with_spans`] switches to a
+//! verbatim mode when a line consists of such a delimiter: it emits
+//! [`Token::ListingDelimiter`], [`Token::LiteralDelimiter`],
+//! [`Token::CommentDelimiter`], or [`Token::PassthroughDelimiter`], the whole
+//! content up to the matching closing delimiter as a single
+//! [`Token::Verbatim`], and the closing delimiter. A block without a closing
+//! delimiter runs to the end of the document.
+//!
+//! A line starting with `//` (in the first column, and not `///`) is a
+//! [`Token::LineComment`] covering the whole line, so nothing in a comment is
+//! lexed as markup.
+//!
+//! ```
+//! use doctora::token::{Token, lex_with_spans};
+//!
+//! let tokens: Vec<Token> = lex_with_spans("----\n**not bold**\n----")
+//!     .into_iter()
+//!     .map(|(token, _)| token)
+//!     .collect();
+//! assert_eq!(
+//!     tokens,
+//!     vec![
+//!         Token::ListingDelimiter,
+//!         Token::Newline,
+//!         Token::Verbatim,
+//!         Token::Newline,
+//!         Token::ListingDelimiter,
+//!     ]
+//! );
+//! ```
+//!
+//! # URLs
+//!
+//! A URL is many tokens to a Logos lexer (`https`, `:`, `/`, `/`, ...), which
+//! [`lex_with_spans`] merges into one [`Token::Url`] when a word `http`,
+//! `https`, `ftp`, or `irc` is followed by `://`. The URL runs to whitespace,
+//! a `` ` ``, or one of `[]<>"`, and trailing `.,;:!?)'` and formatting marks
+//! (`*`, `_`, `#`) are left out, so the period ending `see https://example.com.`
+//! stays punctuation and `*https://example.com*` is bold. Merging keeps formatting
+//! marks inside URLs (`https://example.com/a_b_c`, `https://example.com/#top`)
+//! from pairing.
+//!
+//! ```
+//! use doctora::token::{Token, lex_with_spans};
+//!
+//! let source = "(https://example.com/a_b).";
+//! let tokens: Vec<(Token, &str)> = lex_with_spans(source)
+//!     .into_iter()
+//!     .map(|(token, span)| (token, &source[span]))
+//!     .collect();
+//! assert_eq!(
+//!     tokens,
+//!     vec![
+//!         (Token::Punctuation, "("),
+//!         (Token::Url, "https://example.com/a_b"),
+//!         (Token::Punctuation, ")"),
+//!         (Token::Punctuation, "."),
+//!     ]
+//! );
+//! ```
+//!
+//! # Spans
+//!
+//! [`lex_with_spans`] keeps the byte range of every token in the source, which
+//! the parser uses to recover text. [`LineIndex`] maps byte offsets to 1-based
+//! line and column numbers for diagnostics:
+//!
+//! ```
+//! use doctora::token::{LineIndex, Token, lex_with_spans};
+//!
+//! let source = "= Title\n\nSome text";
+//! let tokens = lex_with_spans(source);
+//! let index = LineIndex::new(source);
+//!
+//! let (token, span) = &tokens[3];
+//! assert_eq!(*token, Token::Word);
+//! assert_eq!(&source[span.clone()], "Some");
+//! assert_eq!(index.line_col(span.start), (3, 1));
+//! ```
 
 use logos::Logos;
+use std::ops::Range;
+
+/// Byte range of a token in the source text
+pub type Span = Range<usize>;
+
+/// A token paired with its byte range in the source text, as the lexer
+/// yields it
+pub type SpannedToken = (Token, Span);
 
 /// Minimal token set for AsciiDoc POC
 ///
@@ -29,7 +176,7 @@ use logos::Logos;
 /// assert_eq!(lex.next(), Some(Ok(Token::Heading1)));
 /// ```
 #[derive(Logos, Debug, Clone, PartialEq)]
-#[logos(skip r"[ \t]+")] // Skip inline whitespace (spaces and tabs)
+#[logos(skip r"[ \t\r]+")] // Skip inline whitespace (spaces, tabs, carriage returns)
 pub enum Token {
     // ===== Document Structure =====
     /// Level 1 heading (=)
@@ -87,30 +234,144 @@ pub enum Token {
     #[token("_")]
     ItalicDelimiter,
 
+    /// Monospace delimiter (`)
+    ///
+    /// Used for monospace (inline code) formatting.
+    /// Example: "`code`"
+    #[token("`")]
+    MonospaceDelimiter,
+
+    /// Highlight delimiter (#)
+    ///
+    /// Used for highlighted (marked) text.
+    /// Example: "#important#"
+    #[token("#")]
+    HighlightDelimiter,
+
+    /// Superscript delimiter (^)
+    ///
+    /// Used for superscript text.
+    /// Example: "E=mc^2^"
+    #[token("^")]
+    SuperscriptDelimiter,
+
+    /// Subscript delimiter (~)
+    ///
+    /// Used for subscript text.
+    /// Example: "H~2~O"
+    #[token("~")]
+    SubscriptDelimiter,
+
     // ===== Whitespace and Structure =====
     /// Single newline
     ///
-    /// Marks the end of a line.
-    #[token("\n")]
+    /// Marks the end of a line. Indentation at the start of the next line is
+    /// part of the token, so the lexer never has to backtrack out of a
+    /// [`Token::BlankLine`] candidate.
+    #[regex(r"\n[ \t\r]*")]
     Newline,
 
     /// Blank line (two or more consecutive newlines)
     ///
     /// Separates blocks in AsciiDoc (paragraphs, sections, etc.).
     /// This is significant whitespace in AsciiDoc.
-    #[regex(r"\n\n+")]
+    /// Lines containing only spaces, tabs, or carriage returns count as blank.
+    #[regex(r"\n[ \t\r]*(\n[ \t\r]*)+")]
     BlankLine,
 
     // ===== Content =====
-    /// A word or sequence of non-special characters
-    ///
-    /// This matches any sequence of characters that aren't formatting delimiters,
-    /// whitespace, or special characters.
+    /// A word: a run of characters that are neither ASCII whitespace nor
+    /// ASCII punctuation
     ///
-    /// Note: The regex is ordered after all other tokens so specific patterns
-    /// (like **) are matched first.
-    #[regex(r"[^\s\*_=]+")]
+    /// Non-ASCII characters (including Unicode whitespace such as no-break
+    /// spaces) are always part of words. See the module documentation for why
+    /// ASCII punctuation is excluded.
+    #[regex(r"[^ \t\r\n!-/:-@\[-`{-~]+")]
     Word,
+
+    /// A single ASCII punctuation character with no dedicated token
+    ///
+    /// Reserves every punctuation character for future delimiters. Parsers
+    /// treat it as plain text unless it is part of recognized syntax.
+    /// Characters claimed by dedicated tokens (`=`, `_`, `` ` ``, `#`, `^`,
+    /// `~`) are excluded from the class; when a new delimiter claims a
+    /// character, remove it here as well.
+    #[regex(r#"[!"$-/:-<>-@\[-\]{-}]"#)]
+    Punctuation,
+
+    // ===== Line-start syntax =====
+    // Not produced by `Token::lexer`; `lex_with_spans` assigns these from line
+    // context (see "List Markers" in the module documentation).
+    /// Unordered list marker (`*` to `*****`, or `-`)
+    ///
+    /// Example: "* item", "** nested item"
+    UnorderedMarker,
+
+    /// Ordered list marker (`.` to `.....`)
+    ///
+    /// Example: ". step", ".. sub-step"
+    OrderedMarker,
+
+    /// List continuation (a line containing only `+`)
+    ///
+    /// Attaches the following block to the preceding list item.
+    ListContinuation,
+
+    /// Description list separator (`::`, `:::`, `::::`, or `;;`)
+    ///
+    /// Separates a term from its description.
+    /// Example: "CPU:: The brain of the computer"
+    DescriptionSeparator,
+
+    /// Listing block delimiter (a line of four or more `-`)
+    ListingDelimiter,
+
+    /// Literal block delimiter (a line of four or more `.`)
+    LiteralDelimiter,
+
+    /// Comment block delimiter (a line of four or more `/`)
+    CommentDelimiter,
+
+    /// Passthrough block delimiter (a line of four or more `+`)
+    PassthroughDelimiter,
+
+    /// A line comment: a line starting with `//` (but not `///`), lexed as a
+    /// whole
+    ///
+    /// Example: "// TODO: expand this section"
+    LineComment,
+
+    /// Verbatim content of a listing, literal, comment, or passthrough block,
+    /// lexed as a whole
+    Verbatim,
+
+    /// Quote block delimiter (a line of four or more `_`)
+    QuoteDelimiter,
+
+    /// Example block delimiter (a line of four or more `=`)
+    ExampleDelimiter,
+
+    /// Sidebar block delimiter (a line of four or more `*`)
+    SidebarDelimiter,
+
+    /// Open block delimiter (a line of exactly `--`)
+    OpenDelimiter,
+
+    /// Table delimiter (a line containing only `|===`)
+    TableDelimiter,
+
+    /// Table cell separator (`|`), including any cell specifier before it
+    ///
+    /// Example: "|", "2+|", ".2+|", "3*|"
+    CellSeparator,
+
+    // ===== Inline syntax =====
+    // Not produced by `Token::lexer`; `lex_with_spans` merges these from
+    // adjacent tokens (see "URLs" in the module documentation).
+    /// A URL with a known scheme, without trailing sentence punctuation
+    ///
+    /// Example: "<https://example.com/docs>"
+    Url,
 }
 
 impl Token {
@@ -125,17 +386,619 @@ impl Token {
             Token::Heading6 => "level 6 heading (======)",
             Token::BoldDelimiter => "bold delimiter (**)",
             Token::ItalicDelimiter => "italic delimiter (_)",
+            Token::MonospaceDelimiter => "monospace delimiter (`)",
+            Token::HighlightDelimiter => "highlight delimiter (#)",
+            Token::SuperscriptDelimiter => "superscript delimiter (^)",
+            Token::SubscriptDelimiter => "subscript delimiter (~)",
             Token::Newline => "newline",
             Token::BlankLine => "blank line",
             Token::Word => "word",
+            Token::Punctuation => "punctuation",
+            Token::UnorderedMarker => "unordered list marker (*)",
+            Token::OrderedMarker => "ordered list marker (.)",
+            Token::ListContinuation => "list continuation (+)",
+            Token::DescriptionSeparator => "description list separator (::)",
+            Token::ListingDelimiter => "listing block delimiter (----)",
+            Token::LiteralDelimiter => "literal block delimiter (....)",
+            Token::CommentDelimiter => "comment block delimiter (////)",
+            Token::PassthroughDelimiter => "passthrough block delimiter (++++)",
+            Token::LineComment => "line comment (//)",
+            Token::Verbatim => "verbatim block content",
+            Token::QuoteDelimiter => "quote block delimiter (____)",
+            Token::ExampleDelimiter => "example block delimiter (====)",
+            Token::SidebarDelimiter => "sidebar block delimiter (****)",
+            Token::OpenDelimiter => "open block delimiter (--)",
+            Token::TableDelimiter => "table delimiter (|===)",
+            Token::CellSeparator => "table cell separator (|)",
+            Token::Url => "URL",
+        }
+    }
+}
+
+/// Lex `input` into tokens paired with their byte spans
+///
+/// The lexer is total (see the module documentation), so no input is lost.
+/// List markers, list continuations, and description list separators are
+/// re-labelled from their line context (see "List Markers" in the module
+/// documentation), as are table delimiters and cell separators (see
+/// "Tables"), and delimited block content is lexed verbatim (see "Delimited
+/// Blocks"). This collects a [`TokenStream`].
+pub fn lex_with_spans(input: &str) -> Vec<(Token, Span)> {
+    TokenStream::new(input).collect()
+}
+
+/// A lazy lexer: an iterator over the tokens of a source text and their
+/// spans, as [`lex_with_spans`] returns them
+///
+/// The stream lexes one line at a time, and re-labels line syntax and URLs
+/// as it goes, so only the current line's tokens are held in memory. A
+/// delimited block belongs to the line of its closing delimiter, so its
+/// verbatim content is a single token however long it is.
+///
+/// ```
+/// use doctora::token::{Token, TokenStream};
+///
+/// let source = "= Title\n\n* item\n".repeat(100_000);
+/// let first: Vec<Token> = TokenStream::new(&source)
+///     .map(|(token, _)| token)
+///     .take(3)
+///     .collect();
+/// assert_eq!(first, [Token::Heading1, Token::Word, Token::BlankLine]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TokenStream<'a> {
+    input: &'a str,
+    lexer: logos::Lexer<'a, Token>,
+    /// Offset of the line the lexer is at the start of, if it is
+    line_start: Option<usize>,
+    /// Whether the lexer has reached the end of the input
+    finished: bool,
+    /// Whether the stream is between two table delimiters
+    in_table: bool,
+    /// The rest of the current line's tokens
+    line: std::vec::IntoIter<(Token, Span)>,
+}
+
+impl<'a> TokenStream<'a> {
+    /// Creates a stream over the tokens of `input`
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            lexer: Token::lexer(input),
+            line_start: Some(0),
+            finished: false,
+            in_table: false,
+            line: Vec::new().into_iter(),
+        }
+    }
+
+    /// Returns the source text the spans refer to
+    pub fn source(&self) -> &'a str {
+        self.input
+    }
+
+    /// Lex up to and including the next line break, or to the end of the
+    /// input
+    ///
+    /// Line syntax is only recognized at the start of a line, and at a line
+    /// start the lookbehind of [`mark_line_syntax`] sees the same as at the
+    /// start of the input, so marking one line at a time gives the same
+    /// tokens as marking the whole input at once.
+    fn next_line(&mut self) -> Vec<(Token, Span)> {
+        let mut tokens = Vec::new();
+        loop {
+            if let Some(start) = self.line_start.take()
+                && let Some(end) = lex_delimited_block(self.input, start, &mut tokens)
+                    .or_else(|| lex_line_comment(self.input, start, &mut tokens))
+            {
+                self.lexer.bump(end - self.lexer.span().end);
+            }
+            let Some(result) = self.lexer.next() else {
+                self.finished = true;
+                break;
+            };
+            let span = self.lexer.span();
+            if let Ok(token) = result {
+                let line_end = matches!(token, Token::Newline | Token::BlankLine);
+                if line_end {
+                    self.line_start = Some(span.end);
+                }
+                tokens.push((token, span));
+                if line_end {
+                    break;
+                }
+            }
+        }
+        mark_urls(
+            self.input,
+            mark_line_syntax(self.input, tokens, &mut self.in_table),
+        )
+    }
+}
+
+impl Iterator for TokenStream<'_> {
+    type Item = (Token, Span);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.line.next() {
+                return Some(token);
+            }
+            if self.finished {
+                return None;
+            }
+            self.line = self.next_line().into_iter();
+        }
+    }
+}
+
+/// Schemes that start a [`Token::Url`]
+const URL_SCHEMES: [&str; 4] = ["http", "https", "ftp", "irc"];
+
+/// Merge the tokens of each URL into one [`Token::Url`]
+fn mark_urls(input: &str, tokens: Vec<(Token, Span)>) -> Vec<(Token, Span)> {
+    let mut out: Vec<(Token, Span)> = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        match url_end(input, &tokens, i) {
+            Some(end) => {
+                out.push((Token::Url, tokens[i].1.start..tokens[end - 1].1.end));
+                i = end;
+            }
+            None => {
+                out.push(tokens[i].clone());
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// If a URL starts at `tokens[start]`, the index just past its last token
+///
+/// A URL is a scheme word that does not follow a letter or digit, `://`, and the
+/// adjacent text after it up to whitespace or one of `[]<>"`, minus trailing
+/// punctuation that usually ends a sentence or a parenthesis and trailing
+/// formatting marks.
+fn url_end(input: &str, tokens: &[(Token, Span)], start: usize) -> Option<usize> {
+    let (Token::Word, scheme) = &tokens[start] else {
+        return None;
+    };
+    let continues_word = input[..scheme.start]
+        .chars()
+        .next_back()
+        .is_some_and(char::is_alphanumeric);
+    if continues_word
+        || !URL_SCHEMES.contains(&&input[scheme.clone()])
+        || !input[scheme.end..].starts_with("://")
+    {
+        return None;
+    }
+
+    let mut end = start + 1;
+    let mut offset = scheme.end;
+    while let Some((token, span)) = tokens.get(end)
+        && span.start == offset
+        && matches!(
+            token,
+            Token::Word
+                | Token::Punctuation
+                | Token::ItalicDelimiter
+                | Token::BoldDelimiter
+                // `#` in fragments, `~` in home directories
+                | Token::HighlightDelimiter
+                | Token::SuperscriptDelimiter
+                | Token::SubscriptDelimiter
+                // `=` in query strings
+                | Token::Heading1
+                | Token::Heading2
+                | Token::Heading3
+                | Token::Heading4
+                | Token::Heading5
+                | Token::Heading6
+        )
+        && !matches!(&input[span.clone()], "[" | "]" | "<" | ">" | "\"")
+    {
+        offset = span.end;
+        end += 1;
+    }
+    while end > start + 1
+        && match &tokens[end - 1] {
+            (Token::Punctuation, span) => matches!(
+                &input[span.clone()],
+                "." | "," | ";" | ":" | "!" | "?" | ")" | "'" | "*"
+            ),
+            (token, _) => matches!(
+                token,
+                Token::ItalicDelimiter | Token::BoldDelimiter | Token::HighlightDelimiter
+            ),
+        }
+    {
+        end -= 1;
+    }
+    // Something must follow `://`
+    (tokens[end - 1].1.end > scheme.end + 3).then_some(end)
+}
+
+/// Lex a delimited block whose opening delimiter line starts at `start`
+///
+/// Pushes the delimiters and the verbatim content, and returns the offset at
+/// which normal lexing resumes, or `None` if the line is not a delimiter.
+fn lex_delimited_block(
+    input: &str,
+    start: usize,
+    tokens: &mut Vec<(Token, Span)>,
+) -> Option<usize> {
+    let rest = &input[start..];
+    let line_end = rest.find('\n').map_or(input.len(), |i| start + i);
+    let delimiter = input[start..line_end].trim_end_matches([' ', '\t', '\r']);
+    let token = if delimiter.len() >= 4 && delimiter.bytes().all(|b| b == b'-') {
+        Token::ListingDelimiter
+    } else if delimiter.len() >= 4 && delimiter.bytes().all(|b| b == b'.') {
+        Token::LiteralDelimiter
+    } else if delimiter.len() >= 4 && delimiter.bytes().all(|b| b == b'/') {
+        Token::CommentDelimiter
+    } else if delimiter.len() >= 4 && delimiter.bytes().all(|b| b == b'+') {
+        Token::PassthroughDelimiter
+    } else {
+        return None;
+    };
+    tokens.push((token.clone(), start..start + delimiter.len()));
+    if line_end == input.len() {
+        return Some(input.len());
+    }
+    tokens.push((Token::Newline, line_end..line_end + 1));
+
+    // The closing delimiter is the next line that matches exactly
+    let content_start = line_end + 1;
+    let mut offset = content_start;
+    let closing = loop {
+        if offset >= input.len() {
+            break None;
+        }
+        let end = input[offset..]
+            .find('\n')
+            .map_or(input.len(), |i| offset + i);
+        if input[offset..end].trim_end_matches([' ', '\t', '\r']) == delimiter {
+            break Some(offset);
         }
+        offset = end + 1;
+    };
+
+    let Some(closing) = closing else {
+        if content_start < input.len() {
+            tokens.push((Token::Verbatim, content_start..input.len()));
+        }
+        return Some(input.len());
+    };
+    if closing > content_start {
+        tokens.push((Token::Verbatim, content_start..closing - 1));
+        tokens.push((Token::Newline, closing - 1..closing));
+    }
+    tokens.push((token, closing..closing + delimiter.len()));
+    Some(closing + delimiter.len())
+}
+
+/// Lex a line comment starting at `start`, if there is one there, returning
+/// the end of the line
+///
+/// Only a line starting in the first column is a comment, so indented `//`
+/// is left to the regular lexer.
+fn lex_line_comment(input: &str, start: usize, tokens: &mut Vec<(Token, Span)>) -> Option<usize> {
+    let in_first_column = start == 0 || input.as_bytes()[start - 1] == b'\n';
+    let rest = &input[start..];
+    if !in_first_column || !rest.starts_with("//") || rest[2..].starts_with('/') {
+        return None;
+    }
+    let line_end = rest.find('\n').map_or(input.len(), |i| start + i);
+    let comment = input[start..line_end].trim_end_matches([' ', '\t', '\r']);
+    tokens.push((Token::LineComment, start..start + comment.len()));
+    Some(start + comment.len())
+}
+
+/// Re-label list syntax at the start of each line and description list
+/// separators within it, and table delimiters and cell separators
+///
+/// `in_table` says whether `tokens` start inside a table, and is updated to
+/// whether they end inside one.
+fn mark_line_syntax(
+    input: &str,
+    tokens: Vec<(Token, Span)>,
+    in_table: &mut bool,
+) -> Vec<(Token, Span)> {
+    let mut out: Vec<(Token, Span)> = Vec::with_capacity(tokens.len());
+    // Whether the current line may still get a description list separator
+    let mut separator_allowed = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        let line_start = matches!(
+            out.last(),
+            None | Some((Token::Newline | Token::BlankLine, _))
+        );
+        if line_start && is_table_delimiter(input, &tokens[i..]) {
+            *in_table = !*in_table;
+            out.push((
+                Token::TableDelimiter,
+                tokens[i].1.start..tokens[i + 1].1.end,
+            ));
+            i += 2;
+            continue;
+        }
+        if *in_table {
+            let (token, span) = &tokens[i];
+            if *token == Token::Punctuation && &input[span.clone()] == "|" {
+                let start = take_cell_spec(input, &mut out, span.start).unwrap_or(span.start);
+                out.push((Token::CellSeparator, start..span.end));
+            } else {
+                out.push((token.clone(), span.clone()));
+            }
+            i += 1;
+            continue;
+        }
+        let marked = match out.last() {
+            None | Some((Token::Newline | Token::BlankLine, _)) => {
+                separator_allowed = matches!(
+                    tokens[i].0,
+                    Token::Word
+                        | Token::Punctuation
+                        | Token::BoldDelimiter
+                        | Token::ItalicDelimiter
+                        | Token::MonospaceDelimiter
+                        | Token::HighlightDelimiter
+                        | Token::SuperscriptDelimiter
+                        | Token::SubscriptDelimiter
+                );
+                line_syntax(input, &tokens[i..]).or_else(|| compound_delimiter(input, &tokens[i..]))
+            }
+            Some((prev_token, prev)) if separator_allowed => {
+                let continues_run =
+                    *prev_token == Token::Punctuation && prev.end == tokens[i].1.start;
+                let len = (!continues_run)
+                    .then(|| description_separator(input, &tokens[i..]))
+                    .flatten();
+                len.map(|len| (Token::DescriptionSeparator, len))
+            }
+            Some(_) => None,
+        };
+        if let Some((token, len)) = marked {
+            separator_allowed &= token != Token::DescriptionSeparator;
+            let span = tokens[i].1.start..tokens[i + len - 1].1.end;
+            out.push((token, span));
+            i += len;
+            continue;
+        }
+        out.push(tokens[i].clone());
+        i += 1;
+    }
+    out
+}
+
+/// Recognize a compound block delimiter line at the start of `line`
+///
+/// The line must consist of four or more `_` (quote), `=` (example), or `*`
+/// (sidebar), or of exactly `--` (open). Returns the token and the number of
+/// lexed tokens it replaces.
+fn compound_delimiter(input: &str, line: &[(Token, Span)]) -> Option<(Token, usize)> {
+    let len = line
+        .iter()
+        .enumerate()
+        .take_while(|(i, (t, span))| {
+            !matches!(t, Token::Newline | Token::BlankLine | Token::Word)
+                && (*i == 0 || line[i - 1].1.end == span.start)
+        })
+        .count();
+    let at_line_end = matches!(
+        line.get(len),
+        None | Some((Token::Newline | Token::BlankLine, _))
+    );
+    if len == 0 || !at_line_end {
+        return None;
+    }
+    let run = &input[line[0].1.start..line[len - 1].1.end];
+    // Zero-width tokens (the content of an empty delimited block) form no run
+    let first = *run.as_bytes().first()?;
+    let token = match first {
+        b'_' => Token::QuoteDelimiter,
+        b'=' => Token::ExampleDelimiter,
+        b'*' => Token::SidebarDelimiter,
+        b'-' if run == "--" => return Some((Token::OpenDelimiter, len)),
+        _ => return None,
+    };
+    (run.len() >= 4 && run.bytes().all(|b| b == first)).then_some((token, len))
+}
+
+/// True if `line` starts with `|===` followed by the end of the line
+fn is_table_delimiter(input: &str, line: &[(Token, Span)]) -> bool {
+    match line {
+        [
+            (Token::Punctuation, bar),
+            (Token::Heading3, equals),
+            rest @ ..,
+        ] => {
+            &input[bar.clone()] == "|"
+                && bar.end == equals.start
+                && matches!(
+                    rest.first(),
+                    None | Some((Token::Newline | Token::BlankLine, _))
+                )
+        }
+        _ => false,
+    }
+}
+
+/// Remove a cell specifier that ends at `end` from the tokens in `out`
+///
+/// The specifier is the run of adjacent text tokens directly before the
+/// separator; it must start a line, follow whitespace, or follow another
+/// separator. Returns the specifier's start offset if one was removed.
+fn take_cell_spec(input: &str, out: &mut Vec<(Token, Span)>, end: usize) -> Option<usize> {
+    let mut len = 0;
+    let mut start = end;
+    for (token, span) in out.iter().rev() {
+        if span.end != start
+            || !matches!(
+                token,
+                Token::Word | Token::Punctuation | Token::BoldDelimiter
+            )
+        {
+            break;
+        }
+        start = span.start;
+        len += 1;
+    }
+    let preceded_by_boundary = match out.len().checked_sub(len + 1).map(|i| &out[i]) {
+        None | Some((Token::Newline | Token::BlankLine | Token::CellSeparator, _)) => true,
+        Some((_, span)) => span.end < start,
+    };
+    if len == 0 || !preceded_by_boundary || parse_cell_spec(&input[start..end]).is_none() {
+        return None;
+    }
+    out.truncate(out.len() - len);
+    Some(start)
+}
+
+/// A cell specifier: column and row span, or repetition count
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CellSpec {
+    pub colspan: usize,
+    pub rowspan: usize,
+    pub repeat: usize,
+}
+
+/// Parse a cell span (`2+`, `.3+`, `2.3+`) or duplication (`3*`) specifier
+pub(crate) fn parse_cell_spec(spec: &str) -> Option<CellSpec> {
+    let number = |s: &str| {
+        s.bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| s.parse::<usize>().ok())
+            .flatten()
+            .filter(|&n| n > 0)
+    };
+    let mut parsed = CellSpec {
+        colspan: 1,
+        rowspan: 1,
+        repeat: 1,
+    };
+    if let Some(repeat) = spec.strip_suffix('*') {
+        parsed.repeat = number(repeat)?;
+        return Some(parsed);
+    }
+    match spec.strip_suffix('+')?.split_once('.') {
+        Some((colspan, rowspan)) => {
+            if !colspan.is_empty() {
+                parsed.colspan = number(colspan)?;
+            }
+            parsed.rowspan = number(rowspan)?;
+        }
+        None => parsed.colspan = number(spec.strip_suffix('+')?)?,
+    }
+    Some(parsed)
+}
+
+/// Length of the punctuation run at the start of `tokens`
+///
+/// A run is a sequence of punctuation tokens with no whitespace between them.
+fn punctuation_run(tokens: &[(Token, Span)], include_bold: bool) -> usize {
+    tokens
+        .iter()
+        .enumerate()
+        .take_while(|(i, (t, span))| {
+            (*t == Token::Punctuation || include_bold && *t == Token::BoldDelimiter)
+                && (*i == 0 || tokens[i - 1].1.end == span.start)
+        })
+        .count()
+}
+
+/// Recognize a description list separator at the start of `rest`
+///
+/// Returns the number of lexed tokens it replaces.
+fn description_separator(input: &str, rest: &[(Token, Span)]) -> Option<usize> {
+    let len = punctuation_run(rest, false);
+    if len == 0 {
+        return None;
+    }
+    let end = rest[len - 1].1.end;
+    let run = &input[rest[0].1.start..end];
+    let followed_by_space = match rest.get(len) {
+        None | Some((Token::Newline | Token::BlankLine, _)) => true,
+        Some((_, next)) => next.start > end,
+    };
+    (matches!(run, "::" | ":::" | "::::" | ";;") && followed_by_space).then_some(len)
+}
+
+/// Recognize list syntax at the start of `line`
+///
+/// Returns the token and the number of lexed tokens it replaces. A marker is
+/// a run of up to five adjacent `*` or `.` characters (or a single `-`)
+/// followed by whitespace and more text on the same line; a continuation is
+/// a lone `+`. A longer run of `*` followed by whitespace becomes a single
+/// [`Token::Punctuation`], so its `**` pairs are not taken as bold marks.
+fn line_syntax(input: &str, line: &[(Token, Span)]) -> Option<(Token, usize)> {
+    let len = punctuation_run(line, true);
+    if len == 0 {
+        return None;
+    }
+    let run = &input[line[0].1.start..line[len - 1].1.end];
+    let rest = line
+        .get(len)
+        .filter(|(t, _)| !matches!(t, Token::Newline | Token::BlankLine));
+
+    if run == "+" && rest.is_none() {
+        return Some((Token::ListContinuation, 1));
+    }
+    let (_, next) = rest?;
+    if next.start == line[len - 1].1.end {
+        return None;
+    }
+    let stars = run.bytes().all(|b| b == b'*');
+    if run.len() > 5 {
+        // Too deep for a marker, and not bold marks either
+        return stars.then_some((Token::Punctuation, len));
+    }
+    if run == "-" || stars {
+        Some((Token::UnorderedMarker, len))
+    } else if run.bytes().all(|b| b == b'.') {
+        Some((Token::OrderedMarker, len))
+    } else {
+        None
+    }
+}
+
+/// Maps byte offsets in a source text to line and column numbers
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    /// Byte offset at which each line starts
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    /// Builds the index for `source`
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Returns the 1-based `(line, column)` of byte `offset`
+    ///
+    /// Columns count characters, not bytes. Offsets past the end of the source
+    /// map to the end of the last line.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.source[self.line_starts[line]..offset].chars().count();
+        (line + 1, column + 1)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use logos::Logos;
 
     /// Helper function to collect all tokens from input
     fn lex_all(input: &str) -> Vec<Token> {
@@ -144,20 +1007,6 @@ mod tests {
             .collect()
     }
 
-    /// Helper function to collect tokens with their spans
-    fn lex_with_spans(input: &str) -> Vec<(Token, std::ops::Range<usize>)> {
-        let mut lexer = Token::lexer(input);
-        let mut result = Vec::new();
-
-        while let Some(token_result) = lexer.next() {
-            if let Ok(token) = token_result {
-                result.push((token, lexer.span()));
-            }
-        }
-
-        result
-    }
-
     #[test]
     fn test_heading_levels() {
         assert_eq!(lex_all("="), vec![Token::Heading1]);
@@ -192,6 +1041,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_monospace_formatting() {
+        let tokens = lex_all("``a`b");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::MonospaceDelimiter,
+                Token::MonospaceDelimiter,
+                Token::Word,
+                Token::MonospaceDelimiter,
+                Token::Word
+            ]
+        );
+        // URLs stop before a closing backtick
+        let spans: Vec<Span> = lex_with_spans("`https://example.com`")
+            .into_iter()
+            .map(|(_, span)| span)
+            .collect();
+        assert_eq!(spans, vec![0..1, 1..20, 20..21]);
+    }
+
+    #[test]
+    fn test_highlight_superscript_subscript() {
+        let tokens = lex_all("#a# ^b~");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::HighlightDelimiter,
+                Token::Word,
+                Token::HighlightDelimiter,
+                Token::SuperscriptDelimiter,
+                Token::Word,
+                Token::SubscriptDelimiter
+            ]
+        );
+        // Fragments and `~` stay in URLs, a closing highlight mark does not
+        let spans: Vec<Span> = lex_with_spans("#https://example.com/~me#top#")
+            .into_iter()
+            .map(|(_, span)| span)
+            .collect();
+        assert_eq!(spans, vec![0..1, 1..28, 28..29]);
+    }
+
     #[test]
     fn test_mixed_formatting() {
         let tokens = lex_all("This is **bold** and _italic_.");
@@ -207,7 +1099,7 @@ mod tests {
                 Token::ItalicDelimiter,
                 Token::Word, // italic
                 Token::ItalicDelimiter,
-                Token::Word, // .
+                Token::Punctuation, // .
             ]
         );
     }
@@ -244,7 +1136,7 @@ mod tests {
                 Token::ItalicDelimiter,
                 Token::Word, // italic
                 Token::ItalicDelimiter,
-                Token::Word, // .
+                Token::Punctuation, // .
             ]
         );
     }
@@ -314,12 +1206,498 @@ mod tests {
         );
     }
 
+    /// Tokens of `input` as returned by [`lex_with_spans`]
+    fn lex_marked(input: &str) -> Vec<Token> {
+        lex_with_spans(input).into_iter().map(|(t, _)| t).collect()
+    }
+
+    #[test]
+    fn test_list_markers() {
+        assert_eq!(
+            lex_marked("* a\n** b\n- c"),
+            vec![
+                Token::UnorderedMarker,
+                Token::Word,
+                Token::Newline,
+                Token::UnorderedMarker,
+                Token::Word,
+                Token::Newline,
+                Token::UnorderedMarker,
+                Token::Word,
+            ]
+        );
+        assert_eq!(
+            lex_marked(". a\n.. b"),
+            vec![
+                Token::OrderedMarker,
+                Token::Word,
+                Token::Newline,
+                Token::OrderedMarker,
+                Token::Word,
+            ]
+        );
+
+        let tokens = lex_with_spans("*** deep");
+        assert_eq!(tokens[0], (Token::UnorderedMarker, 0..3));
+    }
+
+    #[test]
+    fn test_list_markers_need_line_start_and_space() {
+        // Mid-line `*`, bold at line start, and dots without text are not markers
+        assert_eq!(
+            lex_marked("2 * 3"),
+            vec![Token::Word, Token::Punctuation, Token::Word]
+        );
+        assert_eq!(
+            lex_marked("**bold** text"),
+            vec![
+                Token::BoldDelimiter,
+                Token::Word,
+                Token::BoldDelimiter,
+                Token::Word
+            ]
+        );
+        assert_eq!(
+            lex_marked("...\n"),
+            vec![
+                Token::Punctuation,
+                Token::Punctuation,
+                Token::Punctuation,
+                Token::Newline
+            ]
+        );
+        assert_eq!(lex_marked(".Title"), vec![Token::Punctuation, Token::Word]);
+        // Too deep for a marker: one literal run, not bold marks
+        assert_eq!(
+            lex_with_spans("****** x"),
+            vec![(Token::Punctuation, 0..6), (Token::Word, 7..8)]
+        );
+    }
+
+    #[test]
+    fn test_list_continuation() {
+        assert_eq!(
+            lex_marked("* a\n+\nmore"),
+            vec![
+                Token::UnorderedMarker,
+                Token::Word,
+                Token::Newline,
+                Token::ListContinuation,
+                Token::Newline,
+                Token::Word,
+            ]
+        );
+        // A trailing `+` is not a continuation line
+        assert_eq!(lex_marked("a +"), vec![Token::Word, Token::Punctuation]);
+    }
+
+    #[test]
+    fn test_description_separator() {
+        assert_eq!(
+            lex_marked("CPU:: The brain\nTerm;;\nDeep term:::"),
+            vec![
+                Token::Word,
+                Token::DescriptionSeparator,
+                Token::Word,
+                Token::Word,
+                Token::Newline,
+                Token::Word,
+                Token::DescriptionSeparator,
+                Token::Newline,
+                Token::Word,
+                Token::Word,
+                Token::DescriptionSeparator,
+            ]
+        );
+        // Only the first separator on a line counts
+        assert_eq!(
+            lex_marked("a:: b:: c"),
+            vec![
+                Token::Word,
+                Token::DescriptionSeparator,
+                Token::Word,
+                Token::Punctuation,
+                Token::Punctuation,
+                Token::Word,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_description_separator_needs_term_and_space() {
+        let punctuation_only = |input: &str| {
+            lex_marked(input)
+                .iter()
+                .all(|t| matches!(t, Token::Word | Token::Punctuation))
+        };
+        assert!(punctuation_only("image::diagram.png[]"));
+        assert!(punctuation_only(":: no term"));
+        assert!(punctuation_only("a::::: b"));
+        assert!(punctuation_only(":author: Jane"));
+    }
+
+    #[test]
+    fn test_verbatim_block_content() {
+        let input = "[source]\n----\n  * not a list\n\nline _2_\n----\n\nafter";
+        let tokens = lex_with_spans(input);
+        let verbatim = tokens
+            .iter()
+            .find(|(t, _)| *t == Token::Verbatim)
+            .map(|(_, span)| &input[span.clone()]);
+        assert_eq!(verbatim, Some("  * not a list\n\nline _2_"));
+        assert_eq!(
+            tokens[tokens.len() - 3..]
+                .iter()
+                .map(|(t, _)| t.clone())
+                .collect::<Vec<_>>(),
+            vec![Token::ListingDelimiter, Token::BlankLine, Token::Word]
+        );
+    }
+
+    #[test]
+    fn test_quote_delimiter() {
+        assert_eq!(
+            lex_marked("____\n_a_\n______"),
+            vec![
+                Token::QuoteDelimiter,
+                Token::Newline,
+                Token::ItalicDelimiter,
+                Token::Word,
+                Token::ItalicDelimiter,
+                Token::Newline,
+                Token::QuoteDelimiter,
+            ]
+        );
+        // Too short, not alone on the line, or not at line start
+        assert!(!lex_marked("___\n____ x\nx ____").contains(&Token::QuoteDelimiter));
+    }
+
+    #[test]
+    fn test_open_delimiter() {
+        assert_eq!(
+            lex_marked("--\ntext\n--"),
+            vec![
+                Token::OpenDelimiter,
+                Token::Newline,
+                Token::Word,
+                Token::Newline,
+                Token::OpenDelimiter,
+            ]
+        );
+        // Only an exact `--` alone at the start of a line
+        assert!(!lex_marked("---\n-- x\na --\n- -").contains(&Token::OpenDelimiter));
+    }
+
+    #[test]
+    fn test_example_and_sidebar_delimiters() {
+        assert_eq!(
+            lex_marked("====\n*****\n=======\n****"),
+            vec![
+                Token::ExampleDelimiter,
+                Token::Newline,
+                Token::SidebarDelimiter,
+                Token::Newline,
+                Token::ExampleDelimiter,
+                Token::Newline,
+                Token::SidebarDelimiter,
+            ]
+        );
+        // Headings, mixed runs, and short runs are not delimiters
+        assert_eq!(
+            lex_marked("==== Title\n==**\n***"),
+            vec![
+                Token::Heading4,
+                Token::Word,
+                Token::Newline,
+                Token::Heading2,
+                Token::BoldDelimiter,
+                Token::Newline,
+                Token::BoldDelimiter,
+                Token::Punctuation,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_table_tokens() {
+        let input = "|===\n|a 2+|b\n.2+|c\n|===\n| not a cell";
+        let tokens = lex_with_spans(input);
+        let shown: Vec<(Token, &str)> = tokens
+            .iter()
+            .map(|(t, span)| (t.clone(), &input[span.clone()]))
+            .collect();
+        assert_eq!(
+            shown,
+            vec![
+                (Token::TableDelimiter, "|==="),
+                (Token::Newline, "\n"),
+                (Token::CellSeparator, "|"),
+                (Token::Word, "a"),
+                (Token::CellSeparator, "2+|"),
+                (Token::Word, "b"),
+                (Token::Newline, "\n"),
+                (Token::CellSeparator, ".2+|"),
+                (Token::Word, "c"),
+                (Token::Newline, "\n"),
+                (Token::TableDelimiter, "|==="),
+                (Token::Newline, "\n"),
+                (Token::Punctuation, "|"),
+                (Token::Word, "not"),
+                (Token::Word, "a"),
+                (Token::Word, "cell"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_token_stream_is_lazy() {
+        // Table state carries from line to line, and a partly read stream
+        // resumes where it stopped
+        let input = "|===\n|a 2+|b\n----\n|x\n----\n|===\nsee https://example.com\n";
+        let mut stream = TokenStream::new(input);
+        let head: Vec<_> = stream.by_ref().take(4).collect();
+        assert_eq!(head[..], lex_with_spans(input)[..4]);
+        assert_eq!(stream.source(), input);
+        let rest: Vec<_> = stream.collect();
+        assert_eq!([head, rest].concat(), lex_with_spans(input));
+        assert!(
+            lex_with_spans(input).iter().any(
+                |(token, span)| *token == Token::CellSeparator && &input[span.clone()] == "2+|"
+            )
+        );
+        // Only the current line is lexed ahead
+        let long = "word ".repeat(1_000) + "\nnext";
+        let mut stream = TokenStream::new(&long);
+        stream.next();
+        assert_eq!(stream.line.len(), 1_000);
+    }
+
+    #[test]
+    fn test_cell_specs() {
+        let spec = |colspan, rowspan, repeat| {
+            Some(CellSpec {
+                colspan,
+                rowspan,
+                repeat,
+            })
+        };
+        assert_eq!(parse_cell_spec("2+"), spec(2, 1, 1));
+        assert_eq!(parse_cell_spec(".3+"), spec(1, 3, 1));
+        assert_eq!(parse_cell_spec("2.3+"), spec(2, 3, 1));
+        assert_eq!(parse_cell_spec("3*"), spec(1, 1, 3));
+        for invalid in ["", "+", "2", "a+", "0+", ".+", "2.+", "-1*", "2+*"] {
+            assert_eq!(parse_cell_spec(invalid), None, "{invalid:?}");
+        }
+
+        // A specifier must not be glued to preceding cell text
+        assert_eq!(
+            lex_marked("|===\n|a2+|b\n|==="),
+            vec![
+                Token::TableDelimiter,
+                Token::Newline,
+                Token::CellSeparator,
+                Token::Word,
+                Token::Punctuation,
+                Token::CellSeparator,
+                Token::Word,
+                Token::Newline,
+                Token::TableDelimiter,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_delimited_block_edge_cases() {
+        // Empty block
+        assert_eq!(
+            lex_marked("....\n...."),
+            vec![
+                Token::LiteralDelimiter,
+                Token::Newline,
+                Token::LiteralDelimiter
+            ]
+        );
+        // Unclosed block runs to the end; a shorter fence does not close it
+        assert_eq!(
+            lex_marked("-----\nx\n----"),
+            vec![Token::ListingDelimiter, Token::Newline, Token::Verbatim]
+        );
+        // Fences must be alone on their line
+        assert!(!lex_marked("a ----\n").contains(&Token::ListingDelimiter));
+        assert!(!lex_marked("---").contains(&Token::ListingDelimiter));
+        // Passthrough content is verbatim too, so `+` is no list continuation
+        assert_eq!(
+            lex_marked("++++\n<b>x</b>\n+\n++++"),
+            vec![
+                Token::PassthroughDelimiter,
+                Token::Newline,
+                Token::Verbatim,
+                Token::Newline,
+                Token::PassthroughDelimiter,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blank_delimited_blocks() {
+        // A body of one blank line lexes to zero-width verbatim content
+        for source in ["----\n\n----\n", "....\n\n....\n", "////\n\n////\n"] {
+            let doc = crate::parse_document(source).unwrap();
+            assert_eq!(
+                doc.blocks.len(),
+                usize::from(!source.starts_with('/')),
+                "{source:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_comments() {
+        let source = "// *not* bold\n////\n* a\n////\n//\n/// x\n a // b\n  // c";
+        let tokens: Vec<(Token, &str)> = lex_with_spans(source)
+            .into_iter()
+            .map(|(token, span)| (token, &source[span]))
+            .collect();
+        assert_eq!(
+            tokens[..10],
+            [
+                (Token::LineComment, "// *not* bold"),
+                (Token::Newline, "\n"),
+                (Token::CommentDelimiter, "////"),
+                (Token::Newline, "\n"),
+                (Token::Verbatim, "* a"),
+                (Token::Newline, "\n"),
+                (Token::CommentDelimiter, "////"),
+                (Token::Newline, "\n"),
+                (Token::LineComment, "//"),
+                (Token::Newline, "\n"),
+            ]
+        );
+        // `///` and comments not in the first column are text
+        assert_eq!(
+            tokens
+                .iter()
+                .filter(|(token, _)| *token == Token::LineComment)
+                .count(),
+            2
+        );
+    }
+
     #[test]
     fn test_token_description() {
         assert_eq!(Token::Heading1.description(), "level 1 heading (=)");
         assert_eq!(Token::BoldDelimiter.description(), "bold delimiter (**)");
         assert_eq!(Token::ItalicDelimiter.description(), "italic delimiter (_)");
+        assert_eq!(
+            Token::MonospaceDelimiter.description(),
+            "monospace delimiter (`)"
+        );
+        assert_eq!(
+            Token::SubscriptDelimiter.description(),
+            "subscript delimiter (~)"
+        );
         assert_eq!(Token::Word.description(), "word");
+        assert_eq!(Token::Punctuation.description(), "punctuation");
+    }
+
+    #[test]
+    fn test_punctuation_never_joins_words() {
+        let tokens = lex_all("don't e.g. a|b [x] `y` #z#");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Word,               // don
+                Token::Punctuation,        // '
+                Token::Word,               // t
+                Token::Word,               // e
+                Token::Punctuation,        // .
+                Token::Word,               // g
+                Token::Punctuation,        // .
+                Token::Word,               // a
+                Token::Punctuation,        // |
+                Token::Word,               // b
+                Token::Punctuation,        // [
+                Token::Word,               // x
+                Token::Punctuation,        // ]
+                Token::MonospaceDelimiter, // `
+                Token::Word,               // y
+                Token::MonospaceDelimiter, // `
+                Token::HighlightDelimiter, // #
+                Token::Word,               // z
+                Token::HighlightDelimiter, // #
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_asterisk_is_punctuation() {
+        assert_eq!(
+            lex_all("2 * 3"),
+            vec![Token::Word, Token::Punctuation, Token::Word]
+        );
+        assert_eq!(
+            lex_all("***"),
+            vec![Token::BoldDelimiter, Token::Punctuation]
+        );
+    }
+
+    #[test]
+    fn test_lexer_is_total() {
+        // Unicode text and whitespace, control characters, and every ASCII
+        // punctuation character must lex without errors
+        let input = "caf\u{e9} \u{a0}nbsp\u{2003}em \u{1F600} \x0b\x0c\0 !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+        assert!(Token::lexer(input).all(|result| result.is_ok()));
+    }
+
+    #[test]
+    fn test_crlf_and_whitespace_only_blank_lines() {
+        assert_eq!(
+            lex_all("a\r\n\r\nb"),
+            vec![Token::Word, Token::BlankLine, Token::Word]
+        );
+        assert_eq!(
+            lex_all("a\n  \t\nb"),
+            vec![Token::Word, Token::BlankLine, Token::Word]
+        );
+        assert_eq!(
+            lex_all("a\r\nb"),
+            vec![Token::Word, Token::Newline, Token::Word]
+        );
+    }
+
+    #[test]
+    fn test_indented_line_is_not_an_error() {
+        assert_eq!(
+            lex_all("{\n    body"),
+            vec![Token::Punctuation, Token::Newline, Token::Word]
+        );
+    }
+
+    #[test]
+    fn test_urls() {
+        let urls = |source: &str| -> Vec<String> {
+            lex_with_spans(source)
+                .into_iter()
+                .filter(|(token, _)| *token == Token::Url)
+                .map(|(_, span)| source[span].to_string())
+                .collect()
+        };
+        assert_eq!(
+            urls("See https://example.com/a_b?q=1&r=*x*, or _ftp://f.org/x.tgz_."),
+            ["https://example.com/a_b?q=1&r=*x", "ftp://f.org/x.tgz"]
+        );
+        assert_eq!(
+            urls("https://example.com[Example]"),
+            ["https://example.com"]
+        );
+        assert_eq!(urls("<http://a.io>"), ["http://a.io"]);
+        assert_eq!(
+            urls("(see irc://irc.libera.chat)"),
+            ["irc://irc.libera.chat"]
+        );
+        // Not URLs: unknown scheme, scheme inside a word, nothing after `://`
+        assert!(urls("file://x xhttps://y https:// https://. mailto:a@b").is_empty());
+        assert_eq!(Token::Url.description(), "URL");
     }
 
     #[test]
@@ -333,4 +1711,16 @@ mod tests {
         let tokens = lex_all("   \t  ");
         assert_eq!(tokens, vec![]);
     }
+
+    #[test]
+    fn test_line_index() {
+        let source = "ab\ncé d\n\nx";
+        let index = LineIndex::new(source);
+        assert_eq!(index.line_col(0), (1, 1));
+        assert_eq!(index.line_col(2), (1, 3)); // the newline itself
+        assert_eq!(index.line_col(3), (2, 1));
+        assert_eq!(index.line_col(7), (2, 4)); // "d", after the two-byte é
+        assert_eq!(index.line_col(10), (4, 1));
+        assert_eq!(index.line_col(100), (4, 2));
+    }
 }
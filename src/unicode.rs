@@ -0,0 +1,210 @@
+//! Opt-in Unicode normalization of text in the parsed AST
+//!
+//! The same text can be written with different code points: `é` as one
+//! precomposed character or as `e` followed by a combining acute accent, `ﬁ` as
+//! a ligature or as two letters. Search indexes and diffs treat those as
+//! different strings. [`normalize_text`] rewrites the AST's text to one
+//! [`NormalizationForm`] so they compare equal. It is never applied
+//! implicitly; enable it for a parse with
+//! [`ParseOptions::normalize_unicode`](crate::options::ParseOptions::normalize_unicode).
+//!
+//! Only the AST changes. The source text the document was parsed from (and
+//! anything that maps back into it, such as lint fixes) keeps the original
+//! bytes, so source columns from [`crate::metrics`] no longer line up with
+//! normalized text that changed length.
+//!
+//! # What Is Normalized
+//!
+//! Text nodes (at any nesting depth), section and block titles, and the
+//! attribution and citation of quotes and verses. Listing blocks are verbatim
+//! and keep their content as written, as do link targets, IDs, and attributes.
+//!
+//! # Normalizers
+//!
+//! The Unicode normalization tables are large, so doctora does not ship them.
+//! The transformation comes from a [`UnicodeNormalizer`], typically a thin
+//! wrapper around a crate such as `unicode-normalization` or
+//! `icu_normalizer`. ASCII text is the same in every form and is skipped
+//! without calling the normalizer.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::ast::{Block, Inline};
+//! use doctora::parse_document;
+//! use doctora::unicode::{NormalizationForm, UnicodeNormalizer, normalize_text};
+//!
+//! /// Composes `e` + U+0301 only; real code would use a normalization crate
+//! #[derive(Debug)]
+//! struct Acute;
+//!
+//! impl UnicodeNormalizer for Acute {
+//!     fn normalize(&self, text: &str, form: NormalizationForm) -> String {
+//!         assert_eq!(form, NormalizationForm::Nfc);
+//!         text.replace("e\u{0301}", "\u{00E9}")
+//!     }
+//! }
+//!
+//! let mut doc = parse_document("Cafe\u{0301} au lait.\n").unwrap();
+//! normalize_text(&mut doc, NormalizationForm::Nfc, &Acute);
+//!
+//! let Block::Paragraph { content, .. } = &doc.blocks[0] else {
+//!     panic!("expected a paragraph");
+//! };
+//! assert_eq!(content[0], Inline::Text("Caf\u{00E9} au lait.".to_string()));
+//! ```
+
+use crate::ast::visit::{VisitorMut, walk_block_mut, walk_inline_mut};
+use crate::ast::{Block, Document, Inline};
+use std::fmt::{self, Debug};
+
+/// A Unicode normalization form (Unicode Standard Annex #15)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NormalizationForm {
+    /// Canonical composition: the usual form for storage and comparison
+    Nfc,
+    /// Canonical decomposition
+    Nfd,
+    /// Compatibility composition: also folds ligatures, full-width forms,
+    /// superscript digits and the like, which suits search indexing
+    Nfkc,
+    /// Compatibility decomposition
+    Nfkd,
+}
+
+impl NormalizationForm {
+    /// Conventional name of the form (`NFC`, `NFKC`, ...)
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NormalizationForm::Nfc => "NFC",
+            NormalizationForm::Nfd => "NFD",
+            NormalizationForm::Nfkc => "NFKC",
+            NormalizationForm::Nfkd => "NFKD",
+        }
+    }
+}
+
+impl fmt::Display for NormalizationForm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Converts text to a normalization form
+pub trait UnicodeNormalizer: Debug + Send + Sync {
+    /// Returns `text` in `form`
+    ///
+    /// Never called with ASCII-only text.
+    fn normalize(&self, text: &str, form: NormalizationForm) -> String;
+}
+
+/// Normalize the text throughout `doc` to `form` (see the module
+/// documentation)
+pub fn normalize_text(
+    doc: &mut Document,
+    form: NormalizationForm,
+    normalizer: &dyn UnicodeNormalizer,
+) {
+    Pass { form, normalizer }.visit_document_mut(doc);
+}
+
+struct Pass<'a> {
+    form: NormalizationForm,
+    normalizer: &'a dyn UnicodeNormalizer,
+}
+
+impl Pass<'_> {
+    fn text(&self, text: &mut String) {
+        if !text.is_ascii() {
+            *text = self.normalizer.normalize(text, self.form);
+        }
+    }
+}
+
+impl VisitorMut for Pass<'_> {
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        if let Block::Quote {
+            attribution,
+            citation,
+            ..
+        }
+        | Block::Verse {
+            attribution,
+            citation,
+            ..
+        } = block
+        {
+            attribution
+                .iter_mut()
+                .chain(citation)
+                .for_each(|text| self.text(text));
+        }
+        walk_block_mut(self, block);
+    }
+
+    fn visit_inline_mut(&mut self, inline: &mut Inline) {
+        if let Inline::Text(text) = inline {
+            self.text(text);
+        }
+        walk_inline_mut(self, inline);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::plain_text;
+    use crate::parse_document;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Folds the `ﬁ` ligature under the compatibility forms, counting calls
+    #[derive(Debug, Default)]
+    struct Ligatures {
+        calls: AtomicUsize,
+    }
+
+    impl UnicodeNormalizer for Ligatures {
+        fn normalize(&self, text: &str, form: NormalizationForm) -> String {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            match form {
+                NormalizationForm::Nfkc | NormalizationForm::Nfkd => text.replace('ﬁ', "fi"),
+                NormalizationForm::Nfc | NormalizationForm::Nfd => text.to_string(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_normalizes_text_nodes_and_titles() {
+        let source = "== ﬁrst\n\n* *ﬁne* plain\n\n[quote, ﬁeld]\n____\nq\n____\n\n----\nﬁ\n----\n";
+        let mut doc = parse_document(source).unwrap();
+        let normalizer = Ligatures::default();
+        normalize_text(&mut doc, NormalizationForm::Nfkc, &normalizer);
+
+        let Block::Section { title, content, .. } = &doc.blocks[0] else {
+            panic!("expected a section, got {:?}", doc.blocks[0]);
+        };
+        assert_eq!(plain_text(title), "first");
+        let Block::UnorderedList { items, .. } = &content[0] else {
+            panic!("expected a list, got {:?}", content[0]);
+        };
+        assert_eq!(
+            items[0].content,
+            vec![
+                Inline::Bold(vec![Inline::Text("fine".to_string())]),
+                Inline::Text(" plain".to_string()),
+            ]
+        );
+        assert!(
+            matches!(&content[1], Block::Quote { attribution: Some(name), .. } if name == "field")
+        );
+        assert!(matches!(&content[2], Block::Listing { content, .. } if content == "ﬁ"));
+        // ASCII text (" plain", "q") is skipped
+        assert_eq!(normalizer.calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_form_names() {
+        assert_eq!(NormalizationForm::Nfkc.to_string(), "NFKC");
+        assert_eq!(NormalizationForm::Nfd.as_str(), "NFD");
+    }
+}
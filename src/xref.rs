@@ -0,0 +1,544 @@
+//! Anchors and cross references across a parsed document
+//!
+//! The parser records anchors where they are written: on sections
+//! ([`Block::Section::anchor`]), in the `id` attribute of blocks with an
+//! attribute list, and inline ([`Inline::Anchor`]). Cross references
+//! ([`Inline::XRef`]) name an anchor by ID. This module gathers both, so
+//! tools can check that every reference has a target ([`unresolved`]) and
+//! renderers can show the target's text for references without their own
+//! ([`References`]).
+//!
+//! Sections without an explicit anchor can be given the ID Asciidoctor
+//! would generate from their title ([`generate_id`], [`section_ids`]), so
+//! `<<_getting_started>>` works as it does there. The parse option
+//! [`ParseOptions::generate_section_ids`](crate::options::ParseOptions::generate_section_ids)
+//! stores them as section anchors ([`assign_section_ids`]).
+//!
+//! A reference whose ID has a document part (`other.adoc#install`, or a
+//! bare `other.adoc`) points into another document and is not checked; see
+//! [`crate::targets::xref_document_target`] for where it links to.
+//!
+//! # Examples
+//!
+//! ```
+//! use doctora::parse_document;
+//! use doctora::xref::{References, unresolved};
+//!
+//! let doc = parse_document("[[setup]]\n== Setup\n\nSee <<setup>> and <<missing>>.\n").unwrap();
+//!
+//! let missing = unresolved(&doc);
+//! assert_eq!(missing.len(), 1);
+//! assert_eq!(missing[0].id, "missing");
+//! assert_eq!(missing[0].path, vec![0, 0]);
+//!
+//! let references = References::new(&doc);
+//! assert_eq!(references.label("setup"), "Setup");
+//! assert_eq!(references.label("missing"), "[missing]");
+//! ```
+
+use crate::ast::{Anchor, Block, Document, Inline, plain_text};
+use crate::attributes::Attributes;
+use crate::targets::xref_document_target;
+use std::collections::HashMap;
+
+/// An anchor defined in a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnchorEntry {
+    /// The anchor ID
+    pub id: String,
+    /// Text for references without their own: the reference text, or the
+    /// title of an anchored section
+    pub label: Option<String>,
+    /// Position of the anchored block, or of the block holding an inline
+    /// anchor, as child indices from the document root
+    pub path: Vec<usize>,
+}
+
+/// A cross reference found in a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XRefEntry {
+    /// The referenced ID, as written
+    pub id: String,
+    /// Position of the block holding the reference, as child indices from
+    /// the document root
+    pub path: Vec<usize>,
+}
+
+/// Every anchor in `doc`, in document order
+///
+/// Paths follow the children of sections and delimited blocks (examples,
+/// sidebars, quotes, and open blocks); anchors inside lists and tables
+/// report the path of the list or table.
+pub fn anchors(doc: &Document) -> Vec<AnchorEntry> {
+    let mut walker = Walker::default();
+    walker.blocks(&doc.blocks, &mut Vec::new(), false);
+    walker.anchors
+}
+
+/// Every cross reference in `doc`, in document order
+///
+/// Paths are as for [`anchors`].
+pub fn xrefs(doc: &Document) -> Vec<XRefEntry> {
+    let mut walker = Walker::default();
+    walker.blocks(&doc.blocks, &mut Vec::new(), false);
+    walker.xrefs
+}
+
+/// The cross references in `doc` whose ID matches no anchor
+///
+/// References into other documents are not checked.
+pub fn unresolved(doc: &Document) -> Vec<XRefEntry> {
+    let mut walker = Walker::default();
+    walker.blocks(&doc.blocks, &mut Vec::new(), false);
+    let Walker { anchors, xrefs } = walker;
+    xrefs
+        .into_iter()
+        .filter(|xref| {
+            local_id(&xref.id).is_some_and(|id| !anchors.iter().any(|anchor| anchor.id == id))
+        })
+        .collect()
+}
+
+/// A section and the ID it is linked by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionId {
+    /// The section's anchor ID, or the one generated from its title
+    pub id: String,
+    /// The section title
+    pub title: String,
+    /// Position of the section, as child indices from the document root
+    pub path: Vec<usize>,
+    /// True if `id` was generated rather than written as an anchor
+    pub generated: bool,
+}
+
+/// ID generated from a section `title`, as Asciidoctor does
+///
+/// The title is lowercased and characters that are not letters, digits,
+/// `_`, spaces, `.` or `-` are dropped. Then runs of spaces, `.`, `-` and the
+/// separator itself become one `separator`, and the result follows `prefix`.
+/// A trailing separator is dropped, and so is a leading one if there is no
+/// prefix. Like Asciidoctor, only the first character of `separator` is
+/// used; an empty one just drops the spaces. Asciidoctor's defaults are the
+/// `idprefix` and `idseparator` attributes' defaults, `_` and `_`.
+///
+/// # Examples
+///
+/// ```
+/// use doctora::xref::generate_id;
+///
+/// assert_eq!(generate_id("Getting Started!", "_", "_"), "_getting_started");
+/// assert_eq!(generate_id("v1.2 - Notes", "", "-"), "v1-2-notes");
+/// assert_eq!(generate_id("v1.2 - Notes", "", ""), "v1.2-notes");
+/// ```
+pub fn generate_id(title: &str, prefix: &str, separator: &str) -> String {
+    let kept = title
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|&c| c.is_alphanumeric() || matches!(c, '_' | ' ' | '.' | '-'));
+    let mut id = prefix.to_string();
+    let Some(separator) = separator.chars().next() else {
+        id.extend(kept.filter(|&c| c != ' '));
+        return id;
+    };
+    for c in kept {
+        if matches!(c, ' ' | '.' | '-') || c == separator {
+            if !id.ends_with(separator) {
+                id.push(separator);
+            }
+        } else {
+            id.push(c);
+        }
+    }
+    if id.ends_with(separator) {
+        id.pop();
+    }
+    if prefix.is_empty() && id.starts_with(separator) {
+        id.remove(0);
+    }
+    id
+}
+
+/// The ID of every section in `doc`, in document order
+///
+/// Sections with an anchor keep it. The others get [`generate_id`] with the
+/// document's `idprefix` and `idseparator` attributes, and an `_2`, `_3`, ...
+/// suffix (using the separator) where the ID is already taken by an anchor
+/// or an earlier section. These are the IDs cross references resolve to once
+/// [`assign_section_ids`] has stored them.
+pub fn section_ids(doc: &Document) -> Vec<SectionId> {
+    let prefix = doc.attributes.get("idprefix").unwrap_or("_");
+    let separator = doc.attributes.get("idseparator").map_or("_", |separator| {
+        separator
+            .char_indices()
+            .nth(1)
+            .map_or(separator, |(end, _)| &separator[..end])
+    });
+    let mut taken: Vec<String> = anchors(doc).into_iter().map(|anchor| anchor.id).collect();
+    let mut out = Vec::new();
+    collect_sections(&doc.blocks, &mut Vec::new(), &mut out);
+    for section in &mut out {
+        if !section.generated {
+            continue;
+        }
+        let base = generate_id(&section.title, prefix, separator);
+        let mut id = base.clone();
+        let mut number = 2;
+        while taken.contains(&id) {
+            id = format!("{base}{separator}{number}");
+            number += 1;
+        }
+        taken.push(id.clone());
+        section.id = id;
+    }
+    out
+}
+
+/// Give every section without an anchor its generated ID (see
+/// [`section_ids`])
+pub fn assign_section_ids(doc: &mut Document) {
+    for section in section_ids(doc) {
+        if !section.generated {
+            continue;
+        }
+        let mut blocks = &mut doc.blocks;
+        let (last, parents) = section
+            .path
+            .split_last()
+            .expect("section paths are not empty");
+        for &index in parents {
+            let Block::Section { content, .. } = &mut blocks[index] else {
+                unreachable!("section paths only go through sections");
+            };
+            blocks = content;
+        }
+        if let Block::Section { anchor, .. } = &mut blocks[*last] {
+            *anchor = Some(Anchor::new(section.id));
+        }
+    }
+}
+
+/// Push the sections among `blocks` and their subsections, with their
+/// anchor IDs (left empty where they are to be generated)
+fn collect_sections(blocks: &[Block], path: &mut Vec<usize>, out: &mut Vec<SectionId>) {
+    for (index, block) in blocks.iter().enumerate() {
+        if let Block::Section {
+            title,
+            content,
+            anchor,
+            ..
+        } = block
+        {
+            path.push(index);
+            out.push(SectionId {
+                id: anchor
+                    .as_ref()
+                    .map(|anchor| anchor.id.clone())
+                    .unwrap_or_default(),
+                title: plain_text(title),
+                path: path.clone(),
+                generated: anchor.is_none(),
+            });
+            collect_sections(content, path, out);
+            path.pop();
+        }
+    }
+}
+
+/// Link targets and default text for the cross references of one document
+#[derive(Debug, Clone, Default)]
+pub struct References {
+    /// Labels by anchor ID; the first anchor with an ID wins
+    labels: HashMap<String, Option<String>>,
+    /// Document attributes, for references into other documents
+    attributes: Attributes,
+}
+
+impl References {
+    /// Collects the anchors of `doc`
+    pub fn new(doc: &Document) -> Self {
+        let mut labels = HashMap::new();
+        for anchor in anchors(doc) {
+            labels.entry(anchor.id).or_insert(anchor.label);
+        }
+        Self {
+            labels,
+            attributes: doc.attributes.clone(),
+        }
+    }
+
+    /// Returns true if an anchor `id` is defined
+    pub fn contains(&self, id: &str) -> bool {
+        self.labels.contains_key(id)
+    }
+
+    /// The URL a reference to `id` links to
+    ///
+    /// `#id` within the document; references into other documents resolve
+    /// through the `relfileprefix` and `relfilesuffix` attributes.
+    pub fn href(&self, id: &str) -> String {
+        match local_id(id) {
+            Some(id) => format!("#{id}"),
+            None => xref_document_target(id, &self.attributes),
+        }
+    }
+
+    /// Text for a reference to `id` that has none of its own
+    ///
+    /// The anchor's reference text or section title, else the ID in
+    /// brackets, as Asciidoctor shows it.
+    pub fn label(&self, id: &str) -> String {
+        local_id(id)
+            .and_then(|id| self.labels.get(id)?.clone())
+            .unwrap_or_else(|| format!("[{id}]"))
+    }
+}
+
+/// The anchor ID of a reference within the same document, or `None` for a
+/// reference into another document
+fn local_id(id: &str) -> Option<&str> {
+    match id.split_once('#') {
+        Some(("", fragment)) => Some(fragment),
+        Some(_) => None,
+        None if id.ends_with(".adoc") => None,
+        None => Some(id),
+    }
+}
+
+/// Collects anchors and references in one pass
+#[derive(Default)]
+struct Walker {
+    anchors: Vec<AnchorEntry>,
+    xrefs: Vec<XRefEntry>,
+}
+
+impl Walker {
+    /// Walk `blocks`; in a `leaf` (the blocks of a list item), they all
+    /// report the leaf's path
+    fn blocks(&mut self, blocks: &[Block], path: &mut Vec<usize>, leaf: bool) {
+        for (index, block) in blocks.iter().enumerate() {
+            if !leaf {
+                path.push(index);
+            }
+            self.block(block, path, leaf);
+            if !leaf {
+                path.pop();
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block, path: &mut Vec<usize>, leaf: bool) {
+        if let Some(anchor) = block.anchor() {
+            let label = match block {
+                Block::Section { title, .. } => anchor.reftext.or_else(|| Some(plain_text(title))),
+                _ => anchor.reftext,
+            };
+            self.anchors.push(AnchorEntry {
+                id: anchor.id,
+                label,
+                path: path.clone(),
+            });
+        }
+        if let Some(title) = &block.metadata().title {
+            self.inlines(title, path);
+        }
+        match block {
+            Block::Paragraph { content, .. } | Block::Verse { content, .. } => {
+                self.inlines(content, path)
+            }
+            Block::UnorderedList { items, .. } | Block::OrderedList { items, .. } => {
+                for item in items {
+                    self.inlines(&item.content, path);
+                    self.blocks(&item.blocks, path, true);
+                }
+            }
+            Block::DescriptionList { items, .. } => {
+                for item in items {
+                    self.inlines(&item.term, path);
+                    self.inlines(&item.description, path);
+                }
+            }
+            Block::Table { header, rows, .. } => {
+                for row in header.iter().chain(rows) {
+                    for cell in &row.cells {
+                        self.inlines(&cell.content, path);
+                    }
+                }
+            }
+            Block::Section { title, content, .. } => {
+                self.inlines(title, path);
+                self.blocks(content, path, leaf);
+            }
+            Block::Quote { content, .. }
+            | Block::Admonition { content, .. }
+            | Block::Example { content, .. }
+            | Block::Sidebar { content, .. }
+            | Block::Open { content, .. } => self.blocks(content, path, leaf),
+            Block::Listing { .. }
+            | Block::Image { .. }
+            | Block::Passthrough { .. }
+            | Block::Comment { .. }
+            | Block::AttributeEntry { .. }
+            | Block::Unknown { .. } => {}
+        }
+    }
+
+    fn inlines(&mut self, content: &[Inline], path: &[usize]) {
+        for inline in content {
+            match inline {
+                Inline::Text(_)
+                | Inline::Image { .. }
+                | Inline::Icon { .. }
+                | Inline::BreakHint(_) => {}
+                Inline::Bold(nested)
+                | Inline::Italic(nested)
+                | Inline::Monospace(nested)
+                | Inline::Highlight(nested)
+                | Inline::Span {
+                    content: nested, ..
+                }
+                | Inline::Superscript(nested)
+                | Inline::Subscript(nested) => self.inlines(nested, path),
+                Inline::Link { text, .. } | Inline::Footnote { text, .. } => {
+                    self.inlines(text, path)
+                }
+                Inline::XRef { id, text } => {
+                    self.xrefs.push(XRefEntry {
+                        id: id.clone(),
+                        path: path.to_vec(),
+                    });
+                    self.inlines(text, path);
+                }
+                Inline::Anchor(anchor) => self.anchors.push(AnchorEntry {
+                    id: anchor.id.clone(),
+                    label: anchor.reftext.clone(),
+                    path: path.to_vec(),
+                }),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_document;
+
+    #[test]
+    fn test_anchor_forms() {
+        let doc = parse_document(
+            "[#intro,reftext=Introduction]\n== Intro\n\n[[code,Sample]]\n----\nx\n----\n\n\
+             [[p1]]Text with [[inline,Here]] anchors.\n\n* item [[li]]\n",
+        )
+        .unwrap();
+        let anchors = anchors(&doc);
+        let summary: Vec<(&str, Option<&str>, Vec<usize>)> = anchors
+            .iter()
+            .map(|a| (a.id.as_str(), a.label.as_deref(), a.path.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("intro", Some("Introduction"), vec![0]),
+                ("code", Some("Sample"), vec![0, 0]),
+                ("p1", None, vec![0, 1]),
+                ("inline", Some("Here"), vec![0, 1]),
+                ("li", None, vec![0, 2]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unresolved_skips_other_documents() {
+        let doc = parse_document(
+            "[[a]]\n== A\n\n====\n<<a>> <<b,B>> <<#a>> <<other.adoc#x>> <<other.adoc>>\n====\n",
+        )
+        .unwrap();
+        let ids: Vec<String> = xrefs(&doc).into_iter().map(|xref| xref.id).collect();
+        assert_eq!(ids, ["a", "b", "#a", "other.adoc#x", "other.adoc"]);
+        assert_eq!(
+            unresolved(&doc),
+            vec![XRefEntry {
+                id: "b".to_string(),
+                path: vec![0, 0, 0],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_generate_id() {
+        let cases = [
+            ("Introduction", "_introduction"),
+            ("Getting  Started", "_getting_started"),
+            ("What's new in v2.0?", "_whats_new_in_v2_0"),
+            ("snake_case - API", "_snake_case_api"),
+            ("Über uns", "_über_uns"),
+            ("-- Trailing --", "_trailing"),
+        ];
+        for (title, id) in cases {
+            assert_eq!(generate_id(title, "_", "_"), id, "{title:?}");
+        }
+        assert_eq!(generate_id("A B", "", ""), "ab");
+
+        // Custom prefixes and separators, as set with `idprefix` and `idseparator`
+        let cases = [
+            ("Quick Start", "", "-", "quick-start"),
+            ("snake_case API", "", "-", "snake_case-api"),
+            ("a__b", "_", "_", "_a_b"),
+            ("- Leading", "", "-", "leading"),
+            ("- Leading", "sec-", "-", "sec-leading"),
+            ("v1.2 - Notes", "", "", "v1.2-notes"),
+            ("Release: 2.0", "", ":", "release:2:0"),
+        ];
+        for (title, prefix, separator, id) in cases {
+            assert_eq!(generate_id(title, prefix, separator), id, "{title:?}");
+        }
+    }
+
+    #[test]
+    fn test_section_ids() {
+        let mut doc = parse_document(
+            "== Setup\n\n[[_setup_2]]Taken.\n\n=== Setup\n\n[[custom]]\n=== Setup\n\n=== Setup\n",
+        )
+        .unwrap();
+        let ids: Vec<(String, bool)> = section_ids(&doc)
+            .into_iter()
+            .map(|section| (section.id, section.generated))
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                ("_setup".to_string(), true),
+                ("_setup_3".to_string(), true),
+                ("custom".to_string(), false),
+                ("_setup_4".to_string(), true),
+            ]
+        );
+
+        assign_section_ids(&mut doc);
+        assert_eq!(doc.blocks[0].anchor(), Some(Anchor::new("_setup")));
+        assert!(unresolved(&doc).is_empty());
+
+        let mut doc = parse_document("== Quick Start\n").unwrap();
+        doc.attributes.set("idprefix", "");
+        doc.attributes.set("idseparator", "-");
+        assert_eq!(section_ids(&doc)[0].id, "quick-start");
+        // Only the first character of a longer separator is used
+        doc.attributes.set("idseparator", "-+");
+        assert_eq!(section_ids(&doc)[0].id, "quick-start");
+    }
+
+    #[test]
+    fn test_references() {
+        let mut doc =
+            parse_document("[[s1]]\n== First\n\n[[s2,Second part]]\n== Second\n").unwrap();
+        doc.attributes.set("relfileprefix", "../");
+        let references = References::new(&doc);
+        assert!(references.contains("s1"));
+        assert_eq!(references.label("s1"), "First");
+        assert_eq!(references.label("s2"), "Second part");
+        assert_eq!(references.href("s2"), "#s2");
+        assert_eq!(references.href("guide.adoc#s3"), "../guide.html#s3");
+        assert_eq!(references.label("guide.adoc#s3"), "[guide.adoc#s3]");
+    }
+}
@@ -0,0 +1,337 @@
+//! End-to-end tests of the `doctora` binary
+//!
+//! Each test runs the built executable with real arguments and checks its
+//! output and exit code, so argument parsing and I/O are covered alongside
+//! the library calls behind them.
+
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Run `doctora` with `args`, feeding `stdin` to it
+fn doctora(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_doctora"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("the binary runs");
+    // A run that fails on its arguments exits without reading stdin
+    let written = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    if let Err(err) = written {
+        assert_eq!(err.kind(), ErrorKind::BrokenPipe, "{err}");
+    }
+    child.wait_with_output().unwrap()
+}
+
+/// A fresh scratch directory for one test
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("doctora-cli-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_parse_stdin_as_json() {
+    let output = doctora(&["parse"], "Hello, *world*!\n");
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["schema_version"], 1);
+    assert_eq!(json["blocks"][0]["type"], "paragraph");
+    assert_eq!(json["blocks"][0]["content"][1]["type"], "bold");
+}
+
+#[test]
+fn test_parse_files_to_output() {
+    let dir = scratch("output");
+    let (first, second, out) = (dir.join("a.adoc"), dir.join("b.adoc"), dir.join("ast.yaml"));
+    fs::write(&first, "= First\n\nOne.\n").unwrap();
+    fs::write(&second, "Two.\n").unwrap();
+
+    let output = doctora(
+        &[
+            "parse",
+            first.to_str().unwrap(),
+            second.to_str().unwrap(),
+            "--format",
+            "yaml",
+            "-o",
+            out.to_str().unwrap(),
+        ],
+        "",
+    );
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    let yaml = fs::read_to_string(&out).unwrap();
+    assert_eq!(yaml.matches("---\n").count(), 2);
+    assert!(yaml.contains("  title: First\n"));
+    assert!(yaml.contains("      - text: Two.\n"));
+
+    let output = doctora(&["parse", "-f", "debug", first.to_str().unwrap()], "");
+    let debug = String::from_utf8(output.stdout).unwrap();
+    assert!(debug.starts_with("Header: Some(\"First\")\n"));
+    assert!(debug.contains("Inline 0: Text(\"One.\")"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_convert_globs_with_attributes() {
+    let dir = scratch("convert");
+    fs::create_dir_all(dir.join("docs/guide")).unwrap();
+    fs::create_dir_all(dir.join("out")).unwrap();
+    fs::write(
+        dir.join("docs/index.adoc"),
+        ":product: Old\n\nUse {product}.\n",
+    )
+    .unwrap();
+    fs::write(dir.join("docs/guide/setup.adoc"), "Set *up*.\n").unwrap();
+    fs::write(dir.join("docs/notes.txt"), "Skipped.\n").unwrap();
+
+    let pattern = dir.join("docs/**/*.adoc");
+    let out = dir.join("out");
+    let output = doctora(
+        &[
+            "convert",
+            pattern.to_str().unwrap(),
+            "--backend",
+            "text",
+            "-a",
+            "product=New",
+            "-D",
+            out.to_str().unwrap(),
+        ],
+        "",
+    );
+    assert!(output.status.success());
+    let mut written: Vec<String> = fs::read_dir(&out)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    written.sort();
+    assert_eq!(written, ["index.txt", "setup.txt"]);
+    assert_eq!(
+        fs::read_to_string(out.join("index.txt")).unwrap(),
+        "Use New.\n"
+    );
+
+    let page = dir.join("docs/index.adoc");
+    assert!(
+        doctora(&["convert", page.to_str().unwrap()], "")
+            .status
+            .success()
+    );
+    let html = fs::read_to_string(dir.join("docs/index.html")).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_convert_stdin() {
+    let output = doctora(&["convert", "--embedded"], "Hello, *world*!\n");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "<p>Hello, <strong>world</strong>!</p>\n"
+    );
+
+    let output = doctora(&["convert", "--backend", "markdown"], "Text.\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("available backends: html, manpage"));
+}
+
+/// Wait up to ten seconds for `path` to hold `expected`
+fn wait_for(path: &Path, expected: &str) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(10) {
+        if fs::read_to_string(path).is_ok_and(|contents| contents == expected) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    false
+}
+
+#[test]
+fn test_convert_watch() {
+    let dir = scratch("watch");
+    let (page, part) = (dir.join("page.adoc"), dir.join("part.adoc"));
+    fs::write(&page, "Page.\n\ninclude::part.adoc[]\n").unwrap();
+    fs::write(&part, "First.\n").unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_doctora"))
+        .args(["convert", "--watch", "-b", "text", page.to_str().unwrap()])
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let output = dir.join("page.txt");
+    let first = wait_for(&output, "Page.\n\nFirst.\n");
+    // Let the modification time move past the first stamp
+    thread::sleep(Duration::from_millis(20));
+    fs::write(&part, "Second.\n").unwrap();
+    let second = wait_for(&output, "Page.\n\nSecond.\n");
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(first && second);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_convert_watch_out_file() {
+    let dir = scratch("watch-out-file");
+    let (first, second) = (dir.join("a.adoc"), dir.join("b.adoc"));
+    fs::write(&first, "First.\n").unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_doctora"))
+        .args([
+            "convert", "--watch", "-b", "text", "-o", "out.txt", "*.adoc",
+        ])
+        .current_dir(&dir)
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let output = dir.join("out.txt");
+    let converted = wait_for(&output, "First.\n");
+    // A second match would write the same file, so nothing is converted
+    fs::write(&second, "Second.\n").unwrap();
+    let mut refused = false;
+    let mut line = String::new();
+    while !refused && stderr.read_line(&mut line).unwrap() > 0 {
+        refused = line.contains("--out-file takes a single input");
+    }
+    fs::write(&first, "First, edited.\n").unwrap();
+    thread::sleep(Duration::from_millis(600));
+    let unchanged = fs::read_to_string(&output).unwrap() == "First.\n";
+    fs::remove_file(&second).unwrap();
+    let resumed = wait_for(&output, "First, edited.\n");
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(converted && refused && unchanged && resumed);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// Send a raw HTTP request for `path` to `address`, naming `host`, and
+/// return the whole response
+fn http_get(address: &str, host: &str, path: &str) -> String {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "GET {path} HTTP/1.1\r\nHost: {host}\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn test_serve() {
+    let dir = scratch("serve");
+    let part = dir.join("part.adoc");
+    fs::write(
+        dir.join("page.adoc"),
+        "= Page\n\nIntro.\n\ninclude::part.adoc[]\n",
+    )
+    .unwrap();
+    fs::write(&part, "First.\n").unwrap();
+    fs::write(dir.join("logo.png"), "PNG").unwrap();
+    fs::write(dir.join(".env"), "SECRET=1").unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_doctora"))
+        .args(["serve", "--port", "0", "*.adoc"])
+        .current_dir(&dir)
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut banner = String::new();
+    BufReader::new(child.stderr.take().unwrap())
+        .read_line(&mut banner)
+        .unwrap();
+    let address = banner
+        .split("http://")
+        .nth(1)
+        .and_then(|rest| rest.split('/').next())
+        .unwrap()
+        .to_string();
+
+    let page = http_get(&address, &address, "/page.html");
+    let index = http_get(&address, &address, "/");
+    let outside = http_get(&address, &address, "/../page.adoc");
+    let logo = http_get(&address, &address, "/logo.png");
+    let hidden = http_get(&address, &address, "/.env");
+    let source = http_get(&address, &address, "/page.adoc");
+    let port = address.rsplit(':').next().unwrap();
+    let rebound = http_get(&address, &format!("attacker.example:{port}"), "/page.html");
+    let localhost = http_get(&address, &format!("localhost:{port}"), "/page.html");
+    let upgrade = |origin: &str| {
+        format!(
+            "GET /_doctora/live?page=%2Fpage.html HTTP/1.1\r\nHost: {address}\r\n\
+             Origin: {origin}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n"
+        )
+    };
+    let mut foreign = TcpStream::connect(&address).unwrap();
+    foreign
+        .write_all(upgrade("http://attacker.example").as_bytes())
+        .unwrap();
+    let mut refused = String::new();
+    foreign.read_to_string(&mut refused).unwrap();
+
+    let mut socket = TcpStream::connect(&address).unwrap();
+    socket
+        .write_all(upgrade(&format!("http://{address}")).as_bytes())
+        .unwrap();
+    let mut reader = BufReader::new(socket);
+    let mut handshake = String::new();
+    while reader.read_line(&mut handshake).unwrap() > 2 {}
+    // Let the server take its stamps, then the modification time move past them
+    thread::sleep(Duration::from_millis(100));
+    fs::write(&part, "Second.\n").unwrap();
+    reader
+        .get_ref()
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut message = Vec::new();
+    let mut chunk = [0; 1024];
+    while !String::from_utf8_lossy(&message).contains("Second.") {
+        match reader.read(&mut chunk) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => message.extend_from_slice(&chunk[..read]),
+        }
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert!(page.starts_with("HTTP/1.1 200 OK\r\n"), "{page}");
+    assert!(page.contains("<p data-node=\"1\">First.</p>"));
+    assert!(page.contains("<script src=\"/_doctora/live.js\"></script>"));
+    assert!(index.contains("<a href=\"/page.html\">page.adoc</a>"));
+    assert!(outside.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    assert!(logo.starts_with("HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n"));
+    assert!(hidden.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    assert!(source.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    assert!(rebound.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+    assert!(localhost.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(
+        refused.starts_with("HTTP/1.1 403 Forbidden\r\n"),
+        "{refused}"
+    );
+    assert!(handshake.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+    assert!(handshake.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+    // A text frame holding a patch that replaces the included paragraph
+    assert_eq!(message.first(), Some(&0x81));
+    assert!(String::from_utf8_lossy(&message).contains(r#"node("1").outerHTML"#));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_exit_codes() {
+    let missing = doctora(&["parse", "missing.adoc"], "");
+    assert_eq!(missing.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&missing.stderr).starts_with("missing.adoc: "));
+
+    let bad_format = doctora(&["parse", "--format", "xml"], "");
+    assert_eq!(bad_format.status.code(), Some(2));
+}
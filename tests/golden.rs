@@ -0,0 +1,164 @@
+//! Golden-output snapshots for every renderer
+//!
+//! Each `.adoc` fixture in `tests/golden/` covers one feature (lists, tables,
+//! sections, ...). It is parsed with [`golden_options`] and rendered by every
+//! backend in [`BACKENDS`], and each output is compared against the checked-in
+//! file with the backend's extension next to the fixture
+//! (`lists.adoc` -> `lists.html`, `lists.json`). A converter change fails this
+//! test with the list of outputs it touched, so the snapshot diff shows
+//! exactly what renders differently.
+//!
+//! To accept an intended change, regenerate the snapshots and review the diff:
+//!
+//! ```text
+//! DOCTORA_BLESS=1 cargo test --test golden
+//! ```
+//!
+//! To cover a new feature, add a fixture and bless it; a missing snapshot
+//! fails like a changed one.
+
+use doctora::ast::Document;
+use doctora::clock::{FixedClock, Timestamp};
+use doctora::options::ParseOptions;
+use doctora::parse_document_with_options;
+use doctora::render::html::{HtmlOptions, render_html};
+use doctora::schema::to_json_string;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Renders a parsed fixture to one backend's output
+type Render = fn(&Document) -> String;
+
+/// Snapshot file extension and renderer for each backend
+const BACKENDS: &[(&str, Render)] = &[
+    ("html", |doc| render_html(doc, &HtmlOptions::new())),
+    ("json", |doc| to_json_string(doc) + "\n"),
+];
+
+/// Parse options for every fixture, matching what a converter run enables
+///
+/// The clock is pinned so date attributes do not change between runs.
+fn golden_options() -> ParseOptions {
+    ParseOptions::new()
+        .clock(FixedClock(Timestamp::from_unix(0)))
+        .substitute_attributes(true)
+        .generate_section_ids(true)
+}
+
+/// All fixtures, sorted for stable output
+fn fixtures() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("tests/golden should exist")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "adoc"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Describe where `actual` first departs from `expected`
+fn first_difference(expected: &str, actual: &str) -> String {
+    let line = expected
+        .lines()
+        .zip(actual.lines())
+        .position(|(e, a)| e != a)
+        .unwrap_or_else(|| expected.lines().count().min(actual.lines().count()));
+    format!(
+        "line {} (expected {:?}, got {:?})",
+        line + 1,
+        expected.lines().nth(line),
+        actual.lines().nth(line),
+    )
+}
+
+#[test]
+fn golden_outputs_match_fixtures() {
+    let bless = std::env::var_os("DOCTORA_BLESS").is_some();
+    let options = golden_options();
+    let mut mismatches = Vec::new();
+
+    for path in fixtures() {
+        let input = fs::read_to_string(&path).unwrap();
+        let doc = parse_document_with_options(&input, &options)
+            .unwrap_or_else(|err| panic!("{} does not parse: {err}", path.display()));
+
+        for (extension, render) in BACKENDS {
+            let actual = render(&doc);
+            let snapshot = path.with_extension(extension);
+
+            if bless {
+                fs::write(&snapshot, &actual).unwrap();
+                continue;
+            }
+
+            match fs::read_to_string(&snapshot) {
+                Ok(expected) if expected == actual => {}
+                Ok(expected) => mismatches.push(format!(
+                    "{}: first difference at {}",
+                    snapshot.display(),
+                    first_difference(&expected, &actual)
+                )),
+                Err(_) => mismatches.push(format!("{}: no snapshot", snapshot.display())),
+            }
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "golden outputs changed (rerun with DOCTORA_BLESS=1 to accept):\n{}",
+        mismatches.join("\n")
+    );
+}
+
+#[test]
+fn fixtures_have_no_stray_snapshots() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    for entry in fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        if extension == "adoc" {
+            continue;
+        }
+        assert!(
+            BACKENDS.iter().any(|(backend, _)| *backend == extension),
+            "{} belongs to no backend",
+            path.display()
+        );
+        assert!(
+            path.with_extension("adoc").exists(),
+            "{} has no fixture; delete it or restore the fixture",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn html_snapshots_nest_sections_by_level() {
+    // A snapshot is only blessed output; this keeps a parser bug in section
+    // nesting from being approved with it
+    for path in fixtures() {
+        let html = fs::read_to_string(path.with_extension("html")).unwrap();
+        let mut depth = 0;
+        let mut lines = html.lines();
+        while let Some(line) = lines.next() {
+            if line.starts_with("<section") {
+                depth += 1;
+                let heading = lines.next().unwrap_or_default();
+                let level = heading
+                    .strip_prefix("<h")
+                    .and_then(|rest| rest.chars().next())
+                    .and_then(|level| level.to_digit(10));
+                assert_eq!(
+                    level,
+                    Some((depth + 1).min(6)),
+                    "{}: {heading:?} at section depth {depth}",
+                    path.display()
+                );
+            } else if line == "</section>" {
+                depth -= 1;
+            }
+        }
+        assert_eq!(depth, 0, "{}: unbalanced sections", path.display());
+    }
+}
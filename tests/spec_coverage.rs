@@ -0,0 +1,187 @@
+//! AsciiDoc feature coverage matrix
+//!
+//! Reads the feature checklist in `tests/spec/features.txt`, checks that every
+//! test it references exists, and renders the coverage report in
+//! `docs/spec-coverage.md`. The report is checked in so users can see which
+//! syntax is supported without building the crate; this test fails when the
+//! report is out of date.
+//!
+//! To regenerate the report after editing the checklist:
+//!
+//! ```text
+//! DOCTORA_BLESS=1 cargo test --test spec_coverage
+//! ```
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// A group of features under a `[Name]` heading
+struct Category {
+    name: String,
+    features: Vec<Feature>,
+}
+
+/// One checklist entry
+struct Feature {
+    id: String,
+    title: String,
+    tests: Vec<String>,
+}
+
+/// Parse the checklist format described at the top of `features.txt`
+fn parse_checklist(text: &str) -> Result<Vec<Category>, String> {
+    let mut categories: Vec<Category> = Vec::new();
+    let mut ids = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_no = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            categories.push(Category {
+                name: name.to_string(),
+                features: Vec::new(),
+            });
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+        let [id, title, tests] = fields[..] else {
+            return Err(format!("line {}: expected `id | feature | tests`", line_no));
+        };
+        if id.is_empty() || title.is_empty() {
+            return Err(format!("line {}: empty id or feature", line_no));
+        }
+        if ids.contains(&id) {
+            return Err(format!("line {}: duplicate feature id `{}`", line_no, id));
+        }
+        ids.push(id);
+
+        let category = categories
+            .last_mut()
+            .ok_or_else(|| format!("line {}: feature outside of a category", line_no))?;
+        category.features.push(Feature {
+            id: id.to_string(),
+            title: title.to_string(),
+            tests: tests
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect(),
+        });
+    }
+
+    Ok(categories)
+}
+
+/// Returns true if the test named by `path` is defined in the crate
+///
+/// The first path segment selects the file (`src/<segment>.rs` or
+/// `tests/<segment>.rs`) and the last segment is the test function.
+fn test_exists(root: &Path, path: &str) -> bool {
+    let segments: Vec<&str> = path.split("::").collect();
+    let (Some(file), Some(name)) = (segments.first(), segments.last()) else {
+        return false;
+    };
+    let needle = format!("fn {}(", name);
+
+    [format!("src/{}.rs", file), format!("tests/{}.rs", file)]
+        .iter()
+        .filter_map(|candidate| fs::read_to_string(root.join(candidate)).ok())
+        .any(|source| source.contains(&needle))
+}
+
+/// Render the Markdown coverage report
+fn render_report(categories: &[Category]) -> String {
+    let features: Vec<&Feature> = categories.iter().flat_map(|c| &c.features).collect();
+    let covered = features.iter().filter(|f| !f.tests.is_empty()).count();
+
+    let mut out = String::new();
+    out.push_str("# AsciiDoc Feature Coverage\n\n");
+    out.push_str(
+        "<!-- Generated from tests/spec/features.txt by tests/spec_coverage.rs. Do not edit. -->\n\n",
+    );
+    writeln!(
+        out,
+        "{} of {} tracked features are supported and covered by tests.\n",
+        covered,
+        features.len()
+    )
+    .unwrap();
+
+    for category in categories {
+        writeln!(out, "## {}\n", category.name).unwrap();
+        out.push_str("| Feature | Status | Tests |\n");
+        out.push_str("|---------|--------|-------|\n");
+        for feature in &category.features {
+            let status = if feature.tests.is_empty() {
+                "❌ Not supported"
+            } else {
+                "✅ Supported"
+            };
+            let tests: Vec<String> = feature.tests.iter().map(|t| format!("`{}`", t)).collect();
+            writeln!(
+                out,
+                "| {} (`{}`) | {} | {} |",
+                feature.title,
+                feature.id,
+                status,
+                tests.join("<br>")
+            )
+            .unwrap();
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+#[test]
+fn spec_coverage_report_is_current() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let checklist = fs::read_to_string(root.join("tests/spec/features.txt")).unwrap();
+    let categories = parse_checklist(&checklist).unwrap();
+
+    let missing: Vec<&str> = categories
+        .iter()
+        .flat_map(|c| &c.features)
+        .flat_map(|f| &f.tests)
+        .filter(|test| !test_exists(root, test))
+        .map(String::as_str)
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "checklist references unknown tests: {:?}",
+        missing
+    );
+
+    let report = render_report(&categories);
+    let report_path = root.join("docs/spec-coverage.md");
+    if std::env::var_os("DOCTORA_BLESS").is_some() {
+        fs::write(&report_path, &report).unwrap();
+        return;
+    }
+
+    let current = fs::read_to_string(&report_path).unwrap_or_default();
+    assert!(
+        current == report,
+        "docs/spec-coverage.md is out of date (rerun with DOCTORA_BLESS=1 to regenerate)"
+    );
+}
+
+#[test]
+fn checklist_rejects_malformed_lines() {
+    assert!(parse_checklist("a | b | c").is_err()); // no category
+    assert!(parse_checklist("[X]\na | b").is_err());
+    assert!(parse_checklist("[X]\na | b |\na | c |").is_err());
+
+    let categories = parse_checklist("# comment\n[X]\na | B | m::t, n::u\nb | C |\n").unwrap();
+    assert_eq!(categories[0].features.len(), 2);
+    assert_eq!(categories[0].features[0].tests, vec!["m::t", "n::u"]);
+    assert!(categories[0].features[1].tests.is_empty());
+}
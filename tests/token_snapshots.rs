@@ -0,0 +1,99 @@
+//! Token-level compatibility snapshots
+//!
+//! Lexes every `.adoc` document in `tests/corpus/` with [`lex_with_spans`]
+//! (the token stream the parser sees, including list markers) and compares it
+//! against the checked-in `.tokens` file next to it. Any change in how
+//! existing documents tokenize (for example after adding a delimiter token)
+//! fails this test, so tokenization shifts are always deliberate.
+//!
+//! To accept an intended change, regenerate the snapshots and review the diff:
+//!
+//! ```text
+//! DOCTORA_BLESS=1 cargo test --test token_snapshots
+//! ```
+
+use doctora::token::{Token, lex_with_spans};
+use logos::Logos;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Render the token stream of `input`, one token per line
+fn render_tokens(input: &str) -> String {
+    let mut out = String::new();
+    for (token, span) in lex_with_spans(input) {
+        writeln!(
+            out,
+            "{}..{} {:?} {:?}",
+            span.start,
+            span.end,
+            token,
+            &input[span.clone()]
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// All corpus documents, sorted for stable output
+fn corpus_documents() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("tests/corpus should exist")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "adoc"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn token_snapshots_match_corpus() {
+    let bless = std::env::var_os("DOCTORA_BLESS").is_some();
+    let mut mismatches = Vec::new();
+
+    for path in corpus_documents() {
+        let input = fs::read_to_string(&path).unwrap();
+        let actual = render_tokens(&input);
+        let snapshot = path.with_extension("tokens");
+
+        if bless {
+            fs::write(&snapshot, &actual).unwrap();
+            continue;
+        }
+
+        let expected = fs::read_to_string(&snapshot).unwrap_or_default();
+        if expected != actual {
+            let first_diff = expected
+                .lines()
+                .zip(actual.lines())
+                .position(|(e, a)| e != a)
+                .unwrap_or_else(|| expected.lines().count().min(actual.lines().count()));
+            mismatches.push(format!(
+                "{}: first difference at token {} (expected {:?}, got {:?})",
+                path.display(),
+                first_diff,
+                expected.lines().nth(first_diff),
+                actual.lines().nth(first_diff),
+            ));
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "token snapshots changed (rerun with DOCTORA_BLESS=1 to accept):\n{}",
+        mismatches.join("\n")
+    );
+}
+
+#[test]
+fn corpus_lexes_without_errors() {
+    for path in corpus_documents() {
+        let input = fs::read_to_string(&path).unwrap();
+        assert!(
+            Token::lexer(&input).all(|result| result.is_ok()),
+            "{} produced lexer errors",
+            path.display()
+        );
+    }
+}